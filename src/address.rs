@@ -0,0 +1,423 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::key_registration::registered_keys;
+use crate::signing::{parse_public_key, PublicKey};
+use crate::transaction::Transaction;
+use crate::wallet::{Wallet, GENERATED_ADDRESS_BYTES};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Начало адресов контрактов, которые выдает цепочка
+const CONTRACT_ADDRESS_PREFIX: &str = "contract_";
+
+/// Сколько байт контрольной суммы идет в конце адреса сети
+pub const CHECKSUM_BYTES: usize = 4;
+
+/// Сеть, для которой выпущен адрес; у цепочки задается спецификацией
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    pub fn name(self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+        }
+    }
+    
+    /// Начало адресов сети: `hc1` или `thc1`
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Network::Mainnet => "hc1",
+            Network::Testnet => "thc1",
+        }
+    }
+}
+
+/// Адрес, разобранный по его виду
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    /// Адрес сети: ее префикс, байты хеша открытого ключа и контрольная сумма в hex
+    Network { network: Network, bytes: [u8; GENERATED_ADDRESS_BYTES] },
+    /// Служебный адрес протокола, например `BLOCKCHAIN_REWARD`, или адрес контракта; годится в любой сети
+    System(String),
+    /// Адрес без контрольной суммы: имена демо-цепочек вроде `alice`, `hc` с hex у старых узлов
+    /// и случайные адреса сети, выданные до появления контрольной суммы
+    Legacy(String),
+}
+
+/// Строчные hex-цифры нужного числа байт
+fn is_address_hex(text: &str, bytes: usize) -> bool {
+    text.len() == 2 * bytes && text.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Байты адреса, выведенные из открытого ключа: начало его SHA-256
+pub fn address_bytes(public_key: &PublicKey) -> [u8; GENERATED_ADDRESS_BYTES] {
+    let mut bytes = [0; GENERATED_ADDRESS_BYTES];
+    bytes.copy_from_slice(&Sha256::digest(public_key.as_bytes())[..GENERATED_ADDRESS_BYTES]);
+    bytes
+}
+
+/// Контрольная сумма адреса: начало SHA-256 от префикса сети и байт адреса. Префикс входит в
+/// сумму, поэтому адрес, перенесенный в другую сеть сменой префикса, ее не проходит.
+fn address_checksum(network: Network, bytes: &[u8; GENERATED_ADDRESS_BYTES]) -> [u8; CHECKSUM_BYTES] {
+    let digest = Sha256::new().chain_update(network.prefix()).chain_update(bytes).finalize();
+    let mut checksum = [0; CHECKSUM_BYTES];
+    checksum.copy_from_slice(&digest[..CHECKSUM_BYTES]);
+    checksum
+}
+
+/// Начинается ли текст с префикса сети в любом регистре
+fn has_network_prefix(text: &str, network: Network) -> bool {
+    text.get(..network.prefix().len()).is_some_and(|start| start.eq_ignore_ascii_case(network.prefix()))
+}
+
+/// Служебные адреса пишутся заглавными латинскими буквами, цифрами и подчеркиваниями
+fn is_system_name(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit() || byte == b'_')
+}
+
+impl Address {
+    /// Адрес сети, выведенный из открытого ключа
+    pub fn from_public_key(network: Network, public_key: &PublicKey) -> Address {
+        Address::Network { network, bytes: address_bytes(public_key) }
+    }
+    
+    /// Разбирает адрес и определяет его сеть. Адрес с префиксом сети, но с неверной контрольной
+    /// суммой, другой длиной или заглавными буквами считается ошибкой, а не старым адресом, чтобы
+    /// опечатка не прошла в режиме совместимости.
+    pub fn parse(text: &str) -> Result<Address, BlockchainError> {
+        for network in [Network::Mainnet, Network::Testnet] {
+            if !has_network_prefix(text, network) {
+                continue;
+            }
+            // В другом регистре тот же адрес стал бы вторым ключом в карте кошельков
+            if text.bytes().any(|byte| byte.is_ascii_uppercase()) {
+                return Err(BlockchainError::InvalidTransaction(format!("{} has uppercase letters; addresses are written in lowercase", text)));
+            }
+            let payload = text.get(network.prefix().len()..).unwrap_or_default();
+            let mut decoded = [0; GENERATED_ADDRESS_BYTES + CHECKSUM_BYTES];
+            if is_address_hex(payload, decoded.len()) && hex::decode_to_slice(payload, &mut decoded).is_ok() {
+                let mut bytes = [0; GENERATED_ADDRESS_BYTES];
+                bytes.copy_from_slice(&decoded[..GENERATED_ADDRESS_BYTES]);
+                if decoded[GENERATED_ADDRESS_BYTES..] != address_checksum(network, &bytes) {
+                    return Err(BlockchainError::InvalidTransaction(format!("{} has a wrong checksum; check it for typos", text)));
+                }
+                return Ok(Address::Network { network, bytes });
+            }
+            // Случайный адрес без контрольной суммы и старый адрес, у которого hex начинается с единицы
+            if is_address_hex(payload, GENERATED_ADDRESS_BYTES) || text.strip_prefix("hc").is_some_and(|hex| is_address_hex(hex, GENERATED_ADDRESS_BYTES)) {
+                return Ok(Address::Legacy(text.to_string()));
+            }
+            return Err(BlockchainError::InvalidTransaction(format!("{} is not a valid {} address", text, network.name())));
+        }
+        
+        if is_system_name(text) || text.starts_with(CONTRACT_ADDRESS_PREFIX) {
+            Ok(Address::System(text.to_string()))
+        } else {
+            Ok(Address::Legacy(text.to_string()))
+        }
+    }
+    
+    /// Сеть адреса; у служебных и старых адресов ее нет
+    pub fn network(&self) -> Option<Network> {
+        match self {
+            Address::Network { network, .. } => Some(*network),
+            Address::System(_) | Address::Legacy(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Network { network, bytes } => {
+                write!(f, "{}{}{}", network.prefix(), hex::encode(bytes), hex::encode(address_checksum(*network, bytes)))
+            },
+            Address::System(text) | Address::Legacy(text) => f.write_str(text),
+        }
+    }
+}
+
+/// Проверяет вид адреса и контрольную сумму адреса сети. Служебные адреса, адреса контрактов и
+/// старые адреса без суммы проходят; принимает ли их цепочка, решает `Blockchain::check_address`.
+pub fn validate_address(address: &str) -> Result<(), BlockchainError> {
+    Address::parse(address).map(|_| ())
+}
+
+impl Blockchain {
+    pub fn network(&self) -> Network {
+        self.network
+    }
+    
+    /// Разбирает адрес и проверяет, что цепочка его принимает: адрес своей сети, служебный или,
+    /// если включена совместимость, старый без префикса
+    pub fn check_address(&self, address: &str) -> Result<Address, BlockchainError> {
+        let parsed = Address::parse(address)?;
+        match &parsed {
+            Address::Network { network, .. } if *network != self.network => {
+                Err(BlockchainError::WrongNetwork { expected: self.network.name(), found: network.name() })
+            },
+            Address::Legacy(_) if !self.accept_legacy_addresses => {
+                Err(BlockchainError::WrongNetwork { expected: self.network.name(), found: "legacy" })
+            },
+            _ => Ok(parsed),
+        }
+    }
+    
+    /// Проверяет все адреса транзакции: отправителя, получателя, спонсора и выходов
+    pub(crate) fn check_transaction_addresses(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        let fee_payer = tx.fee_payer.as_ref().map(|payer| payer.address.as_str());
+        let outputs = tx.outputs.iter().map(|output| output.address.as_str());
+        for address in [tx.sender.as_str(), tx.receiver.as_str()].into_iter().chain(fee_payer).chain(outputs) {
+            self.check_address(address)?;
+        }
+        Ok(())
+    }
+    
+    /// Заводит кошелек для адреса, полученного извне, например из другого узла или от пользователя,
+    /// с открытым ключом владельца в hex. Ключ адреса сети должен быть тем, из которого адрес выведен;
+    /// кошельку, который уже есть, но без ключа (например, у майнера), он записывается так же. За имя
+    /// ключ закрепляет только транзакция `RegisterKey` его владельца, поэтому ключ имени должен быть
+    /// уже закреплен в цепочке или в пуле. Без ключа кошелек получает новую ключевую пару, как в
+    /// `create_wallet`.
+    pub fn import_wallet(&mut self, address: &str, public_key: Option<&str>) -> Result<&Wallet, BlockchainError> {
+        let Some(public_key) = public_key else {
+            return self.create_wallet(address.to_string());
+        };
+        let key = parse_public_key(public_key)
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("{} is not an ed25519 public key", public_key)))?;
+        let public_key = hex::encode(key.as_bytes());
+        if let Address::Network { bytes, .. } = self.check_address(address)? {
+            if address_bytes(&key) != bytes {
+                return Err(BlockchainError::InvalidTransaction(format!("Key of wallet {} does not match its address", address)));
+            }
+        } else {
+            let registered = match self.registered_key(address) {
+                Some(registered) => Some(registered == public_key),
+                None => registered_keys(&self.mempool.lock().transactions).get(address).map(|registered| *registered == public_key),
+            };
+            return match registered {
+                Some(true) => self.insert_wallet(Wallet::new(address.to_string())),
+                Some(false) => Err(BlockchainError::InvalidTransaction(format!("Wallet {} is registered to another key", address))),
+                None => Err(BlockchainError::InvalidTransaction(format!(
+                    "Key of wallet {} is not registered; its owner sends a RegisterKey transaction signed by it", address
+                ))),
+            };
+        }
+        if self.wallets.get(address).is_some_and(|wallet| wallet.public_key.is_none()) {
+            let wallet = self.get_or_create_wallet(address);
+            wallet.public_key = Some(public_key);
+            return Ok(wallet);
+        }
+        let mut wallet = Wallet::new(address.to_string());
+        wallet.public_key = Some(public_key);
+        self.insert_wallet(wallet)
+    }
+    
+    /// Добавляет готовый кошелек, например из `Wallet::generate` или поиска красивого адреса.
+    /// Ключ кошелька с адресом сети должен быть тем, из которого адрес выведен, а за имя с ключевой
+    /// парой в пул ставится закрепление ее ключа, как в `create_wallet`.
+    pub fn add_wallet(&mut self, wallet: Wallet) -> Result<&Wallet, BlockchainError> {
+        let address = self.check_address(&wallet.address)?;
+        if let Some(keypair) = wallet.keypair() {
+            if let Address::Network { bytes, .. } = address {
+                if address_bytes(&keypair.public_key()) != bytes {
+                    return Err(BlockchainError::InvalidTransaction(format!("Key of wallet {} does not match its address", wallet.address)));
+                }
+            } else if !self.wallets.contains_key(&wallet.address) {
+                self.register_key(&wallet.address, keypair)?;
+            }
+        }
+        self.insert_wallet(wallet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::builder::BlockchainBuilder;
+    use crate::entropy::SeededEntropy;
+    use crate::spec::ChainSpec;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    fn chain(network: Network) -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.network = network;
+        chain
+    }
+    
+    fn wrong_network<T>(result: Result<T, BlockchainError>, found: &str) -> bool {
+        matches!(result, Err(BlockchainError::WrongNetwork { expected: "mainnet", found: reported }) if reported == found)
+    }
+    
+    #[test]
+    fn addresses_round_trip_on_both_networks() {
+        for network in [Network::Mainnet, Network::Testnet] {
+            let wallet = Wallet::generate(&SeededEntropy::new(7), network);
+            assert!(wallet.address.starts_with(network.prefix()));
+            let parsed = Address::parse(&wallet.address).unwrap();
+            assert_eq!(parsed.network(), Some(network));
+            assert_eq!(parsed.to_string(), wallet.address);
+            
+            // Та же пара в другой сети: другой префикс и другая сумма
+            let other = if network == Network::Mainnet { Network::Testnet } else { Network::Mainnet };
+            let moved = format!("{}{}", other.prefix(), &wallet.address[network.prefix().len()..]);
+            assert!(matches!(Address::parse(&moved), Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("checksum")));
+            assert!(Address::parse(&wallet.address.to_uppercase()).is_err());
+        }
+        
+        assert_eq!(Address::parse("BLOCKCHAIN_REWARD").unwrap(), Address::System("BLOCKCHAIN_REWARD".to_string()));
+        assert_eq!(Address::parse("contract_counter").unwrap().network(), None);
+        assert_eq!(Address::parse("alice").unwrap(), Address::Legacy("alice".to_string()));
+    }
+    
+    #[test]
+    fn other_network_addresses_are_rejected_at_every_entry_point() {
+        let mut chain = chain(Network::Mainnet);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let foreign = Wallet::generate(&SeededEntropy::new(1), Network::Testnet);
+        let foreign_key = foreign.keypair().unwrap().public_key_hex();
+        
+        assert!(wrong_network(chain.check_address(&foreign.address), "testnet"));
+        assert!(wrong_network(chain.create_wallet(foreign.address.clone()), "testnet"));
+        assert!(wrong_network(chain.import_wallet(&foreign.address, Some(&foreign_key)), "testnet"));
+        assert!(wrong_network(chain.add_wallet(foreign.clone()), "testnet"));
+        assert!(wrong_network(chain.get_block_template(&foreign.address), "testnet"));
+        
+        let mut tx = Transaction::new("alice".to_string(), foreign.address.clone(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut tx);
+        assert!(wrong_network(chain.add_transaction(tx), "testnet"));
+        assert!(chain.mempool().is_empty());
+        assert!(!chain.wallets.contains_key(&foreign.address));
+        
+        // Без режима совместимости имена демо-цепочек тоже чужие, а служебные адреса годятся всегда
+        chain.accept_legacy_addresses = false;
+        assert!(wrong_network(chain.check_address("bob"), "legacy"));
+        assert!(chain.check_address("BLOCKCHAIN_REWARD").is_ok());
+        let own = Wallet::generate(&SeededEntropy::new(2), Network::Mainnet);
+        assert!(chain.add_wallet(own).is_ok());
+    }
+    
+    #[test]
+    fn legacy_names_are_refused_unless_the_chain_opts_in() {
+        let spec: ChainSpec = serde_json::from_value(serde_json::json!({ "difficulty": 1, "mining_reward": 50.0, "consensus": "ProofOfWork", "allocations": { "alice": 100.0 } })).unwrap();
+        assert!(!spec.accept_legacy_addresses);
+        assert!(wrong_network(spec.build(), "legacy"));
+        
+        let mut stored = serde_json::to_value(Blockchain::new(1, 100.0, ConsensusAlgorithm::ProofOfWork)).unwrap();
+        stored.as_object_mut().unwrap().remove("accept_legacy_addresses");
+        let mut stored: Blockchain = serde_json::from_value(stored).unwrap();
+        let built = BlockchainBuilder::new().build().unwrap();
+        for mut chain in [Blockchain::new(1, 100.0, ConsensusAlgorithm::ProofOfWork), built] {
+            assert!(wrong_network(chain.create_wallet("alice".to_string()), "legacy"));
+            assert!(wrong_network(chain.mine_pending_transactions("miner".to_string()), "legacy"));
+            let own = Wallet::generate(&SeededEntropy::new(4), Network::Mainnet);
+            chain.mine_pending_transactions(own.address).unwrap();
+        }
+        
+        // Цепочка, сохраненная до появления поля, продолжает принимать свои имена
+        assert!(stored.accept_legacy_addresses);
+        stored.create_wallet("alice".to_string()).unwrap();
+        stored.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let mut chain = BlockchainBuilder::new().accept_legacy_addresses(true).build().unwrap();
+        chain.create_wallet("alice".to_string()).unwrap();
+    }
+    
+    #[test]
+    fn stored_keys_derive_the_address_of_the_chain_they_load_into() {
+        let data_dir = std::env::temp_dir().join(format!("hellochain-address-{}", std::process::id()));
+        let mut testnet = chain(Network::Testnet);
+        let wallet = Wallet::generate(&SeededEntropy::new(3), Network::Testnet);
+        let address = wallet.address.clone();
+        testnet.add_wallet(wallet).unwrap();
+        let loaded = testnet.save(&data_dir).and_then(|_| Blockchain::load(&data_dir));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.network(), Network::Testnet);
+        
+        let keypair = loaded.get_wallet_info(&address).unwrap().keypair().unwrap().clone();
+        assert_eq!(Wallet::from_keypair(keypair.clone(), loaded.network()).address, address);
+        // В основной сети тот же ключ дает адрес с ее префиксом, и только такой она принимает
+        let mut mainnet = chain(Network::Mainnet);
+        let rederived = Wallet::from_keypair(keypair.clone(), mainnet.network());
+        assert!(rederived.address.starts_with("hc1"));
+        assert_eq!(Address::parse(&rederived.address).unwrap().network(), Some(Network::Mainnet));
+        assert!(wrong_network(mainnet.add_wallet(Wallet::from_keypair(keypair, Network::Testnet)), "testnet"));
+        assert!(mainnet.add_wallet(rederived).is_ok());
+    }
+    
+    #[test]
+    fn typos_and_case_changes_are_caught() {
+        let address = Wallet::generate(&SeededEntropy::new(11), Network::Mainnet).address;
+        let prefix = Network::Mainnet.prefix().len();
+        validate_address(&address).unwrap();
+        
+        // Любая одна замененная цифра ломает контрольную сумму
+        for position in prefix..address.len() {
+            let original = address.as_bytes()[position];
+            let replacement = if original == b'0' { '1' } else { '0' };
+            let mut typo = address.clone();
+            typo.replace_range(position..position + 1, &replacement.to_string());
+            let result = validate_address(&typo);
+            assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("checksum")), "{}: {:?}", typo, result);
+        }
+        // Пропущенная или лишняя цифра меняет длину
+        assert!(validate_address(&address[..address.len() - 1]).is_err());
+        assert!(validate_address(&format!("{}0", address)).is_err());
+        
+        // Смешанный регистр не дает второго написания того же адреса
+        let letter = address[prefix..].find(|c: char| c.is_ascii_alphabetic()).expect("hex has a letter") + prefix;
+        let mut mixed = address.clone();
+        mixed.replace_range(letter..letter + 1, &address[letter..letter + 1].to_uppercase());
+        assert!(matches!(validate_address(&mixed), Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("uppercase")));
+        assert!(validate_address(&address.to_uppercase()).is_err());
+        
+        for reserved in ["BLOCKCHAIN_REWARD", "BLOCKCHAIN_DATA", "contract_counter", "alice"] {
+            validate_address(reserved).unwrap();
+        }
+    }
+    
+    #[test]
+    fn network_address_only_moves_funds_with_its_own_key() {
+        let mut chain = chain(Network::Mainnet);
+        let owner = Wallet::generate(&SeededEntropy::new(21), Network::Mainnet);
+        let stranger = Wallet::generate(&SeededEntropy::new(22), Network::Mainnet);
+        let address = owner.address.clone();
+        let stranger_key = stranger.keypair().unwrap().clone();
+        
+        // Ключ, из которого адрес не выводится, к нему не привязать
+        let forged = Wallet::with_keypair(address.clone(), stranger_key.clone());
+        assert!(chain.add_wallet(forged).is_err());
+        assert!(chain.import_wallet(&address, Some(&stranger_key.public_key_hex())).is_err());
+        assert!(!chain.wallets.contains_key(&address));
+        
+        // Адрес сети заводится без ключа, а свой ключ потом дописывается
+        assert!(chain.create_wallet(address.clone()).unwrap().keypair().is_none());
+        chain.mine_pending_transactions(address.clone()).unwrap();
+        let owner_key = owner.keypair().unwrap().clone();
+        chain.import_wallet(&address, Some(&owner_key.public_key_hex())).unwrap();
+        
+        let mut stolen = Transaction::new(address.clone(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        stolen.set_nonce(chain.get_account_nonce(&address));
+        stolen.sign(&stranger_key);
+        assert!(matches!(chain.add_transaction(stolen), Err(BlockchainError::InvalidSignature { .. })));
+        
+        let mut spend = Transaction::new(address.clone(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        spend.set_nonce(chain.get_account_nonce(&address));
+        spend.sign(&owner_key);
+        chain.add_transaction(spend).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("bob"), 10.0);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::mempool::reserved_asset;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Больше стольких знаков после запятой у актива быть не может
+pub const MAX_ASSET_DECIMALS: u8 = 18;
+
+/// Актив, выпущенный пользователем; суммы хранятся в минимальных единицах
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Asset {
+    /// Идентификатор транзакции выпуска
+    pub id: String,
+    pub ticker: String,
+    pub issuer: String,
+    pub total_supply: u64,
+    pub decimals: u8,
+}
+
+/// Сверка выпуска актива с балансами кошельков и переводами в пуле
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetSupplyAudit {
+    pub asset_id: String,
+    pub ticker: String,
+    pub total_supply: u64,
+    /// Сумма балансов кошельков за вычетом занятого пулом
+    pub held: u64,
+    /// Занято ожидающими переводами: спишется с отправителей, когда они попадут в блок
+    pub in_flight: u64,
+}
+
+impl AssetSupplyAudit {
+    pub fn is_conserved(&self) -> bool {
+        self.held.checked_add(self.in_flight) == Some(self.total_supply)
+    }
+}
+
+/// Какой актив и сколько транзакция списывает с отправителя
+pub(crate) fn asset_debit(tx: &Transaction) -> Option<(&str, u64)> {
+    match &tx.transaction_type {
+        TransactionType::AssetTransfer { asset_id, amount } => Some((asset_id, *amount)),
+        _ => None,
+    }
+}
+
+impl Blockchain {
+    /// Ставит в очередь выпуск актива; после включения в блок весь выпуск зачисляется эмитенту.
+    /// Возвращает идентификатор актива — идентификатор транзакции выпуска.
+    pub fn issue_asset(&mut self, issuer: &str, ticker: &str, total_supply: u64, decimals: u8) -> Result<String, BlockchainError> {
+        let tx = self.new_transaction(
+            issuer.to_string(),
+            issuer.to_string(),
+            0.0,
+            TransactionType::IssueAsset { ticker: ticker.to_string(), total_supply, decimals },
+        );
+        self.queue_asset_transaction(tx)
+    }
+    
+    /// Ставит в очередь перевод актива; комиссия платится в основной монете
+    pub fn transfer_asset(&mut self, sender: &str, receiver: &str, asset_id: &str, amount: u64) -> Result<String, BlockchainError> {
+        let tx = self.new_transaction(
+            sender.to_string(),
+            receiver.to_string(),
+            0.0,
+            TransactionType::AssetTransfer { asset_id: asset_id.to_string(), amount },
+        );
+        self.queue_asset_transaction(tx)
+    }
+    
+    fn queue_asset_transaction(&mut self, mut tx: Transaction) -> Result<String, BlockchainError> {
+        if let Some(base_fee) = self.current_base_fee() {
+            tx.fee = tx.fee.max(base_fee * tx.weight() as f64);
+        }
+        self.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    pub fn get_asset(&self, asset_id: &str) -> Option<&Asset> {
+        self.assets.get(asset_id)
+    }
+    
+    pub fn find_asset_by_ticker(&self, ticker: &str) -> Option<&Asset> {
+        self.assets.values().find(|asset| asset.ticker == ticker)
+    }
+    
+    /// Баланс адреса в активе, в минимальных единицах, за вычетом занятого транзакциями пула
+    pub fn get_asset_balance(&self, address: &str, asset_id: &str) -> u64 {
+        let held = self.wallets.get(address).and_then(|wallet| wallet.assets.get(asset_id)).copied().unwrap_or(0);
+        held.saturating_sub(reserved_asset(&self.mempool.lock().transactions, address, asset_id))
+    }
+    
+    /// Проверяет выпуск и перевод актива без учета балансов: актив должен существовать,
+    /// тикер — быть свободным, а основная монета — не переводиться
+    pub(crate) fn check_asset_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        match &tx.transaction_type {
+            TransactionType::IssueAsset { ticker, total_supply, decimals } => {
+                if ticker.is_empty() || ticker.len() > 12 || !ticker.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+                    return Err(BlockchainError::InvalidTransaction(format!("Ticker {:?} must be 1-12 uppercase letters or digits", ticker)));
+                }
+                if self.find_asset_by_ticker(ticker).is_some() {
+                    return Err(BlockchainError::InvalidTransaction(format!("Ticker {} is already taken", ticker)));
+                }
+                if *total_supply == 0 || *decimals > MAX_ASSET_DECIMALS {
+                    return Err(BlockchainError::InvalidTransaction(format!("Asset {} has supply {} and {} decimals", ticker, total_supply, decimals)));
+                }
+                if tx.receiver != tx.sender {
+                    return Err(BlockchainError::InvalidTransaction(format!("Asset {} must be issued to its issuer", ticker)));
+                }
+            },
+            TransactionType::AssetTransfer { asset_id, amount } => {
+                if !self.assets.contains_key(asset_id) {
+                    return Err(BlockchainError::InvalidTransaction(format!("Asset {} not found", asset_id)));
+                }
+                if *amount == 0 {
+                    return Err(BlockchainError::InvalidTransaction(format!("Transfer {} moves no {}", tx.id, asset_id)));
+                }
+            },
+            _ => return Ok(()),
+        }
+        
+        if tx.amount != 0.0 {
+            return Err(BlockchainError::InvalidTransaction(format!("Asset transaction {} must not move native coins", tx.id)));
+        }
+        Ok(())
+    }
+    
+    /// Проверяет, что тикеры выпусков в наборе транзакций не повторяются
+    pub(crate) fn check_unique_tickers<'a>(&self, transactions: impl IntoIterator<Item = &'a Transaction>) -> Result<(), BlockchainError> {
+        let mut tickers = HashSet::new();
+        for tx in transactions {
+            if let TransactionType::IssueAsset { ticker, .. } = &tx.transaction_type {
+                if !tickers.insert(ticker) {
+                    return Err(BlockchainError::InvalidTransaction(format!("Ticker {} is already taken", ticker)));
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Регистрирует выпуски из блока и зачисляет переводы активов получателям
+    pub(crate) fn apply_asset_transactions(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            let (asset_id, amount) = match &tx.transaction_type {
+                TransactionType::IssueAsset { ticker, total_supply, decimals } => {
+                    self.assets.insert(tx.id.clone(), Asset {
+                        id: tx.id.clone(),
+                        ticker: ticker.clone(),
+                        issuer: tx.sender.clone(),
+                        total_supply: *total_supply,
+                        decimals: *decimals,
+                    });
+                    (&tx.id, *total_supply)
+                },
+                TransactionType::AssetTransfer { asset_id, amount } => (asset_id, *amount),
+                _ => continue,
+            };
+            
+            let wallet = self.get_or_create_wallet(&tx.receiver);
+            *wallet.assets.entry(asset_id.clone()).or_insert(0) += amount;
+        }
+    }
+    
+    /// Сверяет выпуск каждого актива с суммой балансов и переводов в пуле
+    pub fn audit_asset_supplies(&self) -> Vec<AssetSupplyAudit> {
+        let mut held: BTreeMap<&str, u64> = BTreeMap::new();
+        for wallet in self.wallets.values() {
+            for (asset_id, balance) in &wallet.assets {
+                *held.entry(asset_id).or_insert(0) += balance;
+            }
+        }
+        let pool = self.mempool.lock();
+        let mut in_flight: BTreeMap<&str, u64> = BTreeMap::new();
+        for (asset_id, amount) in pool.transactions.iter().filter_map(asset_debit) {
+            *in_flight.entry(asset_id).or_insert(0) += amount;
+        }
+        
+        let mut audits: Vec<AssetSupplyAudit> = self.assets.values()
+            .map(|asset| AssetSupplyAudit {
+                asset_id: asset.id.clone(),
+                ticker: asset.ticker.clone(),
+                total_supply: asset.total_supply,
+                held: held.get(asset.id.as_str()).copied().unwrap_or(0)
+                    .saturating_sub(in_flight.get(asset.id.as_str()).copied().unwrap_or(0)),
+                in_flight: in_flight.get(asset.id.as_str()).copied().unwrap_or(0),
+            })
+            .collect();
+        audits.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+        audits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    /// Цепочка, где `alice` выпустила 1000 единиц PTS
+    fn chain_with_asset() -> (Blockchain, String) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let asset_id = chain.issue_asset("alice", "PTS", 1_000, 2).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        (chain, asset_id)
+    }
+    
+    fn assert_conserved(chain: &Blockchain) {
+        for audit in chain.audit_asset_supplies() {
+            assert!(audit.is_conserved(), "{:?}", audit);
+        }
+    }
+    
+    #[test]
+    fn transfer_moves_the_asset_and_pays_the_fee_in_coins() {
+        let (mut chain, asset_id) = chain_with_asset();
+        let asset = chain.get_asset(&asset_id).unwrap();
+        assert_eq!((asset.ticker.as_str(), asset.issuer.as_str(), asset.total_supply), ("PTS", "alice", 1_000));
+        assert_eq!(chain.get_asset_balance("alice", &asset_id), 1_000);
+        
+        let alice_coins = chain.get_balance("alice");
+        let tx_id = chain.transfer_asset("alice", "bob", &asset_id, 300).unwrap();
+        let fee = chain.mempool().transactions().iter().find(|tx| tx.id == tx_id).unwrap().fee;
+        assert!(fee > 0.0);
+        let audit = chain.audit_asset_supplies();
+        assert_eq!((audit[0].held, audit[0].in_flight), (700, 300));
+        assert_conserved(&chain);
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_asset_balance("alice", &asset_id), 700);
+        assert_eq!(chain.get_asset_balance("bob", &asset_id), 300);
+        assert!((chain.get_balance("alice") - (alice_coins - fee)).abs() < 1e-9);
+        assert_eq!(chain.get_balance("bob"), 0.0);
+        assert_conserved(&chain);
+        assert!(chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn overdrafts_unknown_assets_and_taken_tickers_are_rejected() {
+        let (mut chain, asset_id) = chain_with_asset();
+        
+        let overdraft = chain.transfer_asset("alice", "bob", &asset_id, 1_001);
+        assert!(matches!(overdraft, Err(BlockchainError::InsufficientBalance { .. })), "{:?}", overdraft);
+        chain.transfer_asset("alice", "bob", &asset_id, 600).unwrap();
+        // Переводы в пуле уже списаны, второй не помещается в остаток
+        assert!(matches!(chain.transfer_asset("alice", "bob", &asset_id, 401), Err(BlockchainError::InsufficientBalance { .. })));
+        
+        let unknown = chain.transfer_asset("alice", "bob", "missing", 1);
+        assert!(matches!(&unknown, Err(BlockchainError::InvalidTransaction(reason)) if reason == "Asset missing not found"), "{:?}", unknown);
+        assert!(chain.issue_asset("alice", "PTS", 5, 0).is_err());
+        chain.issue_asset("alice", "NEW", 5, 0).unwrap();
+        assert!(chain.issue_asset("alice", "NEW", 5, 0).is_err());
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_asset_balance("alice", &asset_id), 400);
+        assert_eq!(chain.audit_asset_supplies().len(), 2);
+        assert_conserved(&chain);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::block::Block;
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::errors::BlockchainError;
+use crate::events::ChainEvent;
+use crate::mining::MiningError;
+use crate::shared::SharedBlockchain;
+use crate::transaction::Transaction;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+
+/// Асинхронная обертка над `SharedBlockchain`: блокирующие вызовы уходят в пул `spawn_blocking`,
+/// чтобы не занимать потоки рантайма
+#[derive(Clone)]
+pub struct AsyncBlockchain {
+    chain: SharedBlockchain,
+}
+
+/// Останавливает фоновый майнинг, если будущее `mine` сброшено до окончания
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl AsyncBlockchain {
+    pub fn new(blockchain: Blockchain) -> Self {
+        AsyncBlockchain { chain: SharedBlockchain::new(blockchain) }
+    }
+    
+    /// Общий синхронный дескриптор той же цепочки
+    pub fn shared(&self) -> &SharedBlockchain {
+        &self.chain
+    }
+    
+    /// Выполняет `f` в пуле блокирующих потоков; паника там пробрасывается вызывающему
+    async fn blocking<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(SharedBlockchain) -> T + Send + 'static,
+    {
+        let chain = self.chain.clone();
+        tokio::task::spawn_blocking(move || f(chain)).await
+            .unwrap_or_else(|e| panic::resume_unwind(e.into_panic()))
+    }
+    
+    /// Майнит ожидающие транзакции и возвращает добавленный блок. PoW перебирается фоновым майнером
+    /// без блокировки цепочки; сброс будущего останавливает его потоки.
+    pub async fn mine(&self, miner_address: String) -> Result<Block, MiningError> {
+        let proof_of_work = self.chain.read().consensus_algorithm == ConsensusAlgorithm::ProofOfWork;
+        if !proof_of_work {
+            return self.blocking(move |chain| {
+                let mut chain = chain.write();
+                chain.mine_pending_transactions(miner_address)?;
+                Ok(chain.get_latest_block().clone())
+            }).await;
+        }
+        
+        let handle = self.chain.start_mining(miner_address);
+        let _cancel = CancelOnDrop(handle.cancel_flag());
+        self.blocking(move |_| handle.join()).await
+    }
+    
+    /// Проверяет транзакцию и ставит ее в очередь ожидающих
+    pub async fn submit_transaction(&self, transaction: Transaction) -> Result<(), BlockchainError> {
+        self.blocking(move |chain| chain.add_transaction(transaction)).await
+    }
+    
+    pub async fn get_balance(&self, address: &str) -> f64 {
+        let address = address.to_string();
+        self.blocking(move |chain| chain.read().get_balance(&address)).await
+    }
+    
+    /// Поток событий цепочки. Подписчик, не успевающий разобрать `capacity` событий, отключается,
+    /// и поток завершается.
+    pub fn events(&self, capacity: usize) -> impl Stream<Item = ChainEvent> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(capacity.max(1));
+        self.chain.write().add_event_hook(Box::new(move |event| sender.try_send(event.clone()).is_ok()));
+        ReceiverStream::new(receiver)
+    }
+    
+    /// Сохраняет цепочку в каталог данных
+    pub async fn save(&self, data_dir: impl AsRef<Path>) -> Result<(), BlockchainError> {
+        let data_dir = data_dir.as_ref().to_path_buf();
+        self.blocking(move |chain| chain.read().save(&data_dir)).await
+    }
+    
+    /// Загружает цепочку из каталога данных
+    pub async fn load(data_dir: impl Into<PathBuf>) -> Result<AsyncBlockchain, BlockchainError> {
+        let data_dir = data_dir.into();
+        tokio::task::spawn_blocking(move || Blockchain::load(&data_dir)).await
+            .unwrap_or_else(|e| panic::resume_unwind(e.into_panic()))
+            .map(AsyncBlockchain::new)
+    }
+}
+
+impl From<SharedBlockchain> for AsyncBlockchain {
+    fn from(chain: SharedBlockchain) -> Self {
+        AsyncBlockchain { chain }
+    }
+}
+
+impl From<Blockchain> for AsyncBlockchain {
+    fn from(blockchain: Blockchain) -> Self {
+        AsyncBlockchain::new(blockchain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::clock::Clock;
+    use crate::mining::MiningOptions;
+    use crate::target::CompactTarget;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    use std::sync::atomic::AtomicU64;
+    use std::time::{Duration, Instant};
+    use tokio_stream::StreamExt;
+    
+    /// Часы, считающие обращения: майнинг обращается к ним между раундами перебора
+    #[derive(Default)]
+    struct CountingClock {
+        calls: AtomicU64,
+    }
+    
+    impl Clock for CountingClock {
+        fn now_ts(&self) -> i64 {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            GENESIS_TIMESTAMP
+        }
+    }
+    
+    /// Цепочка с добытым блоком у alice; дальше майнинг идет с заданной сложностью короткими раундами
+    fn chain(difficulty: usize, clock: Arc<CountingClock>) -> AsyncBlockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.set_clock(clock);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain.difficulty = difficulty;
+        chain.target = Some(CompactTarget::from_difficulty(difficulty));
+        chain.mining_threads = Some(1);
+        chain.mining_options = MiningOptions { roll_interval: Some(1 << 10), extra_nonce: true };
+        AsyncBlockchain::new(chain)
+    }
+    
+    fn transfer(chain: &AsyncBlockchain, amount: f64) -> Transaction {
+        let chain = chain.shared().read();
+        let mut tx = Transaction::new_at("alice".to_string(), "bob".to_string(), amount, TransactionType::Transfer, chain.now_ts());
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut tx);
+        tx
+    }
+    
+    /// Ждет в блокирующем пуле, чтобы не занимать поток рантайма
+    async fn pause(duration: Duration) {
+        tokio::task::spawn_blocking(move || std::thread::sleep(duration)).await.unwrap();
+    }
+    
+    /// Ждет, пока майнинг пройдет хотя бы один раунд
+    async fn wait_for_rounds(clock: &CountingClock) {
+        let start = clock.calls.load(Ordering::Relaxed);
+        while clock.calls.load(Ordering::Relaxed) == start {
+            pause(Duration::from_millis(1)).await;
+        }
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn submits_complete_during_an_in_flight_mine() {
+        let clock = Arc::new(CountingClock::default());
+        let chain = chain(12, clock.clone());
+        let mining = tokio::spawn({
+            let chain = chain.clone();
+            async move { chain.mine("miner".to_string()).await }
+        });
+        wait_for_rounds(&clock).await;
+        
+        let started = Instant::now();
+        for amount in [1.0, 2.0, 3.0] {
+            chain.submit_transaction(transfer(&chain, amount)).await.unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+        assert_eq!(chain.shared().read().mempool().len(), 3);
+        assert_eq!(chain.get_balance("alice").await, 100.0 - 6.0 * 1.001);
+        assert!(!mining.is_finished());
+        mining.abort();
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dropping_the_mine_future_stops_the_worker() {
+        let clock = Arc::new(CountingClock::default());
+        let chain = chain(12, clock.clone());
+        let mining = tokio::spawn({
+            let chain = chain.clone();
+            async move { chain.mine("miner".to_string()).await }
+        });
+        wait_for_rounds(&clock).await;
+        mining.abort();
+        assert!(mining.await.unwrap_err().is_cancelled());
+        
+        // Остановленный перебор больше не начинает раунды
+        pause(Duration::from_millis(100)).await;
+        let rounds = clock.calls.load(Ordering::Relaxed);
+        pause(Duration::from_millis(200)).await;
+        assert_eq!(clock.calls.load(Ordering::Relaxed), rounds);
+        assert_eq!(chain.shared().read().chain.len(), 2);
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn event_stream_yields_mined_blocks_in_order() {
+        let chain = chain(1, Arc::new(CountingClock::default()));
+        let events = chain.events(16);
+        tokio::pin!(events);
+        
+        let mut mined = Vec::new();
+        for _ in 0..3 {
+            mined.push(chain.mine("miner".to_string()).await.unwrap().hash);
+        }
+        let mut received = Vec::new();
+        while received.len() < mined.len() {
+            if let Some(ChainEvent::BlockAdded { block }) = events.next().await {
+                received.push(block.hash);
+            }
+        }
+        assert_eq!(received, mined);
+        
+        let dir = std::env::temp_dir().join(format!("hellochain-async-{}", std::process::id()));
+        chain.save(&dir).await.unwrap();
+        let loaded = AsyncBlockchain::load(dir.clone()).await;
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(loaded.unwrap().shared().read().get_latest_block().hash, mined[2]);
+    }
+}
@@ -0,0 +1,371 @@
+use crate::block::Block;
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::errors::BlockchainError;
+use crate::mining::MiningError;
+use crate::shared::SharedBlockchain;
+use crate::trace::{info, warn};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Как часто по умолчанию фоновый майнер смотрит на часы и пул
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Во сколько раз больше интервала может вырасти пауза после ошибок подряд
+const MAX_BACKOFF_FACTOR: u64 = 64;
+
+/// Настройки фонового майнера
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoMinerConfig {
+    /// Кому достается награда; в PoS и DPoS это валидатор, который печатает блоки
+    pub producer: String,
+    /// Сколько секунд по часам цепочки проходит между попытками выпустить блок
+    pub interval_secs: u64,
+    /// Не выпускать блоки, когда в пуле нет транзакций
+    pub skip_empty_blocks: bool,
+    /// Остановиться после стольких блоков; без значения — работать до `stop`
+    pub max_blocks: Option<u64>,
+    /// Столько транзакций в пуле будят майнер, не дожидаясь интервала
+    pub pending_threshold: Option<usize>,
+    /// Как часто поток смотрит на часы и пул
+    pub poll_interval: Duration,
+}
+
+impl AutoMinerConfig {
+    pub fn new(producer: String, interval_secs: u64) -> Self {
+        AutoMinerConfig {
+            producer,
+            interval_secs,
+            skip_empty_blocks: false,
+            max_blocks: None,
+            pending_threshold: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+}
+
+/// Состояние фонового майнера
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutoMinerStatus {
+    /// Работает ли еще поток майнера
+    pub running: bool,
+    pub blocks_produced: u64,
+    /// Высота последнего выпущенного блока
+    pub last_block: Option<u64>,
+    pub last_error: Option<String>,
+    /// Ошибок подряд; после каждой пауза до следующей попытки удваивается
+    pub consecutive_errors: u32,
+}
+
+/// Общее для потока майнера и его дескриптора: флаг остановки и состояние
+#[derive(Default)]
+struct AutoMinerShared {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+    status: Mutex<AutoMinerStatus>,
+}
+
+impl AutoMinerShared {
+    /// Ждет до `timeout` или до остановки; возвращает true, если майнер остановлен
+    fn wait(&self, timeout: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *stopped {
+            return true;
+        }
+        let (stopped, _) = self.wake.wait_timeout(stopped, timeout).unwrap_or_else(|poisoned| poisoned.into_inner());
+        *stopped
+    }
+    
+    fn stop(&self) {
+        *self.stopped.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        self.wake.notify_all();
+    }
+    
+    fn status(&self) -> MutexGuard<'_, AutoMinerStatus> {
+        self.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Дескриптор фонового майнера; при удалении майнер тоже останавливается
+pub struct AutoMinerHandle {
+    shared: Arc<AutoMinerShared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl AutoMinerHandle {
+    /// Сколько блоков выпущено и чем закончилась последняя ошибка
+    pub fn status(&self) -> AutoMinerStatus {
+        self.shared.status().clone()
+    }
+    
+    /// Останавливает майнер и дожидается его потока. Перебор PoW бросается без добавления блока,
+    /// а блок, который уже добавляется, добавляется целиком: это происходит под блокировкой цепочки.
+    pub fn stop(mut self) -> AutoMinerStatus {
+        self.shutdown();
+        self.status()
+    }
+    
+    fn shutdown(&mut self) {
+        self.shared.stop();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for AutoMinerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl SharedBlockchain {
+    /// Запускает фоновый майнер: он выпускает блок обычным путем раз в `interval_secs` секунд по часам
+    /// цепочки или сразу, когда в пуле набралось `pending_threshold` транзакций. В PoS и DPoS блок
+    /// выпускается только в слоты, где производитель вытянул жребий. После ошибки пауза удваивается.
+    pub fn start_auto_miner(&self, config: AutoMinerConfig) -> AutoMinerHandle {
+        let shared = Arc::new(AutoMinerShared::default());
+        shared.status().running = true;
+        
+        // Отсчет интервала начинается с запуска, а не с того, когда поток успел проснуться
+        let first_attempt = self.read().now_ts().saturating_add_unsigned(config.interval_secs);
+        let chain = self.clone();
+        let worker_shared = shared.clone();
+        let worker = thread::spawn(move || {
+            run_auto_miner(&chain, &config, first_attempt, &worker_shared);
+            worker_shared.status().running = false;
+        });
+        
+        AutoMinerHandle { shared, worker: Some(worker) }
+    }
+}
+
+fn run_auto_miner(chain: &SharedBlockchain, config: &AutoMinerConfig, first_attempt: i64, shared: &AutoMinerShared) {
+    let interval = config.interval_secs;
+    let mut next_attempt = first_attempt;
+    
+    while !shared.wait(config.poll_interval) {
+        let (now, pending, slot_open) = {
+            let chain = chain.read();
+            (chain.now_ts(), chain.mempool().len(), chain.producer_slot_open(&config.producer))
+        };
+        // Порог будит майнер только без недавних ошибок, иначе пауза не соблюдалась бы
+        let errors = shared.status().consecutive_errors;
+        let woken = errors == 0 && config.pending_threshold.is_some_and(|threshold| pending >= threshold.max(1));
+        if (now < next_attempt && !woken) || !slot_open {
+            continue;
+        }
+        if config.skip_empty_blocks && pending == 0 {
+            next_attempt = now.saturating_add_unsigned(interval);
+            continue;
+        }
+        
+        match produce_block(chain, &config.producer, config.poll_interval, shared) {
+            Ok(Some(block)) => {
+                info!(index = block.index, hash = %block.hash, txs = block.transactions.len(), "Auto miner produced a block");
+                let mut status = shared.status();
+                status.blocks_produced += 1;
+                status.last_block = Some(block.index);
+                status.consecutive_errors = 0;
+                if config.max_blocks.is_some_and(|max_blocks| status.blocks_produced >= max_blocks) {
+                    return;
+                }
+                next_attempt = now.saturating_add_unsigned(interval);
+            },
+            Ok(None) => return,
+            Err(error) => {
+                let mut status = shared.status();
+                status.consecutive_errors = status.consecutive_errors.saturating_add(1);
+                status.last_error = Some(error.to_string());
+                let factor = 1u64.checked_shl(status.consecutive_errors).unwrap_or(u64::MAX).min(MAX_BACKOFF_FACTOR);
+                let backoff = interval.max(1).saturating_mul(factor);
+                warn!(%error, errors = status.consecutive_errors, backoff, "Auto miner failed to produce a block");
+                next_attempt = now.saturating_add_unsigned(backoff);
+            },
+        }
+    }
+}
+
+/// Выпускает один блок. PoW перебирается без блокировки цепочки и бросается при остановке майнера;
+/// None — майнер остановлен, блок не добавлен.
+fn produce_block(chain: &SharedBlockchain, producer: &str, poll_interval: Duration, shared: &AutoMinerShared) -> Result<Option<Block>, BlockchainError> {
+    if chain.read().consensus_algorithm != ConsensusAlgorithm::ProofOfWork {
+        return chain.mine_block(producer.to_string()).map(Some);
+    }
+    
+    loop {
+        let handle = chain.start_mining(producer.to_string());
+        while !handle.is_finished() {
+            if shared.wait(poll_interval) {
+                handle.cancel();
+            }
+        }
+        match handle.join() {
+            Ok(block) => return Ok(Some(block)),
+            Err(MiningError::Cancelled) => return Ok(None),
+            Err(MiningError::Preempted { .. } | MiningError::StaleTip { .. }) => {
+                if shared.wait(Duration::ZERO) {
+                    return Ok(None);
+                }
+            },
+            Err(MiningError::Chain(error)) => return Err(error),
+        }
+    }
+}
+
+impl Blockchain {
+    /// Может ли `producer` выпустить блок сейчас: в PoW всегда, в PoS и DPoS — если прошел наименьший
+    /// интервал после вершины и производитель вытянул жребий текущего слота
+    pub fn producer_slot_open(&self, producer: &str) -> bool {
+        if self.consensus_algorithm == ConsensusAlgorithm::ProofOfWork {
+            return true;
+        }
+        if self.jailed_validators.contains(producer) {
+            return false;
+        }
+        let tip = self.get_latest_block();
+        let probe = Block::new_at(tip.index + 1, Vec::new(), tip.hash.clone(), 0, self.next_block_timestamp());
+        let earliest = tip.timestamp.saturating_add_unsigned(self.stake_params().unwrap_or_default().min_block_interval);
+        probe.timestamp >= earliest && self.is_scheduled_producer(&probe, producer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::target::CompactTarget;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    use std::time::Instant;
+    
+    const INTERVAL: u64 = 30;
+    
+    /// Цепочка с наградой alice за первый блок и часы, которые идут только по команде теста
+    fn mock_chain(difficulty: usize) -> (SharedBlockchain, Arc<MockClock>) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let clock = Arc::new(MockClock::new(chain.get_latest_block().timestamp + 1));
+        chain.set_clock(clock.clone());
+        chain.difficulty = difficulty;
+        chain.target = Some(CompactTarget::from_difficulty(difficulty));
+        chain.mining_threads = Some(1);
+        chain.mining_check_interval = Some(1);
+        (SharedBlockchain::new(chain), clock)
+    }
+    
+    fn config() -> AutoMinerConfig {
+        AutoMinerConfig { poll_interval: Duration::from_millis(2), ..AutoMinerConfig::new("miner".to_string(), INTERVAL) }
+    }
+    
+    fn transfer(chain: &SharedBlockchain, amount: f64) -> Transaction {
+        let chain = chain.read();
+        let mut tx = Transaction::new_at("alice".to_string(), "bob".to_string(), amount, TransactionType::Transfer, chain.now_ts());
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut tx);
+        tx
+    }
+    
+    /// Ждет, пока майнер выпустит `blocks` блоков
+    fn wait_for_blocks(handle: &AutoMinerHandle, blocks: u64) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while handle.status().blocks_produced < blocks {
+            assert!(Instant::now() < deadline, "auto miner stalled: {:?}", handle.status());
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    
+    /// Дает потоку майнера несколько опросов, за которые он ничего не должен сделать
+    fn idle() {
+        thread::sleep(Duration::from_millis(40));
+    }
+    
+    #[test]
+    fn transactions_are_mined_on_the_interval() {
+        let (chain, clock) = mock_chain(1);
+        let handle = chain.start_auto_miner(config());
+        
+        for (round, amount) in [1.0, 2.0, 3.0].into_iter().enumerate() {
+            let tx = transfer(&chain, amount);
+            chain.add_transaction(tx.clone()).unwrap();
+            // До конца интервала блок не выпускается
+            clock.advance(INTERVAL as i64 - 1);
+            idle();
+            assert_eq!(handle.status().blocks_produced, round as u64);
+            
+            clock.advance(1);
+            wait_for_blocks(&handle, round as u64 + 1);
+            let chain = chain.read();
+            assert_eq!(chain.chain.len(), round + 3);
+            assert!(chain.get_latest_block().transactions.iter().any(|mined| mined.id == tx.id));
+            assert!(chain.mempool().is_empty());
+        }
+        
+        let status = handle.stop();
+        assert!(!status.running);
+        assert_eq!(status.last_block, Some(4));
+        assert_eq!(status.last_error, None);
+        assert!(chain.read().is_chain_valid());
+    }
+    
+    #[test]
+    fn empty_blocks_follow_the_policy() {
+        let (chain, clock) = mock_chain(1);
+        let handle = chain.start_auto_miner(AutoMinerConfig { skip_empty_blocks: true, ..config() });
+        for _ in 0..3 {
+            clock.advance(INTERVAL as i64);
+            idle();
+        }
+        assert_eq!(handle.status().blocks_produced, 0);
+        assert_eq!(chain.read().chain.len(), 2);
+        
+        let tx = transfer(&chain, 1.0);
+        chain.add_transaction(tx).unwrap();
+        clock.advance(INTERVAL as i64);
+        wait_for_blocks(&handle, 1);
+        handle.stop();
+        
+        // Без пропуска пустой интервал дает блок с одной наградой
+        let handle = chain.start_auto_miner(AutoMinerConfig { max_blocks: Some(1), ..config() });
+        clock.advance(INTERVAL as i64);
+        wait_for_blocks(&handle, 1);
+        let status = handle.stop();
+        assert_eq!(status.blocks_produced, 1);
+        let chain = chain.read();
+        assert_eq!(chain.chain.len(), 4);
+        assert!(chain.get_latest_block().transactions.iter().all(|tx| tx.sender == "BLOCKCHAIN_REWARD"));
+    }
+    
+    #[test]
+    fn pending_threshold_wakes_the_miner_early() {
+        let (chain, _clock) = mock_chain(1);
+        let handle = chain.start_auto_miner(AutoMinerConfig { pending_threshold: Some(2), ..config() });
+        chain.add_transaction(transfer(&chain, 1.0)).unwrap();
+        idle();
+        assert_eq!(handle.status().blocks_produced, 0);
+        
+        chain.add_transaction(transfer(&chain, 2.0)).unwrap();
+        wait_for_blocks(&handle, 1);
+        handle.stop();
+        assert_eq!(chain.read().get_latest_block().transactions.len(), 3);
+    }
+    
+    #[test]
+    fn stop_abandons_the_grind_without_appending() {
+        let (chain, _clock) = mock_chain(12);
+        let handle = chain.start_auto_miner(AutoMinerConfig { pending_threshold: Some(1), ..config() });
+        let tx = transfer(&chain, 1.0);
+        chain.add_transaction(tx.clone()).unwrap();
+        // Порог будит майнер на первом опросе, и при такой сложности перебор за это время не кончится
+        idle();
+        
+        let status = handle.stop();
+        assert!(!status.running);
+        assert_eq!(status.blocks_produced, 0);
+        let chain = chain.read();
+        assert_eq!(chain.chain.len(), 2);
+        assert!(chain.is_chain_valid());
+        assert!(chain.mempool().transactions().iter().any(|pending| pending.id == tx.id));
+    }
+}
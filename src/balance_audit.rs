@@ -0,0 +1,305 @@
+use crate::blockchain::Blockchain;
+use crate::staking::STAKING_MINTER;
+use crate::transaction::Transaction;
+use crate::wallet::Wallet;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::RangeBounds;
+
+/// Часть кошелька, которую затронуло изменение
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BalanceBucket {
+    Balance,
+    Staking,
+    Claimable,
+}
+
+impl BalanceBucket {
+    pub const ALL: [BalanceBucket; 3] = [BalanceBucket::Balance, BalanceBucket::Staking, BalanceBucket::Claimable];
+    
+    pub fn value(self, wallet: &Wallet) -> f64 {
+        match self {
+            BalanceBucket::Balance => wallet.balance,
+            BalanceBucket::Staking => wallet.staking_balance,
+            BalanceBucket::Claimable => wallet.claimable_rewards,
+        }
+    }
+}
+
+/// Почему изменился баланс
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BalanceChangeReason {
+    /// Списание с отправителя или зачисление получателю транзакции
+    Transaction { tx_id: String },
+    /// Награда за блок с комиссиями майнеру
+    Coinbase { tx_id: String },
+    /// Выплата стейкинга на границе эпохи
+    StakingPayout { tx_id: String },
+    /// Возврат списанного при отмене ожидающей транзакции; встречается только в журналах версий,
+    /// где пул списывал средства при приеме
+    Cancelled { tx_id: String },
+    /// Начальный баланс генезиса (`allocate_at_genesis`)
+    Funding,
+    /// Перевод на стейкинг при регистрации валидатора
+    Bond,
+    /// Возврат стейка при снятии валидатора
+    Unbond,
+    Slash,
+    /// Остаток удаленного кошелька сгорел
+    Reaped,
+    /// Исправление при замене состояния восстановленным по цепочке
+    Rebuild,
+}
+
+impl BalanceChangeReason {
+    /// Причина изменения зачисления получателю транзакции
+    fn credit(tx: &Transaction) -> Self {
+        let tx_id = tx.id.clone();
+        match tx.sender.as_str() {
+            "BLOCKCHAIN_REWARD" => BalanceChangeReason::Coinbase { tx_id },
+            STAKING_MINTER => BalanceChangeReason::StakingPayout { tx_id },
+            _ => BalanceChangeReason::Transaction { tx_id },
+        }
+    }
+    
+    /// Изменения, сделанные напрямую, а не блоком или транзакцией
+    fn is_out_of_band(&self) -> bool {
+        matches!(self, BalanceChangeReason::Funding | BalanceChangeReason::Bond | BalanceChangeReason::Unbond
+            | BalanceChangeReason::Slash | BalanceChangeReason::Rebuild)
+    }
+}
+
+/// Запись журнала изменений баланса кошелька
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceAuditEntry {
+    /// Высота применяемого блока; у списаний ожидающих транзакций — следующего блока
+    pub height: u64,
+    pub reason: BalanceChangeReason,
+    pub bucket: BalanceBucket,
+    pub delta: f64,
+    /// Значение части кошелька после изменения
+    pub balance_after: f64,
+    /// Изменение сделано в обход блоков
+    pub out_of_band: bool,
+}
+
+/// Страница журнала изменений баланса
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceAuditPage<'a> {
+    pub entries: Vec<&'a BalanceAuditEntry>,
+    /// Всего записей в запрошенном диапазоне высот
+    pub total: usize,
+    /// Смещение следующей страницы, если она есть
+    pub next_offset: Option<usize>,
+}
+
+impl Blockchain {
+    /// Записи журнала адреса в диапазоне высот, начиная с `offset`, не больше `limit`
+    pub fn balance_audit(&self, address: &str, heights: impl RangeBounds<u64>, offset: usize, limit: usize) -> BalanceAuditPage<'_> {
+        let matching: Vec<&BalanceAuditEntry> = self.balance_audit_log.get(address)
+            .map(|entries| entries.iter().filter(|entry| heights.contains(&entry.height)).collect())
+            .unwrap_or_default();
+        let entries: Vec<&BalanceAuditEntry> = matching.iter().skip(offset).take(limit).copied().collect();
+        let next = offset + entries.len();
+        BalanceAuditPage {
+            next_offset: (next < matching.len()).then_some(next),
+            total: matching.len(),
+            entries,
+        }
+    }
+    
+    /// Дописывает в журнал изменение, уже внесенное в кошелек
+    pub(crate) fn record_balance_change(&mut self, address: &str, bucket: BalanceBucket, delta: f64, reason: BalanceChangeReason) {
+        if delta == 0.0 {
+            return;
+        }
+        let entry = BalanceAuditEntry {
+            height: self.chain.len() as u64,
+            out_of_band: reason.is_out_of_band(),
+            reason,
+            bucket,
+            delta,
+            balance_after: self.wallets.get(address).map_or(0.0, |wallet| bucket.value(wallet)),
+        };
+        self.balance_audit_log.entry(address.to_string()).or_default().push(entry);
+    }
+    
+    /// Записывает зачисление получателю транзакции блока
+    pub(crate) fn record_credit(&mut self, tx: &Transaction, bucket: BalanceBucket) {
+        self.record_balance_change(&tx.receiver, bucket, tx.amount, BalanceChangeReason::credit(tx));
+    }
+    
+    /// Записывает исправления, которыми восстановленные по цепочке кошельки отличаются от прежних.
+    /// Вклад отброшенных записей откаченных блоков исправлением не считается
+    pub(crate) fn record_rebuild_corrections(&mut self, previous: &HashMap<String, Wallet>, reverted: &[(String, BalanceAuditEntry)]) {
+        let addresses: BTreeSet<String> = previous.keys().chain(self.wallets.keys()).cloned().collect();
+        for address in addresses {
+            for bucket in BalanceBucket::ALL {
+                let reverted_delta: f64 = reverted.iter()
+                    .filter(|(reverted_address, entry)| *reverted_address == address && entry.bucket == bucket)
+                    .map(|(_, entry)| entry.delta)
+                    .sum();
+                let before = previous.get(&address).map_or(0.0, |wallet| bucket.value(wallet)) - reverted_delta;
+                let after = self.wallets.get(&address).map_or(0.0, |wallet| bucket.value(wallet));
+                self.record_balance_change(&address, bucket, after - before, BalanceChangeReason::Rebuild);
+            }
+        }
+    }
+    
+    /// Отбрасывает записи блоков, которых в цепочке больше нет, и возвращает их; изменения в обход
+    /// блоков остаются, потому что восстановление состояния их повторяет, а баланс после них
+    /// уменьшается на отброшенные до них изменения
+    pub(crate) fn trim_balance_audit_to_chain(&mut self) -> Vec<(String, BalanceAuditEntry)> {
+        let height = self.chain.len() as u64;
+        let mut reverted = Vec::new();
+        for (address, entries) in self.balance_audit_log.iter_mut() {
+            let mut reverted_delta = HashMap::new();
+            entries.retain_mut(|entry| {
+                if entry.height < height || entry.out_of_band {
+                    entry.balance_after -= reverted_delta.get(&entry.bucket).copied().unwrap_or(0.0);
+                    return true;
+                }
+                *reverted_delta.entry(entry.bucket).or_insert(0.0) += entry.delta;
+                reverted.push((address.clone(), entry.clone()));
+                false
+            });
+        }
+        self.balance_audit_log.retain(|_, entries| !entries.is_empty());
+        reverted
+    }
+    
+    /// Отбрасывает записи старше окна хранения
+    pub(crate) fn prune_balance_audit(&mut self) {
+        let Some(retention) = self.balance_audit_retention else {
+            return;
+        };
+        let oldest = (self.chain.len() as u64).saturating_sub(retention);
+        for entries in self.balance_audit_log.values_mut() {
+            let expired = entries.partition_point(|entry| entry.height < oldest);
+            entries.drain(..expired);
+        }
+        self.balance_audit_log.retain(|_, entries| !entries.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    fn send(chain: &mut Blockchain, from: &str, to: &str, amount: f64) -> String {
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx).unwrap();
+        tx_id
+    }
+    
+    /// Начальный баланс, награды, переводы, стейк валидатора, отмененный и ожидающий переводы
+    fn scenario_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.allocate_at_genesis("alice", 20.0).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        send(&mut chain, "alice", "bob", 30.0);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.add_validator("bob".to_string(), 10.0).unwrap();
+        let cancelled = send(&mut chain, "bob", "alice", 5.0);
+        chain.cancel_pending_transaction(&cancelled).unwrap();
+        send(&mut chain, "bob", "carol", 2.0);
+        chain.mine_pending_transactions("bob".to_string()).unwrap();
+        send(&mut chain, "alice", "carol", 1.0);
+        chain
+    }
+    
+    fn assert_entries_sum_to_wallets(chain: &Blockchain) {
+        for (address, wallet) in &chain.wallets {
+            let page = chain.balance_audit(address, .., 0, usize::MAX);
+            for bucket in BalanceBucket::ALL {
+                let entries: Vec<_> = page.entries.iter().filter(|entry| entry.bucket == bucket).collect();
+                let sum: f64 = entries.iter().map(|entry| entry.delta).sum();
+                assert!((sum - bucket.value(wallet)).abs() < 1e-9, "{} {:?}: {} != {}", address, bucket, sum, bucket.value(wallet));
+                if let Some(last) = entries.last() {
+                    assert!((last.balance_after - bucket.value(wallet)).abs() < 1e-9);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn entries_sum_to_each_wallet_bucket() {
+        let chain = scenario_chain();
+        assert_entries_sum_to_wallets(&chain);
+        
+        let bob = chain.balance_audit("bob", .., 0, usize::MAX).entries;
+        let reasons: Vec<&BalanceChangeReason> = bob.iter().map(|entry| &entry.reason).collect();
+        assert!(reasons.contains(&&BalanceChangeReason::Bond));
+        // Пул ничего не списывает, и отмена не оставляет записей
+        assert!(!reasons.iter().any(|reason| matches!(reason, BalanceChangeReason::Cancelled { .. })));
+        assert!(reasons.iter().any(|reason| matches!(reason, BalanceChangeReason::Coinbase { .. })));
+        assert!(bob.iter().all(|entry| entry.out_of_band == (entry.reason == BalanceChangeReason::Bond)));
+        let funding = &chain.balance_audit("alice", ..=1, 0, usize::MAX).entries[0];
+        assert_eq!((&funding.reason, funding.delta, funding.out_of_band), (&BalanceChangeReason::Funding, 20.0, true));
+    }
+    
+    #[test]
+    fn pages_cover_the_height_range() {
+        let chain = scenario_chain();
+        let all = chain.balance_audit("alice", .., 0, usize::MAX);
+        assert!(all.total > 2);
+        let mut paged = Vec::new();
+        let mut offset = Some(0);
+        while let Some(next) = offset {
+            let page = chain.balance_audit("alice", .., next, 2);
+            assert!(page.entries.len() <= 2);
+            assert_eq!(page.total, all.total);
+            paged.extend(page.entries);
+            offset = page.next_offset;
+        }
+        assert_eq!(paged, all.entries);
+        
+        let at_two = chain.balance_audit("alice", 2..=2, 0, usize::MAX);
+        assert!(at_two.total > 0 && at_two.entries.iter().all(|entry| entry.height == 2));
+        assert_eq!(chain.balance_audit("nobody", .., 0, 10).total, 0);
+    }
+    
+    #[test]
+    fn journal_is_persisted_and_bounded_by_retention() {
+        let mut chain = scenario_chain();
+        let dir = std::env::temp_dir().join(format!("hellochain-balance-audit-{}", std::process::id()));
+        chain.save(&dir).unwrap();
+        let loaded = Blockchain::load(&dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(loaded.balance_audit_log, chain.balance_audit_log);
+        
+        chain.balance_audit_retention = Some(1);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let oldest = chain.get_latest_block().index;
+        assert!(chain.balance_audit_log.values().flatten().all(|entry| entry.height >= oldest));
+        assert!(chain.balance_audit_log.contains_key("miner"));
+        assert!(!chain.balance_audit_log.contains_key("bob"));
+    }
+    
+    #[test]
+    fn rollback_removes_the_entries_of_reverted_blocks() {
+        let mut chain = scenario_chain();
+        let bob_coinbase = chain.get_latest_block().transactions.iter()
+            .find(|tx| tx.sender == "BLOCKCHAIN_REWARD")
+            .unwrap().id.clone();
+        
+        // Откат до первого блока: перевод bob и его награда пропадают вместе с записями
+        chain.chain.truncate(2);
+        chain.rebuild_state_from_chain(false);
+        
+        let entries: Vec<&BalanceAuditEntry> = chain.balance_audit_log.values().flatten().collect();
+        assert!(entries.iter().all(|entry| entry.height < 2 || entry.out_of_band), "{:?}", entries);
+        assert!(!entries.iter().any(|entry| entry.reason == BalanceChangeReason::Coinbase { tx_id: bob_coinbase.clone() }));
+        assert!(!entries.iter().any(|entry| entry.reason == BalanceChangeReason::Rebuild), "{:?}", entries);
+        let bob = chain.balance_audit("bob", .., 0, usize::MAX).entries;
+        assert_eq!(bob.iter().map(|entry| &entry.reason).collect::<Vec<_>>(), vec![&BalanceChangeReason::Bond, &BalanceChangeReason::Bond]);
+        assert_entries_sum_to_wallets(&chain);
+    }
+}
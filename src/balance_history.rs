@@ -0,0 +1,199 @@
+use crate::assets::Asset;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::vesting::BalanceDetails;
+use crate::wallet::Wallet;
+use std::collections::{BTreeMap, HashMap};
+
+/// Через сколько блоков по умолчанию сохраняется снимок состояния для исторических запросов
+pub const DEFAULT_BALANCE_SNAPSHOT_INTERVAL: u64 = 100;
+
+/// Кошельки и активы, восстановленные по цепочке до блока с хешем `block_hash`
+struct StateSnapshot {
+    block_hash: String,
+    wallets: HashMap<String, Wallet>,
+    assets: HashMap<String, Asset>,
+}
+
+/// Снимки состояния по высотам. Они получены проигрыванием цепочки от базы `replay_base`,
+/// поэтому устаревают, когда меняются пополнения в обход блоков или стейки валидаторов
+#[derive(Default)]
+pub(crate) struct BalanceSnapshots {
+    off_chain_credits: BTreeMap<String, f64>,
+    validators: HashMap<String, f64>,
+    by_height: BTreeMap<u64, StateSnapshot>,
+}
+
+impl BalanceSnapshots {
+    /// Отбрасывает снимки, не подходящие к цепочке: все при смене базы, а после отката —
+    /// снимки блоков, которых в цепочке больше нет
+    fn retain_valid(&mut self, chain: &Blockchain) {
+        if self.off_chain_credits != chain.off_chain_credits || self.validators != chain.validators {
+            *self = BalanceSnapshots {
+                off_chain_credits: chain.off_chain_credits.clone(),
+                validators: chain.validators.clone(),
+                by_height: BTreeMap::new(),
+            };
+            return;
+        }
+        
+        let stale = self.by_height.iter()
+            .find(|(height, snapshot)| chain.get_block_by_index(**height).is_none_or(|block| block.hash != snapshot.block_hash))
+            .map(|(height, _)| *height);
+        if let Some(stale) = stale {
+            self.by_height.split_off(&stale);
+        }
+    }
+}
+
+impl Blockchain {
+    /// Баланс адреса в том виде, в каком он был после блока `height`: цепочка проигрывается от
+    /// ближайшего снимка (или от генезиса, если снимки выключены). Списания ожидающих транзакций
+    /// не учитываются; адрес, которого тогда не было, получает нули.
+    pub fn balance_at_height(&mut self, address: &str, height: u64) -> Result<BalanceDetails, BlockchainError> {
+        let tip = self.get_latest_block().index;
+        if height > tip {
+            return Err(BlockchainError::InvalidBlock(format!("Height {} is above the tip {}", height, tip)));
+        }
+        let first = self.first_replayed_height();
+        if height + 1 < first {
+            return Err(BlockchainError::InvalidBlock(format!("State below the snapshot height {} is not available", first - 1)));
+        }
+        
+        let wallets = self.wallets_at_height(height);
+        Ok(BalanceDetails::of(wallets.get(address), height + 1))
+    }
+    
+    /// Кошельки после блока `height`, восстановленные по цепочке
+    fn wallets_at_height(&mut self, height: u64) -> HashMap<String, Wallet> {
+        let Some(interval) = self.balance_snapshot_interval.filter(|interval| *interval > 0) else {
+            let (wallets, assets) = self.replay_base();
+            let (wallets, _) = self.replay_blocks(wallets, assets, self.first_replayed_height() as usize..height as usize + 1);
+            return wallets;
+        };
+        
+        let mut snapshots = std::mem::take(&mut self.balance_snapshots);
+        snapshots.retain_valid(self);
+        
+        let (mut replayed, mut wallets, mut assets) = match snapshots.by_height.range(..=height).next_back() {
+            Some((&replayed, snapshot)) => (replayed, snapshot.wallets.clone(), snapshot.assets.clone()),
+            None => {
+                let (wallets, assets) = self.replay_base();
+                (self.first_replayed_height() - 1, wallets, assets)
+            },
+        };
+        // Кошельки, созданные после снимка, в нем отсутствуют, а списания проигрываются только с существующих
+        for address in self.wallets.keys() {
+            wallets.entry(address.clone()).or_insert_with(|| Wallet::new(address.clone()));
+        }
+        
+        while replayed < height {
+            let next = ((replayed / interval + 1) * interval).min(height);
+            (wallets, assets) = self.replay_blocks(wallets, assets, replayed as usize + 1..next as usize + 1);
+            replayed = next;
+            if replayed % interval == 0 {
+                if let Some(block) = self.get_block_by_index(replayed) {
+                    snapshots.by_height.insert(replayed, StateSnapshot {
+                        block_hash: block.hash.clone(),
+                        wallets: wallets.clone(),
+                        assets: assets.clone(),
+                    });
+                }
+            }
+        }
+        
+        self.balance_snapshots = snapshots;
+        wallets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    
+    const ADDRESSES: [&str; 4] = ["alice", "bob", "carol", "nobody"];
+    
+    fn send(chain: &mut Blockchain, from: &str, to: &str, amount: f64) {
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(from));
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+    }
+    
+    /// Добывает блок и запоминает разбивку балансов сразу после него — эталон для исторических запросов
+    fn mine(chain: &mut Blockchain, miner: &str, expected: &mut Vec<Vec<BalanceDetails>>) {
+        chain.mine_pending_transactions(miner.to_string()).unwrap();
+        expected.push(ADDRESSES.iter().map(|address| chain.get_balance_detailed(address)).collect());
+    }
+    
+    /// Цепочка из восьми блоков с переводами, грантом вестинга и кошельком, созданным на высоте 4
+    fn history(interval: Option<u64>) -> (Blockchain, Vec<Vec<BalanceDetails>>) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.balance_snapshot_interval = interval;
+        let mut expected = vec![ADDRESSES.iter().map(|address| chain.get_balance_detailed(address)).collect()];
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        mine(&mut chain, "alice", &mut expected);
+        mine(&mut chain, "bob", &mut expected);
+        send(&mut chain, "alice", "bob", 30.0);
+        chain.grant_vesting("bob", "alice", 40.0, 3, 0, 4).unwrap();
+        mine(&mut chain, "alice", &mut expected);
+        chain.create_wallet("carol".to_string()).unwrap();
+        send(&mut chain, "bob", "carol", 12.5);
+        mine(&mut chain, "bob", &mut expected);
+        for round in 0..4 {
+            send(&mut chain, "alice", "carol", 1.0 + round as f64);
+            mine(&mut chain, if round % 2 == 0 { "carol" } else { "alice" }, &mut expected);
+        }
+        (chain, expected)
+    }
+    
+    fn assert_matches(chain: &mut Blockchain, expected: &[Vec<BalanceDetails>], heights: impl Iterator<Item = u64>) {
+        for height in heights {
+            for (address, details) in ADDRESSES.iter().zip(&expected[height as usize]) {
+                assert_eq!(chain.balance_at_height(address, height).unwrap(), *details, "{} at {}", address, height);
+            }
+        }
+    }
+    
+    #[test]
+    fn historical_balances_match_the_live_state_after_each_block() {
+        let (mut replayed, expected) = history(None);
+        let (mut snapshotted, _) = history(Some(3));
+        assert_eq!(replayed.get_latest_block().index, 8);
+        
+        assert_matches(&mut replayed, &expected, 0..=8);
+        // В обратном порядке снимки еще не сняты, и первый запрос сам их создает
+        assert_matches(&mut snapshotted, &expected, (0..=8).rev());
+        assert_eq!(snapshotted.balance_snapshots.by_height.keys().copied().collect::<Vec<_>>(), vec![3, 6]);
+        assert_matches(&mut snapshotted, &expected, [7, 2, 5, 8].into_iter());
+        
+        // Грант открывается по четверти за блок, начиная с высоты 3
+        assert_eq!(expected[3][0].unvested, 30.0);
+        assert_eq!(expected[8][0].unvested, 0.0);
+        assert_eq!(expected[3][2], BalanceDetails::of(None, 4));
+        assert!(matches!(replayed.balance_at_height("alice", 9), Err(BlockchainError::InvalidBlock(_))));
+    }
+    
+    #[test]
+    fn snapshots_of_replaced_blocks_are_dropped() {
+        let (mut chain, mut expected) = history(Some(3));
+        assert_matches(&mut chain, &expected, 8..=8);
+        
+        // Откат до высоты 4 и другая ветка поверх него
+        chain.chain.truncate(5);
+        expected.truncate(5);
+        chain.rebuild_state_from_chain(false);
+        for _ in 0..4 {
+            send(&mut chain, "carol", "bob", 2.0);
+            mine(&mut chain, "bob", &mut expected);
+        }
+        
+        assert_matches(&mut chain, &expected, (0..=8).rev());
+        chain.balance_snapshot_interval = None;
+        assert_matches(&mut chain, &expected, 0..=8);
+    }
+}
@@ -0,0 +1,177 @@
+use hellochain::{Blockchain, BlockchainBuilder, BlockchainError, ConsensusAlgorithm, Transaction, TransactionType};
+
+/// Демонстрационный сценарий: PoW-цепочка с переводами, контрактом и данными, затем PoS-цепочка
+fn main() {
+    // PoW, difficulty level = 2, mining reward = 100
+    let mut my_chain = BlockchainBuilder::new().difficulty(2).mining_reward(100.0).accept_legacy_addresses(true).build().unwrap();
+    
+    my_chain.create_wallet(String::from("alice")).unwrap();
+    my_chain.create_wallet(String::from("bob")).unwrap();
+    my_chain.create_wallet(String::from("miner")).unwrap();
+    
+    println!("--Initial balances:");
+    
+    my_chain.allocate_at_genesis("alice", 1000.0).unwrap();
+    my_chain.allocate_at_genesis("bob", 500.0).unwrap();
+    
+    if let Err(e) = my_chain.create_wallet(String::from("alice")) {
+        println!("Creating alice again: {}", e);
+    }
+    
+    println!("Alice: {}", my_chain.get_balance("alice"));
+    println!("Bob: {}", my_chain.get_balance("bob"));
+    println!("Miner: {}", my_chain.get_balance("miner"));
+    
+    println!("\n--Adding test transaction...");
+    match signed_transfer(&my_chain, "alice", "bob", 50.0).and_then(|transaction| my_chain.add_transaction(transaction)) {
+        Ok(_) => println!("Transaction added to pendings"),
+        Err(e) => println!("Error: {}", e),
+    }
+    
+    println!("\n--Mining block...");
+    match my_chain.mine_pending_transactions(String::from("miner")) {
+        Ok(_) => println!("Block added to chain"),
+        Err(e) => println!("Mining error: {}", e),
+    }
+    
+    println!("\n--Balances after transaction:");
+    println!("Alice: {}", my_chain.get_balance("alice"));
+    println!("Bob: {}", my_chain.get_balance("bob"));
+    println!("Miner: {}", my_chain.get_balance("miner"));
+    
+    println!("\n--Another transaction...");
+    match signed_transfer(&my_chain, "bob", "alice", 20.0).and_then(|transaction| my_chain.add_transaction(transaction)) {
+        Ok(_) => println!("Transaction added to pendings"),
+        Err(e) => println!("Error: {}", e),
+    }
+    
+    println!("\n--Mining block...");
+    match my_chain.mine_pending_transactions(String::from("miner")) {
+        Ok(_) => println!("Block added to chain"),
+        Err(e) => println!("Mining error: {}", e),
+    }
+    
+    
+    println!("\n--Balances after transaction:");
+    println!("Alice: {}", my_chain.get_balance("alice"));
+    println!("Bob: {}", my_chain.get_balance("bob"));
+    println!("Miner: {}", my_chain.get_balance("miner"));
+    
+    println!("\n--Creating smart contract...");
+    match my_chain.create_smart_contract(
+        String::from("alice"),
+        String::from("function transfer() { return 'transfer executed'; }"),
+        10.0
+    ) {
+        Ok(address) => {
+            println!("Smart contract created. Its address: {}", address);
+            
+            println!("\nMining block with smart contract...");
+            match my_chain.mine_pending_transactions(String::from("miner")) {
+                Ok(_) => {
+                    println!("Block added");
+                    
+                    println!("\nRunning smart contract...");
+                    match my_chain.execute_smart_contract(&address, "transfer", vec![]) {
+                        Ok(result) => println!("Result is: {}", result),
+                        Err(e) => println!("Error: {}", e),
+                    }
+                },
+                Err(e) => println!("Error when mining: {}", e),
+            }
+        },
+        Err(e) => println!("Cannot create smart contract: {}", e),
+    }
+    
+    println!("\n--Bob saves some data in blockchain as a transaction...");
+    match my_chain.store_data(
+        String::from("bob"),
+        "Some important data".as_bytes().to_vec()
+    ) {
+        Ok(data_id) => {
+            println!("Data stored with ID: {}", data_id);
+            
+            println!("\nMining a block with data...");
+            match my_chain.mine_pending_transactions(String::from("miner")) {
+                Ok(_) => println!("Success"),
+                Err(e) => println!("Error: {}", e),
+            }
+        },
+        Err(e) => println!("Cannot save data: {}", e),
+    }
+    
+    println!("\n--Let now create another blockchain. It will use Proof of Stake...");
+    let mut pos_chain = BlockchainBuilder::new().mining_reward(50.0).consensus(ConsensusAlgorithm::ProofOfStake).accept_legacy_addresses(true).build().unwrap();
+    
+    pos_chain.create_wallet(String::from("validator1")).unwrap();
+    pos_chain.create_wallet(String::from("validator2")).unwrap();
+    pos_chain.create_wallet(String::from("justuser")).unwrap();
+    
+    pos_chain.allocate_at_genesis("validator1", 1000.0).unwrap();
+    pos_chain.allocate_at_genesis("validator2", 2000.0).unwrap();
+    pos_chain.allocate_at_genesis("justuser", 500.0).unwrap();
+    
+    println!("--Registering validators...");
+    match pos_chain.add_validator(String::from("validator1"), 800.0) {
+        Ok(_) => println!("validator1 registered with stake 800.0"),
+        Err(e) => println!("Error: {}", e),
+    }
+    
+    match pos_chain.add_validator(String::from("validator2"), 1500.0) {
+        Ok(_) => println!("validator1 registered with stake 1500.0"),
+        Err(e) => println!("Error: {}", e),
+    }
+    
+    println!("\n--Adding transaction in PoS...");
+    match signed_transfer(&pos_chain, "justuser", "validator1", 25.0).and_then(|transaction| pos_chain.add_transaction(transaction)) {
+        Ok(_) => println!("Transaction added"),
+        Err(e) => println!("Error: {}", e),
+    }
+    
+    println!("\n--Validation block in PoS...");
+    match pos_chain.mine_pending_transactions(String::from("validator2")) {
+        Ok(_) => println!("Block validated and added into chain"),
+        Err(e) => println!("Error: {}", e),
+    }
+    
+    println!("\n--Balances in PoS blockchain:");
+    println!("validator1: {}", pos_chain.get_balance("validator1"));
+    println!("validator2: {}", pos_chain.get_balance("validator2"));
+    println!("justuser: {}", pos_chain.get_balance("justuser"));
+    
+    if let Some(wallet) = pos_chain.get_wallet_info("validator2") {
+        println!("\nvalidator2 wallet:");
+        println!("Address: {}", wallet.address);
+        println!("Balance: {}", wallet.balance);
+        println!("Staking balance: {}", wallet.staking_balance);
+        println!("Transaction count: {}", wallet.history_len());
+    }
+    
+    println!("\nChecking chain validity:");
+    println!("PoW chain: {}", my_chain.is_chain_valid());
+    println!("PoS chain: {}", pos_chain.is_chain_valid());
+    
+    my_chain.adjust_difficulty();
+    
+    println!("\nAll blocks in PoW chain:");
+    for block in &my_chain.chain {
+        println!("{:?}", block);
+    }
+    
+    println!("\nAll blocks в PoS chain:");
+    for block in &pos_chain.chain {
+        println!("{:?}", block);
+    }
+    
+    println!("\nTests done!");
+}
+
+/// Перевод со следующим номером отправителя, подписанный ключом его кошелька
+fn signed_transfer(chain: &Blockchain, from: &str, to: &str, amount: f64) -> Result<Transaction, BlockchainError> {
+    let mut transaction = chain.new_transaction(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+    transaction.set_nonce(chain.get_account_nonce(from));
+    if let Some(wallet) = chain.get_wallet_info(from) {
+        wallet.sign_transaction(&mut transaction)?;
+    }
+    Ok(transaction)
+}
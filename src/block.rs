@@ -1,9 +1,33 @@
-use crate::transaction::{Transaction, calculate_hash};
+use crate::errors::BlockchainError;
+use crate::hashing::HashKind;
+use crate::transaction::{Hash, HashWriter, Transaction};
+use crate::merkle::{IncrementalMerkle, merkle_root_digest};
+use crate::mining::MiningProgress;
+use crate::target::{CompactTarget, COMPACT_TARGET_VERSION, LEGACY_BLOCK_VERSION};
+use crate::trace::debug;
 use chrono::prelude::*;
-use std::fmt::{self, Debug, Formatter};
+use std::fmt::{self, Debug, Formatter, Write as _};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+
+/// Фиксированное время генезис-блока, чтобы у всех узлов с одинаковыми параметрами он совпадал
+pub const GENESIS_TIMESTAMP: i64 = 1_700_000_000;
+
+/// Больше стольких байт JSON блока не разбирается: столько вмещает кадр сетевого протокола
+pub const MAX_BLOCK_JSON_LEN: usize = crate::network::MAX_FRAME_SIZE;
+
+/// Как часто поток майнинга проверяет, не нашел ли решение другой поток
+pub const STOP_CHECK_INTERVAL: u64 = 1024;
+
+/// Как часто наблюдатель майнинга просыпается, чтобы проверить счетчик попыток
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Представляет блок в блокчейне, содержащий транзакции и метаданные
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Block {
     pub index: u64,
     pub timestamp: i64,
@@ -11,9 +35,76 @@ pub struct Block {
     pub merkle_root: String,
     pub previous_hash: String,
     pub hash: String,
+    /// Перебираемое число PoW; у PoS и DPoS всегда 0
     pub nonce: u64,
+    /// Число ведущих нулевых цифр хеша; у блоков с компактной целью — сколько нулей она гарантирует.
+    /// У новых цепочек PoS и DPoS всегда 0.
     pub difficulty: usize,
     pub validator: Option<String>,
+    /// Версия правил печати: `LEGACY_BLOCK_VERSION` или `COMPACT_TARGET_VERSION`
+    #[serde(default)]
+    pub version: u32,
+    /// Цель PoW в компактной записи, начиная с `COMPACT_TARGET_VERSION`
+    #[serde(default)]
+    pub bits: u32,
+    /// Базовая комиссия за единицу веса, если в цепочке включен `fee_market`
+    #[serde(default)]
+    pub base_fee: Option<f64>,
+    /// Алгоритм хеширования цепочки: им посчитаны хеш блока, корень Меркла и идентификаторы транзакций
+    #[serde(default, skip_serializing_if = "HashKind::is_sha256")]
+    pub hash_algorithm: HashKind,
+}
+
+/// Заголовок блока без тела: достаточно для проверки связности цепочки и печати
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub index: u64,
+    pub timestamp: i64,
+    pub merkle_root: String,
+    pub previous_hash: String,
+    pub hash: String,
+    pub nonce: u64,
+    pub difficulty: usize,
+    pub validator: Option<String>,
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub bits: u32,
+    #[serde(default)]
+    pub base_fee: Option<f64>,
+    #[serde(default, skip_serializing_if = "HashKind::is_sha256")]
+    pub hash_algorithm: HashKind,
+}
+
+impl BlockHeader {
+    /// Вычисляет хеш заголовка тем же способом, что и у полного блока
+    pub fn calculate_hash(&self) -> String {
+        block_hash(&self.seal_fields(), self.nonce)
+    }
+    
+    /// Проверяет, что хеш заголовка удовлетворяет его сложности
+    pub fn meets_difficulty(&self) -> bool {
+        self.seal_fields().hash_meets(&self.hash)
+    }
+    
+    /// Оценивает работу, вложенную в заголовок, для выбора цепочки с наибольшей суммарной работой
+    pub fn difficulty_as_work(&self) -> u128 {
+        self.seal_fields().work()
+    }
+    
+    fn seal_fields(&self) -> SealFields<'_> {
+        SealFields {
+            index: self.index,
+            timestamp: self.timestamp,
+            merkle_root: &self.merkle_root,
+            previous_hash: &self.previous_hash,
+            difficulty: self.difficulty,
+            version: self.version,
+            bits: self.bits,
+            base_fee: self.base_fee,
+            hash_algorithm: self.hash_algorithm,
+        }
+    }
 }
 
 impl Debug for Block {
@@ -33,12 +124,30 @@ impl Debug for Block {
 impl Block {
     /// Создает новый блок с указанным индексом, транзакциями, предыдущим хешем и сложностью
     pub fn new(index: u64, transactions: Vec<Transaction>, previous_hash: String, difficulty: usize) -> Self {
-        let now = Utc::now();
-        let merkle_root = Block::calculate_merkle_root(&transactions);
-        
+        Block::new_at(index, transactions, previous_hash, difficulty, Utc::now().timestamp())
+    }
+    
+    /// То же, что `new`, но с заданным временем блока. Блок хешируется SHA-256; цепочка с другим
+    /// алгоритмом переводит его на свой через `set_hash_algorithm`.
+    pub fn new_at(index: u64, transactions: Vec<Transaction>, previous_hash: String, difficulty: usize, timestamp: i64) -> Self {
+        let hash_algorithm = HashKind::default();
+        let merkle_root = Block::calculate_merkle_root(hash_algorithm, &transactions);
+        Block::with_merkle_root(hash_algorithm, index, transactions, merkle_root, previous_hash, difficulty, timestamp)
+    }
+    
+    /// Создает блок с числовой целью из набора транзакций, дерево Меркла которого уже построено при отборе транзакций;
+    /// блок хешируется алгоритмом дерева
+    pub fn from_candidate(index: u64, transactions: Vec<Transaction>, merkle: &IncrementalMerkle, previous_hash: String, target: CompactTarget, timestamp: i64) -> Self {
+        debug_assert_eq!(merkle.len(), transactions.len());
+        let mut block = Block::with_merkle_root(merkle.hash_algorithm(), index, transactions, merkle.root(), previous_hash, 0, timestamp);
+        block.set_target(target);
+        block
+    }
+    
+    fn with_merkle_root(hash_algorithm: HashKind, index: u64, transactions: Vec<Transaction>, merkle_root: String, previous_hash: String, difficulty: usize, timestamp: i64) -> Self {
         let mut block = Block {
             index,
-            timestamp: now.timestamp(),
+            timestamp,
             transactions,
             merkle_root,
             previous_hash,
@@ -46,81 +155,539 @@ impl Block {
             nonce: 0,
             difficulty,
             validator: None,
+            version: LEGACY_BLOCK_VERSION,
+            bits: 0,
+            base_fee: None,
+            hash_algorithm,
         };
         
         block.hash = block.calculate_hash();
         block
     }
     
+    /// Разбирает блок из недоверенного JSON
+    pub fn from_json(bytes: &[u8]) -> Result<Self, BlockchainError> {
+        if bytes.len() > MAX_BLOCK_JSON_LEN {
+            return Err(BlockchainError::InvalidBlock(format!("Block exceeds {} bytes", MAX_BLOCK_JSON_LEN)));
+        }
+        serde_json::from_slice(bytes).map_err(|e| BlockchainError::InvalidBlock(format!("Malformed block: {}", e)))
+    }
+    
+    /// Создает генезис-блок с фиксированным временем, одинаковый для всех узлов сети
+    pub fn genesis(difficulty: usize) -> Self {
+        Block::new_at(0, Vec::new(), String::from("0"), difficulty, GENESIS_TIMESTAMP)
+    }
+    
+    /// Блок без тела по его заголовку: так хранятся блоки ниже снимка состояния
+    pub fn from_header(header: BlockHeader) -> Self {
+        Block {
+            index: header.index,
+            timestamp: header.timestamp,
+            transactions: Vec::new(),
+            merkle_root: header.merkle_root,
+            previous_hash: header.previous_hash,
+            hash: header.hash,
+            nonce: header.nonce,
+            difficulty: header.difficulty,
+            validator: header.validator,
+            version: header.version,
+            bits: header.bits,
+            base_fee: header.base_fee,
+            hash_algorithm: header.hash_algorithm,
+        }
+    }
+    
+    /// Тело блока отброшено: транзакций нет, хотя корень Меркла их подразумевает
+    pub fn is_pruned(&self) -> bool {
+        self.transactions.is_empty() && self.merkle_root != Block::calculate_merkle_root(self.hash_algorithm, &[])
+    }
+    
     /// Вычисляет корень дерева Меркла для списка транзакций
-    pub fn calculate_merkle_root(transactions: &[Transaction]) -> String {
-        if transactions.is_empty() {
-            return String::from("0");
+    pub fn calculate_merkle_root(hash_algorithm: HashKind, transactions: &[Transaction]) -> String {
+        merkle_root_digest(hash_algorithm, transactions).map_or_else(|| String::from("0"), hex::encode)
+    }
+    
+    /// Вычисляет хеш блока на основе его метаданных алгоритмом блока
+    pub fn calculate_hash(&self) -> String {
+        block_hash(&self.seal_fields(), self.nonce)
+    }
+    
+    /// Переводит блок на алгоритм цепочки и пересчитывает корень Меркла и хеш.
+    /// Идентификаторы транзакций блока должны быть посчитаны тем же алгоритмом.
+    pub fn set_hash_algorithm(&mut self, hash_algorithm: HashKind) {
+        self.hash_algorithm = hash_algorithm;
+        self.merkle_root = Block::calculate_merkle_root(hash_algorithm, &self.transactions);
+        self.hash = self.calculate_hash();
+    }
+    
+    /// Переводит блок на числовую цель PoW; сложность становится числом нулей, которое она гарантирует
+    pub fn set_target(&mut self, target: CompactTarget) {
+        self.version = COMPACT_TARGET_VERSION;
+        self.bits = target.0;
+        self.difficulty = target.leading_zero_digits();
+        self.hash = self.calculate_hash();
+    }
+    
+    /// Записывает в блок базовую комиссию; она входит в хеш
+    pub fn set_base_fee(&mut self, base_fee: Option<f64>) {
+        self.base_fee = base_fee;
+        self.hash = self.calculate_hash();
+    }
+    
+    /// Проверяет, что хеш блока удовлетворяет его сложности или цели
+    pub fn meets_difficulty(&self) -> bool {
+        self.seal_fields().hash_meets(&self.hash)
+    }
+    
+    /// Ожидаемое число хешей для печати блока; складывается в суммарную работу цепочки
+    pub fn difficulty_as_work(&self) -> u128 {
+        self.seal_fields().work()
+    }
+    
+    fn seal_fields(&self) -> SealFields<'_> {
+        SealFields {
+            index: self.index,
+            timestamp: self.timestamp,
+            merkle_root: &self.merkle_root,
+            previous_hash: &self.previous_hash,
+            difficulty: self.difficulty,
+            version: self.version,
+            bits: self.bits,
+            base_fee: self.base_fee,
+            hash_algorithm: self.hash_algorithm,
+        }
+    }
+    
+    /// Возвращает заголовок блока
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            timestamp: self.timestamp,
+            merkle_root: self.merkle_root.clone(),
+            previous_hash: self.previous_hash.clone(),
+            hash: self.hash.clone(),
+            nonce: self.nonce,
+            difficulty: self.difficulty,
+            validator: self.validator.clone(),
+            version: self.version,
+            bits: self.bits,
+            base_fee: self.base_fee,
+            hash_algorithm: self.hash_algorithm,
+        }
+    }
+    
+    /// Майнит блок с использованием алгоритма Proof of Work
+    pub fn mine_block(&mut self) {
+        let fields = self.seal_fields();
+        let hasher = HeaderHasher::new(&fields);
+        let mut nonce = self.nonce;
+        let mut hash = hasher.hash(nonce);
+        
+        while !fields.digest_meets(&hash) {
+            nonce += 1;
+            hash = hasher.hash(nonce);
+        }
+        self.nonce = nonce;
+        self.hash = hex::encode(hash);
+        
+        debug!(index = self.index, hash = %self.hash, difficulty = self.difficulty, nonce = self.nonce, "Block mined");
+    }
+    
+    /// Майнит блок на нескольких потоках, каждый перебирает свой диапазон nonce.
+    /// Все потоки завершаются до возврата; возвращает общее число вычисленных хешей.
+    pub fn mine_block_parallel(&mut self, threads: usize) -> u64 {
+        if threads <= 1 {
+            let start = self.nonce;
+            self.mine_block();
+            return self.nonce - start + 1;
         }
         
-        let mut hashes: Vec<String> = transactions
-            .iter()
-            .map(|tx| calculate_hash(&format!("{}{}{}", tx.sender, tx.receiver, tx.amount)))
-            .collect();
+        let attempts = AtomicU64::new(0);
+        self.mine_block_until(threads, &AtomicBool::new(false), &attempts);
+        attempts.into_inner()
+    }
+    
+    /// Майнит блок, пока решение не найдено или не выставлен флаг отмены.
+    /// Счетчик попыток пополняется по ходу работы; возвращает true, если решение найдено.
+    pub fn mine_block_until(&mut self, threads: usize, cancel: &AtomicBool, attempts: &AtomicU64) -> bool {
+        self.mine_block_while(threads, STOP_CHECK_INTERVAL, attempts, || !cancel.load(Ordering::Relaxed))
+    }
+    
+    /// Майнит блок, пока решение не найдено и `keep_going` возвращает true. Каждый поток спрашивает
+    /// `keep_going` раз в `check_interval` попыток, поэтому проверка должна быть дешевой.
+    pub fn mine_block_while<F>(&mut self, threads: usize, check_interval: u64, attempts: &AtomicU64, keep_going: F) -> bool
+    where
+        F: Fn() -> bool + Sync,
+    {
+        let threads = threads.max(1) as u64;
+        let check_interval = check_interval.max(1);
+        let fields = self.seal_fields();
+        let hasher = HeaderHasher::new(&fields);
+        let start = self.nonce;
+        let range = (u64::MAX - start) / threads;
+        let found = AtomicBool::new(false);
+        let winner: Mutex<Option<(u64, Hash)>> = Mutex::new(None);
+        
+        thread::scope(|scope| {
+            for worker in 0..threads {
+                let (fields, hasher, found, winner, keep_going) = (&fields, &hasher, &found, &winner, &keep_going);
+                scope.spawn(move || {
+                    let first = start + worker * range;
+                    let last = if worker == threads - 1 { u64::MAX } else { first + range - 1 };
+                    let mut tried = 0;
+                    
+                    for nonce in first..=last {
+                        if tried == check_interval {
+                            attempts.fetch_add(tried, Ordering::Relaxed);
+                            tried = 0;
+                            if found.load(Ordering::Relaxed) || !keep_going() {
+                                break;
+                            }
+                        }
+                        tried += 1;
+                        
+                        let hash = hasher.hash(nonce);
+                        if fields.digest_meets(&hash) {
+                            if !found.swap(true, Ordering::Relaxed) {
+                                *winner.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some((nonce, hash));
+                            }
+                            break;
+                        }
+                    }
+                    
+                    attempts.fetch_add(tried, Ordering::Relaxed);
+                });
+            }
+        });
+        
+        let Some((nonce, hash)) = winner.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()) else {
+            return false;
+        };
+        self.nonce = nonce;
+        self.hash = hex::encode(hash);
         
-        while hashes.len() > 1 {
-            let mut next_level = Vec::new();
+        debug!(index = self.index, hash = %self.hash, difficulty = self.difficulty, nonce = self.nonce, threads, "Block mined");
+        true
+    }
+    
+    /// Майнит блок, как `mine_block_while`, раундами по `round` попыток. Если за раунд решения нет,
+    /// `roll` меняет заголовок (время, extra-nonce награды), и перебор начинается с нулевого nonce;
+    /// если менять было нечего, перебор продолжается после уже испробованных nonce.
+    pub fn mine_block_rolling<F, R>(&mut self, threads: usize, check_interval: u64, attempts: &AtomicU64, round: u64, keep_going: F, mut roll: R) -> bool
+    where
+        F: Fn() -> bool + Sync,
+        R: FnMut(&mut Block) -> bool,
+    {
+        let round = round.max(1);
+        loop {
+            let round_start = attempts.load(Ordering::Relaxed);
+            let in_round = || attempts.load(Ordering::Relaxed) - round_start < round && keep_going();
+            if self.mine_block_while(threads, check_interval, attempts, in_round) {
+                return true;
+            }
+            if !keep_going() {
+                return false;
+            }
             
-            for i in (0..hashes.len()).step_by(2) {
-                if i + 1 < hashes.len() {
-                    let combined = format!("{}{}", hashes[i], hashes[i + 1]);
-                    next_level.push(calculate_hash(&combined));
-                } else {
-                    next_level.push(hashes[i].clone());
+            let tried = attempts.load(Ordering::Relaxed) - round_start;
+            self.nonce = if roll(self) { 0 } else { self.nonce.saturating_add(tried) };
+            debug!(index = self.index, timestamp = self.timestamp, nonce = self.nonce, "Mining round rolled");
+        }
+    }
+    
+    /// Майнит блок, вызывая `on_progress` примерно каждые `interval` попыток. Обратный вызов работает
+    /// в вызывающем потоке и не тормозит перебор; `ControlFlow::Break` останавливает майнинг.
+    /// Возвращает число попыток или None, если майнинг прерван.
+    pub fn mine_block_with_progress<F>(&mut self, threads: usize, interval: u64, on_progress: F) -> Option<u64>
+    where
+        F: FnMut(MiningProgress) -> ControlFlow<()>,
+    {
+        self.mine_block_with_progress_rolling(threads, interval, u64::MAX, |_| false, on_progress)
+    }
+    
+    /// То же, что `mine_block_with_progress`, но перебор идет раундами, как в `mine_block_rolling`
+    pub fn mine_block_with_progress_rolling<R, F>(&mut self, threads: usize, interval: u64, round: u64, roll: R, mut on_progress: F) -> Option<u64>
+    where
+        R: FnMut(&mut Block) -> bool + Send,
+        F: FnMut(MiningProgress) -> ControlFlow<()>,
+    {
+        let cancel = AtomicBool::new(false);
+        let attempts = AtomicU64::new(0);
+        let interval = interval.max(1);
+        let started = Instant::now();
+        let observer = thread::current();
+        
+        let found = thread::scope(|scope| {
+            let miner = scope.spawn(|| {
+                let keep_going = || !cancel.load(Ordering::Relaxed);
+                let found = self.mine_block_rolling(threads, STOP_CHECK_INTERVAL, &attempts, round, keep_going, roll);
+                observer.unpark();
+                found
+            });
+            
+            let mut previous = MiningProgress::default();
+            let mut next_report = interval;
+            while !miner.is_finished() {
+                thread::park_timeout(PROGRESS_POLL_INTERVAL);
+                let tried = attempts.load(Ordering::Relaxed);
+                if tried < next_report || cancel.load(Ordering::Relaxed) {
+                    continue;
+                }
+                
+                next_report = (tried / interval + 1) * interval;
+                let progress = previous.advance(tried, started.elapsed());
+                debug!(attempts = progress.attempts, hash_rate = progress.current_hash_rate, "Mining in progress");
+                previous = progress;
+                if on_progress(progress).is_break() {
+                    cancel.store(true, Ordering::Relaxed);
                 }
             }
             
-            hashes = next_level;
-        }
+            miner.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        });
         
-        hashes[0].clone()
+        found.then(|| attempts.into_inner())
     }
     
-    /// Вычисляет SHA-256 хеш блока на основе его метаданных
-    pub fn calculate_hash(&self) -> String {
-        let data = format!("{}{}{}{}{}{}", 
-            self.index,
-            self.timestamp,
-            &self.merkle_root,
-            self.previous_hash,
-            self.nonce,
-            self.difficulty
-        );
+    /// Валидирует блок с использованием алгоритма Proof of Stake, если жребий `draw` не выше порога ставки
+    pub fn validate_with_pos(&mut self, validator: String, stake_amount: f64, draw: f64) -> bool {
+        if draw <= pos_threshold(stake_amount) {
+            self.validator = Some(validator);
+            self.hash = self.calculate_hash();
+            return true;
+        }
         
-        calculate_hash(&data)
+        false
+    }
+}
+
+/// Порог жребия PoS: валидатор со ставкой 1000 и больше проходит всегда
+pub fn pos_threshold(stake_amount: f64) -> f64 {
+    stake_amount / 1000.0
+}
+
+/// Поля заголовка, которые входят в хеш и определяют правило печати
+struct SealFields<'a> {
+    index: u64,
+    timestamp: i64,
+    merkle_root: &'a str,
+    previous_hash: &'a str,
+    difficulty: usize,
+    version: u32,
+    bits: u32,
+    base_fee: Option<f64>,
+    hash_algorithm: HashKind,
+}
+
+impl SealFields<'_> {
+    /// Подходит ли двоичный хеш: у старых блоков по числу нулей, у новых по числовой цели
+    fn digest_meets(&self, hash: &Hash) -> bool {
+        if self.version == LEGACY_BLOCK_VERSION {
+            digest_meets_difficulty(hash, self.difficulty)
+        } else {
+            CompactTarget(self.bits).is_met_by(hash)
+        }
     }
     
-    /// Майнит блок с использованием алгоритма Proof of Work
-    pub fn mine_block(&mut self) {
-        let target = "0".repeat(self.difficulty);
+    /// Проверка печати при валидации: старый блок с нулевой сложностью не содержит работы
+    fn hash_meets(&self, hash: &str) -> bool {
+        let mut digest = [0u8; 32];
+        let has_work = self.version != LEGACY_BLOCK_VERSION || self.difficulty > 0;
+        has_work && hex::decode_to_slice(hash, &mut digest).is_ok() && self.digest_meets(&digest)
+    }
+    
+    fn work(&self) -> u128 {
+        if self.version == LEGACY_BLOCK_VERSION {
+            u32::try_from(self.difficulty).ok()
+                .and_then(|difficulty| difficulty.checked_mul(4))
+                .and_then(|shift| 1u128.checked_shl(shift))
+                .unwrap_or(u128::MAX)
+        } else {
+            CompactTarget(self.bits).work()
+        }
+    }
+}
+
+/// Вычисляет хеш по полям заголовка
+fn block_hash(fields: &SealFields, nonce: u64) -> String {
+    hex::encode(HeaderHasher::new(fields).hash(nonce))
+}
+
+/// Хеширует заголовок с разными nonce без выделения памяти: поля до nonce поглощаются один раз,
+/// а на каждую попытку в копию состояния дописываются только nonce и хвост после него.
+/// У старых блоков байты те же, что у конкатенации полей в десятичной записи, поэтому их хеши не меняются;
+/// у блоков с компактной целью в хвост добавляются версия и цель, а при базовой комиссии — и она.
+#[derive(Clone)]
+struct HeaderHasher {
+    prefix: HashWriter,
+    suffix: Vec<u8>,
+}
+
+impl HeaderHasher {
+    fn new(fields: &SealFields) -> Self {
+        let mut prefix = HashWriter::new(fields.hash_algorithm);
+        let _ = write!(prefix, "{}{}{}{}", fields.index, fields.timestamp, fields.merkle_root, fields.previous_hash);
         
-        while &self.hash[..self.difficulty] != target {
-            self.nonce += 1;
-            self.hash = self.calculate_hash();
+        let mut suffix = fields.difficulty.to_string();
+        if fields.version != LEGACY_BLOCK_VERSION {
+            let _ = write!(suffix, ":{}:{}", fields.version, fields.bits);
         }
+        if let Some(base_fee) = fields.base_fee {
+            let _ = write!(suffix, ":{}", base_fee);
+        }
+        HeaderHasher { prefix, suffix: suffix.into_bytes() }
+    }
+    
+    fn hash(&self, nonce: u64) -> Hash {
+        let mut hasher = self.prefix.clone();
+        hasher.update(DecimalBuf::new(nonce).as_bytes());
+        hasher.update(&self.suffix);
+        hasher.finish()
+    }
+}
+
+/// Десятичная запись числа в буфере на стеке
+#[derive(Clone, Copy)]
+struct DecimalBuf {
+    digits: [u8; 20],
+    start: usize,
+}
+
+impl DecimalBuf {
+    fn new(mut value: u64) -> Self {
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                return DecimalBuf { digits, start };
+            }
+        }
+    }
+    
+    fn as_bytes(&self) -> &[u8] {
+        &self.digits[self.start..]
+    }
+}
+
+/// Проверяет, что двоичный хеш начинается с `difficulty` нулевых шестнадцатеричных цифр
+fn digest_meets_difficulty(hash: &Hash, difficulty: usize) -> bool {
+    if difficulty > 2 * hash.len() {
+        return false;
+    }
+    hash[..difficulty / 2].iter().all(|b| *b == 0) && (difficulty.is_multiple_of(2) || hash[difficulty / 2] >> 4 == 0)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn unmined(difficulty: usize) -> Block {
+        Block::new_at(1, Vec::new(), Block::genesis(1).hash, difficulty, GENESIS_TIMESTAMP + 60)
+    }
+    
+    #[test]
+    fn parallel_mining_finds_a_valid_block() {
+        let mut block = unmined(4);
+        let attempts = block.mine_block_parallel(4);
+        
+        assert!(block.meets_difficulty());
+        assert_eq!(block.hash, block.calculate_hash());
+        assert!(attempts >= 1);
+    }
+    
+    #[test]
+    fn single_thread_fallback_matches_mine_block() {
+        let mut sequential = unmined(4);
+        sequential.mine_block();
+        let mut fallback = unmined(4);
+        let attempts = fallback.mine_block_parallel(1);
         
-        println!("Block mined: {} (difficulty: {}, nonce: {})", self.hash, self.difficulty, self.nonce);
+        assert_eq!((fallback.nonce, &fallback.hash), (sequential.nonce, &sequential.hash));
+        assert_eq!(attempts, sequential.nonce + 1);
     }
     
-    /// Валидирует блок с использованием алгоритма Proof of Stake
-    pub fn validate_with_pos(&mut self, validator: String, stake_amount: f64) -> bool {
-        use rand::{rngs::ThreadRng, Rng};
+    #[test]
+    fn workers_stop_once_mining_returns() {
+        let checks = AtomicU64::new(0);
+        let attempts = AtomicU64::new(0);
+        let mut block = unmined(4);
+        assert!(block.mine_block_while(4, 16, &attempts, || {
+            checks.fetch_add(1, Ordering::Relaxed);
+            true
+        }));
+        assert!(block.meets_difficulty());
         
-        let mut rng = ThreadRng::default();
-        let validation_threshold = stake_amount / 1000.0;
-        let random_value: f64 = rng.random();
+        // Потоки присоединены до возврата, поэтому после него никто не спрашивает условие и не считает хеши
+        let (checks_after, attempts_after) = (checks.load(Ordering::Relaxed), attempts.load(Ordering::Relaxed));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(checks.load(Ordering::Relaxed), checks_after);
+        assert_eq!(attempts.load(Ordering::Relaxed), attempts_after);
+    }
+    
+    #[test]
+    fn cancelled_mining_leaves_the_block_unsealed() {
+        let mut block = unmined(64);
+        let before = (block.nonce, block.hash.clone());
+        let attempts = AtomicU64::new(0);
         
-        if random_value <= validation_threshold {
-            self.validator = Some(validator);
-            self.hash = self.calculate_hash();
-            return true;
+        assert!(!block.mine_block_until(4, &AtomicBool::new(true), &attempts));
+        assert_eq!((block.nonce, block.hash.clone()), before);
+        assert!(attempts.into_inner() <= 4 * STOP_CHECK_INTERVAL);
+    }
+    
+    #[test]
+    fn progress_reports_grow_until_the_callback_breaks() {
+        let mut block = unmined(64);
+        let before = (block.nonce, block.hash.clone());
+        let mut reports: Vec<MiningProgress> = Vec::new();
+        
+        let result = block.mine_block_with_progress(2, 1000, |progress| {
+            reports.push(progress);
+            if reports.len() == 5 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        });
+        
+        assert_eq!(result, None);
+        assert_eq!(reports.len(), 5);
+        for pair in reports.windows(2) {
+            assert!(pair[1].attempts > pair[0].attempts, "{:?}", reports);
+            assert!(pair[1].elapsed >= pair[0].elapsed);
         }
+        assert!(reports.iter().all(|progress| progress.attempts >= 1000 && progress.current_hash_rate > 0.0));
+        assert_eq!((block.nonce, block.hash.clone()), before);
+    }
+    
+    /// Хеш десятичной конкатенации полей, как его считали до хеширования без строк
+    fn formatted_hash(block: &Block) -> String {
+        let mut text = format!("{}{}{}{}{}{}", block.index, block.timestamp, block.merkle_root, block.previous_hash, block.nonce, block.difficulty);
+        if block.version != LEGACY_BLOCK_VERSION {
+            text += &format!(":{}:{}", block.version, block.bits);
+        }
+        if let Some(base_fee) = block.base_fee {
+            text += &format!(":{}", base_fee);
+        }
+        hex::encode(<sha2::Sha256 as sha2::Digest>::digest(text.as_bytes()))
+    }
+    
+    #[test]
+    fn header_hash_matches_the_formatted_fields() {
+        let legacy = unmined(3);
+        let mut compact = unmined(3);
+        compact.set_target(CompactTarget::from_difficulty(3));
+        let mut with_base_fee = compact.clone();
+        with_base_fee.set_base_fee(Some(0.25));
+        assert_eq!((legacy.version, compact.version), (LEGACY_BLOCK_VERSION, COMPACT_TARGET_VERSION));
         
-        false
+        for mut block in [legacy, compact, with_base_fee] {
+            for nonce in [0, 9, 10, 12_345, u64::MAX] {
+                block.nonce = nonce;
+                assert_eq!(block.calculate_hash(), formatted_hash(&block));
+                assert_eq!(block.header().calculate_hash(), formatted_hash(&block));
+                assert_eq!(hex::encode(HeaderHasher::new(&block.seal_fields()).hash(nonce)), formatted_hash(&block));
+            }
+        }
+        // Генезис-блок одинаков у всех узлов, его хеш служит контрольным значением
+        assert_eq!(Block::genesis(1).hash, "44c5c533212739ff5d7eb1379947f0efd7831ae55a5d01e552b5d425a1da31d0");
     }
-}
\ No newline at end of file
+}
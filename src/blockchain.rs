@@ -1,42 +1,376 @@
-use crate::block::Block;
-use crate::transaction::{Transaction, TransactionType, calculate_hash};
-use crate::wallet::Wallet;
+#![deny(clippy::indexing_slicing)]
+
+use crate::address::{Address, Network};
+use crate::block::{Block, STOP_CHECK_INTERVAL};
+use crate::clock::{system_clock, Clock};
+use crate::entropy::{os_entropy, EntropySource};
+use crate::bloom::{AddressIndex, DEFAULT_ADDRESS_FILTER_RATE};
+use crate::balance_audit::{BalanceAuditEntry, BalanceBucket, BalanceChangeReason};
+use crate::ledger::LedgerEntry;
+use crate::mempool::{reserved_asset, reserved_balance, reserved_claim, Mempool, Pool};
+use crate::balance_history::{BalanceSnapshots, DEFAULT_BALANCE_SNAPSHOT_INTERVAL};
+use crate::merkle::IncrementalMerkle;
+use crate::hashing::{check_block_hash_algorithm, HashKind};
+use crate::ordering::check_canonical_order;
+use crate::forks::ForkIndex;
+use crate::template::IssuedTemplate;
+use crate::snapshot::SnapshotBase;
+use crate::utxo::{LedgerMode, OutPoint, TxOutput};
+use crate::transaction::{SignatureVerification, Transaction, TransactionType, calculate_hash, verify_signatures};
+use crate::wallet::{TxLocation, Wallet};
+use crate::signing::Keypair;
+use crate::labels::WalletMetadata;
 use crate::errors::BlockchainError;
-use std::collections::HashMap;
-use chrono::prelude::*;
-use rand::{rngs::ThreadRng, Rng};
+use crate::events::{ChainEvent, EventBus};
+use crate::features::Feature;
+use crate::faucet::Faucet;
+use crate::target::{retarget, CompactTarget, LEGACY_BLOCK_VERSION};
+use crate::params::{ChainParams, StakeParams, DEFAULT_MIN_BLOCK_INTERVAL};
+use crate::fee_market::{block_weight, miner_fees, BaseFeeConfig};
+use crate::fee_estimate::FeeHistory;
+use crate::treasury::{Treasury, TREASURY_MINTER};
+use crate::staking::Staking;
+use crate::vesting::VestingGrant;
+use crate::freeze::FreezeConfig;
+use crate::governance::{Governance, GOVERNANCE_ESCROW};
+use crate::key_registration::registered_keys;
+use crate::assets::{asset_debit, Asset};
+use crate::rewards::{balance_debit, claim_debit};
+use crate::sponsorship::fee_payer_debit;
+use crate::supply::is_system_sender;
+use crate::bridge::BRIDGE_ESCROW;
+use crate::mining::{BlockMiningStats, HeaderRoller, MiningOptions, MiningProgress, PROGRESS_INTERVAL};
+use crate::trace::{info, info_span, warn};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::hash_map::Entry;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use serde::{de, Serialize, Deserialize, Deserializer};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConsensusAlgorithm {
     ProofOfWork,
     ProofOfStake,
-    #[allow(dead_code)] // TODO
     DelegatedProofOfStake,
 }
 
+/// Результат приема блока от другого узла
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockAcceptance {
+    /// Блок продолжил цепочку
+    Appended,
+    /// Родитель блока неизвестен, блок отложен до его появления
+    Orphaned,
+    /// Блок уже известен
+    Duplicate,
+    /// Блок не продолжает текущую вершину
+    Stale,
+    /// Блок продолжает боковую ветку; она учтена, но главная цепочка не изменилась, даже если
+    /// ветка стала тяжелее: реорганизаций узел не делает
+    Forked,
+}
+
+/// Итог поиска печати блока
+enum Seal {
+    /// Блок запечатан; для PoW известно число перебранных хешей
+    Sealed(Option<u64>),
+    /// Майнинг прерван обратным вызовом прогресса
+    Aborted,
+}
+
+/// Через сколько блоков подстраивается цель майнинга
+pub const RETARGET_INTERVAL: usize = 10;
+
+/// Желаемое время между блоками в секундах по умолчанию и длина слота жребия PoS по умолчанию
+pub const TARGET_BLOCK_TIME: f64 = 60.0;
+
+/// Максимальное число блоков, ожидающих своего родителя
+pub const MAX_ORPHAN_BLOCKS: usize = 256;
+
+/// Идентификатор цепочки по умолчанию, входит в подписываемую кодировку транзакций
+pub const DEFAULT_CHAIN_ID: u64 = 1;
+
+fn default_chain_id() -> u64 {
+    DEFAULT_CHAIN_ID
+}
+
+fn default_balance_snapshot_interval() -> Option<u64> {
+    Some(DEFAULT_BALANCE_SNAPSHOT_INTERVAL)
+}
+
+/// Ошибка загрузки цепочки без единого блока
+pub(crate) const EMPTY_CHAIN: &str = "chain has no blocks, not even genesis";
+
+fn non_empty_chain<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Block>, D::Error> {
+    let blocks = Vec::<Block>::deserialize(deserializer)?;
+    if blocks.is_empty() {
+        return Err(de::Error::custom(EMPTY_CHAIN));
+    }
+    Ok(blocks)
+}
+
+fn default_address_filter_rate() -> f64 {
+    DEFAULT_ADDRESS_FILTER_RATE
+}
+
+fn default_accept_legacy_addresses() -> bool {
+    true
+}
+
+
+/// Транзакции блока, сумма которых зачисляется получателю при добавлении блока, включая
+/// награду майнеру; переводы на `BLOCKCHAIN_REWARD` сжигаются
+pub(crate) fn receiver_credits(transactions: &[Transaction]) -> impl Iterator<Item = &Transaction> {
+    transactions.iter().filter(|tx| tx.receiver != "BLOCKCHAIN_REWARD")
+}
+
+/// Проверяет сохраненный блок относительно предыдущего: алгоритм хеширования, хеш, связность, корень Меркла и подписи
+/// Единственная награда блока; блок без нее или с несколькими недействителен
+fn block_reward(block: &Block) -> Result<&Transaction, BlockchainError> {
+    let rewards: Vec<&Transaction> = block.transactions.iter().filter(|tx| tx.sender == "BLOCKCHAIN_REWARD").collect();
+    match rewards.as_slice() {
+        [reward] => Ok(reward),
+        _ => Err(BlockchainError::InvalidBlock(format!("Block # {} must contain exactly one reward transaction", block.index))),
+    }
+}
+
+pub(crate) fn validate_stored_block(previous: &Block, block: &Block, verification: SignatureVerification, legacy: bool) -> Result<(), BlockchainError> {
+    let i = block.index;
+    
+    check_block_hash_algorithm(block, previous.hash_algorithm)?;
+    if block.hash != block.calculate_hash() {
+        return Err(BlockchainError::InvalidBlock(format!("Wrong hash of block # {}", i)));
+    }
+    
+    if i != previous.index + 1 || block.previous_hash != previous.hash {
+        return Err(BlockchainError::InvalidBlock(format!("Wrong previous block before block # {}", i)));
+    }
+    
+    // Блоки сразу за генезисом у узла, загруженного из снимка, хранятся без тел
+    if block.is_pruned() && (previous.index == 0 || previous.is_pruned()) {
+        return Ok(());
+    }
+    
+    if block.merkle_root != Block::calculate_merkle_root(block.hash_algorithm, &block.transactions) {
+        return Err(BlockchainError::InvalidBlock(format!("Wrong Merkle root in block # {}", i)));
+    }
+    block_reward(block)?;
+    
+    verify_signatures(&block.transactions, verification, legacy)
+        .map_err(|e| BlockchainError::InvalidBlock(format!("Block # {}: {}", i, e)))
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Blockchain {
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u64,
+    /// Сеть, адреса которой принимает цепочка
+    #[serde(default)]
+    pub network: Network,
+    /// Принимать ли адреса без префикса сети. Новые цепочки их не принимают, а сохраненные до
+    /// появления поля принимают, иначе они отвергли бы собственную историю
+    #[serde(default = "default_accept_legacy_addresses")]
+    pub accept_legacy_addresses: bool,
+    /// Хеш-функция блоков и транзакций; хранится перед блоками, чтобы потоковая загрузка успела ее выбрать
+    #[serde(default)]
+    pub hash_algorithm: HashKind,
+    /// Наибольшая высота, блоки до которой могут нести прежние хеш-подписи транзакций без ключей.
+    /// Хранится перед блоками; файлам без поля, записанным до подписей ed25519, загрузка ставит вершину.
+    #[serde(default)]
+    pub(crate) legacy_signatures_until: Option<u64>,
+    /// Блоки от генезиса; никогда не пуст
+    #[serde(deserialize_with = "non_empty_chain")]
     pub chain: Vec<Block>,
     pub difficulty: usize,
-    pub pending_transactions: Vec<Transaction>,
-    pub mining_reward: f64,
+    /// Цель PoW для новых блоков; у цепочек, созданных до компактных целей, отсутствует
+    #[serde(default)]
+    pub target: Option<CompactTarget>,
+    /// Пул ожидающих транзакций; хранится под полями `pending_transactions`, `max_pending_transactions` и `pending_parents`
+    #[serde(flatten)]
+    pub(crate) mempool: Mempool,
+    #[serde(flatten)]
+    pub params: ChainParams,
     pub wallets: HashMap<String, Wallet>,
+    /// Локальные пометки адресов оператором узла: имена, метки и заметки; в консенсус не входят
+    #[serde(default)]
+    pub wallet_metadata: BTreeMap<String, WalletMetadata>,
     pub consensus_algorithm: ConsensusAlgorithm,
     pub transaction_fees: f64,
     pub validators: HashMap<String, f64>,
+    /// Отстраненные валидаторы: не выпускают блоки и не получают выплат стейкинга
+    #[serde(default)]
+    pub jailed_validators: HashSet<String>,
+    pub orphan_blocks: HashMap<String, Block>,
+    /// Релееры, которым разрешено выпускать и возвращать средства моста
+    #[serde(default)]
+    pub bridge_relayers: HashSet<String>,
+    /// Сколько потоков использовать для PoW; по умолчанию все доступные ядра
+    #[serde(default)]
+    pub mining_threads: Option<usize>,
+    /// Через сколько попыток фоновый майнинг проверяет отмену и смену вершины; по умолчанию `STOP_CHECK_INTERVAL`
+    #[serde(default)]
+    pub mining_check_interval: Option<u64>,
+    /// Как майнинг обновляет время и extra-nonce во время долгого перебора
+    #[serde(default)]
+    pub mining_options: MiningOptions,
+    /// Кран тестовой сети, если он включен
+    #[serde(default)]
+    pub faucet: Option<Faucet>,
+    /// Как проверять подписи транзакций в принимаемых блоках
+    #[serde(default)]
+    pub signature_verification: SignatureVerification,
+    /// Доля ложных срабатываний фильтров адресов в блоках; меньше — фильтры крупнее
+    #[serde(default = "default_address_filter_rate")]
+    pub address_filter_rate: f64,
+    /// Режим базовой комиссии со сжиганием; без него комиссии целиком достаются майнеру
+    #[serde(default)]
+    pub fee_market: Option<BaseFeeConfig>,
+    /// Казна, получающая долю награды за каждый блок
+    #[serde(default)]
+    pub treasury: Option<Treasury>,
+    /// Инфляционные выплаты валидаторам на границах эпох
+    #[serde(default)]
+    pub staking: Option<Staking>,
+    /// Администраторы, которые могут замораживать счета
+    #[serde(default)]
+    pub account_freeze: Option<FreezeConfig>,
+    /// Голосование по предложениям, взвешенное стейком
+    #[serde(default)]
+    pub governance: Option<Governance>,
+    /// Выпущенные пользователями активы по идентификаторам
+    #[serde(default)]
+    pub assets: HashMap<String, Asset>,
+    /// Изменения балансов в обход блоков по адресам: пополнения и штрафы валидаторов
+    #[serde(default)]
+    pub off_chain_credits: BTreeMap<String, f64>,
+    /// Открытые ключи в hex, за которыми распределения генезиса закрепили свои имена
+    #[serde(default)]
+    pub allocation_keys: BTreeMap<String, String>,
+    /// Состояние из снимка, с которого начал узел; блоки до его высоты хранятся без тел
+    #[serde(default)]
+    pub(crate) snapshot_base: Option<SnapshotBase>,
+    /// Учет средств по счетам или по непотраченным выходам; выбирается при создании цепочки
+    #[serde(default)]
+    pub(crate) ledger_mode: LedgerMode,
+    /// Удаленные кошельки меньше экзистенциального депозита и время удаления
+    #[serde(default)]
+    pub reaped_accounts: HashMap<String, i64>,
+    /// Транзакции, убранные из пула по истечении срока, по идентификаторам
+    #[serde(default)]
+    pub(crate) expired_transactions: HashMap<String, Transaction>,
+    /// Сколько последних блоков хранить в журнале изменений балансов и в проводках; по умолчанию все
+    #[serde(default)]
+    pub balance_audit_retention: Option<u64>,
+    /// Журнал изменений балансов по адресам
+    #[serde(default)]
+    pub(crate) balance_audit_log: BTreeMap<String, Vec<BalanceAuditEntry>>,
+    /// Проводки двойной записи по всем примененным блокам
+    #[serde(default)]
+    pub(crate) ledger: Vec<LedgerEntry>,
+    /// Через сколько блоков сохранять снимок состояния для `balance_at_height`; None — проигрывать от генезиса
+    #[serde(default = "default_balance_snapshot_interval")]
+    pub balance_snapshot_interval: Option<u64>,
+    #[serde(skip)]
+    pub(crate) balance_snapshots: BalanceSnapshots,
+    #[serde(skip)]
+    pub(crate) address_index: AddressIndex,
+    /// Непотраченные выходы UTXO-цепочки; пересобирается по блокам при загрузке
+    #[serde(skip)]
+    pub(crate) utxo_set: HashMap<OutPoint, TxOutput>,
+    #[serde(skip)]
+    pub(crate) fee_history: FeeHistory,
+    /// Затраты на майнинг последних блоков этого узла
+    #[serde(skip)]
+    pub(crate) mining_stats: VecDeque<BlockMiningStats>,
+    #[serde(skip)]
+    pub(crate) events: EventBus,
+    /// Блоки конкурирующих веток, которые еще не ушли ниже окончательной высоты
+    #[serde(skip)]
+    pub(crate) forks: ForkIndex,
+    /// Счетчик смен вершины: фоновый майнинг сверяется с ним, чтобы бросить блок на устаревшем родителе
+    #[serde(skip)]
+    pub(crate) tip_epoch: Arc<AtomicU64>,
+    /// Шаблоны блоков, выданные внешним майнерам
+    #[serde(skip)]
+    pub(crate) block_templates: Vec<IssuedTemplate>,
+    /// Часы, по которым ставится время новых блоков и транзакций; по умолчанию системные
+    #[serde(skip, default = "system_clock")]
+    pub(crate) clock: Arc<dyn Clock>,
+    /// Случайность вне консенсуса; по умолчанию системная
+    #[serde(skip, default = "os_entropy")]
+    pub(crate) entropy: Arc<dyn EntropySource>,
+    #[cfg(feature = "metrics")]
+    #[serde(skip)]
+    pub(crate) metrics: crate::metrics::Metrics,
 }
 
 impl Blockchain {
-    /// Создает новый блокчейн с заданной сложностью, наградой за майнинг и алгоритмом консенсуса
+    /// Создает новый блокчейн с заданной сложностью, наградой за майнинг и алгоритмом консенсуса.
+    /// Сложность нужна только PoW; цепочки PoS и DPoS получают `StakeParams` и сложность 0.
     pub fn new(difficulty: usize, mining_reward: f64, consensus_algorithm: ConsensusAlgorithm) -> Self {
+        let proof_of_work = consensus_algorithm == ConsensusAlgorithm::ProofOfWork;
+        let difficulty = if proof_of_work { difficulty } else { 0 };
+        let mut params = ChainParams::new(mining_reward);
+        if !proof_of_work {
+            params.stake_params = Some(StakeParams { min_block_interval: DEFAULT_MIN_BLOCK_INTERVAL, ..StakeParams::default() });
+        }
         let mut blockchain = Blockchain {
+            chain_id: DEFAULT_CHAIN_ID,
+            network: Network::default(),
+            accept_legacy_addresses: false,
+            hash_algorithm: HashKind::default(),
+            legacy_signatures_until: Some(0),
             chain: Vec::new(),
             difficulty,
-            pending_transactions: Vec::new(),
-            mining_reward,
+            target: Some(CompactTarget::from_difficulty(difficulty)),
+            mempool: Mempool::default(),
+            params,
             wallets: HashMap::new(),
+            wallet_metadata: BTreeMap::new(),
             consensus_algorithm,
             transaction_fees: 0.0,
             validators: HashMap::new(),
+            jailed_validators: HashSet::new(),
+            orphan_blocks: HashMap::new(),
+            bridge_relayers: HashSet::new(),
+            mining_threads: None,
+            mining_check_interval: None,
+            mining_options: MiningOptions::default(),
+            faucet: None,
+            signature_verification: SignatureVerification::default(),
+            address_filter_rate: DEFAULT_ADDRESS_FILTER_RATE,
+            fee_market: None,
+            treasury: None,
+            staking: None,
+            account_freeze: None,
+            governance: None,
+            assets: HashMap::new(),
+            off_chain_credits: BTreeMap::new(),
+            allocation_keys: BTreeMap::new(),
+            snapshot_base: None,
+            ledger_mode: LedgerMode::Account,
+            reaped_accounts: HashMap::new(),
+            expired_transactions: HashMap::new(),
+            balance_audit_retention: None,
+            balance_snapshot_interval: default_balance_snapshot_interval(),
+            balance_audit_log: BTreeMap::new(),
+            ledger: Vec::new(),
+            balance_snapshots: BalanceSnapshots::default(),
+            address_index: AddressIndex::default(),
+            utxo_set: HashMap::new(),
+            fee_history: FeeHistory::default(),
+            mining_stats: VecDeque::new(),
+            events: EventBus::default(),
+            forks: ForkIndex::default(),
+            tip_epoch: Arc::default(),
+            block_templates: Vec::new(),
+            clock: system_clock(),
+            entropy: os_entropy(),
+            #[cfg(feature = "metrics")]
+            metrics: Default::default(),
         };
         
         blockchain.create_genesis_block();
@@ -45,194 +379,844 @@ impl Blockchain {
     
     /// Создает и добавляет генезис-блок (первый блок) в цепочку
     pub fn create_genesis_block(&mut self) {
-        let genesis_block = Block::new(0, Vec::new(), String::from("0"), self.difficulty);
-        self.chain.push(genesis_block);
-        
-        println!("Genesis block created");
+        let mut genesis_block = Block::genesis(self.difficulty);
+        genesis_block.set_hash_algorithm(self.hash_algorithm);
+        genesis_block.set_target(self.current_target());
+        self.append_block(genesis_block);
+    }
+    
+    /// Подменяет часы цепочки, например на управляемые в тестах
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+    
+    /// Текущее время по часам цепочки
+    pub fn now_ts(&self) -> i64 {
+        self.clock.now_ts()
     }
     
-    /// Возвращает ссылку на последний блок в цепочке
+    /// Неподписанная транзакция со временем по часам цепочки и идентификатором по ее алгоритму хеширования
+    pub fn new_transaction(&self, sender: String, receiver: String, amount: f64, transaction_type: TransactionType) -> Transaction {
+        self.new_transaction_at(sender, receiver, amount, transaction_type, self.now_ts())
+    }
+    
+    /// То же, что `new_transaction`, но с заданным временем создания
+    pub fn new_transaction_at(&self, sender: String, receiver: String, amount: f64, transaction_type: TransactionType, timestamp: i64) -> Transaction {
+        let mut tx = Transaction::new_at(sender, receiver, amount, transaction_type, timestamp);
+        tx.set_hash_algorithm(self.hash_algorithm);
+        tx
+    }
+    
+    /// Возвращает ссылку на последний блок в цепочке. Цепочка без блоков не создается:
+    /// `new` добавляет генезис, а загрузка отклоняет файл с пустым списком блоков.
     pub fn get_latest_block(&self) -> &Block {
-        &self.chain[self.chain.len() - 1]
+        self.chain.last().expect("a chain always contains its genesis block")
+    }
+    
+    /// Ищет блок по его высоте в цепочке
+    pub fn get_block_by_index(&self, index: u64) -> Option<&Block> {
+        self.chain.get(index as usize)
+    }
+    
+    /// Ищет блок по его хешу
+    pub fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.chain.iter().find(|block| block.hash == hash)
+    }
+    
+    /// Создает кошелек для адреса, который принимает цепочка, и возвращает ссылку на него; занятый
+    /// адрес — ошибка. Имени без контрольной суммы выдается новая ключевая пара, и в пул ставится
+    /// закрепляющая ее транзакция `RegisterKey`; кошелек адреса сети заводится без ключа: подписывает
+    /// его владелец.
+    pub fn create_wallet(&mut self, address: String) -> Result<&Wallet, BlockchainError> {
+        let keypair = match self.check_address(&address)? {
+            // Ключ адреса сети выводится из самого адреса, и новая пара ему не подойдет
+            Address::Network { .. } => return self.insert_wallet(Wallet::new(address)),
+            _ => Keypair::generate(self.entropy.as_ref()),
+        };
+        if self.wallets.contains_key(&address) {
+            return Err(BlockchainError::DuplicateWallet { address });
+        }
+        self.register_key(&address, &keypair)?;
+        self.insert_wallet(Wallet::with_keypair(address, keypair))
+    }
+    
+    /// Добавляет кошелек без проверок адреса; занятый адрес — ошибка
+    pub(crate) fn insert_wallet(&mut self, wallet: Wallet) -> Result<&Wallet, BlockchainError> {
+        match self.wallets.entry(wallet.address.clone()) {
+            Entry::Occupied(_) => Err(BlockchainError::DuplicateWallet { address: wallet.address }),
+            Entry::Vacant(entry) => Ok(entry.insert(wallet)),
+        }
     }
     
-    /// Создает новый кошелек с указанным адресом и возвращает ссылку на него
-    pub fn create_wallet(&mut self, address: String) -> &Wallet {
-        self.wallets.insert(address.clone(), Wallet::new(address.clone()));
-        self.wallets.get(&address).unwrap()
+    /// Кошелек адреса; если его нет, заводит пустой. Существующий кошелек не меняется.
+    pub(crate) fn get_or_create_wallet(&mut self, address: &str) -> &mut Wallet {
+        self.wallets.entry(address.to_string()).or_insert_with(|| Wallet::new(address.to_string()))
     }
     
-    /// Добавляет средства на кошелек по указанному адресу
+    /// Пополняет существующий кошелек транзакцией выпуска; средства появятся после майнинга блока
+    #[deprecated(note = "use `mint`, or `allocate_at_genesis` for initial balances")]
     pub fn add_funds_to_wallet(&mut self, address: &str, amount: f64) -> Result<(), BlockchainError> {
-        if let Some(wallet) = self.wallets.get_mut(address) {
-            wallet.balance += amount;
-            Ok(())
-        } else {
-            Err(BlockchainError::InvalidTransaction(format!("Кошелек {} не найден", address)))
+        if !self.wallets.contains_key(address) {
+            return Err(BlockchainError::WalletNotFound { address: address.to_string() });
         }
+        self.mint(address, amount).map(|_| ())
     }
     
     /// Добавляет транзакцию в список ожидающих с проверкой валидности и баланса
     pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), BlockchainError> {
+        self.try_add_transaction(transaction).map_err(|(error, _)| error)
+    }
+    
+    /// Как `add_transaction`, но отклоненная транзакция возвращается вместе с ошибкой
+    pub(crate) fn try_add_transaction(&mut self, transaction: Transaction) -> Result<(), (BlockchainError, Box<Transaction>)> {
+        let result = self.try_submit_transaction(transaction);
+        #[cfg(feature = "metrics")]
+        if let Err((error, _)) = &result {
+            self.record_validation_failure(error);
+        }
+        result
+    }
+    
+    /// Ставит в пул транзакцию системного отправителя, которую создал сам узел: решение администраторов
+    /// заморозки, выплату моста или выпуск эмитента. Извне такие транзакции в пул не попадают.
+    pub(crate) fn queue_protocol_transaction(&mut self, transaction: Transaction) -> Result<(), BlockchainError> {
+        {
+            let mut pool = self.mempool.lock();
+            self.check_pool_transaction(&transaction, &pool)?;
+            self.insert_pending(&mut pool, transaction.clone());
+        }
+        self.events.emit(&ChainEvent::TransactionQueued { transaction: Box::new(transaction) });
+        Ok(())
+    }
+    
+    /// Проверяет транзакцию, пришедшую в пул извне. Системные отправители извне ничего не отправляют:
+    /// награду и другие выплаты в конце блока строит только его производитель, а остальные системные
+    /// транзакции узел ставит сам через `queue_protocol_transaction`.
+    pub(crate) fn check_queued_transaction(&self, transaction: &Transaction, pool: &Pool) -> Result<(), BlockchainError> {
+        if is_system_sender(&transaction.sender) || [BRIDGE_ESCROW, GOVERNANCE_ESCROW].contains(&transaction.sender.as_str()) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} only sends transactions created by the node", transaction.sender)));
+        }
+        self.check_pool_transaction(transaction, pool)
+    }
+    
+    /// Проверки транзакции перед постановкой в пул, общие для внешних и системных транзакций.
+    /// Средства под транзакции пула заняты: они спишутся, когда транзакции попадут в блок.
+    fn check_pool_transaction(&self, transaction: &Transaction, pool: &Pool) -> Result<(), BlockchainError> {
         if !transaction.is_valid() {
-            return Err(BlockchainError::InvalidTransaction("Транзакция невалидна".to_string()));
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} has no sender, receiver or amount", transaction.id)));
+        }
+        if transaction.hash_algorithm != self.hash_algorithm {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Transaction {} is hashed with {}, the chain uses {}", transaction.id, transaction.hash_algorithm.name(), self.hash_algorithm.name()
+            )));
         }
+        self.check_not_pending(transaction, pool)?;
+        self.check_transaction_addresses(transaction)?;
+        self.check_capacity(pool)?;
         
-        let total_amount = transaction.amount + transaction.fee;
+        let pending = &pool.transactions;
+        self.check_account_transaction(transaction)?;
+        let registered = registered_keys(pending);
+        self.check_sender_signature(transaction, &registered)?;
+        self.check_key_registration(transaction, &registered, pending)?;
+        self.check_nonce(transaction, pending)?;
+        self.check_feature(transaction)?;
+        self.check_bridge_transaction(transaction)?;
+        self.check_mint_transaction(transaction)?;
+        self.check_minimum_fee(transaction)?;
+        self.check_existential_deposit(transaction)?;
+        self.check_reaped_replay(transaction)?;
+        self.check_base_fee(transaction, self.current_base_fee())?;
+        self.check_treasury_transaction(transaction)?;
+        self.check_staking_transaction(transaction)?;
+        self.check_vesting_transaction(transaction)?;
+        self.check_freeze_transaction(transaction)?;
+        self.check_wallet_closure(transaction, self.chain.len() as u64)?;
+        self.check_expiry(transaction, self.chain.len() as u64)?;
+        self.check_fee_payer(transaction)?;
+        self.check_asset_transaction(transaction)?;
+        self.check_claim_transaction(transaction)?;
+        self.check_governance_transaction(transaction, self.chain.len() as u64)?;
+        self.check_unique_tickers(pending.iter().chain([transaction]))?;
         
-        if transaction.sender != "BLOCKCHAIN_REWARD" {
+        let total_amount = balance_debit(transaction);
+        
+        // Закрепление ключа ничего не списывает, а кошелек нового имени заводит блок
+        if !is_system_sender(&transaction.sender) && transaction.transaction_type != TransactionType::RegisterKey {
             if let Some(wallet) = self.wallets.get(&transaction.sender) {
-                if wallet.balance < total_amount {
+                // Отправитель может тратить и зачисления транзакций пула: тогда они станут его родителями
+                let available = wallet.balance.min(wallet.spendable(self.chain.len() as u64)) - reserved_balance(pending, &transaction.sender)
+                    + self.pending_credit(&transaction.sender, pending);
+                if available < total_amount {
                     return Err(BlockchainError::InsufficientBalance {
                         required: total_amount,
-                        available: wallet.balance,
+                        available,
                     });
                 }
+                if let Some((asset_id, amount)) = asset_debit(transaction) {
+                    let available = wallet.assets.get(asset_id).copied().unwrap_or(0)
+                        .saturating_sub(reserved_asset(pending, &transaction.sender, asset_id));
+                    if available < amount {
+                        return Err(BlockchainError::InsufficientBalance { required: amount as f64, available: available as f64 });
+                    }
+                }
+                if let Some(amount) = claim_debit(transaction) {
+                    let available = wallet.claimable_rewards - reserved_claim(pending, &transaction.sender);
+                    if available < amount {
+                        return Err(BlockchainError::InsufficientBalance { required: amount, available });
+                    }
+                }
+                if let Some((payer, fee)) = fee_payer_debit(transaction) {
+                    let available = self.spendable_balance(payer, self.chain.len() as u64) - reserved_balance(pending, payer);
+                    if available < fee {
+                        return Err(BlockchainError::InsufficientBalance { required: fee, available });
+                    }
+                }
             } else {
-                return Err(BlockchainError::InvalidTransaction(format!("Wallet recipient {} not found", transaction.sender)));
-            }
-            
-            if let Some(wallet) = self.wallets.get_mut(&transaction.sender) {
-                wallet.balance -= total_amount;
-                wallet.transaction_history.push(transaction.id.clone());
+                return Err(BlockchainError::WalletNotFound { address: transaction.sender.clone() });
             }
         }
-        
-        self.pending_transactions.push(transaction);
         Ok(())
     }
     
+    /// Списывает с кошелька отправителя сумму транзакции с комиссией, активы и востребованные награды
+    pub(crate) fn debit_sender(&mut self, tx: &Transaction) {
+        if let Some(wallet) = self.wallets.get_mut(&tx.sender) {
+            wallet.balance -= balance_debit(tx);
+            if let Some((asset_id, amount)) = asset_debit(tx) {
+                *wallet.assets.entry(asset_id.to_string()).or_insert(0) -= amount;
+            }
+            wallet.claimable_rewards -= claim_debit(tx).unwrap_or(0.0);
+        }
+        let reason = BalanceChangeReason::Transaction { tx_id: tx.id.clone() };
+        self.record_balance_change(&tx.sender, BalanceBucket::Balance, -balance_debit(tx), reason.clone());
+        self.record_balance_change(&tx.sender, BalanceBucket::Claimable, -claim_debit(tx).unwrap_or(0.0), reason);
+        self.debit_fee_payer(tx);
+    }
+    
+    /// Убирает транзакцию из списка ожидающих, освобождая занятые под нее средства. Потомки
+    /// транзакции, которым без нее нечем платить, убираются вместе с ней.
+    pub fn cancel_pending_transaction(&mut self, tx_id: &str) -> Result<Transaction, BlockchainError> {
+        for descendant in self.pending_descendants(tx_id).into_iter().rev() {
+            if let Ok(evicted) = self.remove_pending_transaction(&descendant) {
+                info!(tx_id = %evicted.id, parent = %tx_id, "Dependent pending transaction evicted");
+            }
+        }
+        self.remove_pending_transaction(tx_id)
+    }
+    
+    pub(crate) fn remove_pending_transaction(&mut self, tx_id: &str) -> Result<Transaction, BlockchainError> {
+        self.mempool.get_mut().remove(tx_id).ok_or_else(|| BlockchainError::TransactionNotFound { tx_id: tx_id.to_string() })
+    }
+    
+    /// Число потоков PoW-майнинга с учетом настройки по умолчанию
+    pub(crate) fn mining_thread_count(&self) -> usize {
+        self.mining_threads.unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+    }
+    
+    pub(crate) fn mining_check_interval(&self) -> u64 {
+        self.mining_check_interval.unwrap_or(STOP_CHECK_INTERVAL)
+    }
+    
     /// Майнит ожидающие транзакции, создает новый блок и добавляет его в цепочку
     pub fn mine_pending_transactions(&mut self, miner_address: String) -> Result<(), BlockchainError> {
-        if !self.wallets.contains_key(&miner_address) {
-            return Err(BlockchainError::InvalidTransaction(format!("Miner wallet {} not found", miner_address)));
+        let _span = info_span!("mine_pending_transactions", miner = %miner_address, height = self.chain.len()).entered();
+        self.mine_pending(miner_address, None).map(|_| ())
+    }
+    
+    /// Майнит блок из ожидающих транзакций, сообщая о ходе PoW в `on_progress`.
+    /// Возвращает false, если обратный вызов прервал майнинг; тогда цепочка и пул не меняются.
+    pub(crate) fn mine_pending(&mut self, miner_address: String, on_progress: Option<&mut dyn FnMut(MiningProgress) -> ControlFlow<()>>) -> Result<bool, BlockchainError> {
+        // Кошелек майнера создается при зачислении награды, если его еще нет; за новым именем
+        // узел закрепляет свой ключ в том же блоке
+        self.check_address(&miner_address)?;
+        let binding = self.bind_reward_key(&miner_address);
+        let mined = self.mine_candidate(miner_address, on_progress);
+        if let (Some(tx_id), false) = (binding, matches!(mined, Ok(true))) {
+            self.release_reward_key(&tx_id);
         }
+        mined
+    }
+    
+    fn mine_candidate(&mut self, miner_address: String, on_progress: Option<&mut dyn FnMut(MiningProgress) -> ControlFlow<()>>) -> Result<bool, BlockchainError> {
+        // Пул только читается, копируются лишь отобранные транзакции: пул и балансы меняются
+        // только после печати, поэтому при любой ошибке пул остается прежним вместе с порядком транзакций
+        let base_fee = self.current_base_fee();
+        let mut transactions = {
+            let pool = self.mempool.lock();
+            let (transactions, selected) = self.select_block_transactions(&pool, self.chain.len() as u64, base_fee);
+            self.check_reserved_space(&transactions, &pool, &selected, self.chain.len() as u64, base_fee)?;
+            transactions
+        };
         
-        let total_fees: f64 = self.pending_transactions.iter().map(|tx| tx.fee).sum();
-        self.transaction_fees = total_fees;
-        
-        let reward_tx = Transaction::new(
-            String::from("BLOCKCHAIN_REWARD"),
-            miner_address.clone(),
-            self.mining_reward + total_fees,
-            TransactionType::Transfer
-        );
-        
-        self.pending_transactions.push(reward_tx);
+        let mut merkle = IncrementalMerkle::from_transactions(self.hash_algorithm, &transactions);
+        for tx in self.coinbase_transactions(self.chain.len() as u64, &miner_address, &transactions, base_fee) {
+            merkle.push(&tx);
+            transactions.push(tx);
+        }
         
-        let mut new_block = Block::new(
+        let mut new_block = Block::from_candidate(
             self.chain.len() as u64,
-            self.pending_transactions.clone(),
+            transactions,
+            &merkle,
             self.get_latest_block().hash.clone(),
-            self.difficulty
+            self.current_target(),
+            self.next_block_timestamp()
         );
+        new_block.set_base_fee(base_fee);
         
+        let started = Instant::now();
+        
+        let hash_attempts = match self.seal_block(&mut new_block, &miner_address, on_progress)? {
+            Seal::Sealed(hash_attempts) => hash_attempts,
+            Seal::Aborted => return Ok(false),
+        };
+        self.record_block_mining(&new_block, hash_attempts, started.elapsed());
+        
+        self.apply_block_effects(&new_block);
+        self.append_block(new_block);
+        #[cfg(feature = "metrics")]
+        self.record_appended_block();
+        self.events.emit(&ChainEvent::BlockAdded { block: self.get_latest_block().clone() });
+        self.transaction_fees = 0.0;
+        
+        Ok(true)
+    }
+    
+    /// Системные транзакции в конце блока: выплаты стейкинга на границе эпохи, доля казны,
+    /// если она включена, итоги закончившихся голосований и награда майнеру с его частью комиссий
+    pub(crate) fn coinbase_transactions(&self, height: u64, miner_address: &str, transactions: &[Transaction], base_fee: Option<f64>) -> Vec<Transaction> {
+        let mut coinbase = self.staking_payout_transactions(height);
+        coinbase.extend(self.treasury_cut_transaction(height));
+        coinbase.extend(self.governance_settlements(height));
+        let mut reward = self.new_transaction(
+            String::from("BLOCKCHAIN_REWARD"),
+            miner_address.to_string(),
+            self.reward_at_height(height) - self.treasury_cut(height) + miner_fees(transactions, base_fee),
+            TransactionType::Transfer
+        );
+        if self.ledger_mode == LedgerMode::Utxo {
+            reward.outputs.push(TxOutput { address: reward.receiver.clone(), amount: reward.amount });
+            reward.valid_until_height = Some(height);
+            reward.reseal();
+        }
+        coinbase.push(reward);
+        coinbase
+    }
+    
+    /// Запечатывает блок по текущему консенсусу
+    fn seal_block(&self, block: &mut Block, miner_address: &str, on_progress: Option<&mut dyn FnMut(MiningProgress) -> ControlFlow<()>>) -> Result<Seal, BlockchainError> {
         match self.consensus_algorithm {
             ConsensusAlgorithm::ProofOfWork => {
-                new_block.mine_block();
+                let threads = self.mining_thread_count();
+                let mut roller = HeaderRoller::new(self.clock.clone(), self.mining_options, block);
+                let round = roller.round();
+                let roll = |block: &mut Block| roller.roll(block);
+                Ok(match on_progress {
+                    Some(on_progress) => block.mine_block_with_progress_rolling(threads, PROGRESS_INTERVAL, round, roll, on_progress)
+                        .map_or(Seal::Aborted, |attempts| Seal::Sealed(Some(attempts))),
+                    None => {
+                        let attempts = AtomicU64::new(0);
+                        block.mine_block_rolling(threads, self.mining_check_interval(), &attempts, round, || true, roll);
+                        Seal::Sealed(Some(attempts.into_inner()))
+                    },
+                })
             },
             ConsensusAlgorithm::ProofOfStake => {
-                if let Some(stake) = self.validators.get(&miner_address) {
-                    if !new_block.validate_with_pos(miner_address.clone(), *stake) {
+                self.check_block_interval(block)?;
+                if self.jailed_validators.contains(miner_address) {
+                    return Err(BlockchainError::ValidatorJailed { address: miner_address.to_string() });
+                }
+                if let Some(stake) = self.validators.get(miner_address) {
+                    let draw = self.producer_draw(block, miner_address);
+                    if !block.validate_with_pos(miner_address.to_string(), *stake, draw) {
                         return Err(BlockchainError::ConsensusError("Cannot validate block with PoS".to_string()));
                     }
                 } else {
-                    return Err(BlockchainError::ConsensusError(format!("This address {} is not a validator", miner_address)));
+                    return Err(BlockchainError::ValidatorNotFound { address: miner_address.to_string() });
                 }
+                Ok(Seal::Sealed(None))
             },
             ConsensusAlgorithm::DelegatedProofOfStake => {
-                let mut rng = ThreadRng::default();
-                let is_delegate = rng.random_bool(0.5);
-                
-                if !is_delegate {
-                    return Err(BlockchainError::ConsensusError("This address is not a delegate of this block".to_string()));
+                self.check_block_interval(block)?;
+                if !self.is_scheduled_producer(block, miner_address) {
+                    return Err(BlockchainError::NotScheduledProducer { address: miner_address.to_string() });
                 }
                 
-                new_block.validator = Some(miner_address.clone());
+                block.validator = Some(miner_address.to_string());
+                Ok(Seal::Sealed(None))
             }
         }
-        
-        for tx in &new_block.transactions {
-            if tx.sender != "BLOCKCHAIN_REWARD" && tx.receiver != "BLOCKCHAIN_REWARD" {
+    }
+    
+    /// Параметры выпуска блоков PoS и DPoS; None у PoW-цепочек
+    pub fn stake_params(&self) -> Option<StakeParams> {
+        match self.consensus_algorithm {
+            ConsensusAlgorithm::ProofOfWork => None,
+            _ => Some(self.params.stake_params.unwrap_or_default()),
+        }
+    }
+    
+    /// Проверяет, что блок PoS или DPoS выпущен не раньше `min_block_interval` после вершины
+    fn check_block_interval(&self, block: &Block) -> Result<(), BlockchainError> {
+        let Some(stake_params) = self.stake_params() else {
+            return Ok(());
+        };
+        let parent = self.get_latest_block();
+        let earliest = parent.timestamp.saturating_add_unsigned(stake_params.min_block_interval);
+        if block.timestamp < earliest {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "Block # {} comes {} s after its parent; the minimum interval is {} s",
+                block.index, block.timestamp - parent.timestamp, stake_params.min_block_interval
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Применяет к состоянию все последствия блока, кроме его добавления в цепочку. Общая для
+    /// майнинга, приема блоков от других узлов и проигрывания цепочки, поэтому состояние у них
+    /// одинаково. Пул ничего не списывает: блок списывает всех отправителей сам и убирает
+    /// вошедшие в него транзакции из пула.
+    pub(crate) fn apply_block_effects(&mut self, block: &Block) {
+        let included: HashSet<&str> = block.transactions.iter().map(|tx| tx.id.as_str()).collect();
+        self.mempool.get_mut().transactions.retain(|tx| !included.contains(tx.id.as_str()));
+        match self.ledger_mode {
+            LedgerMode::Account => {
+                for tx in block.transactions.iter().filter(|tx| !is_system_sender(&tx.sender)) {
+                    self.debit_sender(tx);
+                }
+                self.credit_block_receivers(block);
+            },
+            LedgerMode::Utxo => self.apply_utxo_block(block),
+        }
+        self.apply_asset_transactions(block);
+        self.apply_account_freezes(block);
+        self.apply_key_registrations(block);
+        self.apply_account_nonces(block);
+        self.apply_wallet_closures(block);
+        self.apply_governance(block);
+        self.reap_block_senders(block);
+    }
+    
+    /// Добавляет блок на вершину, обновляет фильтр адресов и истории кошельков и убирает
+    /// из пула просроченные транзакции
+    fn append_block(&mut self, block: Block) {
+        let miner = block.transactions.iter().find(|tx| tx.sender == "BLOCKCHAIN_REWARD").map(|tx| tx.receiver.as_str());
+        info!(
+            index = block.index, hash = %block.hash, difficulty = block.difficulty, txs = block.transactions.len(), miner,
+            "Block appended"
+        );
+        self.chain.push(block);
+        self.tip_epoch.fetch_add(1, Ordering::Relaxed);
+        self.index_latest_block();
+        self.record_wallet_history(self.chain.len() - 1);
+        self.record_fee_rates();
+        self.prune_balance_audit();
+        self.prune_ledger();
+        self.prune_forks();
+        self.prune_pending_parents();
+        self.drop_expired_transactions();
+        self.drop_replaced_nonces();
+        self.prune_system_queue();
+    }
+    
+    /// Записывает транзакции блока указанной высоты в истории кошельков отправителей и получателей
+    fn record_wallet_history(&mut self, height: usize) {
+        let Some(block) = self.chain.get(height) else {
+            return;
+        };
+        for (position, tx) in block.transactions.iter().enumerate() {
+            let location = TxLocation { block: height as u64, position: position as u32 };
+            if let Some(wallet) = self.wallets.get_mut(&tx.sender) {
+                wallet.record(location);
+            }
+            if tx.receiver != tx.sender {
                 if let Some(wallet) = self.wallets.get_mut(&tx.receiver) {
-                    wallet.balance += tx.amount;
-                    wallet.transaction_history.push(tx.id.clone());
+                    wallet.record(location);
+                }
+            }
+        }
+    }
+    
+    /// Пересобирает истории кошельков по всей цепочке, например после загрузки с диска
+    pub fn rebuild_wallet_histories(&mut self) {
+        for wallet in self.wallets.values_mut() {
+            wallet.clear_history();
+        }
+        for height in 0..self.chain.len() {
+            self.record_wallet_history(height);
+        }
+    }
+    
+    /// Зачисляет получателям средства из транзакций блока, создавая недостающие кошельки;
+    /// гранты зачисляются вместе с графиком вестинга, копящиеся награды — в накопленные,
+    /// а пыль на новые адреса может сгореть
+    pub(crate) fn credit_block_receivers(&mut self, block: &Block) {
+        self.accrue_block_rewards(block);
+        let mut dust_burned = HashSet::new();
+        for tx in receiver_credits(&block.transactions) {
+            if self.accrues_reward(tx) {
+                continue;
+            }
+            if self.burns_dust_credit(tx) {
+                self.record_off_chain_credit(&tx.receiver, -tx.amount);
+                dust_burned.insert(tx.id.as_str());
+                continue;
+            }
+            let wallet = self.get_or_create_wallet(&tx.receiver);
+            wallet.balance += tx.amount;
+            wallet.vesting.extend(VestingGrant::from_transaction(tx));
+            self.record_credit(tx, BalanceBucket::Balance);
+        }
+        self.record_block_ledger(block, &dust_burned);
+    }
+    
+    /// Проверяет блок, полученный извне, как продолжение текущей вершины цепочки
+    pub fn verify_block(&self, block: &Block) -> Result<(), BlockchainError> {
+        let tip = self.get_latest_block();
+        
+        if block.index != tip.index + 1 || block.previous_hash != tip.hash {
+            return Err(BlockchainError::InvalidBlock(format!("Block {} does not extend the tip {}", block.index, tip.index)));
+        }
+        
+        check_block_hash_algorithm(block, self.hash_algorithm)?;
+        if block.hash != block.calculate_hash() {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong hash of block # {}", block.index)));
+        }
+        
+        if block.merkle_root != Block::calculate_merkle_root(self.hash_algorithm, &block.transactions) {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong Merkle root in block # {}", block.index)));
+        }
+        
+        match self.consensus_algorithm {
+            ConsensusAlgorithm::ProofOfWork => {
+                let expected = if block.version == LEGACY_BLOCK_VERSION {
+                    block.difficulty == self.difficulty
                 } else {
-                    let mut new_wallet = Wallet::new(tx.receiver.clone());
-                    new_wallet.balance = tx.amount;
-                    new_wallet.transaction_history.push(tx.id.clone());
-                    self.wallets.insert(tx.receiver.clone(), new_wallet);
+                    let target = self.current_target();
+                    block.bits == target.0 && block.difficulty == target.leading_zero_digits()
+                };
+                if !expected || !block.meets_difficulty() {
+                    return Err(BlockchainError::InvalidBlock(format!("Block # {} does not meet difficulty {}", block.index, self.difficulty)));
+                }
+            },
+            ConsensusAlgorithm::ProofOfStake | ConsensusAlgorithm::DelegatedProofOfStake => {
+                self.check_block_interval(block)?;
+                match &block.validator {
+                    None => return Err(BlockchainError::InvalidBlock(format!("Block # {} has no validator", block.index))),
+                    Some(validator) if self.jailed_validators.contains(validator) => {
+                        return Err(BlockchainError::InvalidBlock(format!("Block # {} is sealed by jailed validator {}", block.index, validator)));
+                    },
+                    Some(validator) if !self.is_scheduled_producer(block, validator) => {
+                        return Err(BlockchainError::InvalidBlock(format!("Block # {} is sealed by {}, who did not win the draw", block.index, validator)));
+                    },
+                    Some(_) => {},
+                }
+            },
+        }
+        
+        verify_signatures(&block.transactions, self.signature_verification, self.accepts_legacy_signatures(block.index))?;
+        check_canonical_order(block)?;
+        
+        let reward = block_reward(block)?;
+        
+        let base_fee = self.expected_base_fee(tip);
+        if block.base_fee != base_fee {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong base fee in block # {}", block.index)));
+        }
+        if let Some(config) = &self.fee_market {
+            if block_weight(&block.transactions) > config.max_block_weight() {
+                return Err(BlockchainError::InvalidBlock(format!("Block # {} exceeds the maximum weight {}", block.index, config.max_block_weight())));
+            }
+        }
+        
+        let cut = self.treasury_cut(block.index);
+        let treasury_cuts: Vec<&Transaction> = block.transactions.iter().filter(|tx| tx.sender == TREASURY_MINTER).collect();
+        let cut_ok = match (treasury_cuts.as_slice(), &self.treasury) {
+            ([], _) => cut <= 0.0,
+            ([tx], Some(treasury)) => tx.receiver == treasury.config.address && (tx.amount - cut).abs() <= 1e-9,
+            _ => false,
+        };
+        if !cut_ok {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong treasury cut in block # {}", block.index)));
+        }
+        self.check_staking_payouts(block)?;
+        self.check_governance_settlements(block)?;
+        
+        if (reward.amount - (self.reward_at_height(block.index) - cut + miner_fees(&block.transactions, base_fee))).abs() > 1e-9 {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong reward in block # {}", block.index)));
+        }
+        
+        if self.ledger_mode == LedgerMode::Utxo {
+            return self.check_utxo_block(block, base_fee);
+        }
+        self.check_block_nonces(block)?;
+        
+        // Пул ничего не списывает, поэтому по балансу проверяется каждая транзакция блока, в том числе
+        // лежащие в локальном пуле. Отправитель может тратить зачисленное ему транзакциями выше по блоку, поэтому по каждому
+        // адресу считается наибольшее превышение списаний над такими зачислениями.
+        let mut required: HashMap<&str, f64> = HashMap::new();
+        let mut debited: HashMap<&str, f64> = HashMap::new();
+        let mut credited: HashMap<&str, f64> = HashMap::new();
+        let mut required_assets: HashMap<(&str, &str), u64> = HashMap::new();
+        let mut required_claims: HashMap<&str, f64> = HashMap::new();
+        let mut registered = HashMap::new();
+        self.check_unique_tickers(&block.transactions)?;
+        for tx in &block.transactions {
+            if !tx.is_valid() {
+                return Err(BlockchainError::InvalidTransaction(format!("Transaction {} is invalid", tx.id)));
+            }
+            self.check_account_transaction(tx)?;
+            self.check_sender_signature(tx, &registered)?;
+            self.check_key_registration(tx, &registered, &block.transactions)?;
+            registered.extend(registered_keys([tx]));
+            self.check_feature(tx)?;
+            self.check_bridge_transaction(tx)?;
+            self.check_mint_transaction(tx)?;
+            self.check_minimum_fee(tx)?;
+            self.check_existential_deposit(tx)?;
+            self.check_reaped_replay(tx)?;
+            self.check_base_fee(tx, base_fee)?;
+            self.check_vesting_transaction(tx)?;
+            self.check_freeze_transaction(tx)?;
+            self.check_wallet_closure(tx, block.index)?;
+            self.check_expiry(tx, block.index)?;
+            self.check_fee_payer(tx)?;
+            self.check_asset_transaction(tx)?;
+            self.check_claim_transaction(tx)?;
+            if tx.sender != GOVERNANCE_ESCROW {
+                self.check_governance_transaction(tx, block.index)?;
+            }
+            if tx.sender != TREASURY_MINTER {
+                self.check_treasury_transaction(tx)?;
+            }
+            if !is_system_sender(&tx.sender) && tx.transaction_type != TransactionType::RegisterKey {
+                let mut debit = |address, amount: f64| {
+                    let total = debited.entry(address).or_insert(0.0);
+                    *total += amount;
+                    let shortfall = *total - credited.get(address).copied().unwrap_or(0.0);
+                    let needed = required.entry(address).or_insert(0.0);
+                    *needed = needed.max(shortfall);
+                };
+                debit(tx.sender.as_str(), balance_debit(tx));
+                if let Some((asset_id, amount)) = asset_debit(tx) {
+                    *required_assets.entry((tx.sender.as_str(), asset_id)).or_insert(0) += amount;
+                }
+                if let Some(amount) = claim_debit(tx) {
+                    *required_claims.entry(tx.sender.as_str()).or_insert(0.0) += amount;
                 }
+                if let Some((payer, fee)) = fee_payer_debit(tx) {
+                    debit(payer, fee);
+                }
+            }
+            if tx.receiver != "BLOCKCHAIN_REWARD" && !self.accrues_reward(tx) && !self.burns_dust_credit(tx) {
+                *credited.entry(tx.receiver.as_str()).or_insert(0.0) += tx.amount;
             }
         }
         
-        self.chain.push(new_block);
-        self.pending_transactions = Vec::new();
-        self.transaction_fees = 0.0;
+        for (sender, amount) in required_claims {
+            let available = self.wallets.get(sender).map_or(0.0, |wallet| wallet.claimable_rewards);
+            if available < amount {
+                return Err(BlockchainError::InsufficientBalance { required: amount, available });
+            }
+        }
+        
+        for ((sender, asset_id), amount) in required_assets {
+            let available = self.wallets.get(sender).and_then(|wallet| wallet.assets.get(asset_id)).copied().unwrap_or(0);
+            if available < amount {
+                return Err(BlockchainError::InsufficientBalance { required: amount as f64, available: available as f64 });
+            }
+        }
+        
+        for (sender, amount) in required {
+            let available = self.spendable_balance(sender, block.index);
+            if !self.wallets.contains_key(sender) {
+                return Err(BlockchainError::WalletNotFound { address: sender.to_string() });
+            }
+            if available < amount {
+                return Err(BlockchainError::InsufficientBalance { required: amount, available });
+            }
+        }
         
         Ok(())
     }
     
+    /// Принимает блок от другого узла: проверяет, применяет и подтягивает ожидавших его потомков
+    pub fn accept_block(&mut self, block: Block) -> Result<BlockAcceptance, BlockchainError> {
+        if self.chain.iter().any(|known| known.hash == block.hash) || self.orphan_blocks.contains_key(&block.previous_hash) || self.forks.contains(&block.hash) {
+            return Ok(BlockAcceptance::Duplicate);
+        }
+        
+        let tip = self.get_latest_block();
+        if block.previous_hash != tip.hash && (block.index <= tip.index || self.forks.contains(&block.previous_hash)) {
+            return self.accept_side_block(block);
+        }
+        if block.index > tip.index + 1 {
+            if self.orphan_blocks.len() >= MAX_ORPHAN_BLOCKS {
+                return Err(BlockchainError::InvalidBlock("Orphan pool is full".to_string()));
+            }
+            self.orphan_blocks.insert(block.previous_hash.clone(), block);
+            return Ok(BlockAcceptance::Orphaned);
+        }
+        if block.index <= tip.index {
+            return Ok(BlockAcceptance::Stale);
+        }
+        
+        let verified = self.verify_block(&block);
+        if let Err(error) = &verified {
+            warn!(index = block.index, hash = %block.hash, %error, "Block rejected");
+        }
+        #[cfg(feature = "metrics")]
+        if let Err(error) = &verified {
+            self.record_validation_failure(error);
+        }
+        verified?;
+        self.apply_external_block(block);
+        
+        loop {
+            let tip_hash = self.get_latest_block().hash.clone();
+            let Some(orphan) = self.orphan_blocks.remove(&tip_hash) else { break };
+            if self.verify_block(&orphan).is_err() {
+                break;
+            }
+            self.apply_external_block(orphan);
+        }
+        
+        Ok(BlockAcceptance::Appended)
+    }
+    
+    /// Применяет проверенный внешний блок и добавляет его на вершину
+    fn apply_external_block(&mut self, block: Block) {
+        self.apply_block_effects(&block);
+        self.append_block(block);
+        #[cfg(feature = "metrics")]
+        self.record_appended_block();
+        self.events.emit(&ChainEvent::BlockAdded { block: self.get_latest_block().clone() });
+    }
+    
     /// Регистрирует валидатора для PoS с указанной суммой стейкинга
     pub fn add_validator(&mut self, address: String, stake_amount: f64) -> Result<(), BlockchainError> {
+        self.require_account_mode("Staking")?;
+        let height = self.chain.len() as u64;
+        let reserved = reserved_balance(&self.mempool.get_mut().transactions, &address);
         if let Some(wallet) = self.wallets.get_mut(&address) {
-            if wallet.balance < stake_amount {
-                return Err(BlockchainError::InsufficientBalance {
-                    required: stake_amount,
-                    available: wallet.balance,
-                });
+            // Средства под транзакции пула заняты
+            let available = wallet.spendable(height) - reserved;
+            if available < stake_amount {
+                return Err(BlockchainError::InsufficientBalance { required: stake_amount, available });
             }
             
             wallet.balance -= stake_amount;
             wallet.staking_balance += stake_amount;
+            if let Some(staking) = &mut self.staking {
+                staking.bond(&address, self.chain.len() as u64);
+            }
+            self.record_balance_change(&address, BalanceBucket::Balance, -stake_amount, BalanceChangeReason::Bond);
+            self.record_balance_change(&address, BalanceBucket::Staking, stake_amount, BalanceChangeReason::Bond);
             self.validators.insert(address, stake_amount);
             Ok(())
         } else {
-            Err(BlockchainError::InvalidTransaction(format!("Cannot find wallet {}", address)))
+            Err(BlockchainError::WalletNotFound { address: address.to_string() })
+        }
+    }
+    
+    /// Снимает валидатора и возвращает его стейк на основной баланс
+    pub fn remove_validator(&mut self, address: &str) -> Result<f64, BlockchainError> {
+        let stake = self.validators.remove(address)
+            .ok_or_else(|| BlockchainError::ValidatorNotFound { address: address.to_string() })?;
+        self.jailed_validators.remove(address);
+        if let Some(staking) = &mut self.staking {
+            staking.unbond(address);
+        }
+        
+        if let Some(wallet) = self.wallets.get_mut(address) {
+            wallet.staking_balance -= stake;
+            wallet.balance += stake;
+            self.record_balance_change(address, BalanceBucket::Staking, -stake, BalanceChangeReason::Unbond);
+            self.record_balance_change(address, BalanceBucket::Balance, stake, BalanceChangeReason::Unbond);
+        }
+        
+        Ok(stake)
+    }
+    
+    /// Списывает долю ставки валидатора в наказание; валидатор с нулевой ставкой удаляется
+    pub fn slash_validator(&mut self, address: &str, fraction: f64) -> Result<f64, BlockchainError> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(BlockchainError::InvalidTransaction(format!("Slash fraction {} is out of range", fraction)));
         }
+        
+        let stake = self.validators.get_mut(address)
+            .ok_or_else(|| BlockchainError::ValidatorNotFound { address: address.to_string() })?;
+        let amount = *stake * fraction;
+        *stake -= amount;
+        if *stake <= 0.0 {
+            self.validators.remove(address);
+        }
+        
+        if let Some(wallet) = self.wallets.get_mut(address) {
+            wallet.staking_balance -= amount;
+            self.record_off_chain_credit(address, -amount);
+            self.record_balance_change(address, BalanceBucket::Staking, -amount, BalanceChangeReason::Slash);
+        }
+        
+        self.events.emit(&ChainEvent::ValidatorSlashed { validator: address.to_string(), amount });
+        Ok(amount)
     }
     
     /// Проверяет валидность всей цепочки блоков
     pub fn is_chain_valid(&self) -> bool {
-        for i in 1..self.chain.len() {
-            let current_block = &self.chain[i];
-            let previous_block = &self.chain[i - 1];
-            
-            if current_block.hash != current_block.calculate_hash() {
-                println!("Wrong hash of block # {}", i);
-                return false;
-            }
-            
-            if current_block.previous_hash != previous_block.hash {
-                println!("Wrong previous block before block # {}", i);
-                return false;
-            }
-            
-            let merkle_root = Block::calculate_merkle_root(&current_block.transactions);
-            if current_block.merkle_root != merkle_root {
-                println!("Wrong Merkle root in block # {}", i);
-                return false;
-            }
+        match self.validate_chain(self.signature_verification) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(error = %e, "Chain validation failed");
+                false
+            },
+        }
+    }
+    
+    /// Проверяет алгоритм хеширования, хеши, связность, корни Меркла и подписи транзакций всей цепочки
+    pub fn validate_chain(&self, verification: SignatureVerification) -> Result<(), BlockchainError> {
+        let _span = info_span!("validate_chain", blocks = self.chain.len()).entered();
+        if let Some(genesis) = self.chain.first() {
+            check_block_hash_algorithm(genesis, self.hash_algorithm)?;
+        }
+        for (previous, block) in self.chain.iter().zip(self.chain.iter().skip(1)) {
+            validate_stored_block(previous, block, verification, self.accepts_legacy_signatures(block.index))?;
         }
         
-        true
+        Ok(())
+    }
+    
+    /// Принимаются ли в блоке высоты `height` прежние хеш-подписи: только в блоках, сохраненных
+    /// до подписей ed25519
+    pub(crate) fn accepts_legacy_signatures(&self, height: u64) -> bool {
+        self.legacy_signatures_until.is_some_and(|until| height <= until)
     }
     
-    /// Возвращает баланс кошелька по указанному адресу
+    /// Возвращает баланс кошелька по указанному адресу за вычетом средств, занятых транзакциями пула
     pub fn get_balance(&self, address: &str) -> f64 {
+        if self.ledger_mode == LedgerMode::Utxo {
+            return self.utxo_balance(address);
+        }
         if let Some(wallet) = self.wallets.get(address) {
-            return wallet.balance;
+            return wallet.balance - reserved_balance(&self.mempool.lock().transactions, address);
         }
         
         0.0
     }
     
     /// Возвращает историю транзакций для указанного адреса
-    #[allow(dead_code)]
     pub fn get_transaction_history(&self, address: &str) -> Vec<Transaction> {
+        if let Some(wallet) = self.wallets.get(address) {
+            return wallet.history(self).cloned().collect();
+        }
+        
         let mut history = Vec::new();
         
-        for block in &self.chain {
+        for height in self.blocks_possibly_involving(address) {
+            let Some(block) = self.chain.get(height as usize) else {
+                continue;
+            };
             for tx in &block.transactions {
                 if tx.sender == address || tx.receiver == address {
                     history.push(tx.clone());
@@ -249,7 +1233,6 @@ impl Blockchain {
     }
     
     /// Ищет транзакцию по её ID
-    #[allow(dead_code)]
     pub fn find_transaction(&self, tx_id: &str) -> Option<Transaction> {
         for block in &self.chain {
             for tx in &block.transactions {
@@ -262,83 +1245,512 @@ impl Blockchain {
         None
     }
     
-    /// Корректирует сложность майнинга на основе времени создания блоков
+    /// Цель PoW для следующего блока; у цепочек без нее выводится из целой сложности
+    pub fn current_target(&self) -> CompactTarget {
+        self.target.unwrap_or_else(|| CompactTarget::from_difficulty(self.difficulty))
+    }
+    
+    /// Корректирует цель майнинга каждые `RETARGET_INTERVAL` блоков пропорционально
+    /// отношению фактического времени между блоками к желаемому из параметров цепочки.
+    /// У PoS и DPoS цели нет, и вызов ничего не делает.
     pub fn adjust_difficulty(&mut self) {
-        if self.chain.len() % 10 == 0 && self.chain.len() > 1 {
-            let last_ten_blocks = &self.chain[self.chain.len() - 10..];
-            let latest_block = self.get_latest_block();
-            let first_of_last_ten = &last_ten_blocks[0];
-            
-            let time_diff = latest_block.timestamp - first_of_last_ten.timestamp;
-            let avg_block_time = time_diff as f64 / 10.0;
-            
-            let target_time = 60.0;
+        if self.consensus_algorithm != ConsensusAlgorithm::ProofOfWork {
+            return;
+        }
+        if self.chain.len().is_multiple_of(RETARGET_INTERVAL) && self.chain.len() > 1 {
+            let Some(first) = self.chain.len().checked_sub(RETARGET_INTERVAL).and_then(|start| self.chain.get(start)) else {
+                return;
+            };
+            let time_diff = self.get_latest_block().timestamp - first.timestamp;
+            let avg_block_time = time_diff as f64 / (RETARGET_INTERVAL - 1) as f64;
             
-            if avg_block_time < target_time * 0.9 {
-                self.difficulty += 1;
-                println!("Difficulty increased, current: {}", self.difficulty);
-            } else if avg_block_time > target_time * 1.1 && self.difficulty > 1 {
-                self.difficulty -= 1;
-                println!("Difficulty decreased, current: {}", self.difficulty);
-            }
+            let target = retarget(self.current_target(), avg_block_time, self.params.target_block_time);
+            self.target = Some(target);
+            self.difficulty = target.leading_zero_digits();
+            info!(target = %format_args!("{:08x}", target.0), difficulty = self.difficulty, avg_block_time, "Target adjusted");
         }
     }
     
     /// Создает смарт-контракт и добавляет его в виде транзакции
     pub fn create_smart_contract(&mut self, creator: String, code: String, initial_value: f64) -> Result<String, BlockchainError> {
-        let contract_address = format!("contract_{}", calculate_hash(&format!("{}{}{}", creator, code, Utc::now().timestamp())));
+        let now = self.now_ts();
+        let contract_address = format!("contract_{}", calculate_hash(self.hash_algorithm, &format!("{}{}{}", creator, code, now)));
         
-        let tx = Transaction::new(
+        let mut tx = self.new_transaction_at(
             creator,
             contract_address.clone(),
             initial_value,
-            TransactionType::SmartContract(code)
+            TransactionType::SmartContract(code),
+            now
         );
+        self.sign_as_sender(&mut tx);
         
         self.add_transaction(tx)?;
         
-        self.create_wallet(contract_address.clone());
+        self.get_or_create_wallet(&contract_address);
         
         Ok(contract_address)
     }
     
     /// Сохраняет данные в блокчейне в виде транзакции
     pub fn store_data(&mut self, sender: String, data: Vec<u8>) -> Result<String, BlockchainError> {
-        let data_id = format!("data_{}", calculate_hash(&format!("{}{:?}", sender, data)));
+        let data_id = format!("data_{}", calculate_hash(self.hash_algorithm, &format!("{}{:?}", sender, data)));
         
-        let tx = Transaction::new(
+        let mut tx = self.new_transaction(
             sender,
             String::from("BLOCKCHAIN_DATA"),
             0.1,
             TransactionType::Data(data)
         );
+        self.sign_as_sender(&mut tx);
         
         self.add_transaction(tx)?;
         
         Ok(data_id)
     }
     
-    /// Имитирует выполнение функции смарт-контракта
-    pub fn execute_smart_contract(&mut self, contract_address: &str, function: &str, args: Vec<String>) -> Result<String, BlockchainError> {
-        let mut contract_code = String::new();
+    /// Возвращает данные, сохраненные через `store_data` или потоковую загрузку
+    pub fn get_data(&self, data_id: &str) -> Option<Vec<u8>> {
         for block in &self.chain {
             for tx in &block.transactions {
-                if let TransactionType::SmartContract(ref code) = tx.transaction_type {
-                    if tx.receiver == contract_address {
-                        contract_code = code.clone();
-                        break;
+                if let TransactionType::Data(ref data) = tx.transaction_type {
+                    if format!("data_{}", calculate_hash(self.hash_algorithm, &format!("{}{:?}", tx.sender, data))) == data_id {
+                        return Some(data.clone());
                     }
                 }
             }
-            if !contract_code.is_empty() {
-                break;
-            }
         }
         
-        if contract_code.is_empty() {
-            return Err(BlockchainError::InvalidTransaction(format!("Smart contract {} not found", contract_address)));
+        self.read_data(data_id)
+    }
+    
+    /// Ищет транзакцию, которой был развернут смарт-контракт
+    pub fn find_smart_contract(&self, contract_address: &str) -> Option<&Transaction> {
+        self.chain.iter()
+            .flat_map(|block| &block.transactions)
+            .find(|tx| tx.receiver == contract_address && matches!(tx.transaction_type, TransactionType::SmartContract(_)))
+            .or_else(|| self.snapshot_base.as_ref().and_then(|base| base.state.contracts.get(contract_address)))
+    }
+    
+    /// Имитирует выполнение функции смарт-контракта
+    pub fn execute_smart_contract(&mut self, contract_address: &str, function: &str, args: Vec<String>) -> Result<String, BlockchainError> {
+        self.require_feature(Feature::ContractCalls)?;
+        if self.find_smart_contract(contract_address).is_none() {
+            return Err(BlockchainError::ContractError { reason: format!("Smart contract {} not found", contract_address) });
         }
         
         Ok(format!("Called function {} in smart contract {}: {:?}", function, contract_address, args))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::encode_transaction;
+    use crate::shared::SharedBlockchain;
+    use crate::test_support::ChainFixture;
+    
+    #[test]
+    fn coinbase_is_credited_to_a_new_miner_wallet() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        assert!(chain.get_wallet_info("miner").is_none());
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("miner"), 2.0 * 100.0);
+        let rewards = chain.get_transaction_history("miner").iter().filter(|tx| tx.sender == "BLOCKCHAIN_REWARD").count();
+        assert_eq!(rewards, 2);
+        
+        let report = chain.rebuild_state_from_chain(false);
+        assert!(report.is_clean(), "{:?}", report.differences);
+        assert_eq!(chain.get_balance("miner"), 2.0 * 100.0);
+    }
+    
+    #[test]
+    fn second_create_wallet_fails_and_keeps_the_wallet() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let public_key = chain.get_wallet_info("alice").unwrap().public_key.clone();
+        assert!(public_key.is_some());
+        
+        let result = chain.create_wallet("alice".to_string()).map(|wallet| wallet.address.clone());
+        assert!(matches!(&result, Err(BlockchainError::DuplicateWallet { address }) if address == "alice"), "{:?}", result);
+        let wallet = chain.get_wallet_info("alice").unwrap();
+        assert_eq!(wallet.balance, 100.0);
+        assert_eq!(wallet.public_key, public_key);
+        assert!(chain.rebuild_state_from_chain(true).is_clean());
+    }
+    
+    #[test]
+    fn contract_created_at_a_funded_address_keeps_the_funds() {
+        use crate::block::GENESIS_TIMESTAMP;
+        use crate::clock::MockClock;
+        
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.set_clock(Arc::new(MockClock::new(GENESIS_TIMESTAMP + 100)));
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        
+        // Адрес контракта выводится из автора, кода и времени, поэтому его можно пополнить заранее
+        let address = format!("contract_{}", calculate_hash(chain.hash_algorithm, &format!("{}{}{}", "alice", "counter", GENESIS_TIMESTAMP + 100)));
+        let mut funding = Transaction::new("alice".to_string(), address.clone(), 20.0, TransactionType::Transfer);
+        funding.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut funding);
+        chain.add_transaction(funding).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        assert_eq!(chain.get_balance(&address), 20.0);
+        
+        assert_eq!(chain.create_smart_contract("alice".to_string(), "counter".to_string(), 5.0).unwrap(), address);
+        assert_eq!(chain.get_balance(&address), 20.0);
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        assert_eq!(chain.get_balance(&address), 25.0);
+        assert!(chain.find_smart_contract(&address).is_some());
+    }
+    
+    #[test]
+    fn block_creates_wallets_for_new_receivers() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut tx = Transaction::new("alice".to_string(), "dave".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        assert!(chain.get_wallet_info("dave").is_none());
+        
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        assert_eq!(chain.get_wallet_info("dave").unwrap().balance, 10.0);
+        assert!(chain.rebuild_state_from_chain(true).is_clean());
+    }
+    
+    #[test]
+    fn miner_receives_block_fees_once() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.fee = 2.5;
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        chain.add_transaction(tx).unwrap();
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("miner"), 100.0 + 2.5);
+        assert_eq!(chain.get_balance("alice"), 100.0 - 10.0 - 2.5);
+        chain.rebuild_state_from_chain(false);
+        assert_eq!(chain.get_balance("miner"), 100.0 + 2.5);
+    }
+    
+    #[test]
+    fn rebuild_keeps_node_keys_and_compares_closures_and_keys() {
+        use crate::entropy::OsEntropy;
+        
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let alice = chain.get_wallet_info("alice").unwrap().keypair().unwrap().public_key_hex();
+        // Адрес сети, импортированный только с открытым ключом
+        let imported = Wallet::generate(&OsEntropy, Network::Mainnet);
+        let imported_key = imported.public_key.clone().unwrap();
+        chain.import_wallet(&imported.address, Some(&imported_key)).unwrap();
+        
+        let report = chain.rebuild_state_from_chain(true);
+        assert!(report.is_clean(), "{:?}", report.differences);
+        
+        let wallet = chain.wallets.get_mut("alice").unwrap();
+        wallet.closed_at = Some(1);
+        wallet.public_key = None;
+        let report = chain.rebuild_state_from_chain(false);
+        let fields: Vec<&str> = report.differences.iter().map(|difference| difference.field).collect();
+        assert_eq!(fields, ["closed_at", "public_key"]);
+        assert!(report.applied);
+        
+        let wallet = chain.get_wallet_info("alice").unwrap();
+        assert_eq!(wallet.closed_at, None);
+        assert_eq!(wallet.public_key.as_deref(), Some(alice.as_str()));
+        assert_eq!(wallet.keypair().unwrap().public_key_hex(), alice);
+        assert_eq!(chain.get_wallet_info(&imported.address).unwrap().public_key.as_deref(), Some(imported_key.as_str()));
+    }
+    
+    #[test]
+    fn blocks_without_exactly_one_reward_are_rejected() {
+        let chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let mut producer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        let mined = producer.get_latest_block();
+        let reward = mined.transactions.last().unwrap().clone();
+        for transactions in [Vec::new(), [mined.transactions.clone(), vec![reward]].concat()] {
+            let mut block = mined.clone();
+            block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &transactions);
+            block.transactions = transactions;
+            block.mine_block();
+            let result = chain.verify_block(&block);
+            assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason.ends_with("must contain exactly one reward transaction")), "{:?}", result);
+        }
+    }
+    
+    #[test]
+    fn system_senders_cannot_enter_the_pool() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        for sender in ["BLOCKCHAIN_REWARD", TREASURY_MINTER, GOVERNANCE_ESCROW, BRIDGE_ESCROW] {
+            let tx = Transaction::new(sender.to_string(), "thief".to_string(), 100.0, TransactionType::Transfer);
+            assert!(chain.submit_raw_transaction(encode_transaction(&tx, chain.chain_id)).is_err(), "{}", sender);
+            let result = chain.add_transaction(tx);
+            assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.ends_with("created by the node")), "{:?}", result);
+        }
+        let shared = SharedBlockchain::new(chain);
+        let reward = Transaction::new("BLOCKCHAIN_REWARD".to_string(), "thief".to_string(), 100.0, TransactionType::Transfer);
+        assert!(matches!(shared.add_transaction(reward.clone()), Err(BlockchainError::InvalidTransaction(_))));
+        
+        // Награда, попавшая в пул в обход проверок, в блок не попадает: его награду строит производитель
+        let mut chain = shared.write();
+        chain.mempool.get_mut().transactions.push(reward);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let rewards = chain.get_latest_block().transactions.iter().filter(|tx| tx.sender == "BLOCKCHAIN_REWARD").count();
+        assert_eq!(rewards, 1);
+        assert_eq!(chain.get_balance("thief"), 0.0);
+        assert!(chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn chain_validation_requires_exactly_one_reward() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.is_chain_valid());
+        
+        let second = Transaction::new("BLOCKCHAIN_REWARD".to_string(), "thief".to_string(), 100.0, TransactionType::Transfer);
+        let block = chain.chain.last_mut().unwrap();
+        block.transactions.insert(0, second);
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.mine_block();
+        let result = chain.validate_chain(SignatureVerification::Serial);
+        assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason.ends_with("must contain exactly one reward transaction")), "{:?}", result);
+        assert!(!chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn peer_does_not_debit_system_senders() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("issuer".to_string()).unwrap();
+        chain.enable_minting("issuer").unwrap();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        chain.mint("alice", 50.0).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        assert_eq!(peer.accept_block(chain.get_latest_block().clone()).unwrap(), BlockAcceptance::Appended);
+        assert_eq!(peer.ledger, chain.ledger);
+        assert_eq!(peer.balance_audit_log, chain.balance_audit_log);
+        assert!(peer.balance_audit_log.keys().all(|address| !is_system_sender(address)));
+        assert_eq!(peer.get_balance("alice"), 50.0);
+    }
+    
+    #[test]
+    fn peer_and_rebuild_follow_governance_blocks() {
+        use crate::entropy::OsEntropy;
+        use crate::governance::{GovernanceConfig, ProposalAction, ProposalStatus};
+        
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.enable_governance(GovernanceConfig {
+            min_deposit: 10.0,
+            voting_period_blocks: 1,
+            activation_delay_blocks: 1,
+            quorum: 0.0,
+            threshold: 0.5,
+        }).unwrap();
+        let proposer = chain.add_wallet(Wallet::generate(&OsEntropy, Network::Mainnet)).unwrap().address.clone();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        chain.mine_pending_transactions(proposer.clone()).unwrap();
+        let proposal_id = chain.submit_proposal(&proposer, ProposalAction::Signal("upgrade".to_string()), 10.0).unwrap();
+        // Блок с предложением, конец голосования без голосов и блок с итогом, возвращающим залог
+        for _ in 0..3 {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        
+        for block in chain.chain.iter().skip(1).cloned() {
+            assert_eq!(peer.accept_block(block).unwrap(), BlockAcceptance::Appended);
+        }
+        assert_eq!(chain.proposal(&proposal_id).unwrap().status, ProposalStatus::Rejected);
+        assert_eq!(peer.proposal(&proposal_id).unwrap().status, ProposalStatus::Rejected);
+        for address in [proposer.as_str(), GOVERNANCE_ESCROW, "miner"] {
+            assert_eq!(peer.get_balance(address), chain.get_balance(address), "{}", address);
+        }
+        assert_eq!(chain.get_balance(GOVERNANCE_ESCROW), 0.0);
+        
+        let report = chain.rebuild_state_from_chain(true);
+        assert!(report.is_clean(), "{:?}", report.differences);
+        let report = peer.rebuild_state_from_chain(true);
+        assert!(report.is_clean(), "{:?}", report.differences);
+        assert_eq!(peer.proposal(&proposal_id).unwrap().status, ProposalStatus::Rejected);
+    }
+    
+    #[test]
+    fn mining_failure_leaves_the_mempool_untouched() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfStake);
+        for amount in [3.0, 1.0, 2.0] {
+            let tx = Transaction::new("alice".to_string(), "bob".to_string(), amount, TransactionType::Transfer);
+            chain.mempool.get_mut().transactions.push(tx);
+        }
+        let before: Vec<String> = chain.mempool().transactions().iter().map(|tx| tx.id.clone()).collect();
+        
+        // Печать не находится уже после отбора транзакций в блок
+        let result = chain.mine_pending_transactions("stranger".to_string());
+        assert!(matches!(result, Err(BlockchainError::ValidatorNotFound { .. })));
+        let after: Vec<String> = chain.mempool().transactions().iter().map(|tx| tx.id.clone()).collect();
+        assert_eq!(after, before);
+        assert_eq!(chain.chain.len(), 1);
+    }
+    
+    #[test]
+    fn failed_pos_attempts_do_not_stack_rewards() {
+        use crate::clock::MockClock;
+        
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfStake);
+        let clock = Arc::new(MockClock::new(chain.get_latest_block().timestamp + 3600));
+        chain.set_clock(clock.clone());
+        for address in ["alice", "validator"] {
+            chain.create_wallet(address.to_string()).unwrap();
+        }
+        chain.allocate_at_genesis("alice", 50.0).unwrap();
+        chain.allocate_at_genesis("validator", 1000.0).unwrap();
+        // С нулевой ставкой жребий не проходит никогда
+        chain.add_validator("validator".to_string(), 0.0).unwrap();
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        let (transfer_id, fee) = (tx.id.clone(), tx.fee);
+        chain.add_transaction(tx).unwrap();
+        let before: Vec<String> = chain.mempool().transactions().iter().map(|tx| tx.id.clone()).collect();
+        
+        for _ in 0..3 {
+            let result = chain.mine_pending_transactions("validator".to_string());
+            assert!(matches!(result, Err(BlockchainError::ConsensusError(_))), "{:?}", result);
+            let after: Vec<String> = chain.mempool().transactions().iter().map(|tx| tx.id.clone()).collect();
+            assert_eq!(after, before);
+            assert_eq!(chain.get_balance("alice"), 50.0 - 10.0 - fee);
+            assert_eq!(chain.get_balance("validator"), 1000.0);
+            clock.advance(60);
+        }
+        
+        chain.remove_validator("validator").unwrap();
+        chain.add_validator("validator".to_string(), 1000.0).unwrap();
+        chain.mine_pending_transactions("validator".to_string()).unwrap();
+        let block = chain.get_latest_block();
+        assert_eq!(block.index, 1);
+        assert_eq!(block.transactions.iter().filter(|tx| tx.sender == "BLOCKCHAIN_REWARD").count(), 1);
+        let mined: Vec<String> = block.transactions.iter().filter(|tx| tx.sender != "BLOCKCHAIN_REWARD").map(|tx| tx.id.clone()).collect();
+        assert!(mined.contains(&transfer_id));
+        assert_eq!(mined.len(), before.len());
+        assert!(chain.mempool().is_empty());
+        assert_eq!(chain.get_balance("alice"), 50.0 - 10.0 - fee);
+        assert_eq!(chain.get_balance("bob"), 10.0);
+        assert_eq!(chain.get_balance("validator"), 100.0 + fee);
+        let report = chain.rebuild_state_from_chain(true);
+        assert!(report.is_clean(), "{:?}", report.differences);
+    }
+    
+    /// Цепочка, у которой в вершине 500 подписанных переводов
+    fn chain_with_signed_block() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 1000.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        for i in 0..500 {
+            let mut tx = Transaction::new("alice".to_string(), format!("user{}", i), 1.0, TransactionType::Transfer);
+            tx.set_nonce(chain.get_account_nonce("alice"));
+            tx.sign(&keypair);
+            chain.add_transaction(tx).unwrap();
+        }
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        assert_eq!(chain.get_latest_block().transactions.len(), 501);
+        chain
+    }
+    
+    /// Портит подпись транзакции вершины и заново запечатывает блок, чтобы дошло до проверки подписей
+    fn corrupt_signature(chain: &mut Blockchain, position: usize) -> String {
+        let block = chain.chain.last_mut().unwrap();
+        let tx = block.transactions.get_mut(position).unwrap();
+        let flipped = if tx.signature.starts_with('0') { "1" } else { "0" };
+        tx.signature.replace_range(..1, flipped);
+        let tx_id = tx.id.clone();
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.mine_block();
+        tx_id
+    }
+    
+    #[test]
+    fn serial_and_parallel_validation_agree() {
+        let mut chain = chain_with_signed_block();
+        chain.validate_chain(SignatureVerification::Serial).unwrap();
+        chain.validate_chain(SignatureVerification::Parallel).unwrap();
+        
+        // Из двух испорченных подписей обе проверки называют первую по порядку в блоке
+        let later = corrupt_signature(&mut chain, 480);
+        let first = corrupt_signature(&mut chain, 12);
+        for verification in [SignatureVerification::Serial, SignatureVerification::Parallel] {
+            let error = chain.validate_chain(verification).unwrap_err().to_string();
+            assert!(error.contains(&first) && !error.contains(&later), "{:?}: {}", verification, error);
+            let result = verify_signatures(&chain.get_latest_block().transactions, verification, false);
+            assert!(matches!(&result, Err(BlockchainError::InvalidSignature { tx_id }) if *tx_id == first), "{:?}: {:?}", verification, result);
+        }
+    }
+    
+    /// PoS-цепочка с валидатором и часами, стоящими через `offset` секунд после генезиса
+    fn stake_chain(min_block_interval: u64, offset: i64) -> (Blockchain, Arc<crate::clock::MockClock>) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfStake);
+        chain.params.stake_params = Some(StakeParams { min_block_interval, slot_duration: 60 });
+        let clock = Arc::new(crate::clock::MockClock::new(chain.get_latest_block().timestamp + offset));
+        chain.set_clock(clock.clone());
+        chain.create_wallet("validator".to_string()).unwrap();
+        chain.allocate_at_genesis("validator", 1000.0).unwrap();
+        chain.add_validator("validator".to_string(), 1000.0).unwrap();
+        (chain, clock)
+    }
+    
+    #[test]
+    fn stake_chains_never_retarget() {
+        let (mut chain, clock) = stake_chain(1, 1);
+        assert_eq!(chain.difficulty, 0);
+        let target = chain.target;
+        // Блоки идут с наименьшим интервалом, PoW-цепочка на таком темпе подняла бы сложность
+        for _ in 0..2 * RETARGET_INTERVAL {
+            chain.mine_pending_transactions("validator".to_string()).unwrap();
+            clock.advance(1);
+        }
+        chain.adjust_difficulty();
+        assert_eq!((chain.difficulty, chain.target), (0, target));
+        assert!(chain.chain.iter().all(|block| block.difficulty == 0 && block.nonce == 0));
+        assert!(chain.is_chain_valid());
+        
+        // У PoW-цепочки сложность прежняя, а параметров выпуска PoS нет
+        let pow = ChainFixture::chain(2, 100.0, ConsensusAlgorithm::ProofOfWork);
+        assert_eq!((pow.difficulty, pow.stake_params()), (2, None));
+        assert_eq!(pow.get_latest_block().difficulty, 2);
+    }
+    
+    #[test]
+    fn peer_stake_block_inside_the_interval_is_rejected() {
+        let (mut chain, _clock) = stake_chain(30, 0);
+        // Узел с нулевым интервалом выпускает блок через 5 секунд после генезиса
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        peer.params.stake_params = Some(StakeParams { min_block_interval: 0, slot_duration: 60 });
+        let peer_clock = Arc::new(crate::clock::MockClock::new(chain.get_latest_block().timestamp + 5));
+        peer.set_clock(peer_clock.clone());
+        peer.mine_pending_transactions("validator".to_string()).unwrap();
+        let early = peer.get_latest_block().clone();
+        
+        let result = chain.accept_block(early);
+        assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason.contains("comes 5 s after its parent; the minimum interval is 30 s")), "{:?}", result);
+        assert_eq!(chain.chain.len(), 1);
+        
+        // Тот же узел, выдержав интервал, выпускает блок, который принимается
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        peer_clock.advance(25);
+        peer.set_clock(peer_clock);
+        peer.mine_pending_transactions("validator".to_string()).unwrap();
+        assert!(matches!(chain.accept_block(peer.get_latest_block().clone()), Ok(BlockAcceptance::Appended)));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::hashing::HashKind;
+use crate::transaction::hash_bytes;
+use std::collections::HashSet;
+
+/// Доля ложных срабатываний фильтра адресов блока по умолчанию
+pub const DEFAULT_ADDRESS_FILTER_RATE: f64 = 0.01;
+
+/// Фильтр Блума по строкам: ложные срабатывания возможны с заданной вероятностью, пропуски — нет
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// Подбирает размер и число хеш-функций под `items` элементов и долю ложных срабатываний
+    pub fn new(items: usize, false_positive_rate: f64) -> Self {
+        let items = items.max(1) as f64;
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = next_prime(((-items * rate.ln() / (ln2 * ln2)).ceil() as u64).max(64));
+        let hashes = ((bit_count as f64 / items * ln2).round() as u32).clamp(1, 32);
+        
+        BloomFilter { bits: vec![0; bit_count.div_ceil(64) as usize], bit_count, hashes }
+    }
+    
+    pub fn insert(&mut self, item: &str) {
+        for bit in positions(item, self.bit_count, self.hashes) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+    
+    pub fn may_contain(&self, item: &str) -> bool {
+        positions(item, self.bit_count, self.hashes).all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+}
+
+/// Наименьшее простое число не меньше `n`. При числе битов, равном степени двойки, позиции
+/// зависят только от младших битов хеша и у разных адресов часто совпадают целиком
+fn next_prime(n: u64) -> u64 {
+    let is_prime = |n: u64| n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d));
+    (n..).find(|&n| is_prime(n)).unwrap_or(n)
+}
+
+/// Позиции битов по двойному хешированию из одного SHA-256: арифметическая прогрессия по модулю
+/// простого числа битов с ненулевым шагом, поэтому все позиции одного адреса различны
+fn positions(item: &str, bit_count: u64, hashes: u32) -> impl Iterator<Item = u64> {
+    let hash = hash_bytes(HashKind::Sha256, item.as_bytes());
+    let first = u64::from_be_bytes(hash[..8].try_into().unwrap_or_default()) % bit_count;
+    let step = 1 + u64::from_be_bytes(hash[8..16].try_into().unwrap_or_default()) % (bit_count - 1);
+    (0..hashes as u64).map(move |i| (first + i * step) % bit_count)
+}
+
+/// Фильтры адресов отправителей и получателей по высотам блоков
+#[derive(Debug, Clone, Default)]
+pub struct AddressIndex {
+    filters: Vec<BloomFilter>,
+}
+
+impl AddressIndex {
+    pub fn build(blocks: &[Block], false_positive_rate: f64) -> Self {
+        let mut index = AddressIndex::default();
+        for block in blocks {
+            index.push(block, false_positive_rate);
+        }
+        index
+    }
+    
+    /// Добавляет фильтр очередного блока
+    pub fn push(&mut self, block: &Block, false_positive_rate: f64) {
+        let addresses: HashSet<&str> = block.transactions.iter()
+            .flat_map(|tx| [tx.sender.as_str(), tx.receiver.as_str()])
+            .collect();
+        
+        let mut filter = BloomFilter::new(addresses.len(), false_positive_rate);
+        for address in addresses {
+            filter.insert(address);
+        }
+        self.filters.push(filter);
+    }
+    
+    /// Число блоков, для которых построены фильтры
+    pub(crate) fn filter_count(&self) -> usize {
+        self.filters.len()
+    }
+    
+    /// Может ли блок на этой высоте затрагивать адрес; блоки без фильтра считаются затрагивающими
+    pub fn may_involve(&self, height: usize, address: &str) -> bool {
+        self.filters.get(height).is_none_or(|filter| filter.may_contain(address))
+    }
+}
+
+impl Blockchain {
+    /// Высоты блоков, которые могут затрагивать адрес как отправителя или получателя.
+    /// Смотрит только фильтры, поэтому среди высот бывают лишние, но нужные не пропускаются.
+    pub fn blocks_possibly_involving(&self, address: &str) -> Vec<u64> {
+        (0..self.chain.len())
+            .filter(|height| self.address_index.may_involve(*height, address))
+            .map(|height| height as u64)
+            .collect()
+    }
+    
+    /// Меняет долю ложных срабатываний фильтров адресов и пересобирает их
+    pub fn set_address_filter_rate(&mut self, false_positive_rate: f64) {
+        self.address_filter_rate = false_positive_rate;
+        self.rebuild_address_index();
+    }
+    
+    /// Пересобирает фильтры адресов по всей цепочке, например после загрузки с диска
+    pub fn rebuild_address_index(&mut self) {
+        self.address_index = AddressIndex::build(&self.chain, self.address_filter_rate);
+    }
+    
+    /// Добавляет фильтр адресов только что добавленного блока
+    pub(crate) fn index_latest_block(&mut self) {
+        let height = self.chain.len() - 1;
+        if self.address_index.filters.len() == height {
+            self.address_index.push(&self.chain[height], self.address_filter_rate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::entropy::{EntropySource, SeededEntropy};
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    
+    fn random(entropy: &SeededEntropy, bound: u64) -> u64 {
+        let mut bytes = [0; 8];
+        entropy.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes) % bound
+    }
+    
+    /// Цепочка из 60 блоков: майнер `hub` рассылает случайным из 40 адресов по 1–4 перевода в блок,
+    /// а `rare` получает средства только в блоках 20 и 45
+    fn random_chain(seed: u64) -> Blockchain {
+        let entropy = SeededEntropy::new(seed);
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("hub".to_string()).unwrap();
+        chain.mine_pending_transactions("hub".to_string()).unwrap();
+        
+        for height in 2..=60 {
+            let mut receivers: Vec<String> = (0..=random(&entropy, 3)).map(|_| format!("user{}", random(&entropy, 40))).collect();
+            if height == 20 || height == 45 {
+                receivers.push("rare".to_string());
+            }
+            for receiver in receivers {
+                let mut tx = Transaction::new("hub".to_string(), receiver, 1.0, TransactionType::Transfer);
+                chain.sign_as_sender(&mut tx);
+                chain.add_transaction(tx).unwrap();
+            }
+            chain.mine_pending_transactions("hub".to_string()).unwrap();
+        }
+        chain
+    }
+    
+    fn scanned_history(chain: &Blockchain, address: &str) -> Vec<String> {
+        chain.chain.iter()
+            .flat_map(|block| &block.transactions)
+            .filter(|tx| tx.sender == address || tx.receiver == address)
+            .map(|tx| tx.id.clone())
+            .collect()
+    }
+    
+    #[test]
+    fn filtered_history_matches_a_full_scan() {
+        for seed in 1..=3 {
+            let chain = random_chain(seed);
+            let addresses = ["hub", "rare", "nobody"].map(String::from).into_iter().chain((0..40).map(|i| format!("user{}", i)));
+            for address in addresses {
+                let involved: Vec<u64> = chain.chain.iter()
+                    .filter(|block| block.transactions.iter().any(|tx| tx.sender == address || tx.receiver == address))
+                    .map(|block| block.index)
+                    .collect();
+                let candidates = chain.blocks_possibly_involving(&address);
+                assert!(involved.iter().all(|height| candidates.contains(height)), "seed {}, {}", seed, address);
+                
+                let history: Vec<String> = chain.get_transaction_history(&address).into_iter().map(|tx| tx.id).collect();
+                assert_eq!(history, scanned_history(&chain, &address), "seed {}, {}", seed, address);
+            }
+        }
+    }
+    
+    #[test]
+    fn sparse_address_touches_few_block_bodies() {
+        let chain = random_chain(7);
+        let candidates = chain.blocks_possibly_involving("rare");
+        assert!(candidates.contains(&20) && candidates.contains(&45));
+        // При доле ложных срабатываний 1% лишних тел почти не бывает
+        assert!(candidates.len() <= 5, "{} of {} blocks", candidates.len(), chain.chain.len());
+        assert!(chain.blocks_possibly_involving("nobody").len() <= 3);
+    }
+    
+    #[test]
+    fn false_positive_rate_follows_the_configuration() {
+        for rate in [0.01, 0.1] {
+            let mut filter = BloomFilter::new(1000, rate);
+            for i in 0..1000 {
+                filter.insert(&format!("member{}", i));
+            }
+            assert!((0..1000).all(|i| filter.may_contain(&format!("member{}", i))));
+            
+            let false_positives = (0..10_000).filter(|i| filter.may_contain(&format!("stranger{}", i))).count();
+            let observed = false_positives as f64 / 10_000.0;
+            assert!(observed < 2.0 * rate, "rate {}: observed {}", rate, observed);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::shared::SharedBlockchain;
+use crate::transaction::{Transaction, TransactionType};
+
+/// Отправитель транзакций выпуска: средства появляются без списания с кошелька
+pub const BRIDGE_MINTER: &str = "BLOCKCHAIN_BRIDGE";
+/// Адрес, на котором мост держит заблокированные средства
+pub const BRIDGE_ESCROW: &str = "BRIDGE_ESCROW";
+/// Адрес для сжигания обернутых средств; потратить с него ничего нельзя
+pub const BRIDGE_BURN: &str = "BRIDGE_BURN";
+
+/// Сторона моста
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChainId {
+    A,
+    B,
+}
+
+impl ChainId {
+    pub fn other(self) -> ChainId {
+        match self {
+            ChainId::A => ChainId::B,
+            ChainId::B => ChainId::A,
+        }
+    }
+    
+    fn index(self) -> usize {
+        match self {
+            ChainId::A => 0,
+            ChainId::B => 1,
+        }
+    }
+}
+
+/// Что произошло в исходной цепочке
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayKind {
+    /// Средства заблокированы в эскроу, в другой цепочке нужно выпустить обернутые
+    Lock,
+    /// Обернутые средства сожжены, в другой цепочке нужно вернуть их из эскроу
+    Burn,
+}
+
+/// Событие для релея: транзакция в исходной цепочке и кому зачислить средства в другой
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayEvent {
+    pub source: ChainId,
+    pub source_tx_id: String,
+    pub recipient: String,
+    pub amount: f64,
+    pub kind: RelayKind,
+}
+
+/// Мост между двумя цепочками в одном процессе
+pub struct Bridge {
+    chains: [SharedBlockchain; 2],
+    relayers: [String; 2],
+    confirmations: u64,
+    pending: Vec<RelayEvent>,
+}
+
+impl Blockchain {
+    /// Проверяет правила для транзакций моста и адресов эскроу, выпуска и сжигания
+    pub(crate) fn check_bridge_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        let relayer = match &tx.transaction_type {
+            TransactionType::BridgeMint { relayer, .. } if tx.sender == BRIDGE_MINTER => Some(relayer),
+            TransactionType::BridgeRelease { relayer, .. } if tx.sender == BRIDGE_ESCROW => Some(relayer),
+            TransactionType::BridgeMint { .. } | TransactionType::BridgeRelease { .. } => {
+                return Err(BlockchainError::InvalidTransaction(format!("Bridge transaction {} has a wrong sender", tx.id)));
+            },
+            _ if tx.sender == BRIDGE_MINTER || tx.sender == BRIDGE_ESCROW || tx.sender == BRIDGE_BURN => {
+                return Err(BlockchainError::InvalidTransaction(format!("{} can only be spent by the bridge", tx.sender)));
+            },
+            _ => None,
+        };
+        
+        if let Some(relayer) = relayer {
+            if !self.bridge_relayers.contains(relayer) {
+                return Err(BlockchainError::InvalidTransaction(format!("{} is not a bridge relayer", relayer)));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Есть ли уже в блоках или в пуле транзакция моста для исходной транзакции
+    fn has_bridge_transaction_for(&self, source_tx_id: &str) -> bool {
+        self.chain.iter()
+            .flat_map(|block| &block.transactions)
+            .chain(&self.mempool.lock().transactions)
+            .any(|tx| match &tx.transaction_type {
+                TransactionType::BridgeMint { source_tx_id: id, .. } | TransactionType::BridgeRelease { source_tx_id: id, .. } => id == source_tx_id,
+                _ => false,
+            })
+    }
+    
+    /// Число подтверждений транзакции: 1, если она в последнем блоке
+    fn confirmations_of(&self, tx_id: &str) -> Option<(u64, &Transaction)> {
+        let tip = self.get_latest_block().index;
+        self.chain.iter().find_map(|block| {
+            block.transactions.iter()
+                .find(|tx| tx.id == tx_id)
+                .map(|tx| (tip - block.index + 1, tx))
+        })
+    }
+}
+
+impl Bridge {
+    /// Создает мост и регистрирует релеера в каждой из цепочек
+    pub fn new(chain_a: SharedBlockchain, chain_b: SharedBlockchain, relayer_wallets: [String; 2]) -> Self {
+        chain_a.write().bridge_relayers.insert(relayer_wallets[0].clone());
+        chain_b.write().bridge_relayers.insert(relayer_wallets[1].clone());
+        
+        Bridge {
+            chains: [chain_a, chain_b],
+            relayers: relayer_wallets,
+            confirmations: 1,
+            pending: Vec::new(),
+        }
+    }
+    
+    /// Задает, сколько подтверждений нужно исходной транзакции до релея
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+    
+    pub fn chain(&self, id: ChainId) -> &SharedBlockchain {
+        &self.chains[id.index()]
+    }
+    
+    /// События, ожидающие подтверждений
+    pub fn pending_events(&self) -> &[RelayEvent] {
+        &self.pending
+    }
+    
+    /// Блокирует средства в эскроу исходной цепочки; обернутые будут выпущены после подтверждений
+    pub fn transfer(&mut self, from: ChainId, sender: &str, recipient: &str, amount: f64) -> Result<String, BlockchainError> {
+        let mut chain = self.chain(from).write();
+        let mut tx = chain.new_transaction(sender.to_string(), BRIDGE_ESCROW.to_string(), amount, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx)?;
+        drop(chain);
+        
+        self.pending.push(RelayEvent {
+            source: from,
+            source_tx_id: tx_id.clone(),
+            recipient: recipient.to_string(),
+            amount,
+            kind: RelayKind::Lock,
+        });
+        Ok(tx_id)
+    }
+    
+    /// Сжигает обернутые средства в цепочке `on`; эскроу в другой цепочке вернет их после подтверждений
+    pub fn redeem(&mut self, on: ChainId, holder: &str, recipient: &str, amount: f64) -> Result<String, BlockchainError> {
+        let escrow = self.chain(on.other()).read().get_balance(BRIDGE_ESCROW);
+        let reserved: f64 = self.pending.iter()
+            .filter(|event| event.kind == RelayKind::Burn && event.source == on)
+            .map(|event| event.amount)
+            .sum();
+        if amount > escrow - reserved {
+            return Err(BlockchainError::InsufficientBalance { required: amount, available: escrow - reserved });
+        }
+        
+        let mut chain = self.chain(on).write();
+        let mut tx = chain.new_transaction(holder.to_string(), BRIDGE_BURN.to_string(), amount, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx)?;
+        drop(chain);
+        
+        self.pending.push(RelayEvent {
+            source: on,
+            source_tx_id: tx_id.clone(),
+            recipient: recipient.to_string(),
+            amount,
+            kind: RelayKind::Burn,
+        });
+        Ok(tx_id)
+    }
+    
+    /// Релеит все события, набравшие нужное число подтверждений; возвращает созданные транзакции
+    pub fn relay(&mut self) -> Result<Vec<String>, BlockchainError> {
+        let mut relayed = Vec::new();
+        let mut waiting = Vec::new();
+        
+        for event in std::mem::take(&mut self.pending) {
+            let confirmed = self.chain(event.source).read().confirmations_of(&event.source_tx_id)
+                .is_some_and(|(confirmations, _)| confirmations >= self.confirmations);
+            if !confirmed {
+                waiting.push(event);
+                continue;
+            }
+            
+            match self.relay_event(&event) {
+                Ok(Some(tx_id)) => relayed.push(tx_id),
+                Ok(None) => {},
+                Err(e) => {
+                    waiting.push(event);
+                    waiting.extend(std::mem::take(&mut self.pending));
+                    self.pending = waiting;
+                    return Err(e);
+                },
+            }
+        }
+        
+        self.pending = waiting;
+        Ok(relayed)
+    }
+    
+    /// Исполняет одно событие в целевой цепочке; повторное событие ничего не выпускает
+    pub fn relay_event(&self, event: &RelayEvent) -> Result<Option<String>, BlockchainError> {
+        let expected_receiver = match event.kind {
+            RelayKind::Lock => BRIDGE_ESCROW,
+            RelayKind::Burn => BRIDGE_BURN,
+        };
+        
+        {
+            let source = self.chain(event.source).read();
+            let (confirmations, tx) = source.confirmations_of(&event.source_tx_id)
+                .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Source transaction {} is not confirmed", event.source_tx_id)))?;
+            if confirmations < self.confirmations {
+                return Err(BlockchainError::InvalidTransaction(format!("Source transaction {} has {} of {} confirmations", event.source_tx_id, confirmations, self.confirmations)));
+            }
+            if tx.receiver != expected_receiver || (tx.amount - event.amount).abs() > 1e-9 {
+                return Err(BlockchainError::InvalidTransaction(format!("Source transaction {} does not match the relay event", event.source_tx_id)));
+            }
+        }
+        
+        let target = event.source.other();
+        let relayer = self.relayers[target.index()].clone();
+        let mut chain = self.chain(target).write();
+        if chain.has_bridge_transaction_for(&event.source_tx_id) {
+            return Ok(None);
+        }
+        
+        let (sender, transaction_type) = match event.kind {
+            RelayKind::Lock => (BRIDGE_MINTER, TransactionType::BridgeMint { source_tx_id: event.source_tx_id.clone(), relayer: relayer.clone() }),
+            RelayKind::Burn => (BRIDGE_ESCROW, TransactionType::BridgeRelease { source_tx_id: event.source_tx_id.clone(), relayer: relayer.clone() }),
+        };
+        let mut tx = chain.new_transaction(sender.to_string(), event.recipient.clone(), event.amount, transaction_type);
+        tx.id = tx.expected_id();
+        let tx_id = tx.id.clone();
+        chain.queue_protocol_transaction(tx)?;
+        
+        Ok(Some(tx_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    /// Мост между цепочкой A, где у `alice` есть награда, и цепочкой B с кошельком `bob`
+    fn bridge() -> Bridge {
+        let mut chain_a = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain_a.create_wallet("alice".to_string()).unwrap();
+        chain_a.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut chain_b = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain_b.create_wallet("bob".to_string()).unwrap();
+        chain_b.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        Bridge::new(chain_a.into(), chain_b.into(), ["relayer_a".to_string(), "relayer_b".to_string()]).with_confirmations(2)
+    }
+    
+    fn mine(bridge: &Bridge, id: ChainId) {
+        bridge.chain(id).write().mine_pending_transactions("miner".to_string()).unwrap();
+    }
+    
+    fn balance(bridge: &Bridge, id: ChainId, address: &str) -> f64 {
+        bridge.chain(id).read().get_balance(address)
+    }
+    
+    #[test]
+    fn round_trip_keeps_escrow_equal_to_wrapped_supply() {
+        let mut bridge = bridge();
+        bridge.transfer(ChainId::A, "alice", "bob", 30.0).unwrap();
+        mine(&bridge, ChainId::A);
+        assert!(bridge.relay().unwrap().is_empty());
+        mine(&bridge, ChainId::A);
+        assert_eq!(bridge.relay().unwrap().len(), 1);
+        mine(&bridge, ChainId::B);
+        assert_eq!(balance(&bridge, ChainId::A, BRIDGE_ESCROW), 30.0);
+        assert_eq!(balance(&bridge, ChainId::B, "bob"), 30.0);
+        
+        bridge.redeem(ChainId::B, "bob", "alice", 20.0).unwrap();
+        let alice_before = balance(&bridge, ChainId::A, "alice");
+        mine(&bridge, ChainId::B);
+        mine(&bridge, ChainId::B);
+        assert_eq!(bridge.relay().unwrap().len(), 1);
+        mine(&bridge, ChainId::A);
+        
+        assert_eq!(balance(&bridge, ChainId::A, "alice"), alice_before + 20.0);
+        assert_eq!(balance(&bridge, ChainId::A, BRIDGE_ESCROW), 10.0);
+        // Обернутые средства вне адреса сжигания равны остатку в эскроу
+        assert_eq!(balance(&bridge, ChainId::B, BRIDGE_BURN), 20.0);
+        assert!(bridge.pending_events().is_empty());
+        assert!(bridge.chain(ChainId::A).read().is_chain_valid());
+        assert!(bridge.chain(ChainId::B).read().is_chain_valid());
+    }
+    
+    #[test]
+    fn redemption_above_the_escrow_is_refused() {
+        let mut bridge = bridge();
+        bridge.transfer(ChainId::A, "alice", "bob", 30.0).unwrap();
+        mine(&bridge, ChainId::A);
+        mine(&bridge, ChainId::A);
+        bridge.relay().unwrap();
+        mine(&bridge, ChainId::B);
+        bridge.chain(ChainId::B).write().enable_minting("bob").unwrap();
+        bridge.chain(ChainId::B).write().mint("bob", 50.0).unwrap();
+        mine(&bridge, ChainId::B);
+        
+        let error = bridge.redeem(ChainId::B, "bob", "alice", 31.0).unwrap_err();
+        assert!(matches!(error, BlockchainError::InsufficientBalance { required: 31.0, available: 30.0 }), "{}", error);
+        bridge.redeem(ChainId::B, "bob", "alice", 20.0).unwrap();
+        let error = bridge.redeem(ChainId::B, "bob", "alice", 20.0).unwrap_err();
+        assert!(matches!(error, BlockchainError::InsufficientBalance { required: 20.0, available: 10.0 }), "{}", error);
+    }
+    
+    #[test]
+    fn replayed_relay_mints_nothing() {
+        let mut bridge = bridge();
+        bridge.transfer(ChainId::A, "alice", "bob", 30.0).unwrap();
+        let event = bridge.pending_events()[0].clone();
+        mine(&bridge, ChainId::A);
+        assert!(bridge.relay_event(&event).is_err());
+        mine(&bridge, ChainId::A);
+        
+        assert!(bridge.relay_event(&event).unwrap().is_some());
+        assert_eq!(bridge.relay_event(&event).unwrap(), None);
+        assert!(bridge.relay().unwrap().is_empty());
+        mine(&bridge, ChainId::B);
+        assert_eq!(bridge.relay_event(&event).unwrap(), None);
+        mine(&bridge, ChainId::B);
+        assert_eq!(balance(&bridge, ChainId::B, "bob"), 30.0);
+    }
+}
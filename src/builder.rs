@@ -0,0 +1,110 @@
+use crate::blockchain::{Blockchain, ConsensusAlgorithm, TARGET_BLOCK_TIME};
+use crate::errors::BlockchainError;
+use crate::features::Feature;
+use crate::target::MAX_DIFFICULTY;
+use crate::utxo::LedgerMode;
+use std::collections::BTreeSet;
+
+const DEFAULT_DIFFICULTY: usize = 2;
+const DEFAULT_MINING_REWARD: f64 = 100.0;
+
+/// Настройка новой цепочки по шагам вместо позиционного `Blockchain::new`; значения проверяются в `build`
+#[derive(Debug, Clone)]
+pub struct BlockchainBuilder {
+    difficulty: usize,
+    mining_reward: f64,
+    consensus: ConsensusAlgorithm,
+    target_block_time: u64,
+    ledger_mode: LedgerMode,
+    accept_legacy_addresses: bool,
+    disabled_features: BTreeSet<Feature>,
+}
+
+impl Default for BlockchainBuilder {
+    fn default() -> Self {
+        BlockchainBuilder {
+            difficulty: DEFAULT_DIFFICULTY,
+            mining_reward: DEFAULT_MINING_REWARD,
+            consensus: ConsensusAlgorithm::ProofOfWork,
+            target_block_time: TARGET_BLOCK_TIME as u64,
+            ledger_mode: LedgerMode::Account,
+            accept_legacy_addresses: false,
+            disabled_features: BTreeSet::new(),
+        }
+    }
+}
+
+impl BlockchainBuilder {
+    /// PoW-цепочка со сложностью 2, наградой 100 и блоком раз в минуту
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Сложность PoW от 1 до `MAX_DIFFICULTY`; у PoS и DPoS не используется
+    pub fn difficulty(mut self, difficulty: usize) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+    
+    /// Награда за блок на высоте 1
+    pub fn mining_reward(mut self, mining_reward: f64) -> Self {
+        self.mining_reward = mining_reward;
+        self
+    }
+    
+    /// Алгоритм консенсуса, по умолчанию PoW
+    pub fn consensus(mut self, consensus: ConsensusAlgorithm) -> Self {
+        self.consensus = consensus;
+        self
+    }
+    
+    /// Целевое время между блоками в секундах: по нему PoW пересчитывает сложность,
+    /// а у PoS и DPoS это длина слота
+    pub fn target_block_time(mut self, secs: u64) -> Self {
+        self.target_block_time = secs;
+        self
+    }
+    
+    /// Учет средств: балансы счетов (по умолчанию) или непотраченные выходы; UTXO-режим возможен только у PoW
+    pub fn ledger_mode(mut self, ledger_mode: LedgerMode) -> Self {
+        self.ledger_mode = ledger_mode;
+        self
+    }
+    
+    /// Принимать ли адреса без префикса сети, например демо-имена вроде `alice`; по умолчанию нет
+    pub fn accept_legacy_addresses(mut self, accept: bool) -> Self {
+        self.accept_legacy_addresses = accept;
+        self
+    }
+    
+    /// Выключает возможность цепочки; после генезиса ее включает только голосование
+    pub fn disable_feature(mut self, feature: Feature) -> Self {
+        self.disabled_features.insert(feature);
+        self
+    }
+    
+    /// Проверяет параметры и создает цепочку с генезис-блоком
+    pub fn build(self) -> Result<Blockchain, BlockchainError> {
+        if self.consensus == ConsensusAlgorithm::ProofOfWork && !(1..=MAX_DIFFICULTY).contains(&self.difficulty) {
+            return Err(BlockchainError::InvalidConfig(format!("Difficulty {} is not between 1 and {}", self.difficulty, MAX_DIFFICULTY)));
+        }
+        if !self.mining_reward.is_finite() || self.mining_reward < 0.0 {
+            return Err(BlockchainError::InvalidConfig(format!("Mining reward {} is invalid", self.mining_reward)));
+        }
+        if self.target_block_time == 0 || self.target_block_time > i64::MAX as u64 {
+            return Err(BlockchainError::InvalidConfig(format!("Target block time {} s is invalid", self.target_block_time)));
+        }
+        
+        let mut chain = Blockchain::new(self.difficulty, self.mining_reward, self.consensus);
+        chain.accept_legacy_addresses = self.accept_legacy_addresses;
+        chain.params.target_block_time = self.target_block_time as f64;
+        chain.params.disabled_features = self.disabled_features;
+        if let Some(stake_params) = chain.params.stake_params.as_mut() {
+            stake_params.slot_duration = self.target_block_time;
+        }
+        if self.ledger_mode == LedgerMode::Utxo {
+            chain.enable_utxo_mode()?;
+        }
+        Ok(chain)
+    }
+}
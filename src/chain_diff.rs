@@ -0,0 +1,292 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::supply::SUPPLY_TOLERANCE;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+/// Высота и хеш блока
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockRef {
+    pub height: u64,
+    pub hash: String,
+}
+
+impl BlockRef {
+    fn of(block: &Block) -> Self {
+        BlockRef { height: block.index, hash: block.hash.clone() }
+    }
+}
+
+/// Параметр цепочки с разными значениями; отсутствующий параметр равен null
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamDifference {
+    pub name: String,
+    pub ours: Value,
+    pub theirs: Value,
+}
+
+/// Все средства адреса (баланс, стейк и невостребованные награды) после общего блока
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceDifference {
+    pub address: String,
+    pub ours: f64,
+    pub theirs: f64,
+}
+
+/// Сравнение двух цепочек с общим генезисом
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainComparison {
+    /// Последний блок, который есть в обеих цепочках
+    pub common: BlockRef,
+    /// Блоки после общего только в этой цепочке и только в другой
+    pub only_ours: Vec<BlockRef>,
+    pub only_theirs: Vec<BlockRef>,
+    pub params: Vec<ParamDifference>,
+    /// Средства адресов после общего блока, восстановленные по блокам одинаково для обеих сторон
+    pub balances: Vec<BalanceDifference>,
+    /// Идентификаторы ожидающих транзакций, которые есть только в одном пуле
+    pub pending_only_ours: Vec<String>,
+    pub pending_only_theirs: Vec<String>,
+}
+
+impl ChainComparison {
+    pub fn is_empty(&self) -> bool {
+        self.only_ours.is_empty() && self.only_theirs.is_empty() && self.params.is_empty() && self.balances.is_empty()
+            && self.pending_only_ours.is_empty() && self.pending_only_theirs.is_empty()
+    }
+}
+
+/// Чем различаются две цепочки
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ChainDiff {
+    /// Генезисы разные: у цепочек нет ничего общего
+    Incomparable { our_genesis: String, their_genesis: String },
+    Compared(ChainComparison),
+}
+
+impl ChainDiff {
+    /// Одинаковы ли цепочки во всем, что сравнивается
+    pub fn is_empty(&self) -> bool {
+        matches!(self, ChainDiff::Compared(comparison) if comparison.is_empty())
+    }
+}
+
+/// Высоты первого и последнего блока списка
+fn span(blocks: &[BlockRef]) -> String {
+    match (blocks.first(), blocks.last()) {
+        (Some(first), Some(last)) if first.height != last.height => format!("{} blocks (#{}..#{})", blocks.len(), first.height, last.height),
+        (Some(first), _) => format!("1 block (#{})", first.height),
+        _ => "no blocks".to_string(),
+    }
+}
+
+impl fmt::Display for ChainDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comparison = match self {
+            ChainDiff::Incomparable { our_genesis, their_genesis } => {
+                return write!(f, "Incomparable: genesis {} vs {}", our_genesis, their_genesis);
+            },
+            ChainDiff::Compared(comparison) => comparison,
+        };
+        if comparison.is_empty() {
+            return write!(f, "Identical up to #{} {}", comparison.common.height, comparison.common.hash);
+        }
+        
+        writeln!(f, "Common block: #{} {}", comparison.common.height, comparison.common.hash)?;
+        writeln!(f, "Only ours: {}, only theirs: {}", span(&comparison.only_ours), span(&comparison.only_theirs))?;
+        for param in &comparison.params {
+            writeln!(f, "Param {}: {} vs {}", param.name, param.ours, param.theirs)?;
+        }
+        writeln!(f, "Balances differing at #{}: {}", comparison.common.height, comparison.balances.len())?;
+        write!(f, "Pending only ours: {}, only theirs: {}", comparison.pending_only_ours.len(), comparison.pending_only_theirs.len())
+    }
+}
+
+/// Поля параметров в JSON; параметры всегда сериализуются в объект
+fn param_fields(chain: &Blockchain) -> Map<String, Value> {
+    match serde_json::to_value(&chain.params) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// Ожидающие транзакции одной цепочки, которых нет в пуле другой
+fn pending_only(ours: &Blockchain, theirs: &Blockchain) -> Vec<String> {
+    let (ours, theirs) = (ours.mempool().transactions(), theirs.mempool().transactions());
+    let theirs: HashSet<&str> = theirs.iter().map(|tx| tx.id.as_str()).collect();
+    let mut only: Vec<String> = ours.iter().filter(|tx| !theirs.contains(tx.id.as_str())).map(|tx| tx.id.clone()).collect();
+    only.sort_unstable();
+    only
+}
+
+impl Blockchain {
+    /// Сравнивает цепочку с другой, ничего не меняя ни в одной из них. Цепочки с разным генезисом
+    /// сразу считаются несравнимыми. Иначе общий блок ищется двоичным поиском: хеш блока
+    /// закрепляет всех его предков, поэтому совпавшие высоты идут подряд от генезиса.
+    pub fn diff(&self, other: &Blockchain) -> ChainDiff {
+        let (ours, theirs) = (&self.chain, &other.chain);
+        let (our_genesis, their_genesis) = (&self.chain[0].hash, &other.chain[0].hash);
+        if our_genesis != their_genesis {
+            return ChainDiff::Incomparable { our_genesis: our_genesis.clone(), their_genesis: their_genesis.clone() };
+        }
+        
+        // Блоки ниже `matching` совпадают, начиная с `shared` сравнивать уже нечего
+        let (mut matching, mut shared) = (1, ours.len().min(theirs.len()));
+        while matching < shared {
+            let middle = matching + (shared - matching) / 2;
+            if ours[middle].hash == theirs[middle].hash {
+                matching = middle + 1;
+            } else {
+                shared = middle;
+            }
+        }
+        let common = &ours[matching - 1];
+        
+        let (our_params, their_params) = (param_fields(self), param_fields(other));
+        let names: BTreeSet<&String> = our_params.keys().chain(their_params.keys()).collect();
+        let params = names.into_iter()
+            .filter_map(|name| {
+                let ours = our_params.get(name).cloned().unwrap_or(Value::Null);
+                let theirs = their_params.get(name).cloned().unwrap_or(Value::Null);
+                (ours != theirs).then(|| ParamDifference { name: name.clone(), ours, theirs })
+            })
+            .collect();
+        
+        let (our_balances, their_balances) = (self.expected_balances_at(common.index), other.expected_balances_at(common.index));
+        let addresses: BTreeSet<&String> = our_balances.keys().chain(their_balances.keys()).collect();
+        let balances = addresses.into_iter()
+            .map(|address| BalanceDifference {
+                address: address.clone(),
+                ours: our_balances.get(address).copied().unwrap_or(0.0),
+                theirs: their_balances.get(address).copied().unwrap_or(0.0),
+            })
+            .filter(|difference| (difference.ours - difference.theirs).abs() > SUPPLY_TOLERANCE)
+            .collect();
+        
+        ChainDiff::Compared(ChainComparison {
+            common: BlockRef::of(common),
+            only_ours: ours[matching..].iter().map(BlockRef::of).collect(),
+            only_theirs: theirs[matching..].iter().map(BlockRef::of).collect(),
+            params,
+            balances,
+            pending_only_ours: pending_only(self, other),
+            pending_only_theirs: pending_only(other, self),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    
+    fn copy(chain: &Blockchain) -> Blockchain {
+        serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap()
+    }
+    
+    fn heights(blocks: &[BlockRef]) -> Vec<u64> {
+        blocks.iter().map(|block| block.height).collect()
+    }
+    
+    #[test]
+    fn chains_diverging_after_block_ten_report_the_fork_point() {
+        let mut ours = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        ours.create_wallet("alice".to_string()).unwrap();
+        for _ in 0..10 {
+            ours.mine_pending_transactions("alice".to_string()).unwrap();
+        }
+        let mut theirs = copy(&ours);
+        ours.mine_pending_transactions("miner".to_string()).unwrap();
+        ours.mine_pending_transactions("miner".to_string()).unwrap();
+        for _ in 0..3 {
+            theirs.mine_pending_transactions("rival".to_string()).unwrap();
+        }
+        theirs.params.target_block_time = 30.0;
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 5.0, TransactionType::Transfer);
+        tx.set_nonce(ours.get_account_nonce("alice"));
+        ours.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        ours.add_transaction(tx).unwrap();
+        
+        let (our_json, their_json) = (serde_json::to_string(&ours).unwrap(), serde_json::to_string(&theirs).unwrap());
+        let ChainDiff::Compared(comparison) = ours.diff(&theirs) else {
+            panic!("chains share their genesis");
+        };
+        assert_eq!(comparison.common, BlockRef::of(&ours.chain[10]));
+        assert_eq!(heights(&comparison.only_ours), [11, 12]);
+        assert_eq!(heights(&comparison.only_theirs), [11, 12, 13]);
+        assert_eq!(comparison.only_theirs[2].hash, theirs.get_latest_block().hash);
+        assert_eq!(comparison.params, [ParamDifference { name: "target_block_time".to_string(), ours: 60.0.into(), theirs: 30.0.into() }]);
+        // На общей высоте состояние одно и то же, хотя дальше награды достались разным майнерам
+        assert!(comparison.balances.is_empty(), "{:?}", comparison.balances);
+        assert_eq!((comparison.pending_only_ours.as_slice(), comparison.pending_only_theirs.len()), (&[tx_id][..], 0));
+        
+        let diff = ChainDiff::Compared(comparison);
+        let summary = diff.to_string();
+        assert!(summary.contains(&format!("Common block: #10 {}", ours.chain[10].hash)), "{}", summary);
+        assert!(summary.contains("Only ours: 2 blocks (#11..#12), only theirs: 3 blocks (#11..#13)"), "{}", summary);
+        assert!(summary.contains("Param target_block_time: 60.0 vs 30.0"), "{}", summary);
+        assert!(summary.ends_with("Pending only ours: 1, only theirs: 0"), "{}", summary);
+        assert_eq!(serde_json::to_value(&diff).unwrap()["status"], "compared");
+        
+        // Сравнение ничего не меняет ни в одной из цепочек
+        assert_eq!(serde_json::to_string(&ours).unwrap(), our_json);
+        assert_eq!(serde_json::to_string(&theirs).unwrap(), their_json);
+    }
+    
+    #[test]
+    fn identical_chains_have_an_empty_diff() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        for _ in 0..3 {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        let diff = chain.diff(&copy(&chain));
+        assert!(diff.is_empty(), "{:?}", diff);
+        assert_eq!(diff.to_string(), format!("Identical up to #3 {}", chain.get_latest_block().hash));
+        
+        // Отставший узел: общая вершина — его последний блок, лишние блоки только у нас
+        let mut behind = copy(&chain);
+        behind.chain.truncate(2);
+        let ChainDiff::Compared(comparison) = chain.diff(&behind) else {
+            panic!("chains share their genesis");
+        };
+        assert_eq!((comparison.common.height, heights(&comparison.only_ours), comparison.only_theirs.len()), (1, vec![2, 3], 0));
+    }
+    
+    #[test]
+    fn off_chain_credits_show_up_as_balance_differences() {
+        let mut ours = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        ours.create_wallet("alice".to_string()).unwrap();
+        let mut theirs = copy(&ours);
+        ours.allocate_at_genesis("alice", 50.0).unwrap();
+        
+        let ChainDiff::Compared(comparison) = ours.diff(&theirs) else {
+            panic!("chains share their genesis");
+        };
+        assert_eq!(comparison.common.height, 0);
+        assert_eq!(comparison.balances, [BalanceDifference { address: "alice".to_string(), ours: 50.0, theirs: 0.0 }]);
+        theirs.allocate_at_genesis("alice", 50.0).unwrap();
+        assert!(ours.diff(&theirs).is_empty());
+    }
+    
+    #[test]
+    fn different_genesis_is_incomparable() {
+        let ours = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let mut theirs = ChainFixture::chain(2, 100.0, ConsensusAlgorithm::ProofOfWork);
+        // Дальше генезиса сравнение не идет: блок с чужой высотой его бы запутал
+        let mut stray = theirs.chain[0].clone();
+        stray.index = 99;
+        theirs.chain.push(stray);
+        
+        let diff = ours.diff(&theirs);
+        assert_eq!(diff, ChainDiff::Incomparable { our_genesis: ours.chain[0].hash.clone(), their_genesis: theirs.chain[0].hash.clone() });
+        assert!(!diff.is_empty());
+        assert_eq!(diff.to_string(), format!("Incomparable: genesis {} vs {}", ours.chain[0].hash, theirs.chain[0].hash));
+        assert_eq!(serde_json::to_value(&diff).unwrap()["status"], "incomparable");
+    }
+}
@@ -0,0 +1,181 @@
+#![deny(clippy::indexing_slicing)]
+
+use crate::blockchain::Blockchain;
+use crate::fee_estimate::percentile;
+use crate::supply::is_system_sender;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Показатели цепочки за окно последних блоков, вычисленные по самим блокам
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChainMetrics {
+    /// Сколько блоков вошло в окно; генезис не считается
+    pub blocks: u64,
+    pub first_height: u64,
+    pub last_height: u64,
+    /// Интервалы между блоком и его родителем, в секундах
+    pub average_block_interval: f64,
+    pub median_block_interval: f64,
+    pub p90_block_interval: f64,
+    pub p99_block_interval: f64,
+    /// Транзакции пользователей; выплаты наград, стейкинга и моста не считаются
+    pub transactions: u64,
+    pub transactions_per_block: f64,
+    pub total_fees: f64,
+    pub average_fees_per_block: f64,
+    /// Отправители и получатели пользовательских транзакций
+    pub active_addresses: usize,
+    /// Байты `Data` и фрагментов потоковой загрузки
+    pub data_bytes: u64,
+    /// Транзакции смарт-контрактов; вызовы исполняются вне блоков и сюда не попадают
+    pub contract_transactions: u64,
+}
+
+fn data_len(tx: &Transaction) -> usize {
+    match &tx.transaction_type {
+        TransactionType::Data(data) => data.len(),
+        TransactionType::DataChunk { payload, .. } => payload.len(),
+        _ => 0,
+    }
+}
+
+impl Blockchain {
+    /// Считает показатели по последним `window_blocks` блокам; окно больше цепочки берет ее целиком
+    pub fn compute_metrics(&self, window_blocks: usize) -> ChainMetrics {
+        let start = self.chain.len().saturating_sub(window_blocks).max(1);
+        let window = self.chain.get(start..).unwrap_or_default();
+        let (Some(first), Some(last)) = (window.first(), window.last()) else {
+            return ChainMetrics::default();
+        };
+        
+        let parents = self.chain.iter().skip(start - 1);
+        let mut intervals: Vec<f64> = parents.zip(window)
+            .map(|(parent, block)| (block.timestamp - parent.timestamp) as f64)
+            .collect();
+        intervals.sort_by(f64::total_cmp);
+        
+        let user_transactions = || window.iter()
+            .flat_map(|block| &block.transactions)
+            .filter(|tx| !is_system_sender(&tx.sender));
+        let mut addresses = BTreeSet::new();
+        for tx in user_transactions() {
+            addresses.insert(tx.sender.as_str());
+            addresses.insert(tx.receiver.as_str());
+        }
+        
+        let blocks = window.len() as f64;
+        let transactions = user_transactions().count() as u64;
+        let total_fees: f64 = user_transactions().map(|tx| tx.fee).sum();
+        
+        ChainMetrics {
+            blocks: window.len() as u64,
+            first_height: first.index,
+            last_height: last.index,
+            average_block_interval: intervals.iter().sum::<f64>() / blocks,
+            median_block_interval: percentile(&intervals, 0.5),
+            p90_block_interval: percentile(&intervals, 0.9),
+            p99_block_interval: percentile(&intervals, 0.99),
+            transactions,
+            transactions_per_block: transactions as f64 / blocks,
+            total_fees,
+            average_fees_per_block: total_fees / blocks,
+            active_addresses: addresses.len(),
+            data_bytes: user_transactions().map(data_len).sum::<usize>() as u64,
+            contract_transactions: user_transactions()
+                .filter(|tx| matches!(tx.transaction_type, TransactionType::SmartContract(_)))
+                .count() as u64,
+        }
+    }
+}
+
+impl fmt::Display for ChainMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.blocks == 0 {
+            return write!(f, "No blocks after genesis");
+        }
+        writeln!(f, "Blocks {}-{} ({} blocks)", self.first_height, self.last_height, self.blocks)?;
+        writeln!(f, "Block interval: avg {:.2}s, median {}s, p90 {}s, p99 {}s",
+            self.average_block_interval, self.median_block_interval, self.p90_block_interval, self.p99_block_interval)?;
+        writeln!(f, "Transactions: {} ({:.2} per block)", self.transactions, self.transactions_per_block)?;
+        writeln!(f, "Fees: {} ({:.6} per block)", self.total_fees, self.average_fees_per_block)?;
+        writeln!(f, "Active addresses: {}", self.active_addresses)?;
+        write!(f, "Data stored: {} bytes, contract transactions: {}", self.data_bytes, self.contract_transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::clock::MockClock;
+    use crate::test_support::ChainFixture;
+    use std::sync::Arc;
+    
+    /// Три блока через 10, 20 и 30 секунд: закрепление ключа, перевод, затем данные и контракт
+    fn scenario_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let clock = Arc::new(MockClock::new(GENESIS_TIMESTAMP + 10));
+        chain.set_clock(clock.clone());
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        
+        clock.advance(20);
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        
+        clock.advance(30);
+        chain.store_data("alice".to_string(), b"hello world".to_vec()).unwrap();
+        chain.create_smart_contract("alice".to_string(), "counter".to_string(), 10.0).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain
+    }
+    
+    #[test]
+    fn metrics_are_computed_from_block_data() {
+        let chain = scenario_chain();
+        let metrics = chain.compute_metrics(100);
+        assert_eq!((metrics.blocks, metrics.first_height, metrics.last_height), (3, 1, 3));
+        assert_eq!(metrics.average_block_interval, 20.0);
+        assert_eq!(metrics.median_block_interval, 20.0);
+        assert_eq!((metrics.p90_block_interval, metrics.p99_block_interval), (30.0, 30.0));
+        assert_eq!(metrics.transactions, 4);
+        assert!((metrics.transactions_per_block - 4.0 / 3.0).abs() < 1e-12);
+        // 0.1% перевода, 0.5% суммы и 0.0001 за байт данных, 1% суммы и 0.5 за контракт
+        assert!((metrics.total_fees - (0.01 + 0.0016 + 0.6)).abs() < 1e-12, "{}", metrics.total_fees);
+        assert!((metrics.average_fees_per_block - metrics.total_fees / 3.0).abs() < 1e-12);
+        // alice, bob, BLOCKCHAIN_DATA и адрес контракта
+        assert_eq!(metrics.active_addresses, 4);
+        assert_eq!(metrics.data_bytes, 11);
+        assert_eq!(metrics.contract_transactions, 1);
+        
+        // Загруженная цепочка дает те же показатели
+        let loaded: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        assert_eq!(loaded.compute_metrics(100), metrics);
+        let text = metrics.to_string();
+        assert!(text.starts_with("Blocks 1-3 (3 blocks)\nBlock interval: avg 20.00s, median 20s, p90 30s, p99 30s"), "{}", text);
+    }
+    
+    #[test]
+    fn window_covers_the_latest_blocks() {
+        let chain = scenario_chain();
+        let metrics = chain.compute_metrics(2);
+        assert_eq!((metrics.blocks, metrics.first_height, metrics.last_height), (2, 2, 3));
+        assert_eq!(metrics.average_block_interval, 25.0);
+        assert_eq!(metrics.transactions, 3);
+        assert_eq!(chain.compute_metrics(3), chain.compute_metrics(usize::MAX));
+    }
+    
+    #[test]
+    fn genesis_only_chain_has_zero_metrics() {
+        let chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let metrics = chain.compute_metrics(10);
+        assert_eq!(metrics, ChainMetrics::default());
+        assert_eq!(metrics.to_string(), "No blocks after genesis");
+        assert_eq!(scenario_chain().compute_metrics(0), ChainMetrics::default());
+    }
+}
@@ -0,0 +1,674 @@
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::dot::DotOptions;
+use crate::errors::BlockchainError;
+use crate::health::HealthStatus;
+use crate::registry::{self, ChainRegistry};
+use crate::spec::ChainSpec;
+use crate::storage;
+use crate::transaction::{Transaction, TransactionType};
+use crate::utxo::LedgerMode;
+use crate::vanity::generate_vanity;
+use crate::wallet::Wallet;
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Командная строка узла hellochain; тексты справки для clap задаются атрибутами на английском
+#[derive(Debug, Parser)]
+#[command(name = "hellochain", version, about = "Same as hello world, but it is blockchain")]
+pub struct Cli {
+    #[arg(long, global = true, default_value = ".hellochain", help = "Directory with the persisted chain")]
+    pub data_dir: PathBuf,
+    
+    #[arg(long, global = true, help = "Print machine-readable JSON instead of text")]
+    pub json: bool,
+    
+    #[arg(long, global = true, help = "Use the named chain kept under <data-dir>/chains/<name>")]
+    pub chain: Option<String>,
+    
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Подкоманды CLI, они же команды консоли
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "Create a chain from a spec file")]
+    Init {
+        spec: PathBuf,
+        #[arg(long, help = "Overwrite an existing chain")]
+        force: bool,
+    },
+    #[command(subcommand, about = "Manage wallets")]
+    Wallet(WalletCommand),
+    #[command(about = "Queue a transfer")]
+    Send {
+        from: String,
+        to: String,
+        amount: f64,
+        #[arg(long, help = "Wallet that pays the fee instead of the sender")]
+        fee_payer: Option<String>,
+        #[arg(long, help = "Last block height the transaction may be included in")]
+        valid_until: Option<u64>,
+    },
+    #[command(about = "Mine a block from pending transactions")]
+    Mine { miner: String },
+    #[command(about = "Request test funds from the chain faucet")]
+    Faucet { address: String },
+    #[command(subcommand, about = "Manage PoS validators")]
+    Validator(ValidatorCommand),
+    #[command(subcommand, about = "Deploy and call smart contracts")]
+    Contract(ContractCommand),
+    #[command(subcommand, about = "Store and read data")]
+    Data(DataCommand),
+    #[command(subcommand, about = "Inspect the chain")]
+    Chain(ChainCommand),
+    #[command(about = "Interactive console over the chain in the data directory")]
+    Console,
+    #[command(about = "List the named chains in the data directory")]
+    Chains,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WalletCommand {
+    #[command(about = "Create a wallet with the given address, or with a new key and the address derived from it")]
+    New { address: Option<String> },
+    #[command(about = "Register a wallet with a known address")]
+    Import {
+        address: String,
+        #[arg(long, help = "Hex ed25519 key of the owner; only it can spend from the address. A new key pair is created when omitted")]
+        public_key: Option<String>,
+    },
+    #[command(about = "Create a wallet whose generated address starts with a prefix")]
+    Vanity {
+        prefix: String,
+        #[arg(long, help = "Match letters in the given case only")]
+        case_sensitive: bool,
+        #[arg(long, help = "Worker threads; all cores by default")]
+        threads: Option<usize>,
+        #[arg(long, default_value_t = 60, help = "Give up after this many seconds")]
+        timeout: u64,
+    },
+    #[command(about = "Queue a mint transaction that credits a wallet")]
+    Fund { address: String, amount: f64 },
+    #[command(about = "Show wallet balance")]
+    Balance {
+        address: String,
+        #[arg(long, help = "Show the balance as of this block height, replayed from the chain")]
+        height: Option<u64>,
+    },
+    #[command(about = "Show confirmed and pending transactions of a wallet")]
+    History { address: String },
+    #[command(about = "Sweep the whole balance to another address and close the wallet")]
+    Close { address: String, destination: String },
+    #[command(about = "Set the display label of an address, or clear it when omitted")]
+    Label { address: String, label: Option<String> },
+    #[command(about = "Set notes about an address, or clear them when omitted")]
+    Notes { address: String, notes: Option<String> },
+    #[command(about = "Tag an address")]
+    Tag { address: String, tag: String },
+    #[command(about = "Remove a tag from an address")]
+    Untag { address: String, tag: String },
+    #[command(about = "List addresses with a tag")]
+    Tagged { tag: String },
+    #[command(about = "Export labels, tags and notes of all addresses to a JSON file")]
+    ExportMetadata { output: PathBuf },
+    #[command(about = "Merge labels, tags and notes from a JSON file")]
+    ImportMetadata { input: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ValidatorCommand {
+    #[command(about = "Stake funds and register a validator")]
+    Add { address: String, stake: f64 },
+    #[command(about = "Unregister a validator and return its stake")]
+    Remove { address: String },
+    #[command(about = "List validators")]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ContractCommand {
+    #[command(about = "Deploy a contract")]
+    Deploy {
+        creator: String,
+        code: String,
+        #[arg(long, default_value_t = 1.0, help = "Initial contract balance")]
+        value: f64,
+    },
+    #[command(about = "Call a contract function")]
+    Call {
+        address: String,
+        function: String,
+        args: Vec<String>,
+    },
+    #[command(about = "Show contract code and balance")]
+    Query { address: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DataCommand {
+    #[command(about = "Store a string or a file")]
+    Store {
+        sender: String,
+        #[arg(required_unless_present = "file")]
+        data: Option<String>,
+        #[arg(long, conflicts_with = "data")]
+        file: Option<PathBuf>,
+    },
+    #[command(about = "Read stored data")]
+    Get {
+        id: String,
+        #[arg(long, help = "Write data to a file instead of printing it")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ChainCommand {
+    #[command(about = "Check hashes and links of all blocks")]
+    Validate,
+    #[command(about = "Check chain, indexes, supply, mempool and contracts")]
+    Health,
+    #[command(about = "Show height, tip and parameters")]
+    Info,
+    #[command(about = "Show block interval, throughput and fee statistics")]
+    Metrics {
+        #[arg(long, default_value_t = 100, help = "Number of latest blocks to cover")]
+        window: usize,
+    },
+    #[command(about = "Show the largest holders and how holdings are distributed")]
+    Holders {
+        #[arg(long, default_value_t = 10, help = "Number of largest holders to list")]
+        top: usize,
+        #[arg(long, help = "Count treasury, escrow, burn and other system addresses too")]
+        include_system: bool,
+    },
+    #[command(about = "Export blocks as JSON")]
+    Export {
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Render the chain and its side branches as a Graphviz DOT graph")]
+    Dot {
+        #[arg(long, help = "Lowest height to include")]
+        from: Option<u64>,
+        #[arg(long, help = "Highest height to include")]
+        to: Option<u64>,
+        #[arg(long, help = "Draw the transactions of every block")]
+        transactions: bool,
+    },
+}
+
+/// Результат команды в двух представлениях
+pub(crate) struct Output {
+    pub(crate) text: String,
+    pub(crate) json: Value,
+    pub(crate) success: bool,
+}
+
+impl Output {
+    fn ok(text: impl Into<String>, json: Value) -> Self {
+        Output { text: text.into(), json, success: true }
+    }
+}
+
+/// Разбирает аргументы, выполняет команду и возвращает код завершения
+pub fn run() -> ExitCode {
+    let cli = Cli::parse();
+    let json_mode = cli.json;
+    
+    match execute(cli) {
+        Ok(output) => {
+            if json_mode {
+                println!("{}", serde_json::to_string_pretty(&output.json).unwrap_or_default());
+            } else if !output.text.is_empty() {
+                println!("{}", output.text);
+            }
+            
+            if output.success { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+        },
+        Err(error) => {
+            if json_mode {
+                println!("{}", json!({ "error": error }));
+            } else {
+                eprintln!("Error: {}", error);
+            }
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn open_chain(data_dir: &Path) -> Result<Blockchain, BlockchainError> {
+    if !storage::is_initialized(data_dir) {
+        return Err(BlockchainError::Storage(format!(
+            "No chain in {}, run `hellochain init` first", data_dir.display()
+        )));
+    }
+    
+    Blockchain::load(data_dir)
+}
+
+/// Выполняет команду над цепочкой из каталога данных и сохраняет изменения
+fn execute(cli: Cli) -> Result<Output, BlockchainError> {
+    let data_dir = match &cli.chain {
+        Some(name) => {
+            registry::validate_chain_name(name)?;
+            registry::chain_dir(&cli.data_dir, name)
+        },
+        None => cli.data_dir.clone(),
+    };
+    
+    match cli.command {
+        Command::Init { spec, force } => {
+            if storage::is_initialized(&data_dir) && !force {
+                return Err(BlockchainError::Storage(format!(
+                    "Chain already exists in {}, use --force to overwrite", data_dir.display()
+                )));
+            }
+            
+            let chain = ChainSpec::from_file(&spec)?.build()?;
+            chain.save(&data_dir)?;
+            let genesis = &chain.chain[0];
+            Ok(Output::ok(
+                format!("Chain initialized in {} (genesis {})", data_dir.display(), genesis.hash),
+                json!({ "data_dir": data_dir, "genesis_hash": genesis.hash }),
+            ))
+        },
+        Command::Chains => {
+            let chains = ChainRegistry::load(&cli.data_dir)?.list();
+            let text = chains.iter()
+                .map(|chain| format!("{} #{} {:?} chain id {} (genesis {})", chain.name, chain.height, chain.consensus, chain.chain_id, chain.genesis_hash))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(Output::ok(text, json!(chains)))
+        },
+        Command::Console => {
+            let chain = if storage::is_initialized(&data_dir) {
+                Blockchain::load(&data_dir)?
+            } else {
+                eprintln!("No chain in {}, using a fresh in-memory chain", data_dir.display());
+                Blockchain::new(2, 100.0, ConsensusAlgorithm::ProofOfWork)
+            };
+            let persisted = storage::is_initialized(&data_dir).then_some(data_dir);
+            crate::console::run(chain, persisted)?;
+            Ok(Output::ok("", json!({})))
+        },
+        command => {
+            let mut chain = open_chain(&data_dir)?;
+            let output = execute_on_chain(&mut chain, command)?;
+            chain.save(&data_dir)?;
+            Ok(output)
+        },
+    }
+}
+
+/// Выполняет команду, которой нужна загруженная цепочка
+pub(crate) fn execute_on_chain(chain: &mut Blockchain, command: Command) -> Result<Output, BlockchainError> {
+    match command {
+        Command::Wallet(command) => wallet_command(chain, command),
+        Command::Send { from, to, amount, fee_payer, valid_until } => {
+            let mut transaction = match chain.ledger_mode() {
+                LedgerMode::Account => {
+                    let mut transaction = chain.new_transaction(from.clone(), to, amount, TransactionType::Transfer);
+                    // Номер меняет идентификатор, который подписывает спонсор
+                    transaction.set_nonce(chain.get_account_nonce(&from));
+                    transaction
+                },
+                LedgerMode::Utxo => chain.build_utxo_transfer(&from, &to, amount)?,
+            };
+            if let Some(height) = valid_until {
+                transaction.set_valid_until(height);
+            }
+            if let Some(fee_payer) = fee_payer {
+                let fee = transaction.fee;
+                chain.get_wallet_info(&fee_payer)
+                    .ok_or_else(|| BlockchainError::WalletNotFound { address: fee_payer.clone() })?
+                    .sponsor_transaction(&mut transaction, fee)?;
+            }
+            chain.sign_as_sender(&mut transaction);
+            let tx_id = transaction.id.clone();
+            let fee = transaction.fee;
+            chain.add_transaction(transaction)?;
+            Ok(Output::ok(
+                format!("Transaction {} queued (fee {})", tx_id, fee),
+                json!({ "id": tx_id, "fee": fee }),
+            ))
+        },
+        Command::Mine { miner } => {
+            chain.mine_pending_transactions(miner)?;
+            let block = chain.get_latest_block();
+            Ok(Output::ok(
+                format!("Block #{} mined: {} ({} transactions)", block.index, block.hash, block.transactions.len()),
+                json!(block),
+            ))
+        },
+        Command::Faucet { address } => {
+            let tx_id = chain.request_from_faucet(&address)?;
+            Ok(Output::ok(
+                format!("Faucet transaction {} queued for {}", tx_id, address),
+                json!({ "id": tx_id, "address": address }),
+            ))
+        },
+        Command::Validator(command) => validator_command(chain, command),
+        Command::Contract(command) => contract_command(chain, command),
+        Command::Data(command) => data_command(chain, command),
+        Command::Chain(command) => chain_command(chain, command),
+        Command::Init { .. } | Command::Console | Command::Chains => unreachable!("handled without a loaded chain"),
+    }
+}
+
+fn wallet_command(chain: &mut Blockchain, command: WalletCommand) -> Result<Output, BlockchainError> {
+    match command {
+        WalletCommand::New { address } => {
+            let address = match address {
+                Some(address) => chain.import_wallet(&address, None)?.address.clone(),
+                None => {
+                    let wallet = Wallet::generate(chain.entropy.as_ref(), chain.network());
+                    chain.add_wallet(wallet)?.address.clone()
+                },
+            };
+            Ok(Output::ok(address.clone(), json!({ "address": address })))
+        },
+        WalletCommand::Vanity { prefix, case_sensitive, threads, timeout } => {
+            let threads = threads.unwrap_or_else(|| chain.mining_thread_count());
+            let wallet = generate_vanity(chain.network(), &prefix, case_sensitive, threads, Duration::from_secs(timeout))
+                .map_err(|e| BlockchainError::InvalidTransaction(e.to_string()))?;
+            let address = chain.add_wallet(wallet)?.address.clone();
+            Ok(Output::ok(address.clone(), json!({ "address": address })))
+        },
+        WalletCommand::Import { address, public_key } => {
+            chain.import_wallet(&address, public_key.as_deref())?;
+            Ok(Output::ok(format!("Wallet {} imported", address), json!({ "address": address })))
+        },
+        WalletCommand::Fund { address, amount } => {
+            let tx_id = chain.mint(&address, amount)?;
+            Ok(Output::ok(format!("Mint {} queued", tx_id), json!({ "tx_id": tx_id, "address": address, "amount": amount })))
+        },
+        WalletCommand::Balance { address, height: Some(height) } => {
+            let details = chain.balance_at_height(&address, height)?;
+            Ok(Output::ok(
+                format!("{} at #{}: {} (staking {}, unvested {})", chain.display_address(&address), height, details.total, details.staked, details.unvested),
+                json!({ "address": address, "height": height, "balance": details }),
+            ))
+        },
+        WalletCommand::Balance { address, height: None } => {
+            let wallet = chain.get_wallet_info(&address)
+                .ok_or_else(|| BlockchainError::WalletNotFound { address: address.clone() })?;
+            let details = chain.get_balance_detailed(&address);
+            Ok(Output::ok(
+                format!("{}: {} (staking {}, unvested {})", chain.display_address(&wallet.address), wallet.balance, wallet.staking_balance, details.unvested),
+                json!({
+                    "address": wallet.address,
+                    "balance": wallet.balance,
+                    "staking_balance": wallet.staking_balance,
+                    "spendable": details.spendable,
+                    "vested": details.vested,
+                    "unvested": details.unvested,
+                    "frozen": wallet.frozen,
+                    "assets": wallet.assets,
+                    "claimable_rewards": wallet.claimable_rewards,
+                    "metadata": chain.wallet_metadata(&address),
+                }),
+            ))
+        },
+        WalletCommand::History { address } => {
+            let mut history: Vec<(Transaction, bool)> = chain.get_transaction_history(&address)
+                .into_iter()
+                .map(|tx| (tx, true))
+                .collect();
+            history.extend(chain.mempool().transactions().into_iter()
+                .filter(|tx| tx.sender == address || tx.receiver == address)
+                .map(|tx| (tx, false)));
+            
+            let text = history.iter()
+                .map(|(tx, confirmed)| format!(
+                    "{} {} -> {} {} [{}]",
+                    tx.id, chain.display_address(&tx.sender), chain.display_address(&tx.receiver), tx.amount,
+                    if *confirmed { "confirmed" } else { "pending" }
+                ))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let label = |address: &str| chain.wallet_metadata(address).and_then(|metadata| metadata.label.clone());
+            let json = history.iter()
+                .map(|(tx, confirmed)| json!({
+                    "transaction": tx,
+                    "confirmed": confirmed,
+                    "sender_label": label(&tx.sender),
+                    "receiver_label": label(&tx.receiver),
+                }))
+                .collect();
+            Ok(Output::ok(text, Value::Array(json)))
+        },
+        WalletCommand::Label { address, label } => {
+            chain.set_wallet_label(&address, label.as_deref());
+            Ok(Output::ok(chain.display_address(&address), json!({ "address": address, "metadata": chain.wallet_metadata(&address) })))
+        },
+        WalletCommand::Notes { address, notes } => {
+            chain.set_wallet_notes(&address, notes.as_deref());
+            Ok(Output::ok(
+                format!("Notes of {} updated", chain.display_address(&address)),
+                json!({ "address": address, "metadata": chain.wallet_metadata(&address) }),
+            ))
+        },
+        WalletCommand::Tag { address, tag } => {
+            let added = chain.add_wallet_tag(&address, &tag)?;
+            let name = chain.display_address(&address);
+            let text = if added { format!("{} tagged {}", name, tag) } else { format!("{} is already tagged {}", name, tag) };
+            Ok(Output::ok(text, json!({ "address": address, "added": added, "metadata": chain.wallet_metadata(&address) })))
+        },
+        WalletCommand::Untag { address, tag } => {
+            let removed = chain.remove_wallet_tag(&address, &tag);
+            let name = chain.display_address(&address);
+            let text = if removed { format!("Tag {} removed from {}", tag, name) } else { format!("{} is not tagged {}", name, tag) };
+            Ok(Output::ok(text, json!({ "address": address, "removed": removed, "metadata": chain.wallet_metadata(&address) })))
+        },
+        WalletCommand::Tagged { tag } => {
+            let addresses = chain.find_wallets_by_tag(&tag);
+            let text = addresses.iter().map(|address| chain.display_address(address)).collect::<Vec<_>>().join("\n");
+            Ok(Output::ok(text, json!(addresses)))
+        },
+        WalletCommand::ExportMetadata { output } => {
+            chain.export_wallet_metadata(&output)?;
+            Ok(Output::ok(
+                format!("Metadata of {} addresses exported to {}", chain.wallet_metadata.len(), output.display()),
+                json!({ "addresses": chain.wallet_metadata.len(), "output": output }),
+            ))
+        },
+        WalletCommand::Close { address, destination } => {
+            let tx_id = chain.close_wallet(&address, &destination, &address)?;
+            Ok(Output::ok(
+                format!("Closing transaction {} queued", tx_id),
+                json!({ "id": tx_id }),
+            ))
+        },
+        WalletCommand::ImportMetadata { input } => {
+            let count = chain.import_wallet_metadata(&input)?;
+            Ok(Output::ok(
+                format!("Metadata of {} addresses imported from {}", count, input.display()),
+                json!({ "addresses": count, "input": input }),
+            ))
+        },
+    }
+}
+
+fn validator_command(chain: &mut Blockchain, command: ValidatorCommand) -> Result<Output, BlockchainError> {
+    match command {
+        ValidatorCommand::Add { address, stake } => {
+            chain.add_validator(address.clone(), stake)?;
+            Ok(Output::ok(
+                format!("Validator {} added with stake {}", address, stake),
+                json!({ "address": address, "stake": stake }),
+            ))
+        },
+        ValidatorCommand::Remove { address } => {
+            let stake = chain.remove_validator(&address)?;
+            Ok(Output::ok(
+                format!("Validator {} removed, {} returned to balance", address, stake),
+                json!({ "address": address, "stake": stake }),
+            ))
+        },
+        ValidatorCommand::List => {
+            let mut validators: Vec<(&String, &f64)> = chain.validators.iter().collect();
+            validators.sort_by(|a, b| a.0.cmp(b.0));
+            let text = validators.iter()
+                .map(|(address, stake)| format!("{} {}", address, stake))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let json = validators.iter()
+                .map(|(address, stake)| json!({ "address": address, "stake": stake }))
+                .collect();
+            Ok(Output::ok(text, Value::Array(json)))
+        },
+    }
+}
+
+fn contract_command(chain: &mut Blockchain, command: ContractCommand) -> Result<Output, BlockchainError> {
+    match command {
+        ContractCommand::Deploy { creator, code, value } => {
+            let address = chain.create_smart_contract(creator, code, value)?;
+            Ok(Output::ok(
+                format!("Contract {} deployed, mine a block to activate it", address),
+                json!({ "address": address }),
+            ))
+        },
+        ContractCommand::Call { address, function, args } => {
+            let result = chain.execute_smart_contract(&address, &function, args)?;
+            Ok(Output::ok(result.clone(), json!({ "result": result })))
+        },
+        ContractCommand::Query { address } => {
+            let deployment = chain.find_smart_contract(&address)
+                .ok_or_else(|| BlockchainError::ContractError { reason: format!("Smart contract {} not found", address) })?;
+            let TransactionType::SmartContract(code) = &deployment.transaction_type else {
+                unreachable!("find_smart_contract returns deployments only");
+            };
+            let balance = chain.get_balance(&address);
+            Ok(Output::ok(
+                format!("Contract {}\nCreator: {}\nBalance: {}\nCode: {}", address, deployment.sender, balance, code),
+                json!({ "address": address, "creator": deployment.sender, "balance": balance, "code": code }),
+            ))
+        },
+    }
+}
+
+fn data_command(chain: &mut Blockchain, command: DataCommand) -> Result<Output, BlockchainError> {
+    match command {
+        DataCommand::Store { sender, data, file } => {
+            let bytes = match file {
+                Some(path) => std::fs::read(&path)
+                    .map_err(|e| BlockchainError::Storage(format!("{}: {}", path.display(), e)))?,
+                None => data.unwrap_or_default().into_bytes(),
+            };
+            let data_id = chain.store_data(sender, bytes)?;
+            Ok(Output::ok(data_id.clone(), json!({ "id": data_id })))
+        },
+        DataCommand::Get { id, output } => {
+            let data = chain.get_data(&id)
+                .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Data {} not found", id)))?;
+            
+            if let Some(path) = output {
+                std::fs::write(&path, &data)
+                    .map_err(|e| BlockchainError::Storage(format!("{}: {}", path.display(), e)))?;
+                return Ok(Output::ok(
+                    format!("{} bytes written to {}", data.len(), path.display()),
+                    json!({ "id": id, "len": data.len(), "output": path }),
+                ));
+            }
+            
+            Ok(Output::ok(
+                String::from_utf8_lossy(&data).into_owned(),
+                json!({ "id": id, "len": data.len(), "hex": hex::encode(&data) }),
+            ))
+        },
+    }
+}
+
+fn chain_command(chain: &mut Blockchain, command: ChainCommand) -> Result<Output, BlockchainError> {
+    match command {
+        ChainCommand::Validate => {
+            let valid = chain.is_chain_valid();
+            Ok(Output {
+                text: if valid { "Chain is valid".to_string() } else { "Chain is INVALID".to_string() },
+                json: json!({ "valid": valid }),
+                success: valid,
+            })
+        },
+        ChainCommand::Health => {
+            let report = chain.health_check();
+            let mut text = format!("Status: {:?} at height {}", report.status, report.height);
+            for finding in &report.findings {
+                text.push_str(&format!("\n{}: {:?}", finding.check, finding.status));
+                for detail in &finding.details {
+                    text.push_str(&format!("\n  {}", detail));
+                }
+            }
+            Ok(Output {
+                text,
+                json: json!(report),
+                success: report.status != HealthStatus::Fail,
+            })
+        },
+        ChainCommand::Info => {
+            let tip = chain.get_latest_block();
+            let info = json!({
+                "height": tip.index,
+                "tip_hash": tip.hash,
+                "difficulty": chain.difficulty,
+                "mining_reward": chain.params.initial_reward,
+                "next_reward": chain.reward_at_height(tip.index + 1),
+                "circulating_supply": chain.circulating_supply(),
+                "base_fee": chain.current_base_fee(),
+                "total_burned": chain.total_burned(),
+                "treasury_balance": chain.treasury.as_ref().map(|_| chain.treasury_balance()),
+                "consensus": chain.consensus_algorithm,
+                "wallets": chain.wallets.len(),
+                "validators": chain.validators.len(),
+                "pending_transactions": chain.mempool().len(),
+            });
+            Ok(Output::ok(
+                format!(
+                    "Height: {}\nTip: {}\nConsensus: {:?}\nDifficulty: {}\nWallets: {}\nPending transactions: {}",
+                    tip.index, tip.hash, chain.consensus_algorithm, chain.difficulty,
+                    chain.wallets.len(), chain.mempool().len()
+                ),
+                info,
+            ))
+        },
+        ChainCommand::Metrics { window } => {
+            let metrics = chain.compute_metrics(window);
+            Ok(Output::ok(metrics.to_string(), json!(metrics)))
+        },
+        ChainCommand::Holders { top, include_system } => {
+            let holders = chain.top_holders_with(top, include_system);
+            let report = chain.distribution_report_with(include_system);
+            let mut text = String::new();
+            for (rank, (address, details)) in holders.iter().enumerate() {
+                text.push_str(&format!("{}. {}: {} (staked {})\n", rank + 1, chain.display_address(address), details.total + details.staked, details.staked));
+            }
+            text.push_str(&report.to_string());
+            Ok(Output::ok(text, json!({ "top_holders": holders, "distribution": report })))
+        },
+        ChainCommand::Export { output } => {
+            let blocks = serde_json::to_string_pretty(&chain.chain)
+                .map_err(|e| BlockchainError::Storage(e.to_string()))?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, blocks)
+                        .map_err(|e| BlockchainError::Storage(format!("{}: {}", path.display(), e)))?;
+                    Ok(Output::ok(
+                        format!("{} blocks exported to {}", chain.chain.len(), path.display()),
+                        json!({ "blocks": chain.chain.len(), "output": path }),
+                    ))
+                },
+                None => Ok(Output::ok(blocks, json!(chain.chain))),
+            }
+        },
+        ChainCommand::Dot { from, to, transactions } => {
+            let heights = (from.is_some() || to.is_some()).then(|| from.unwrap_or(0)..=to.unwrap_or(u64::MAX));
+            let dot = chain.export_dot(&DotOptions { heights, include_transactions: transactions });
+            Ok(Output::ok(dot.clone(), json!({ "dot": dot })))
+        },
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::Utc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+/// Источник текущего времени для блоков и транзакций, которые создает цепочка
+pub trait Clock: Send + Sync {
+    /// Текущее время в секундах Unix
+    fn now_ts(&self) -> i64;
+}
+
+/// Системные часы
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ts(&self) -> i64 {
+        Utc::now().timestamp()
+    }
+}
+
+pub(crate) fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Часы для тестов и симуляций: время идет только по команде
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicI64,
+}
+
+impl MockClock {
+    pub fn new(start: i64) -> Self {
+        MockClock { now: AtomicI64::new(start) }
+    }
+    
+    /// Сдвигает время вперед на `secs` секунд
+    pub fn advance(&self, secs: i64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+    
+    pub fn set(&self, ts: i64) {
+        self.now.store(ts, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ts(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::errors::BlockchainError;
+    use crate::params::StakeParams;
+    use crate::test_support::ChainFixture;
+    
+    #[test]
+    fn chain_timestamps_follow_the_mock_clock() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let clock = Arc::new(MockClock::new(GENESIS_TIMESTAMP + 100));
+        chain.set_clock(clock.clone());
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        assert_eq!(chain.get_latest_block().timestamp, GENESIS_TIMESTAMP + 100);
+        
+        clock.set(GENESIS_TIMESTAMP + 500);
+        chain.store_data("alice".to_string(), b"note".to_vec()).unwrap();
+        chain.create_smart_contract("alice".to_string(), "counter".to_string(), 1.0).unwrap();
+        assert!(chain.mempool().transactions().iter().all(|tx| tx.timestamp == GENESIS_TIMESTAMP + 500));
+        clock.advance(7);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_latest_block().timestamp, GENESIS_TIMESTAMP + 507);
+    }
+    
+    #[test]
+    fn stake_block_waits_for_the_clock_to_pass_the_minimum_interval() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfStake);
+        chain.params.stake_params = Some(StakeParams { min_block_interval: 30, slot_duration: 60 });
+        let clock = Arc::new(MockClock::new(GENESIS_TIMESTAMP + 30));
+        chain.set_clock(clock.clone());
+        chain.create_wallet("validator".to_string()).unwrap();
+        chain.allocate_at_genesis("validator", 1000.0).unwrap();
+        chain.add_validator("validator".to_string(), 1000.0).unwrap();
+        chain.mine_pending_transactions("validator".to_string()).unwrap();
+        
+        clock.advance(29);
+        let result = chain.mine_pending_transactions("validator".to_string());
+        assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason.contains("comes 29 s after its parent")), "{:?}", result);
+        assert_eq!(chain.chain.len(), 2);
+        clock.advance(1);
+        chain.mine_pending_transactions("validator".to_string()).unwrap();
+        assert_eq!(chain.get_latest_block().timestamp, GENESIS_TIMESTAMP + 60);
+    }
+}
@@ -0,0 +1,588 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::governance::{ProposalAction, VoteChoice};
+use crate::hashing::HashKind;
+use crate::transaction::{FeePayer, Transaction, TransactionType};
+use crate::utxo::{OutPoint, TxInput, TxOutput};
+
+/// Версия канонического двоичного формата транзакции; первый байт закодированной транзакции
+pub const RAW_TRANSACTION_VERSION: u8 = 1;
+
+/// Больше стольких байт двоичная транзакция не разбирается
+pub const MAX_RAW_TRANSACTION_LEN: usize = 1024 * 1024;
+
+/// Теги необязательных полей после подписи; поля идут по возрастанию тегов
+const FEE_PAYER_TAG: u8 = 1;
+const VALID_UNTIL_TAG: u8 = 2;
+const INPUTS_TAG: u8 = 3;
+const OUTPUTS_TAG: u8 = 4;
+const EXTRA_NONCE_TAG: u8 = 5;
+const PUBLIC_KEY_TAG: u8 = 6;
+const NONCE_TAG: u8 = 7;
+const FEE_PAYER_KEY_TAG: u8 = 8;
+const INPUT_KEYS_TAG: u8 = 9;
+
+/// Больше стольких одобрений в одной транзакции не бывает
+const MAX_APPROVALS: u32 = 64;
+
+/// Больше стольких входов или выходов в одной транзакции не бывает
+const MAX_UTXO_ENTRIES: u32 = 1024;
+
+/// Транзакция вместе с идентификатором цепочки, для которой она подписана
+#[derive(Debug, Clone)]
+pub struct RawTransaction {
+    pub chain_id: u64,
+    pub transaction: Transaction,
+}
+
+/// Кодирует транзакцию в канонический формат: версия, идентификатор цепочки, поля транзакции
+/// и необязательные поля с тегами: спонсор, срок, входы, выходы, extra-nonce, открытый ключ и номер транзакции отправителя,
+/// открытый ключ спонсора и открытые ключи владельцев входов. Числа записываются в big-endian, строки и байты предваряются длиной (u32).
+pub fn encode_transaction(transaction: &Transaction, chain_id: u64) -> Vec<u8> {
+    let mut out = vec![RAW_TRANSACTION_VERSION];
+    out.extend_from_slice(&chain_id.to_be_bytes());
+    put_str(&mut out, &transaction.id);
+    
+    match &transaction.transaction_type {
+        TransactionType::Transfer => out.push(0),
+        TransactionType::SmartContract(code) => {
+            out.push(1);
+            put_str(&mut out, code);
+        },
+        TransactionType::Data(payload) => {
+            out.push(2);
+            put_bytes(&mut out, payload);
+        },
+        TransactionType::DataChunk { data_id, index, payload } => {
+            out.push(3);
+            put_str(&mut out, data_id);
+            out.extend_from_slice(&index.to_be_bytes());
+            put_bytes(&mut out, payload);
+        },
+        TransactionType::DataManifest { data_id, chunk_count, total_len, digest } => {
+            out.push(4);
+            put_str(&mut out, data_id);
+            out.extend_from_slice(&chunk_count.to_be_bytes());
+            out.extend_from_slice(&total_len.to_be_bytes());
+            put_str(&mut out, digest);
+        },
+        TransactionType::BridgeMint { source_tx_id, relayer } => {
+            out.push(5);
+            put_str(&mut out, source_tx_id);
+            put_str(&mut out, relayer);
+        },
+        TransactionType::BridgeRelease { source_tx_id, relayer } => {
+            out.push(6);
+            put_str(&mut out, source_tx_id);
+            put_str(&mut out, relayer);
+        },
+        TransactionType::TreasurySpend { approvals } => {
+            out.push(7);
+            put_approvals(&mut out, approvals);
+        },
+        TransactionType::VestingGrant { start_height, cliff_blocks, duration_blocks } => {
+            out.push(8);
+            out.extend_from_slice(&start_height.to_be_bytes());
+            out.extend_from_slice(&cliff_blocks.to_be_bytes());
+            out.extend_from_slice(&duration_blocks.to_be_bytes());
+        },
+        TransactionType::FreezeAccount { reason, approvals } => {
+            out.push(9);
+            put_str(&mut out, reason);
+            put_approvals(&mut out, approvals);
+        },
+        TransactionType::UnfreezeAccount { approvals } => {
+            out.push(10);
+            put_approvals(&mut out, approvals);
+        },
+        TransactionType::IssueAsset { ticker, total_supply, decimals } => {
+            out.push(11);
+            put_str(&mut out, ticker);
+            out.extend_from_slice(&total_supply.to_be_bytes());
+            out.push(*decimals);
+        },
+        TransactionType::AssetTransfer { asset_id, amount } => {
+            out.push(12);
+            put_str(&mut out, asset_id);
+            out.extend_from_slice(&amount.to_be_bytes());
+        },
+        TransactionType::ClaimRewards => out.push(13),
+        TransactionType::CloseWallet => out.push(17),
+        TransactionType::Mint => out.push(18),
+        TransactionType::RegisterKey => out.push(19),
+        TransactionType::Propose { action } => {
+            out.push(14);
+            match action {
+                ProposalAction::SetParam { name, value } => {
+                    out.push(0);
+                    put_str(&mut out, name);
+                    out.extend_from_slice(&value.to_be_bytes());
+                },
+                ProposalAction::Signal(text) => {
+                    out.push(1);
+                    put_str(&mut out, text);
+                },
+            }
+        },
+        TransactionType::Vote { proposal_id, choice } => {
+            out.push(15);
+            put_str(&mut out, proposal_id);
+            out.push(match choice {
+                VoteChoice::Yes => 0,
+                VoteChoice::No => 1,
+                VoteChoice::Abstain => 2,
+            });
+        },
+        TransactionType::SettleProposal { proposal_id, passed } => {
+            out.push(16);
+            put_str(&mut out, proposal_id);
+            out.push(u8::from(*passed));
+        },
+    }
+    
+    put_str(&mut out, &transaction.sender);
+    put_str(&mut out, &transaction.receiver);
+    out.extend_from_slice(&transaction.amount.to_be_bytes());
+    out.extend_from_slice(&transaction.fee.to_be_bytes());
+    out.extend_from_slice(&transaction.timestamp.to_be_bytes());
+    put_str(&mut out, &transaction.signature);
+    if let Some(payer) = &transaction.fee_payer {
+        out.push(FEE_PAYER_TAG);
+        put_str(&mut out, &payer.address);
+        out.extend_from_slice(&payer.max_fee.to_be_bytes());
+        put_str(&mut out, &payer.signature);
+    }
+    if let Some(height) = transaction.valid_until_height {
+        out.push(VALID_UNTIL_TAG);
+        out.extend_from_slice(&height.to_be_bytes());
+    }
+    if !transaction.inputs.is_empty() {
+        out.push(INPUTS_TAG);
+        out.extend_from_slice(&(transaction.inputs.len() as u32).to_be_bytes());
+        for input in &transaction.inputs {
+            put_str(&mut out, &input.outpoint.txid);
+            out.extend_from_slice(&input.outpoint.index.to_be_bytes());
+            put_str(&mut out, &input.signature);
+        }
+    }
+    if !transaction.outputs.is_empty() {
+        out.push(OUTPUTS_TAG);
+        out.extend_from_slice(&(transaction.outputs.len() as u32).to_be_bytes());
+        for output in &transaction.outputs {
+            put_str(&mut out, &output.address);
+            out.extend_from_slice(&output.amount.to_be_bytes());
+        }
+    }
+    if let Some(extra_nonce) = transaction.extra_nonce {
+        out.push(EXTRA_NONCE_TAG);
+        out.extend_from_slice(&extra_nonce.to_be_bytes());
+    }
+    if let Some(public_key) = &transaction.public_key {
+        out.push(PUBLIC_KEY_TAG);
+        put_str(&mut out, public_key);
+    }
+    if let Some(nonce) = transaction.nonce {
+        out.push(NONCE_TAG);
+        out.extend_from_slice(&nonce.to_be_bytes());
+    }
+    if let Some(public_key) = transaction.fee_payer.as_ref().and_then(|payer| payer.public_key.as_ref()) {
+        out.push(FEE_PAYER_KEY_TAG);
+        put_str(&mut out, public_key);
+    }
+    // Ключи идут по одному на вход; пустая строка — вход без ключа
+    if transaction.inputs.iter().any(|input| input.public_key.is_some()) {
+        out.push(INPUT_KEYS_TAG);
+        out.extend_from_slice(&(transaction.inputs.len() as u32).to_be_bytes());
+        for input in &transaction.inputs {
+            put_str(&mut out, input.public_key.as_deref().unwrap_or_default());
+        }
+    }
+    out
+}
+
+/// Декодирует транзакцию; ошибка указывает смещение и поле, на котором разбор остановился. Алгоритм
+/// хеширования в формат не входит: декодированная транзакция считается SHA-256, пока ее не примет цепочка.
+pub fn decode_transaction(bytes: &[u8]) -> Result<RawTransaction, BlockchainError> {
+    if bytes.len() > MAX_RAW_TRANSACTION_LEN {
+        return Err(malformed(MAX_RAW_TRANSACTION_LEN, "end", format!("transaction exceeds {} bytes", MAX_RAW_TRANSACTION_LEN)));
+    }
+    let mut reader = Reader { bytes, offset: 0 };
+    
+    let version = reader.u8("version")?;
+    if version != RAW_TRANSACTION_VERSION {
+        return Err(malformed(0, "version", format!("unsupported version {}", version)));
+    }
+    let chain_id = reader.u64("chain_id")?;
+    let id = reader.string("id")?;
+    
+    let tag_offset = reader.offset;
+    let transaction_type = match reader.u8("type")? {
+        0 => TransactionType::Transfer,
+        1 => TransactionType::SmartContract(reader.string("code")?),
+        2 => TransactionType::Data(reader.bytes("payload")?),
+        3 => TransactionType::DataChunk {
+            data_id: reader.string("data_id")?,
+            index: reader.u32("index")?,
+            payload: reader.bytes("payload")?,
+        },
+        4 => TransactionType::DataManifest {
+            data_id: reader.string("data_id")?,
+            chunk_count: reader.u32("chunk_count")?,
+            total_len: reader.u64("total_len")?,
+            digest: reader.string("digest")?,
+        },
+        5 => TransactionType::BridgeMint {
+            source_tx_id: reader.string("source_tx_id")?,
+            relayer: reader.string("relayer")?,
+        },
+        6 => TransactionType::BridgeRelease {
+            source_tx_id: reader.string("source_tx_id")?,
+            relayer: reader.string("relayer")?,
+        },
+        7 => TransactionType::TreasurySpend { approvals: reader.approvals()? },
+        8 => TransactionType::VestingGrant {
+            start_height: reader.u64("start_height")?,
+            cliff_blocks: reader.u64("cliff_blocks")?,
+            duration_blocks: reader.u64("duration_blocks")?,
+        },
+        9 => TransactionType::FreezeAccount {
+            reason: reader.string("reason")?,
+            approvals: reader.approvals()?,
+        },
+        10 => TransactionType::UnfreezeAccount { approvals: reader.approvals()? },
+        11 => TransactionType::IssueAsset {
+            ticker: reader.string("ticker")?,
+            total_supply: reader.u64("total_supply")?,
+            decimals: reader.u8("decimals")?,
+        },
+        12 => TransactionType::AssetTransfer {
+            asset_id: reader.string("asset_id")?,
+            amount: reader.u64("asset_amount")?,
+        },
+        13 => TransactionType::ClaimRewards,
+        14 => TransactionType::Propose { action: reader.proposal_action()? },
+        15 => TransactionType::Vote {
+            proposal_id: reader.string("proposal_id")?,
+            choice: reader.vote_choice()?,
+        },
+        16 => TransactionType::SettleProposal {
+            proposal_id: reader.string("proposal_id")?,
+            passed: reader.flag("passed")?,
+        },
+        17 => TransactionType::CloseWallet,
+        18 => TransactionType::Mint,
+        19 => TransactionType::RegisterKey,
+        tag => return Err(malformed(tag_offset, "type", format!("unknown transaction type {}", tag))),
+    };
+    
+    let mut transaction = Transaction {
+        id,
+        transaction_type,
+        sender: reader.string("sender")?,
+        receiver: reader.string("receiver")?,
+        amount: f64::from_bits(reader.u64("amount")?),
+        fee: f64::from_bits(reader.u64("fee")?),
+        timestamp: reader.u64("timestamp")? as i64,
+        signature: reader.string("signature")?,
+        public_key: None,
+        nonce: None,
+        fee_payer: None,
+        valid_until_height: None,
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        extra_nonce: None,
+        hash_algorithm: HashKind::default(),
+    };
+    // Необязательные поля записываются после подписи, только если они есть, и по возрастанию тегов
+    let mut last_tag = 0;
+    while reader.offset < bytes.len() {
+        let tag_offset = reader.offset;
+        let tag = reader.u8("optional field")?;
+        if tag <= last_tag {
+            return Err(malformed(tag_offset, "optional field", format!("field {} is repeated or out of order", tag)));
+        }
+        match tag {
+            FEE_PAYER_TAG => transaction.fee_payer = Some(FeePayer {
+                address: reader.string("fee_payer")?,
+                max_fee: f64::from_bits(reader.u64("max_fee")?),
+                signature: reader.string("fee_payer_signature")?,
+                public_key: None,
+            }),
+            VALID_UNTIL_TAG => transaction.valid_until_height = Some(reader.u64("valid_until_height")?),
+            INPUTS_TAG => transaction.inputs = reader.inputs()?,
+            OUTPUTS_TAG => transaction.outputs = reader.outputs()?,
+            EXTRA_NONCE_TAG => transaction.extra_nonce = Some(reader.u64("extra_nonce")?),
+            PUBLIC_KEY_TAG => transaction.public_key = Some(reader.string("public_key")?),
+            NONCE_TAG => transaction.nonce = Some(reader.u64("nonce")?),
+            FEE_PAYER_KEY_TAG => match &mut transaction.fee_payer {
+                Some(payer) => payer.public_key = Some(reader.string("fee_payer_key")?),
+                None => return Err(malformed(tag_offset, "fee_payer_key", "key of a missing fee payer".to_string())),
+            },
+            INPUT_KEYS_TAG => reader.input_keys(&mut transaction.inputs)?,
+            _ => return Err(malformed(tag_offset, "optional field", format!("unknown field {}", tag))),
+        }
+        last_tag = tag;
+    }
+    
+    Ok(RawTransaction { chain_id, transaction })
+}
+
+/// Разбирает вход как двоичную транзакцию или, если он не начинается с байта версии, как hex-строку
+pub fn decode_raw(raw: &[u8]) -> Result<RawTransaction, BlockchainError> {
+    if raw.first() == Some(&RAW_TRANSACTION_VERSION) {
+        return decode_transaction(raw);
+    }
+    
+    if raw.len() > 2 * MAX_RAW_TRANSACTION_LEN + 2 {
+        return Err(malformed(0, "hex", format!("transaction exceeds {} bytes", MAX_RAW_TRANSACTION_LEN)));
+    }
+    let text = std::str::from_utf8(raw)
+        .map_err(|e| malformed(e.valid_up_to(), "hex", "input is neither hex nor a binary transaction".to_string()))?;
+    let trimmed = text.trim();
+    let digits = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+    let bytes = hex::decode(digits).map_err(|e| {
+        let offset = match e {
+            hex::FromHexError::InvalidHexCharacter { index, .. } => index,
+            _ => digits.len(),
+        };
+        malformed(offset, "hex", e.to_string())
+    })?;
+    decode_transaction(&bytes)
+}
+
+impl Transaction {
+    /// Кодирует транзакцию в hex-строку канонического формата для указанной цепочки
+    pub fn to_raw_hex(&self, chain_id: u64) -> String {
+        hex::encode(encode_transaction(self, chain_id))
+    }
+}
+
+impl Blockchain {
+    /// Принимает подписанную транзакцию в каноническом формате (байты или hex) и ставит ее в очередь.
+    /// Это единственная точка входа для транзакций, собранных внешними кошельками.
+    pub fn submit_raw_transaction(&mut self, raw: impl AsRef<[u8]>) -> Result<String, BlockchainError> {
+        let transaction = self.check_raw_transaction(raw.as_ref())?;
+        let tx_id = transaction.id.clone();
+        self.add_transaction(transaction)?;
+        Ok(tx_id)
+    }
+    
+    /// Разбирает сырую транзакцию и проверяет идентификатор цепочки, подпись и повтор
+    pub(crate) fn check_raw_transaction(&self, raw: &[u8]) -> Result<Transaction, BlockchainError> {
+        let RawTransaction { chain_id, mut transaction } = decode_raw(raw)?;
+        
+        if chain_id != self.chain_id {
+            return Err(BlockchainError::WrongChainId { expected: self.chain_id, actual: chain_id });
+        }
+        // Транзакция подписана для этой цепочки, поэтому ее идентификатор посчитан алгоритмом цепочки
+        transaction.hash_algorithm = self.hash_algorithm;
+        if !transaction.verify_integrity() {
+            return Err(BlockchainError::InvalidSignature { tx_id: transaction.id.clone() });
+        }
+        
+        // Повтор подтвержденной транзакции отклонила бы и проверка номера, но по идентификатору
+        // ошибка точнее, а старые транзакции без номера иначе не отсечь
+        if self.mempool.contains(&transaction.id) || self.find_transaction(&transaction.id).is_some() {
+            return Err(BlockchainError::DuplicateTransaction { tx_id: transaction.id.clone() });
+        }
+        Ok(transaction)
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    put_bytes(out, value.as_bytes());
+}
+
+fn put_approvals(out: &mut Vec<u8>, approvals: &[String]) {
+    out.extend_from_slice(&(approvals.len() as u32).to_be_bytes());
+    for signer in approvals {
+        put_str(out, signer);
+    }
+}
+
+fn malformed(offset: usize, field: &str, reason: String) -> BlockchainError {
+    BlockchainError::MalformedTransaction { offset, field: field.to_string(), reason }
+}
+
+/// Читатель с учетом смещения, чтобы сообщать, где именно оборвался разбор
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, field: &str) -> Result<&'a [u8], BlockchainError> {
+        let remaining = self.bytes.len() - self.offset;
+        if len > remaining {
+            return Err(malformed(self.offset, field, format!("needs {} bytes, {} left", len, remaining)));
+        }
+        let slice = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(slice)
+    }
+    
+    fn array<const N: usize>(&mut self, field: &str) -> Result<[u8; N], BlockchainError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N, field)?);
+        Ok(array)
+    }
+    
+    fn u8(&mut self, field: &str) -> Result<u8, BlockchainError> {
+        Ok(self.array::<1>(field)?[0])
+    }
+    
+    fn u32(&mut self, field: &str) -> Result<u32, BlockchainError> {
+        Ok(u32::from_be_bytes(self.array(field)?))
+    }
+    
+    fn u64(&mut self, field: &str) -> Result<u64, BlockchainError> {
+        Ok(u64::from_be_bytes(self.array(field)?))
+    }
+    
+    fn bytes(&mut self, field: &str) -> Result<Vec<u8>, BlockchainError> {
+        let len = self.u32(field)? as usize;
+        Ok(self.take(len, field)?.to_vec())
+    }
+    
+    fn string(&mut self, field: &str) -> Result<String, BlockchainError> {
+        let start = self.offset;
+        let bytes = self.bytes(field)?;
+        String::from_utf8(bytes).map_err(|e| malformed(start + 4 + e.utf8_error().valid_up_to(), field, "invalid UTF-8".to_string()))
+    }
+    
+    fn approvals(&mut self) -> Result<Vec<String>, BlockchainError> {
+        let start = self.offset;
+        let count = self.u32("approval_count")?;
+        if count > MAX_APPROVALS {
+            return Err(malformed(start, "approval_count", format!("more than {} approvals", MAX_APPROVALS)));
+        }
+        (0..count).map(|_| self.string("approval")).collect()
+    }
+    
+    fn entry_count(&mut self, field: &str) -> Result<u32, BlockchainError> {
+        let start = self.offset;
+        let count = self.u32(field)?;
+        if count == 0 || count > MAX_UTXO_ENTRIES {
+            return Err(malformed(start, field, format!("expected 1 to {} entries", MAX_UTXO_ENTRIES)));
+        }
+        Ok(count)
+    }
+    
+    fn inputs(&mut self) -> Result<Vec<TxInput>, BlockchainError> {
+        let count = self.entry_count("input_count")?;
+        (0..count).map(|_| Ok(TxInput {
+            outpoint: OutPoint { txid: self.string("input_txid")?, index: self.u32("input_index")? },
+            signature: self.string("input_signature")?,
+            public_key: None,
+        })).collect()
+    }
+    
+    fn input_keys(&mut self, inputs: &mut [TxInput]) -> Result<(), BlockchainError> {
+        let start = self.offset;
+        if self.u32("input_key_count")? as usize != inputs.len() {
+            return Err(malformed(start, "input_key_count", format!("expected one key for each of {} inputs", inputs.len())));
+        }
+        for input in inputs {
+            let public_key = self.string("input_key")?;
+            input.public_key = (!public_key.is_empty()).then_some(public_key);
+        }
+        Ok(())
+    }
+    
+    fn outputs(&mut self) -> Result<Vec<TxOutput>, BlockchainError> {
+        let count = self.entry_count("output_count")?;
+        (0..count).map(|_| Ok(TxOutput {
+            address: self.string("output_address")?,
+            amount: f64::from_bits(self.u64("output_amount")?),
+        })).collect()
+    }
+    
+    fn flag(&mut self, field: &str) -> Result<bool, BlockchainError> {
+        let start = self.offset;
+        match self.u8(field)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(malformed(start, field, format!("invalid flag {}", value))),
+        }
+    }
+    
+    fn proposal_action(&mut self) -> Result<ProposalAction, BlockchainError> {
+        let start = self.offset;
+        match self.u8("action")? {
+            0 => Ok(ProposalAction::SetParam {
+                name: self.string("param")?,
+                value: f64::from_bits(self.u64("value")?),
+            }),
+            1 => Ok(ProposalAction::Signal(self.string("signal")?)),
+            tag => Err(malformed(start, "action", format!("unknown proposal action {}", tag))),
+        }
+    }
+    
+    fn vote_choice(&mut self) -> Result<VoteChoice, BlockchainError> {
+        let start = self.offset;
+        match self.u8("choice")? {
+            0 => Ok(VoteChoice::Yes),
+            1 => Ok(VoteChoice::No),
+            2 => Ok(VoteChoice::Abstain),
+            tag => Err(malformed(start, "choice", format!("unknown vote choice {}", tag))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed_transfer, ChainFixture};
+    
+    #[test]
+    fn externally_signed_transfer_is_accepted_and_mined() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let tx = signed_transfer(&chain, "alice", "bob", 10.0);
+        
+        let tx_id = chain.submit_raw_transaction(tx.to_raw_hex(chain.chain_id)).unwrap();
+        assert_eq!(tx_id, tx.id);
+        assert!(matches!(chain.submit_raw_transaction(encode_transaction(&tx, chain.chain_id)), Err(BlockchainError::DuplicateTransaction { .. })));
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.get_latest_block().transactions.iter().any(|mined| mined.id == tx_id));
+        assert_eq!(chain.get_balance("bob"), 10.0);
+        assert_eq!(chain.get_balance("alice"), 100.0 - 10.0 - tx.fee);
+    }
+    
+    #[test]
+    fn truncation_and_tampering_fail_differently() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let tx = signed_transfer(&chain, "alice", "bob", 10.0);
+        let hex = tx.to_raw_hex(chain.chain_id);
+        
+        // Версия, идентификатор цепочки и длина id занимают 13 байт, обрыв приходится на сам id
+        let truncated = chain.submit_raw_transaction(&hex[..2 * 18]);
+        assert!(matches!(truncated, Err(BlockchainError::MalformedTransaction { offset: 13, ref field, .. }) if field == "id"), "{:?}", truncated);
+        assert!(matches!(chain.submit_raw_transaction(&hex[..hex.len() - 1]), Err(BlockchainError::MalformedTransaction { ref field, .. }) if field == "hex"));
+        
+        let mut tampered = tx.clone();
+        let flipped = if tampered.signature.starts_with('0') { "1" } else { "0" };
+        tampered.signature.replace_range(..1, flipped);
+        let refused = chain.submit_raw_transaction(tampered.to_raw_hex(chain.chain_id));
+        assert!(matches!(refused, Err(BlockchainError::InvalidSignature { ref tx_id }) if *tx_id == tx.id), "{:?}", refused);
+        
+        assert!(chain.mempool().is_empty());
+        assert!(chain.mempool.is_empty());
+    }
+    
+    #[test]
+    fn transaction_for_another_chain_is_refused() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let tx = signed_transfer(&chain, "alice", "bob", 10.0);
+        
+        let refused = chain.submit_raw_transaction(tx.to_raw_hex(chain.chain_id + 1));
+        let expected = chain.chain_id;
+        assert!(matches!(refused, Err(BlockchainError::WrongChainId { expected: e, actual }) if e == expected && actual == expected + 1));
+        assert!(chain.submit_raw_transaction(tx.to_raw_hex(chain.chain_id)).is_ok());
+    }
+}
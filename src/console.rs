@@ -0,0 +1,288 @@
+use crate::blockchain::Blockchain;
+use crate::cli::{self, Command};
+use crate::errors::BlockchainError;
+use clap::{CommandFactory, Parser};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+use std::path::PathBuf;
+
+/// Имя файла истории команд в каталоге данных
+const HISTORY_FILE: &str = "console_history";
+
+/// Строка консоли: команды CLI и короткие команды для изучения цепочки
+#[derive(Debug, Parser)]
+#[command(multicall = true, about = "hellochain console commands")]
+enum ConsoleLine {
+    #[command(flatten)]
+    Cli(Command),
+    #[command(about = "Show wallet balance")]
+    Balance { address: String },
+    #[command(about = "Show a block by height or hash")]
+    Block { id: String },
+    #[command(about = "List pending transactions")]
+    Pending,
+    #[command(alias = "quit", about = "Leave the console")]
+    Exit,
+}
+
+/// Результат выполнения строки консоли
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleOutcome {
+    Output(String),
+    Error(String),
+    Exit,
+}
+
+/// Диспетчер команд консоли поверх блокчейна; не зависит от терминала
+pub struct Console {
+    chain: Blockchain,
+    data_dir: Option<PathBuf>,
+}
+
+impl Console {
+    /// Создает консоль; если указан каталог данных, изменения сохраняются после каждой команды
+    pub fn new(chain: Blockchain, data_dir: Option<PathBuf>) -> Self {
+        Console { chain, data_dir }
+    }
+    
+    /// Известные адреса кошельков для автодополнения
+    pub fn addresses(&self) -> Vec<String> {
+        let mut addresses: Vec<String> = self.chain.wallets.keys().cloned().collect();
+        addresses.sort();
+        addresses
+    }
+    
+    /// Разбирает и выполняет одну (возможно, многострочную) команду
+    pub fn execute_line(&mut self, line: &str) -> ConsoleOutcome {
+        let words = match shell_words::split(line) {
+            Ok(words) if words.is_empty() => return ConsoleOutcome::Output(String::new()),
+            Ok(words) => words,
+            Err(e) => return ConsoleOutcome::Error(e.to_string()),
+        };
+        
+        let parsed = match ConsoleLine::try_parse_from(words) {
+            Ok(parsed) => parsed,
+            // Справка и версия приходят от clap как "ошибка", но это обычный вывод
+            Err(e) if !e.use_stderr() => return ConsoleOutcome::Output(e.render().to_string().trim_end().to_string()),
+            Err(e) => return ConsoleOutcome::Error(e.render().to_string().trim_end().to_string()),
+        };
+        
+        match self.execute(parsed) {
+            Ok(Some(text)) => ConsoleOutcome::Output(text),
+            Ok(None) => ConsoleOutcome::Exit,
+            Err(e) => ConsoleOutcome::Error(format!("Error: {}", e)),
+        }
+    }
+    
+    fn execute(&mut self, line: ConsoleLine) -> Result<Option<String>, BlockchainError> {
+        let text = match line {
+            ConsoleLine::Exit => return Ok(None),
+            ConsoleLine::Cli(Command::Init { .. } | Command::Console | Command::Chains) => {
+                return Err(BlockchainError::InvalidTransaction("This command is not available in the console".to_string()));
+            },
+            ConsoleLine::Cli(command) => {
+                let output = cli::execute_on_chain(&mut self.chain, command)?;
+                if let Some(data_dir) = &self.data_dir {
+                    self.chain.save(data_dir)?;
+                }
+                output.text
+            },
+            ConsoleLine::Balance { address } => {
+                let wallet = self.chain.get_wallet_info(&address)
+                    .ok_or_else(|| BlockchainError::WalletNotFound { address: address.to_string() })?;
+                format!("{}: {} (staking {})", wallet.address, wallet.balance, wallet.staking_balance)
+            },
+            ConsoleLine::Block { id } => {
+                let block = match id.parse::<u64>() {
+                    Ok(height) => self.chain.get_block_by_index(height),
+                    Err(_) => self.chain.get_block_by_hash(&id),
+                };
+                let block = block.ok_or_else(|| BlockchainError::InvalidBlock(format!("Block {} not found", id)))?;
+                
+                let mut text = format!("{:?}\nPrevious: {}\nMerkle root: {}", block, block.previous_hash, block.merkle_root);
+                for tx in &block.transactions {
+                    text.push_str(&format!("\n  {} {} -> {} {} (fee {})", tx.id, tx.sender, tx.receiver, tx.amount, tx.fee));
+                }
+                text
+            },
+            ConsoleLine::Pending => {
+                let pending = self.chain.mempool().transactions();
+                if pending.is_empty() {
+                    "No pending transactions".to_string()
+                } else {
+                    pending.iter()
+                        .map(|tx| format!("{} {} -> {} {} (fee {})", tx.id, tx.sender, tx.receiver, tx.amount, tx.fee))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+            },
+        };
+        
+        Ok(Some(text))
+    }
+}
+
+/// Подсказчик для rustyline: дополнение команд и адресов, многострочный ввод
+struct ConsoleHelper {
+    addresses: Vec<String>,
+}
+
+impl Completer for ConsoleHelper {
+    type Candidate = Pair;
+    
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let start = line.char_indices().rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let prefix = &line[start..];
+        
+        // Спускаемся по дереву подкоманд clap по уже введенным словам
+        let mut command = ConsoleLine::command();
+        for word in line[..start].split_whitespace() {
+            match command.find_subcommand(word) {
+                Some(subcommand) => command = subcommand.clone(),
+                None => break,
+            }
+        }
+        
+        let names: Vec<String> = if command.has_subcommands() {
+            command.get_subcommands().map(|subcommand| subcommand.get_name().to_string()).collect()
+        } else {
+            self.addresses.clone()
+        };
+        
+        let candidates = names.into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| Pair { display: name.clone(), replacement: name })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ConsoleHelper {}
+
+impl Validator for ConsoleHelper {
+    /// Незакрытые кавычки или фигурные скобки продолжают ввод на следующей строке
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        let open_braces = input.matches('{').count() > input.matches('}').count();
+        
+        if open_braces || shell_words::split(input).is_err() {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ConsoleHelper {}
+
+/// Запускает интерактивную консоль в терминале
+pub fn run(chain: Blockchain, data_dir: Option<PathBuf>) -> Result<(), BlockchainError> {
+    let history_path = data_dir.as_ref().map(|dir| dir.join(HISTORY_FILE));
+    let mut console = Console::new(chain, data_dir);
+    
+    let mut editor: Editor<ConsoleHelper, _> = Editor::new()
+        .map_err(|e| BlockchainError::Storage(e.to_string()))?;
+    editor.set_helper(Some(ConsoleHelper { addresses: console.addresses() }));
+    if let Some(path) = &history_path {
+        let _ = editor.load_history(path);
+    }
+    
+    println!("hellochain console, type `help` for commands");
+    loop {
+        let line = match editor.readline("hellochain> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(BlockchainError::Storage(e.to_string())),
+        };
+        let _ = editor.add_history_entry(line.as_str());
+        
+        match console.execute_line(&line) {
+            ConsoleOutcome::Output(text) if text.is_empty() => {},
+            ConsoleOutcome::Output(text) => println!("{}", text),
+            ConsoleOutcome::Error(text) => eprintln!("{}", text),
+            ConsoleOutcome::Exit => break,
+        }
+        
+        if let Some(helper) = editor.helper_mut() {
+            helper.addresses = console.addresses();
+        }
+    }
+    
+    if let Some(path) = &history_path {
+        let _ = editor.save_history(path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use rustyline::history::DefaultHistory;
+    
+    fn output(console: &mut Console, line: &str) -> String {
+        match console.execute_line(line) {
+            ConsoleOutcome::Output(text) => text,
+            outcome => panic!("{}: {:?}", line, outcome),
+        }
+    }
+    
+    #[test]
+    fn scripted_session_moves_funds() {
+        // Выпускает alice, чей ключ назначен ключом эмитента
+        let mut chain = ChainFixture::chain(1, 50.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.enable_minting("alice").unwrap();
+        let mut console = Console::new(chain, None);
+        for line in ["wallet new bob", "wallet fund alice 100", "mine miner", "send alice bob 10"] {
+            output(&mut console, line);
+        }
+        assert!(output(&mut console, "pending").contains("alice -> bob 10"));
+        assert!(output(&mut console, "mine miner").starts_with("Block #2 mined"));
+        assert_eq!(output(&mut console, "pending"), "No pending transactions");
+        assert!(output(&mut console, "block 2").contains("alice -> bob 10"));
+        assert_eq!(output(&mut console, "balance bob"), "bob: 10 (staking 0)");
+        assert_eq!(console.addresses(), ["alice", "bob", "miner"]);
+        assert!(console.chain.is_chain_valid());
+        
+        assert_eq!(console.execute_line("balance carol"), ConsoleOutcome::Error("Error: Wallet carol not found".to_string()));
+        assert_eq!(
+            console.execute_line("send alice bob 1000"),
+            ConsoleOutcome::Error("Error: Insufficient funds: 1001 required, 89.99 available".to_string()),
+        );
+        assert!(matches!(console.execute_line("frobnicate"), ConsoleOutcome::Error(_)));
+        assert!(output(&mut console, "help").contains("wallet"));
+        assert_eq!(console.execute_line("quit"), ConsoleOutcome::Exit);
+    }
+    
+    #[test]
+    fn completes_commands_and_addresses() {
+        let helper = ConsoleHelper { addresses: vec!["alice".to_string(), "bob".to_string()] };
+        let history = DefaultHistory::new();
+        let context = Context::new(&history);
+        let complete = |line: &str| {
+            let (start, candidates) = helper.complete(line, line.len(), &context).unwrap();
+            (start, candidates.into_iter().map(|pair| pair.replacement).collect::<Vec<_>>())
+        };
+        
+        assert_eq!(complete("wallet ba"), (7, vec!["balance".to_string()]));
+        assert_eq!(complete("balance a"), (8, vec!["alice".to_string()]));
+        assert_eq!(complete("se").1, ["send"]);
+        // Многобайтовые пробелы тоже разделяют слова
+        assert_eq!(complete("balance\u{3000}a"), (10, vec!["alice".to_string()]));
+        assert_eq!(complete("balance\u{a0}"), (9, vec!["alice".to_string(), "bob".to_string()]));
+    }
+}
@@ -0,0 +1,322 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::{TransactionType, calculate_hash};
+use sha2::{Sha256, Digest};
+use std::io::{self, Write};
+
+/// Параметры потоковой загрузки данных в блокчейн
+#[derive(Debug, Clone)]
+pub struct DataStreamOptions {
+    pub chunk_size: usize,
+}
+
+impl Default for DataStreamOptions {
+    fn default() -> Self {
+        DataStreamOptions { chunk_size: 64 * 1024 }
+    }
+}
+
+/// Итог потоковой загрузки: идентификатор данных и транзакции фрагментов
+#[derive(Debug, Clone)]
+pub struct DataStreamReceipt {
+    pub data_id: String,
+    pub chunk_tx_ids: Vec<String>,
+    pub manifest_tx_id: String,
+}
+
+/// Потоковый писатель, который режет данные на фрагменты и отправляет их транзакциями
+pub struct DataWriter<'a> {
+    chain: &'a mut Blockchain,
+    sender: String,
+    data_id: String,
+    options: DataStreamOptions,
+    buffer: Vec<u8>,
+    hasher: Sha256,
+    total_len: u64,
+    chunk_tx_ids: Vec<String>,
+    closed: bool,
+}
+
+impl<'a> DataWriter<'a> {
+    /// Возвращает идентификатор загружаемых данных
+    pub fn data_id(&self) -> &str {
+        &self.data_id
+    }
+    
+    /// Возвращает идентификаторы уже отправленных фрагментов
+    pub fn chunk_tx_ids(&self) -> &[String] {
+        &self.chunk_tx_ids
+    }
+    
+    /// Отправляет оставшийся буфер и манифест, завершая загрузку
+    pub fn finish(mut self) -> Result<DataStreamReceipt, BlockchainError> {
+        self.emit_full_chunks()?;
+        if !self.buffer.is_empty() {
+            let chunk = std::mem::take(&mut self.buffer);
+            self.emit_chunk(chunk).map_err(|(error, _)| error)?;
+        }
+        
+        let digest = format!("{:x}", self.hasher.clone().finalize());
+        let mut manifest = self.chain.new_transaction(
+            self.sender.clone(),
+            String::from("BLOCKCHAIN_DATA"),
+            0.1,
+            TransactionType::DataManifest {
+                data_id: self.data_id.clone(),
+                chunk_count: self.chunk_tx_ids.len() as u32,
+                total_len: self.total_len,
+                digest,
+            }
+        );
+        self.chain.sign_as_sender(&mut manifest);
+        let manifest_tx_id = manifest.id.clone();
+        self.chain.add_transaction(manifest)?;
+        
+        self.closed = true;
+        Ok(DataStreamReceipt {
+            data_id: self.data_id.clone(),
+            chunk_tx_ids: std::mem::take(&mut self.chunk_tx_ids),
+            manifest_tx_id,
+        })
+    }
+    
+    /// Отбрасывает буфер и отменяет фрагменты, которые ещё не попали в блок
+    pub fn abort(mut self) {
+        self.cancel_pending_chunks();
+    }
+    
+    fn cancel_pending_chunks(&mut self) {
+        self.closed = true;
+        self.buffer.clear();
+        for tx_id in std::mem::take(&mut self.chunk_tx_ids) {
+            let _ = self.chain.cancel_pending_transaction(&tx_id);
+        }
+    }
+    
+    fn emit_full_chunks(&mut self) -> Result<(), BlockchainError> {
+        while self.buffer.len() >= self.options.chunk_size {
+            let rest = self.buffer.split_off(self.options.chunk_size);
+            let chunk = std::mem::replace(&mut self.buffer, rest);
+            if let Err((error, mut chunk)) = self.emit_chunk(chunk) {
+                chunk.append(&mut self.buffer);
+                self.buffer = chunk;
+                return Err(error);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Отправляет фрагмент; если пул его не принял, байты фрагмента возвращаются вместе с ошибкой
+    fn emit_chunk(&mut self, payload: Vec<u8>) -> Result<(), (BlockchainError, Vec<u8>)> {
+        let mut tx = self.chain.new_transaction(
+            self.sender.clone(),
+            String::from("BLOCKCHAIN_DATA"),
+            0.1,
+            TransactionType::DataChunk {
+                data_id: self.data_id.clone(),
+                index: self.chunk_tx_ids.len() as u32,
+                payload,
+            }
+        );
+        self.chain.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        if let Err((error, tx)) = self.chain.try_add_transaction(tx) {
+            let payload = match tx.transaction_type {
+                TransactionType::DataChunk { payload, .. } => payload,
+                _ => Vec::new(),
+            };
+            return Err((error, payload));
+        }
+        self.chunk_tx_ids.push(tx_id);
+        Ok(())
+    }
+}
+
+impl Write for DataWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.closed {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "data stream is closed"));
+        }
+        
+        self.emit_full_chunks().map_err(to_io_error)?;
+        
+        let room = self.options.chunk_size - self.buffer.len();
+        let accepted = &buf[..room.min(buf.len())];
+        if accepted.len() == room {
+            // Заполненный фрагмент отправляется сразу; если отправка не удалась, байты не принимаются
+            // и эта же запись возвращает ошибку
+            let mut chunk = std::mem::take(&mut self.buffer);
+            chunk.extend_from_slice(accepted);
+            if let Err((error, mut chunk)) = self.emit_chunk(chunk) {
+                chunk.truncate(chunk.len() - accepted.len());
+                self.buffer = chunk;
+                return Err(to_io_error(error));
+            }
+        } else {
+            self.buffer.extend_from_slice(accepted);
+        }
+        self.hasher.update(accepted);
+        self.total_len += accepted.len() as u64;
+        Ok(accepted.len())
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        self.emit_full_chunks().map_err(to_io_error)
+    }
+}
+
+impl Drop for DataWriter<'_> {
+    fn drop(&mut self) {
+        if !self.closed {
+            self.cancel_pending_chunks();
+        }
+    }
+}
+
+/// Преобразует ошибку блокчейна в ошибку ввода-вывода для интерфейса Write
+fn to_io_error(error: BlockchainError) -> io::Error {
+    match error {
+        BlockchainError::MempoolFull { .. } => io::Error::new(io::ErrorKind::WouldBlock, error),
+        other => io::Error::other(other),
+    }
+}
+
+impl Blockchain {
+    /// Открывает потоковую загрузку данных от имени отправителя
+    pub fn open_data_stream(&mut self, sender: String, options: DataStreamOptions) -> Result<DataWriter<'_>, BlockchainError> {
+        if options.chunk_size == 0 {
+            return Err(BlockchainError::InvalidTransaction("Chunk size must be positive".to_string()));
+        }
+        
+        if !self.wallets.contains_key(&sender) {
+            return Err(BlockchainError::WalletNotFound { address: sender.to_string() });
+        }
+        
+        let salt = self.entropy.next_u64();
+        let data_id = format!("data_{}", calculate_hash(self.hash_algorithm, &format!("{}{}{}", sender, self.now_ts(), salt)));
+        
+        Ok(DataWriter {
+            chain: self,
+            sender,
+            data_id,
+            options,
+            buffer: Vec::new(),
+            hasher: Sha256::new(),
+            total_len: 0,
+            chunk_tx_ids: Vec::new(),
+            closed: false,
+        })
+    }
+    
+    /// Собирает загруженные потоком данные из фрагментов в блоках и сверяет их с манифестом
+    pub fn read_data(&self, data_id: &str) -> Option<Vec<u8>> {
+        let mut manifest = None;
+        let mut chunks: Vec<(u32, &[u8])> = Vec::new();
+        
+        for block in &self.chain {
+            for tx in &block.transactions {
+                match &tx.transaction_type {
+                    TransactionType::DataChunk { data_id: id, index, payload } if id == data_id => {
+                        chunks.push((*index, payload));
+                    },
+                    TransactionType::DataManifest { data_id: id, chunk_count, total_len, digest } if id == data_id => {
+                        manifest = Some((*chunk_count, *total_len, digest.clone()));
+                    },
+                    _ => {}
+                }
+            }
+        }
+        
+        let (chunk_count, total_len, digest) = manifest?;
+        chunks.sort_by_key(|(index, _)| *index);
+        chunks.dedup_by_key(|(index, _)| *index);
+        
+        if chunks.len() != chunk_count as usize || chunks.iter().enumerate().any(|(i, (index, _))| *index != i as u32) {
+            return None;
+        }
+        
+        let data: Vec<u8> = chunks.into_iter().flat_map(|(_, payload)| payload.iter().copied()).collect();
+        if data.len() as u64 != total_len || format!("{:x}", Sha256::digest(&data)) != digest {
+            return None;
+        }
+        
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    fn chain_with_alice(funded: bool) -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        if funded {
+            chain.mine_pending_transactions("alice".to_string()).unwrap();
+        }
+        chain
+    }
+    
+    #[test]
+    fn streamed_data_is_read_back_after_mining() {
+        let mut chain = chain_with_alice(true);
+        let data: Vec<u8> = (0..10).collect();
+        let mut writer = chain.open_data_stream("alice".to_string(), DataStreamOptions { chunk_size: 4 }).unwrap();
+        writer.write_all(&data).unwrap();
+        assert_eq!(writer.chunk_tx_ids().len(), 2);
+        let receipt = writer.finish().unwrap();
+        assert_eq!(receipt.chunk_tx_ids.len(), 3);
+        
+        assert_eq!(chain.read_data(&receipt.data_id), None);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.read_data(&receipt.data_id), Some(data.clone()));
+        assert_eq!(chain.get_data(&receipt.data_id), Some(data));
+    }
+    
+    #[test]
+    fn failed_chunk_is_reported_by_the_write_that_fills_it() {
+        let mut chain = chain_with_alice(false);
+        let mut writer = chain.open_data_stream("alice".to_string(), DataStreamOptions { chunk_size: 4 }).unwrap();
+        assert_eq!(writer.write(&[1, 2, 3]).unwrap(), 3);
+        // У alice нет средств на фрагмент: ошибку возвращает эта же запись, и ее байты не приняты
+        assert!(writer.write(&[4, 5]).is_err());
+        assert!(writer.chunk_tx_ids().is_empty());
+        assert!(writer.flush().is_ok());
+        drop(writer);
+        assert!(chain.mempool().transactions().iter().all(|tx| tx.transaction_type == TransactionType::RegisterKey));
+    }
+    
+    #[test]
+    fn abort_cancels_chunks_still_in_the_pool() {
+        let mut chain = chain_with_alice(true);
+        let balance = chain.get_balance("alice");
+        let mut writer = chain.open_data_stream("alice".to_string(), DataStreamOptions { chunk_size: 4 }).unwrap();
+        writer.write_all(&[0; 10]).unwrap();
+        let chunk_tx_ids = writer.chunk_tx_ids().to_vec();
+        assert_eq!(chunk_tx_ids.len(), 2);
+        writer.abort();
+        
+        assert!(chunk_tx_ids.iter().all(|tx_id| !chain.mempool().contains(tx_id)));
+        assert_eq!(chain.get_balance("alice"), balance);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.get_latest_block().transactions.iter().all(|tx| !matches!(tx.transaction_type, TransactionType::DataChunk { .. })));
+    }
+    
+    #[test]
+    fn full_pool_is_reported_as_would_block() {
+        let mut chain = chain_with_alice(true);
+        chain.mempool.set_capacity(Some(1));
+        let mut writer = chain.open_data_stream("alice".to_string(), DataStreamOptions { chunk_size: 4 }).unwrap();
+        assert_eq!(writer.write(&[1, 2, 3, 4, 5, 6]).unwrap(), 4);
+        
+        // Второй фрагмент в пул не помещается: запись просит повторить позже и байты не принимает
+        let error = writer.write(&[5, 6, 7, 8]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(writer.chunk_tx_ids().len(), 1);
+        assert_eq!(writer.write(&[5, 6, 7]).unwrap(), 3);
+        assert_eq!(writer.write(&[8]).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+/// Сколько первых символов хеша или идентификатора показывать в подписях
+const SHORT_HASH_LEN: usize = 8;
+
+/// Что включать в граф цепочки
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// Высоты блоков, в том числе блоков веток; без значения — все
+    pub heights: Option<RangeInclusive<u64>>,
+    /// Рисовать транзакции каждого блока отдельным подграфом; годится только для небольших цепочек
+    pub include_transactions: bool,
+}
+
+fn short(hash: &str) -> &str {
+    hash.get(..SHORT_HASH_LEN).unwrap_or(hash)
+}
+
+/// Экранирует строку для записи в кавычках DOT
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Кто выпустил блок: валидатор или получатель награды за блок
+fn producer(block: &Block) -> Option<&str> {
+    block.validator.as_deref().or_else(|| {
+        block.transactions.iter().find(|tx| tx.sender == "BLOCKCHAIN_REWARD").map(|tx| tx.receiver.as_str())
+    })
+}
+
+/// Узел блока, а с транзакциями — кластер из блока и его транзакций
+fn write_block(out: &mut String, block: &Block, style: &str, options: &DotOptions, indent: &str) {
+    let mut label = format!("#{}\\n{}\\n{} tx", block.index, short(&block.hash), block.transactions.len());
+    if let Some(producer) = producer(block) {
+        let _ = write!(label, "\\n{}", escape(producer));
+    }
+    if !options.include_transactions || block.transactions.is_empty() {
+        let _ = writeln!(out, "{}\"{}\" [label=\"{}\", {}];", indent, block.hash, label, style);
+        return;
+    }
+    
+    let _ = writeln!(out, "{}subgraph \"cluster_{}\" {{", indent, block.hash);
+    let _ = writeln!(out, "{}    style=dotted;", indent);
+    let _ = writeln!(out, "{}    \"{}\" [label=\"{}\", {}];", indent, block.hash, label, style);
+    for tx in &block.transactions {
+        let _ = writeln!(
+            out, "{}    \"{}/{}\" [shape=note, label=\"{}\\n{} -> {}\\n{}\"];",
+            indent, block.hash, tx.id, short(&tx.id), escape(&tx.sender), escape(&tx.receiver), tx.amount
+        );
+        let _ = writeln!(out, "{}    \"{}\" -> \"{}/{}\" [style=dotted, arrowhead=none];", indent, block.hash, block.hash, tx.id);
+    }
+    let _ = writeln!(out, "{}}}", indent);
+}
+
+impl Blockchain {
+    /// Граф цепочки и ее веток в формате Graphviz DOT. Ребра идут от родителя к блоку, блоки
+    /// главной цепочки обведены жирно, блоки веток — пунктиром, окончательные блоки собраны в
+    /// затененный кластер. Для одной и той же цепочки и веток текст всегда одинаков.
+    pub fn export_dot(&self, options: &DotOptions) -> String {
+        let in_range = |block: &Block| options.heights.as_ref().is_none_or(|heights| heights.contains(&block.index));
+        let finalized = self.finalized_height();
+        let main: Vec<&Block> = self.chain.iter().filter(|block| in_range(block)).collect();
+        let mut side: Vec<&Block> = self.forks.blocks().filter(|block| in_range(block)).collect();
+        side.sort_by(|a, b| a.index.cmp(&b.index).then_with(|| a.hash.cmp(&b.hash)));
+        
+        let mut out = String::from("digraph chain {\n    rankdir=LR;\n    node [shape=box, fontname=\"monospace\"];\n");
+        let main_style = "style=\"filled,bold\", fillcolor=white";
+        let (settled, recent): (Vec<&Block>, Vec<&Block>) = main.iter().partition(|block| block.index <= finalized);
+        if !settled.is_empty() {
+            out.push_str("    subgraph cluster_finalized {\n        label=\"finalized\";\n        style=filled;\n        fillcolor=lightgrey;\n");
+            for block in settled {
+                write_block(&mut out, block, main_style, options, "        ");
+            }
+            out.push_str("    }\n");
+        }
+        for block in recent {
+            write_block(&mut out, block, main_style, options, "    ");
+        }
+        for block in &side {
+            write_block(&mut out, block, "style=dashed, color=gray40", options, "    ");
+        }
+        
+        for pair in main.windows(2) {
+            if let [parent, block] = pair {
+                let _ = writeln!(out, "    \"{}\" -> \"{}\";", parent.hash, block.hash);
+            }
+        }
+        for block in &side {
+            let parent_shown = main.iter().chain(&side).any(|parent| parent.hash == block.previous_hash);
+            if parent_shown {
+                let _ = writeln!(out, "    \"{}\" -> \"{}\" [style=dashed];", block.previous_hash, block.hash);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{BlockAcceptance, ConsensusAlgorithm};
+    use crate::forks::FINALITY_DEPTH;
+    use crate::test_support::ChainFixture;
+    
+    /// Цепочка из `FINALITY_DEPTH + 3` блоков и боковая ветка из одного блока от предпоследнего
+    fn forked_chain() -> (Blockchain, Block) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        for _ in 0..FINALITY_DEPTH + 2 {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        let mut rival: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        rival.mine_pending_transactions("rival".to_string()).unwrap();
+        let side = rival.get_latest_block().clone();
+        assert_eq!(chain.accept_block(side.clone()).unwrap(), BlockAcceptance::Forked);
+        (chain, side)
+    }
+    
+    /// Нестрогая проверка синтаксиса: граф открыт и закрыт, скобки и кавычки парные, а каждая
+    /// строка внутри — открытие подграфа, его закрытие или оператор с точкой с запятой
+    fn check_dot(dot: &str) {
+        assert!(dot.starts_with("digraph chain {\n") && dot.ends_with("}\n"), "{}", dot);
+        let mut depth = 0i32;
+        for line in dot.lines() {
+            let line = line.trim();
+            let unescaped = line.replace("\\\\", "").replace("\\\"", "");
+            assert!(unescaped.matches('"').count().is_multiple_of(2), "{}", line);
+            if line.ends_with('{') {
+                depth += 1;
+            } else if line == "}" {
+                depth -= 1;
+            } else {
+                assert!(line.ends_with(';'), "{}", line);
+            }
+            assert!(depth >= 0, "{}", line);
+        }
+        assert_eq!(depth, 0);
+    }
+    
+    #[test]
+    fn side_branch_is_drawn_with_its_fork_edge() {
+        let (chain, side) = forked_chain();
+        let dot = chain.export_dot(&DotOptions::default());
+        check_dot(&dot);
+        
+        let nodes = dot.lines().filter(|line| line.contains("[label=")).count();
+        assert_eq!(nodes, chain.chain.len() + 1);
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\" [style=dashed];", side.previous_hash, side.hash)));
+        assert!(dot.contains(&format!("\"{}\" [label=\"#{}\\n{}\\n2 tx\\nrival\", style=dashed, color=gray40];", side.hash, side.index, short(&side.hash))));
+        let tip = chain.get_latest_block();
+        assert!(dot.contains(&format!("\"{}\" [label=\"#{}\\n{}\\n1 tx\\nminer\", style=\"filled,bold\", fillcolor=white];", tip.hash, tip.index, short(&tip.hash))));
+        
+        // Окончательные блоки лежат в затененном кластере, а вершина — вне его
+        let finalized = dot.split("subgraph cluster_finalized {").nth(1).and_then(|rest| rest.split("\n    }\n").next()).unwrap();
+        assert!(finalized.contains("fillcolor=lightgrey"));
+        assert_eq!(finalized.matches("[label=").count() as u64, chain.finalized_height() + 1);
+        assert!(!finalized.contains(&tip.hash));
+        assert_eq!(chain.export_dot(&DotOptions::default()), dot);
+    }
+    
+    #[test]
+    fn height_range_leaves_out_other_blocks() {
+        let (chain, side) = forked_chain();
+        let heights = side.index - 1..=side.index;
+        let dot = chain.export_dot(&DotOptions { heights: Some(heights.clone()), include_transactions: false });
+        check_dot(&dot);
+        
+        assert_eq!(dot.lines().filter(|line| line.contains("[label=")).count(), 3);
+        for block in &chain.chain {
+            assert_eq!(dot.contains(&format!("\"{}\" [", block.hash)), heights.contains(&block.index), "# {}", block.index);
+        }
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\" [style=dashed];", side.previous_hash, side.hash)));
+        // Без родителя в диапазоне ребро ветки не рисуется
+        let only_side = chain.export_dot(&DotOptions { heights: Some(side.index..=side.index), include_transactions: false });
+        check_dot(&only_side);
+        assert!(!only_side.contains("->"));
+    }
+    
+    #[test]
+    fn transactions_are_drawn_in_block_clusters() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice \"quoted\"".to_string()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let dot = chain.export_dot(&DotOptions { heights: None, include_transactions: true });
+        check_dot(&dot);
+        
+        let block = chain.get_latest_block();
+        assert!(dot.contains(&format!("subgraph \"cluster_{}\" {{", block.hash)));
+        for tx in &block.transactions {
+            assert!(dot.contains(&format!("\"{}\" -> \"{}/{}\" [style=dotted, arrowhead=none];", block.hash, block.hash, tx.id)));
+        }
+        assert!(dot.contains("alice \\\"quoted\\\""));
+    }
+}
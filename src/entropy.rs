@@ -0,0 +1,263 @@
+use crate::block::{pos_threshold, Block};
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::hashing::HashKind;
+use crate::transaction::{calculate_hash, hash_bytes};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::sync::{Arc, Mutex};
+
+/// Доля слотов, в которых делегат DPoS может выпустить блок
+pub const DPOS_SCHEDULE_SHARE: f64 = 0.5;
+/// Сколько валидаторов с наибольшими ставками избираются делегатами DPoS
+pub const DPOS_DELEGATE_COUNT: usize = 21;
+
+/// Случайность для решений вне консенсуса: соли идентификаторов, адреса новых кошельков.
+/// Решения консенсуса берутся только из `consensus_draw`.
+pub trait EntropySource: Send + Sync {
+    fn fill_bytes(&self, dest: &mut [u8]);
+    
+    fn next_u64(&self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+}
+
+/// Случайность операционной системы
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        rand::fill(dest);
+    }
+}
+
+/// Воспроизводимая последовательность из зерна для тестов и симуляций
+#[derive(Debug)]
+pub struct SeededEntropy {
+    rng: Mutex<StdRng>,
+}
+
+impl SeededEntropy {
+    pub fn new(seed: u64) -> Self {
+        SeededEntropy { rng: Mutex::new(StdRng::seed_from_u64(seed)) }
+    }
+}
+
+impl EntropySource for SeededEntropy {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        self.rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).fill_bytes(dest);
+    }
+}
+
+pub(crate) fn os_entropy() -> Arc<dyn EntropySource> {
+    Arc::new(OsEntropy)
+}
+
+/// Жребий консенсуса в [0, 1), одинаковый на всех узлах. Строка
+/// `домен|хеш предыдущего блока|высота|хеш набора валидаторов|адрес|слот` хешируется SHA-256,
+/// первые 8 байт читаются как big-endian u64, и его старшие 53 бита делятся на 2^53.
+pub fn consensus_draw(domain: &str, previous_hash: &str, height: u64, validator_set_hash: &str, address: &str, slot: i64) -> f64 {
+    let digest = hash_bytes(HashKind::Sha256, format!("{}|{}|{}|{}|{}|{}", domain, previous_hash, height, validator_set_hash, address, slot).as_bytes());
+    let [b0, b1, b2, b3, b4, b5, b6, b7, ..] = digest;
+    (u64::from_be_bytes([b0, b1, b2, b3, b4, b5, b6, b7]) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Blockchain {
+    /// Подменяет источник случайности вне консенсуса
+    pub fn set_entropy(&mut self, entropy: Arc<dyn EntropySource>) {
+        self.entropy = entropy;
+    }
+    
+    /// Хеш активных валидаторов со ставками в порядке адресов
+    pub fn validator_set_hash(&self) -> String {
+        let mut validators: Vec<(&String, &f64)> = self.validators.iter()
+            .filter(|(address, _)| !self.jailed_validators.contains(*address))
+            .collect();
+        validators.sort_by(|a, b| a.0.cmp(b.0));
+        let set: Vec<String> = validators.iter().map(|(address, stake)| format!("{}:{}", address, stake)).collect();
+        calculate_hash(self.hash_algorithm, &set.join(","))
+    }
+    
+    /// Избранные делегаты DPoS: до `DPOS_DELEGATE_COUNT` незаблокированных валидаторов с наибольшими
+    /// ставками, при равных ставках — по адресу
+    pub fn elected_delegates(&self) -> Vec<&str> {
+        let mut delegates: Vec<(&String, &f64)> = self.validators.iter()
+            .filter(|(address, _)| !self.jailed_validators.contains(*address))
+            .collect();
+        delegates.sort_by(|a, b| b.1.total_cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        delegates.into_iter().take(DPOS_DELEGATE_COUNT).map(|(address, _)| address.as_str()).collect()
+    }
+    
+    fn slot_duration(&self) -> i64 {
+        self.stake_params().unwrap_or_default().slot_duration.max(1) as i64
+    }
+    
+    /// Жребий производителя блока. Слот — время блока в интервалах `slot_duration` из `StakeParams`,
+    /// поэтому не вытянувший жребий валидатор может попробовать снова в следующем слоте.
+    pub fn producer_draw(&self, block: &Block, producer: &str) -> f64 {
+        let domain = match self.consensus_algorithm {
+            ConsensusAlgorithm::DelegatedProofOfStake => "dpos",
+            _ => "pos",
+        };
+        let slot = block.timestamp.div_euclid(self.slot_duration());
+        consensus_draw(domain, &block.previous_hash, block.index, &self.validator_set_hash(), producer, slot)
+    }
+    
+    /// Последняя секунда слота, следующего за слотом вершины. Блок DPoS не может быть позже: иначе
+    /// делегат перебирал бы времена будущих слотов, пока не вытянет жребий.
+    fn latest_delegate_timestamp(&self) -> i64 {
+        let slot_duration = self.slot_duration();
+        let tip_slot = self.get_latest_block().timestamp.div_euclid(slot_duration);
+        tip_slot.saturating_add(2).saturating_mul(slot_duration).saturating_sub(1)
+    }
+    
+    /// Время нового блока по часам цепочки; в DPoS не позже слота, следующего за слотом вершины
+    pub(crate) fn next_block_timestamp(&self) -> i64 {
+        match self.consensus_algorithm {
+            ConsensusAlgorithm::DelegatedProofOfStake => self.now_ts().min(self.latest_delegate_timestamp()),
+            _ => self.now_ts(),
+        }
+    }
+    
+    /// Может ли `producer` выпустить этот блок, продолжающий вершину, по жребию текущего консенсуса.
+    /// В DPoS это избранный делегат, а время блока не дальше слота, следующего за слотом вершины.
+    /// В каждом слоте выпускать может хотя бы делегат с наименьшим жребием, поэтому граница слота
+    /// не останавливает цепочку.
+    pub fn is_scheduled_producer(&self, block: &Block, producer: &str) -> bool {
+        match self.consensus_algorithm {
+            ConsensusAlgorithm::ProofOfWork => true,
+            ConsensusAlgorithm::ProofOfStake => self.validators.get(producer)
+                .is_some_and(|stake| self.producer_draw(block, producer) <= pos_threshold(*stake)),
+            ConsensusAlgorithm::DelegatedProofOfStake => {
+                let delegates = self.elected_delegates();
+                if !delegates.contains(&producer) || block.timestamp > self.latest_delegate_timestamp() {
+                    return false;
+                }
+                let draw = self.producer_draw(block, producer);
+                draw < DPOS_SCHEDULE_SHARE || delegates.iter().all(|delegate| self.producer_draw(block, delegate) >= draw)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::clock::MockClock;
+    use crate::simulation::{Simulation, SimulationConfig};
+    use crate::test_support::ChainFixture;
+    
+    /// DPoS-цепочка на управляемых часах с валидаторами `v00`, `v01`, ... и ставками 100, 110, ...
+    fn dpos_chain(validators: usize) -> (Blockchain, Arc<MockClock>) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::DelegatedProofOfStake);
+        let clock = Arc::new(MockClock::new(GENESIS_TIMESTAMP));
+        chain.set_clock(clock.clone());
+        for i in 0..validators {
+            let address = format!("v{:02}", i);
+            let stake = 100.0 + 10.0 * i as f64;
+            chain.create_wallet(address.clone()).unwrap();
+            chain.allocate_at_genesis(&address, stake).unwrap();
+            chain.add_validator(address, stake).unwrap();
+        }
+        (chain, clock)
+    }
+    
+    /// Кандидат на следующий блок со временем `timestamp`
+    fn probe(chain: &Blockchain, timestamp: i64) -> Block {
+        let tip = chain.get_latest_block();
+        Block::new_at(tip.index + 1, Vec::new(), tip.hash.clone(), 0, timestamp)
+    }
+    
+    fn scheduled(chain: &Blockchain, timestamp: i64) -> Vec<String> {
+        let block = probe(chain, timestamp);
+        let mut selected: Vec<String> = chain.validators.keys().filter(|producer| chain.is_scheduled_producer(&block, producer)).cloned().collect();
+        selected.sort();
+        selected
+    }
+    
+    #[test]
+    fn identical_chains_make_identical_selections() {
+        let (mut chain, clock) = dpos_chain(4);
+        let mut copy: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        let slot = chain.slot_duration();
+        
+        for _ in 0..3 {
+            let tip_timestamp = chain.get_latest_block().timestamp;
+            for timestamp in (tip_timestamp..=chain.latest_delegate_timestamp()).step_by(slot as usize / 4) {
+                let selected = scheduled(&chain, timestamp);
+                assert!(!selected.is_empty(), "no producer at {}", timestamp);
+                assert_eq!(scheduled(&copy, timestamp), selected);
+                for producer in chain.validators.keys() {
+                    assert_eq!(copy.producer_draw(&probe(&copy, timestamp), producer), chain.producer_draw(&probe(&chain, timestamp), producer));
+                }
+            }
+            
+            clock.advance(slot);
+            let producer = scheduled(&chain, chain.next_block_timestamp()).remove(0);
+            chain.mine_pending_transactions(producer).unwrap();
+            copy.accept_block(chain.get_latest_block().clone()).unwrap();
+        }
+        assert_eq!(copy.get_latest_block().hash, chain.get_latest_block().hash);
+    }
+    
+    #[test]
+    fn only_elected_delegates_produce_within_the_next_slot() {
+        let (mut chain, clock) = dpos_chain(DPOS_DELEGATE_COUNT + 1);
+        let delegates = chain.elected_delegates();
+        assert_eq!(delegates.len(), DPOS_DELEGATE_COUNT);
+        // Наименьшая ставка у v00, и делегатом он не избран
+        assert!(!delegates.contains(&"v00"));
+        
+        let latest = chain.latest_delegate_timestamp();
+        for timestamp in (GENESIS_TIMESTAMP..=latest).step_by(7) {
+            let selected = scheduled(&chain, timestamp);
+            assert!(!selected.is_empty());
+            assert!(!selected.contains(&"v00".to_string()));
+        }
+        for timestamp in (latest + 1..latest + 10 * chain.slot_duration()).step_by(7) {
+            assert!(scheduled(&chain, timestamp).is_empty(), "{}", timestamp);
+        }
+        
+        // Часы давно ушли вперед, но блок получает время в пределах следующего слота
+        clock.advance(100 * chain.slot_duration());
+        let producer = scheduled(&chain, chain.next_block_timestamp()).remove(0);
+        chain.mine_pending_transactions(producer).unwrap();
+        assert_eq!(chain.get_latest_block().timestamp, latest);
+        assert!(chain.mine_pending_transactions("v00".to_string()).is_err());
+    }
+    
+    #[test]
+    fn seeded_simulation_is_reproducible() {
+        let config = SimulationConfig { wallets: 6, transactions_per_block: 4, seed: 7, ..SimulationConfig::default() };
+        let first = Simulation::new(config.clone()).unwrap().run(5).unwrap();
+        let second = Simulation::new(config.clone()).unwrap().run(5).unwrap();
+        assert_eq!(first.to_json().unwrap(), second.to_json().unwrap());
+        assert_eq!(first.to_csv(), second.to_csv());
+        assert!(first.confirmed > 0);
+        
+        let other = Simulation::new(SimulationConfig { seed: 8, ..config }).unwrap().run(5).unwrap();
+        assert_ne!(other.tip_hash, first.tip_hash);
+    }
+    
+    #[test]
+    fn consensus_code_never_reaches_the_thread_generator() {
+        let patterns = [format!("{}Rng", "Thread"), format!("{}_rng(", "thread"), format!("rand::{}(", "rng")];
+        let mut directories = vec![std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src")];
+        while let Some(directory) = directories.pop() {
+            for entry in std::fs::read_dir(&directory).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    directories.push(path);
+                    continue;
+                }
+                let source = std::fs::read_to_string(&path).unwrap();
+                for (number, line) in source.lines().enumerate() {
+                    assert!(!patterns.iter().any(|pattern| line.contains(pattern.as_str())), "{}:{} uses the thread generator: {}", path.display(), number + 1, line.trim());
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,10 @@
+use serde::{Serialize, Serializer, Deserialize};
+use serde_json::{json, Value};
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum BlockchainError {
     #[error("Insufficient funds: {required} required, {available} available")]
     InsufficientBalance { required: f64, available: f64 },
@@ -9,9 +13,386 @@ pub enum BlockchainError {
     InvalidTransaction(String),
     
     #[error("Invalid block: {0}")]
-    #[allow(dead_code)] // Помечаем как используемый, чтобы убрать предупреждение
     InvalidBlock(String),
     
     #[error("Consensus error: {0}")]
     ConsensusError(String),
-}
\ No newline at end of file
+    
+    #[error("Mempool is full: {capacity} pending transactions")]
+    MempoolFull { capacity: usize },
+    
+    #[error("Storage error: {0}")]
+    Storage(String),
+    
+    #[error("Malformed transaction at byte {offset} ({field}): {reason}")]
+    MalformedTransaction { offset: usize, field: String, reason: String },
+    
+    #[error("Rate limited: {reason}, retry in {retry_after_blocks} blocks")]
+    RateLimited { reason: String, retry_after_blocks: u64 },
+    
+    #[error("Faucet is depleted: {balance} left, {required} required")]
+    FaucetDepleted { balance: f64, required: f64 },
+    
+    #[error("Wallet {address} not found")]
+    WalletNotFound { address: String },
+    
+    #[error("Wallet {address} already exists")]
+    DuplicateWallet { address: String },
+    
+    #[error("{address} is not a validator")]
+    ValidatorNotFound { address: String },
+    
+    #[error("Validator {address} is jailed")]
+    ValidatorJailed { address: String },
+    
+    #[error("{address} is not the scheduled producer of this block")]
+    NotScheduledProducer { address: String },
+    
+    #[error("Transaction {tx_id} has an invalid signature")]
+    InvalidSignature { tx_id: String },
+    
+    #[error("Transaction {tx_id} is already known")]
+    DuplicateTransaction { tx_id: String },
+    
+    #[error("Transaction {tx_id} not found")]
+    TransactionNotFound { tx_id: String },
+    
+    #[error("Fee {provided} is below the required {required}")]
+    FeeTooLow { required: f64, provided: f64 },
+    
+    #[error("Transaction is signed for chain {actual}, this is chain {expected}")]
+    WrongChainId { expected: u64, actual: u64 },
+    
+    #[error("Account {address} is frozen")]
+    AccountFrozen { address: String },
+    
+    #[error("{feature} is not enabled on this chain")]
+    FeatureDisabled { feature: &'static str },
+    
+    #[error("Contract error: {reason}")]
+    ContractError { reason: String },
+    
+    #[error("Chain {name} is not registered")]
+    ChainNotFound { name: String },
+    
+    #[error("Chain {name} is already registered as {existing}")]
+    DuplicateChain { name: String, existing: String },
+    
+    #[error("Wallet {address} is closed")]
+    WalletClosed { address: String },
+    
+    #[error("Block template {template_id} is stale; request a new one")]
+    StaleTemplate { template_id: String },
+    
+    #[error("{operation} is not available in {mode} mode")]
+    WrongLedgerMode { operation: &'static str, mode: &'static str },
+    
+    #[error("Address is for {found}, this chain is on {expected}")]
+    WrongNetwork { expected: &'static str, found: &'static str },
+    
+    #[error("Block #{height} does not match its hash under {algorithm}; the chain was hashed with another algorithm")]
+    HashAlgorithmMismatch { algorithm: &'static str, height: u64 },
+    
+    #[error("Transaction from {address} has nonce {found}, expected {expected}")]
+    InvalidNonce { address: String, expected: u64, found: u64 },
+    
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+/// Стабильный код ошибки для клиентов за пределами процесса (JSON-RPC, REST, `--json` в CLI).
+/// Номера не меняются между версиями и не переиспользуются: новые ошибки получают новые номера,
+/// а клиент читает незнакомый номер как `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u16", into = "u16")]
+pub enum ErrorCode {
+    /// Зарезервирован: ошибка вне блокчейна или неизвестная клиенту
+    Other = 0,
+    InsufficientBalance = 1,
+    InvalidTransaction = 2,
+    InvalidBlock = 3,
+    Consensus = 4,
+    MempoolFull = 5,
+    Storage = 6,
+    MalformedTransaction = 7,
+    RateLimited = 8,
+    FaucetDepleted = 9,
+    WalletNotFound = 10,
+    ValidatorNotFound = 11,
+    ValidatorJailed = 12,
+    NotScheduledProducer = 13,
+    InvalidSignature = 14,
+    DuplicateTransaction = 15,
+    TransactionNotFound = 16,
+    FeeTooLow = 17,
+    WrongChainId = 18,
+    AccountFrozen = 19,
+    FeatureDisabled = 20,
+    ContractError = 21,
+    ChainNotFound = 22,
+    DuplicateChain = 23,
+    WalletClosed = 24,
+    StaleTemplate = 25,
+    WrongLedgerMode = 26,
+    WrongNetwork = 27,
+    DuplicateWallet = 28,
+    HashAlgorithmMismatch = 29,
+    InvalidNonce = 30,
+    InvalidConfig = 31,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 32] = [
+        ErrorCode::Other, ErrorCode::InsufficientBalance, ErrorCode::InvalidTransaction, ErrorCode::InvalidBlock,
+        ErrorCode::Consensus, ErrorCode::MempoolFull, ErrorCode::Storage, ErrorCode::MalformedTransaction,
+        ErrorCode::RateLimited, ErrorCode::FaucetDepleted, ErrorCode::WalletNotFound, ErrorCode::ValidatorNotFound,
+        ErrorCode::ValidatorJailed, ErrorCode::NotScheduledProducer, ErrorCode::InvalidSignature,
+        ErrorCode::DuplicateTransaction, ErrorCode::TransactionNotFound, ErrorCode::FeeTooLow, ErrorCode::WrongChainId,
+        ErrorCode::AccountFrozen, ErrorCode::FeatureDisabled, ErrorCode::ContractError, ErrorCode::ChainNotFound,
+        ErrorCode::DuplicateChain, ErrorCode::WalletClosed, ErrorCode::StaleTemplate, ErrorCode::WrongLedgerMode,
+        ErrorCode::WrongNetwork, ErrorCode::DuplicateWallet, ErrorCode::HashAlgorithmMismatch, ErrorCode::InvalidNonce,
+        ErrorCode::InvalidConfig,
+    ];
+}
+
+impl From<ErrorCode> for u16 {
+    fn from(code: ErrorCode) -> u16 {
+        code as u16
+    }
+}
+
+impl From<u16> for ErrorCode {
+    fn from(number: u16) -> ErrorCode {
+        ErrorCode::ALL.into_iter().find(|code| *code as u16 == number).unwrap_or(ErrorCode::Other)
+    }
+}
+
+/// Ошибка в виде, пересекающем границу процесса: код, текст и поля варианта в `data`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+impl ErrorBody {
+    /// Ошибка слоя API, не относящаяся к блокчейну
+    #[cfg(feature = "http-api")]
+    pub fn other(message: impl Into<String>) -> Self {
+        ErrorBody { code: ErrorCode::Other, message: message.into(), data: Value::Null }
+    }
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<&BlockchainError> for ErrorBody {
+    fn from(error: &BlockchainError) -> Self {
+        ErrorBody { code: error.code(), message: error.to_string(), data: error.data() }
+    }
+}
+
+impl BlockchainError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            BlockchainError::InsufficientBalance { .. } => ErrorCode::InsufficientBalance,
+            BlockchainError::InvalidTransaction(_) => ErrorCode::InvalidTransaction,
+            BlockchainError::InvalidBlock(_) => ErrorCode::InvalidBlock,
+            BlockchainError::ConsensusError(_) => ErrorCode::Consensus,
+            BlockchainError::MempoolFull { .. } => ErrorCode::MempoolFull,
+            BlockchainError::Storage(_) => ErrorCode::Storage,
+            BlockchainError::MalformedTransaction { .. } => ErrorCode::MalformedTransaction,
+            BlockchainError::RateLimited { .. } => ErrorCode::RateLimited,
+            BlockchainError::FaucetDepleted { .. } => ErrorCode::FaucetDepleted,
+            BlockchainError::WalletNotFound { .. } => ErrorCode::WalletNotFound,
+            BlockchainError::DuplicateWallet { .. } => ErrorCode::DuplicateWallet,
+            BlockchainError::ValidatorNotFound { .. } => ErrorCode::ValidatorNotFound,
+            BlockchainError::ValidatorJailed { .. } => ErrorCode::ValidatorJailed,
+            BlockchainError::NotScheduledProducer { .. } => ErrorCode::NotScheduledProducer,
+            BlockchainError::InvalidSignature { .. } => ErrorCode::InvalidSignature,
+            BlockchainError::DuplicateTransaction { .. } => ErrorCode::DuplicateTransaction,
+            BlockchainError::TransactionNotFound { .. } => ErrorCode::TransactionNotFound,
+            BlockchainError::FeeTooLow { .. } => ErrorCode::FeeTooLow,
+            BlockchainError::WrongChainId { .. } => ErrorCode::WrongChainId,
+            BlockchainError::AccountFrozen { .. } => ErrorCode::AccountFrozen,
+            BlockchainError::FeatureDisabled { .. } => ErrorCode::FeatureDisabled,
+            BlockchainError::ContractError { .. } => ErrorCode::ContractError,
+            BlockchainError::ChainNotFound { .. } => ErrorCode::ChainNotFound,
+            BlockchainError::DuplicateChain { .. } => ErrorCode::DuplicateChain,
+            BlockchainError::WalletClosed { .. } => ErrorCode::WalletClosed,
+            BlockchainError::StaleTemplate { .. } => ErrorCode::StaleTemplate,
+            BlockchainError::WrongLedgerMode { .. } => ErrorCode::WrongLedgerMode,
+            BlockchainError::WrongNetwork { .. } => ErrorCode::WrongNetwork,
+            BlockchainError::HashAlgorithmMismatch { .. } => ErrorCode::HashAlgorithmMismatch,
+            BlockchainError::InvalidNonce { .. } => ErrorCode::InvalidNonce,
+            BlockchainError::InvalidConfig(_) => ErrorCode::InvalidConfig,
+        }
+    }
+    
+    /// Поля варианта для клиентов: суммы, адреса, идентификаторы транзакций
+    pub fn data(&self) -> Value {
+        match self {
+            BlockchainError::InsufficientBalance { required, available } => json!({ "required": required, "available": available }),
+            BlockchainError::InvalidTransaction(reason) | BlockchainError::InvalidBlock(reason) | BlockchainError::ConsensusError(reason)
+            | BlockchainError::Storage(reason) | BlockchainError::ContractError { reason }
+            | BlockchainError::InvalidConfig(reason) => json!({ "reason": reason }),
+            BlockchainError::MempoolFull { capacity } => json!({ "capacity": capacity }),
+            BlockchainError::MalformedTransaction { offset, field, reason } => json!({ "offset": offset, "field": field, "reason": reason }),
+            BlockchainError::RateLimited { reason, retry_after_blocks } => json!({ "reason": reason, "retry_after_blocks": retry_after_blocks }),
+            BlockchainError::FaucetDepleted { balance, required } => json!({ "balance": balance, "required": required }),
+            BlockchainError::WalletNotFound { address } | BlockchainError::ValidatorNotFound { address }
+            | BlockchainError::ValidatorJailed { address } | BlockchainError::NotScheduledProducer { address }
+            | BlockchainError::AccountFrozen { address } | BlockchainError::WalletClosed { address }
+            | BlockchainError::DuplicateWallet { address } => json!({ "address": address }),
+            BlockchainError::InvalidSignature { tx_id } | BlockchainError::DuplicateTransaction { tx_id }
+            | BlockchainError::TransactionNotFound { tx_id } => json!({ "tx_id": tx_id }),
+            BlockchainError::FeeTooLow { required, provided } => json!({ "required": required, "provided": provided }),
+            BlockchainError::WrongChainId { expected, actual } => json!({ "expected": expected, "actual": actual }),
+            BlockchainError::FeatureDisabled { feature } => json!({ "feature": feature }),
+            BlockchainError::ChainNotFound { name } => json!({ "name": name }),
+            BlockchainError::DuplicateChain { name, existing } => json!({ "name": name, "existing": existing }),
+            BlockchainError::StaleTemplate { template_id } => json!({ "template_id": template_id }),
+            BlockchainError::WrongLedgerMode { operation, mode } => json!({ "operation": operation, "mode": mode }),
+            BlockchainError::WrongNetwork { expected, found } => json!({ "expected": expected, "found": found }),
+            BlockchainError::HashAlgorithmMismatch { algorithm, height } => json!({ "algorithm": algorithm, "height": height }),
+            BlockchainError::InvalidNonce { address, expected, found } => json!({ "address": address, "expected": expected, "found": found }),
+        }
+    }
+}
+
+impl Serialize for BlockchainError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ErrorBody::from(self).serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::entropy::OsEntropy;
+    use crate::test_support::{signed_transfer, ChainFixture};
+    use crate::transaction::{Transaction, TransactionType};
+    use crate::wallet::Wallet;
+    
+    #[test]
+    fn missing_sender_wallet_names_the_sender() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let stranger = Wallet::generate(&OsEntropy, Network::Mainnet);
+        let mut tx = Transaction::new(stranger.address.clone(), "alice".to_string(), 1.0, TransactionType::Transfer);
+        tx.set_nonce(0);
+        tx.sign(stranger.keypair().unwrap());
+        
+        let error = chain.add_transaction(tx).unwrap_err();
+        assert!(matches!(&error, BlockchainError::WalletNotFound { address } if *address == stranger.address), "{:?}", error);
+        assert_eq!(error.to_string(), format!("Wallet {} not found", stranger.address));
+        assert_eq!(error.code(), ErrorCode::WalletNotFound);
+    }
+    
+    #[test]
+    fn pool_rejections_have_their_own_variants() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let tx = signed_transfer(&chain, "alice", "bob", 1.0);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx.clone()).unwrap();
+        let error = chain.add_transaction(tx).unwrap_err();
+        assert!(matches!(&error, BlockchainError::DuplicateTransaction { tx_id: id } if *id == tx_id), "{:?}", error);
+        
+        chain.mempool.set_capacity(Some(1));
+        let error = chain.add_transaction(signed_transfer(&chain, "alice", "bob", 2.0)).unwrap_err();
+        assert!(matches!(error, BlockchainError::MempoolFull { capacity: 1 }), "{:?}", error);
+        assert_eq!(error.to_string(), "Mempool is full: 1 pending transactions");
+    }
+    
+    #[test]
+    fn producer_and_contract_failures_have_their_own_variants() {
+        let mut chain = ChainFixture::new()
+            .with_consensus(ConsensusAlgorithm::DelegatedProofOfStake)
+            .using_mock_clock(GENESIS_TIMESTAMP + 3600)
+            .with_validators(&[("v0", 100.0)])
+            .build().unwrap().chain;
+        let error = chain.mine_pending_transactions("outsider".to_string()).unwrap_err();
+        assert!(matches!(&error, BlockchainError::NotScheduledProducer { address } if address == "outsider"), "{:?}", error);
+        
+        let error = chain.execute_smart_contract("nowhere", "run", Vec::new()).unwrap_err();
+        assert!(matches!(&error, BlockchainError::ContractError { reason } if reason == "Smart contract nowhere not found"), "{:?}", error);
+        assert_eq!(error.code(), ErrorCode::ContractError);
+    }
+    
+    /// Все варианты ошибок с их задокументированными номерами кодов
+    fn every_variant() -> Vec<(BlockchainError, u16)> {
+        let address = || "alice".to_string();
+        let tx_id = || "tx1".to_string();
+        vec![
+            (BlockchainError::InsufficientBalance { required: 5.0, available: 2.5 }, 1),
+            (BlockchainError::InvalidTransaction("bad".to_string()), 2),
+            (BlockchainError::InvalidBlock("bad".to_string()), 3),
+            (BlockchainError::ConsensusError("bad".to_string()), 4),
+            (BlockchainError::MempoolFull { capacity: 10 }, 5),
+            (BlockchainError::Storage("disk".to_string()), 6),
+            (BlockchainError::MalformedTransaction { offset: 4, field: "amount".to_string(), reason: "short".to_string() }, 7),
+            (BlockchainError::RateLimited { reason: "cooldown".to_string(), retry_after_blocks: 3 }, 8),
+            (BlockchainError::FaucetDepleted { balance: 1.0, required: 10.0 }, 9),
+            (BlockchainError::WalletNotFound { address: address() }, 10),
+            (BlockchainError::ValidatorNotFound { address: address() }, 11),
+            (BlockchainError::ValidatorJailed { address: address() }, 12),
+            (BlockchainError::NotScheduledProducer { address: address() }, 13),
+            (BlockchainError::InvalidSignature { tx_id: tx_id() }, 14),
+            (BlockchainError::DuplicateTransaction { tx_id: tx_id() }, 15),
+            (BlockchainError::TransactionNotFound { tx_id: tx_id() }, 16),
+            (BlockchainError::FeeTooLow { required: 0.1, provided: 0.01 }, 17),
+            (BlockchainError::WrongChainId { expected: 1, actual: 2 }, 18),
+            (BlockchainError::AccountFrozen { address: address() }, 19),
+            (BlockchainError::FeatureDisabled { feature: "Staking" }, 20),
+            (BlockchainError::ContractError { reason: "missing".to_string() }, 21),
+            (BlockchainError::ChainNotFound { name: "test".to_string() }, 22),
+            (BlockchainError::DuplicateChain { name: "test".to_string(), existing: "main".to_string() }, 23),
+            (BlockchainError::WalletClosed { address: address() }, 24),
+            (BlockchainError::StaleTemplate { template_id: "t1".to_string() }, 25),
+            (BlockchainError::WrongLedgerMode { operation: "Minting", mode: "utxo" }, 26),
+            (BlockchainError::WrongNetwork { expected: "mainnet", found: "testnet" }, 27),
+            (BlockchainError::DuplicateWallet { address: address() }, 28),
+            (BlockchainError::HashAlgorithmMismatch { algorithm: "blake3", height: 7 }, 29),
+            (BlockchainError::InvalidNonce { address: address(), expected: 3, found: 5 }, 30),
+            (BlockchainError::InvalidConfig("difficulty".to_string()), 31),
+        ]
+    }
+    
+    #[test]
+    fn every_variant_serializes_to_its_documented_code() {
+        let variants = every_variant();
+        assert_eq!(variants.len(), ErrorCode::ALL.len() - 1);
+        for (error, number) in variants {
+            let value = serde_json::to_value(&error).unwrap();
+            assert_eq!(value["code"], json!(number), "{:?}", error);
+            assert_eq!(u16::from(error.code()), number);
+            assert_eq!(value["message"], json!(error.to_string()));
+            assert_eq!(value["data"], error.data());
+            assert!(error.data().as_object().is_some_and(|fields| !fields.is_empty()), "{:?} has no data", error);
+        }
+        assert_eq!(json!(BlockchainError::InsufficientBalance { required: 5.0, available: 2.5 })["data"], json!({ "required": 5.0, "available": 2.5 }));
+        assert_eq!(json!(BlockchainError::InvalidNonce { address: "alice".to_string(), expected: 3, found: 5 })["data"], json!({ "address": "alice", "expected": 3, "found": 5 }));
+    }
+    
+    #[test]
+    fn error_body_round_trips_through_json() {
+        for (error, _) in every_variant() {
+            let body: ErrorBody = serde_json::from_str(&serde_json::to_string(&error).unwrap()).unwrap();
+            assert_eq!(body, ErrorBody::from(&error));
+            assert_eq!(body.to_string(), error.to_string());
+        }
+        
+        // Клиент старой версии читает код новой ошибки как Other и все равно показывает сообщение
+        let body: ErrorBody = serde_json::from_value(json!({ "code": 999, "message": "Something new", "data": { "x": 1 } })).unwrap();
+        assert_eq!(body.code, ErrorCode::Other);
+        assert_eq!(body.to_string(), "Something new");
+        let body: ErrorBody = serde_json::from_value(json!({ "code": 17, "message": "Fee 0.01 is below the required 0.1" })).unwrap();
+        assert_eq!((body.code, body.data), (ErrorCode::FeeTooLow, Value::Null));
+    }
+}
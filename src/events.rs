@@ -0,0 +1,108 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::forks::ForkInfo;
+use crate::transaction::Transaction;
+use serde::{Serialize, Deserialize};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Mutex, MutexGuard};
+
+/// События цепочки, которые рассылаются подписчикам
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ChainEvent {
+    /// В цепочку добавлен новый блок
+    BlockAdded { block: Block },
+    /// Транзакция принята в список ожидающих
+    TransactionQueued { transaction: Box<Transaction> },
+    /// Ход синхронизации с сетью: применённая высота и целевая высота
+    SyncProgress { height: u64, target: u64 },
+    /// У валидатора списана часть ставки
+    ValidatorSlashed { validator: String, amount: f64 },
+    /// Кошелек меньше экзистенциального депозита удален, остаток сожжен
+    AccountReaped { address: String, dust: f64 },
+    /// От блока главной цепочки отошла конкурирующая ветка
+    ForkDetected { fork: ForkInfo },
+    /// Точка расхождения ветки ушла ниже окончательной высоты, ветка отброшена. Переход на ветку
+    /// это событие не означает: реорганизаций нет
+    ForkResolved { fork: ForkInfo },
+}
+
+/// Обработчик событий; возвращает false, если его нужно отписать
+pub type EventHook = Box<dyn FnMut(&ChainEvent) -> bool + Send + Sync>;
+
+/// Идентификатор подписки, по которому её можно удалить
+pub type HookId = u64;
+
+/// Список обработчиков событий блокчейна. Обработчики под своей блокировкой, чтобы о транзакции,
+/// принятой в пул под блокировкой цепочки на чтение, тоже можно было сообщить.
+#[derive(Default)]
+pub struct EventBus {
+    hooks: Mutex<Vec<(HookId, EventHook)>>,
+    next_id: HookId,
+}
+
+impl EventBus {
+    fn hooks(&self) -> MutexGuard<'_, Vec<(HookId, EventHook)>> {
+        self.hooks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    /// Регистрирует обработчик и возвращает его идентификатор
+    pub fn add_hook(&mut self, hook: EventHook) -> HookId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.hooks().push((id, hook));
+        id
+    }
+    
+    /// Удаляет обработчик; возвращает true, если он был зарегистрирован
+    pub fn remove_hook(&mut self, id: HookId) -> bool {
+        let mut hooks = self.hooks();
+        let before = hooks.len();
+        hooks.retain(|(hook_id, _)| *hook_id != id);
+        hooks.len() != before
+    }
+    
+    /// Передает событие всем обработчикам и отписывает тех, кто отказался от дальнейших событий
+    pub fn emit(&self, event: &ChainEvent) {
+        self.hooks().retain_mut(|(_, hook)| hook(event));
+    }
+    
+    /// Возвращает число активных обработчиков
+    pub fn len(&self) -> usize {
+        self.hooks().len()
+    }
+    
+    /// Проверяет, есть ли активные обработчики
+    pub fn is_empty(&self) -> bool {
+        self.hooks().is_empty()
+    }
+}
+
+/// Создает обработчик, складывающий события в ограниченную очередь.
+/// Переполненная или закрытая очередь отписывается, чтобы медленный получатель не тормозил цепочку.
+pub fn channel_hook(capacity: usize) -> (EventHook, Receiver<ChainEvent>) {
+    let (sender, receiver): (SyncSender<ChainEvent>, Receiver<ChainEvent>) = mpsc::sync_channel(capacity);
+    let hook: EventHook = Box::new(move |event| {
+        !matches!(sender.try_send(event.clone()), Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)))
+    });
+    (hook, receiver)
+}
+
+impl Blockchain {
+    /// Регистрирует обработчик событий цепочки (новые блоки, новые ожидающие транзакции)
+    pub fn add_event_hook(&mut self, hook: EventHook) -> HookId {
+        self.events.add_hook(hook)
+    }
+    
+    /// Удаляет ранее зарегистрированный обработчик событий
+    pub fn remove_event_hook(&mut self, id: HookId) -> bool {
+        self.events.remove_hook(id)
+    }
+    
+    /// Подписывается на события через ограниченную очередь; при переполнении подписка снимается
+    pub fn subscribe(&mut self, capacity: usize) -> Receiver<ChainEvent> {
+        let (hook, receiver) = channel_hook(capacity);
+        self.events.add_hook(hook);
+        receiver
+    }
+}
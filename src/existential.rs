@@ -0,0 +1,192 @@
+use crate::balance_audit::{BalanceBucket, BalanceChangeReason};
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::events::ChainEvent;
+use crate::governance::GOVERNANCE_ESCROW;
+use crate::params::DustPolicy;
+use crate::supply::is_system_sender;
+use crate::trace::info;
+use crate::transaction::{Transaction, TransactionType};
+
+impl Blockchain {
+    /// Проверяет, что перевод не создает кошелек меньше экзистенциального депозита; выпуск
+    /// протоколом и переводы без суммы создают кошельки всегда
+    pub(crate) fn check_existential_deposit(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        let Some(deposit) = self.params.existential_deposit else {
+            return Ok(());
+        };
+        if deposit.policy != DustPolicy::Reject || !self.creates_dust_wallet(tx) {
+            return Ok(());
+        }
+        Err(BlockchainError::InvalidTransaction(format!(
+            "Transfer of {} would create wallet {} below the existential deposit {}", tx.amount, tx.receiver, deposit.amount
+        )))
+    }
+    
+    /// Создал бы перевод новый кошелек с суммой меньше депозита
+    fn creates_dust_wallet(&self, tx: &Transaction) -> bool {
+        self.params.existential_deposit.is_some_and(|deposit| {
+            !is_system_sender(&tx.sender) && tx.amount > 0.0 && tx.amount < deposit.amount && !self.wallets.contains_key(&tx.receiver)
+        })
+    }
+    
+    /// Сжигается ли зачисление вместо создания кошелька
+    pub(crate) fn burns_dust_credit(&self, tx: &Transaction) -> bool {
+        self.params.existential_deposit.is_some_and(|deposit| deposit.policy == DustPolicy::Burn) && self.creates_dust_wallet(tx)
+    }
+    
+    /// Отклоняет транзакции удаленного счета, подписанные до его удаления
+    pub(crate) fn check_reaped_replay(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        match self.reaped_accounts.get(&tx.sender) {
+            Some(reaped_at) if tx.timestamp <= *reaped_at => Err(BlockchainError::InvalidTransaction(format!(
+                "Transaction {} predates the reaping of {}", tx.id, tx.sender
+            ))),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Можно ли удалить кошелек: баланс со стейком меньше депозита, нет активов, ставки
+    /// валидатора, заморозки, закрытия и транзакций в пуле; эскроу управления не удаляется
+    fn is_reapable(&self, address: &str) -> bool {
+        if address == GOVERNANCE_ESCROW {
+            return false;
+        }
+        let (Some(deposit), Some(wallet)) = (self.params.existential_deposit, self.wallets.get(address)) else {
+            return false;
+        };
+        wallet.balance + wallet.staking_balance < deposit.amount
+            && wallet.assets.values().all(|&amount| amount == 0)
+            && wallet.frozen.is_none()
+            && wallet.closed_at.is_none()
+            && !self.validators.contains_key(address)
+            && !self.mempool.lock().transactions.iter().any(|tx| tx.sender == address || tx.receiver == address)
+    }
+    
+    /// Удаляет кошелек, сжигая остаток; транзакции с меткой времени до `timestamp` от этого адреса
+    /// больше не принимаются
+    fn reap_account(&mut self, address: &str, timestamp: i64) -> Option<f64> {
+        let wallet = self.wallets.remove(address)?;
+        let dust = wallet.balance + wallet.staking_balance;
+        self.record_off_chain_credit(address, -dust);
+        self.reaped_accounts.insert(address.to_string(), timestamp);
+        for bucket in BalanceBucket::ALL {
+            self.record_balance_change(address, bucket, -bucket.value(&wallet), BalanceChangeReason::Reaped);
+        }
+        self.events.emit(&ChainEvent::AccountReaped { address: address.to_string(), dust });
+        info!(address, dust, "Account reaped");
+        Some(dust)
+    }
+    
+    /// Удаляет отправителей блока, чьи балансы опустились ниже депозита
+    pub(crate) fn reap_block_senders(&mut self, block: &Block) {
+        // Имя, только что закрепившее ключ, еще пусто, но удалять его нельзя
+        let mut senders: Vec<&str> = block.transactions.iter()
+            .filter(|tx| tx.transaction_type != TransactionType::RegisterKey)
+            .map(|tx| tx.sender.as_str())
+            .filter(|sender| !is_system_sender(sender))
+            .collect();
+        senders.dedup();
+        for sender in senders {
+            if self.is_reapable(sender) {
+                self.reap_account(sender, block.timestamp);
+            }
+        }
+    }
+    
+    /// Удаляет все кошельки меньше экзистенциального депозита и возвращает их адреса с сожженными остатками
+    pub fn reap_dust_accounts(&mut self) -> Vec<(String, f64)> {
+        let mut addresses: Vec<String> = self.wallets.keys().filter(|address| self.is_reapable(address)).cloned().collect();
+        addresses.sort();
+        let now = self.now_ts();
+        addresses.into_iter()
+            .filter_map(|address| self.reap_account(&address, now).map(|dust| (address, dust)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::entropy::OsEntropy;
+    use crate::params::ExistentialDeposit;
+    use crate::signing::Keypair;
+    use crate::test_support::{ChainFixture, FIXTURE_MINER};
+    use crate::wallet::Wallet;
+    
+    const DEPOSIT: f64 = 5.0;
+    
+    /// Цепочка с депозитом 5 и адресом сети с наградой за блок; ключ адреса хранится отдельно,
+    /// чтобы подписывать и после удаления кошелька. Майнер фикстуры тоже получает награду, чтобы
+    /// его кошелек не был пылью
+    fn funded_chain(policy: DustPolicy) -> (Blockchain, String, Keypair) {
+        let mut chain = ChainFixture::new().with_mining_reward(100.0).with_block_mined_by(FIXTURE_MINER).build().unwrap().chain;
+        chain.params.existential_deposit = Some(ExistentialDeposit { amount: DEPOSIT, policy });
+        let wallet = Wallet::generate(&OsEntropy, Network::Mainnet);
+        let keypair = wallet.keypair().unwrap().clone();
+        let address = chain.add_wallet(wallet).unwrap().address.clone();
+        chain.mine_pending_transactions(address.clone()).unwrap();
+        (chain, address, keypair)
+    }
+    
+    fn transfer(chain: &Blockchain, from: &str, to: &str, amount: f64, keypair: &Keypair, timestamp: i64) -> Transaction {
+        let mut tx = Transaction::new_at(from.to_string(), to.to_string(), amount, TransactionType::Transfer, timestamp);
+        tx.set_nonce(chain.get_account_nonce(from));
+        tx.sign(keypair);
+        tx
+    }
+    
+    #[test]
+    fn dust_creation_is_rejected_or_burned_by_policy() {
+        let (mut chain, alice, keypair) = funded_chain(DustPolicy::Reject);
+        let tx = transfer(&chain, &alice, "carol", 2.0, &keypair, chain.now_ts());
+        let result = chain.add_transaction(tx);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("existential deposit")), "{:?}", result);
+        // Существующему кошельку и новому с депозитом переводить можно
+        chain.add_transaction(transfer(&chain, &alice, "carol", DEPOSIT, &keypair, chain.now_ts())).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.add_transaction(transfer(&chain, &alice, "carol", 2.0, &keypair, chain.now_ts())).unwrap();
+        
+        let (mut chain, alice, keypair) = funded_chain(DustPolicy::Burn);
+        chain.add_transaction(transfer(&chain, &alice, "carol", 2.0, &keypair, chain.now_ts())).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(!chain.wallets.contains_key("carol"));
+        assert!((chain.get_balance(&alice) - (100.0 - 2.0 - 0.002)).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn drained_account_is_reaped_and_recreated_without_replay() {
+        let (mut chain, alice, keypair) = funded_chain(DustPolicy::Reject);
+        let drain = transfer(&chain, &alice, "bob", 97.0, &keypair, chain.now_ts());
+        chain.add_transaction(drain.clone()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        assert!(!chain.wallets.contains_key(&alice));
+        let reaped_at = chain.reaped_accounts[&alice];
+        assert!(chain.reap_dust_accounts().is_empty());
+        
+        // Новая награда заводит кошелек заново с нулевым номером
+        chain.mine_pending_transactions(alice.clone()).unwrap();
+        assert_eq!(chain.get_balance(&alice), 100.0);
+        assert_eq!(chain.get_account_nonce(&alice), 0);
+        
+        let result = chain.add_transaction(drain);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("predates the reaping")), "{:?}", result);
+        chain.add_transaction(transfer(&chain, &alice, "bob", 10.0, &keypair, reaped_at + 1)).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("bob"), 107.0);
+    }
+    
+    #[test]
+    fn explicit_reaping_skips_wallets_with_pending_transactions() {
+        let (mut chain, alice, keypair) = funded_chain(DustPolicy::Reject);
+        let empty = chain.add_wallet(Wallet::generate(&OsEntropy, Network::Mainnet)).unwrap().address.clone();
+        chain.add_transaction(transfer(&chain, &alice, "bob", 99.0, &keypair, chain.now_ts())).unwrap();
+        
+        // Перевод alice еще в пуле, поэтому удаляется только пустой кошелек
+        let reaped = chain.reap_dust_accounts();
+        assert_eq!(reaped, vec![(empty, 0.0)]);
+        assert!(chain.wallets.contains_key(&alice));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::trace::info;
+use crate::transaction::Transaction;
+
+/// Истек ли срок транзакции к блоку высоты `height`
+pub(crate) fn is_expired(tx: &Transaction, height: u64) -> bool {
+    tx.valid_until_height.is_some_and(|until| until < height)
+}
+
+impl Blockchain {
+    /// Проверяет, что транзакция еще может попасть в блок высоты `height`. При приеме в пул
+    /// это следующий блок, так что срок, закончившийся до него, отклоняется сразу.
+    pub(crate) fn check_expiry(&self, tx: &Transaction, height: u64) -> Result<(), BlockchainError> {
+        match tx.valid_until_height {
+            Some(until) if until < height => Err(BlockchainError::InvalidTransaction(format!(
+                "Transaction {} is valid until height {} and cannot enter block # {}", tx.id, until, height
+            ))),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Убирает из пула транзакции, которые уже не попадут в следующий блок
+    pub(crate) fn drop_expired_transactions(&mut self) {
+        let height = self.chain.len() as u64;
+        let expired: Vec<String> = self.mempool.get_mut().transactions.iter()
+            .filter(|tx| is_expired(tx, height))
+            .map(|tx| tx.id.clone())
+            .collect();
+        for tx_id in expired {
+            if let Ok(transaction) = self.cancel_pending_transaction(&tx_id) {
+                info!(tx_id = %transaction.id, valid_until_height = transaction.valid_until_height, "Pending transaction expired");
+                self.expired_transactions.insert(tx_id, transaction);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::receipt::ReceiptStatus;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    fn expiring(chain: &Blockchain, until: u64) -> Transaction {
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.set_valid_until(until);
+        chain.sign_as_sender(&mut tx);
+        tx
+    }
+    
+    fn copy(chain: &Blockchain) -> Blockchain {
+        serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap()
+    }
+    
+    #[test]
+    fn unmined_transaction_expires_with_a_receipt_and_a_refund() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let mut producer = copy(&chain);
+        let tx = expiring(&chain, 2);
+        chain.add_transaction(tx.clone()).unwrap();
+        assert_eq!(chain.get_balance("alice"), 100.0 - 10.01);
+        
+        // Блок высоты 2 от другого узла ее не включил, и в блок 3 она уже не попадет
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.accept_block(producer.get_latest_block().clone()).unwrap();
+        assert!(chain.mempool().is_empty());
+        assert_eq!(chain.get_balance("alice"), 100.0);
+        assert_eq!(chain.get_transaction_receipt(&tx.id).map(|receipt| receipt.status), Some(ReceiptStatus::Expired));
+        assert!(chain.add_transaction(tx).is_err());
+    }
+    
+    #[test]
+    fn block_including_an_expired_transaction_is_invalid() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let tx = expiring(&chain, 2);
+        let mut producer = copy(&chain);
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.accept_block(producer.get_latest_block().clone()).unwrap();
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let mut block = producer.get_latest_block().clone();
+        assert_eq!(block.index, 3);
+        // Награда забирает и комиссию вставленной транзакции, так что блок отличается только ею
+        if let Some(reward) = block.transactions.last_mut() {
+            reward.amount += tx.fee;
+            reward.reseal();
+        }
+        block.transactions.insert(0, tx);
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.mine_block();
+        let result = chain.verify_block(&block);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.ends_with("cannot enter block # 3")), "{:?}", result);
+    }
+    
+    #[test]
+    fn expiry_is_committed_in_the_signature() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        assert!(chain.add_transaction(expiring(&chain, 1)).is_err());
+        
+        let mut extended = expiring(&chain, 2);
+        extended.valid_until_height = Some(100);
+        assert!(chain.add_transaction(extended).is_err());
+        chain.add_transaction(expiring(&chain, 2)).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("bob"), 10.0);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::TransactionType;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Настройки крана тестовой сети
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaucetConfig {
+    /// Кошелек, с которого раздаются средства
+    pub address: String,
+    /// Сколько средств отправляется за один запрос
+    pub drip_amount: f64,
+    /// Через сколько блоков один адрес может запросить средства снова
+    #[serde(default = "default_address_cooldown")]
+    pub address_cooldown_blocks: u64,
+    /// Длина окна в блоках для общего лимита
+    #[serde(default = "default_window")]
+    pub window_blocks: u64,
+    /// Сколько раздач разрешено в окне на все адреса
+    #[serde(default = "default_max_drips")]
+    pub max_drips_per_window: usize,
+}
+
+fn default_address_cooldown() -> u64 {
+    10
+}
+
+fn default_window() -> u64 {
+    10
+}
+
+fn default_max_drips() -> usize {
+    100
+}
+
+/// Кран: отправляет средства настоящими транзакциями и помнит, кому и когда отправлял
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Faucet {
+    pub config: FaucetConfig,
+    /// Высота последней раздачи по адресам
+    last_drip: BTreeMap<String, u64>,
+    /// Высоты раздач внутри текущего окна
+    recent_drips: VecDeque<u64>,
+}
+
+impl Faucet {
+    pub fn new(config: FaucetConfig) -> Self {
+        Faucet { config, last_drip: BTreeMap::new(), recent_drips: VecDeque::new() }
+    }
+    
+    /// Ставит в очередь перевод с кошелька крана на адрес и возвращает идентификатор транзакции
+    pub fn request(&mut self, chain: &mut Blockchain, address: &str) -> Result<String, BlockchainError> {
+        let height = chain.get_latest_block().index;
+        
+        if let Some(last) = self.last_drip.get(address) {
+            let next = last + self.config.address_cooldown_blocks;
+            if height < next {
+                return Err(BlockchainError::RateLimited {
+                    reason: format!("{} already received funds at block {}", address, last),
+                    retry_after_blocks: next - height,
+                });
+            }
+        }
+        
+        while self.recent_drips.front().is_some_and(|drip| drip + self.config.window_blocks <= height) {
+            self.recent_drips.pop_front();
+        }
+        if self.recent_drips.len() >= self.config.max_drips_per_window {
+            let oldest = self.recent_drips.front().copied().unwrap_or(height);
+            return Err(BlockchainError::RateLimited {
+                reason: format!("Faucet limit of {} requests per {} blocks reached", self.config.max_drips_per_window, self.config.window_blocks),
+                retry_after_blocks: oldest + self.config.window_blocks - height,
+            });
+        }
+        
+        let mut transaction = chain.new_transaction(self.config.address.clone(), address.to_string(), self.config.drip_amount, TransactionType::Transfer);
+        chain.sign_as_sender(&mut transaction);
+        let required = transaction.amount + transaction.fee;
+        let balance = chain.get_balance(&self.config.address);
+        if balance < required {
+            return Err(BlockchainError::FaucetDepleted { balance, required });
+        }
+        
+        let tx_id = transaction.id.clone();
+        chain.add_transaction(transaction)?;
+        self.last_drip.insert(address.to_string(), height);
+        self.recent_drips.push_back(height);
+        Ok(tx_id)
+    }
+}
+
+impl Blockchain {
+    /// Включает кран; состояние крана сохраняется вместе с цепочкой
+    pub fn enable_faucet(&mut self, config: FaucetConfig) {
+        self.faucet = Some(Faucet::new(config));
+    }
+    
+    /// Запрашивает средства у крана этой цепочки
+    pub fn request_from_faucet(&mut self, address: &str) -> Result<String, BlockchainError> {
+        self.require_account_mode("The faucet")?;
+        let mut faucet = self.faucet.take()
+            .ok_or(BlockchainError::FeatureDisabled { feature: "Faucet" })?;
+        let result = faucet.request(self, address);
+        self.faucet = Some(faucet);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    /// Цепочка с краном на кошельке `faucet`, пополненном одной наградой за блок
+    fn chain_with_faucet(cooldown: u64, max_drips: usize) -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("faucet".to_string()).unwrap();
+        chain.mine_pending_transactions("faucet".to_string()).unwrap();
+        chain.enable_faucet(FaucetConfig {
+            address: "faucet".to_string(),
+            drip_amount: 10.0,
+            address_cooldown_blocks: cooldown,
+            window_blocks: cooldown,
+            max_drips_per_window: max_drips,
+        });
+        chain
+    }
+    
+    fn mine(chain: &mut Blockchain, blocks: u64) {
+        for _ in 0..blocks {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+    }
+    
+    #[test]
+    fn address_is_rate_limited_until_the_cooldown_passes() {
+        let mut chain = chain_with_faucet(3, 100);
+        
+        let tx_id = chain.request_from_faucet("bob").unwrap();
+        mine(&mut chain, 1);
+        assert!(chain.get_latest_block().transactions.iter().any(|tx| tx.id == tx_id));
+        assert_eq!(chain.get_balance("bob"), 10.0);
+        
+        let limited = chain.request_from_faucet("bob");
+        assert!(matches!(limited, Err(BlockchainError::RateLimited { retry_after_blocks: 2, .. })), "{:?}", limited);
+        // Лимит переживает сохранение цепочки
+        let dir = std::env::temp_dir().join(format!("hellochain-faucet-{}", std::process::id()));
+        chain.save(&dir).unwrap();
+        let mut restored = Blockchain::load(&dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(matches!(restored.request_from_faucet("bob"), Err(BlockchainError::RateLimited { .. })));
+        
+        mine(&mut restored, 2);
+        restored.request_from_faucet("bob").unwrap();
+        mine(&mut restored, 1);
+        assert_eq!(restored.get_balance("bob"), 20.0);
+    }
+    
+    #[test]
+    fn window_limits_drips_across_addresses() {
+        let mut chain = chain_with_faucet(2, 2);
+        chain.request_from_faucet("bob").unwrap();
+        chain.request_from_faucet("carol").unwrap();
+        assert!(matches!(chain.request_from_faucet("dave"), Err(BlockchainError::RateLimited { retry_after_blocks: 2, .. })));
+        
+        mine(&mut chain, 2);
+        chain.request_from_faucet("dave").unwrap();
+    }
+    
+    #[test]
+    fn empty_faucet_reports_its_balance() {
+        let mut chain = chain_with_faucet(1, 100);
+        let mut drips = 0;
+        let depleted = loop {
+            match chain.request_from_faucet(&format!("user{}", drips)) {
+                Ok(_) => drips += 1,
+                Err(e) => break e,
+            }
+        };
+        // Баланс учитывает переводы, еще ждущие в очереди, вместе с комиссиями
+        assert_eq!(drips, 9);
+        let BlockchainError::FaucetDepleted { balance, required } = depleted else { panic!("{:?}", depleted) };
+        assert!(balance < required);
+        assert_eq!(required, 10.0 + chain.mempool().transactions()[0].fee);
+    }
+}
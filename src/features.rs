@@ -0,0 +1,142 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+
+/// Возможность цепочки, которую можно выключить в параметрах; по умолчанию включены все
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Feature {
+    /// Развертывание смарт-контрактов
+    SmartContracts,
+    /// Вызовы функций развернутых контрактов
+    ContractCalls,
+    /// Хранение данных, в том числе потоковая загрузка
+    Data,
+    /// Выпуск новых активов; переводы уже выпущенных остаются доступны
+    AssetIssuance,
+    /// Выпуск основной монеты транзакциями `Mint`; включается ключом эмитента в спецификации,
+    /// а не голосованием
+    Minting,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 5] = [Feature::SmartContracts, Feature::ContractCalls, Feature::Data, Feature::AssetIssuance, Feature::Minting];
+    
+    /// Название в ошибке `FeatureDisabled`
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::SmartContracts => "Smart contract deployment",
+            Feature::ContractCalls => "Smart contract calls",
+            Feature::Data => "Data storage",
+            Feature::AssetIssuance => "Asset issuance",
+            Feature::Minting => "Minting",
+        }
+    }
+    
+    /// Имя параметра, которым возможность включается (1) и выключается (0) голосованием
+    pub fn param(self) -> &'static str {
+        match self {
+            Feature::SmartContracts => "feature.smart_contracts",
+            Feature::ContractCalls => "feature.contract_calls",
+            Feature::Data => "feature.data",
+            Feature::AssetIssuance => "feature.asset_issuance",
+            Feature::Minting => "feature.minting",
+        }
+    }
+    
+    pub fn from_param(name: &str) -> Option<Feature> {
+        Feature::ALL.into_iter().find(|feature| feature.param() == name)
+    }
+    
+    /// Возможность, которой пользуется транзакция этого типа
+    pub fn of(transaction_type: &TransactionType) -> Option<Feature> {
+        match transaction_type {
+            TransactionType::SmartContract(_) => Some(Feature::SmartContracts),
+            TransactionType::Data(_) | TransactionType::DataChunk { .. } | TransactionType::DataManifest { .. } => Some(Feature::Data),
+            TransactionType::IssueAsset { .. } => Some(Feature::AssetIssuance),
+            TransactionType::Mint => Some(Feature::Minting),
+            _ => None,
+        }
+    }
+}
+
+impl Blockchain {
+    /// Включена ли возможность; выпуску монеты, кроме того, нужен ключ эмитента
+    pub fn is_feature_enabled(&self, feature: Feature) -> bool {
+        !self.params.disabled_features.contains(&feature)
+            && (feature != Feature::Minting || self.params.mint_authority.is_some())
+    }
+    
+    /// Включенные возможности цепочки
+    pub fn enabled_features(&self) -> Vec<Feature> {
+        Feature::ALL.into_iter().filter(|&feature| self.is_feature_enabled(feature)).collect()
+    }
+    
+    pub(crate) fn require_feature(&self, feature: Feature) -> Result<(), BlockchainError> {
+        if !self.is_feature_enabled(feature) {
+            return Err(BlockchainError::FeatureDisabled { feature: feature.name() });
+        }
+        Ok(())
+    }
+    
+    /// Проверяет, что тип транзакции не выключен в параметрах цепочки
+    pub(crate) fn check_feature(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        match Feature::of(&tx.transaction_type) {
+            Some(feature) => self.require_feature(feature),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::BlockAcceptance;
+    use crate::builder::BlockchainBuilder;
+    use crate::spec::ChainSpec;
+    
+    fn spec(disabled_features: &[&str]) -> ChainSpec {
+        serde_json::from_value(serde_json::json!({
+            "difficulty": 1,
+            "mining_reward": 100.0,
+            "consensus": "ProofOfWork",
+            "accept_legacy_addresses": true,
+            "disabled_features": disabled_features,
+        })).unwrap()
+    }
+    
+    #[test]
+    fn builder_disables_data_storage() {
+        let mut chain = BlockchainBuilder::new().difficulty(1).accept_legacy_addresses(true).disable_feature(Feature::Data).build().unwrap();
+        assert!(!chain.enabled_features().contains(&Feature::Data));
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        
+        let result = chain.store_data("alice".to_string(), vec![1, 2, 3]);
+        assert!(matches!(result, Err(BlockchainError::FeatureDisabled { feature: "Data storage" })), "{:?}", result);
+        assert!(chain.mempool().is_empty());
+    }
+    
+    #[test]
+    fn data_block_is_rejected_only_where_data_is_disabled() {
+        let mut producer = spec(&[]).build().unwrap();
+        let mut enabled = spec(&[]).build().unwrap();
+        let mut disabled = spec(&["Data"]).build().unwrap();
+        assert_eq!(disabled.params.disabled_features, [Feature::Data].into());
+        
+        producer.create_wallet("alice".to_string()).unwrap();
+        producer.mine_pending_transactions("alice".to_string()).unwrap();
+        producer.store_data("alice".to_string(), vec![1, 2, 3]).unwrap();
+        producer.mine_pending_transactions("alice".to_string()).unwrap();
+        let (funding, data) = (producer.chain[1].clone(), producer.chain[2].clone());
+        
+        for peer in [&mut enabled, &mut disabled] {
+            assert_eq!(peer.accept_block(funding.clone()).unwrap(), BlockAcceptance::Appended);
+        }
+        let result = disabled.accept_block(data.clone());
+        assert!(matches!(result, Err(BlockchainError::FeatureDisabled { feature: "Data storage" })), "{:?}", result);
+        assert_eq!(disabled.chain.len(), 2);
+        assert_eq!(enabled.accept_block(data).unwrap(), BlockAcceptance::Appended);
+        assert_eq!(enabled.chain.len(), 3);
+    }
+}
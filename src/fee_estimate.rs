@@ -0,0 +1,223 @@
+#![deny(clippy::indexing_slicing)]
+
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::fee_market::pays_base_fee;
+use crate::transaction::{Transaction, TransactionType};
+use std::collections::VecDeque;
+
+/// По скольким последним блокам оценивается комиссия
+pub const FEE_HISTORY_BLOCKS: usize = 20;
+
+/// Процентили ставок из недавних блоков для уровней оценки
+const LOW_PERCENTILE: f64 = 0.1;
+const MEDIUM_PERCENTILE: f64 = 0.5;
+const HIGH_PERCENTILE: f64 = 0.9;
+
+/// Оценка полной комиссии транзакции для подтверждения за заданное число блоков
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeEstimate {
+    pub low: f64,
+    pub medium: f64,
+    pub high: f64,
+    /// Нижняя граница: минимум политики плюс базовая комиссия следующего блока
+    pub floor: f64,
+    /// Сколько транзакций из недавних блоков легло в оценку
+    pub samples: usize,
+}
+
+/// Ставки чаевых транзакций последних блоков — комиссия сверх минимума и базовой комиссии
+/// за единицу веса; обновляется при добавлении блоков
+#[derive(Debug, Default)]
+pub(crate) struct FeeHistory {
+    blocks: VecDeque<Vec<f64>>,
+}
+
+impl FeeHistory {
+    fn push(&mut self, block: &Block) {
+        let rates = block.transactions.iter()
+            .filter(|tx| pays_base_fee(tx))
+            .map(|tx| tip_rate(tx, block.base_fee))
+            .collect();
+        self.blocks.push_back(rates);
+        if self.blocks.len() > FEE_HISTORY_BLOCKS {
+            self.blocks.pop_front();
+        }
+    }
+    
+    fn sorted_rates(&self) -> Vec<f64> {
+        let mut rates: Vec<f64> = self.blocks.iter().flatten().copied().collect();
+        rates.sort_by(f64::total_cmp);
+        rates
+    }
+}
+
+/// Сколько транзакция платит сверх обязательной части за единицу веса
+fn tip_rate(tx: &Transaction, base_fee: Option<f64>) -> f64 {
+    let weight = tx.weight().max(1);
+    let required = Transaction::minimum_fee(&tx.transaction_type, tx.amount) + base_fee.unwrap_or(0.0) * weight as f64;
+    ((tx.fee - required) / weight as f64).max(0.0)
+}
+
+/// Значение процентиля по ближайшему рангу; для пустого списка 0
+pub(crate) fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.saturating_sub(1)).or(sorted.last()).copied().unwrap_or(0.0)
+}
+
+impl Blockchain {
+    /// Оценивает комиссию транзакции с указанными типом, суммой и весом для подтверждения в пределах
+    /// `target_blocks` блоков. Каждый уровень — наибольшая из двух ставок чаевых:
+    /// - процентиль ставок, попавших в последние `FEE_HISTORY_BLOCKS` блоков (10-й, 50-й и 90-й для
+    ///   low, medium и high): столько платила заданная доля подтвержденных транзакций;
+    /// - ставка, с которой транзакция обгоняет пул настолько, чтобы уместиться в вес `target_blocks`
+    ///   блоков (high — в половину этого веса с запасом на новые транзакции, low — в удвоенный).
+    ///
+    /// Пул может быть перегружен только при `fee_market`, где вес блока ограничен. Без истории и
+    /// очереди оценка равна нижней границе: минимуму политики плюс текущей базовой комиссии.
+    pub fn estimate_fee_for_confirmation(&self, target_blocks: u64, tx_type: &TransactionType, amount: f64, weight: u64) -> FeeEstimate {
+        let base_fee = self.current_base_fee();
+        let floor = Transaction::minimum_fee(tx_type, amount) + base_fee.unwrap_or(0.0) * weight as f64;
+        
+        let history = self.fee_history.sorted_rates();
+        let pending = self.pending_tip_rates(base_fee);
+        let block_capacity = self.fee_market.as_ref().map(|config| config.max_block_weight());
+        let clearing_rate = |share: f64| match block_capacity {
+            Some(capacity) => clearing_rate(&pending, (capacity as f64 * target_blocks.max(1) as f64 * share) as u64, weight),
+            None => 0.0,
+        };
+        
+        let estimate = |q: f64, share: f64| floor + percentile(&history, q).max(clearing_rate(share)) * weight as f64;
+        FeeEstimate {
+            low: estimate(LOW_PERCENTILE, 2.0),
+            medium: estimate(MEDIUM_PERCENTILE, 1.0),
+            high: estimate(HIGH_PERCENTILE, 0.5),
+            floor,
+            samples: history.len(),
+        }
+    }
+    
+    /// Ставки чаевых и веса транзакций пула по убыванию ставки
+    fn pending_tip_rates(&self, base_fee: Option<f64>) -> Vec<(f64, u64)> {
+        let mut rates: Vec<(f64, u64)> = self.mempool.lock().transactions.iter()
+            .filter(|tx| pays_base_fee(tx))
+            .map(|tx| (tip_rate(tx, base_fee), tx.weight()))
+            .collect();
+        rates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        rates
+    }
+    
+    /// Добавляет ставки только что добавленного блока в историю оценщика
+    pub(crate) fn record_fee_rates(&mut self) {
+        if let Some(block) = self.chain.last() {
+            self.fee_history.push(block);
+        }
+    }
+    
+    /// Пересобирает историю ставок по последним блокам, например после загрузки с диска
+    pub fn rebuild_fee_history(&mut self) {
+        self.fee_history = FeeHistory::default();
+        let start = self.chain.len().saturating_sub(FEE_HISTORY_BLOCKS);
+        for block in self.chain.iter().skip(start) {
+            self.fee_history.push(block);
+        }
+    }
+}
+
+/// Ставка, на которую надо превзойти транзакции пула, чтобы вместе с более дорогими уложиться
+/// в `budget` единиц веса; 0, если весь пул помещается
+fn clearing_rate(pending: &[(f64, u64)], budget: u64, weight: u64) -> f64 {
+    let mut used = weight;
+    for &(rate, tx_weight) in pending {
+        used += tx_weight;
+        if used > budget {
+            return rate;
+        }
+    }
+    0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee_market::BaseFeeConfig;
+    use crate::test_support::ChainFixture;
+    
+    /// Перевод от `alice`, который платит обязательную часть и `tip` за единицу веса
+    fn tipped_transfer(chain: &Blockchain, tip: f64) -> Transaction {
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 1.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        let weight = tx.weight() as f64;
+        tx.fee = Transaction::minimum_fee(&tx.transaction_type, tx.amount) + (chain.current_base_fee().unwrap_or(0.0) + tip) * weight;
+        tx.reseal();
+        tx.sign(&keypair);
+        tx
+    }
+    
+    fn estimate(chain: &Blockchain, target_blocks: u64) -> FeeEstimate {
+        let weight = tipped_transfer(chain, 0.0).weight();
+        chain.estimate_fee_for_confirmation(target_blocks, &TransactionType::Transfer, 1.0, weight)
+    }
+    
+    #[test]
+    fn without_history_the_estimate_is_the_floor() {
+        let chain = ChainFixture::new().build().unwrap().chain;
+        let estimate = chain.estimate_fee_for_confirmation(1, &TransactionType::Transfer, 10.0, 200);
+        assert_eq!(estimate.floor, Transaction::minimum_fee(&TransactionType::Transfer, 10.0));
+        assert_eq!((estimate.low, estimate.medium, estimate.high), (estimate.floor, estimate.floor, estimate.floor));
+        assert_eq!(estimate.samples, 0);
+    }
+    
+    #[test]
+    fn congested_pool_raises_the_next_block_estimate() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let transfer_weight = tipped_transfer(&chain, 0.0).weight();
+        chain.fee_market = Some(BaseFeeConfig {
+            initial_base_fee: 0.0001,
+            target_block_weight: transfer_weight * 2,
+            elasticity_multiplier: 1,
+            change_denominator: 8.0,
+            min_base_fee: 0.0,
+        });
+        let idle = estimate(&chain, 1);
+        
+        // Двадцать переводов в пуле — десять блоков по два
+        for i in 1..=20 {
+            let tx = tipped_transfer(&chain, i as f64 * 1e-5);
+            chain.add_transaction(tx).unwrap();
+        }
+        let next_block = estimate(&chain, 1);
+        let five_blocks = estimate(&chain, 5);
+        assert!(next_block.medium > five_blocks.medium, "{:?} {:?}", next_block, five_blocks);
+        assert!(next_block.high > five_blocks.high);
+        assert!(next_block.medium > idle.medium);
+        assert!(five_blocks.medium > five_blocks.floor);
+        assert!(estimate(&chain, 20).medium <= five_blocks.medium);
+    }
+    
+    #[test]
+    fn estimates_follow_mined_blocks_and_never_fall_below_the_floor() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let samples = estimate(&chain, 1).samples;
+        // Закрепления ключей alice и майнера фикстуры уже дали две нулевые ставки
+        for tip in [1e-5, 2e-5, 5e-5, 1e-4] {
+            let tx = tipped_transfer(&chain, tip);
+            chain.add_transaction(tx).unwrap();
+        }
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        
+        let after_mining = estimate(&chain, 1);
+        assert_eq!(after_mining.samples, samples + 4);
+        assert!(after_mining.high > after_mining.medium && after_mining.medium > after_mining.low);
+        
+        for target_blocks in [0, 1, 2, 5, 100] {
+            for (tx_type, amount) in [(TransactionType::Transfer, 0.0), (TransactionType::Transfer, 50.0), (TransactionType::Data(vec![0; 100]), 0.0)] {
+                let estimate = chain.estimate_fee_for_confirmation(target_blocks, &tx_type, amount, 150);
+                assert!(estimate.floor >= Transaction::minimum_fee(&tx_type, amount));
+                assert!(estimate.low >= estimate.floor && estimate.medium >= estimate.low && estimate.high >= estimate.medium);
+            }
+        }
+    }
+}
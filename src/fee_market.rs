@@ -0,0 +1,395 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::codec::encode_transaction;
+use crate::errors::BlockchainError;
+use crate::expiry::is_expired;
+use crate::transaction::{Transaction, TransactionType};
+use crate::freeze::FREEZE_AUTHORITY;
+use crate::governance::GOVERNANCE_ESCROW;
+use crate::mempool::Pool;
+use crate::mint::MINT_AUTHORITY;
+use crate::ordering::{is_coinbase, sort_canonical};
+use crate::packages::{exclude_descendants, rate_weight, with_ancestors};
+use crate::reserved::system_queue_order;
+use crate::staking::STAKING_MINTER;
+use crate::treasury::TREASURY_MINTER;
+use serde::{Serialize, Deserialize};
+
+/// Настройки базовой комиссии: она меняется от блока к блоку по заполненности родителя и сжигается,
+/// а майнеру достаются только чаевые сверх нее
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaseFeeConfig {
+    /// Базовая комиссия за единицу веса в первом блоке режима
+    pub initial_base_fee: f64,
+    /// Вес блока, при котором базовая комиссия не меняется
+    pub target_block_weight: u64,
+    /// Во сколько раз предельный вес блока больше целевого
+    #[serde(default = "default_elasticity")]
+    pub elasticity_multiplier: u64,
+    /// Наибольшее изменение за блок — 1/знаменатель (у полностью заполненного или пустого родителя)
+    #[serde(default = "default_change_denominator")]
+    pub change_denominator: f64,
+    /// Ниже этого значения базовая комиссия не опускается
+    #[serde(default)]
+    pub min_base_fee: f64,
+}
+
+fn default_elasticity() -> u64 {
+    2
+}
+
+fn default_change_denominator() -> f64 {
+    8.0
+}
+
+impl BaseFeeConfig {
+    /// Предельный вес блока
+    pub fn max_block_weight(&self) -> u64 {
+        self.target_block_weight.saturating_mul(self.elasticity_multiplier)
+    }
+    
+    /// Базовая комиссия блока по базовой комиссии и весу его родителя
+    pub fn next_base_fee(&self, parent_base_fee: f64, parent_weight: u64) -> f64 {
+        let target = self.target_block_weight.max(1) as f64;
+        let delta = parent_base_fee * (parent_weight as f64 - target) / target / self.change_denominator;
+        (parent_base_fee + delta).max(self.min_base_fee)
+    }
+}
+
+impl Transaction {
+    /// Вес транзакции — длина ее канонической двоичной записи
+    pub fn weight(&self) -> u64 {
+        encode_transaction(self, 0).len() as u64
+    }
+}
+
+/// Платит ли транзакция базовую комиссию: награда, доля казны, выплаты стейкинга, заморозки счетов,
+/// итоги голосований и операции моста выпускаются протоколом без комиссии
+pub fn pays_base_fee(tx: &Transaction) -> bool {
+    tx.sender != "BLOCKCHAIN_REWARD" && tx.sender != TREASURY_MINTER && tx.sender != STAKING_MINTER && tx.sender != FREEZE_AUTHORITY
+        && tx.sender != GOVERNANCE_ESCROW && tx.sender != MINT_AUTHORITY && !matches!(tx.transaction_type, TransactionType::BridgeMint { .. } | TransactionType::BridgeRelease { .. })
+}
+
+/// Суммарный вес транзакций блока, платящих базовую комиссию
+pub fn block_weight(transactions: &[Transaction]) -> u64 {
+    transactions.iter().filter(|tx| pays_base_fee(tx)).map(Transaction::weight).sum()
+}
+
+/// Сколько комиссий сжигается в блоке с указанной базовой комиссией
+pub fn burned_fees(transactions: &[Transaction], base_fee: Option<f64>) -> f64 {
+    base_fee.map_or(0.0, |base_fee| base_fee * block_weight(transactions) as f64)
+}
+
+/// Доход майнера от комиссий: все комиссии транзакций за вычетом сжигаемой части
+pub fn miner_fees(transactions: &[Transaction], base_fee: Option<f64>) -> f64 {
+    let total: f64 = transactions.iter().filter(|tx| pays_base_fee(tx)).map(|tx| tx.fee).sum();
+    total - burned_fees(transactions, base_fee)
+}
+
+/// Оставляет в пуле транзакции, не отмеченные в `selected`, в прежнем порядке
+pub(crate) fn retain_deferred(pending: &mut Vec<Transaction>, selected: &[bool]) {
+    let mut selected = selected.iter();
+    pending.retain(|_| !selected.next().copied().unwrap_or(false));
+}
+
+impl Blockchain {
+    /// Базовая комиссия за единицу веса для следующего блока; None, если режим не включен
+    pub fn current_base_fee(&self) -> Option<f64> {
+        self.expected_base_fee(self.get_latest_block())
+    }
+    
+    /// Базовая комиссия, которую должен нести потомок блока `parent`
+    pub(crate) fn expected_base_fee(&self, parent: &Block) -> Option<f64> {
+        let config = self.fee_market.as_ref()?;
+        Some(match parent.base_fee {
+            Some(parent_base_fee) => config.next_base_fee(parent_base_fee, block_weight(&parent.transactions)),
+            None => config.initial_base_fee,
+        })
+    }
+    
+    /// Сколько комиссий сожжено за всю цепочку
+    pub fn total_burned(&self) -> f64 {
+        self.chain.iter().map(|block| burned_fees(&block.transactions, block.base_fee)).sum()
+    }
+    
+    /// Проверяет, что транзакция покрывает базовую комиссию и помещается в блок
+    pub(crate) fn check_base_fee(&self, tx: &Transaction, base_fee: Option<f64>) -> Result<(), BlockchainError> {
+        let (Some(config), Some(base_fee)) = (&self.fee_market, base_fee) else {
+            return Ok(());
+        };
+        if !pays_base_fee(tx) {
+            return Ok(());
+        }
+        
+        let weight = tx.weight();
+        if weight > config.max_block_weight() {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} weighs {}, more than a block can hold", tx.id, weight)));
+        }
+        let required = base_fee * weight as f64;
+        if tx.fee < required {
+            return Err(BlockchainError::FeeTooLow { required, provided: tx.fee });
+        }
+        Ok(())
+    }
+    
+    /// Минимальная комиссия транзакции со скидкой по текущему стейку отправителя
+    pub fn required_minimum_fee(&self, tx: &Transaction) -> f64 {
+        let stake = self.wallets.get(&tx.sender).map_or(0.0, |wallet| wallet.staking_balance);
+        Transaction::minimum_fee(&tx.transaction_type, tx.amount) * (1.0 - self.params.fee_discount(stake))
+    }
+    
+    /// Проверяет, что транзакция платит минимальную комиссию; выпускаемые протоколом не платят
+    pub(crate) fn check_minimum_fee(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        if !pays_base_fee(tx) {
+            return Ok(());
+        }
+        let required = self.required_minimum_fee(tx);
+        if tx.fee + f64::EPSILON < required {
+            return Err(BlockchainError::FeeTooLow { required, provided: tx.fee });
+        }
+        Ok(())
+    }
+    
+    /// Делит транзакции пула на попадающие в блок высоты `height` и остающиеся ждать. Просроченные
+    /// к этой высоте ждут, пока их не уберет пул. Скидка за стейк
+    /// и существование получателя проверяются на момент включения в блок, поэтому транзакция,
+    /// чей отправитель с тех пор снял стейк или получатель удален, ждет. Вместе с транзакцией ждут
+    /// ее потомки в пуле. Без базовой комиссии в блок идут все остальные, иначе — сначала системные транзакции
+    /// в пределах резерва, затем оплатившие ее пакеты по убыванию ставки, пока не набран предельный вес.
+    /// Возвращает копии попавших в блок в каноническом порядке и отметки отбора по индексам пула.
+    pub(crate) fn select_block_transactions(&self, pool: &Pool, height: u64, base_fee: Option<f64>) -> (Vec<Transaction>, Vec<bool>) {
+        let pending = &pool.transactions;
+        let config = self.fee_market.as_ref().filter(|_| base_fee.is_some());
+        let parents = self.pending_graph(pool);
+        let excluded = self.unselectable(pending, &parents, height, base_fee);
+        
+        let selected = match config {
+            None => excluded.iter().map(|excluded| !excluded).collect(),
+            Some(config) => {
+                // Ставка считается по пакету с предками, и транзакция попадает в блок только вместе с ними
+                let package_rate = |package: &[usize]| {
+                    let (fee, weight) = package.iter().fold((0.0, 0), |(fee, weight), &i| (fee + pending[i].fee, weight + rate_weight(&pending[i])));
+                    fee / weight as f64
+                };
+                let mut candidates: Vec<(usize, f64)> = (0..pending.len())
+                    .filter(|&i| !excluded[i])
+                    .map(|i| (i, package_rate(&with_ancestors(&parents, i, &excluded))))
+                    .collect();
+                candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+                
+                let mut selected = vec![false; pending.len()];
+                let mut weight = 0;
+                let mut take = |i: usize, limit: u64, selected: &mut Vec<bool>| {
+                    if selected[i] || excluded[i] {
+                        return;
+                    }
+                    let package = with_ancestors(&parents, i, selected);
+                    let package_weight: u64 = package.iter().map(|&j| &pending[j]).filter(|tx| pays_base_fee(tx)).map(Transaction::weight).sum();
+                    if weight + package_weight <= limit {
+                        weight += package_weight;
+                        for j in package {
+                            selected[j] = true;
+                        }
+                    }
+                };
+                // Сначала резерв заполняют системные транзакции по давности, остаток резерва достается остальным
+                if let Some(reserved_weight) = self.reserved_weight() {
+                    for i in system_queue_order(pool) {
+                        take(i, reserved_weight, &mut selected);
+                    }
+                }
+                for (i, _) in candidates {
+                    take(i, config.max_block_weight(), &mut selected);
+                }
+                selected
+            },
+        };
+        
+        let included = pending.iter().zip(&selected).filter(|(_, &selected)| selected).map(|(tx, _)| tx.clone()).collect();
+        (sort_canonical(included), selected)
+    }
+    
+    /// Транзакции пула, которые не могут попасть в блок высоты `height`, вместе с их потомками
+    pub(crate) fn unselectable(&self, pending: &[Transaction], parents: &[Vec<usize>], height: u64, base_fee: Option<f64>) -> Vec<bool> {
+        let fee_market = self.fee_market.is_some() && base_fee.is_some();
+        let mut excluded: Vec<bool> = pending.iter()
+            .map(|tx| {
+                // Выплаты конца блока строит только его производитель, а не пул
+                is_coinbase(tx) || is_expired(tx, height) || self.check_minimum_fee(tx).is_err() || self.check_existential_deposit(tx).is_err()
+                    || (fee_market && self.check_base_fee(tx, base_fee).is_err())
+            })
+            .collect();
+        exclude_descendants(parents, &mut excluded);
+        excluded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::params::FeeDiscountTier;
+    use crate::signing::Keypair;
+    use crate::test_support::ChainFixture;
+    
+    const INITIAL_BASE_FEE: f64 = 0.001;
+    const TIP: f64 = 0.01;
+    
+    fn keypair(chain: &Blockchain) -> Keypair {
+        chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone()
+    }
+    
+    /// Перевод от `alice`, оплачивающий базовую комиссию по своему весу и чаевые
+    fn priced_transfer(chain: &Blockchain, amount: f64, base_fee: f64) -> Transaction {
+        let keypair = keypair(chain);
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        tx.fee = base_fee * tx.weight() as f64 + TIP;
+        tx.reseal();
+        tx.sign(&keypair);
+        tx
+    }
+    
+    /// Копия цепочки без ключей кошельков
+    fn copy(chain: &Blockchain) -> Blockchain {
+        serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap()
+    }
+    
+    /// Цепочка, в которой целевой вес блока равен весу одного перевода
+    fn chain_with_fee_market() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let transfer_weight = priced_transfer(&chain, 1.0, INITIAL_BASE_FEE).weight();
+        chain.fee_market = Some(BaseFeeConfig {
+            initial_base_fee: INITIAL_BASE_FEE,
+            target_block_weight: transfer_weight,
+            elasticity_multiplier: 4,
+            change_denominator: 8.0,
+            min_base_fee: 0.0,
+        });
+        chain
+    }
+    
+    #[test]
+    fn base_fee_follows_block_fullness_and_is_burned() {
+        let mut chain = chain_with_fee_market();
+        assert_eq!(chain.current_base_fee(), Some(INITIAL_BASE_FEE));
+        for amount in [1.0, 2.0, 3.0] {
+            let tx = priced_transfer(&chain, amount, INITIAL_BASE_FEE);
+            chain.add_transaction(tx).unwrap();
+        }
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let full = chain.get_latest_block().clone();
+        assert_eq!(full.base_fee, Some(INITIAL_BASE_FEE));
+        assert_eq!(full.transactions.len(), 4);
+        let after_full = chain.current_base_fee().unwrap();
+        assert!(after_full > INITIAL_BASE_FEE, "{}", after_full);
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let after_empty = chain.current_base_fee().unwrap();
+        assert!((after_empty - after_full * (1.0 - 1.0 / 8.0)).abs() < 1e-12, "{}", after_empty);
+        
+        // Сжигается базовая часть, майнер получает награду и чаевые
+        let burned = INITIAL_BASE_FEE * block_weight(&full.transactions) as f64;
+        assert!((chain.total_burned() - burned).abs() < 1e-9);
+        let coinbase = full.transactions.iter().find(|tx| tx.sender == "BLOCKCHAIN_REWARD").unwrap();
+        assert!((coinbase.amount - (100.0 + 3.0 * TIP)).abs() < 1e-6, "{}", coinbase.amount);
+        assert!((chain.circulating_supply() - (300.0 - burned)).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn block_with_an_underpriced_transaction_fails_validation() {
+        let chain = chain_with_fee_market();
+        let tx = priced_transfer(&chain, 10.0, INITIAL_BASE_FEE);
+        let required = INITIAL_BASE_FEE * tx.weight() as f64;
+        
+        let mut underpriced = tx.clone();
+        underpriced.fee = required / 2.0;
+        underpriced.reseal();
+        underpriced.sign(&keypair(&chain));
+        assert!(matches!(copy(&chain).add_transaction(underpriced.clone()), Err(BlockchainError::FeeTooLow { .. })));
+        
+        // Честный блок собирается копией цепочки, затем комиссия в нем занижается с новой подписью
+        let mut producer = copy(&chain);
+        producer.add_transaction(tx).unwrap();
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        let mut block = producer.get_latest_block().clone();
+        chain.verify_block(&block).unwrap();
+        
+        *block.transactions.iter_mut().find(|tx| tx.sender == "alice").unwrap() = underpriced;
+        let fees = miner_fees(&block.transactions, block.base_fee);
+        let reward = block.transactions.iter_mut().find(|tx| tx.sender == "BLOCKCHAIN_REWARD").unwrap();
+        reward.amount = chain.reward_at_height(block.index) + fees;
+        reward.reseal();
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.mine_block();
+        
+        let result = chain.verify_block(&block);
+        assert!(matches!(result, Err(BlockchainError::FeeTooLow { .. })), "{:?}", result);
+    }
+    
+    /// Перевод 100 монет с минимальной комиссией 0.1 и указанной фактической комиссией
+    fn transfer_with_fee(chain: &Blockchain, sender: &str, fee: f64) -> Transaction {
+        let keypair = chain.get_wallet_info(sender).unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new(sender.to_string(), "bob".to_string(), 100.0, TransactionType::Transfer);
+        tx.fee = fee;
+        tx.set_nonce(chain.get_account_nonce(sender));
+        tx.sign(&keypair);
+        tx
+    }
+    
+    #[test]
+    fn stake_brackets_lower_the_minimum_fee() {
+        let mut chain = ChainFixture::chain(1, 2_000.0, ConsensusAlgorithm::ProofOfWork);
+        chain.params.fee_discount_tiers = vec![
+            FeeDiscountTier { min_stake: 100.0, percent: 10.0 },
+            FeeDiscountTier { min_stake: 1_000.0, percent: 25.0 },
+        ];
+        for address in ["alice", "staker"] {
+            chain.create_wallet(address.to_string()).unwrap();
+            chain.mine_pending_transactions(address.to_string()).unwrap();
+        }
+        
+        // Без стейка скидки нет
+        assert!(matches!(chain.add_transaction(transfer_with_fee(&chain, "alice", 0.09)), Err(BlockchainError::FeeTooLow { .. })));
+        chain.add_transaction(transfer_with_fee(&chain, "alice", 0.1)).unwrap();
+        
+        chain.add_validator("staker".to_string(), 999.0).unwrap();
+        let discounted = transfer_with_fee(&chain, "staker", 0.09);
+        let discounted_id = discounted.id.clone();
+        chain.add_transaction(discounted).unwrap();
+        assert!(matches!(chain.add_transaction(transfer_with_fee(&chain, "staker", 0.075)), Err(BlockchainError::FeeTooLow { .. })));
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let receipt = chain.get_transaction_receipt(&discounted_id).unwrap();
+        assert!((receipt.fee_discount - 0.01).abs() < 1e-9, "{}", receipt.fee_discount);
+        
+        // Стейк 1000 переходит на следующую ступень
+        chain.add_validator("staker".to_string(), 1.0).unwrap();
+        assert_eq!(chain.get_wallet_info("staker").unwrap().staking_balance, 1_000.0);
+        chain.add_transaction(transfer_with_fee(&chain, "staker", 0.075)).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.mempool().is_empty());
+    }
+    
+    #[test]
+    fn discount_is_checked_against_the_stake_when_mined() {
+        let mut chain = ChainFixture::chain(1, 2_000.0, ConsensusAlgorithm::ProofOfWork);
+        chain.params.fee_discount_tiers = vec![FeeDiscountTier { min_stake: 100.0, percent: 10.0 }];
+        chain.create_wallet("staker".to_string()).unwrap();
+        chain.mine_pending_transactions("staker".to_string()).unwrap();
+        chain.add_validator("staker".to_string(), 100.0).unwrap();
+        
+        let tx = transfer_with_fee(&chain, "staker", 0.09);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx).unwrap();
+        chain.remove_validator("staker").unwrap();
+        
+        // Стейк снят до блока: транзакция ждет в пуле
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.get_latest_block().transactions.iter().all(|tx| tx.id != tx_id));
+        assert!(chain.mempool().transactions().iter().any(|tx| tx.id == tx_id));
+    }
+}
@@ -0,0 +1,302 @@
+use crate::block::Block;
+use crate::blockchain::{BlockAcceptance, Blockchain};
+use crate::errors::BlockchainError;
+use crate::events::ChainEvent;
+use crate::hashing::check_block_hash_algorithm;
+use crate::sync::{header_work, validate_header};
+use crate::trace::warn;
+use crate::transaction::verify_signatures;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+/// Глубина, после которой блок главной цепочки считается окончательным
+pub const FINALITY_DEPTH: u64 = 6;
+
+/// Сколько блоков боковых веток узел держит в памяти
+pub const MAX_FORK_BLOCKS: usize = 256;
+
+/// Блоки боковых веток, сгруппированные по хешу родителя
+#[derive(Default)]
+pub struct ForkIndex {
+    children: HashMap<String, Vec<Block>>,
+    len: usize,
+}
+
+impl ForkIndex {
+    pub fn get(&self, hash: &str) -> Option<&Block> {
+        self.children.values().flatten().find(|block| block.hash == hash)
+    }
+    
+    pub fn contains(&self, hash: &str) -> bool {
+        self.get(hash).is_some()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    fn insert(&mut self, block: Block) {
+        self.children.entry(block.previous_hash.clone()).or_default().push(block);
+        self.len += 1;
+    }
+    
+    /// Все блоки индекса в произвольном порядке
+    pub(crate) fn blocks(&self) -> impl Iterator<Item = &Block> {
+        self.children.values().flatten()
+    }
+    
+    /// Вершины веток: блоки, у которых нет потомков в индексе
+    fn leaves(&self) -> impl Iterator<Item = &Block> {
+        self.children.values().flatten().filter(|block| !self.children.contains_key(&block.hash))
+    }
+    
+    /// Удаляет потомков блока с этим хешем на всю глубину
+    fn remove_descendants(&mut self, hash: &str) {
+        let mut pending = vec![hash.to_string()];
+        while let Some(hash) = pending.pop() {
+            for child in self.children.remove(&hash).unwrap_or_default() {
+                self.len -= 1;
+                pending.push(child.hash);
+            }
+        }
+    }
+}
+
+/// Конкурирующая ветка от точки расхождения с главной цепочкой до ее вершины
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForkInfo {
+    /// Высота и хеш последнего общего с главной цепочкой блока
+    pub fork_height: u64,
+    pub fork_hash: String,
+    /// Число блоков ветки после точки расхождения
+    pub length: u64,
+    pub tip_height: u64,
+    pub tip_hash: String,
+    /// Суммарная работа ветки после точки расхождения; для PoS — число блоков
+    pub work: u128,
+    /// Работа главной цепочки на том же участке
+    pub main_work: u128,
+}
+
+impl ForkInfo {
+    /// Тяжелее ли ветка главной цепочки. Это только сигнал: реорганизации нет, и узел остается
+    /// на своей цепочке, даже если ветка тяжелее
+    pub fn is_heavier(&self) -> bool {
+        self.work > self.main_work
+    }
+}
+
+impl Blockchain {
+    /// Высота, ниже которой ветки больше не рассматриваются
+    pub fn finalized_height(&self) -> u64 {
+        self.get_latest_block().index.saturating_sub(FINALITY_DEPTH)
+    }
+    
+    /// Конкурирующие ветки по высоте расхождения, затем по хешу вершины
+    pub fn forks(&self) -> Vec<ForkInfo> {
+        let mut forks: Vec<ForkInfo> = self.forks.leaves().filter_map(|tip| self.fork_info(tip)).collect();
+        forks.sort_by(|a, b| a.fork_height.cmp(&b.fork_height).then_with(|| a.tip_hash.cmp(&b.tip_hash)));
+        forks
+    }
+    
+    /// Описывает ветку, проходя от вершины назад до главной цепочки
+    fn fork_info(&self, tip: &Block) -> Option<ForkInfo> {
+        let mut work = 0;
+        let mut block = tip;
+        loop {
+            work += header_work(&block.header(), &self.consensus_algorithm);
+            match self.forks.get(&block.previous_hash) {
+                Some(parent) => block = parent,
+                None => break,
+            }
+        }
+        
+        let fork_point = self.get_block_by_hash(&block.previous_hash)?;
+        let main_work = self.chain[fork_point.index as usize + 1..].iter()
+            .map(|block| header_work(&block.header(), &self.consensus_algorithm))
+            .sum();
+        Some(ForkInfo {
+            fork_height: fork_point.index,
+            fork_hash: fork_point.hash.clone(),
+            length: tip.index - fork_point.index,
+            tip_height: tip.index,
+            tip_hash: tip.hash.clone(),
+            work,
+            main_work,
+        })
+    }
+    
+    /// Принимает блок, который продолжает не вершину, а более ранний блок или боковую ветку.
+    /// Ветка только учитывается: узел не переключается на нее, даже если она тяжелее. Откатывать
+    /// состояние до точки расхождения узел не умеет, поэтому реорганизации нет совсем; узел на более
+    /// легкой ветке перейдет на другую только после новой синхронизации с генезиса или снимка.
+    pub(crate) fn accept_side_block(&mut self, block: Block) -> Result<BlockAcceptance, BlockchainError> {
+        let side_parent = self.forks.get(&block.previous_hash);
+        let on_main_chain = side_parent.is_none();
+        let Some(parent) = side_parent.or_else(|| self.get_block_by_hash(&block.previous_hash)) else {
+            return Ok(BlockAcceptance::Stale);
+        };
+        let fork_height = match side_parent {
+            Some(parent) => self.fork_info(parent).map(|fork| fork.fork_height),
+            None => Some(parent.index),
+        };
+        
+        validate_header(&parent.header(), &block.header(), &self.consensus_algorithm)
+            .map_err(|e| BlockchainError::InvalidBlock(format!("Side block # {}: {}", block.index, e)))?;
+        check_block_hash_algorithm(&block, self.hash_algorithm)?;
+        if block.merkle_root != Block::calculate_merkle_root(self.hash_algorithm, &block.transactions) {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong Merkle root in block # {}", block.index)));
+        }
+        verify_signatures(&block.transactions, self.signature_verification, self.accepts_legacy_signatures(block.index))?;
+        if fork_height.is_none_or(|height| height < self.finalized_height()) {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "Block # {} forks below the finalized height {}", block.index, self.finalized_height()
+            )));
+        }
+        if self.forks.len() >= MAX_FORK_BLOCKS {
+            return Err(BlockchainError::InvalidBlock("Fork index is full".to_string()));
+        }
+        
+        let hash = block.hash.clone();
+        self.forks.insert(block);
+        let Some(fork) = self.forks.get(&hash).and_then(|tip| self.fork_info(tip)) else {
+            return Ok(BlockAcceptance::Forked);
+        };
+        if fork.is_heavier() {
+            warn!(fork_height = fork.fork_height, tip = %fork.tip_hash, work = fork.work, main_work = fork.main_work, "Competing branch has more work; staying on the main chain");
+        }
+        if on_main_chain {
+            self.events.emit(&ChainEvent::ForkDetected { fork });
+        }
+        Ok(BlockAcceptance::Forked)
+    }
+    
+    /// Удаляет ветки, разошедшиеся с главной цепочкой ниже окончательной высоты
+    pub(crate) fn prune_forks(&mut self) {
+        if self.forks.is_empty() {
+            return;
+        }
+        
+        let finalized = self.finalized_height();
+        let resolved: Vec<ForkInfo> = self.forks().into_iter().filter(|fork| fork.fork_height < finalized).collect();
+        for fork in resolved {
+            self.forks.remove_descendants(&fork.fork_hash);
+            self.events.emit(&ChainEvent::ForkResolved { fork });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::{ChainFixture, FIXTURE_MINER};
+    use std::sync::{Arc, Mutex};
+    
+    /// Копия цепочки, которая дальше майнит свою ветку
+    fn rival(chain: &Blockchain) -> Blockchain {
+        serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap()
+    }
+    
+    fn record_events(chain: &mut Blockchain) -> Arc<Mutex<Vec<ChainEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        chain.add_event_hook(Box::new(move |event| {
+            if matches!(event, ChainEvent::ForkDetected { .. } | ChainEvent::ForkResolved { .. }) {
+                sink.lock().unwrap().push(event.clone());
+            }
+            true
+        }));
+        events
+    }
+    
+    #[test]
+    fn side_branch_is_reported_and_resolved_past_finality() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let mut side = rival(&chain);
+        let mut late = rival(&chain);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let events = record_events(&mut chain);
+        let main_tip = chain.get_latest_block().hash.clone();
+        
+        side.mine_pending_transactions("rival".to_string()).unwrap();
+        assert_eq!(chain.accept_block(side.get_latest_block().clone()).unwrap(), BlockAcceptance::Forked);
+        let forks = chain.forks();
+        assert_eq!(forks.len(), 1);
+        let fork = &forks[0];
+        assert_eq!((fork.fork_height, fork.length, fork.tip_height), (1, 1, 2));
+        assert_eq!(fork.fork_hash, chain.chain[1].hash);
+        assert_eq!(fork.tip_hash, side.get_latest_block().hash);
+        assert!(!fork.is_heavier());
+        
+        // Ветка обгоняет главную цепочку, но узел на нее не переключается
+        side.mine_pending_transactions("rival".to_string()).unwrap();
+        assert_eq!(chain.accept_block(side.get_latest_block().clone()).unwrap(), BlockAcceptance::Forked);
+        let forks = chain.forks();
+        assert_eq!(forks.len(), 1);
+        assert_eq!((forks[0].length, forks[0].tip_height), (2, 3));
+        assert!(forks[0].is_heavier());
+        assert_eq!(chain.get_latest_block().hash, main_tip);
+        assert!(matches!(events.lock().unwrap().as_slice(), [ChainEvent::ForkDetected { fork }] if fork.length == 1));
+        
+        while chain.finalized_height() <= 1 {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        assert!(chain.forks().is_empty());
+        assert!(chain.forks.is_empty());
+        let events = events.lock().unwrap();
+        assert!(matches!(events.as_slice(), [_, ChainEvent::ForkResolved { fork }] if fork.fork_height == 1 && fork.length == 2));
+        
+        // Продолжение убранной ветки уже не к чему присоединить, а новая ветка ниже окончательной высоты отклоняется
+        side.mine_pending_transactions("rival".to_string()).unwrap();
+        assert_eq!(chain.accept_block(side.get_latest_block().clone()).unwrap(), BlockAcceptance::Stale);
+        late.mine_pending_transactions("late".to_string()).unwrap();
+        let result = chain.accept_block(late.get_latest_block().clone());
+        assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason.contains("below the finalized height")), "{:?}", result);
+    }
+    
+    #[test]
+    fn heavier_branch_is_reported_but_never_adopted() {
+        let mut chain = ChainFixture::new().with_mined_blocks(1).build().unwrap().chain;
+        let mut side = rival(&chain);
+        let shared_work = chain.cumulative_work();
+        for _ in 0..2 {
+            chain.mine_pending_transactions(FIXTURE_MINER.to_string()).unwrap();
+        }
+        let events = record_events(&mut chain);
+        let main_tip = chain.get_latest_block().hash.clone();
+        let miner_balance = chain.get_balance(FIXTURE_MINER);
+        
+        // Ветка догоняет главную цепочку на высоте 3 и уходит на два блока дальше
+        for height in 2..=5 {
+            side.mine_pending_transactions("rival".to_string()).unwrap();
+            assert_eq!(chain.accept_block(side.get_latest_block().clone()).unwrap(), BlockAcceptance::Forked, "height {}", height);
+        }
+        let forks = chain.forks();
+        assert_eq!(forks.len(), 1);
+        let fork = &forks[0];
+        assert_eq!((fork.fork_height, fork.length, fork.tip_height), (1, 4, 5));
+        assert_eq!(fork.tip_hash, side.get_latest_block().hash);
+        assert_eq!(fork.work, side.cumulative_work() - shared_work);
+        assert_eq!(fork.main_work, chain.cumulative_work() - shared_work);
+        assert!(fork.is_heavier());
+        
+        // Реорганизации нет: вершина, состояние и события остаются прежними
+        assert_eq!(chain.get_latest_block().hash, main_tip);
+        assert_eq!(chain.get_latest_block().index, 3);
+        assert_eq!(chain.get_balance(FIXTURE_MINER), miner_balance);
+        assert_eq!(chain.get_balance("rival"), 0.0);
+        assert!(matches!(events.lock().unwrap().as_slice(), [ChainEvent::ForkDetected { fork }] if fork.length == 1));
+        
+        // Узел продолжает свою цепочку, а ветка по-прежнему числится тяжелее
+        chain.mine_pending_transactions(FIXTURE_MINER.to_string()).unwrap();
+        assert_eq!(chain.get_latest_block().previous_hash, main_tip);
+        assert!(chain.forks()[0].is_heavier());
+        assert!(chain.is_chain_valid());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::bridge::BRIDGE_MINTER;
+use crate::mint::MINT_AUTHORITY;
+use crate::errors::BlockchainError;
+use crate::staking::STAKING_MINTER;
+use crate::transaction::{Transaction, TransactionType};
+use crate::treasury::TREASURY_MINTER;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+
+/// Отправитель транзакций заморозки и разморозки счетов
+pub const FREEZE_AUTHORITY: &str = "BLOCKCHAIN_ADMIN";
+
+/// Кто может замораживать счета и что запрещено замороженным адресам
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FreezeConfig {
+    /// Администраторы; заморозку и разморозку одобряют не меньше `threshold` из них
+    pub admins: BTreeSet<String>,
+    pub threshold: usize,
+    /// Запрещать ли замороженным адресам и получать средства
+    #[serde(default)]
+    pub block_receiving: bool,
+}
+
+impl Blockchain {
+    /// Включает заморозку счетов администраторами
+    pub fn enable_account_freeze(&mut self, config: FreezeConfig) -> Result<(), BlockchainError> {
+        if config.threshold == 0 || config.threshold > config.admins.len() {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Freeze threshold {} does not fit {} admins", config.threshold, config.admins.len()
+            )));
+        }
+        self.account_freeze = Some(config);
+        Ok(())
+    }
+    
+    /// Заморожен ли адрес
+    pub fn is_frozen(&self, address: &str) -> bool {
+        self.wallets.get(address).is_some_and(|wallet| wallet.frozen.is_some())
+    }
+    
+    /// Ставит в очередь заморозку адреса с одобрениями администраторов и возвращает идентификатор транзакции
+    pub fn freeze_account(&mut self, address: &str, reason: &str, approvals: Vec<String>) -> Result<String, BlockchainError> {
+        self.queue_admin_action(address, TransactionType::FreezeAccount { reason: reason.to_string(), approvals })
+    }
+    
+    /// Ставит в очередь разморозку адреса с одобрениями администраторов и возвращает идентификатор транзакции
+    pub fn unfreeze_account(&mut self, address: &str, approvals: Vec<String>) -> Result<String, BlockchainError> {
+        self.queue_admin_action(address, TransactionType::UnfreezeAccount { approvals })
+    }
+    
+    fn queue_admin_action(&mut self, address: &str, transaction_type: TransactionType) -> Result<String, BlockchainError> {
+        let mut tx = self.new_transaction(FREEZE_AUTHORITY.to_string(), address.to_string(), 0.0, transaction_type);
+        tx.fee = 0.0;
+        let tx_id = tx.id.clone();
+        self.queue_protocol_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Отклоняет транзакции замороженных отправителей (и получателей, если так настроено)
+    /// и проверяет полномочия транзакций заморозки
+    pub(crate) fn check_freeze_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        if self.is_frozen(&tx.sender) {
+            return Err(BlockchainError::AccountFrozen { address: tx.sender.clone() });
+        }
+        if self.account_freeze.as_ref().is_some_and(|config| config.block_receiving) && self.is_frozen(&tx.receiver) {
+            return Err(BlockchainError::AccountFrozen { address: tx.receiver.clone() });
+        }
+        
+        let (approvals, freeze) = match &tx.transaction_type {
+            TransactionType::FreezeAccount { approvals, .. } => (approvals, true),
+            TransactionType::UnfreezeAccount { approvals } => (approvals, false),
+            _ if tx.sender == FREEZE_AUTHORITY => {
+                return Err(BlockchainError::InvalidTransaction(format!("{} only freezes and unfreezes accounts", FREEZE_AUTHORITY)));
+            },
+            _ => return Ok(()),
+        };
+        
+        let config = self.account_freeze.as_ref()
+            .ok_or(BlockchainError::FeatureDisabled { feature: "Account freezing" })?;
+        if tx.sender != FREEZE_AUTHORITY || tx.amount != 0.0 {
+            return Err(BlockchainError::InvalidTransaction(format!("Admin action {} must be sent by {} without an amount", tx.id, FREEZE_AUTHORITY)));
+        }
+        
+        let admins: BTreeSet<&String> = approvals.iter().collect();
+        if admins.len() != approvals.len() {
+            return Err(BlockchainError::InvalidTransaction(format!("Admin action {} repeats an approval", tx.id)));
+        }
+        if let Some(outsider) = admins.iter().find(|admin| !config.admins.contains(admin.as_str())) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} is not a freeze admin", outsider)));
+        }
+        if admins.len() < config.threshold {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Admin action {} has {} of {} required approvals", tx.id, admins.len(), config.threshold
+            )));
+        }
+        
+        let target = tx.receiver.as_str();
+        if [FREEZE_AUTHORITY, "BLOCKCHAIN_REWARD", BRIDGE_MINTER, MINT_AUTHORITY, TREASURY_MINTER, STAKING_MINTER].contains(&target) || config.admins.contains(target) {
+            return Err(BlockchainError::InvalidTransaction(format!("Account {} cannot be frozen", target)));
+        }
+        if freeze == self.is_frozen(target) {
+            let state = if freeze { "already frozen" } else { "not frozen" };
+            return Err(BlockchainError::InvalidTransaction(format!("Account {} is {}", target, state)));
+        }
+        Ok(())
+    }
+    
+    /// Применяет заморозки и разморозки из блока. Ожидающие транзакции замороженного адреса
+    /// снимаются из пула с возвратом средств, так как в блок они уже не попадут.
+    pub(crate) fn apply_account_freezes(&mut self, block: &Block) {
+        let block_receiving = self.account_freeze.as_ref().is_some_and(|config| config.block_receiving);
+        for tx in block.transactions.iter().filter(|tx| tx.sender == FREEZE_AUTHORITY) {
+            let wallet = self.get_or_create_wallet(&tx.receiver);
+            match &tx.transaction_type {
+                TransactionType::FreezeAccount { reason, .. } => wallet.frozen = Some(reason.clone()),
+                TransactionType::UnfreezeAccount { .. } => {
+                    wallet.frozen = None;
+                    continue;
+                },
+                _ => continue,
+            }
+            
+            let stuck: Vec<String> = self.mempool.lock().transactions.iter()
+                .filter(|pending| pending.sender == tx.receiver || (block_receiving && pending.receiver == tx.receiver && pending.sender != FREEZE_AUTHORITY))
+                .map(|pending| pending.id.clone())
+                .collect();
+            for tx_id in stuck {
+                let _ = self.cancel_pending_transaction(&tx_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    fn admins(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+    
+    fn chain_with_admins() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain.enable_account_freeze(FreezeConfig {
+            admins: admins(&["admin1", "admin2", "admin3"]).into_iter().collect(),
+            threshold: 2,
+            block_receiving: false,
+        }).unwrap();
+        chain
+    }
+    
+    fn mine(chain: &mut Blockchain) {
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+    }
+    
+    fn transfer(chain: &Blockchain, amount: f64) -> Transaction {
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        tx
+    }
+    
+    #[test]
+    fn frozen_account_cannot_send_until_unfrozen() {
+        let mut chain = chain_with_admins();
+        chain.freeze_account("alice", "compromised key", admins(&["admin1", "admin3"])).unwrap();
+        mine(&mut chain);
+        assert!(chain.is_frozen("alice"));
+        let result = chain.add_transaction(transfer(&chain, 1.0));
+        assert!(matches!(&result, Err(BlockchainError::AccountFrozen { address }) if address == "alice"), "{:?}", result);
+        
+        // Заморозка переживает сохранение цепочки
+        let dir = std::env::temp_dir().join(format!("hellochain-freeze-{}", std::process::id()));
+        chain.save(&dir).unwrap();
+        let mut chain = Blockchain::load(&dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(chain.get_wallet_info("alice").unwrap().frozen.as_deref(), Some("compromised key"));
+        
+        chain.unfreeze_account("alice", admins(&["admin2", "admin3"])).unwrap();
+        mine(&mut chain);
+        assert!(!chain.is_frozen("alice"));
+        chain.add_transaction(transfer(&chain, 1.0)).unwrap();
+        mine(&mut chain);
+        assert_eq!(chain.get_balance("bob"), 1.0);
+    }
+    
+    #[test]
+    fn only_enough_admins_can_freeze_ordinary_accounts() {
+        let mut chain = chain_with_admins();
+        let outsider = chain.freeze_account("alice", "", admins(&["admin1", "mallory"]));
+        assert!(matches!(&outsider, Err(BlockchainError::InvalidTransaction(reason)) if reason == "mallory is not a freeze admin"), "{:?}", outsider);
+        assert!(chain.freeze_account("alice", "", admins(&["admin1"])).is_err());
+        assert!(chain.freeze_account("alice", "", admins(&["admin1", "admin1"])).is_err());
+        for target in ["admin2", FREEZE_AUTHORITY, "BLOCKCHAIN_REWARD", TREASURY_MINTER] {
+            let result = chain.freeze_account(target, "", admins(&["admin1", "admin3"]));
+            assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.ends_with("cannot be frozen")), "{}: {:?}", target, result);
+        }
+        
+        assert!(chain.mempool().is_empty());
+        mine(&mut chain);
+        assert!(!chain.is_frozen("alice"));
+    }
+    
+    #[test]
+    fn block_with_a_frozen_sender_fails_validation() {
+        let mut chain = chain_with_admins();
+        chain.freeze_account("alice", "compromised key", admins(&["admin1", "admin2"])).unwrap();
+        mine(&mut chain);
+        
+        // Узел, не знающий о заморозке, включает в блок перевод замороженного адреса
+        let mut producer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        producer.wallets.get_mut("alice").unwrap().frozen = None;
+        producer.add_transaction(transfer(&chain, 1.0)).unwrap();
+        mine(&mut producer);
+        
+        let result = chain.verify_block(producer.get_latest_block());
+        assert!(matches!(&result, Err(BlockchainError::AccountFrozen { address }) if address == "alice"), "{:?}", result);
+    }
+}
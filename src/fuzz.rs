@@ -0,0 +1,56 @@
+use crate::block::Block;
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::codec::{decode_raw, encode_transaction, RAW_TRANSACTION_VERSION};
+use crate::transaction::SignatureVerification;
+
+/// Цепочка, с вершиной которой сверяются блоки в `accept_block`
+fn fixed_chain() -> Blockchain {
+    let mut chain = Blockchain::new(1, 50.0, ConsensusAlgorithm::ProofOfWork);
+    chain.accept_legacy_addresses = true;
+    chain.signature_verification = SignatureVerification::Serial;
+    chain.mining_threads = Some(1);
+    for address in ["alice", "bob"] {
+        // Кошельки держат свои ключи, иначе их транзакции не пройдут проверку подписи
+        let _ = chain.create_wallet(address.to_string());
+        let _ = chain.allocate_at_genesis(address, 1000.0);
+    }
+    chain
+}
+
+/// Разбирает транзакцию из байт или hex; разобранная двоичная транзакция кодируется обратно в те же байты
+pub fn transaction_decode(data: &[u8]) {
+    let Ok(raw) = decode_raw(data) else { return };
+    if data.first() == Some(&RAW_TRANSACTION_VERSION) {
+        assert_eq!(encode_transaction(&raw.transaction, raw.chain_id), data, "re-encoding changed the transaction");
+    }
+    raw.transaction.verify_integrity();
+    raw.transaction.weight();
+}
+
+/// Разбирает блок из JSON и считает все, что узел считает по заголовку без состояния
+pub fn block_decode(data: &[u8]) {
+    let Ok(block) = Block::from_json(data) else { return };
+    block.calculate_hash();
+    block.meets_difficulty();
+    block.difficulty_as_work();
+    Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+}
+
+/// Разбирает блок и проверяет его как продолжение небольшой фиксированной цепочки
+pub fn accept_block(data: &[u8]) {
+    let Ok(block) = Block::from_json(data) else { return };
+    let _ = fixed_chain().verify_block(&block);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn fixed_chain_wallets_can_sign() {
+        let mut chain = fixed_chain();
+        let address = chain.create_smart_contract("alice".to_string(), "code".to_string(), 1.0).unwrap();
+        chain.mine_pending_transactions("bob".to_string()).unwrap();
+        assert!(chain.execute_smart_contract(&address, "main", Vec::new()).is_ok());
+    }
+}
@@ -0,0 +1,493 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::features::Feature;
+use crate::params::ChainParams;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+/// Адрес, на котором лежат залоги предложений до подведения итогов голосования
+pub const GOVERNANCE_ESCROW: &str = "BLOCKCHAIN_GOVERNANCE";
+
+/// Правила голосования по предложениям
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GovernanceConfig {
+    /// Минимальный залог предложения
+    pub min_deposit: f64,
+    /// Сколько блоков после блока с предложением принимаются голоса
+    pub voting_period_blocks: u64,
+    /// Через сколько блоков после конца голосования вступает в силу принятое изменение; не меньше 1
+    pub activation_delay_blocks: u64,
+    /// Доля стейка снимка, которая должна проголосовать, считая воздержавшихся
+    pub quorum: f64,
+    /// Доля голосов «за» среди «за» и «против», достаточная для принятия
+    pub threshold: f64,
+}
+
+/// Что делает принятое предложение
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProposalAction {
+    /// Устанавливает параметр цепочки из `GOVERNABLE_PARAMS`
+    SetParam { name: String, value: f64 },
+    /// Ничего не меняет, только фиксирует решение сети
+    Signal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoteChoice {
+    Yes,
+    No,
+    Abstain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProposalStatus {
+    /// Голосование идет или его итог еще не записан в цепочку
+    Voting,
+    /// Принято; изменение параметра ждет высоты вступления в силу
+    Passed,
+    Rejected,
+    /// Изменение параметра применено
+    Enacted,
+}
+
+/// Предложение и поданные по нему голоса
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Proposal {
+    /// Идентификатор транзакции предложения
+    pub id: String,
+    pub proposer: String,
+    pub action: ProposalAction,
+    pub deposit: f64,
+    /// Высота блока с предложением; вес голоса — стейк на кошельке голосующего на ней
+    pub snapshot_height: u64,
+    /// Последняя высота, на которой принимаются голоса
+    pub voting_end: u64,
+    /// Высота, на которой применяется принятое изменение
+    pub activation_height: u64,
+    /// Стейки кошельков на высоте снимка
+    pub stakes: BTreeMap<String, f64>,
+    /// Последний голос каждого валидатора
+    pub votes: BTreeMap<String, VoteChoice>,
+    pub status: ProposalStatus,
+}
+
+/// Голоса по предложению, взвешенные стейком снимка
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Tally {
+    pub yes: f64,
+    pub no: f64,
+    pub abstain: f64,
+    /// Весь стейк снимка
+    pub total: f64,
+}
+
+impl Tally {
+    /// Проголосовала ли достаточная доля стейка
+    pub fn quorum_reached(&self, config: &GovernanceConfig) -> bool {
+        self.total > 0.0 && (self.yes + self.no + self.abstain) / self.total >= config.quorum
+    }
+    
+    /// Принято ли предложение: кворум собран, и доля «за» не меньше порога
+    pub fn passes(&self, config: &GovernanceConfig) -> bool {
+        self.quorum_reached(config) && self.yes + self.no > 0.0 && self.yes / (self.yes + self.no) >= config.threshold
+    }
+}
+
+impl Proposal {
+    pub fn tally(&self) -> Tally {
+        let mut tally = Tally { total: self.stakes.values().sum(), ..Tally::default() };
+        for (voter, choice) in &self.votes {
+            let weight = self.stakes.get(voter).copied().unwrap_or(0.0);
+            match choice {
+                VoteChoice::Yes => tally.yes += weight,
+                VoteChoice::No => tally.no += weight,
+                VoteChoice::Abstain => tally.abstain += weight,
+            }
+        }
+        tally
+    }
+}
+
+/// Управление: правила голосования и предложения по идентификаторам
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Governance {
+    pub config: GovernanceConfig,
+    proposals: BTreeMap<String, Proposal>,
+}
+
+impl Governance {
+    pub fn new(config: GovernanceConfig) -> Self {
+        Governance { config, proposals: BTreeMap::new() }
+    }
+    
+    pub fn proposal(&self, id: &str) -> Option<&Proposal> {
+        self.proposals.get(id)
+    }
+    
+    pub fn proposals(&self) -> impl Iterator<Item = &Proposal> {
+        self.proposals.values()
+    }
+}
+
+/// Изменение параметра, принятое голосованием
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamChange {
+    pub proposal_id: String,
+    /// Высота, с блока которой действует новое значение
+    pub height: u64,
+    pub name: String,
+    pub value: f64,
+}
+
+impl Blockchain {
+    /// Включает голосование по предложениям и создает кошелек эскроу залогов
+    pub fn enable_governance(&mut self, config: GovernanceConfig) -> Result<(), BlockchainError> {
+        self.require_account_mode("Governance")?;
+        if !config.min_deposit.is_finite() || config.min_deposit < 0.0 {
+            return Err(BlockchainError::InvalidTransaction(format!("Minimum deposit {} is invalid", config.min_deposit)));
+        }
+        if config.voting_period_blocks == 0 || config.activation_delay_blocks == 0 {
+            return Err(BlockchainError::InvalidTransaction("Voting period and activation delay must be at least one block".to_string()));
+        }
+        for (name, value) in [("Quorum", config.quorum), ("Threshold", config.threshold)] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(BlockchainError::InvalidTransaction(format!("{} {} is not between 0 and 1", name, value)));
+            }
+        }
+        
+        self.get_or_create_wallet(GOVERNANCE_ESCROW);
+        self.governance = Some(Governance::new(config));
+        Ok(())
+    }
+    
+    /// Ставит в очередь предложение с залогом и возвращает его идентификатор
+    pub fn submit_proposal(&mut self, proposer: &str, action: ProposalAction, deposit: f64) -> Result<String, BlockchainError> {
+        let mut tx = self.new_transaction(proposer.to_string(), GOVERNANCE_ESCROW.to_string(), deposit, TransactionType::Propose { action });
+        self.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Ставит в очередь голос валидатора и возвращает идентификатор транзакции
+    pub fn vote(&mut self, voter: &str, proposal_id: &str, choice: VoteChoice) -> Result<String, BlockchainError> {
+        let transaction_type = TransactionType::Vote { proposal_id: proposal_id.to_string(), choice };
+        let mut tx = self.new_transaction(voter.to_string(), GOVERNANCE_ESCROW.to_string(), 0.0, transaction_type);
+        self.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Предложение по идентификатору; None, если его нет или управление не включено
+    pub fn proposal(&self, id: &str) -> Option<&Proposal> {
+        self.governance.as_ref().and_then(|governance| governance.proposal(id))
+    }
+    
+    /// Проверяет предложения и голоса для блока высоты height; итоги голосования выпускает только протокол
+    pub(crate) fn check_governance_transaction(&self, tx: &Transaction, height: u64) -> Result<(), BlockchainError> {
+        let is_governance = matches!(tx.transaction_type, TransactionType::Propose { .. } | TransactionType::Vote { .. });
+        if matches!(tx.transaction_type, TransactionType::SettleProposal { .. }) || tx.sender == GOVERNANCE_ESCROW {
+            return Err(BlockchainError::InvalidTransaction(format!("{} only settles proposals", GOVERNANCE_ESCROW)));
+        }
+        if !is_governance {
+            if tx.receiver == GOVERNANCE_ESCROW {
+                return Err(BlockchainError::InvalidTransaction(format!("{} only takes proposal deposits and votes", GOVERNANCE_ESCROW)));
+            }
+            return Ok(());
+        }
+        
+        let governance = self.governance.as_ref()
+            .ok_or_else(|| BlockchainError::InvalidTransaction("Governance is not enabled".to_string()))?;
+        if tx.receiver != GOVERNANCE_ESCROW {
+            return Err(BlockchainError::InvalidTransaction(format!("Governance transaction {} must be sent to {}", tx.id, GOVERNANCE_ESCROW)));
+        }
+        
+        match &tx.transaction_type {
+            TransactionType::Propose { action } => {
+                if tx.amount < governance.config.min_deposit {
+                    return Err(BlockchainError::InvalidTransaction(format!(
+                        "Proposal deposit {} is below the minimum {}", tx.amount, governance.config.min_deposit
+                    )));
+                }
+                match action {
+                    ProposalAction::SetParam { name, value } => ChainParams::check_governable(name, *value),
+                    ProposalAction::Signal(text) if text.trim().is_empty() => {
+                        Err(BlockchainError::InvalidTransaction(format!("Signal proposal {} has no text", tx.id)))
+                    },
+                    ProposalAction::Signal(_) => Ok(()),
+                }
+            },
+            TransactionType::Vote { proposal_id, .. } => {
+                let proposal = governance.proposal(proposal_id)
+                    .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Unknown proposal {}", proposal_id)))?;
+                if tx.amount != 0.0 {
+                    return Err(BlockchainError::InvalidTransaction(format!("Vote {} carries an amount", tx.id)));
+                }
+                if height > proposal.voting_end {
+                    return Err(BlockchainError::InvalidTransaction(format!(
+                        "Voting on proposal {} closed at block {}", proposal_id, proposal.voting_end
+                    )));
+                }
+                if !proposal.stakes.get(&tx.sender).is_some_and(|&stake| stake > 0.0) {
+                    return Err(BlockchainError::InvalidTransaction(format!(
+                        "{} had no bonded stake when proposal {} was made", tx.sender, proposal_id
+                    )));
+                }
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+    
+    /// Итоги голосований, закончившихся блоком height - 1: залог возвращается автору, если
+    /// собран кворум, и сжигается иначе
+    pub(crate) fn governance_settlements(&self, height: u64) -> Vec<Transaction> {
+        let Some(governance) = &self.governance else {
+            return Vec::new();
+        };
+        governance.proposals()
+            .filter(|proposal| proposal.status == ProposalStatus::Voting && proposal.voting_end + 1 == height)
+            .map(|proposal| {
+                let tally = proposal.tally();
+                let receiver = if tally.quorum_reached(&governance.config) { proposal.proposer.clone() } else { String::from("BLOCKCHAIN_REWARD") };
+                let transaction_type = TransactionType::SettleProposal { proposal_id: proposal.id.clone(), passed: tally.passes(&governance.config) };
+                let mut tx = self.new_transaction(GOVERNANCE_ESCROW.to_string(), receiver, proposal.deposit, transaction_type);
+                tx.fee = 0.0;
+                tx
+            })
+            .collect()
+    }
+    
+    /// Проверяет, что блок содержит ровно положенные на его высоте итоги голосований
+    pub(crate) fn check_governance_settlements(&self, block: &Block) -> Result<(), BlockchainError> {
+        let expected = self.governance_settlements(block.index);
+        let actual: Vec<&Transaction> = block.transactions.iter().filter(|tx| tx.sender == GOVERNANCE_ESCROW).collect();
+        let matches = expected.len() == actual.len() && expected.iter().zip(&actual).all(|(expected, actual)| {
+            expected.transaction_type == actual.transaction_type && expected.receiver == actual.receiver
+                && (expected.amount - actual.amount).abs() <= 1e-9 && actual.fee == 0.0
+        });
+        if !matches {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong proposal settlements in block # {}", block.index)));
+        }
+        Ok(())
+    }
+    
+    /// Записывает предложения, голоса и итоги блока, применяет изменения, вступающие в силу на его
+    /// высоте, и отменяет оставшиеся в пуле голоса по закрывшимся голосованиям и транзакции
+    /// выключенных возможностей
+    pub(crate) fn apply_governance(&mut self, block: &Block) {
+        let Some(governance) = &mut self.governance else {
+            return;
+        };
+        let height = block.index;
+        let config = governance.config.clone();
+        
+        for tx in &block.transactions {
+            match &tx.transaction_type {
+                TransactionType::Propose { action } => {
+                    let voting_end = height + config.voting_period_blocks;
+                    governance.proposals.insert(tx.id.clone(), Proposal {
+                        id: tx.id.clone(),
+                        proposer: tx.sender.clone(),
+                        action: action.clone(),
+                        deposit: tx.amount,
+                        snapshot_height: height,
+                        voting_end,
+                        activation_height: voting_end + config.activation_delay_blocks,
+                        // Стейк берется из состояния кошельков, по которому проверяются блоки, а не из реестра валидаторов узла
+                        stakes: self.wallets.iter()
+                            .filter(|(_, wallet)| wallet.staking_balance > 0.0)
+                            .map(|(address, wallet)| (address.clone(), wallet.staking_balance))
+                            .collect(),
+                        votes: BTreeMap::new(),
+                        status: ProposalStatus::Voting,
+                    });
+                },
+                TransactionType::Vote { proposal_id, choice } => {
+                    if let Some(proposal) = governance.proposals.get_mut(proposal_id) {
+                        proposal.votes.insert(tx.sender.clone(), *choice);
+                    }
+                },
+                TransactionType::SettleProposal { proposal_id, passed } => {
+                    if let Some(proposal) = governance.proposals.get_mut(proposal_id) {
+                        proposal.status = if *passed { ProposalStatus::Passed } else { ProposalStatus::Rejected };
+                    }
+                },
+                _ => {},
+            }
+        }
+        
+        for proposal in governance.proposals.values_mut() {
+            if proposal.status != ProposalStatus::Passed || proposal.activation_height != height {
+                continue;
+            }
+            if let ProposalAction::SetParam { name, value } = &proposal.action {
+                self.params.set_governable(name, *value);
+                proposal.status = ProposalStatus::Enacted;
+            }
+        }
+        
+        let closed: Vec<String> = self.mempool.lock().transactions.iter()
+            .filter(|tx| match &tx.transaction_type {
+                TransactionType::Vote { proposal_id, .. } => governance.proposal(proposal_id).is_some_and(|proposal| proposal.voting_end <= height),
+                transaction_type => Feature::of(transaction_type).is_some_and(|feature| self.params.disabled_features.contains(&feature)),
+            })
+            .map(|tx| tx.id.clone())
+            .collect();
+        for tx_id in closed {
+            let _ = self.cancel_pending_transaction(&tx_id);
+        }
+    }
+    
+    /// Изменения параметров, принятые голосованием, восстановленные только по цепочке: по
+    /// транзакциям предложений и записанным итогам голосований, без состояния узла
+    pub fn param_changes(&self) -> Vec<ParamChange> {
+        let Some(config) = self.governance.as_ref().map(|governance| &governance.config) else {
+            return Vec::new();
+        };
+        
+        let mut proposed = BTreeMap::new();
+        let mut changes = Vec::new();
+        for block in &self.chain {
+            for tx in &block.transactions {
+                match &tx.transaction_type {
+                    TransactionType::Propose { action: ProposalAction::SetParam { name, value } } => {
+                        let height = block.index + config.voting_period_blocks + config.activation_delay_blocks;
+                        proposed.insert(tx.id.as_str(), (height, name, *value));
+                    },
+                    TransactionType::SettleProposal { proposal_id, passed: true } => {
+                        if let Some(&(height, name, value)) = proposed.get(proposal_id.as_str()) {
+                            changes.push(ParamChange { proposal_id: proposal_id.clone(), height, name: name.clone(), value });
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+        
+        let tip = self.get_latest_block().index;
+        changes.retain(|change| change.height <= tip);
+        changes.sort_by_key(|change| change.height);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    /// Цепочка с управлением и тремя валидаторами со стейками 60, 30 и 10
+    fn governed_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.enable_governance(GovernanceConfig {
+            min_deposit: 10.0,
+            voting_period_blocks: 2,
+            activation_delay_blocks: 2,
+            quorum: 0.5,
+            threshold: 0.5,
+        }).unwrap();
+        for (name, stake) in [("alice", 60.0), ("bob", 30.0), ("carol", 10.0)] {
+            chain.create_wallet(name.to_string()).unwrap();
+            chain.mine_pending_transactions(name.to_string()).unwrap();
+            chain.add_validator(name.to_string(), stake).unwrap();
+        }
+        chain
+    }
+    
+    /// Предлагает новое время блока и майнит блок с предложением
+    fn propose_block_time(chain: &mut Blockchain, value: f64) -> String {
+        let action = ProposalAction::SetParam { name: "target_block_time".to_string(), value };
+        let proposal_id = chain.submit_proposal("carol", action, 10.0).unwrap();
+        chain.mine_pending_transactions("carol".to_string()).unwrap();
+        proposal_id
+    }
+    
+    fn mine_to(chain: &mut Blockchain, height: u64) {
+        while chain.get_latest_block().index < height {
+            chain.mine_pending_transactions("carol".to_string()).unwrap();
+        }
+    }
+    
+    #[test]
+    fn passed_proposal_changes_block_time_at_activation() {
+        let mut chain = governed_chain();
+        let initial = chain.params.target_block_time;
+        let proposal_id = propose_block_time(&mut chain, 30.0);
+        chain.vote("alice", &proposal_id, VoteChoice::Yes).unwrap();
+        chain.vote("bob", &proposal_id, VoteChoice::No).unwrap();
+        chain.mine_pending_transactions("carol".to_string()).unwrap();
+        
+        let activation_height = chain.proposal(&proposal_id).unwrap().activation_height;
+        mine_to(&mut chain, activation_height - 1);
+        assert_eq!(chain.proposal(&proposal_id).unwrap().status, ProposalStatus::Passed);
+        assert_eq!(chain.params.target_block_time, initial);
+        
+        mine_to(&mut chain, activation_height);
+        assert_eq!(chain.proposal(&proposal_id).unwrap().status, ProposalStatus::Enacted);
+        assert_eq!(chain.params.target_block_time, 30.0);
+        let changes = chain.param_changes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].height, changes[0].name.as_str(), changes[0].value), (activation_height, "target_block_time", 30.0));
+    }
+    
+    #[test]
+    fn rejected_proposal_changes_nothing() {
+        let mut chain = governed_chain();
+        let initial = chain.params.target_block_time;
+        let proposal_id = propose_block_time(&mut chain, 30.0);
+        chain.vote("alice", &proposal_id, VoteChoice::No).unwrap();
+        chain.vote("bob", &proposal_id, VoteChoice::Yes).unwrap();
+        chain.vote("carol", &proposal_id, VoteChoice::Yes).unwrap();
+        chain.mine_pending_transactions("carol".to_string()).unwrap();
+        
+        let activation_height = chain.proposal(&proposal_id).unwrap().activation_height;
+        mine_to(&mut chain, activation_height + 1);
+        assert_eq!(chain.proposal(&proposal_id).unwrap().status, ProposalStatus::Rejected);
+        assert_eq!(chain.params.target_block_time, initial);
+        assert!(chain.param_changes().is_empty());
+    }
+    
+    #[test]
+    fn votes_after_the_window_are_rejected() {
+        let mut chain = governed_chain();
+        let proposal_id = propose_block_time(&mut chain, 30.0);
+        let voting_end = chain.proposal(&proposal_id).unwrap().voting_end;
+        mine_to(&mut chain, voting_end);
+        
+        let result = chain.vote("alice", &proposal_id, VoteChoice::Yes);
+        assert!(matches!(result, Err(BlockchainError::InvalidTransaction(_))), "{:?}", result);
+        assert!(chain.mempool().is_empty());
+        assert!(chain.proposal(&proposal_id).unwrap().votes.is_empty());
+    }
+    
+    #[test]
+    fn second_vote_replaces_the_first() {
+        let mut chain = governed_chain();
+        let proposal_id = propose_block_time(&mut chain, 30.0);
+        chain.vote("alice", &proposal_id, VoteChoice::Yes).unwrap();
+        chain.mine_pending_transactions("carol".to_string()).unwrap();
+        chain.vote("alice", &proposal_id, VoteChoice::No).unwrap();
+        chain.mine_pending_transactions("carol".to_string()).unwrap();
+        
+        let proposal = chain.proposal(&proposal_id).unwrap();
+        assert_eq!(proposal.votes.len(), 1);
+        assert_eq!(proposal.votes["alice"], VoteChoice::No);
+        assert_eq!(proposal.tally(), Tally { yes: 0.0, no: 60.0, abstain: 0.0, total: 100.0 });
+    }
+    
+    #[test]
+    fn stakes_come_from_wallets_at_the_proposal_block() {
+        let mut chain = governed_chain();
+        // Стейк, снятый после блока с предложением, сохраняет вес голоса
+        let proposal_id = propose_block_time(&mut chain, 30.0);
+        chain.remove_validator("bob").unwrap();
+        let stakes = &chain.proposal(&proposal_id).unwrap().stakes;
+        assert_eq!(stakes.iter().map(|(address, &stake)| (address.as_str(), stake)).collect::<Vec<_>>(), [("alice", 60.0), ("bob", 30.0), ("carol", 10.0)]);
+        chain.vote("bob", &proposal_id, VoteChoice::Yes).unwrap();
+    }
+}
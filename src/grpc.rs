@@ -0,0 +1,907 @@
+use crate::block::Block;
+use crate::errors::BlockchainError;
+use crate::events::ChainEvent;
+use crate::governance::{ProposalAction, VoteChoice};
+use crate::hashing::HashKind;
+use crate::receipt::{ReceiptStatus, TransactionReceipt};
+use crate::shared::SharedBlockchain;
+use crate::transaction::{FeePayer, Transaction, TransactionType};
+use crate::utxo::{OutPoint, TxInput, TxOutput};
+use std::convert::Infallible;
+use std::future::Future;
+use std::task::{Context, Poll};
+use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
+use tonic::codegen::{http, Body, BoxFuture, Service, StdError};
+use tonic::{Code, Request, Response, Status};
+use tonic_prost::ProstCodec;
+
+/// Сообщения из `proto/hellochain.proto`; при изменении схемы правятся вместе с ней
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GetBlockRequest {
+        #[prost(oneof = "get_block_request::Selector", tags = "1, 2")]
+        pub selector: Option<get_block_request::Selector>,
+    }
+    
+    pub mod get_block_request {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Selector {
+            #[prost(uint64, tag = "1")]
+            Height(u64),
+            #[prost(string, tag = "2")]
+            Hash(String),
+        }
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Block {
+        #[prost(uint64, tag = "1")]
+        pub index: u64,
+        #[prost(int64, tag = "2")]
+        pub timestamp: i64,
+        #[prost(message, repeated, tag = "3")]
+        pub transactions: Vec<Transaction>,
+        #[prost(string, tag = "4")]
+        pub merkle_root: String,
+        #[prost(string, tag = "5")]
+        pub previous_hash: String,
+        #[prost(string, tag = "6")]
+        pub hash: String,
+        #[prost(uint64, tag = "7")]
+        pub nonce: u64,
+        #[prost(uint64, tag = "8")]
+        pub difficulty: u64,
+        #[prost(string, optional, tag = "9")]
+        pub validator: Option<String>,
+        #[prost(uint32, tag = "10")]
+        pub version: u32,
+        #[prost(uint32, tag = "11")]
+        pub bits: u32,
+        #[prost(double, optional, tag = "12")]
+        pub base_fee: Option<f64>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Transaction {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub sender: String,
+        #[prost(string, tag = "3")]
+        pub receiver: String,
+        #[prost(double, tag = "4")]
+        pub amount: f64,
+        #[prost(double, tag = "5")]
+        pub fee: f64,
+        #[prost(int64, tag = "6")]
+        pub timestamp: i64,
+        #[prost(string, tag = "7")]
+        pub signature: String,
+        #[prost(oneof = "transaction::Kind", tags = "8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 30, 34")]
+        pub kind: Option<transaction::Kind>,
+        #[prost(message, optional, tag = "26")]
+        pub fee_payer: Option<FeePayer>,
+        #[prost(uint64, optional, tag = "27")]
+        pub valid_until_height: Option<u64>,
+        #[prost(message, repeated, tag = "28")]
+        pub inputs: Vec<TxInput>,
+        #[prost(message, repeated, tag = "29")]
+        pub outputs: Vec<TxOutput>,
+        #[prost(uint64, optional, tag = "31")]
+        pub extra_nonce: Option<u64>,
+        #[prost(string, optional, tag = "32")]
+        pub public_key: Option<String>,
+        #[prost(uint64, optional, tag = "33")]
+        pub nonce: Option<u64>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FeePayer {
+        #[prost(string, tag = "1")]
+        pub address: String,
+        #[prost(double, tag = "2")]
+        pub max_fee: f64,
+        #[prost(string, tag = "3")]
+        pub signature: String,
+        #[prost(string, optional, tag = "4")]
+        pub public_key: Option<String>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TxInput {
+        #[prost(string, tag = "1")]
+        pub txid: String,
+        #[prost(uint32, tag = "2")]
+        pub index: u32,
+        #[prost(string, tag = "3")]
+        pub signature: String,
+        #[prost(string, optional, tag = "4")]
+        pub public_key: Option<String>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TxOutput {
+        #[prost(string, tag = "1")]
+        pub address: String,
+        #[prost(double, tag = "2")]
+        pub amount: f64,
+    }
+    
+    pub mod transaction {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Kind {
+            #[prost(message, tag = "8")]
+            Transfer(super::Transfer),
+            #[prost(message, tag = "9")]
+            SmartContract(super::SmartContract),
+            #[prost(message, tag = "10")]
+            Data(super::Data),
+            #[prost(message, tag = "11")]
+            DataChunk(super::DataChunk),
+            #[prost(message, tag = "12")]
+            DataManifest(super::DataManifest),
+            #[prost(message, tag = "13")]
+            BridgeMint(super::BridgeMint),
+            #[prost(message, tag = "14")]
+            BridgeRelease(super::BridgeRelease),
+            #[prost(message, tag = "15")]
+            TreasurySpend(super::TreasurySpend),
+            #[prost(message, tag = "16")]
+            VestingGrant(super::VestingGrant),
+            #[prost(message, tag = "17")]
+            FreezeAccount(super::FreezeAccount),
+            #[prost(message, tag = "18")]
+            UnfreezeAccount(super::UnfreezeAccount),
+            #[prost(message, tag = "19")]
+            IssueAsset(super::IssueAsset),
+            #[prost(message, tag = "20")]
+            AssetTransfer(super::AssetTransfer),
+            #[prost(message, tag = "21")]
+            ClaimRewards(super::ClaimRewards),
+            #[prost(message, tag = "22")]
+            Propose(super::Propose),
+            #[prost(message, tag = "23")]
+            Vote(super::Vote),
+            #[prost(message, tag = "24")]
+            SettleProposal(super::SettleProposal),
+            #[prost(message, tag = "25")]
+            CloseWallet(super::CloseWallet),
+            #[prost(message, tag = "30")]
+            Mint(super::Mint),
+            #[prost(message, tag = "34")]
+            RegisterKey(super::RegisterKey),
+        }
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Transfer {}
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SmartContract {
+        #[prost(string, tag = "1")]
+        pub code: String,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Data {
+        #[prost(bytes = "vec", tag = "1")]
+        pub payload: Vec<u8>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DataChunk {
+        #[prost(string, tag = "1")]
+        pub data_id: String,
+        #[prost(uint32, tag = "2")]
+        pub index: u32,
+        #[prost(bytes = "vec", tag = "3")]
+        pub payload: Vec<u8>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DataManifest {
+        #[prost(string, tag = "1")]
+        pub data_id: String,
+        #[prost(uint32, tag = "2")]
+        pub chunk_count: u32,
+        #[prost(uint64, tag = "3")]
+        pub total_len: u64,
+        #[prost(string, tag = "4")]
+        pub digest: String,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BridgeMint {
+        #[prost(string, tag = "1")]
+        pub source_tx_id: String,
+        #[prost(string, tag = "2")]
+        pub relayer: String,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BridgeRelease {
+        #[prost(string, tag = "1")]
+        pub source_tx_id: String,
+        #[prost(string, tag = "2")]
+        pub relayer: String,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TreasurySpend {
+        #[prost(string, repeated, tag = "1")]
+        pub approvals: Vec<String>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct VestingGrant {
+        #[prost(uint64, tag = "1")]
+        pub start_height: u64,
+        #[prost(uint64, tag = "2")]
+        pub cliff_blocks: u64,
+        #[prost(uint64, tag = "3")]
+        pub duration_blocks: u64,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FreezeAccount {
+        #[prost(string, tag = "1")]
+        pub reason: String,
+        #[prost(string, repeated, tag = "2")]
+        pub approvals: Vec<String>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct UnfreezeAccount {
+        #[prost(string, repeated, tag = "1")]
+        pub approvals: Vec<String>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct IssueAsset {
+        #[prost(string, tag = "1")]
+        pub ticker: String,
+        #[prost(uint64, tag = "2")]
+        pub total_supply: u64,
+        #[prost(uint32, tag = "3")]
+        pub decimals: u32,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AssetTransfer {
+        #[prost(string, tag = "1")]
+        pub asset_id: String,
+        #[prost(uint64, tag = "2")]
+        pub amount: u64,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ClaimRewards {}
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CloseWallet {}
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Mint {}
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RegisterKey {}
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Propose {
+        #[prost(oneof = "propose::Action", tags = "1, 2")]
+        pub action: Option<propose::Action>,
+    }
+    
+    pub mod propose {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Action {
+            #[prost(message, tag = "1")]
+            SetParam(super::SetParam),
+            #[prost(string, tag = "2")]
+            Signal(String),
+        }
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SetParam {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(double, tag = "2")]
+        pub value: f64,
+    }
+    
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum VoteChoice {
+        Unspecified = 0,
+        Yes = 1,
+        No = 2,
+        Abstain = 3,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Vote {
+        #[prost(string, tag = "1")]
+        pub proposal_id: String,
+        #[prost(enumeration = "VoteChoice", tag = "2")]
+        pub choice: i32,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SettleProposal {
+        #[prost(string, tag = "1")]
+        pub proposal_id: String,
+        #[prost(bool, tag = "2")]
+        pub passed: bool,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SubmitTransactionResponse {
+        #[prost(string, tag = "1")]
+        pub id: String,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GetBalanceRequest {
+        #[prost(string, tag = "1")]
+        pub address: String,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Balance {
+        #[prost(string, tag = "1")]
+        pub address: String,
+        #[prost(double, tag = "2")]
+        pub balance: f64,
+        #[prost(double, tag = "3")]
+        pub staking_balance: f64,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GetReceiptRequest {
+        #[prost(string, tag = "1")]
+        pub id: String,
+    }
+    
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum ReceiptStatus {
+        Unspecified = 0,
+        Pending = 1,
+        Confirmed = 2,
+        Expired = 3,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Receipt {
+        #[prost(string, tag = "1")]
+        pub tx_id: String,
+        #[prost(enumeration = "ReceiptStatus", tag = "2")]
+        pub status: i32,
+        #[prost(uint64, optional, tag = "3")]
+        pub block_index: Option<u64>,
+        #[prost(string, optional, tag = "4")]
+        pub block_hash: Option<String>,
+        #[prost(string, tag = "5")]
+        pub sender: String,
+        #[prost(string, tag = "6")]
+        pub receiver: String,
+        #[prost(double, tag = "7")]
+        pub amount: f64,
+        #[prost(double, tag = "8")]
+        pub fee: f64,
+        #[prost(double, tag = "9")]
+        pub fee_discount: f64,
+        #[prost(string, optional, tag = "10")]
+        pub fee_payer: Option<String>,
+    }
+    
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SubscribeBlocksRequest {}
+}
+
+/// Полное имя сервиса из proto-файла
+pub const SERVICE_NAME: &str = "hellochain.v1.Chain";
+
+/// Размер очереди блоков для одного подписчика `SubscribeBlocks`
+const SUBSCRIBER_QUEUE_CAPACITY: usize = 64;
+
+impl From<&Transaction> for proto::Transaction {
+    fn from(tx: &Transaction) -> Self {
+        use proto::transaction::Kind;
+        
+        let kind = match &tx.transaction_type {
+            TransactionType::Transfer => Kind::Transfer(proto::Transfer {}),
+            TransactionType::SmartContract(code) => Kind::SmartContract(proto::SmartContract { code: code.clone() }),
+            TransactionType::Data(payload) => Kind::Data(proto::Data { payload: payload.clone() }),
+            TransactionType::DataChunk { data_id, index, payload } => Kind::DataChunk(proto::DataChunk {
+                data_id: data_id.clone(),
+                index: *index,
+                payload: payload.clone(),
+            }),
+            TransactionType::DataManifest { data_id, chunk_count, total_len, digest } => Kind::DataManifest(proto::DataManifest {
+                data_id: data_id.clone(),
+                chunk_count: *chunk_count,
+                total_len: *total_len,
+                digest: digest.clone(),
+            }),
+            TransactionType::BridgeMint { source_tx_id, relayer } => Kind::BridgeMint(proto::BridgeMint {
+                source_tx_id: source_tx_id.clone(),
+                relayer: relayer.clone(),
+            }),
+            TransactionType::BridgeRelease { source_tx_id, relayer } => Kind::BridgeRelease(proto::BridgeRelease {
+                source_tx_id: source_tx_id.clone(),
+                relayer: relayer.clone(),
+            }),
+            TransactionType::TreasurySpend { approvals } => Kind::TreasurySpend(proto::TreasurySpend { approvals: approvals.clone() }),
+            TransactionType::VestingGrant { start_height, cliff_blocks, duration_blocks } => Kind::VestingGrant(proto::VestingGrant {
+                start_height: *start_height,
+                cliff_blocks: *cliff_blocks,
+                duration_blocks: *duration_blocks,
+            }),
+            TransactionType::FreezeAccount { reason, approvals } => Kind::FreezeAccount(proto::FreezeAccount {
+                reason: reason.clone(),
+                approvals: approvals.clone(),
+            }),
+            TransactionType::UnfreezeAccount { approvals } => Kind::UnfreezeAccount(proto::UnfreezeAccount { approvals: approvals.clone() }),
+            TransactionType::IssueAsset { ticker, total_supply, decimals } => Kind::IssueAsset(proto::IssueAsset {
+                ticker: ticker.clone(),
+                total_supply: *total_supply,
+                decimals: u32::from(*decimals),
+            }),
+            TransactionType::AssetTransfer { asset_id, amount } => Kind::AssetTransfer(proto::AssetTransfer {
+                asset_id: asset_id.clone(),
+                amount: *amount,
+            }),
+            TransactionType::ClaimRewards => Kind::ClaimRewards(proto::ClaimRewards {}),
+            TransactionType::CloseWallet => Kind::CloseWallet(proto::CloseWallet {}),
+            TransactionType::Mint => Kind::Mint(proto::Mint {}),
+            TransactionType::RegisterKey => Kind::RegisterKey(proto::RegisterKey {}),
+            TransactionType::Propose { action } => Kind::Propose(proto::Propose {
+                action: Some(match action {
+                    ProposalAction::SetParam { name, value } => proto::propose::Action::SetParam(proto::SetParam { name: name.clone(), value: *value }),
+                    ProposalAction::Signal(text) => proto::propose::Action::Signal(text.clone()),
+                }),
+            }),
+            TransactionType::Vote { proposal_id, choice } => Kind::Vote(proto::Vote {
+                proposal_id: proposal_id.clone(),
+                choice: match choice {
+                    VoteChoice::Yes => proto::VoteChoice::Yes,
+                    VoteChoice::No => proto::VoteChoice::No,
+                    VoteChoice::Abstain => proto::VoteChoice::Abstain,
+                } as i32,
+            }),
+            TransactionType::SettleProposal { proposal_id, passed } => Kind::SettleProposal(proto::SettleProposal {
+                proposal_id: proposal_id.clone(),
+                passed: *passed,
+            }),
+        };
+        
+        proto::Transaction {
+            id: tx.id.clone(),
+            sender: tx.sender.clone(),
+            receiver: tx.receiver.clone(),
+            amount: tx.amount,
+            fee: tx.fee,
+            timestamp: tx.timestamp,
+            signature: tx.signature.clone(),
+            kind: Some(kind),
+            fee_payer: tx.fee_payer.as_ref().map(|payer| proto::FeePayer {
+                address: payer.address.clone(),
+                max_fee: payer.max_fee,
+                signature: payer.signature.clone(),
+                public_key: payer.public_key.clone(),
+            }),
+            valid_until_height: tx.valid_until_height,
+            inputs: tx.inputs.iter().map(|input| proto::TxInput {
+                txid: input.outpoint.txid.clone(),
+                index: input.outpoint.index,
+                signature: input.signature.clone(),
+                public_key: input.public_key.clone(),
+            }).collect(),
+            outputs: tx.outputs.iter().map(|output| proto::TxOutput { address: output.address.clone(), amount: output.amount }).collect(),
+            extra_nonce: tx.extra_nonce,
+            public_key: tx.public_key.clone(),
+            nonce: tx.nonce,
+        }
+    }
+}
+
+impl TryFrom<proto::Transaction> for Transaction {
+    type Error = Status;
+    
+    fn try_from(tx: proto::Transaction) -> Result<Self, Status> {
+        use proto::transaction::Kind;
+        
+        let transaction_type = match tx.kind {
+            Some(Kind::Transfer(_)) => TransactionType::Transfer,
+            Some(Kind::SmartContract(contract)) => TransactionType::SmartContract(contract.code),
+            Some(Kind::Data(data)) => TransactionType::Data(data.payload),
+            Some(Kind::DataChunk(chunk)) => TransactionType::DataChunk {
+                data_id: chunk.data_id,
+                index: chunk.index,
+                payload: chunk.payload,
+            },
+            Some(Kind::DataManifest(manifest)) => TransactionType::DataManifest {
+                data_id: manifest.data_id,
+                chunk_count: manifest.chunk_count,
+                total_len: manifest.total_len,
+                digest: manifest.digest,
+            },
+            Some(Kind::BridgeMint(mint)) => TransactionType::BridgeMint {
+                source_tx_id: mint.source_tx_id,
+                relayer: mint.relayer,
+            },
+            Some(Kind::BridgeRelease(release)) => TransactionType::BridgeRelease {
+                source_tx_id: release.source_tx_id,
+                relayer: release.relayer,
+            },
+            Some(Kind::TreasurySpend(spend)) => TransactionType::TreasurySpend { approvals: spend.approvals },
+            Some(Kind::VestingGrant(grant)) => TransactionType::VestingGrant {
+                start_height: grant.start_height,
+                cliff_blocks: grant.cliff_blocks,
+                duration_blocks: grant.duration_blocks,
+            },
+            Some(Kind::FreezeAccount(freeze)) => TransactionType::FreezeAccount { reason: freeze.reason, approvals: freeze.approvals },
+            Some(Kind::UnfreezeAccount(unfreeze)) => TransactionType::UnfreezeAccount { approvals: unfreeze.approvals },
+            Some(Kind::IssueAsset(issue)) => TransactionType::IssueAsset {
+                ticker: issue.ticker,
+                total_supply: issue.total_supply,
+                decimals: u8::try_from(issue.decimals).map_err(|_| Status::invalid_argument("Asset decimals are out of range"))?,
+            },
+            Some(Kind::AssetTransfer(transfer)) => TransactionType::AssetTransfer { asset_id: transfer.asset_id, amount: transfer.amount },
+            Some(Kind::ClaimRewards(_)) => TransactionType::ClaimRewards,
+            Some(Kind::CloseWallet(_)) => TransactionType::CloseWallet,
+            Some(Kind::Mint(_)) => TransactionType::Mint,
+            Some(Kind::RegisterKey(_)) => TransactionType::RegisterKey,
+            Some(Kind::Propose(propose)) => TransactionType::Propose {
+                action: match propose.action {
+                    Some(proto::propose::Action::SetParam(param)) => ProposalAction::SetParam { name: param.name, value: param.value },
+                    Some(proto::propose::Action::Signal(text)) => ProposalAction::Signal(text),
+                    None => return Err(Status::invalid_argument("Proposal action is missing")),
+                },
+            },
+            Some(Kind::Vote(vote)) => TransactionType::Vote {
+                choice: match proto::VoteChoice::try_from(vote.choice) {
+                    Ok(proto::VoteChoice::Yes) => VoteChoice::Yes,
+                    Ok(proto::VoteChoice::No) => VoteChoice::No,
+                    Ok(proto::VoteChoice::Abstain) => VoteChoice::Abstain,
+                    Ok(proto::VoteChoice::Unspecified) | Err(_) => return Err(Status::invalid_argument("Vote choice is missing")),
+                },
+                proposal_id: vote.proposal_id,
+            },
+            Some(Kind::SettleProposal(settle)) => TransactionType::SettleProposal { proposal_id: settle.proposal_id, passed: settle.passed },
+            None => return Err(Status::invalid_argument("Transaction kind is missing")),
+        };
+        
+        if tx.id.is_empty() {
+            return Err(Status::invalid_argument("Transaction id is missing"));
+        }
+        
+        Ok(Transaction {
+            id: tx.id,
+            transaction_type,
+            sender: tx.sender,
+            receiver: tx.receiver,
+            amount: tx.amount,
+            fee: tx.fee,
+            timestamp: tx.timestamp,
+            signature: tx.signature,
+            fee_payer: tx.fee_payer.map(|payer| FeePayer {
+                address: payer.address,
+                max_fee: payer.max_fee,
+                signature: payer.signature,
+                public_key: payer.public_key,
+            }),
+            valid_until_height: tx.valid_until_height,
+            inputs: tx.inputs.into_iter().map(|input| TxInput {
+                outpoint: OutPoint { txid: input.txid, index: input.index },
+                signature: input.signature,
+                public_key: input.public_key,
+            }).collect(),
+            outputs: tx.outputs.into_iter().map(|output| TxOutput { address: output.address, amount: output.amount }).collect(),
+            extra_nonce: tx.extra_nonce,
+            public_key: tx.public_key,
+            nonce: tx.nonce,
+            hash_algorithm: HashKind::default(),
+        })
+    }
+}
+
+impl From<&Block> for proto::Block {
+    fn from(block: &Block) -> Self {
+        proto::Block {
+            index: block.index,
+            timestamp: block.timestamp,
+            transactions: block.transactions.iter().map(proto::Transaction::from).collect(),
+            merkle_root: block.merkle_root.clone(),
+            previous_hash: block.previous_hash.clone(),
+            hash: block.hash.clone(),
+            nonce: block.nonce,
+            difficulty: block.difficulty as u64,
+            validator: block.validator.clone(),
+            version: block.version,
+            bits: block.bits,
+            base_fee: block.base_fee,
+        }
+    }
+}
+
+impl From<TransactionReceipt> for proto::Receipt {
+    fn from(receipt: TransactionReceipt) -> Self {
+        let status = match receipt.status {
+            ReceiptStatus::Pending => proto::ReceiptStatus::Pending,
+            ReceiptStatus::Confirmed => proto::ReceiptStatus::Confirmed,
+            ReceiptStatus::Expired => proto::ReceiptStatus::Expired,
+        };
+        
+        proto::Receipt {
+            tx_id: receipt.tx_id,
+            status: status as i32,
+            block_index: receipt.block_index,
+            block_hash: receipt.block_hash,
+            sender: receipt.sender,
+            receiver: receipt.receiver,
+            amount: receipt.amount,
+            fee: receipt.fee,
+            fee_discount: receipt.fee_discount,
+            fee_payer: receipt.fee_payer,
+        }
+    }
+}
+
+/// Переводит ошибку блокчейна в канонический статус gRPC
+pub fn status_from_error(error: BlockchainError) -> Status {
+    let code = match error {
+        BlockchainError::InvalidTransaction(_) | BlockchainError::InvalidBlock(_) | BlockchainError::MalformedTransaction { .. }
+        | BlockchainError::InvalidSignature { .. } | BlockchainError::FeeTooLow { .. } | BlockchainError::WrongChainId { .. }
+        | BlockchainError::WrongNetwork { .. } | BlockchainError::InvalidConfig(_) => Code::InvalidArgument,
+        BlockchainError::InsufficientBalance { .. } | BlockchainError::ConsensusError(_) | BlockchainError::ValidatorJailed { .. }
+        | BlockchainError::NotScheduledProducer { .. } | BlockchainError::AccountFrozen { .. } | BlockchainError::ContractError { .. }
+        | BlockchainError::WalletClosed { .. } | BlockchainError::WrongLedgerMode { .. }
+        | BlockchainError::HashAlgorithmMismatch { .. } | BlockchainError::InvalidNonce { .. } => Code::FailedPrecondition,
+        BlockchainError::StaleTemplate { .. } => Code::Aborted,
+        BlockchainError::WalletNotFound { .. } | BlockchainError::ValidatorNotFound { .. } | BlockchainError::TransactionNotFound { .. }
+        | BlockchainError::ChainNotFound { .. } => Code::NotFound,
+        BlockchainError::DuplicateTransaction { .. } | BlockchainError::DuplicateChain { .. }
+        | BlockchainError::DuplicateWallet { .. } => Code::AlreadyExists,
+        BlockchainError::MempoolFull { .. } | BlockchainError::RateLimited { .. } => Code::ResourceExhausted,
+        BlockchainError::FeatureDisabled { .. } => Code::Unimplemented,
+        BlockchainError::FaucetDepleted { .. } => Code::Unavailable,
+        BlockchainError::Storage(_) => Code::Internal,
+    };
+    
+    Status::new(code, error.to_string())
+}
+
+/// Сервис `hellochain.v1.Chain` поверх разделяемого блокчейна
+#[derive(Clone)]
+pub struct ChainServer {
+    chain: SharedBlockchain,
+}
+
+impl ChainServer {
+    pub fn new(chain: SharedBlockchain) -> Self {
+        ChainServer { chain }
+    }
+}
+
+impl tonic::server::NamedService for ChainServer {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<B> Service<http::Request<B>> for ChainServer
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let chain = self.chain.clone();
+        
+        match req.uri().path() {
+            "/hellochain.v1.Chain/GetBlock" => Box::pin(async move {
+                Ok(grpc().unary(Handler(move |request| get_block(chain.clone(), request)), req).await)
+            }),
+            "/hellochain.v1.Chain/SubmitTransaction" => Box::pin(async move {
+                Ok(grpc().unary(Handler(move |request| submit_transaction(chain.clone(), request)), req).await)
+            }),
+            "/hellochain.v1.Chain/GetBalance" => Box::pin(async move {
+                Ok(grpc().unary(Handler(move |request| get_balance(chain.clone(), request)), req).await)
+            }),
+            "/hellochain.v1.Chain/GetReceipt" => Box::pin(async move {
+                Ok(grpc().unary(Handler(move |request| get_receipt(chain.clone(), request)), req).await)
+            }),
+            "/hellochain.v1.Chain/SubscribeBlocks" => Box::pin(async move {
+                Ok(grpc().server_streaming(Handler(move |request| subscribe_blocks(chain.clone(), request)), req).await)
+            }),
+            _ => Box::pin(async move {
+                Ok(Status::unimplemented("Unknown method").into_http())
+            }),
+        }
+    }
+}
+
+fn grpc<T, U>() -> tonic::server::Grpc<ProstCodec<T, U>>
+where
+    T: prost::Message + Send + 'static,
+    U: prost::Message + Default + Send + 'static,
+{
+    tonic::server::Grpc::new(ProstCodec::default())
+}
+
+/// Обертка, превращающая функцию-обработчик в сервис tonic
+struct Handler<F>(F);
+
+impl<F, Fut, M1, M2> Service<Request<M1>> for Handler<F>
+where
+    F: FnMut(Request<M1>) -> Fut,
+    Fut: Future<Output = Result<Response<M2>, Status>>,
+{
+    type Response = Response<M2>;
+    type Error = Status;
+    type Future = Fut;
+    
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+    
+    fn call(&mut self, request: Request<M1>) -> Fut {
+        (self.0)(request)
+    }
+}
+
+async fn get_block(chain: SharedBlockchain, request: Request<proto::GetBlockRequest>) -> Result<Response<proto::Block>, Status> {
+    use proto::get_block_request::Selector;
+    
+    let chain = chain.read();
+    let block = match request.into_inner().selector {
+        Some(Selector::Height(height)) => chain.get_block_by_index(height)
+            .ok_or_else(|| Status::not_found(format!("Block {} not found", height)))?,
+        Some(Selector::Hash(hash)) => chain.get_block_by_hash(&hash)
+            .ok_or_else(|| Status::not_found(format!("Block {} not found", hash)))?,
+        None => return Err(Status::invalid_argument("Expected block height or hash")),
+    };
+    
+    Ok(Response::new(proto::Block::from(block)))
+}
+
+async fn submit_transaction(chain: SharedBlockchain, request: Request<proto::Transaction>) -> Result<Response<proto::SubmitTransactionResponse>, Status> {
+    let mut transaction = Transaction::try_from(request.into_inner())?;
+    // В сообщении нет алгоритма хеширования: транзакция отправлена в эту цепочку и посчитана ее алгоритмом
+    transaction.hash_algorithm = chain.read().hash_algorithm;
+    let id = transaction.id.clone();
+    chain.add_transaction(transaction).map_err(status_from_error)?;
+    Ok(Response::new(proto::SubmitTransactionResponse { id }))
+}
+
+async fn get_balance(chain: SharedBlockchain, request: Request<proto::GetBalanceRequest>) -> Result<Response<proto::Balance>, Status> {
+    let address = request.into_inner().address;
+    let chain = chain.read();
+    let wallet = chain.get_wallet_info(&address)
+        .ok_or_else(|| Status::not_found(format!("Wallet {} not found", address)))?;
+    
+    Ok(Response::new(proto::Balance {
+        address: wallet.address.clone(),
+        balance: wallet.balance,
+        staking_balance: wallet.staking_balance,
+    }))
+}
+
+async fn get_receipt(chain: SharedBlockchain, request: Request<proto::GetReceiptRequest>) -> Result<Response<proto::Receipt>, Status> {
+    let id = request.into_inner().id;
+    let receipt = chain.read().get_transaction_receipt(&id)
+        .ok_or_else(|| Status::not_found(format!("Transaction {} not found", id)))?;
+    Ok(Response::new(proto::Receipt::from(receipt)))
+}
+
+async fn subscribe_blocks(
+    chain: SharedBlockchain,
+    _request: Request<proto::SubscribeBlocksRequest>,
+) -> Result<Response<ReceiverStream<Result<proto::Block, Status>>>, Status> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(SUBSCRIBER_QUEUE_CAPACITY);
+    
+    // Хук снимается сам, когда клиент отключился или не успевает читать
+    chain.write().add_event_hook(Box::new(move |event| match event {
+        ChainEvent::BlockAdded { block } => sender.try_send(Ok(proto::Block::from(block))).is_ok(),
+        _ => !sender.is_closed(),
+    }));
+    
+    Ok(Response::new(ReceiverStream::new(receiver)))
+}
+
+/// Запускает gRPC-сервер на уже открытом сокете
+pub async fn serve(listener: tokio::net::TcpListener, chain: SharedBlockchain) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(ChainServer::new(chain))
+        .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ChainFixture;
+    use tonic::transport::Channel;
+    
+    /// Запускает сервис в рантайме теста и подключает к нему канал
+    async fn connect(chain: SharedBlockchain) -> Channel {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, chain));
+        Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap()
+    }
+    
+    async fn unary<Req, Resp>(channel: &Channel, method: &'static str, request: Req) -> Result<Resp, Status>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut client = tonic::client::Grpc::new(channel.clone());
+        client.ready().await.map_err(|e| Status::unavailable(e.to_string()))?;
+        let path = http::uri::PathAndQuery::from_static(method);
+        client.unary(Request::new(request), path, ProstCodec::default()).await.map(Response::into_inner)
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn subscribers_receive_mined_blocks_in_order() {
+        let shared = SharedBlockchain::new(ChainFixture::funded().build().unwrap().chain);
+        let channel = connect(shared.clone()).await;
+        let mut client = tonic::client::Grpc::new(channel);
+        client.ready().await.unwrap();
+        let path = http::uri::PathAndQuery::from_static("/hellochain.v1.Chain/SubscribeBlocks");
+        let mut stream = client.server_streaming(Request::new(proto::SubscribeBlocksRequest {}), path, ProstCodec::<_, proto::Block>::default())
+            .await
+            .unwrap()
+            .into_inner();
+        
+        let mut mined = Vec::new();
+        for _ in 0..2 {
+            let mut chain = shared.write();
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+            mined.push(proto::Block::from(chain.get_latest_block()));
+        }
+        
+        for expected in mined {
+            assert_eq!(stream.message().await.unwrap(), Some(expected));
+        }
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failures_map_to_canonical_codes() {
+        let chain = ChainFixture::funded().build().unwrap().chain;
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut overspend = Transaction::new("alice".to_string(), "bob".to_string(), 1000.0, TransactionType::Transfer);
+        overspend.set_nonce(chain.get_account_nonce("alice"));
+        overspend.sign(&keypair);
+        let genesis = chain.chain[0].hash.clone();
+        let channel = connect(SharedBlockchain::new(chain)).await;
+        
+        let by_hash = |hash: &str| proto::GetBlockRequest { selector: Some(proto::get_block_request::Selector::Hash(hash.to_string())) };
+        let block: proto::Block = unary(&channel, "/hellochain.v1.Chain/GetBlock", by_hash(&genesis)).await.unwrap();
+        assert_eq!(block.hash, genesis);
+        let missing = unary::<_, proto::Block>(&channel, "/hellochain.v1.Chain/GetBlock", by_hash("00")).await.unwrap_err();
+        assert_eq!(missing.code(), Code::NotFound);
+        
+        let mut malformed = proto::Transaction::from(&overspend);
+        malformed.kind = None;
+        let error = unary::<_, proto::SubmitTransactionResponse>(&channel, "/hellochain.v1.Chain/SubmitTransaction", malformed).await.unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+        
+        let error = unary::<_, proto::SubmitTransactionResponse>(&channel, "/hellochain.v1.Chain/SubmitTransaction", proto::Transaction::from(&overspend)).await.unwrap_err();
+        assert_eq!(error.code(), Code::FailedPrecondition);
+        
+        let request = proto::GetReceiptRequest { id: overspend.id.clone() };
+        let error = unary::<_, proto::Receipt>(&channel, "/hellochain.v1.Chain/GetReceipt", request).await.unwrap_err();
+        assert_eq!(error.code(), Code::NotFound);
+        let balance: proto::Balance = unary(&channel, "/hellochain.v1.Chain/GetBalance", proto::GetBalanceRequest { address: "alice".to_string() }).await.unwrap();
+        assert_eq!(balance.balance, 100.0);
+    }
+}
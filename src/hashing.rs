@@ -0,0 +1,185 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::Hash;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// Потоковая хеш-функция: ею считаются хеши блоков, листья Меркла и идентификаторы транзакций
+pub trait HashAlgorithm: Clone + Default {
+    /// Имя алгоритма для сообщений и спецификации
+    const NAME: &'static str;
+    
+    fn update(&mut self, data: &[u8]);
+    
+    fn finish(self) -> Hash;
+}
+
+#[derive(Clone, Default)]
+pub struct Sha256Hasher(Sha256);
+
+impl HashAlgorithm for Sha256Hasher {
+    const NAME: &'static str = "SHA-256";
+    
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+    
+    fn finish(self) -> Hash {
+        self.0.finalize().into()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Blake3Hasher(blake3::Hasher);
+
+impl HashAlgorithm for Blake3Hasher {
+    const NAME: &'static str = "BLAKE3";
+    
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+    
+    fn finish(self) -> Hash {
+        self.0.finalize().into()
+    }
+}
+
+/// Keccak-256 в варианте Ethereum, с исходным дополнением, а не SHA3-256
+#[derive(Clone, Default)]
+pub struct Keccak256Hasher(Keccak256);
+
+impl HashAlgorithm for Keccak256Hasher {
+    const NAME: &'static str = "Keccak-256";
+    
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+    
+    fn finish(self) -> Hash {
+        self.0.finalize().into()
+    }
+}
+
+/// Хеш-функция цепочки; задается спецификацией и хранится вместе с цепочкой, ее блоками и транзакциями
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashKind {
+    #[default]
+    Sha256,
+    Blake3,
+    Keccak256,
+}
+
+impl HashKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            HashKind::Sha256 => Sha256Hasher::NAME,
+            HashKind::Blake3 => Blake3Hasher::NAME,
+            HashKind::Keccak256 => Keccak256Hasher::NAME,
+        }
+    }
+    
+    /// SHA-256 не записывается в блоки и транзакции, чтобы их JSON остался прежним
+    pub fn is_sha256(&self) -> bool {
+        *self == HashKind::Sha256
+    }
+    
+    pub(crate) fn state(self) -> HashState {
+        match self {
+            HashKind::Sha256 => HashState::Sha256(Sha256Hasher::default()),
+            HashKind::Blake3 => HashState::Blake3(Box::default()),
+            HashKind::Keccak256 => HashState::Keccak256(Box::default()),
+        }
+    }
+    
+    /// Хеш байтов этим алгоритмом
+    pub fn digest(self, data: &[u8]) -> Hash {
+        let mut state = self.state();
+        state.update(data);
+        state.finish()
+    }
+}
+
+/// Незавершенный хеш одним из алгоритмов; большие состояния BLAKE3 и Keccak хранятся в куче
+#[derive(Clone)]
+pub(crate) enum HashState {
+    Sha256(Sha256Hasher),
+    Blake3(Box<Blake3Hasher>),
+    Keccak256(Box<Keccak256Hasher>),
+}
+
+impl HashState {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            HashState::Sha256(hasher) => hasher.update(data),
+            HashState::Blake3(hasher) => hasher.update(data),
+            HashState::Keccak256(hasher) => hasher.update(data),
+        }
+    }
+    
+    pub(crate) fn finish(self) -> Hash {
+        match self {
+            HashState::Sha256(hasher) => hasher.finish(),
+            HashState::Blake3(hasher) => (*hasher).finish(),
+            HashState::Keccak256(hasher) => (*hasher).finish(),
+        }
+    }
+}
+
+/// Эталонные хеши каждого алгоритма для пустой строки и `abc`
+pub const HASH_TEST_VECTORS: [(HashKind, &str, &str); 6] = [
+    (HashKind::Sha256, "", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+    (HashKind::Sha256, "abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+    (HashKind::Blake3, "", "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"),
+    (HashKind::Blake3, "abc", "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"),
+    (HashKind::Keccak256, "", "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+    (HashKind::Keccak256, "abc", "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+];
+
+/// Сверяет реализацию алгоритма с эталонными хешами
+pub fn check_test_vectors(kind: HashKind) -> Result<(), BlockchainError> {
+    for (_, input, expected) in HASH_TEST_VECTORS.iter().filter(|(vector_kind, _, _)| *vector_kind == kind) {
+        if hex::encode(kind.digest(input.as_bytes())) != *expected {
+            return Err(BlockchainError::Storage(format!("{} does not match its test vector for {:?}", kind.name(), input)));
+        }
+    }
+    Ok(())
+}
+
+/// Проверяет, что блок и его транзакции посчитаны алгоритмом цепочки `kind`
+pub(crate) fn check_block_hash_algorithm(block: &Block, kind: HashKind) -> Result<(), BlockchainError> {
+    if block.hash_algorithm != kind || block.transactions.iter().any(|tx| tx.hash_algorithm != kind) {
+        return Err(BlockchainError::HashAlgorithmMismatch { algorithm: kind.name(), height: block.index });
+    }
+    Ok(())
+}
+
+impl Blockchain {
+    /// Переводит новую цепочку на алгоритм `kind` и пересчитывает генезис. Потом алгоритм
+    /// не меняется: им посчитаны хеши блоков и идентификаторы транзакций.
+    pub fn set_hash_algorithm(&mut self, kind: HashKind) -> Result<(), BlockchainError> {
+        check_test_vectors(kind)?;
+        if self.chain.len() > 1 || self.chain.iter().any(|block| !block.transactions.is_empty()) || !self.mempool.is_empty() {
+            return Err(BlockchainError::InvalidConfig("Hash algorithm can only be set before the first transaction".to_string()));
+        }
+        self.hash_algorithm = kind;
+        if let Some(genesis) = self.chain.first_mut() {
+            genesis.set_hash_algorithm(kind);
+        }
+        Ok(())
+    }
+    
+    /// Проверяет реализацию алгоритма цепочки и то, что им посчитаны генезис и вершина.
+    /// Цепочка, посчитанная другим алгоритмом, отклоняется, а не загружается с неверными хешами.
+    pub fn check_hash_algorithm(&self) -> Result<(), BlockchainError> {
+        check_test_vectors(self.hash_algorithm)?;
+        for block in self.chain.first().into_iter().chain(self.chain.last()) {
+            check_block_hash_algorithm(block, self.hash_algorithm)?;
+            if block.hash != block.calculate_hash() {
+                return Err(BlockchainError::HashAlgorithmMismatch { algorithm: self.hash_algorithm.name(), height: block.index });
+            }
+        }
+        Ok(())
+    }
+}
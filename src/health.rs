@@ -0,0 +1,293 @@
+#![deny(clippy::indexing_slicing)]
+
+use crate::blockchain::Blockchain;
+use crate::supply::is_system_sender;
+use crate::transaction::TransactionType;
+use crate::wallet::TxLocation;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Сколько блоков просматривается при сверке индексов с транзакциями
+const INDEX_SAMPLE_BLOCKS: usize = 64;
+
+/// Итог одной проверки; порядок вариантов — от лучшего к худшему
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum HealthStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthFinding {
+    pub check: &'static str,
+    pub status: HealthStatus,
+    pub details: Vec<String>,
+}
+
+impl HealthFinding {
+    /// Проверка не прошла, если есть ошибки, и прошла с предупреждением, если есть только замечания
+    fn from_problems(check: &'static str, errors: Vec<String>, warnings: Vec<String>) -> Self {
+        let status = if !errors.is_empty() {
+            HealthStatus::Fail
+        } else if !warnings.is_empty() {
+            HealthStatus::Warn
+        } else {
+            HealthStatus::Pass
+        };
+        HealthFinding { check, status, details: errors.into_iter().chain(warnings).collect() }
+    }
+}
+
+/// Результат диагностики узла: худший из итогов проверок и сами проверки
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub height: u64,
+    pub findings: Vec<HealthFinding>,
+}
+
+impl HealthReport {
+    fn new(height: u64, findings: Vec<HealthFinding>) -> Self {
+        let status = findings.iter().map(|finding| finding.status).max().unwrap_or(HealthStatus::Pass);
+        HealthReport { status, height, findings }
+    }
+    
+    pub fn finding(&self, check: &str) -> Option<&HealthFinding> {
+        self.findings.iter().find(|finding| finding.check == check)
+    }
+}
+
+/// Равномерная выборка высот с обязательной вершиной
+fn sample_heights(len: usize) -> Vec<usize> {
+    let step = len.div_ceil(INDEX_SAMPLE_BLOCKS).max(1);
+    let mut heights: Vec<usize> = (0..len).step_by(step).collect();
+    if let Some(tip) = len.checked_sub(1).filter(|tip| heights.last() != Some(tip)) {
+        heights.push(tip);
+    }
+    heights
+}
+
+impl Blockchain {
+    /// Прогоняет все проверки состояния узла: цепочку, индексы, сверку эмиссии, пул и контракты
+    pub fn health_check(&self) -> HealthReport {
+        let findings = vec![
+            self.check_chain_validity(),
+            self.check_address_index(),
+            self.check_wallet_histories(),
+            self.check_supply(),
+            self.check_mempool(),
+            self.check_contracts(),
+        ];
+        HealthReport::new(self.get_latest_block().index, findings)
+    }
+    
+    /// То же, что `health_check`, и сверка с цепочкой, сохраненной в каталоге данных
+    pub fn health_check_with_store(&self, data_dir: &Path) -> HealthReport {
+        let mut report = self.health_check();
+        report.findings.push(self.check_persistence(data_dir));
+        HealthReport::new(report.height, report.findings)
+    }
+    
+    fn check_chain_validity(&self) -> HealthFinding {
+        let errors = self.validate_chain(self.signature_verification).err().map(|e| e.to_string()).into_iter().collect();
+        HealthFinding::from_problems("chain", errors, Vec::new())
+    }
+    
+    /// У каждого блока есть фильтр адресов, и фильтры выборки знают все адреса своих транзакций
+    fn check_address_index(&self) -> HealthFinding {
+        let mut errors = Vec::new();
+        let filters = self.address_index.filter_count();
+        if filters != self.chain.len() {
+            errors.push(format!("{} address filters for {} blocks", filters, self.chain.len()));
+        }
+        for height in sample_heights(self.chain.len()) {
+            let Some(block) = self.chain.get(height) else { continue };
+            for tx in &block.transactions {
+                for address in [&tx.sender, &tx.receiver] {
+                    if !self.address_index.may_involve(height, address) {
+                        errors.push(format!("Filter of block {} misses {} from transaction {}", height, address, tx.id));
+                    }
+                }
+            }
+        }
+        HealthFinding::from_problems("address_index", errors, Vec::new())
+    }
+    
+    /// Записи историй кошельков указывают на их транзакции, а транзакции выборки есть в историях
+    fn check_wallet_histories(&self) -> HealthFinding {
+        let mut errors = Vec::new();
+        for (address, wallet) in &self.wallets {
+            for location in wallet.history_locations() {
+                let tx = self.chain.get(location.block as usize).and_then(|block| block.transactions.get(location.position as usize));
+                match tx {
+                    Some(tx) if tx.sender == *address || tx.receiver == *address => {},
+                    Some(tx) => errors.push(format!("History of {} points to transaction {} of other addresses", address, tx.id)),
+                    None => errors.push(format!("History of {} points to missing transaction {}:{}", address, location.block, location.position)),
+                }
+            }
+        }
+        for height in sample_heights(self.chain.len()) {
+            let Some(block) = self.chain.get(height) else { continue };
+            for (position, tx) in block.transactions.iter().enumerate() {
+                let location = TxLocation { block: height as u64, position: position as u32 };
+                for address in [&tx.sender, &tx.receiver] {
+                    let missing = self.wallets.get(address).is_some_and(|wallet| !wallet.history_locations().contains(&location));
+                    if missing {
+                        errors.push(format!("History of {} lacks transaction {}", address, tx.id));
+                    }
+                }
+            }
+        }
+        HealthFinding::from_problems("wallet_histories", errors, Vec::new())
+    }
+    
+    fn check_supply(&self) -> HealthFinding {
+        let errors = match self.audit_supply() {
+            Ok(_) => Vec::new(),
+            Err(discrepancies) => discrepancies.iter()
+                .map(|d| format!("{} holds {}, expected {}", d.address, d.actual, d.expected))
+                .collect(),
+        };
+        HealthFinding::from_problems("supply", errors, Vec::new())
+    }
+    
+    /// В пуле нет повторов и подтвержденных транзакций, у отправителей есть кошельки с их списаниями
+    fn check_mempool(&self) -> HealthFinding {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let confirmed: HashSet<&str> = self.chain.iter()
+            .flat_map(|block| &block.transactions)
+            .map(|tx| tx.id.as_str())
+            .collect();
+        let pool = self.mempool.lock();
+        let mut seen = HashSet::new();
+        for tx in &pool.transactions {
+            if !seen.insert(tx.id.as_str()) {
+                errors.push(format!("Transaction {} is pending twice", tx.id));
+            }
+            if confirmed.contains(tx.id.as_str()) {
+                errors.push(format!("Transaction {} is pending but already confirmed", tx.id));
+            }
+            if !is_system_sender(&tx.sender) && !self.wallets.contains_key(&tx.sender) {
+                errors.push(format!("Sender {} of pending transaction {} has no wallet", tx.sender, tx.id));
+            }
+        }
+        if let Some(capacity) = pool.capacity.filter(|capacity| pool.transactions.len() > *capacity) {
+            warnings.push(format!("{} pending transactions exceed the capacity {}", pool.transactions.len(), capacity));
+        }
+        HealthFinding::from_problems("mempool", errors, warnings)
+    }
+    
+    /// Развернутые в цепочке контракты находятся по адресу и имеют кошелек
+    fn check_contracts(&self) -> HealthFinding {
+        let mut warnings = Vec::new();
+        let contracts = self.chain.iter()
+            .flat_map(|block| &block.transactions)
+            .filter(|tx| matches!(tx.transaction_type, TransactionType::SmartContract(_)));
+        for tx in contracts {
+            if self.find_smart_contract(&tx.receiver).is_none() {
+                warnings.push(format!("Contract {} deployed by {} cannot be resolved", tx.receiver, tx.id));
+            }
+            if !self.wallets.contains_key(&tx.receiver) {
+                warnings.push(format!("Contract {} has no wallet", tx.receiver));
+            }
+        }
+        HealthFinding::from_problems("contracts", Vec::new(), warnings)
+    }
+    
+    /// Сохраненная цепочка загружается с проверкой и совпадает с этой или отстает от нее
+    fn check_persistence(&self, data_dir: &Path) -> HealthFinding {
+        let stored = match Blockchain::load_validated(data_dir, self.signature_verification, 0, |_| {}) {
+            Ok(stored) => stored,
+            Err(e) => return HealthFinding::from_problems("persistence", vec![e.to_string()], Vec::new()),
+        };
+        let stored_tip = stored.get_latest_block();
+        let (errors, warnings) = match self.chain.get(stored_tip.index as usize) {
+            Some(block) if block.hash == stored_tip.hash && stored.chain.len() == self.chain.len() => (Vec::new(), Vec::new()),
+            Some(block) if block.hash == stored_tip.hash => {
+                (Vec::new(), vec![format!("{} blocks are not saved yet", self.chain.len() - stored.chain.len())])
+            },
+            _ => (vec![format!("Saved tip {} at height {} is not in this chain", stored_tip.hash, stored_tip.index)], Vec::new()),
+        };
+        HealthFinding::from_problems("persistence", errors, warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::Transaction;
+    
+    fn healthy_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        chain.create_smart_contract("alice".to_string(), "counter".to_string(), 1.0).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain
+    }
+    
+    /// Проверки, которые не прошли, с их итогами
+    fn failures(report: &HealthReport) -> Vec<(&'static str, HealthStatus)> {
+        report.findings.iter().filter(|finding| finding.status != HealthStatus::Pass).map(|finding| (finding.check, finding.status)).collect()
+    }
+    
+    #[test]
+    fn healthy_chain_passes_every_check() {
+        let mut chain = healthy_chain();
+        let dir = std::env::temp_dir().join(format!("hellochain-health-{}", std::process::id()));
+        chain.save(&dir).unwrap();
+        let report = chain.health_check_with_store(&dir);
+        assert_eq!(report.status, HealthStatus::Pass, "{:?}", report);
+        assert_eq!(report.findings.len(), 7);
+        assert_eq!(report.height, 2);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json.pointer("/status"), Some(&"Pass".into()));
+        assert_eq!(json.pointer("/findings/0/check"), Some(&"chain".into()));
+        
+        // Несохраненный блок — только предупреждение
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let report = chain.health_check_with_store(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(failures(&report), vec![("persistence", HealthStatus::Warn)]);
+        assert_eq!(report.status, HealthStatus::Warn);
+    }
+    
+    #[test]
+    fn corrupted_history_entry_fails_the_history_check() {
+        let mut chain = healthy_chain();
+        chain.wallets.get_mut("bob").unwrap().record(TxLocation { block: 1, position: 0 });
+        let report = chain.health_check();
+        assert_eq!(failures(&report), vec![("wallet_histories", HealthStatus::Fail)]);
+        let details = &report.finding("wallet_histories").unwrap().details;
+        assert!(details.iter().any(|detail| detail.starts_with("History of bob points to transaction")), "{:?}", details);
+    }
+    
+    #[test]
+    fn corrupted_balance_fails_the_supply_check() {
+        let mut chain = healthy_chain();
+        chain.wallets.get_mut("bob").unwrap().balance += 3.0;
+        let report = chain.health_check();
+        assert_eq!(failures(&report), vec![("supply", HealthStatus::Fail)]);
+        assert_eq!(report.status, HealthStatus::Fail);
+        assert_eq!(report.finding("supply").unwrap().details, vec!["bob holds 13, expected 10".to_string()]);
+    }
+    
+    #[test]
+    fn confirmed_transaction_in_the_pool_fails_the_mempool_check() {
+        let mut chain = healthy_chain();
+        let confirmed = chain.get_latest_block().transactions.iter().find(|tx| tx.sender == "alice").unwrap().clone();
+        chain.mempool.get_mut().transactions.push(confirmed.clone());
+        let report = chain.health_check();
+        let finding = report.finding("mempool").unwrap();
+        assert_eq!(finding.status, HealthStatus::Fail);
+        assert_eq!(finding.details, vec![format!("Transaction {} is pending but already confirmed", confirmed.id)]);
+    }
+}
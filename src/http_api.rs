@@ -0,0 +1,359 @@
+use crate::errors::{BlockchainError, ErrorBody};
+use crate::shared::SharedBlockchain;
+use crate::transaction::Transaction;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+
+/// Настройки REST API
+#[derive(Debug, Clone, Default)]
+pub struct HttpApiConfig {
+    /// Токен, который должен прийти в заголовке `Authorization: Bearer ...` для `POST /mine`
+    pub auth_token: Option<String>,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    chain: SharedBlockchain,
+    config: HttpApiConfig,
+}
+
+/// Ошибка REST API с HTTP-статусом
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub body: ErrorBody,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError { status, body: ErrorBody::other(message) }
+    }
+    
+    fn not_found(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, message)
+    }
+    
+    fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl From<BlockchainError> for ApiError {
+    fn from(error: BlockchainError) -> Self {
+        let status = match &error {
+            BlockchainError::InvalidTransaction(_) | BlockchainError::InvalidBlock(_) | BlockchainError::MalformedTransaction { .. }
+            | BlockchainError::InvalidSignature { .. } | BlockchainError::FeeTooLow { .. } | BlockchainError::WrongChainId { .. }
+            | BlockchainError::ContractError { .. } | BlockchainError::WrongNetwork { .. }
+            | BlockchainError::InvalidConfig(_) => StatusCode::BAD_REQUEST,
+            BlockchainError::InsufficientBalance { .. } | BlockchainError::ConsensusError(_) | BlockchainError::ValidatorJailed { .. }
+            | BlockchainError::NotScheduledProducer { .. } | BlockchainError::DuplicateTransaction { .. }
+            | BlockchainError::DuplicateChain { .. } | BlockchainError::StaleTemplate { .. } | BlockchainError::WrongLedgerMode { .. }
+            | BlockchainError::DuplicateWallet { .. } | BlockchainError::HashAlgorithmMismatch { .. }
+            | BlockchainError::InvalidNonce { .. } => StatusCode::CONFLICT,
+            BlockchainError::WalletNotFound { .. } | BlockchainError::ValidatorNotFound { .. } | BlockchainError::TransactionNotFound { .. }
+            | BlockchainError::FeatureDisabled { .. } | BlockchainError::ChainNotFound { .. } => StatusCode::NOT_FOUND,
+            BlockchainError::AccountFrozen { .. } | BlockchainError::WalletClosed { .. } => StatusCode::FORBIDDEN,
+            BlockchainError::MempoolFull { .. } | BlockchainError::FaucetDepleted { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            BlockchainError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            BlockchainError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        
+        ApiError { status, body: ErrorBody::from(&error) }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.body }))).into_response()
+    }
+}
+
+/// Параметры постраничного вывода истории адреса
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+/// Страница транзакций с курсором для запроса следующей страницы
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub next_cursor: Option<String>,
+}
+
+/// Тело запроса на отправку подписанной транзакции
+#[derive(Debug, Deserialize)]
+pub struct RawTransactionRequest {
+    pub raw: String,
+}
+
+/// Тело запроса к крану
+#[derive(Debug, Deserialize)]
+pub struct FaucetRequest {
+    pub address: String,
+}
+
+/// Тело запроса на майнинг блока
+#[derive(Debug, Deserialize)]
+pub struct MineRequest {
+    pub miner: String,
+}
+
+const DEFAULT_PAGE_LIMIT: usize = 50;
+const MAX_PAGE_LIMIT: usize = 500;
+
+/// Создает маршрутизатор REST API поверх разделяемого блокчейна
+pub fn router(chain: SharedBlockchain, config: HttpApiConfig) -> Router {
+    Router::new()
+        .route("/blocks/{height}", get(get_block))
+        .route("/blocks/hash/{hash}", get(get_block_by_hash))
+        .route("/transactions", post(post_transaction))
+        .route("/transactions/{id}", get(get_transaction))
+        .route("/addresses/{addr}/balance", get(get_balance))
+        .route("/addresses/{addr}/transactions", get(get_address_transactions))
+        .route("/mempool", get(get_mempool))
+        .route("/mine", post(post_mine))
+        .route("/faucet", post(post_faucet))
+        .with_state(ApiState { chain, config })
+}
+
+/// Запускает REST API на уже открытом сокете
+pub async fn serve(listener: tokio::net::TcpListener, chain: SharedBlockchain, config: HttpApiConfig) -> std::io::Result<()> {
+    axum::serve(listener, router(chain, config)).await
+}
+
+async fn get_block(State(state): State<ApiState>, Path(height): Path<String>) -> Result<Response, ApiError> {
+    let height: u64 = height.parse().map_err(|_| ApiError::bad_request("Height must be a non-negative integer"))?;
+    let chain = state.chain.read();
+    let block = chain.get_block_by_index(height)
+        .ok_or_else(|| ApiError::not_found(format!("Block {} not found", height)))?;
+    Ok(Json(block).into_response())
+}
+
+async fn get_block_by_hash(State(state): State<ApiState>, Path(hash): Path<String>) -> Result<Response, ApiError> {
+    let chain = state.chain.read();
+    let block = chain.get_block_by_hash(&hash)
+        .ok_or_else(|| ApiError::not_found(format!("Block {} not found", hash)))?;
+    Ok(Json(block).into_response())
+}
+
+async fn get_transaction(State(state): State<ApiState>, Path(id): Path<String>) -> Result<Response, ApiError> {
+    let chain = state.chain.read();
+    let transaction = chain.find_transaction(&id)
+        .or_else(|| chain.mempool().get(&id))
+        .ok_or_else(|| BlockchainError::TransactionNotFound { tx_id: id.clone() })?;
+    Ok(Json(transaction).into_response())
+}
+
+async fn get_balance(State(state): State<ApiState>, Path(addr): Path<String>) -> Result<Response, ApiError> {
+    let chain = state.chain.read();
+    let wallet = chain.get_wallet_info(&addr)
+        .ok_or_else(|| BlockchainError::WalletNotFound { address: addr.clone() })?;
+    Ok(Json(json!({
+        "address": wallet.address,
+        "balance": wallet.balance,
+        "staking_balance": wallet.staking_balance,
+    })).into_response())
+}
+
+async fn get_address_transactions(
+    State(state): State<ApiState>,
+    Path(addr): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<TransactionPage>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+    let offset: usize = match query.cursor {
+        Some(cursor) => cursor.parse().map_err(|_| ApiError::bad_request("Invalid cursor"))?,
+        None => 0,
+    };
+    
+    let history = state.chain.read().get_transaction_history(&addr);
+    let transactions: Vec<Transaction> = history.iter().skip(offset).take(limit).cloned().collect();
+    let next_offset = offset + transactions.len();
+    let next_cursor = (next_offset < history.len()).then(|| next_offset.to_string());
+    
+    Ok(Json(TransactionPage { transactions, next_cursor }))
+}
+
+async fn post_transaction(
+    State(state): State<ApiState>,
+    Json(request): Json<RawTransactionRequest>,
+) -> Result<Response, ApiError> {
+    let tx_id = state.chain.submit_raw_transaction(&request.raw)?;
+    Ok((StatusCode::ACCEPTED, Json(json!({ "id": tx_id }))).into_response())
+}
+
+async fn post_faucet(
+    State(state): State<ApiState>,
+    Json(request): Json<FaucetRequest>,
+) -> Result<Response, ApiError> {
+    let tx_id = state.chain.write().request_from_faucet(&request.address)?;
+    Ok((StatusCode::ACCEPTED, Json(json!({ "id": tx_id }))).into_response())
+}
+
+async fn get_mempool(State(state): State<ApiState>) -> Json<Vec<Transaction>> {
+    let chain = state.chain.read();
+    Json(chain.mempool().transactions())
+}
+
+async fn post_mine(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<MineRequest>,
+) -> Result<Response, ApiError> {
+    if let Some(token) = &state.config.auth_token {
+        let provided = headers.get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if provided != Some(token.as_str()) {
+            return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Invalid auth token"));
+        }
+    }
+    
+    let chain = state.chain.clone();
+    // Майнинг занимает процессор надолго, поэтому выполняем его вне потоков рантайма
+    tokio::task::spawn_blocking(move || {
+        let block = chain.mine_block(request.miner)?;
+        Ok((StatusCode::CREATED, Json(block)).into_response())
+    })
+    .await
+    .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed_transfer, ChainFixture};
+    use serde_json::Value;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use tokio::runtime::Runtime;
+    
+    /// Запускает REST API на свободном порту; он работает, пока жив рантайм
+    fn start_server(chain: SharedBlockchain, config: HttpApiConfig) -> (Runtime, SocketAddr) {
+        let runtime = Runtime::new().unwrap();
+        let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(serve(listener, chain, config));
+        (runtime, addr)
+    }
+    
+    /// Выполняет запрос и возвращает код ответа и разобранное тело
+    fn request(addr: SocketAddr, method: &str, path: &str, token: Option<&str>, body: &str) -> (u16, Value) {
+        let auth = token.map(|token| format!("Authorization: Bearer {}\r\n", token)).unwrap_or_default();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method, path, addr, auth, body.len(), body
+        ).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+    
+    fn get(addr: SocketAddr, path: &str) -> (u16, Value) {
+        request(addr, "GET", path, None, "")
+    }
+    
+    #[test]
+    fn resources_are_served_in_their_serde_form() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let tx = signed_transfer(&chain, "alice", "bob", 10.0);
+        chain.add_transaction(tx.clone()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let block = chain.get_latest_block().clone();
+        let (_runtime, addr) = start_server(SharedBlockchain::new(chain), HttpApiConfig::default());
+        
+        assert_eq!(get(addr, "/blocks/2"), (200, serde_json::to_value(&block).unwrap()));
+        assert_eq!(get(addr, &format!("/blocks/hash/{}", block.hash)), (200, serde_json::to_value(&block).unwrap()));
+        assert_eq!(get(addr, &format!("/transactions/{}", tx.id)), (200, serde_json::to_value(&tx).unwrap()));
+        let (status, balance) = get(addr, "/addresses/bob/balance");
+        assert_eq!(status, 200);
+        assert_eq!(balance["balance"], json!(10.0));
+        
+        assert_eq!(get(addr, "/blocks/3").0, 404);
+        assert_eq!(get(addr, "/blocks/-1").0, 400);
+        assert_eq!(get(addr, "/blocks/hash/00").0, 404);
+        assert_eq!(get(addr, "/transactions/unknown").0, 404);
+        assert_eq!(get(addr, "/addresses/nobody/balance").0, 404);
+    }
+    
+    #[test]
+    fn history_pages_chain_through_the_cursor() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        for _ in 0..4 {
+            let tx = signed_transfer(&chain, "alice", "bob", 1.0);
+            chain.add_transaction(tx).unwrap();
+        }
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let history = chain.get_transaction_history("alice");
+        let (_runtime, addr) = start_server(SharedBlockchain::new(chain), HttpApiConfig::default());
+        
+        let mut pages = Vec::new();
+        let mut path = "/addresses/alice/transactions?limit=2".to_string();
+        loop {
+            let (status, page) = get(addr, &path);
+            assert_eq!(status, 200);
+            let page: TransactionPage = serde_json::from_value(page).unwrap();
+            assert!(page.transactions.len() <= 2);
+            pages.extend(page.transactions);
+            match page.next_cursor {
+                Some(cursor) => path = format!("/addresses/alice/transactions?limit=2&cursor={}", cursor),
+                None => break,
+            }
+        }
+        let ids = |txs: &[Transaction]| txs.iter().map(|tx| tx.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&pages), ids(&history));
+        assert_eq!(get(addr, "/addresses/alice/transactions?cursor=next").0, 400);
+    }
+    
+    #[test]
+    fn refused_transactions_leave_the_chain_untouched() {
+        let chain = ChainFixture::funded().build().unwrap().chain;
+        let mut tampered = signed_transfer(&chain, "alice", "bob", 10.0);
+        tampered.amount = 90.0;
+        let overspend = signed_transfer(&chain, "alice", "bob", 1000.0);
+        let valid = signed_transfer(&chain, "alice", "bob", 10.0);
+        let chain_id = chain.chain_id;
+        let shared = SharedBlockchain::new(chain);
+        let (_runtime, addr) = start_server(shared.clone(), HttpApiConfig::default());
+        let post = |tx: &Transaction| request(addr, "POST", "/transactions", None, &json!({ "raw": tx.to_raw_hex(chain_id) }).to_string());
+        let state = || serde_json::to_string(&*shared.read()).unwrap();
+        
+        let before = state();
+        assert_eq!(post(&tampered).0, 400);
+        assert_eq!(post(&overspend).0, 409);
+        assert_eq!(request(addr, "POST", "/transactions", None, r#"{"raw": "zz"}"#).0, 400);
+        assert_eq!(state(), before);
+        assert_eq!(get(addr, "/mempool"), (200, json!([])));
+        
+        assert_eq!(post(&valid), (202, json!({ "id": valid.id })));
+        assert_eq!(post(&valid).0, 409);
+        assert_eq!(get(addr, "/mempool"), (200, json!([valid])));
+    }
+    
+    #[test]
+    fn mining_requires_the_configured_token() {
+        let chain = ChainFixture::funded().build().unwrap().chain;
+        let config = HttpApiConfig { auth_token: Some("secret".to_string()) };
+        let (_runtime, addr) = start_server(SharedBlockchain::new(chain), config);
+        let body = r#"{"miner": "miner"}"#;
+        
+        assert_eq!(request(addr, "POST", "/mine", None, body).0, 401);
+        assert_eq!(request(addr, "POST", "/mine", Some("guess"), body).0, 401);
+        let (status, block) = request(addr, "POST", "/mine", Some("secret"), body);
+        assert_eq!(status, 201);
+        assert_eq!(block["index"], json!(2));
+        assert_eq!(get(addr, "/addresses/miner/balance").1["balance"], json!(100.0));
+    }
+}
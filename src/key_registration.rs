@@ -0,0 +1,135 @@
+use crate::address::Address;
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::ordering::is_coinbase;
+use crate::rebuild::is_empty;
+use crate::signing::Keypair;
+use crate::transaction::{Transaction, TransactionType};
+use crate::wallet::Wallet;
+use std::collections::HashMap;
+
+/// Начала адресов, которые заводит сам протокол
+const PROTOCOL_PREFIXES: [&str; 3] = ["BLOCKCHAIN_", "BRIDGE_", "contract_"];
+
+/// Ключи, которые закрепляют за своими отправителями транзакции `RegisterKey` из списка
+pub(crate) fn registered_keys<'a>(transactions: impl IntoIterator<Item = &'a Transaction>) -> HashMap<&'a str, &'a str> {
+    transactions.into_iter()
+        .filter(|tx| tx.transaction_type == TransactionType::RegisterKey)
+        .filter_map(|tx| Some((tx.sender.as_str(), tx.public_key.as_deref()?)))
+        .collect()
+}
+
+impl Blockchain {
+    /// Открытый ключ в hex, закрепленный за именем подтвержденной транзакцией `RegisterKey`
+    pub fn registered_key(&self, address: &str) -> Option<&str> {
+        self.wallets.get(address).and_then(|wallet| wallet.public_key.as_deref())
+    }
+    
+    /// Транзакция, закрепляющая за именем `address` ключ `keypair` и подписанная им. Закрепить ключ
+    /// можно только за именем, у которого в цепочке еще ничего нет, поэтому ее номер всегда нулевой.
+    pub fn key_registration(&self, address: &str, keypair: &Keypair) -> Transaction {
+        let mut tx = self.new_transaction(address.to_string(), address.to_string(), 0.0, TransactionType::RegisterKey);
+        tx.set_nonce(0);
+        tx.sign(keypair);
+        tx
+    }
+    
+    /// Ставит в очередь закрепление ключа за именем и возвращает идентификатор транзакции.
+    ///
+    /// Закрепление попадает в блок, и каждый узел проверяет по нему последующие траты имени. Оно
+    /// принимается, только пока имя ничего не отправляло и ничего не получало ни блоками, ни в обход
+    /// них, а в пуле или в том же блоке нет зачислений на него, иначе чужой ключ перехватил бы
+    /// полученное раньше. Имя с распределением генезиса закрепляется только за ключом, записанным
+    /// в `allocation_keys`. Адреса сети, счета протокола, контракты и казна ключ не закрепляют.
+    pub fn register_key(&mut self, address: &str, keypair: &Keypair) -> Result<String, BlockchainError> {
+        let tx = self.key_registration(address, keypair);
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Закрепляет новый ключ за именем, которое заводит награда этого узла: после награды закрепить
+    /// ключ уже нельзя, и без него ее было бы не потратить. Пара остается в кошельке узла, а закрепление
+    /// встает в пул и попадает в тот же блок, что и награда. Известное узлу имя, адрес сети, счет протокола
+    /// и имя, которому в пуле ждут зачисления, остаются как есть. Возвращает идентификатор закрепления.
+    pub(crate) fn bind_reward_key(&mut self, miner: &str) -> Option<String> {
+        if self.wallets.contains_key(miner) || !matches!(Address::parse(miner), Ok(Address::Legacy(_))) || self.is_protocol_account(miner) {
+            return None;
+        }
+        let keypair = Keypair::generate(self.entropy.as_ref());
+        let tx_id = self.register_key(miner, &keypair).ok()?;
+        self.wallets.insert(miner.to_string(), Wallet::with_keypair(miner.to_string(), keypair));
+        Some(tx_id)
+    }
+    
+    /// Отменяет закрепление из `bind_reward_key`, если блок с наградой так и не вышел
+    pub(crate) fn release_reward_key(&mut self, tx_id: &str) {
+        if let Ok(tx) = self.remove_pending_transaction(tx_id) {
+            self.wallets.remove(&tx.sender);
+        }
+    }
+    
+    /// Счета, которыми распоряжается протокол: системные отправители, эскроу, сборщик платы за данные,
+    /// контракты и казна. Ключ за ними не закрепить, даже пока они пусты.
+    fn is_protocol_account(&self, address: &str) -> bool {
+        PROTOCOL_PREFIXES.iter().any(|prefix| address.starts_with(prefix))
+            || self.treasury.as_ref().is_some_and(|treasury| treasury.config.address == address)
+    }
+    
+    /// Проверяет закрепление ключа: нулевой перевод имени самому себе без комиссии и с нулевым номером.
+    /// У имени еще нет ни ключа, ни отправленных транзакций, ни средств, стейка или зачислений в обход
+    /// блоков, иначе чужой ключ перехватил бы полученное им раньше. Имя с распределением генезиса
+    /// закрепляется только за ключом распределения. `registered` — закрепления, которые уже стоят в пуле
+    /// или выше по блоку. `alongside` — транзакции пула или всего блока: зачисление имени среди них тоже
+    /// запрещает закрепление, иначе его можно было бы обогнать. Награду блока выбирает его производитель,
+    /// поэтому она не мешает закрепить ключ за ее получателем в том же блоке.
+    pub(crate) fn check_key_registration<'a>(
+        &self,
+        tx: &Transaction,
+        registered: &HashMap<&str, &str>,
+        alongside: impl IntoIterator<Item = &'a Transaction>
+    ) -> Result<(), BlockchainError> {
+        if tx.transaction_type != TransactionType::RegisterKey {
+            return Ok(());
+        }
+        if tx.receiver != tx.sender || tx.amount != 0.0 || tx.fee != 0.0 || tx.fee_payer.is_some() || tx.nonce != Some(0) {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Key registration {} must be a free zero transfer from {} to itself with nonce 0", tx.id, tx.sender
+            )));
+        }
+        if matches!(Address::parse(&tx.sender), Ok(Address::Network { .. })) || self.is_protocol_account(&tx.sender) {
+            return Err(BlockchainError::InvalidTransaction(format!("No key can be registered for {}", tx.sender)));
+        }
+        if self.registered_key(&tx.sender).is_some() || registered.contains_key(tx.sender.as_str()) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} already has a registered key", tx.sender)));
+        }
+        if let Some(public_key) = self.allocation_keys.get(&tx.sender) {
+            if tx.public_key.as_ref() != Some(public_key) {
+                return Err(BlockchainError::InvalidTransaction(format!("{} can only register the key of its genesis allocation", tx.sender)));
+            }
+            return Ok(());
+        }
+        let in_use = self.wallets.get(&tx.sender).is_some_and(|wallet| wallet.next_nonce > 0 || !is_empty(wallet))
+            || self.validators.contains_key(&tx.sender)
+            || self.off_chain_credits.contains_key(&tx.sender);
+        if in_use {
+            return Err(BlockchainError::InvalidTransaction(format!("Wallet {} is already in use and cannot register a key", tx.sender)));
+        }
+        let credits = |other: &Transaction| {
+            other.transaction_type != TransactionType::RegisterKey && !is_coinbase(other)
+                && (other.receiver == tx.sender || other.outputs.iter().any(|output| output.address == tx.sender))
+        };
+        if alongside.into_iter().any(credits) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} has incoming transfers waiting and cannot register a key", tx.sender)));
+        }
+        Ok(())
+    }
+    
+    /// Закрепляет ключи по транзакциям `RegisterKey` блока, заводя кошельки новых имен
+    pub(crate) fn apply_key_registrations(&mut self, block: &Block) {
+        for (address, public_key) in registered_keys(&block.transactions) {
+            self.get_or_create_wallet(address).public_key = Some(public_key.to_string());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Локальные пометки адреса оператором узла. В блоки и проверки не входят и между узлами
+/// переносятся только экспортом.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WalletMetadata {
+    /// Отображаемое имя, например `exchange-hot`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl WalletMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.label.is_none() && self.tags.is_empty() && self.notes.is_none()
+    }
+    
+    /// Дополняет пометки импортированными: имя и заметки заменяются заданными, метки объединяются
+    fn merge(&mut self, other: WalletMetadata) {
+        if other.label.is_some() {
+            self.label = other.label;
+        }
+        if other.notes.is_some() {
+            self.notes = other.notes;
+        }
+        self.tags.extend(other.tags);
+    }
+}
+
+/// Пустая строка означает снятие пометки
+fn non_empty(text: Option<&str>) -> Option<String> {
+    text.map(str::trim).filter(|text| !text.is_empty()).map(str::to_string)
+}
+
+impl Blockchain {
+    /// Меняет пометки адреса и удаляет запись, если пометок не осталось
+    fn update_wallet_metadata<T>(&mut self, address: &str, update: impl FnOnce(&mut WalletMetadata) -> T) -> T {
+        let metadata = self.wallet_metadata.entry(address.to_string()).or_default();
+        let result = update(metadata);
+        if metadata.is_empty() {
+            self.wallet_metadata.remove(address);
+        }
+        result
+    }
+    
+    /// Задает отображаемое имя адреса; None или пустая строка его снимает
+    pub fn set_wallet_label(&mut self, address: &str, label: Option<&str>) {
+        let label = non_empty(label);
+        self.update_wallet_metadata(address, |metadata| metadata.label = label);
+    }
+    
+    /// Задает заметки об адресе; None или пустая строка их снимает
+    pub fn set_wallet_notes(&mut self, address: &str, notes: Option<&str>) {
+        let notes = non_empty(notes);
+        self.update_wallet_metadata(address, |metadata| metadata.notes = notes);
+    }
+    
+    /// Добавляет адресу метку; false, если она уже была
+    pub fn add_wallet_tag(&mut self, address: &str, tag: &str) -> Result<bool, BlockchainError> {
+        let tag = non_empty(Some(tag)).ok_or_else(|| BlockchainError::InvalidTransaction("Tag is empty".to_string()))?;
+        Ok(self.update_wallet_metadata(address, |metadata| metadata.tags.insert(tag)))
+    }
+    
+    /// Снимает с адреса метку; false, если ее не было
+    pub fn remove_wallet_tag(&mut self, address: &str, tag: &str) -> bool {
+        self.update_wallet_metadata(address, |metadata| metadata.tags.remove(tag.trim()))
+    }
+    
+    pub fn wallet_metadata(&self, address: &str) -> Option<&WalletMetadata> {
+        self.wallet_metadata.get(address)
+    }
+    
+    /// Адреса с меткой в порядке возрастания
+    pub fn find_wallets_by_tag(&self, tag: &str) -> Vec<&str> {
+        self.wallet_metadata.iter()
+            .filter(|(_, metadata)| metadata.tags.contains(tag.trim()))
+            .map(|(address, _)| address.as_str())
+            .collect()
+    }
+    
+    /// Адрес для показа: с именем в скобках, если оно задано, например `alice (exchange-hot)`
+    pub fn display_address(&self, address: &str) -> String {
+        match self.wallet_metadata(address).and_then(|metadata| metadata.label.as_deref()) {
+            Some(label) => format!("{} ({})", address, label),
+            None => address.to_string(),
+        }
+    }
+    
+    /// Записывает пометки всех адресов в JSON-файл
+    pub fn export_wallet_metadata(&self, path: &Path) -> Result<(), BlockchainError> {
+        let bytes = serde_json::to_vec_pretty(&self.wallet_metadata)
+            .map_err(|e| BlockchainError::Storage(format!("{}: {}", path.display(), e)))?;
+        fs::write(path, bytes).map_err(|e| BlockchainError::Storage(format!("{}: {}", path.display(), e)))
+    }
+    
+    /// Добавляет пометки из JSON-файла к имеющимся и возвращает число затронутых адресов
+    pub fn import_wallet_metadata(&mut self, path: &Path) -> Result<usize, BlockchainError> {
+        let bytes = fs::read(path).map_err(|e| BlockchainError::Storage(format!("{}: {}", path.display(), e)))?;
+        let imported: BTreeMap<String, WalletMetadata> = serde_json::from_slice(&bytes)
+            .map_err(|e| BlockchainError::Storage(format!("Invalid wallet metadata {}: {}", path.display(), e)))?;
+        
+        let count = imported.len();
+        for (address, metadata) in imported {
+            self.update_wallet_metadata(&address, |current| current.merge(metadata));
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::clock::MockClock;
+    use crate::entropy::SeededEntropy;
+    use crate::test_support::ChainFixture;
+    use std::sync::Arc;
+    
+    /// Цепочка с одинаковыми ключами и временем при одинаковом зерне, чтобы хеши блоков совпадали
+    fn seeded_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.set_clock(Arc::new(MockClock::new(GENESIS_TIMESTAMP)));
+        chain.set_entropy(Arc::new(SeededEntropy::new(3)));
+        chain.mining_threads = Some(1);
+        chain
+    }
+    
+    fn tagged_chain() -> Blockchain {
+        let mut chain = seeded_chain();
+        for address in ["alice", "bob", "carol"] {
+            chain.create_wallet(address.to_string()).unwrap();
+        }
+        chain.set_wallet_label("alice", Some("exchange-hot"));
+        chain.set_wallet_notes("alice", Some("rotated weekly"));
+        assert!(chain.add_wallet_tag("alice", "exchange").unwrap());
+        assert!(chain.add_wallet_tag("carol", "exchange").unwrap());
+        assert!(chain.add_wallet_tag("bob", " cold ").unwrap());
+        chain
+    }
+    
+    #[test]
+    fn wallets_are_found_by_tag() {
+        let mut chain = tagged_chain();
+        assert_eq!(chain.find_wallets_by_tag("exchange"), vec!["alice", "carol"]);
+        assert_eq!(chain.find_wallets_by_tag("cold"), vec!["bob"]);
+        assert!(!chain.add_wallet_tag("carol", "exchange").unwrap());
+        assert!(chain.add_wallet_tag("carol", "  ").is_err());
+        assert_eq!(chain.display_address("alice"), "alice (exchange-hot)");
+        assert_eq!(chain.display_address("bob"), "bob");
+        
+        assert!(chain.remove_wallet_tag("carol", "exchange"));
+        assert!(!chain.remove_wallet_tag("carol", "exchange"));
+        assert_eq!(chain.find_wallets_by_tag("exchange"), vec!["alice"]);
+        // Без пометок запись об адресе удаляется
+        assert_eq!(chain.wallet_metadata("carol"), None);
+        chain.set_wallet_label("alice", Some(""));
+        assert_eq!(chain.display_address("alice"), "alice");
+    }
+    
+    #[test]
+    fn metadata_survives_save_load_and_moves_by_export() {
+        let chain = tagged_chain();
+        let dir = std::env::temp_dir().join(format!("hellochain-labels-{}", std::process::id()));
+        chain.save(&dir).unwrap();
+        let export = dir.join("labels.json");
+        chain.export_wallet_metadata(&export).unwrap();
+        let loaded = Blockchain::load(&dir);
+        
+        let mut other = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        other.add_wallet_tag("alice", "audited").unwrap();
+        let imported = other.import_wallet_metadata(&export);
+        let _ = fs::remove_dir_all(&dir);
+        
+        assert_eq!(loaded.unwrap().wallet_metadata, chain.wallet_metadata);
+        assert_eq!(imported.unwrap(), 3);
+        let alice = other.wallet_metadata("alice").unwrap();
+        assert_eq!(alice.label.as_deref(), Some("exchange-hot"));
+        assert_eq!(alice.tags.iter().map(String::as_str).collect::<Vec<_>>(), vec!["audited", "exchange"]);
+        // Ключи вместе с пометками не переносятся
+        assert!(other.wallets.is_empty());
+    }
+    
+    #[test]
+    fn metadata_does_not_change_block_hashes() {
+        let mut tagged = tagged_chain();
+        let mut plain = seeded_chain();
+        for address in ["alice", "bob", "carol"] {
+            plain.create_wallet(address.to_string()).unwrap();
+        }
+        for miner in ["alice", "bob"] {
+            tagged.mine_pending_transactions(miner.to_string()).unwrap();
+            plain.mine_pending_transactions(miner.to_string()).unwrap();
+        }
+        let hashes = |chain: &Blockchain| chain.chain.iter().map(|block| block.hash.clone()).collect::<Vec<_>>();
+        assert_eq!(hashes(&tagged), hashes(&plain));
+    }
+}
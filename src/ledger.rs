@@ -0,0 +1,297 @@
+use crate::balance_audit::BalanceBucket;
+use crate::block::Block;
+use crate::blockchain::{receiver_credits, Blockchain};
+use crate::rewards::claim_debit;
+use crate::sponsorship::fee_bearer;
+use crate::staking::STAKING_MINTER;
+use crate::supply::{is_system_sender, SUPPLY_TOLERANCE};
+use crate::transaction::Transaction;
+use crate::utxo::LedgerMode;
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+/// Счет протокола, на который уходят комиссии и сжигаемые суммы и с которого выплачиваются награды
+pub const PROTOCOL_ACCOUNT: &str = "BLOCKCHAIN_REWARD";
+
+/// Счет двойной записи: адрес и часть кошелька
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerAccount {
+    pub address: String,
+    pub bucket: BalanceBucket,
+}
+
+impl LedgerAccount {
+    fn new(address: &str, bucket: BalanceBucket) -> Self {
+        LedgerAccount { address: address.to_string(), bucket }
+    }
+}
+
+/// Почему средства перешли со счета на счет
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedgerReason {
+    /// Сумма транзакции от отправителя получателю
+    Transfer,
+    /// Комиссия отправителя или спонсора
+    Fee,
+    /// Награда за блок или выплата стейкинга
+    Reward,
+    /// Прочий выпуск: мост, казна, транзакции `Mint`
+    Issuance,
+    /// Перевод накопленных наград на баланс
+    Claim,
+    /// Перевод на `BLOCKCHAIN_REWARD`, который сжигает сумму
+    Burn,
+    /// Сумма сгорела, потому что создала бы кошелек меньше экзистенциального депозита
+    DustBurn,
+}
+
+/// Проводка: сумма списывается с одного счета и зачисляется на другой
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub height: u64,
+    pub tx_id: String,
+    pub reason: LedgerReason,
+    pub debit: LedgerAccount,
+    pub credit: LedgerAccount,
+    pub amount: f64,
+}
+
+/// Какие проводки выбрать; пустой фильтр выбирает все
+#[derive(Debug, Clone, Default)]
+pub struct LedgerFilter {
+    /// Адрес, который списывает или получает
+    pub account: Option<String>,
+    pub reason: Option<LedgerReason>,
+    pub heights: Option<RangeInclusive<u64>>,
+}
+
+impl LedgerFilter {
+    fn matches(&self, entry: &LedgerEntry) -> bool {
+        self.account.as_ref().is_none_or(|address| entry.debit.address == *address || entry.credit.address == *address)
+            && self.reason.is_none_or(|reason| entry.reason == reason)
+            && self.heights.as_ref().is_none_or(|heights| heights.contains(&entry.height))
+    }
+}
+
+/// Поле CSV; строки с запятыми, кавычками и переводами строк берутся в кавычки
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Причина проводки суммы транзакции при обычном зачислении получателю
+fn transfer_reason(tx: &Transaction) -> LedgerReason {
+    if claim_debit(tx).is_some() {
+        LedgerReason::Claim
+    } else if tx.sender == PROTOCOL_ACCOUNT || tx.sender == STAKING_MINTER {
+        LedgerReason::Reward
+    } else if is_system_sender(&tx.sender) {
+        LedgerReason::Issuance
+    } else {
+        LedgerReason::Transfer
+    }
+}
+
+impl Blockchain {
+    /// Проводки, подходящие под фильтр, в порядке применения блоков
+    pub fn ledger_entries(&self, filter: &LedgerFilter) -> Vec<&LedgerEntry> {
+        self.ledger.iter().filter(|entry| filter.matches(entry)).collect()
+    }
+    
+    /// Проводки, подходящие под фильтр, в CSV с заголовком
+    pub fn ledger_csv(&self, filter: &LedgerFilter) -> String {
+        let mut csv = String::from("height,tx_id,reason,debit_account,debit_bucket,credit_account,credit_bucket,amount\n");
+        for entry in self.ledger_entries(filter) {
+            let _ = writeln!(
+                csv, "{},{},{:?},{},{:?},{},{:?},{}",
+                entry.height, csv_field(&entry.tx_id), entry.reason, csv_field(&entry.debit.address), entry.debit.bucket,
+                csv_field(&entry.credit.address), entry.credit.bucket, entry.amount
+            );
+        }
+        csv
+    }
+    
+    /// Записывает проводки применяемого блока: суммы транзакций туда, куда они на самом деле
+    /// зачислены (на баланс, в накопленные награды или в сожжение), и комиссии. `dust_burned` —
+    /// транзакции, чьи суммы сгорели вместо зачисления.
+    pub(crate) fn record_block_ledger(&mut self, block: &Block, dust_burned: &HashSet<&str>) {
+        let credited: HashSet<&str> = receiver_credits(&block.transactions).map(|tx| tx.id.as_str()).collect();
+        let first = self.ledger.len();
+        for tx in &block.transactions {
+            let debit_bucket = if claim_debit(tx).is_some() { BalanceBucket::Claimable } else { BalanceBucket::Balance };
+            let mut credits: Vec<(LedgerAccount, f64, LedgerReason)> = Vec::new();
+            if self.ledger_mode == LedgerMode::Utxo {
+                for output in tx.outputs.iter().filter(|output| is_system_sender(&tx.sender) || output.address != tx.sender) {
+                    credits.push((LedgerAccount::new(&output.address, BalanceBucket::Balance), output.amount, transfer_reason(tx)));
+                }
+            } else if tx.receiver == PROTOCOL_ACCOUNT {
+                credits.push((LedgerAccount::new(PROTOCOL_ACCOUNT, BalanceBucket::Balance), tx.amount, LedgerReason::Burn));
+            } else if self.accrues_reward(tx) {
+                credits.push((LedgerAccount::new(&tx.receiver, BalanceBucket::Claimable), tx.amount, LedgerReason::Reward));
+            } else if dust_burned.contains(tx.id.as_str()) {
+                credits.push((LedgerAccount::new(PROTOCOL_ACCOUNT, BalanceBucket::Balance), tx.amount, LedgerReason::DustBurn));
+            } else if credited.contains(tx.id.as_str()) {
+                credits.push((LedgerAccount::new(&tx.receiver, BalanceBucket::Balance), tx.amount, transfer_reason(tx)));
+            }
+            if !is_system_sender(&tx.sender) {
+                credits.push((LedgerAccount::new(PROTOCOL_ACCOUNT, BalanceBucket::Balance), tx.fee, LedgerReason::Fee));
+            }
+            
+            for (credit, amount, reason) in credits.into_iter().filter(|(_, amount, _)| *amount != 0.0) {
+                let debit = match reason {
+                    LedgerReason::Fee => LedgerAccount::new(fee_bearer(tx), BalanceBucket::Balance),
+                    _ => LedgerAccount::new(&tx.sender, debit_bucket),
+                };
+                self.ledger.push(LedgerEntry { height: block.index, tx_id: tx.id.clone(), reason, debit, credit, amount });
+            }
+        }
+        
+        #[cfg(debug_assertions)]
+        {
+            let entries = &self.ledger[first..];
+            // Каждая проводка парная, поэтому расхождение означает нечисловую сумму
+            let debits: f64 = entries.iter().map(|entry| entry.amount).sum();
+            let credits: f64 = entries.iter().map(|entry| entry.amount).sum();
+            debug_assert!(
+                (debits - credits).abs() <= SUPPLY_TOLERANCE,
+                "Ledger of block # {} does not balance: debits {}, credits {}", block.index, debits, credits
+            );
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = first;
+    }
+    
+    /// Отбрасывает проводки блоков, которых больше нет в цепочке, например после отката вершины
+    pub(crate) fn trim_ledger_to_chain(&mut self) {
+        let height = self.chain.len() as u64;
+        let kept = self.ledger.partition_point(|entry| entry.height < height);
+        self.ledger.truncate(kept);
+    }
+    
+    /// Отбрасывает проводки старше окна хранения журнала изменений балансов
+    pub(crate) fn prune_ledger(&mut self) {
+        let Some(retention) = self.balance_audit_retention else {
+            return;
+        };
+        let oldest = (self.chain.len() as u64).saturating_sub(retention);
+        let expired = self.ledger.partition_point(|entry| entry.height < oldest);
+        self.ledger.drain(..expired);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::simulation::{Simulation, SimulationConfig};
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    use std::collections::BTreeMap;
+    
+    fn send(chain: &mut Blockchain, from: &str, to: &str, amount: f64) -> String {
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(from));
+        chain.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx).unwrap();
+        tx_id
+    }
+    
+    fn entry(tx_id: &str, reason: LedgerReason, debit: &str, credit: &str, amount: f64) -> LedgerEntry {
+        let account = |address| LedgerAccount::new(address, BalanceBucket::Balance);
+        LedgerEntry { height: 2, tx_id: tx_id.to_string(), reason, debit: account(debit), credit: account(credit), amount }
+    }
+    
+    #[test]
+    fn mixed_block_produces_the_expected_entries() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.enable_minting("alice").unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let transfer = send(&mut chain, "alice", "bob", 10.0);
+        let burn = send(&mut chain, "alice", PROTOCOL_ACCOUNT, 2.0);
+        let mint = chain.mint("carol", 5.0).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let reward = chain.get_latest_block().transactions.last().unwrap().id.clone();
+        
+        let entries: Vec<LedgerEntry> = chain.ledger_entries(&LedgerFilter { heights: Some(2..=2), ..LedgerFilter::default() }).into_iter().cloned().collect();
+        assert_eq!(entries, [
+            entry(&transfer, LedgerReason::Transfer, "alice", "bob", 10.0),
+            entry(&transfer, LedgerReason::Fee, "alice", PROTOCOL_ACCOUNT, 0.01),
+            entry(&burn, LedgerReason::Burn, "alice", PROTOCOL_ACCOUNT, 2.0),
+            entry(&burn, LedgerReason::Fee, "alice", PROTOCOL_ACCOUNT, 0.002),
+            entry(&mint, LedgerReason::Issuance, "BLOCKCHAIN_MINT", "carol", 5.0),
+            entry(&reward, LedgerReason::Reward, PROTOCOL_ACCOUNT, "miner", 100.012),
+        ]);
+        
+        let fees = chain.ledger_entries(&LedgerFilter { account: Some("alice".to_string()), reason: Some(LedgerReason::Fee), heights: None });
+        assert_eq!(fees.len(), 2);
+        let csv = chain.ledger_csv(&LedgerFilter { account: Some("carol".to_string()), ..LedgerFilter::default() });
+        assert_eq!(csv, format!("height,tx_id,reason,debit_account,debit_bucket,credit_account,credit_bucket,amount\n2,{},Issuance,BLOCKCHAIN_MINT,Balance,carol,Balance,5\n", mint));
+    }
+    
+    #[test]
+    fn ledger_matches_wallets_across_a_long_run() {
+        let config = SimulationConfig { wallets: 6, transactions_per_block: 5, seed: 5, ..SimulationConfig::default() };
+        let mut simulation = Simulation::new(config).unwrap();
+        simulation.run(40).unwrap();
+        let chain = simulation.into_chain();
+        
+        // По блокам проводки идут подряд, и каждая переносит конечную положительную сумму
+        let mut height = 0;
+        for entry in chain.ledger_entries(&LedgerFilter::default()) {
+            assert!(entry.height >= height && entry.height < chain.chain.len() as u64);
+            height = entry.height;
+            assert!(entry.amount.is_finite() && entry.amount > 0.0, "{:?}", entry);
+        }
+        assert_eq!(height, 40);
+        
+        // Сальдо счетов вместе с пополнениями в обход блоков дает кошельки; пул кошельки не списывает
+        let mut net: BTreeMap<&str, f64> = chain.off_chain_credits.iter().map(|(address, amount)| (address.as_str(), *amount)).collect();
+        for entry in chain.ledger_entries(&LedgerFilter::default()) {
+            *net.entry(entry.debit.address.as_str()).or_insert(0.0) -= entry.amount;
+            *net.entry(entry.credit.address.as_str()).or_insert(0.0) += entry.amount;
+        }
+        for (address, wallet) in &chain.wallets {
+            let held = wallet.balance + wallet.staking_balance + wallet.claimable_rewards;
+            let expected = net.get(address.as_str()).copied().unwrap_or(0.0);
+            assert!((held - expected).abs() < SUPPLY_TOLERANCE, "{}: {} vs {}", address, held, expected);
+        }
+    }
+    
+    #[test]
+    fn entries_of_removed_blocks_are_trimmed() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        for _ in 0..4 {
+            send(&mut chain, "alice", "bob", 1.0);
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        
+        // Откат до высоты 2 и другой блок на его месте
+        chain.chain.truncate(3);
+        chain.rebuild_state_from_chain(true);
+        assert_eq!(chain.ledger.last().unwrap().height, 5);
+        chain.rebuild_state_from_chain(false);
+        assert_eq!(chain.ledger.last().unwrap().height, 2);
+        send(&mut chain, "alice", "carol", 3.0);
+        chain.mine_pending_transactions("rival".to_string()).unwrap();
+        let block_ids: Vec<&str> = chain.get_latest_block().transactions.iter().map(|tx| tx.id.as_str()).collect();
+        let replaced = chain.ledger_entries(&LedgerFilter { heights: Some(3..=3), ..LedgerFilter::default() });
+        assert_eq!(replaced.len(), 3);
+        assert!(replaced.iter().all(|entry| block_ids.contains(&entry.tx_id.as_str())));
+        assert_eq!(chain.ledger_entries(&LedgerFilter { account: Some("bob".to_string()), ..LedgerFilter::default() }).len(), 1);
+        
+        // Окно хранения отбрасывает старые проводки
+        chain.balance_audit_retention = Some(2);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.ledger.iter().all(|entry| entry.height >= 3));
+    }
+}
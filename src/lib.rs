@@ -0,0 +1,108 @@
+mod blockchain;
+mod builder;
+mod block;
+mod target;
+mod transaction;
+pub mod clock;
+pub mod entropy;
+pub mod codec;
+mod wallet;
+mod labels;
+pub mod wallet_closure;
+mod errors;
+mod trace;
+mod storage;
+mod spec;
+mod faucet;
+mod params;
+mod fee_market;
+pub mod treasury;
+pub mod staking;
+pub mod vesting;
+pub mod freeze;
+pub mod assets;
+pub mod supply;
+pub mod existential;
+pub mod rewards;
+pub mod governance;
+pub mod rebuild;
+pub mod balance_audit;
+pub mod balance_history;
+pub mod fee_estimate;
+mod chain_metrics;
+pub mod rich_list;
+pub mod health;
+pub mod simulation;
+mod cli;
+mod console;
+pub mod events;
+pub mod datastream;
+pub mod shared;
+pub mod registry;
+pub mod receipt;
+pub mod network;
+pub mod sync;
+pub mod mining;
+pub mod auto_miner;
+mod merkle;
+mod ordering;
+pub mod forks;
+pub mod snapshot;
+pub mod sponsorship;
+pub mod expiry;
+mod nonce;
+pub mod features;
+pub mod vanity;
+pub mod template;
+pub mod utxo;
+pub mod mint;
+pub mod address;
+pub mod dot;
+pub mod chain_diff;
+pub mod ledger;
+pub mod mempool;
+pub mod payment_uri;
+pub mod packages;
+pub mod reserve;
+pub mod hashing;
+pub mod reserved;
+mod signing;
+mod key_registration;
+mod bloom;
+pub mod overlay;
+pub mod light_client;
+pub mod bridge;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "http-api")]
+pub mod http_api;
+#[cfg(feature = "ws")]
+pub mod subscriptions;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "async")]
+pub mod async_chain;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_support;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+
+pub use address::validate_address;
+pub use block::Block;
+pub use blockchain::{Blockchain, ConsensusAlgorithm};
+pub use builder::BlockchainBuilder;
+pub use errors::BlockchainError;
+pub use signing::{Keypair, PublicKey};
+pub use transaction::{Transaction, TransactionType};
+pub use utxo::{LedgerMode, OutPoint, TxInput, TxOutput};
+pub use wallet::Wallet;
+
+/// Запускает командную строку `hellochain`
+pub fn run() -> std::process::ExitCode {
+    trace::init_subscriber();
+    cli::run()
+}
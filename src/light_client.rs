@@ -0,0 +1,272 @@
+#![deny(clippy::indexing_slicing)]
+
+use crate::block::BlockHeader;
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::merkle::{leaf_hash, MerkleProof};
+use crate::sync::{header_work, validate_header};
+use crate::transaction::Transaction;
+use chrono::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Насколько заголовок может опережать локальные часы, в секундах
+pub const MAX_FUTURE_DRIFT_SECS: i64 = 2 * 60 * 60;
+
+/// Сколько заголовков запрашивать у источника за раз
+const HEADER_BATCH: u64 = 500;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LightClientError {
+    #[error("Unknown parent {0}")]
+    UnknownParent(String),
+    
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+    
+    #[error("Header {0} is not on the best chain")]
+    NotOnBestChain(String),
+    
+    #[error("Invalid inclusion proof: {0}")]
+    InvalidProof(String),
+}
+
+/// Утверждение о включении транзакции (и её данных) в блок
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub block_hash: String,
+    pub block_index: u64,
+    pub transaction: Transaction,
+    pub proof: MerkleProof,
+}
+
+/// Источник заголовков для легкого клиента
+pub trait HeaderSource {
+    /// Возвращает до `count` заголовков начиная с высоты `from`
+    fn headers(&self, from: u64, count: u64) -> Vec<BlockHeader>;
+}
+
+impl HeaderSource for Blockchain {
+    fn headers(&self, from: u64, count: u64) -> Vec<BlockHeader> {
+        self.chain.iter()
+            .skip(from as usize)
+            .take(count as usize)
+            .map(|block| block.header())
+            .collect()
+    }
+}
+
+impl Blockchain {
+    /// Готовит доказательство включения подтвержденной транзакции для легкого клиента
+    pub fn inclusion_proof(&self, tx_id: &str) -> Option<InclusionProof> {
+        self.chain.iter().find_map(|block| {
+            let proof = block.merkle_proof(tx_id)?;
+            Some(InclusionProof {
+                block_hash: block.hash.clone(),
+                block_index: block.index,
+                transaction: block.transactions.get(proof.leaf_index)?.clone(),
+                proof,
+            })
+        })
+    }
+}
+
+/// Легкий клиент: хранит только заголовки и проверяет включение по доказательствам Меркла
+pub struct LightClient {
+    consensus: ConsensusAlgorithm,
+    /// Все принятые заголовки по хешу вместе с накопленной работой от контрольной точки
+    known: HashMap<String, (BlockHeader, u128)>,
+    /// Лучшая цепочка от контрольной точки до вершины
+    best_chain: Vec<BlockHeader>,
+}
+
+impl LightClient {
+    /// Создает клиента, доверяющего заголовку генезиса или контрольной точке
+    pub fn new(checkpoint: BlockHeader, consensus: ConsensusAlgorithm) -> Self {
+        let mut known = HashMap::new();
+        known.insert(checkpoint.hash.clone(), (checkpoint.clone(), 0));
+        
+        LightClient {
+            consensus,
+            known,
+            best_chain: vec![checkpoint],
+        }
+    }
+    
+    /// Возвращает вершину лучшей цепочки заголовков; цепочка всегда начинается с контрольной точки
+    pub fn tip(&self) -> &BlockHeader {
+        self.best_chain.last().expect("the best chain always starts at the checkpoint")
+    }
+    
+    /// Возвращает заголовок лучшей цепочки на указанной высоте
+    pub fn header_at(&self, height: u64) -> Option<&BlockHeader> {
+        let offset = height.checked_sub(self.best_chain.first()?.index)?;
+        self.best_chain.get(offset as usize)
+    }
+    
+    /// Проверяет и принимает заголовок; возвращает true, если он стал новой вершиной
+    pub fn apply_header(&mut self, header: BlockHeader) -> Result<bool, LightClientError> {
+        if self.known.contains_key(&header.hash) {
+            return Ok(false);
+        }
+        
+        let (parent, parent_work) = self.known.get(&header.previous_hash)
+            .ok_or_else(|| LightClientError::UnknownParent(header.previous_hash.clone()))?;
+        
+        validate_header(parent, &header, &self.consensus).map_err(LightClientError::InvalidHeader)?;
+        
+        if header.timestamp < parent.timestamp {
+            return Err(LightClientError::InvalidHeader(format!("header {} is older than its parent", header.index)));
+        }
+        if header.timestamp > Utc::now().timestamp() + MAX_FUTURE_DRIFT_SECS {
+            return Err(LightClientError::InvalidHeader(format!("header {} is too far in the future", header.index)));
+        }
+        
+        let work = parent_work.saturating_add(header_work(&header, &self.consensus));
+        let best_work = self.known.get(&self.tip().hash).map_or(0, |(_, work)| *work);
+        self.known.insert(header.hash.clone(), (header.clone(), work));
+        
+        if work <= best_work {
+            return Ok(false);
+        }
+        
+        if header.previous_hash == self.tip().hash {
+            self.best_chain.push(header);
+        } else {
+            self.reorganize_to(&header.hash);
+        }
+        
+        Ok(true)
+    }
+    
+    /// Перестраивает лучшую цепочку так, чтобы она заканчивалась указанным заголовком
+    fn reorganize_to(&mut self, tip_hash: &str) {
+        let mut branch = Vec::new();
+        let mut hash = tip_hash.to_string();
+        let Some(checkpoint_index) = self.best_chain.first().map(|checkpoint| checkpoint.index) else {
+            return;
+        };
+        
+        while let Some((header, _)) = self.known.get(&hash) {
+            branch.push(header.clone());
+            if header.index == checkpoint_index {
+                break;
+            }
+            hash = header.previous_hash.clone();
+        }
+        
+        if !branch.is_empty() {
+            branch.reverse();
+            self.best_chain = branch;
+        }
+    }
+    
+    /// Догружает заголовки из источника до его вершины; возвращает число принятых
+    pub fn sync_from(&mut self, source: &dyn HeaderSource) -> Result<u64, LightClientError> {
+        let mut applied = 0;
+        
+        loop {
+            let headers = source.headers(self.tip().index + 1, HEADER_BATCH);
+            if headers.is_empty() {
+                return Ok(applied);
+            }
+            
+            for header in headers {
+                if self.apply_header(header)? {
+                    applied += 1;
+                }
+            }
+        }
+    }
+    
+    /// Проверяет, что транзакция включена в блок лучшей цепочки
+    pub fn verify_inclusion(&self, bundle: &InclusionProof) -> Result<(), LightClientError> {
+        let header = self.header_at(bundle.block_index)
+            .filter(|header| header.hash == bundle.block_hash)
+            .ok_or_else(|| LightClientError::NotOnBestChain(bundle.block_hash.clone()))?;
+        
+        if bundle.proof.leaf_hash != leaf_hash(&bundle.transaction) {
+            return Err(LightClientError::InvalidProof("leaf does not match the transaction".to_string()));
+        }
+        if !bundle.proof.verify(header.hash_algorithm, &header.merkle_root) {
+            return Err(LightClientError::InvalidProof("merkle root mismatch".to_string()));
+        }
+        
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    /// PoW-цепочка из 50 блоков с переводом от `alice` к `bob` в блоке 2
+    fn long_chain() -> (Blockchain, String) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx).unwrap();
+        while chain.chain.len() <= 50 {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        (chain, tx_id)
+    }
+    
+    fn client_for(chain: &Blockchain) -> LightClient {
+        let genesis = chain.get_block_by_index(0).unwrap().header();
+        LightClient::new(genesis, ConsensusAlgorithm::ProofOfWork)
+    }
+    
+    #[test]
+    fn follows_headers_and_checks_inclusion() {
+        let (chain, tx_id) = long_chain();
+        let mut client = client_for(&chain);
+        for header in chain.headers(1, 50) {
+            assert!(client.apply_header(header).unwrap());
+        }
+        assert_eq!(client.tip(), &chain.get_latest_block().header());
+        
+        let proof = chain.inclusion_proof(&tx_id).unwrap();
+        assert_eq!(proof.block_index, 2);
+        assert_eq!(client.verify_inclusion(&proof), Ok(()));
+        
+        let mut forged = proof.clone();
+        forged.transaction.amount = 1000.0;
+        assert!(matches!(client.verify_inclusion(&forged), Err(LightClientError::InvalidProof(_))));
+    }
+    
+    #[test]
+    fn proof_from_a_fork_is_not_on_the_best_chain() {
+        let (chain, _) = long_chain();
+        let mut fork: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        fork.mine_pending_transactions("rival".to_string()).unwrap();
+        let fork_block = fork.get_latest_block().clone();
+        let reward = fork_block.transactions.iter().find(|tx| tx.receiver == "rival").unwrap();
+        let proof = fork.inclusion_proof(&reward.id).unwrap();
+        
+        let mut client = client_for(&chain);
+        assert_eq!(client.sync_from(&chain), Ok(50));
+        assert_eq!(client.verify_inclusion(&proof), Err(LightClientError::NotOnBestChain(fork_block.hash)));
+    }
+    
+    #[test]
+    fn header_without_its_parent_is_refused() {
+        let (chain, _) = long_chain();
+        let mut client = client_for(&chain);
+        let headers = chain.headers(1, 3);
+        let (first, rest) = headers.split_first().unwrap();
+        
+        for header in rest {
+            assert_eq!(client.apply_header(header.clone()), Err(LightClientError::UnknownParent(header.previous_hash.clone())));
+        }
+        assert!(client.apply_header(first.clone()).unwrap());
+        assert_eq!(client.tip().index, 1);
+    }
+}
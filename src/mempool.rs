@@ -0,0 +1,315 @@
+use crate::assets::asset_debit;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::events::ChainEvent;
+use crate::reserved::QueuedSystemTransaction;
+use crate::rewards::{balance_debit, claim_debit};
+use crate::sponsorship::fee_payer_debit;
+use crate::supply::is_system_sender;
+use crate::transaction::Transaction;
+use crate::utxo::LedgerMode;
+use serde::{Serialize, Deserialize, Deserializer, Serializer};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// Ожидающие транзакции в порядке приема, родители тех, что тратят еще не подтвержденные
+/// зачисления, и предельный размер. В цепочке хранится под прежними именами полей.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Pool {
+    #[serde(rename = "pending_transactions")]
+    pub(crate) transactions: Vec<Transaction>,
+    #[serde(rename = "max_pending_transactions")]
+    pub(crate) capacity: Option<usize>,
+    /// Родители транзакций пула, которые тратят еще не подтвержденные зачисления, по идентификаторам потомков
+    #[serde(rename = "pending_parents", default)]
+    pub(crate) parents: HashMap<String, Vec<String>>,
+    /// Системные транзакции пула в порядке поступления, для которых держится место в блоке
+    #[serde(default)]
+    pub(crate) system_queue: Vec<QueuedSystemTransaction>,
+}
+
+impl Pool {
+    pub(crate) fn contains(&self, tx_id: &str) -> bool {
+        self.transactions.iter().any(|tx| tx.id == tx_id)
+    }
+    
+    /// Убирает транзакцию из пула вместе с ее родителями и местом в очереди резерва
+    pub(crate) fn remove(&mut self, tx_id: &str) -> Option<Transaction> {
+        let position = self.transactions.iter().position(|tx| tx.id == tx_id)?;
+        self.parents.remove(tx_id);
+        self.system_queue.retain(|queued| queued.tx_id != tx_id);
+        Some(self.transactions.remove(position))
+    }
+}
+
+/// Пул ожидающих транзакций со своей блокировкой. Пул ничего не списывает: средства под его
+/// транзакции заняты, пока блок не спишет их сам, поэтому прием транзакции держит цепочку только
+/// на чтение и идет параллельно с майнингом и проверкой блоков. Блок убирает из пула вошедшие
+/// в него транзакции под той же записью, что его применяет, так что транзакция, принятая во время
+/// майнинга, не теряется и не попадает в цепочку дважды.
+///
+/// Порядок блокировок — сначала цепочка, потом пул; под блокировкой пула цепочку не берут.
+#[derive(Default)]
+pub struct Mempool {
+    pool: Mutex<Pool>,
+}
+
+impl Mempool {
+    /// Блокирует пул; паника другого потока не теряет принятые транзакции
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Pool> {
+        self.pool.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    /// Пул без блокировки, когда цепочка и так принадлежит вызывающему целиком
+    pub(crate) fn get_mut(&mut self) -> &mut Pool {
+        self.pool.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    /// Число ожидающих транзакций
+    pub fn len(&self) -> usize {
+        self.lock().transactions.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.lock().transactions.is_empty()
+    }
+    
+    /// Копия пула в порядке приема
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.lock().transactions.clone()
+    }
+    
+    /// Ждет ли транзакция в пуле
+    pub fn contains(&self, tx_id: &str) -> bool {
+        self.lock().contains(tx_id)
+    }
+    
+    /// Копия ожидающей транзакции
+    pub fn get(&self, tx_id: &str) -> Option<Transaction> {
+        self.lock().transactions.iter().find(|tx| tx.id == tx_id).cloned()
+    }
+    
+    /// Сколько транзакций принимает пул; None — без ограничения
+    pub fn capacity(&self) -> Option<usize> {
+        self.lock().capacity
+    }
+    
+    pub fn set_capacity(&self, capacity: Option<usize>) {
+        self.lock().capacity = capacity;
+    }
+}
+
+impl Serialize for Mempool {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.lock().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Mempool {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Pool::deserialize(deserializer).map(|pool| Mempool { pool: Mutex::new(pool) })
+    }
+}
+
+/// Сколько основного баланса адреса заняли транзакции пула как отправитель или спонсор
+pub(crate) fn reserved_balance(pending: &[Transaction], address: &str) -> f64 {
+    let as_sender: f64 = pending.iter().filter(|tx| tx.sender == address).map(balance_debit).sum();
+    let as_payer: f64 = pending.iter().filter_map(fee_payer_debit).filter(|(payer, _)| *payer == address).map(|(_, fee)| fee).sum();
+    as_sender + as_payer
+}
+
+/// Сколько единиц актива адреса заняли транзакции пула
+pub(crate) fn reserved_asset(pending: &[Transaction], address: &str, asset_id: &str) -> u64 {
+    pending.iter()
+        .filter(|tx| tx.sender == address)
+        .filter_map(asset_debit)
+        .filter(|(id, _)| *id == asset_id)
+        .map(|(_, amount)| amount)
+        .sum()
+}
+
+/// Сколько накопленных наград адреса заняли транзакции пула
+pub(crate) fn reserved_claim(pending: &[Transaction], address: &str) -> f64 {
+    pending.iter().filter(|tx| tx.sender == address).filter_map(claim_debit).sum()
+}
+
+impl Blockchain {
+    /// Пул ожидающих транзакций
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
+    }
+    
+    /// Проверяет транзакцию, как `add_transaction`, и ставит ее в пул; цепочку достаточно держать
+    /// на чтение. Проверка и постановка идут под одной блокировкой пула, поэтому две транзакции
+    /// не займут одни и те же средства или один номер.
+    pub fn submit_transaction(&self, transaction: Transaction) -> Result<(), BlockchainError> {
+        self.try_submit_transaction(transaction).map_err(|(error, _)| error)
+    }
+    
+    /// Как `submit_transaction`, но отклоненная транзакция возвращается вместе с ошибкой
+    pub(crate) fn try_submit_transaction(&self, transaction: Transaction) -> Result<(), (BlockchainError, Box<Transaction>)> {
+        let event = {
+            let mut pool = self.mempool.lock();
+            let checked = match self.ledger_mode {
+                LedgerMode::Account => self.check_queued_transaction(&transaction, &pool),
+                LedgerMode::Utxo => self.check_queued_utxo_transaction(&transaction, &pool),
+            };
+            if let Err(error) = checked {
+                return Err((error, Box::new(transaction)));
+            }
+            let event = ChainEvent::TransactionQueued { transaction: Box::new(transaction.clone()) };
+            self.insert_pending(&mut pool, transaction);
+            event
+        };
+        self.events.emit(&event);
+        Ok(())
+    }
+    
+    /// Ставит проверенную транзакцию в конец пула, запоминая транзакции пула, на зачисления
+    /// которых она рассчитывает, и место в очереди резерва
+    pub(crate) fn insert_pending(&self, pool: &mut Pool, transaction: Transaction) {
+        if self.ledger_mode == LedgerMode::Account && !is_system_sender(&transaction.sender) {
+            let parents = self.funding_parents(&transaction, &pool.transactions);
+            if !parents.is_empty() {
+                pool.parents.insert(transaction.id.clone(), parents);
+            }
+        }
+        self.enqueue_system_transaction(pool, &transaction);
+        pool.transactions.push(transaction);
+    }
+    
+    /// Отклоняет транзакцию, которая уже ждет в пуле; повтор отклонила бы и проверка номера,
+    /// но по идентификатору ошибка точнее
+    pub(crate) fn check_not_pending(&self, transaction: &Transaction, pool: &Pool) -> Result<(), BlockchainError> {
+        if pool.contains(&transaction.id) {
+            return Err(BlockchainError::DuplicateTransaction { tx_id: transaction.id.clone() });
+        }
+        Ok(())
+    }
+    
+    /// Отклоняет транзакцию, если пул заполнен
+    pub(crate) fn check_capacity(&self, pool: &Pool) -> Result<(), BlockchainError> {
+        match pool.capacity {
+            Some(capacity) if pool.transactions.len() >= capacity => Err(BlockchainError::MempoolFull { capacity }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::BlockchainBuilder;
+    use crate::mining::MiningError;
+    use crate::shared::SharedBlockchain;
+    use crate::signing::Keypair;
+    use crate::target::CompactTarget;
+    use crate::transaction::TransactionType;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    
+    const SENDERS: usize = 4;
+    const TRANSFERS_PER_SENDER: usize = 10;
+    
+    #[test]
+    fn concurrent_submissions_are_visible_and_mined_exactly_once() {
+        let mut chain = BlockchainBuilder::new().difficulty(1).accept_legacy_addresses(true).build().unwrap();
+        let senders: Vec<String> = (0..SENDERS).map(|i| format!("sender{}", i)).collect();
+        for sender in &senders {
+            chain.create_wallet(sender.clone()).unwrap();
+            chain.mine_pending_transactions(sender.clone()).unwrap();
+        }
+        let keypairs: Vec<Keypair> = senders.iter().map(|sender| chain.get_wallet_info(sender).unwrap().keypair().unwrap().clone()).collect();
+        let shared = SharedBlockchain::new(chain);
+        let done = AtomicBool::new(false);
+        
+        let submitted: Vec<String> = thread::scope(|scope| {
+            let (shared, done) = (&shared, &done);
+            let miner = scope.spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    shared.mine_block("miner".to_string()).unwrap();
+                }
+            });
+            let submitters: Vec<_> = senders.iter().zip(&keypairs).map(|(sender, keypair)| scope.spawn(move || {
+                let mut ids = Vec::new();
+                for _ in 0..TRANSFERS_PER_SENDER {
+                    let nonce = shared.read().get_account_nonce(sender);
+                    let mut tx = Transaction::new(sender.clone(), "bob".to_string(), 1.0, TransactionType::Transfer);
+                    tx.set_nonce(nonce);
+                    tx.sign(keypair);
+                    shared.add_transaction(tx.clone()).unwrap();
+                    
+                    // После возврата транзакцию видит любое чтение цепочки
+                    let chain = shared.read();
+                    assert!(chain.get_account_nonce(sender) > nonce);
+                    assert!(chain.mempool().transactions().iter().any(|pending| pending.id == tx.id) || chain.find_transaction(&tx.id).is_some());
+                    ids.push(tx.id);
+                }
+                ids
+            })).collect();
+            let ids = submitters.into_iter().flat_map(|submitter| submitter.join().unwrap()).collect();
+            done.store(true, Ordering::Relaxed);
+            miner.join().unwrap();
+            ids
+        });
+        
+        while !shared.read().mempool().is_empty() {
+            shared.mine_block("miner".to_string()).unwrap();
+        }
+        let chain = shared.read();
+        assert!(chain.mempool().is_empty());
+        for tx_id in &submitted {
+            let included = chain.chain.iter().flat_map(|block| &block.transactions).filter(|tx| &tx.id == tx_id).count();
+            assert_eq!(included, 1, "{}", tx_id);
+        }
+        assert_eq!(chain.get_balance("bob"), (SENDERS * TRANSFERS_PER_SENDER) as f64);
+        assert!(chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn submissions_during_mining_do_not_wait_for_the_block() {
+        let mut chain = BlockchainBuilder::new().difficulty(1).accept_legacy_addresses(true).build().unwrap();
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        // Недостижимая сложность: перебор идет, пока его не отменят
+        chain.difficulty = 12;
+        chain.target = Some(CompactTarget::from_difficulty(12));
+        chain.mining_threads = Some(1);
+        let shared = SharedBlockchain::new(chain);
+        
+        let handle = shared.start_mining("miner".to_string());
+        while handle.progress().attempts == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let (first, balance) = (shared.read().get_account_nonce("alice"), shared.read().get_balance("alice"));
+        let mut submitted = Vec::new();
+        let mut spent = 0.0;
+        for nonce in first..first + 3 {
+            let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 1.0, TransactionType::Transfer);
+            tx.set_nonce(nonce);
+            tx.sign(&keypair);
+            shared.add_transaction(tx.clone()).unwrap();
+            spent += tx.amount + tx.fee;
+            submitted.push(tx.id);
+        }
+        assert!(!handle.is_finished());
+        assert_eq!(shared.read().mempool().len(), 3);
+        assert!((shared.read().get_balance("alice") - (balance - spent)).abs() < 1e-9);
+        
+        handle.cancel();
+        assert!(matches!(handle.join(), Err(MiningError::Cancelled)));
+        {
+            let mut chain = shared.write();
+            chain.difficulty = 1;
+            chain.target = Some(CompactTarget::from_difficulty(1));
+        }
+        let block = shared.mine_block("miner".to_string()).unwrap();
+        let included: Vec<&String> = block.transactions.iter().map(|tx| &tx.id).filter(|id| submitted.contains(id)).collect();
+        assert_eq!(included, submitted.iter().collect::<Vec<_>>());
+        assert!(shared.read().mempool().is_empty());
+        assert_eq!(shared.read().get_balance("bob"), 3.0);
+        assert!(shared.read().is_chain_valid());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::block::Block;
+use crate::hashing::HashKind;
+use crate::transaction::{Hash, HashWriter, Transaction, hash_bytes};
+use serde::{Serialize, Deserialize};
+
+/// С какой стороны от текущего узла стоит соседний хеш
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Шаг доказательства: соседний хеш на очередном уровне дерева
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub hash: String,
+    pub side: Side,
+}
+
+/// Доказательство включения листа в дерево Меркла
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub leaf_hash: String,
+    pub steps: Vec<ProofStep>,
+}
+
+/// Хеш родительского узла по хешам двух детей
+pub fn hash_pair(hash_algorithm: HashKind, left: &str, right: &str) -> String {
+    let mut hasher = HashWriter::new(hash_algorithm);
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hasher.finish_hex()
+}
+
+/// Хеш родительского узла по двоичным хешам детей. Дети хешируются в шестнадцатеричной записи,
+/// как в `hash_pair`, чтобы корни совпадали с корнями уже записанных блоков.
+pub fn hash_pair_digest(hash_algorithm: HashKind, left: &Hash, right: &Hash) -> Hash {
+    let mut hex = [0u8; 128];
+    let _ = hex::encode_to_slice(left, &mut hex[..64]);
+    let _ = hex::encode_to_slice(right, &mut hex[64..]);
+    hash_bytes(hash_algorithm, &hex)
+}
+
+/// Хеш листа дерева Меркла; покрывает транзакцию целиком, включая тип и данные
+pub fn leaf_hash(tx: &Transaction) -> String {
+    hex::encode(leaf_digest(tx))
+}
+
+/// Двоичный хеш листа дерева Меркла алгоритмом, которым посчитан идентификатор транзакции
+pub fn leaf_digest(tx: &Transaction) -> Hash {
+    let mut hasher = HashWriter::new(tx.hash_algorithm);
+    if serde_json::to_writer(&mut hasher, tx).is_err() {
+        return hash_bytes(tx.hash_algorithm, b"");
+    }
+    hasher.finish()
+}
+
+/// Корень дерева Меркла; для пустого списка None. Уровни считаются на месте в одном буфере:
+/// родитель i записывается поверх ребенка i, который к этому моменту уже прочитан.
+pub fn merkle_root_digest(hash_algorithm: HashKind, transactions: &[Transaction]) -> Option<Hash> {
+    let mut nodes: Vec<Hash> = transactions.iter().map(leaf_digest).collect();
+    let mut len = nodes.len();
+    
+    while len > 1 {
+        let parents = len.div_ceil(2);
+        for parent in 0..parents {
+            let left = 2 * parent;
+            // Непарный последний узел переходит на следующий уровень без хеширования
+            nodes[parent] = if left + 1 < len { hash_pair_digest(hash_algorithm, &nodes[left], &nodes[left + 1]) } else { nodes[left] };
+        }
+        len = parents;
+    }
+    
+    nodes.first().copied()
+}
+
+/// Дерево Меркла с сохраненными уровнями: добавление и удаление последнего листа пересчитывают
+/// только путь до корня. Корень совпадает с `Block::calculate_merkle_root`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IncrementalMerkle {
+    hash_algorithm: HashKind,
+    /// Уровни снизу вверх; нулевой уровень — хеши листьев
+    levels: Vec<Vec<Hash>>,
+}
+
+impl IncrementalMerkle {
+    pub fn new(hash_algorithm: HashKind) -> Self {
+        IncrementalMerkle { hash_algorithm, levels: vec![Vec::new()] }
+    }
+    
+    pub fn from_transactions(hash_algorithm: HashKind, transactions: &[Transaction]) -> Self {
+        let mut tree = IncrementalMerkle::new(hash_algorithm);
+        for tx in transactions {
+            tree.push(tx);
+        }
+        tree
+    }
+    
+    pub fn hash_algorithm(&self) -> HashKind {
+        self.hash_algorithm
+    }
+    
+    pub fn len(&self) -> usize {
+        self.levels.first().map_or(0, Vec::len)
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    pub fn push(&mut self, tx: &Transaction) {
+        self.push_leaf(leaf_digest(tx));
+    }
+    
+    pub fn push_leaf(&mut self, hash: Hash) {
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+        self.levels[0].push(hash);
+        self.update_last_path();
+    }
+    
+    /// Удаляет последний лист и возвращает его хеш
+    pub fn pop(&mut self) -> Option<Hash> {
+        let leaf = self.levels.first_mut()?.pop()?;
+        self.update_last_path();
+        Some(leaf)
+    }
+    
+    /// Корень дерева; для пустого дерева "0", как у блока без транзакций
+    pub fn root(&self) -> String {
+        self.root_digest().map_or_else(|| String::from("0"), hex::encode)
+    }
+    
+    pub fn root_digest(&self) -> Option<Hash> {
+        self.levels.last().and_then(|level| level.first()).filter(|_| !self.is_empty()).copied()
+    }
+    
+    /// Пересчитывает родителей последнего узла на каждом уровне и обрезает лишние узлы и уровни
+    fn update_last_path(&mut self) {
+        let mut level = 0;
+        loop {
+            let len = self.levels[level].len();
+            if len <= 1 {
+                self.levels.truncate(level + 1);
+                return;
+            }
+            
+            let parent_len = len.div_ceil(2);
+            let parent = parent_len - 1;
+            let children = &self.levels[level];
+            let hash = match children.get(2 * parent + 1) {
+                Some(right) => hash_pair_digest(self.hash_algorithm, &children[2 * parent], right),
+                None => children[2 * parent],
+            };
+            
+            if self.levels.len() == level + 1 {
+                self.levels.push(Vec::new());
+            }
+            let parents = &mut self.levels[level + 1];
+            parents.truncate(parent_len);
+            if parents.len() == parent_len {
+                parents[parent] = hash;
+            } else {
+                parents.push(hash);
+            }
+            level += 1;
+        }
+    }
+}
+
+impl MerkleProof {
+    /// Строит доказательство для листа с указанным номером
+    pub fn build(hash_algorithm: HashKind, leaves: &[String], leaf_index: usize) -> Option<MerkleProof> {
+        let leaf_hash = leaves.get(leaf_index)?.clone();
+        let mut steps = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut index = leaf_index;
+        
+        while level.len() > 1 {
+            // Непарный последний узел переходит на следующий уровень без хеширования
+            if index % 2 == 1 {
+                steps.push(ProofStep { hash: level[index - 1].clone(), side: Side::Left });
+            } else if index + 1 < level.len() {
+                steps.push(ProofStep { hash: level[index + 1].clone(), side: Side::Right });
+            }
+            
+            level = level.chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(hash_algorithm, left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            index /= 2;
+        }
+        
+        Some(MerkleProof { leaf_index, leaf_hash, steps })
+    }
+    
+    /// Вычисляет корень, к которому ведет доказательство
+    pub fn compute_root(&self, hash_algorithm: HashKind) -> String {
+        self.steps.iter().fold(self.leaf_hash.clone(), |current, step| match step.side {
+            Side::Left => hash_pair(hash_algorithm, &step.hash, &current),
+            Side::Right => hash_pair(hash_algorithm, &current, &step.hash),
+        })
+    }
+    
+    /// Проверяет доказательство против корня из заголовка, посчитанного алгоритмом `hash_algorithm`
+    pub fn verify(&self, hash_algorithm: HashKind, merkle_root: &str) -> bool {
+        self.compute_root(hash_algorithm) == merkle_root
+    }
+}
+
+impl Block {
+    /// Строит доказательство включения транзакции в этот блок
+    pub fn merkle_proof(&self, tx_id: &str) -> Option<MerkleProof> {
+        let position = self.transactions.iter().position(|tx| tx.id == tx_id)?;
+        let leaves: Vec<String> = self.transactions.iter().map(leaf_hash).collect();
+        MerkleProof::build(self.hash_algorithm, &leaves, position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TransactionType;
+    use proptest::prelude::*;
+    
+    fn transfer(amount: u16) -> Transaction {
+        Transaction::new("alice".to_string(), "bob".to_string(), f64::from(amount), TransactionType::Transfer)
+    }
+    
+    #[test]
+    fn matches_the_reference_for_every_size() {
+        let transactions: Vec<Transaction> = (0..=65).map(transfer).collect();
+        let mut tree = IncrementalMerkle::default();
+        assert_eq!(tree.root(), Block::calculate_merkle_root(HashKind::Sha256, &[]));
+        
+        for len in 1..=transactions.len() {
+            tree.push(&transactions[len - 1]);
+            assert_eq!(tree.root(), Block::calculate_merkle_root(HashKind::Sha256, &transactions[..len]), "{} leaves", len);
+            assert_eq!(IncrementalMerkle::from_transactions(HashKind::Sha256, &transactions[..len]), tree);
+        }
+        for len in (0..transactions.len()).rev() {
+            assert_eq!(tree.pop(), Some(leaf_digest(&transactions[len])));
+            assert_eq!(tree.root(), Block::calculate_merkle_root(HashKind::Sha256, &transactions[..len]), "{} leaves", len);
+        }
+        assert_eq!(tree.pop(), None);
+        assert!(tree.is_empty());
+    }
+    
+    /// Корень по определению: уровни из hex-строк, непарный последний узел переходит выше как есть
+    fn reference_root(transactions: &[Transaction]) -> String {
+        let mut level: Vec<String> = transactions.iter().map(leaf_hash).collect();
+        while level.len() > 1 {
+            level = level.chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(HashKind::Sha256, left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        level.pop().unwrap_or_else(|| String::from("0"))
+    }
+    
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+        
+        #[test]
+        fn root_matches_the_string_reference(amounts in prop::collection::vec(any::<u16>(), 0..=300)) {
+            let transactions: Vec<Transaction> = amounts.into_iter().map(transfer).collect();
+            let root = Block::calculate_merkle_root(HashKind::Sha256, &transactions);
+            prop_assert_eq!(&root, &reference_root(&transactions));
+            prop_assert_eq!(merkle_root_digest(HashKind::Sha256, &transactions).map(hex::encode), (!transactions.is_empty()).then_some(root));
+        }
+        
+        /// `Some` добавляет лист, `None` удаляет последний
+        #[test]
+        fn random_pushes_and_pops_match_the_reference(ops in prop::collection::vec(prop::option::weighted(0.8, any::<u16>()), 0..=200)) {
+            let mut transactions = Vec::new();
+            let mut tree = IncrementalMerkle::default();
+            for op in ops {
+                match op {
+                    Some(amount) => {
+                        let tx = transfer(amount);
+                        tree.push(&tx);
+                        transactions.push(tx);
+                    },
+                    None => prop_assert_eq!(tree.pop(), transactions.pop().as_ref().map(leaf_digest)),
+                }
+                prop_assert_eq!(tree.len(), transactions.len());
+                prop_assert_eq!(tree.root(), Block::calculate_merkle_root(HashKind::Sha256, &transactions));
+            }
+        }
+    }
+}
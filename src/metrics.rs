@@ -0,0 +1,324 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::shared::SharedBlockchain;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Гистограмма с фиксированными границами корзин
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// Верхние границы корзин по возрастанию
+    pub bounds: Vec<f64>,
+    /// Накопительные счетчики для каждой границы, как в формате Prometheus
+    pub cumulative_counts: Vec<u64>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Histogram {
+            bounds: bounds.to_vec(),
+            cumulative_counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+    
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.cumulative_counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Метрики, которые нельзя вычислить по текущему состоянию цепочки
+#[derive(Debug)]
+pub struct Metrics {
+    block_interval: Histogram,
+    mining_duration: Histogram,
+    hash_attempts: Histogram,
+    hash_rate: Histogram,
+    transactions_per_block: Histogram,
+    validation_failures: BTreeMap<&'static str, u64>,
+    peers: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics {
+            block_interval: Histogram::new(&[1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0]),
+            mining_duration: Histogram::new(&[0.001, 0.01, 0.1, 0.5, 1.0, 5.0, 15.0, 60.0]),
+            hash_attempts: Histogram::new(&[1.0, 16.0, 256.0, 4096.0, 65536.0, 1048576.0, 16777216.0]),
+            hash_rate: Histogram::new(&[1e3, 1e4, 1e5, 1e6, 1e7, 1e8]),
+            transactions_per_block: Histogram::new(&[1.0, 2.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0]),
+            validation_failures: BTreeMap::new(),
+            peers: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Снимок всех метрик узла
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub height: u64,
+    pub pending_transactions: usize,
+    pub mempool_bytes: usize,
+    pub last_block_timestamp: i64,
+    pub block_interval_seconds: Histogram,
+    pub mining_duration_seconds: Histogram,
+    pub hash_attempts: Histogram,
+    pub hash_rate: Histogram,
+    pub transactions_per_block: Histogram,
+    pub validation_failures: BTreeMap<String, u64>,
+    pub peers: u64,
+}
+
+/// Метка причины отказа для счетчика ошибок валидации
+fn failure_reason(error: &BlockchainError) -> &'static str {
+    match error {
+        BlockchainError::InsufficientBalance { .. } => "insufficient_balance",
+        BlockchainError::InvalidTransaction(_) => "invalid_transaction",
+        BlockchainError::InvalidBlock(_) => "invalid_block",
+        BlockchainError::ConsensusError(_) => "consensus",
+        BlockchainError::MempoolFull { .. } => "mempool_full",
+        BlockchainError::Storage(_) => "storage",
+        BlockchainError::MalformedTransaction { .. } => "malformed_transaction",
+        BlockchainError::RateLimited { .. } => "rate_limited",
+        BlockchainError::FaucetDepleted { .. } => "faucet_depleted",
+        BlockchainError::WalletNotFound { .. } => "wallet_not_found",
+        BlockchainError::ValidatorNotFound { .. } => "validator_not_found",
+        BlockchainError::ValidatorJailed { .. } => "validator_jailed",
+        BlockchainError::NotScheduledProducer { .. } => "not_scheduled_producer",
+        BlockchainError::InvalidSignature { .. } => "invalid_signature",
+        BlockchainError::DuplicateTransaction { .. } => "duplicate_transaction",
+        BlockchainError::TransactionNotFound { .. } => "transaction_not_found",
+        BlockchainError::FeeTooLow { .. } => "fee_too_low",
+        BlockchainError::WrongChainId { .. } => "wrong_chain_id",
+        BlockchainError::AccountFrozen { .. } => "account_frozen",
+        BlockchainError::FeatureDisabled { .. } => "feature_disabled",
+        BlockchainError::ContractError { .. } => "contract_error",
+        BlockchainError::ChainNotFound { .. } => "chain_not_found",
+        BlockchainError::DuplicateChain { .. } => "duplicate_chain",
+        BlockchainError::WalletClosed { .. } => "wallet_closed",
+        BlockchainError::StaleTemplate { .. } => "stale_template",
+        BlockchainError::WrongLedgerMode { .. } => "wrong_ledger_mode",
+        BlockchainError::WrongNetwork { .. } => "wrong_network",
+        BlockchainError::DuplicateWallet { .. } => "duplicate_wallet",
+        BlockchainError::HashAlgorithmMismatch { .. } => "hash_algorithm_mismatch",
+        BlockchainError::InvalidNonce { .. } => "invalid_nonce",
+        BlockchainError::InvalidConfig(_) => "invalid_config",
+    }
+}
+
+impl Blockchain {
+    /// Учитывает только что добавленный в цепочку блок
+    pub(crate) fn record_appended_block(&mut self) {
+        let block = self.get_latest_block();
+        // Время генезиса фиксировано, поэтому интервал после него не показателен
+        let interval = self.chain.len().checked_sub(2)
+            .filter(|parent| *parent > 0)
+            .map(|parent| (block.timestamp - self.chain[parent].timestamp).max(0) as f64);
+        let transactions = block.transactions.len() as f64;
+        
+        if let Some(interval) = interval {
+            self.metrics.block_interval.observe(interval);
+        }
+        self.metrics.transactions_per_block.observe(transactions);
+    }
+    
+    /// Учитывает затраты на поиск печати для смайненного блока
+    pub(crate) fn record_mining(&mut self, hash_attempts: Option<u64>, duration: Duration) {
+        if let Some(attempts) = hash_attempts {
+            self.metrics.hash_attempts.observe(attempts as f64);
+            if duration > Duration::ZERO {
+                self.metrics.hash_rate.observe(attempts as f64 / duration.as_secs_f64());
+            }
+        }
+        self.metrics.mining_duration.observe(duration.as_secs_f64());
+    }
+    
+    /// Учитывает отклоненную транзакцию или блок
+    pub(crate) fn record_validation_failure(&mut self, error: &BlockchainError) {
+        *self.metrics.validation_failures.entry(failure_reason(error)).or_default() += 1;
+    }
+    
+    /// Обновляет число подключенных пиров
+    pub(crate) fn set_peer_count(&self, peers: usize) {
+        self.metrics.peers.store(peers as u64, Ordering::Relaxed);
+    }
+    
+    /// Возвращает снимок метрик узла
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        let pending = self.mempool.transactions();
+        let mempool_bytes = pending.iter()
+            .map(|tx| serde_json::to_vec(tx).map(|bytes| bytes.len()).unwrap_or_default())
+            .sum();
+        
+        MetricsSnapshot {
+            height: self.get_latest_block().index,
+            pending_transactions: pending.len(),
+            mempool_bytes,
+            last_block_timestamp: self.get_latest_block().timestamp,
+            block_interval_seconds: self.metrics.block_interval.clone(),
+            mining_duration_seconds: self.metrics.mining_duration.clone(),
+            hash_attempts: self.metrics.hash_attempts.clone(),
+            hash_rate: self.metrics.hash_rate.clone(),
+            transactions_per_block: self.metrics.transactions_per_block.clone(),
+            validation_failures: self.metrics.validation_failures.iter()
+                .map(|(reason, count)| (reason.to_string(), *count))
+                .collect(),
+            peers: self.metrics.peers.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MetricsSnapshot {
+    /// Форматирует снимок в текстовом формате Prometheus
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        
+        gauge(&mut out, "hellochain_chain_height", "Height of the best chain", self.height as f64);
+        gauge(&mut out, "hellochain_pending_transactions", "Transactions waiting in the mempool", self.pending_transactions as f64);
+        gauge(&mut out, "hellochain_mempool_bytes", "Serialized size of the mempool", self.mempool_bytes as f64);
+        gauge(&mut out, "hellochain_last_block_timestamp_seconds", "Timestamp of the latest block", self.last_block_timestamp as f64);
+        gauge(&mut out, "hellochain_peers", "Connected peers", self.peers as f64);
+        histogram(&mut out, "hellochain_block_interval_seconds", "Time between consecutive blocks", &self.block_interval_seconds);
+        histogram(&mut out, "hellochain_mining_duration_seconds", "Time spent sealing a mined block", &self.mining_duration_seconds);
+        histogram(&mut out, "hellochain_hash_attempts", "Hashes computed per mined block", &self.hash_attempts);
+        histogram(&mut out, "hellochain_hash_rate", "Effective hashes per second while mining a block", &self.hash_rate);
+        histogram(&mut out, "hellochain_transactions_per_block", "Transactions in each appended block", &self.transactions_per_block);
+        
+        let _ = writeln!(out, "# HELP hellochain_validation_failures_total Rejected transactions and blocks");
+        let _ = writeln!(out, "# TYPE hellochain_validation_failures_total counter");
+        for (reason, count) in &self.validation_failures {
+            let _ = writeln!(out, "hellochain_validation_failures_total{{reason=\"{}\"}} {}", reason, count);
+        }
+        
+        out
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (bound, count) in histogram.bounds.iter().zip(&histogram.cumulative_counts) {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+    let _ = writeln!(out, "{}_sum {}", name, histogram.sum);
+    let _ = writeln!(out, "{}_count {}", name, histogram.count);
+}
+
+/// Создает маршрутизатор с эндпоинтом `/metrics`
+pub fn router(chain: SharedBlockchain) -> Router {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .with_state(chain)
+}
+
+/// Запускает эндпоинт метрик на уже открытом сокете
+pub async fn serve(listener: tokio::net::TcpListener, chain: SharedBlockchain) -> std::io::Result<()> {
+    axum::serve(listener, router(chain)).await
+}
+
+async fn get_metrics(State(chain): State<SharedBlockchain>) -> impl IntoResponse {
+    let body = chain.read().metrics_snapshot().to_prometheus();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    use std::collections::HashMap;
+    use std::io::{Read, Write as _};
+    use std::net::TcpStream;
+    use tokio::runtime::Runtime;
+    
+    /// Забирает `/metrics` по HTTP и раскладывает строки со значениями по имени и меткам
+    fn scrape(chain: SharedBlockchain) -> HashMap<String, f64> {
+        let runtime = Runtime::new().unwrap();
+        let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(serve(listener, chain));
+        
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET /metrics HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200"));
+        assert!(head.contains("text/plain; version=0.0.4"));
+        
+        body.lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let (name, value) = line.rsplit_once(' ').unwrap();
+                (name.to_string(), value.parse().unwrap())
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn endpoint_reports_mining_and_rejections() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        for _ in 0..3 {
+            chain.mine_pending_transactions("alice".to_string()).unwrap();
+        }
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let transfer = |chain: &Blockchain, amount: f64| {
+            let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), amount, TransactionType::Transfer);
+            tx.set_nonce(chain.get_account_nonce("alice"));
+            tx.sign(&keypair);
+            tx
+        };
+        assert!(chain.add_transaction(transfer(&chain, 1000.0)).is_err());
+        chain.add_transaction(transfer(&chain, 10.0)).unwrap();
+        let snapshot = chain.metrics_snapshot();
+        
+        let metrics = scrape(SharedBlockchain::new(chain));
+        assert_eq!(metrics["hellochain_chain_height"], 3.0);
+        assert_eq!(metrics["hellochain_pending_transactions"], 1.0);
+        assert!(metrics["hellochain_mempool_bytes"] > 0.0);
+        assert_eq!(metrics["hellochain_last_block_timestamp_seconds"], snapshot.last_block_timestamp as f64);
+        assert_eq!(metrics["hellochain_peers"], 0.0);
+        assert_eq!(metrics["hellochain_validation_failures_total{reason=\"insufficient_balance\"}"], 1.0);
+        
+        assert_eq!(metrics["hellochain_hash_attempts_count"], 3.0);
+        assert!(metrics["hellochain_hash_attempts_sum"] >= 3.0);
+        assert_eq!(metrics["hellochain_mining_duration_seconds_count"], 3.0);
+        assert_eq!(metrics["hellochain_transactions_per_block_count"], 3.0);
+        // Первый блок регистрирует ключ `alice`, остальные несут только награду
+        assert_eq!(metrics["hellochain_transactions_per_block_sum"], 4.0);
+        assert_eq!(metrics["hellochain_block_interval_seconds_count"], 2.0);
+        
+        let buckets: Vec<f64> = snapshot.transactions_per_block.bounds.iter()
+            .map(|bound| metrics[&format!("hellochain_transactions_per_block_bucket{{le=\"{}\"}}", bound)])
+            .collect();
+        assert!(buckets.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(buckets[0], 2.0);
+        assert_eq!(metrics["hellochain_transactions_per_block_bucket{le=\"+Inf\"}"], 3.0);
+    }
+}
@@ -0,0 +1,546 @@
+use crate::block::Block;
+use crate::blockchain::{BlockAcceptance, Blockchain, ConsensusAlgorithm};
+use crate::clock::Clock;
+use crate::errors::BlockchainError;
+use crate::merkle::IncrementalMerkle;
+use crate::shared::SharedBlockchain;
+use crate::trace::debug;
+use serde::{Serialize, Deserialize};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Ошибки фонового майнинга
+#[derive(Error, Debug)]
+pub enum MiningError {
+    #[error("Mining was cancelled")]
+    Cancelled,
+    
+    #[error("Chain tip changed while mining: expected {expected}, found {actual}")]
+    StaleTip { expected: String, actual: String },
+    
+    /// Вершина сменилась до того, как блок был найден; майнинг брошен, блок не добавлен
+    #[error("Mining preempted by new tip {new_tip}")]
+    Preempted { new_tip: String },
+    
+    #[error(transparent)]
+    Chain(#[from] BlockchainError),
+}
+
+/// Сколько попыток перебирается между отчетами о ходе майнинга
+pub const PROGRESS_INTERVAL: u64 = 1 << 18;
+
+/// Сколько последних блоков хранится в статистике майнинга
+pub const MINING_STATS_CAPACITY: usize = 1000;
+
+/// Через сколько попыток по умолчанию майнинг обновляет заголовок
+pub const DEFAULT_ROLL_INTERVAL: u64 = 1 << 24;
+
+/// Как майнинг обновляет заголовок во время долгого перебора PoW
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MiningOptions {
+    /// Через сколько попыток (суммарно по потокам) обновлять заголовок; None — перебирать nonce одного заголовка
+    pub roll_interval: Option<u64>,
+    /// Менять extra-nonce награды, если время по часам цепочки с прошлого раунда не сдвинулось
+    pub extra_nonce: bool,
+}
+
+impl Default for MiningOptions {
+    fn default() -> Self {
+        MiningOptions { roll_interval: Some(DEFAULT_ROLL_INTERVAL), extra_nonce: true }
+    }
+}
+
+/// Обновляет заголовок между раундами перебора: сдвигает время блока к часам цепочки, а если
+/// оно не изменилось — меняет extra-nonce награды и пересчитывает корень Меркла
+pub(crate) struct HeaderRoller {
+    clock: Arc<dyn Clock>,
+    options: MiningOptions,
+    merkle: IncrementalMerkle,
+}
+
+impl HeaderRoller {
+    pub(crate) fn new(clock: Arc<dyn Clock>, options: MiningOptions, block: &Block) -> Self {
+        HeaderRoller { clock, options, merkle: IncrementalMerkle::from_transactions(block.hash_algorithm, &block.transactions) }
+    }
+    
+    /// Сколько попыток длится раунд
+    pub(crate) fn round(&self) -> u64 {
+        self.options.roll_interval.unwrap_or(u64::MAX)
+    }
+    
+    /// Меняет заголовок; false, если менять нечего
+    pub(crate) fn roll(&mut self, block: &mut Block) -> bool {
+        let now = self.clock.now_ts();
+        if now > block.timestamp {
+            block.timestamp = now;
+            return true;
+        }
+        if !self.options.extra_nonce {
+            return false;
+        }
+        let Some(reward) = block.transactions.last_mut().filter(|tx| tx.sender == "BLOCKCHAIN_REWARD") else {
+            return false;
+        };
+        reward.extra_nonce = Some(reward.extra_nonce.map_or(0, |extra_nonce| extra_nonce.wrapping_add(1)));
+        reward.reseal();
+        self.merkle.pop();
+        self.merkle.push(reward);
+        block.merkle_root = self.merkle.root();
+        true
+    }
+}
+
+/// Сколько уже сделано майнингом
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MiningProgress {
+    pub attempts: u64,
+    pub elapsed: Duration,
+    /// Хешей в секунду с предыдущего отчета
+    pub current_hash_rate: f64,
+}
+
+impl MiningProgress {
+    /// Следующий отчет после этого; скорость считается по приросту попыток
+    pub(crate) fn advance(&self, attempts: u64, elapsed: Duration) -> MiningProgress {
+        MiningProgress {
+            attempts,
+            elapsed,
+            current_hash_rate: hash_rate(attempts.saturating_sub(self.attempts), elapsed.saturating_sub(self.elapsed)),
+        }
+    }
+}
+
+/// Затраты на майнинг одного блока
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMiningStats {
+    pub index: u64,
+    pub hash: String,
+    pub attempts: u64,
+    pub duration: Duration,
+    /// Средняя скорость перебора, хешей в секунду
+    pub hash_rate: f64,
+}
+
+pub(crate) fn hash_rate(attempts: u64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 { attempts as f64 / seconds } else { 0.0 }
+}
+
+/// Дескриптор фонового майнинга
+pub struct MiningHandle {
+    cancel: Arc<AtomicBool>,
+    attempts: Arc<AtomicU64>,
+    started: Instant,
+    worker: JoinHandle<Result<Block, MiningError>>,
+}
+
+impl MiningHandle {
+    /// Просит потоки майнинга остановиться; они проверяют флаг каждые несколько тысяч хешей
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+    
+    /// Флаг отмены для тех, кто останавливает майнинг, не владея дескриптором
+    #[cfg(feature = "async")]
+    pub(crate) fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancel.clone()
+    }
+    
+    pub fn is_finished(&self) -> bool {
+        self.worker.is_finished()
+    }
+    
+    /// Ход майнинга с момента запуска; скорость усреднена за все время
+    pub fn progress(&self) -> MiningProgress {
+        MiningProgress::default().advance(self.attempts.load(Ordering::Relaxed), self.started.elapsed())
+    }
+    
+    /// Дожидается окончания майнинга и возвращает добавленный в цепочку блок
+    pub fn join(self) -> Result<Block, MiningError> {
+        self.worker.join().unwrap_or_else(|_| Err(BlockchainError::ConsensusError("Mining thread panicked".to_string()).into()))
+    }
+}
+
+impl SharedBlockchain {
+    /// Снимает копию ожидающих транзакций и майнит блок в фоне, не удерживая блокировку цепочки.
+    /// Если вершина сменилась, перебор бросается в пределах `mining_check_interval` попыток на поток
+    /// с ошибкой `Preempted`, и вызывающий может сразу начать заново. Готовый блок добавляется через
+    /// `accept_block`; если вершина сменилась уже после решения, возвращается `StaleTip`.
+    pub fn start_mining(&self, miner_address: String) -> MiningHandle {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let attempts = Arc::new(AtomicU64::new(0));
+        
+        let started = Instant::now();
+        let chain = self.clone();
+        let worker_cancel = cancel.clone();
+        let worker_attempts = attempts.clone();
+        let worker = thread::spawn(move || {
+            let Candidate { mut block, threads, check_interval, tip_epoch, epoch, mut roller } = build_candidate(&chain, miner_address)?;
+            
+            let keep_going = || !worker_cancel.load(Ordering::Relaxed) && tip_epoch.load(Ordering::Relaxed) == epoch;
+            let round = roller.round();
+            if !block.mine_block_rolling(threads, check_interval, &worker_attempts, round, keep_going, |block| roller.roll(block)) {
+                if worker_cancel.load(Ordering::Relaxed) {
+                    return Err(MiningError::Cancelled);
+                }
+                let new_tip = chain.read().get_latest_block().hash.clone();
+                debug!(index = block.index, new_tip = %new_tip, attempts = worker_attempts.load(Ordering::Relaxed), "Mining preempted");
+                return Err(MiningError::Preempted { new_tip });
+            }
+            
+            let mut chain = chain.write();
+            let tip = chain.get_latest_block().hash.clone();
+            if tip != block.previous_hash {
+                return Err(MiningError::StaleTip { expected: block.previous_hash, actual: tip });
+            }
+            
+            match chain.accept_block(block.clone())? {
+                BlockAcceptance::Appended => {
+                    chain.record_block_mining(&block, Some(worker_attempts.load(Ordering::Relaxed)), started.elapsed());
+                    Ok(block)
+                },
+                _ => Err(MiningError::StaleTip { expected: block.previous_hash, actual: tip }),
+            }
+        });
+        
+        MiningHandle { cancel, attempts, started, worker }
+    }
+    
+    /// Майнит блок и возвращает его. PoW перебирается через `start_mining` без блокировки цепочки,
+    /// так что транзакции принимаются и во время перебора; при смене вершины майнинг начинается заново.
+    /// PoS-блок печатается под блокировкой на запись.
+    pub fn mine_block(&self, miner_address: String) -> Result<Block, BlockchainError> {
+        if self.read().consensus_algorithm != ConsensusAlgorithm::ProofOfWork {
+            let mut chain = self.write();
+            chain.mine_pending_transactions(miner_address)?;
+            return Ok(chain.get_latest_block().clone());
+        }
+        
+        loop {
+            match self.start_mining(miner_address.clone()).join() {
+                Ok(block) => return Ok(block),
+                Err(MiningError::Preempted { .. } | MiningError::StaleTip { .. }) => continue,
+                Err(MiningError::Chain(error)) => return Err(error),
+                Err(error) => return Err(BlockchainError::ConsensusError(error.to_string())),
+            }
+        }
+    }
+}
+
+impl Blockchain {
+    /// Майнит ожидающие транзакции, как `mine_pending_transactions`, сообщая о ходе PoW каждые
+    /// `PROGRESS_INTERVAL` попыток. Если обратный вызов вернул `Break`, блок не добавляется.
+    pub fn mine_pending_transactions_with_progress<F>(&mut self, miner_address: String, mut on_progress: F) -> Result<(), MiningError>
+    where
+        F: FnMut(MiningProgress) -> ControlFlow<()>,
+    {
+        if self.mine_pending(miner_address, Some(&mut on_progress))? {
+            Ok(())
+        } else {
+            Err(MiningError::Cancelled)
+        }
+    }
+    
+    /// Затраты на майнинг последних блоков, смайненных этим узлом, от старых к новым
+    pub fn mining_stats(&self) -> impl DoubleEndedIterator<Item = &BlockMiningStats> + ExactSizeIterator {
+        self.mining_stats.iter()
+    }
+    
+    /// Учитывает затраты на печать смайненного блока; для PoS число попыток неизвестно
+    pub(crate) fn record_block_mining(&mut self, block: &Block, attempts: Option<u64>, duration: Duration) {
+        #[cfg(feature = "metrics")]
+        self.record_mining(attempts, duration);
+        
+        if let Some(attempts) = attempts {
+            if self.mining_stats.len() == MINING_STATS_CAPACITY {
+                self.mining_stats.pop_front();
+            }
+            self.mining_stats.push_back(BlockMiningStats {
+                index: block.index,
+                hash: block.hash.clone(),
+                attempts,
+                duration,
+                hash_rate: hash_rate(attempts, duration),
+            });
+        }
+    }
+}
+
+/// Блок-кандидат фонового майнинга и смена вершины, на которой он собран
+struct Candidate {
+    block: Block,
+    threads: usize,
+    check_interval: u64,
+    tip_epoch: Arc<AtomicU64>,
+    epoch: u64,
+    roller: HeaderRoller,
+}
+
+/// Собирает блок-кандидат из ожидающих транзакций и награды майнеру; блокировка на запись
+/// сначала переносит в пул очередь приема
+fn build_candidate(chain: &SharedBlockchain, miner_address: String) -> Result<Candidate, BlockchainError> {
+    let chain = chain.read();
+    let (block, _) = chain.candidate_block(&miner_address)?;
+    let roller = HeaderRoller::new(chain.clock.clone(), chain.mining_options, &block);
+    Ok(Candidate {
+        block,
+        threads: chain.mining_thread_count(),
+        check_interval: chain.mining_check_interval(),
+        tip_epoch: chain.tip_epoch.clone(),
+        epoch: chain.tip_epoch.load(Ordering::Relaxed),
+        roller,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::CompactTarget;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    
+    fn set_difficulty(chain: &mut Blockchain, difficulty: usize) {
+        chain.difficulty = difficulty;
+        chain.target = Some(CompactTarget::from_difficulty(difficulty));
+    }
+    
+    /// Цепочка с одним добытым блоком; один поток и проверка условия на каждом хеше
+    fn shared_chain(difficulty: usize) -> SharedBlockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        set_difficulty(&mut chain, difficulty);
+        chain.mining_threads = Some(1);
+        chain.mining_check_interval = Some(1);
+        SharedBlockchain::new(chain)
+    }
+    
+    /// Ждет, пока кандидат собран и перебор пошел
+    fn wait_for_grinding(handle: &MiningHandle) {
+        while handle.progress().attempts == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    
+    #[test]
+    fn cancelled_mining_appends_nothing() {
+        let chain = shared_chain(12);
+        let handle = chain.start_mining("miner".to_string());
+        wait_for_grinding(&handle);
+        
+        handle.cancel();
+        assert!(matches!(handle.join(), Err(MiningError::Cancelled)));
+        assert_eq!(chain.read().chain.len(), 2);
+        assert_eq!(chain.read().mining_stats().len(), 1);
+    }
+    
+    #[test]
+    fn finished_mining_advances_the_chain_once() {
+        let chain = shared_chain(1);
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        chain.write().sign_as_sender(&mut tx);
+        chain.add_transaction(tx.clone()).unwrap();
+        
+        let handle = chain.start_mining("miner".to_string());
+        let block = handle.join().unwrap();
+        
+        let chain = chain.read();
+        assert_eq!(chain.chain.len(), 3);
+        assert_eq!(chain.get_latest_block().hash, block.hash);
+        assert!(block.transactions.iter().any(|mined| mined.id == tx.id));
+        assert!(chain.mempool().is_empty());
+        assert_eq!(chain.mining_stats().next_back().map(|stats| &stats.hash), Some(&block.hash));
+    }
+    
+    #[test]
+    fn new_tip_during_the_grind_preempts_mining() {
+        let chain = shared_chain(12);
+        let handle = chain.start_mining("miner".to_string());
+        wait_for_grinding(&handle);
+        
+        let new_tip = {
+            let mut chain = chain.write();
+            set_difficulty(&mut chain, 1);
+            chain.mine_pending_transactions("other".to_string()).unwrap();
+            chain.get_latest_block().hash.clone()
+        };
+        let preempted = handle.join();
+        assert!(matches!(&preempted, Err(MiningError::Preempted { new_tip: tip }) if *tip == new_tip), "{:?}", preempted);
+        assert_eq!(chain.read().chain.len(), 3);
+    }
+    
+    #[test]
+    fn external_block_preempts_mining_within_the_check_interval() {
+        let chain = shared_chain(12);
+        chain.write().mining_check_interval = Some(64);
+        // Узел с той же историей, который добывает следующий блок первым
+        let mut producer: Blockchain = serde_json::from_str(&serde_json::to_string(&*chain.read()).unwrap()).unwrap();
+        set_difficulty(&mut producer, 1);
+        producer.mine_pending_transactions("other".to_string()).unwrap();
+        let external = producer.get_latest_block().clone();
+        
+        let handle = chain.start_mining("miner".to_string());
+        wait_for_grinding(&handle);
+        let attempts_at_switch = {
+            let mut chain = chain.write();
+            set_difficulty(&mut chain, 1);
+            assert_eq!(chain.accept_block(external.clone()).unwrap(), BlockAcceptance::Appended);
+            handle.progress().attempts
+        };
+        while !handle.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        // Один поток досчитывает не больше одной пачки попыток до проверки вершины
+        assert!(handle.progress().attempts - attempts_at_switch <= 64, "{} attempts after the switch", handle.progress().attempts - attempts_at_switch);
+        let preempted = handle.join();
+        assert!(matches!(&preempted, Err(MiningError::Preempted { new_tip }) if *new_tip == external.hash), "{:?}", preempted);
+        
+        let chain = chain.read();
+        assert_eq!(chain.chain.len(), 3);
+        assert_eq!(chain.get_latest_block().hash, external.hash);
+        assert_eq!(chain.mining_stats().len(), 1);
+    }
+    
+    #[test]
+    fn tip_swapped_after_the_solution_is_stale() {
+        let chain = shared_chain(5);
+        let handle = chain.start_mining("miner".to_string());
+        wait_for_grinding(&handle);
+        
+        // Вершина меняется в обход счетчика смен, как при гонке записи с уже найденным решением
+        let swapped = {
+            let mut chain = chain.write();
+            let tip = chain.get_latest_block().clone();
+            let mut block = Block::new(tip.index + 1, Vec::new(), tip.hash, 1);
+            block.mine_block();
+            chain.chain.push(block.clone());
+            block.hash
+        };
+        let stale = handle.join();
+        assert!(matches!(&stale, Err(MiningError::StaleTip { actual, .. }) if *actual == swapped), "{:?}", stale);
+        assert_eq!(chain.read().chain.len(), 3);
+        assert_eq!(chain.read().get_latest_block().hash, swapped);
+    }
+    
+    #[test]
+    fn breaking_from_the_progress_callback_appends_nothing() {
+        let chain = shared_chain(12);
+        let mut chain = chain.write();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx.clone()).unwrap();
+        
+        let mut reports = 0;
+        let result = chain.mine_pending_transactions_with_progress("miner".to_string(), |_| {
+            reports += 1;
+            ControlFlow::Break(())
+        });
+        assert!(matches!(result, Err(MiningError::Cancelled)), "{:?}", result);
+        assert_eq!(reports, 1);
+        assert_eq!(chain.chain.len(), 2);
+        assert_eq!(chain.mempool().transactions().iter().map(|pending| &pending.id).collect::<Vec<_>>(), [&tx.id]);
+        assert_eq!(chain.get_balance("miner"), 0.0);
+        assert_eq!(chain.mining_stats().len(), 1);
+    }
+    
+    #[test]
+    fn mined_blocks_record_their_statistics() {
+        let chain = shared_chain(2);
+        let mut chain = chain.write();
+        chain.mine_pending_transactions_with_progress("miner".to_string(), |_| ControlFlow::Continue(())).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let stats: Vec<&BlockMiningStats> = chain.mining_stats().collect();
+        assert_eq!(stats.iter().map(|stats| stats.index).collect::<Vec<_>>(), [1, 2, 3]);
+        let latest = stats[2];
+        assert_eq!(latest.hash, chain.get_latest_block().hash);
+        assert!(latest.attempts >= 1);
+        assert_eq!(latest.hash_rate, hash_rate(latest.attempts, latest.duration));
+    }
+    
+    /// Часы, которые сдвигаются на секунду при каждом обращении, как при очень долгом переборе
+    struct TickingClock {
+        now: std::sync::atomic::AtomicI64,
+    }
+    
+    impl Clock for TickingClock {
+        fn now_ts(&self) -> i64 {
+            self.now.fetch_add(1, Ordering::Relaxed)
+        }
+    }
+    
+    /// Воспроизводимая цепочка с добытым блоком у alice и переводом в пуле; майнинг короткими раундами,
+    /// а условие проверяется чаще, чем они кончаются
+    fn rolling_chain(clock: Arc<dyn Clock>, extra_nonce: bool) -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.set_clock(clock);
+        chain.set_entropy(Arc::new(crate::entropy::SeededEntropy::new(3)));
+        chain.mining_threads = Some(1);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut tx = Transaction::new_at("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer, chain.now_ts());
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        set_difficulty(&mut chain, 3);
+        chain.mining_check_interval = Some(16);
+        chain.mining_options = MiningOptions { roll_interval: Some(64), extra_nonce };
+        chain
+    }
+    
+    /// Узел без ключей принимает добытый блок: время, корень Меркла и награда проходят проверку
+    fn assert_accepted_by_a_peer(chain: &Blockchain) {
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap();
+        let block = peer.chain.pop().unwrap();
+        peer.rebuild_state_from_chain(false);
+        peer.verify_block(&block).unwrap();
+        assert!(chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn long_grind_stamps_the_block_near_completion() {
+        use crate::block::GENESIS_TIMESTAMP;
+        
+        let clock = Arc::new(TickingClock { now: (GENESIS_TIMESTAMP + 1000).into() });
+        let mut chain = rolling_chain(clock.clone(), true);
+        let start = clock.now_ts();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let end = clock.now_ts();
+        
+        let block = chain.get_latest_block();
+        assert!(block.timestamp - start > 10, "{} rolls", block.timestamp - start);
+        assert!(end - block.timestamp < block.timestamp - start, "{}..{}..{}", start, block.timestamp, end);
+        // Пока часы идут, extra-nonce не нужен
+        assert_eq!(block.transactions.last().unwrap().extra_nonce, None);
+        assert_accepted_by_a_peer(&chain);
+    }
+    
+    #[test]
+    fn extra_nonce_rolls_keep_the_coinbase_valid() {
+        use crate::block::GENESIS_TIMESTAMP;
+        use crate::clock::MockClock;
+        
+        let mut chain = rolling_chain(Arc::new(MockClock::new(GENESIS_TIMESTAMP + 1000)), true);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let block = chain.get_latest_block();
+        let reward = block.transactions.last().unwrap();
+        assert!(reward.extra_nonce.is_some_and(|extra_nonce| extra_nonce > 0), "{:?}", reward.extra_nonce);
+        assert_eq!(block.timestamp, GENESIS_TIMESTAMP + 1000);
+        assert_eq!(block.merkle_root, IncrementalMerkle::from_transactions(block.hash_algorithm, &block.transactions).root());
+        assert_eq!(chain.get_balance("miner"), 100.0 + 10.0 * 0.001);
+        assert_accepted_by_a_peer(&chain);
+        
+        // Без extra-nonce и с остановленными часами перебор просто продолжает nonce того же заголовка
+        let mut chain = rolling_chain(Arc::new(MockClock::new(GENESIS_TIMESTAMP + 1000)), false);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let block = chain.get_latest_block();
+        assert_eq!(block.transactions.last().unwrap().extra_nonce, None);
+        assert!(block.nonce >= 64, "{}", block.nonce);
+        assert_accepted_by_a_peer(&chain);
+    }
+}
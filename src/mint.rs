@@ -0,0 +1,261 @@
+use crate::balance_audit::{BalanceBucket, BalanceChangeReason};
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::features::Feature;
+use crate::signing::parse_public_key;
+use crate::transaction::{Transaction, TransactionType};
+
+/// Отправитель транзакций выпуска `Mint`: средства появляются без списания с кошелька
+pub const MINT_AUTHORITY: &str = "BLOCKCHAIN_MINT";
+
+impl Blockchain {
+    /// Назначает эмитентом ключ кошелька `issuer`, хранящийся на этом узле. Это параметр генезиса,
+    /// как `mint_authority` спецификации; остальные узлы получают ключ вместе с параметрами цепочки.
+    pub fn enable_minting(&mut self, issuer: &str) -> Result<(), BlockchainError> {
+        self.require_account_mode("Minting")?;
+        let wallet = self.wallets.get(issuer).ok_or_else(|| BlockchainError::WalletNotFound { address: issuer.to_string() })?;
+        let Some(keypair) = wallet.keypair() else {
+            return Err(BlockchainError::InvalidConfig(format!("Wallet {} has no key on this node", issuer)));
+        };
+        self.params.mint_authority = Some(keypair.public_key_hex());
+        Ok(())
+    }
+    
+    /// Ставит в пул транзакцию выпуска `amount` на адрес и возвращает ее идентификатор.
+    /// Средства появляются, когда транзакция попадет в блок. Транзакцию подписывает ключ эмитента,
+    /// поэтому выпускать может только узел, у которого есть кошелек с этим ключом.
+    pub fn mint(&mut self, address: &str, amount: f64) -> Result<String, BlockchainError> {
+        self.require_account_mode("Minting")?;
+        self.require_feature(Feature::Minting)?;
+        let keypair = self.wallets.values()
+            .filter_map(|wallet| wallet.keypair())
+            .find(|keypair| self.params.mint_authority.as_deref() == Some(keypair.public_key_hex().as_str()))
+            .cloned()
+            .ok_or_else(|| BlockchainError::InvalidTransaction("The mint authority key is not held by this node".to_string()))?;
+        let mut tx = self.new_transaction(MINT_AUTHORITY.to_string(), address.to_string(), amount, TransactionType::Mint);
+        tx.sign(&keypair);
+        let tx_id = tx.id.clone();
+        self.queue_protocol_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Проверяет, что выпуск идет только от `MINT_AUTHORITY` с подписью ключа эмитента из параметров,
+    /// а этот адрес ничего другого не отправляет
+    pub(crate) fn check_mint_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        let is_mint = matches!(tx.transaction_type, TransactionType::Mint);
+        if !is_mint {
+            if tx.sender == MINT_AUTHORITY {
+                return Err(BlockchainError::InvalidTransaction(format!("{} can only mint", MINT_AUTHORITY)));
+            }
+            return Ok(());
+        }
+        if tx.sender != MINT_AUTHORITY {
+            return Err(BlockchainError::InvalidTransaction(format!("Mint {} must come from {}", tx.id, MINT_AUTHORITY)));
+        }
+        self.require_feature(Feature::Minting)?;
+        let authority = self.params.mint_authority.as_deref().and_then(parse_public_key);
+        let signed = tx.public_key.as_deref() == self.params.mint_authority.as_deref()
+            && authority.is_some_and(|public_key| tx.verify_signature(&public_key));
+        if !signed {
+            return Err(BlockchainError::InvalidSignature { tx_id: tx.id.clone() });
+        }
+        Ok(())
+    }
+    
+    /// Начальный баланс существующего кошелька. Это часть генезис-состояния, которое каждый узел
+    /// строит из одной и той же спецификации, поэтому после первого блока оно недоступно. Если у
+    /// кошелька есть пара ключей, а имени еще не назначен ключ распределения, им становится ее ключ.
+    pub fn allocate_at_genesis(&mut self, address: &str, amount: f64) -> Result<(), BlockchainError> {
+        self.require_account_mode("Genesis allocation")?;
+        if self.chain.len() > 1 {
+            return Err(BlockchainError::InvalidTransaction("Genesis allocations are only possible before the first block; use a mint".to_string()));
+        }
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(BlockchainError::InvalidTransaction(format!("Allocation {} to {} is invalid", amount, address)));
+        }
+        let Some(wallet) = self.wallets.get_mut(address) else {
+            return Err(BlockchainError::WalletNotFound { address: address.to_string() });
+        };
+        wallet.balance += amount;
+        // Ключ адреса сети выводится из самого адреса, закреплять его не нужно
+        if let (None, Some(keypair)) = (&wallet.public_key, wallet.keypair()) {
+            self.allocation_keys.entry(address.to_string()).or_insert_with(|| keypair.public_key_hex());
+        }
+        self.record_off_chain_credit(address, amount);
+        self.record_balance_change(address, BalanceBucket::Balance, amount, BalanceChangeReason::Funding);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::codec::encode_transaction;
+    use crate::entropy::OsEntropy;
+    use crate::signing::Keypair;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::SignatureVerification;
+    
+    /// Цепочка с кошельками alice и эмитента issuer, ключи которых уже в блоке
+    fn chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("issuer".to_string()).unwrap();
+        chain.enable_minting("issuer").unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain
+    }
+    
+    /// Копия цепочки без ключей, как у другого узла
+    fn peer_of(chain: &Blockchain) -> Blockchain {
+        serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap()
+    }
+    
+    /// Выпуск 50 монет alice без подписи, подписанный чужим ключом и подписанный чужим ключом
+    /// под видом ключа эмитента
+    fn forged_mints(chain: &Blockchain) -> [Transaction; 3] {
+        let forger = Keypair::generate(&OsEntropy);
+        let unsigned = Transaction::new(MINT_AUTHORITY.to_string(), "alice".to_string(), 50.0, TransactionType::Mint);
+        let mut foreign = unsigned.clone();
+        foreign.sign(&forger);
+        let mut impostor = foreign.clone();
+        impostor.public_key = chain.params.mint_authority.clone();
+        [unsigned, foreign, impostor]
+    }
+    
+    /// Следующий блок цепочки с транзакцией `tx`, собранный в обход пула
+    fn block_with(chain: &Blockchain, tx: Transaction) -> Block {
+        let mut producer = peer_of(chain);
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        let mut block = producer.get_latest_block().clone();
+        block.transactions.insert(0, tx);
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.mine_block();
+        block
+    }
+    
+    #[test]
+    fn minted_funds_appear_only_after_a_block() {
+        let mut chain = chain();
+        let supply = chain.circulating_supply();
+        let tx_id = chain.mint("alice", 50.0).unwrap();
+        assert_eq!(chain.get_balance("alice"), 0.0);
+        assert_eq!(chain.circulating_supply(), supply);
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("alice"), 50.0);
+        let block = chain.get_latest_block();
+        assert!(block.transactions.iter().any(|tx| tx.id == tx_id && tx.sender == MINT_AUTHORITY));
+        assert!((chain.circulating_supply() - (supply + 100.0 + 50.0)).abs() < 1e-9);
+        
+        // Старый метод идет тем же путем
+        #[allow(deprecated)]
+        let funded = chain.add_funds_to_wallet("alice", 5.0);
+        funded.unwrap();
+        assert!(chain.mempool().transactions().iter().any(|tx| tx.transaction_type == TransactionType::Mint && tx.amount == 5.0));
+        assert_eq!(chain.get_balance("alice"), 50.0);
+        #[allow(deprecated)]
+        let missing = chain.add_funds_to_wallet("nobody", 5.0);
+        assert!(matches!(missing, Err(BlockchainError::WalletNotFound { .. })));
+    }
+    
+    #[test]
+    fn replaying_the_chain_reproduces_minted_balances() {
+        let mut chain = chain();
+        let mut peer = peer_of(&chain);
+        chain.mint("alice", 50.0).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.mint("bob", 7.5).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        for block in &chain.chain[2..] {
+            peer.accept_block(block.clone()).unwrap();
+        }
+        for address in ["alice", "bob", "miner"] {
+            assert_eq!(peer.get_balance(address), chain.get_balance(address), "{}", address);
+        }
+        assert_eq!(peer.circulating_supply(), chain.circulating_supply());
+        let report = chain.rebuild_state_from_chain(false);
+        assert!(report.is_clean(), "{:?}", report.differences);
+        assert!(peer.validate_chain(SignatureVerification::Serial).is_ok());
+    }
+    
+    #[test]
+    fn mint_is_refused_where_minting_is_disabled() {
+        let mut chain = chain();
+        let mut peer = peer_of(&chain);
+        peer.params.disabled_features.insert(Feature::Minting);
+        peer.mint("alice", 50.0).unwrap_err();
+        
+        chain.mint("alice", 50.0).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let result = peer.accept_block(chain.get_latest_block().clone());
+        assert!(matches!(result, Err(BlockchainError::FeatureDisabled { feature: "Minting" })), "{:?}", result);
+        assert_eq!(peer.get_balance("alice"), 0.0);
+    }
+    
+    #[test]
+    fn only_the_authority_mints_and_it_only_mints() {
+        let chain = chain();
+        let forged = Transaction::new("alice".to_string(), "alice".to_string(), 50.0, TransactionType::Mint);
+        assert!(matches!(chain.check_mint_transaction(&forged), Err(BlockchainError::InvalidTransaction(_))));
+        let spend = Transaction::new(MINT_AUTHORITY.to_string(), "alice".to_string(), 50.0, TransactionType::Transfer);
+        assert!(matches!(chain.check_mint_transaction(&spend), Err(BlockchainError::InvalidTransaction(_))));
+    }
+    
+    #[test]
+    fn minting_is_off_without_an_authority() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        assert!(!chain.is_feature_enabled(Feature::Minting));
+        let result = chain.mint("alice", 50.0);
+        assert!(matches!(result, Err(BlockchainError::FeatureDisabled { feature: "Minting" })), "{:?}", result);
+        let tx = Transaction::new(MINT_AUTHORITY.to_string(), "alice".to_string(), 50.0, TransactionType::Mint);
+        let result = chain.queue_protocol_transaction(tx);
+        assert!(matches!(result, Err(BlockchainError::FeatureDisabled { feature: "Minting" })), "{:?}", result);
+        
+        // Узел без ключа эмитента выпускать не может, даже если выпуск включен
+        let mut peer = peer_of(&self::chain());
+        assert!(peer.is_feature_enabled(Feature::Minting));
+        assert!(matches!(peer.mint("alice", 50.0), Err(BlockchainError::InvalidTransaction(_))));
+    }
+    
+    #[test]
+    fn unsigned_and_forged_mints_are_rejected_at_admission() {
+        let mut chain = chain();
+        for tx in forged_mints(&chain) {
+            // Извне выпуск не принимается вовсе, а выпуск самого узла проверяется по подписи
+            let result = chain.submit_raw_transaction(encode_transaction(&tx, chain.chain_id));
+            assert!(matches!(result, Err(BlockchainError::InvalidTransaction(_))), "{:?}", result);
+            let result = chain.add_transaction(tx.clone());
+            assert!(matches!(result, Err(BlockchainError::InvalidTransaction(_))), "{:?}", result);
+            let result = chain.queue_protocol_transaction(tx);
+            assert!(matches!(result, Err(BlockchainError::InvalidSignature { .. })), "{:?}", result);
+        }
+        assert!(chain.mempool().is_empty());
+        assert_eq!(chain.get_balance("alice"), 0.0);
+    }
+    
+    #[test]
+    fn blocks_with_unsigned_or_forged_mints_are_rejected() {
+        let mut chain = chain();
+        let mut peer = peer_of(&chain);
+        for tx in forged_mints(&chain) {
+            let block = block_with(&chain, tx);
+            let result = chain.verify_block(&block);
+            assert!(matches!(result, Err(BlockchainError::InvalidSignature { .. })), "{:?}", result);
+            let result = peer.accept_block(block);
+            assert!(matches!(result, Err(BlockchainError::InvalidSignature { .. })), "{:?}", result);
+        }
+        assert_eq!(peer.get_balance("alice"), 0.0);
+        
+        // Тот же блок с подписью эмитента принимается
+        let mut signed = Transaction::new(MINT_AUTHORITY.to_string(), "alice".to_string(), 50.0, TransactionType::Mint);
+        signed.sign(chain.get_wallet_info("issuer").unwrap().keypair().unwrap());
+        peer.accept_block(block_with(&chain, signed)).unwrap();
+        assert_eq!(peer.get_balance("alice"), 50.0);
+        chain.accept_block(peer.get_latest_block().clone()).unwrap();
+    }
+}
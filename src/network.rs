@@ -0,0 +1,1182 @@
+use crate::block::{Block, BlockHeader};
+use crate::blockchain::BlockAcceptance;
+use crate::errors::BlockchainError;
+use crate::events::{ChainEvent, HookId};
+use crate::shared::SharedBlockchain;
+use crate::sync::{self, SyncError, SyncOptions, SyncPeer, SyncReport};
+use crate::trace::warn;
+use crate::transaction::Transaction;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Версия протокола обмена между узлами
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Максимальный размер одного сообщения в байтах
+pub const MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+/// Максимальное число блоков в одном ответе на запрос диапазона
+pub const MAX_BLOCKS_PER_MESSAGE: u64 = 500;
+/// Максимальное число заголовков в одном ответе
+pub const MAX_HEADERS_PER_MESSAGE: u64 = 2000;
+/// Максимальное число идентификаторов транзакций в одном объявлении или запросе
+pub const MAX_INV_PER_MESSAGE: usize = 1000;
+/// Сколько транзакций в секунду узел принимает от одного пира; остальные отбрасываются
+pub const TX_RATE_LIMIT: f64 = 100.0;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const ANNOUNCE_QUEUE_CAPACITY: usize = 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const SEEN_CACHE_CAPACITY: usize = 10_000;
+const PEER_KNOWN_CAPACITY: usize = 5_000;
+
+/// Настройки сетевого узла
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    pub listen_addr: SocketAddr,
+    pub seed_peers: Vec<SocketAddr>,
+    pub max_peers: usize,
+    /// Сумма штрафных баллов, после которой пир отключается и банится
+    pub ban_threshold: u32,
+    pub ban_duration: Duration,
+    /// Файл, в котором баны переживают перезапуск узла
+    pub ban_file: Option<PathBuf>,
+}
+
+impl NetworkConfig {
+    /// Создает настройки с адресом прослушивания и без начальных пиров
+    pub fn new(listen_addr: SocketAddr) -> Self {
+        NetworkConfig {
+            listen_addr,
+            seed_peers: Vec::new(),
+            max_peers: 16,
+            ban_threshold: 100,
+            ban_duration: Duration::from_secs(24 * 60 * 60),
+            ban_file: None,
+        }
+    }
+}
+
+/// Нарушения пира и их вес в штрафных баллах
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+    /// Неразбираемое сообщение или сообщение до рукопожатия
+    MalformedMessage,
+    /// Кадр больше допустимого размера
+    OversizedFrame,
+    /// Блок, не прошедший проверку
+    InvalidBlock,
+    /// Блок с другой ветки, например во время реорганизации: обычно честная ошибка
+    StaleBlock,
+    /// Невалидная транзакция
+    InvalidTransaction,
+    /// Ответ или транзакции, которых мы не запрашивали, либо превышение лимита частоты
+    Unsolicited,
+}
+
+impl Misbehavior {
+    pub fn weight(self) -> u32 {
+        match self {
+            Misbehavior::MalformedMessage => 20,
+            Misbehavior::OversizedFrame => 50,
+            Misbehavior::InvalidBlock => 50,
+            Misbehavior::StaleBlock => 0,
+            Misbehavior::InvalidTransaction => 10,
+            Misbehavior::Unsolicited => 5,
+        }
+    }
+}
+
+/// Сообщения протокола между узлами
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Message {
+    /// Рукопожатие: версия протокола, генезис, высота и адрес, на котором узел принимает соединения
+    Version { protocol: u32, genesis_hash: String, height: u64, listen_addr: SocketAddr },
+    /// Отказ в соединении с причиной
+    Reject { reason: String },
+    /// Объявление нового блока
+    NewBlock { block: Block },
+    /// Запрос блоков по диапазону высот включительно
+    GetBlocks { from: u64, to: u64 },
+    /// Ответ с блоками
+    Blocks { blocks: Vec<Block> },
+    /// Запрос известных адресов пиров
+    GetPeers,
+    /// Список адресов пиров
+    Peers { addrs: Vec<SocketAddr> },
+    /// Запрос заголовков для синхронизации
+    GetHeaders { from: u64, count: u64 },
+    /// Ответ с заголовками
+    Headers { headers: Vec<BlockHeader> },
+    /// Запрос тел блоков для синхронизации по диапазону высот включительно
+    GetBodies { from: u64, to: u64 },
+    /// Ответ с телами блоков
+    Bodies { blocks: Vec<Block> },
+    /// Объявление идентификаторов новых ожидающих транзакций
+    InvTransactions { ids: Vec<String> },
+    /// Запрос транзакций по идентификаторам
+    GetTransactions { ids: Vec<String> },
+    /// Ответ с транзакциями из списка ожидающих
+    Transactions { transactions: Vec<Transaction> },
+}
+
+/// Записывает сообщение с префиксом длины (u32, big-endian)
+pub fn write_message(stream: &mut impl Write, message: &Message) -> io::Result<()> {
+    let payload = serde_json::to_vec(message).map_err(io::Error::other)?;
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "message exceeds frame size limit"));
+    }
+    
+    stream.write_all(&(payload.len() as u32).to_be_bytes())?;
+    stream.write_all(&payload)?;
+    stream.flush()
+}
+
+/// Читает сообщение с префиксом длины, отклоняя кадры больше лимита
+pub fn read_message(stream: &mut impl Read) -> io::Result<Message> {
+    read_message_until(stream, &AtomicBool::new(false))
+}
+
+fn read_message_until(stream: &mut impl Read, stop: &AtomicBool) -> io::Result<Message> {
+    let payload = read_frame_until(stream, stop)?;
+    serde_json::from_slice(&payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Читает один кадр; после слишком большого кадра поток рассинхронизирован и соединение нужно закрыть
+fn read_frame_until(stream: &mut impl Read, stop: &AtomicBool) -> io::Result<Vec<u8>> {
+    let mut header = [0u8; 4];
+    read_exact_until(stream, &mut header, stop)?;
+    
+    let length = u32::from_be_bytes(header) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes exceeds limit", length)));
+    }
+    
+    let mut payload = vec![0u8; length];
+    read_exact_until(stream, &mut payload, stop)?;
+    Ok(payload)
+}
+
+/// Читает буфер целиком, переживая таймауты чтения, пока не выставлен флаг остановки
+fn read_exact_until(stream: &mut impl Read, buffer: &mut [u8], stop: &AtomicBool) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match stream.read(&mut buffer[filled..]) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed")),
+            Ok(n) => filled += n,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {
+                if stop.load(Ordering::Relaxed) {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "node is shutting down"));
+                }
+            },
+            Err(e) => return Err(e),
+        }
+    }
+    
+    Ok(())
+}
+
+/// Ограниченное множество недавних идентификаторов: самые старые вытесняются первыми
+struct RecentIds {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl RecentIds {
+    fn new(capacity: usize) -> Self {
+        RecentIds { ids: HashSet::new(), order: VecDeque::new(), capacity }
+    }
+    
+    /// Добавляет идентификатор; возвращает false, если он уже был
+    fn insert(&mut self, id: &str) -> bool {
+        if !self.ids.insert(id.to_string()) {
+            return false;
+        }
+        self.order.push_back(id.to_string());
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+    
+    fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+    
+    fn remove(&mut self, id: &str) {
+        if self.ids.remove(id) {
+            self.order.retain(|other| other != id);
+        }
+    }
+}
+
+/// Ведро токенов для ограничения частоты сообщений от пира
+struct RateLimiter {
+    tokens: f64,
+    rate: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate: f64) -> Self {
+        RateLimiter { tokens: rate, rate, updated: Instant::now() }
+    }
+    
+    /// Забирает токен; возвращает false, если лимит исчерпан
+    fn try_take(&mut self) -> bool {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * self.rate).min(self.rate);
+        self.updated = now;
+        
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Отказ по правилам пула (переполнение, низкая комиссия), а не из-за невалидности транзакции
+fn is_policy_rejection(error: &BlockchainError) -> bool {
+    matches!(error, BlockchainError::MempoolFull { .. })
+}
+
+/// Сведения о подключенном пире
+#[derive(Debug, Clone)]
+pub struct PeerInfo {
+    pub addr: SocketAddr,
+    pub listen_addr: Option<SocketAddr>,
+    pub height: u64,
+    pub outbound: bool,
+    /// Накопленные штрафные баллы
+    pub score: u32,
+}
+
+/// Забаненный адрес и время окончания бана (unix-время в секундах)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BanInfo {
+    pub ip: IpAddr,
+    pub until: i64,
+}
+
+struct Peer {
+    addr: SocketAddr,
+    outbound: bool,
+    listen_addr: Mutex<Option<SocketAddr>>,
+    height: AtomicU64,
+    writer: Mutex<TcpStream>,
+    request_lock: Mutex<()>,
+    response_slot: Mutex<Option<mpsc::Sender<Message>>>,
+    /// Транзакции, о которых пир уже знает: их не нужно ему объявлять
+    known_transactions: Mutex<RecentIds>,
+    /// Транзакции, отклоненные по правилам пула: у этого пира их больше не запрашиваем
+    rejected_transactions: Mutex<RecentIds>,
+    tx_limiter: Mutex<RateLimiter>,
+    /// Транзакции, которые мы запросили у пира; остальные считаются навязанными
+    requested_transactions: Mutex<RecentIds>,
+    score: AtomicU32,
+}
+
+impl Peer {
+    /// Отправляет запрос и ждет ответ, который поток чтения передаст через слот ответа
+    fn request(&self, message: &Message) -> io::Result<Message> {
+        let _guard = self.request_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (sender, receiver) = mpsc::channel();
+        *self.response_slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(sender);
+        
+        let result = self.send(message).and_then(|_| {
+            receiver.recv_timeout(REQUEST_TIMEOUT)
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no response from peer"))
+        });
+        
+        *self.response_slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        result
+    }
+    
+    /// Передает ответ ожидающему запросу; возвращает false, если ответа никто не ждал
+    fn deliver_response(&self, message: Message) -> bool {
+        match self.response_slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take() {
+            Some(sender) => sender.send(message).is_ok(),
+            None => false,
+        }
+    }
+    
+    fn send(&self, message: &Message) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        write_message(&mut *writer, message)
+    }
+    
+    fn listen_addr(&self) -> Option<SocketAddr> {
+        *self.listen_addr.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    fn is_handshaken(&self) -> bool {
+        self.listen_addr().is_some()
+    }
+    
+    fn known_transactions(&self) -> MutexGuard<'_, RecentIds> {
+        self.known_transactions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    fn rejected_transactions(&self) -> MutexGuard<'_, RecentIds> {
+        self.rejected_transactions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    fn requested_transactions(&self) -> MutexGuard<'_, RecentIds> {
+        self.requested_transactions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    fn disconnect(&self) {
+        let _ = self.writer.lock().map(|stream| stream.shutdown(Shutdown::Both));
+    }
+}
+
+/// Что поток рассылки должен объявить пирам
+enum Announcement {
+    Block(Block),
+    Transaction(String),
+}
+
+struct NodeInner {
+    chain: SharedBlockchain,
+    config: NetworkConfig,
+    local_addr: SocketAddr,
+    peers: Mutex<HashMap<SocketAddr, Arc<Peer>>>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    stop: AtomicBool,
+    /// Транзакции, недавно полученные от любого пира: защищает от повторных запросов и петель рассылки
+    seen_transactions: Mutex<RecentIds>,
+    bans: Mutex<BTreeMap<IpAddr, i64>>,
+}
+
+impl NodeInner {
+    fn peers(&self) -> MutexGuard<'_, HashMap<SocketAddr, Arc<Peer>>> {
+        self.peers.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    fn seen_transactions(&self) -> MutexGuard<'_, RecentIds> {
+        self.seen_transactions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    fn bans(&self) -> MutexGuard<'_, BTreeMap<IpAddr, i64>> {
+        self.bans.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    /// Проверяет бан адреса, попутно удаляя истекшие баны
+    fn is_banned(&self, ip: IpAddr) -> bool {
+        let now = chrono::Utc::now().timestamp();
+        let mut bans = self.bans();
+        match bans.get(&ip) {
+            Some(until) if *until > now => true,
+            Some(_) => {
+                bans.remove(&ip);
+                drop(bans);
+                self.save_bans();
+                false
+            },
+            None => false,
+        }
+    }
+    
+    /// Банит адрес и отключает все соединения с него
+    fn ban(&self, ip: IpAddr, duration: Duration) {
+        let until = chrono::Utc::now().timestamp().saturating_add(duration.as_secs() as i64);
+        self.bans().insert(ip, until);
+        self.save_bans();
+        
+        for peer in self.peers().values().filter(|peer| peer.addr.ip() == ip) {
+            peer.disconnect();
+        }
+    }
+    
+    fn save_bans(&self) {
+        let Some(path) = &self.config.ban_file else { return };
+        let bans: BTreeMap<String, i64> = self.bans().iter().map(|(ip, until)| (ip.to_string(), *until)).collect();
+        let saved = serde_json::to_vec_pretty(&bans).map_err(io::Error::other).and_then(|bytes| fs::write(path, bytes));
+        if let Err(e) = saved {
+            warn!(path = %path.display(), error = %e, "Cannot save bans");
+        }
+    }
+    
+    /// Начисляет пиру штрафные баллы; при превышении порога банит его и возвращает ошибку для отключения
+    fn penalize(&self, peer: &Peer, misbehavior: Misbehavior) -> io::Result<()> {
+        let weight = misbehavior.weight();
+        if weight == 0 {
+            return Ok(());
+        }
+        
+        let score = peer.score.fetch_add(weight, Ordering::Relaxed).saturating_add(weight);
+        if score < self.config.ban_threshold {
+            return Ok(());
+        }
+        
+        warn!(peer = %peer.addr, score, ?misbehavior, "Banning peer");
+        self.ban(peer.addr.ip(), self.config.ban_duration);
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "peer is banned"))
+    }
+    
+    /// Передает число подключенных пиров в метрики узла
+    fn publish_peer_count(&self) {
+        #[cfg(feature = "metrics")]
+        {
+            let count = self.peers().len();
+            self.chain.read().set_peer_count(count);
+        }
+    }
+    
+    fn spawn(&self, task: impl FnOnce() + Send + 'static) {
+        let handle = thread::spawn(task);
+        self.threads.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(handle);
+    }
+    
+    fn is_connected_to(&self, listen_addr: SocketAddr) -> bool {
+        listen_addr == self.local_addr
+            || self.peers().values().any(|peer| peer.addr == listen_addr || peer.listen_addr() == Some(listen_addr))
+    }
+    
+    fn broadcast(&self, message: &Message) {
+        let peers: Vec<Arc<Peer>> = self.peers().values().filter(|peer| peer.is_handshaken()).cloned().collect();
+        for peer in peers {
+            if peer.send(message).is_err() {
+                let _ = peer.writer.lock().map(|stream| stream.shutdown(Shutdown::Both));
+            }
+        }
+    }
+    
+    /// Объявляет транзакцию пирам, которые о ней еще не знают
+    fn announce_transaction(&self, id: String) {
+        let peers: Vec<Arc<Peer>> = self.peers().values().filter(|peer| peer.is_handshaken()).cloned().collect();
+        let message = Message::InvTransactions { ids: vec![id.clone()] };
+        for peer in peers {
+            if !peer.known_transactions().insert(&id) {
+                continue;
+            }
+            if peer.send(&message).is_err() {
+                let _ = peer.writer.lock().map(|stream| stream.shutdown(Shutdown::Both));
+            }
+        }
+    }
+}
+
+/// P2P-узел: принимает соединения, обменивается блоками и поддерживает список пиров
+pub struct Node {
+    inner: Arc<NodeInner>,
+    hook_id: HookId,
+}
+
+impl Node {
+    /// Запускает узел: открывает порт, подключается к начальным пирам и начинает рассылать новые блоки
+    pub fn start(config: NetworkConfig, chain: SharedBlockchain) -> io::Result<Node> {
+        let listener = TcpListener::bind(config.listen_addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        
+        let inner = Arc::new(NodeInner {
+            chain: chain.clone(),
+            config: config.clone(),
+            local_addr,
+            peers: Mutex::new(HashMap::new()),
+            threads: Mutex::new(Vec::new()),
+            stop: AtomicBool::new(false),
+            seen_transactions: Mutex::new(RecentIds::new(SEEN_CACHE_CAPACITY)),
+            bans: Mutex::new(load_bans(&config)?),
+        });
+        
+        // Обработчик событий вызывается под блокировкой цепочки, поэтому только кладет объявление в очередь
+        let (announce_sender, announce_receiver) = mpsc::sync_channel::<Announcement>(ANNOUNCE_QUEUE_CAPACITY);
+        let hook_id = chain.write().add_event_hook(Box::new(move |event| {
+            match event {
+                ChainEvent::BlockAdded { block } => { let _ = announce_sender.try_send(Announcement::Block(block.clone())); },
+                ChainEvent::TransactionQueued { transaction } => { let _ = announce_sender.try_send(Announcement::Transaction(transaction.id.clone())); },
+                _ => {},
+            }
+            true
+        }));
+        
+        let broadcaster = inner.clone();
+        inner.spawn(move || {
+            while !broadcaster.stop.load(Ordering::Relaxed) {
+                match announce_receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(Announcement::Block(block)) => broadcaster.broadcast(&Message::NewBlock { block }),
+                    Ok(Announcement::Transaction(id)) => broadcaster.announce_transaction(id),
+                    Err(RecvTimeoutError::Timeout) => {},
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+        
+        let acceptor = inner.clone();
+        inner.spawn(move || {
+            while !acceptor.stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let peer_node = acceptor.clone();
+                        acceptor.spawn(move || run_peer(peer_node, stream, false));
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                    Err(_) => thread::sleep(POLL_INTERVAL),
+                }
+            }
+        });
+        
+        let node = Node { inner, hook_id };
+        for seed in &config.seed_peers {
+            let _ = node.connect(*seed);
+        }
+        
+        Ok(node)
+    }
+    
+    /// Устанавливает исходящее соединение с пиром
+    pub fn connect(&self, addr: SocketAddr) -> io::Result<()> {
+        connect_peer(&self.inner, addr)
+    }
+    
+    /// Возвращает адрес, на котором узел принимает соединения
+    pub fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr
+    }
+    
+    /// Возвращает разделяемый блокчейн узла
+    pub fn chain(&self) -> SharedBlockchain {
+        self.inner.chain.clone()
+    }
+    
+    /// Возвращает сведения о пирах, прошедших рукопожатие, вместе с их штрафными баллами
+    pub fn list_peers(&self) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self.inner.peers().values()
+            .filter(|peer| peer.is_handshaken())
+            .map(|peer| PeerInfo {
+                addr: peer.addr,
+                listen_addr: peer.listen_addr(),
+                height: peer.height.load(Ordering::Relaxed),
+                outbound: peer.outbound,
+                score: peer.score.load(Ordering::Relaxed),
+            })
+            .collect();
+        peers.sort_by_key(|peer| peer.addr);
+        peers
+    }
+    
+    /// Банит адрес на срок из настроек и разрывает соединения с ним
+    pub fn ban(&self, ip: IpAddr) {
+        self.inner.ban(ip, self.inner.config.ban_duration);
+    }
+    
+    /// Снимает бан; возвращает true, если адрес был забанен
+    pub fn unban(&self, ip: IpAddr) -> bool {
+        let removed = self.inner.bans().remove(&ip).is_some();
+        if removed {
+            self.inner.save_bans();
+        }
+        removed
+    }
+    
+    /// Возвращает действующие баны
+    pub fn list_bans(&self) -> Vec<BanInfo> {
+        let now = chrono::Utc::now().timestamp();
+        self.inner.bans().iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(ip, until)| BanInfo { ip: *ip, until: *until })
+            .collect()
+    }
+    
+    /// Синхронизирует цепочку с подключенными пирами по схеме «сначала заголовки»
+    pub fn sync(&self, options: &SyncOptions) -> SyncReport {
+        let peers: Vec<NetworkSyncPeer> = self.inner.peers().values()
+            .filter(|peer| peer.is_handshaken())
+            .map(|peer| NetworkSyncPeer { peer: peer.clone() })
+            .collect();
+        let sources: Vec<&dyn SyncPeer> = peers.iter().map(|peer| peer as &dyn SyncPeer).collect();
+        sync::sync_chain(&self.inner.chain, &sources, options)
+    }
+    
+    /// Останавливает сетевые потоки и закрывает соединения
+    pub fn shutdown(self) {
+        // Вся работа выполняется в Drop
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        self.inner.stop.store(true, Ordering::Relaxed);
+        self.inner.chain.write().remove_event_hook(self.hook_id);
+        
+        for peer in self.inner.peers().values() {
+            let _ = peer.writer.lock().map(|stream| stream.shutdown(Shutdown::Both));
+        }
+        
+        loop {
+            let handles: Vec<JoinHandle<()>> = std::mem::take(&mut *self.inner.threads.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+            if handles.is_empty() {
+                break;
+            }
+            for handle in handles {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+/// Загружает сохраненные баны, пропуская истекшие
+fn load_bans(config: &NetworkConfig) -> io::Result<BTreeMap<IpAddr, i64>> {
+    let Some(path) = config.ban_file.as_ref().filter(|path| path.exists()) else {
+        return Ok(BTreeMap::new());
+    };
+    
+    let saved: BTreeMap<String, i64> = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let now = chrono::Utc::now().timestamp();
+    Ok(saved.into_iter()
+        .filter(|(_, until)| *until > now)
+        .filter_map(|(ip, until)| ip.parse().ok().map(|ip| (ip, until)))
+        .collect())
+}
+
+fn connect_peer(inner: &Arc<NodeInner>, addr: SocketAddr) -> io::Result<()> {
+    if inner.is_connected_to(addr) {
+        return Ok(());
+    }
+    if inner.is_banned(addr.ip()) {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "peer is banned"));
+    }
+    if inner.peers().len() >= inner.config.max_peers {
+        return Err(io::Error::other("peer limit reached"));
+    }
+    
+    let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    let peer_node = inner.clone();
+    inner.spawn(move || run_peer(peer_node, stream, true));
+    Ok(())
+}
+
+/// Обслуживает одно соединение: рукопожатие и цикл обработки сообщений
+fn run_peer(node: Arc<NodeInner>, stream: TcpStream, outbound: bool) {
+    let Ok(addr) = stream.peer_addr() else { return };
+    let Ok(writer) = stream.try_clone() else { return };
+    let _ = stream.set_read_timeout(Some(POLL_INTERVAL));
+    let _ = stream.set_nonblocking(false);
+    let _ = writer.set_write_timeout(Some(WRITE_TIMEOUT));
+    let _ = stream.set_nodelay(true);
+    
+    let peer = Arc::new(Peer {
+        addr,
+        outbound,
+        listen_addr: Mutex::new(None),
+        height: AtomicU64::new(0),
+        writer: Mutex::new(writer),
+        request_lock: Mutex::new(()),
+        response_slot: Mutex::new(None),
+        known_transactions: Mutex::new(RecentIds::new(PEER_KNOWN_CAPACITY)),
+        rejected_transactions: Mutex::new(RecentIds::new(PEER_KNOWN_CAPACITY)),
+        tx_limiter: Mutex::new(RateLimiter::new(TX_RATE_LIMIT)),
+        requested_transactions: Mutex::new(RecentIds::new(PEER_KNOWN_CAPACITY)),
+        score: AtomicU32::new(0),
+    });
+    
+    if node.is_banned(addr.ip()) {
+        let _ = peer.send(&Message::Reject { reason: "banned".to_string() });
+        return;
+    }
+    
+    {
+        let mut peers = node.peers();
+        if peers.len() >= node.config.max_peers {
+            let _ = peer.send(&Message::Reject { reason: "too many peers".to_string() });
+            return;
+        }
+        peers.insert(addr, peer.clone());
+    }
+    node.publish_peer_count();
+    
+    let version = {
+        let chain = node.chain.read();
+        Message::Version {
+            protocol: PROTOCOL_VERSION,
+            genesis_hash: chain.chain[0].hash.clone(),
+            height: chain.get_latest_block().index,
+            listen_addr: node.local_addr,
+        }
+    };
+    
+    let mut reader = stream;
+    if peer.send(&version).is_ok() {
+        while !node.stop.load(Ordering::Relaxed) {
+            let frame = match read_frame_until(&mut reader, &node.stop) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::InvalidData {
+                        let _ = node.penalize(&peer, Misbehavior::OversizedFrame);
+                    }
+                    break;
+                },
+            };
+            
+            // Неразбираемый кадр не ломает поток, поэтому соединение закрывается только по сумме баллов
+            let handled = match serde_json::from_slice(&frame) {
+                Ok(message) => handle_message(&node, &peer, message),
+                Err(_) => node.penalize(&peer, Misbehavior::MalformedMessage),
+            };
+            if handled.is_err() {
+                break;
+            }
+        }
+    }
+    
+    let _ = reader.shutdown(Shutdown::Both);
+    node.peers().remove(&addr);
+    node.publish_peer_count();
+}
+
+fn handle_message(node: &Arc<NodeInner>, peer: &Arc<Peer>, message: Message) -> io::Result<()> {
+    if !peer.is_handshaken() && !matches!(message, Message::Version { .. }) {
+        let _ = node.penalize(peer, Misbehavior::MalformedMessage);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message before handshake"));
+    }
+    
+    match message {
+        Message::Version { protocol, genesis_hash, height, listen_addr } => {
+            let our_genesis = node.chain.read().chain[0].hash.clone();
+            let reason = if protocol != PROTOCOL_VERSION {
+                Some(format!("unsupported protocol {}", protocol))
+            } else if genesis_hash != our_genesis {
+                Some("genesis mismatch".to_string())
+            } else if listen_addr == node.local_addr {
+                Some("self connection".to_string())
+            } else if node.peers().values().any(|other| !Arc::ptr_eq(other, peer) && other.listen_addr() == Some(listen_addr)) {
+                Some("duplicate connection".to_string())
+            } else {
+                None
+            };
+            
+            if let Some(reason) = reason {
+                let _ = peer.send(&Message::Reject { reason: reason.clone() });
+                return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+            }
+            
+            *peer.listen_addr.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(listen_addr);
+            peer.height.store(height, Ordering::Relaxed);
+            request_missing_blocks(node, peer)?;
+            peer.send(&Message::GetPeers)
+        },
+        Message::Reject { reason } => Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason)),
+        Message::NewBlock { block } => {
+            peer.height.fetch_max(block.index, Ordering::Relaxed);
+            let (outcome, extends_tip) = {
+                let mut chain = node.chain.write();
+                let extends_tip = block.previous_hash == chain.get_latest_block().hash;
+                (chain.accept_block(block), extends_tip)
+            };
+            match outcome {
+                Ok(BlockAcceptance::Orphaned) => request_missing_blocks(node, peer),
+                Ok(_) => Ok(()),
+                // Блок не на нашей вершине мог прийти с конкурирующей ветки
+                Err(_) if !extends_tip => node.penalize(peer, Misbehavior::StaleBlock),
+                Err(_) => node.penalize(peer, Misbehavior::InvalidBlock),
+            }
+        },
+        Message::GetBlocks { from, to } => {
+            let blocks: Vec<Block> = {
+                let chain = node.chain.read();
+                let to = to.min(from.saturating_add(MAX_BLOCKS_PER_MESSAGE - 1));
+                chain.chain.iter()
+                    .filter(|block| block.index >= from && block.index <= to)
+                    .cloned()
+                    .collect()
+            };
+            peer.send(&Message::Blocks { blocks })
+        },
+        Message::Blocks { blocks } => {
+            let (advanced, invalid) = {
+                let mut chain = node.chain.write();
+                let before = chain.get_latest_block().index;
+                let mut invalid = false;
+                for block in blocks {
+                    peer.height.fetch_max(block.index, Ordering::Relaxed);
+                    let extends_tip = block.previous_hash == chain.get_latest_block().hash;
+                    if chain.accept_block(block).is_err() {
+                        invalid = extends_tip;
+                        break;
+                    }
+                }
+                (chain.get_latest_block().index > before, invalid)
+            };
+            if invalid {
+                node.penalize(peer, Misbehavior::InvalidBlock)?;
+            }
+            // Продолжаем запрашивать, только если цепочка продвинулась, иначе можно зациклиться на чужой ветке
+            if advanced {
+                request_missing_blocks(node, peer)?;
+            }
+            Ok(())
+        },
+        Message::GetPeers => {
+            let addrs: Vec<SocketAddr> = node.peers().values()
+                .filter(|other| !Arc::ptr_eq(other, peer))
+                .filter_map(|other| other.listen_addr())
+                .collect();
+            peer.send(&Message::Peers { addrs })
+        },
+        Message::GetHeaders { from, count } => {
+            let headers: Vec<BlockHeader> = {
+                let chain = node.chain.read();
+                chain.chain.iter()
+                    .skip(from as usize)
+                    .take(count.min(MAX_HEADERS_PER_MESSAGE) as usize)
+                    .map(Block::header)
+                    .collect()
+            };
+            peer.send(&Message::Headers { headers })
+        },
+        Message::GetBodies { from, to } => {
+            let blocks: Vec<Block> = {
+                let chain = node.chain.read();
+                let to = to.min(from.saturating_add(MAX_BLOCKS_PER_MESSAGE - 1));
+                chain.chain.iter()
+                    .filter(|block| block.index >= from && block.index <= to)
+                    .cloned()
+                    .collect()
+            };
+            peer.send(&Message::Bodies { blocks })
+        },
+        message @ (Message::Headers { .. } | Message::Bodies { .. }) => {
+            if peer.deliver_response(message) {
+                Ok(())
+            } else {
+                node.penalize(peer, Misbehavior::Unsolicited)
+            }
+        },
+        Message::InvTransactions { ids } => {
+            let wanted: Vec<String> = {
+                let chain = node.chain.read();
+                let seen = node.seen_transactions();
+                let mut known = peer.known_transactions();
+                let rejected = peer.rejected_transactions();
+                ids.into_iter()
+                    .take(MAX_INV_PER_MESSAGE)
+                    .filter(|id| {
+                        known.insert(id);
+                        !seen.contains(id) && !rejected.contains(id)
+                            && !chain.mempool().contains(id)
+                    })
+                    .collect()
+            };
+            
+            if wanted.is_empty() {
+                Ok(())
+            } else {
+                let mut requested = peer.requested_transactions();
+                for id in &wanted {
+                    requested.insert(id);
+                }
+                drop(requested);
+                peer.send(&Message::GetTransactions { ids: wanted })
+            }
+        },
+        Message::GetTransactions { ids } => {
+            let transactions: Vec<Transaction> = {
+                let chain = node.chain.read();
+                ids.iter()
+                    .take(MAX_INV_PER_MESSAGE)
+                    .filter_map(|id| chain.mempool().get(id))
+                    .collect()
+            };
+            if transactions.is_empty() {
+                Ok(())
+            } else {
+                peer.send(&Message::Transactions { transactions })
+            }
+        },
+        Message::Transactions { transactions } => {
+            for transaction in transactions {
+                if !peer.tx_limiter.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).try_take() {
+                    return node.penalize(peer, Misbehavior::Unsolicited);
+                }
+                
+                let id = transaction.id.clone();
+                if !peer.requested_transactions().contains(&id) {
+                    node.penalize(peer, Misbehavior::Unsolicited)?;
+                    continue;
+                }
+                peer.requested_transactions().remove(&id);
+                peer.known_transactions().insert(&id);
+                if !node.seen_transactions().insert(&id) {
+                    continue;
+                }
+                
+                // Принятая транзакция объявляется остальным пирам через событие TransactionQueued
+                let result = node.chain.write().add_transaction(transaction);
+                match result {
+                    Err(error) if is_policy_rejection(&error) => {
+                        node.seen_transactions().remove(&id);
+                        peer.rejected_transactions().insert(&id);
+                    },
+                    // Нехватку средств не штрафуем: баланс отправителя у пира мог отличаться от нашего
+                    Err(
+                        BlockchainError::InvalidTransaction(_) | BlockchainError::InvalidSignature { .. } | BlockchainError::FeeTooLow { .. }
+                        | BlockchainError::WalletNotFound { .. } | BlockchainError::AccountFrozen { .. } | BlockchainError::FeatureDisabled { .. }
+                        | BlockchainError::WalletClosed { .. } | BlockchainError::WrongLedgerMode { .. } | BlockchainError::WrongNetwork { .. }
+                    ) => node.penalize(peer, Misbehavior::InvalidTransaction)?,
+                    _ => {},
+                }
+            }
+            Ok(())
+        },
+        Message::Peers { addrs } => {
+            for addr in addrs {
+                if !node.stop.load(Ordering::Relaxed) && node.peers().len() < node.config.max_peers {
+                    let _ = connect_peer(node, addr);
+                }
+            }
+            Ok(())
+        },
+    }
+}
+
+/// Запрашивает у пира блоки, которых не хватает до его известной высоты
+fn request_missing_blocks(node: &Arc<NodeInner>, peer: &Arc<Peer>) -> io::Result<()> {
+    let our_height = node.chain.read().get_latest_block().index;
+    let peer_height = peer.height.load(Ordering::Relaxed);
+    if peer_height > our_height {
+        let to = peer_height.min(our_height + MAX_BLOCKS_PER_MESSAGE);
+        peer.send(&Message::GetBlocks { from: our_height + 1, to })?;
+    }
+    
+    Ok(())
+}
+
+/// Пир сети как источник синхронизации
+struct NetworkSyncPeer {
+    peer: Arc<Peer>,
+}
+
+impl NetworkSyncPeer {
+    fn unavailable(&self, error: io::Error) -> SyncError {
+        SyncError::PeerUnavailable { peer: self.id(), reason: error.to_string() }
+    }
+}
+
+impl SyncPeer for NetworkSyncPeer {
+    fn id(&self) -> String {
+        self.peer.addr.to_string()
+    }
+    
+    fn get_headers(&self, from: u64, count: u64) -> Result<Vec<BlockHeader>, SyncError> {
+        match self.peer.request(&Message::GetHeaders { from, count }).map_err(|e| self.unavailable(e))? {
+            Message::Headers { headers } => Ok(headers),
+            _ => Err(SyncError::PeerUnavailable { peer: self.id(), reason: "unexpected response".to_string() }),
+        }
+    }
+    
+    fn get_bodies(&self, from: u64, to: u64) -> Result<Vec<Block>, SyncError> {
+        match self.peer.request(&Message::GetBodies { from, to }).map_err(|e| self.unavailable(e))? {
+            Message::Bodies { blocks } => Ok(blocks),
+            _ => Err(SyncError::PeerUnavailable { peer: self.id(), reason: "unexpected response".to_string() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+    use crate::signing::Keypair;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    /// Ждет выполнения условия не дольше десяти секунд
+    fn wait_until(condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if condition() {
+                return true;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        condition()
+    }
+    
+    fn start_node(chain: Blockchain) -> Node {
+        Node::start(NetworkConfig::new("127.0.0.1:0".parse().unwrap()), SharedBlockchain::new(chain)).unwrap()
+    }
+    
+    /// Два узла с общим генезисом; у первого уже есть блок с наградой `alice` и ее ключ.
+    /// Второй подключается к первому и дожидается рукопожатия.
+    fn connected_pair() -> (Node, Node, Keypair) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let alice = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        
+        let first = start_node(chain);
+        let second = start_node(peer);
+        second.connect(first.local_addr()).unwrap();
+        assert!(wait_until(|| first.list_peers().len() == 1 && second.list_peers().len() == 1));
+        (first, second, alice)
+    }
+    
+    fn transfer(chain: &Blockchain, from: &str, to: &str, amount: f64, keypair: &Keypair) -> Transaction {
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(from));
+        tx.sign(keypair);
+        tx
+    }
+    
+    fn same_tip(first: &Node, second: &Node) -> bool {
+        first.chain().read().get_latest_block().hash == second.chain().read().get_latest_block().hash
+    }
+    
+    #[test]
+    fn nodes_converge_on_a_signed_transfer() {
+        let (first, second, alice) = connected_pair();
+        // Блок, добытый до подключения, второй узел запрашивает при рукопожатии
+        assert!(wait_until(|| same_tip(&first, &second)));
+        
+        {
+            let shared = first.chain();
+            let mut chain = shared.write();
+            let tx = transfer(&chain, "alice", "bob", 10.0, &alice);
+            chain.add_transaction(tx).unwrap();
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        
+        assert!(wait_until(|| same_tip(&first, &second)));
+        let (first_chain, second_chain) = (first.chain(), second.chain());
+        let (first_chain, second_chain) = (first_chain.read(), second_chain.read());
+        assert_eq!(second_chain.chain.len(), 3);
+        assert_eq!(second_chain.get_balance("bob"), 10.0);
+        for address in ["alice", "bob", "miner"] {
+            assert_eq!(second_chain.get_balance(address), first_chain.get_balance(address), "{}", address);
+        }
+        assert_eq!(second_chain.registered_key("alice"), Some(alice.public_key_hex().as_str()));
+    }
+    
+    #[test]
+    fn relayed_transaction_is_mined_by_the_peer() {
+        let (first, second, alice) = connected_pair();
+        assert!(wait_until(|| same_tip(&first, &second)));
+        
+        let tx = transfer(&first.chain().read(), "alice", "bob", 10.0, &alice);
+        let tx_id = tx.id.clone();
+        first.chain().add_transaction(tx).unwrap();
+        let pending = |node: &Node| node.chain().write().mempool().transactions().iter().any(|pending| pending.id == tx_id);
+        assert!(wait_until(|| pending(&second)));
+        
+        second.chain().write().mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(wait_until(|| same_tip(&first, &second) && !pending(&first)));
+        assert_eq!(first.chain().read().get_balance("bob"), 10.0);
+    }
+    
+    /// Соединение, которое ведет себя как пир: рукопожатие, затем сообщения вручную
+    struct FakePeer {
+        stream: TcpStream,
+    }
+    
+    impl FakePeer {
+        /// Подключается и возвращает первое сообщение узла: `Version` или `Reject`
+        fn connect(node: &Node) -> (FakePeer, Message) {
+            let stream = TcpStream::connect(node.local_addr()).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            let mut peer = FakePeer { stream };
+            let first = peer.receive().unwrap();
+            (peer, first)
+        }
+        
+        fn handshake(node: &Node) -> FakePeer {
+            let (mut peer, first) = FakePeer::connect(node);
+            let Message::Version { genesis_hash, .. } = first else { panic!("{:?}", first) };
+            let listen_addr = "127.0.0.1:9".parse().unwrap();
+            peer.send(&Message::Version { protocol: PROTOCOL_VERSION, genesis_hash, height: 0, listen_addr });
+            peer
+        }
+        
+        fn send(&mut self, message: &Message) {
+            write_message(&mut self.stream, message).unwrap();
+        }
+        
+        fn send_junk(&mut self) {
+            self.stream.write_all(&4u32.to_be_bytes()).unwrap();
+            self.stream.write_all(b"junk").unwrap();
+        }
+        
+        /// Следующее сообщение узла; `None`, когда узел закрыл соединение
+        fn receive(&mut self) -> Option<Message> {
+            read_message(&mut self.stream).ok()
+        }
+    }
+    
+    fn score(node: &Node) -> Option<u32> {
+        node.list_peers().first().map(|peer| peer.score)
+    }
+    
+    /// Блок, который не проходит проверку: у копии цепочки добыт следующий блок, затем подменен nonce
+    fn broken_block(chain: &Blockchain) -> Block {
+        let mut copy: Blockchain = serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap();
+        copy.mine_pending_transactions("forger".to_string()).unwrap();
+        let mut block = copy.get_latest_block().clone();
+        block.nonce = block.nonce.wrapping_add(1);
+        block
+    }
+    
+    #[test]
+    fn escalating_violations_ban_the_peer_until_expiry() {
+        let genesis_only = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let mut chain: Blockchain = serde_json::from_str(&serde_json::to_string(&genesis_only).unwrap()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let invalid = broken_block(&chain);
+        let stale = broken_block(&genesis_only);
+        
+        let ban_file = std::env::temp_dir().join(format!("hellochain-bans-{}.json", std::process::id()));
+        let _ = fs::remove_file(&ban_file);
+        let mut config = NetworkConfig::new("127.0.0.1:0".parse().unwrap());
+        config.ban_duration = Duration::from_secs(3);
+        config.ban_file = Some(ban_file.clone());
+        let node = Node::start(config.clone(), SharedBlockchain::new(chain)).unwrap();
+        
+        let mut peer = FakePeer::handshake(&node);
+        assert!(wait_until(|| score(&node) == Some(0)));
+        peer.send_junk();
+        peer.send_junk();
+        assert!(wait_until(|| score(&node) == Some(2 * Misbehavior::MalformedMessage.weight())));
+        // Блок с конкурирующей ветки считается честной ошибкой
+        peer.send(&Message::NewBlock { block: stale });
+        peer.send(&Message::NewBlock { block: invalid });
+        assert!(wait_until(|| score(&node) == Some(2 * Misbehavior::MalformedMessage.weight() + Misbehavior::InvalidBlock.weight())));
+        assert!(node.list_bans().is_empty());
+        
+        peer.send_junk();
+        while peer.receive().is_some() {}
+        assert!(wait_until(|| node.list_peers().is_empty()));
+        let localhost: IpAddr = "127.0.0.1".parse().unwrap();
+        assert_eq!(node.list_bans().iter().map(|ban| ban.ip).collect::<Vec<_>>(), [localhost]);
+        
+        // Бан переживает перезапуск, пока не снят вручную
+        let restarted = Node::start(config, SharedBlockchain::new(genesis_only)).unwrap();
+        assert_eq!(restarted.list_bans(), node.list_bans());
+        let (mut refused, reply) = FakePeer::connect(&restarted);
+        assert!(matches!(reply, Message::Reject { reason } if reason == "banned"));
+        assert!(refused.receive().is_none());
+        assert!(restarted.unban(localhost));
+        assert!(matches!(FakePeer::connect(&restarted).1, Message::Version { .. }));
+        
+        assert!(matches!(FakePeer::connect(&node).1, Message::Reject { .. }));
+        assert!(wait_until(|| node.list_bans().is_empty()));
+        assert!(matches!(FakePeer::connect(&node).1, Message::Version { .. }));
+        let _ = fs::remove_file(&ban_file);
+    }
+}
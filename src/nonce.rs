@@ -0,0 +1,146 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::trace::info;
+use crate::transaction::Transaction;
+use std::collections::HashMap;
+
+/// Проверяет, что у транзакции ровно ожидаемый порядковый номер
+fn check_expected_nonce(tx: &Transaction, expected: u64) -> Result<(), BlockchainError> {
+    match tx.nonce {
+        None => Err(BlockchainError::InvalidTransaction(format!("Transaction {} has no nonce", tx.id))),
+        Some(found) if found != expected => Err(BlockchainError::InvalidNonce { address: tx.sender.clone(), expected, found }),
+        Some(_) => Ok(()),
+    }
+}
+
+impl Blockchain {
+    /// Порядковый номер, который должна нести следующая транзакция отправителя, с учетом его
+    /// транзакций в пуле
+    pub fn get_account_nonce(&self, address: &str) -> u64 {
+        self.next_nonce(address, &self.mempool.lock().transactions)
+    }
+    
+    /// Следующий номер отправителя после подтвержденных блоков и транзакций `pending`
+    pub(crate) fn next_nonce(&self, address: &str, pending: &[Transaction]) -> u64 {
+        pending.iter()
+            .filter(|tx| tx.sender == address)
+            .filter_map(|tx| tx.nonce)
+            .map(|nonce| nonce + 1)
+            .fold(self.confirmed_nonce(address), u64::max)
+    }
+    
+    /// Следующий номер отправителя по подтвержденным блокам
+    fn confirmed_nonce(&self, address: &str) -> u64 {
+        self.wallets.get(address).map_or(0, |wallet| wallet.next_nonce)
+    }
+    
+    /// Отклоняет повтор и пропуск номера: транзакция, которой нужна подпись отправителя, продолжает
+    /// его номера в пуле `pending` без промежутков
+    pub(crate) fn check_nonce(&self, tx: &Transaction, pending: &[Transaction]) -> Result<(), BlockchainError> {
+        if !tx.needs_sender_signature() {
+            return Ok(());
+        }
+        check_expected_nonce(tx, self.next_nonce(&tx.sender, pending))
+    }
+    
+    /// Проверяет, что номера транзакций каждого отправителя в блоке идут подряд от его
+    /// подтвержденного номера. Канонический порядок ставит их по возрастанию номера.
+    pub(crate) fn check_block_nonces(&self, block: &Block) -> Result<(), BlockchainError> {
+        let mut expected: HashMap<&str, u64> = HashMap::new();
+        for tx in block.transactions.iter().filter(|tx| tx.needs_sender_signature()) {
+            let next = expected.entry(tx.sender.as_str()).or_insert_with(|| self.confirmed_nonce(&tx.sender));
+            check_expected_nonce(tx, *next)?;
+            *next += 1;
+        }
+        Ok(())
+    }
+    
+    /// Продвигает подтвержденные номера отправителей по транзакциям блока
+    pub(crate) fn apply_account_nonces(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            let Some(nonce) = tx.nonce else {
+                continue;
+            };
+            if let Some(wallet) = self.wallets.get_mut(&tx.sender) {
+                wallet.next_nonce = wallet.next_nonce.max(nonce + 1);
+            }
+        }
+    }
+    
+    /// Убирает из пула транзакции, номер которых уже занят подтвержденной транзакцией того же
+    /// отправителя; их потомки в пуле уходят вместе с ними
+    pub(crate) fn drop_replaced_nonces(&mut self) {
+        let replaced: Vec<String> = self.mempool.lock().transactions.iter()
+            .filter(|tx| tx.nonce.is_some_and(|nonce| nonce < self.confirmed_nonce(&tx.sender)))
+            .map(|tx| tx.id.clone())
+            .collect();
+        for tx_id in replaced {
+            if let Ok(transaction) = self.cancel_pending_transaction(&tx_id) {
+                info!(tx_id = %transaction.id, nonce = transaction.nonce, "Pending transaction replaced by a confirmed nonce");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::Keypair;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    /// Цепочка с наградой у `alice`; номер 0 занимает закрепление ее ключа
+    fn funded_chain() -> (Blockchain, Keypair) {
+        let chain = ChainFixture::funded().build().unwrap().chain;
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        (chain, keypair)
+    }
+    
+    fn transfer(nonce: u64, amount: f64, keypair: &Keypair) -> Transaction {
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(nonce);
+        tx.sign(keypair);
+        tx
+    }
+    
+    #[test]
+    fn confirmed_transaction_cannot_be_replayed() {
+        let (mut chain, keypair) = funded_chain();
+        let tx = transfer(1, 10.0, &keypair);
+        chain.add_transaction(tx.clone()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let balance = chain.get_balance("alice");
+        
+        assert!(chain.add_transaction(tx).is_err());
+        assert_eq!(chain.get_balance("alice"), balance);
+        assert!(matches!(
+            chain.add_transaction(transfer(1, 5.0, &keypair)),
+            Err(BlockchainError::InvalidNonce { expected: 2, found: 1, .. })
+        ));
+    }
+    
+    #[test]
+    fn nonce_gap_is_rejected() {
+        let (mut chain, keypair) = funded_chain();
+        assert!(matches!(
+            chain.add_transaction(transfer(2, 10.0, &keypair)),
+            Err(BlockchainError::InvalidNonce { expected: 1, found: 2, .. })
+        ));
+        chain.add_transaction(transfer(1, 10.0, &keypair)).unwrap();
+        chain.add_transaction(transfer(2, 10.0, &keypair)).unwrap();
+    }
+    
+    #[test]
+    fn block_with_a_nonce_gap_is_rejected() {
+        let (mut chain, keypair) = funded_chain();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        // Узел-нарушитель кладет транзакцию в пул в обход проверок
+        chain.mempool.get_mut().transactions.push(transfer(3, 10.0, &keypair));
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let block = chain.get_latest_block().clone();
+        assert!(matches!(peer.verify_block(&block), Err(BlockchainError::InvalidNonce { .. })));
+        assert!(peer.accept_block(block).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use crate::block::Block;
+use crate::errors::BlockchainError;
+use crate::governance::GOVERNANCE_ESCROW;
+use crate::packages::rate_weight;
+use crate::staking::STAKING_MINTER;
+use crate::transaction::Transaction;
+use crate::treasury::TREASURY_MINTER;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Системная транзакция из конца блока: выплата стейкинга, доля казны, итог голосования или награда
+pub(crate) fn is_coinbase(tx: &Transaction) -> bool {
+    ["BLOCKCHAIN_REWARD", TREASURY_MINTER, STAKING_MINTER, GOVERNANCE_ESCROW].contains(&tx.sender.as_str())
+}
+
+/// Транзакции одного отправителя подряд, по порядковому номеру, времени создания, затем по идентификатору
+fn by_sender(a: &Transaction, b: &Transaction) -> Ordering {
+    a.sender.cmp(&b.sender).then(a.nonce.cmp(&b.nonce)).then(a.timestamp.cmp(&b.timestamp)).then_with(|| a.id.cmp(&b.id))
+}
+
+/// Группы, от которых зависит каждая группа отправителя: их транзакции пополняют ее отправителя
+fn group_parents(groups: &[&[(usize, &Transaction)]]) -> Vec<Vec<usize>> {
+    let by_sender: HashMap<&str, usize> = groups.iter().enumerate()
+        .filter_map(|(g, group)| group.first().map(|(_, tx)| (tx.sender.as_str(), g)))
+        .collect();
+    let by_id: HashMap<&str, usize> = groups.iter().enumerate()
+        .flat_map(|(g, group)| group.iter().map(move |(_, tx)| (tx.id.as_str(), g)))
+        .collect();
+    
+    let mut parents = vec![Vec::new(); groups.len()];
+    for (g, group) in groups.iter().enumerate() {
+        for (_, tx) in group.iter() {
+            // Перевод UTXO-цепочки тратит только свои входы, поэтому зависит лишь от их создателей
+            if tx.inputs.is_empty() {
+                if let Some(child) = by_sender.get(tx.receiver.as_str()) {
+                    parents[*child].push(g);
+                }
+            }
+            parents[g].extend(tx.inputs.iter().filter_map(|input| by_id.get(input.outpoint.txid.as_str())));
+        }
+    }
+    for (g, list) in parents.iter_mut().enumerate() {
+        list.retain(|&parent| parent != g);
+        list.sort_unstable();
+        list.dedup();
+    }
+    parents
+}
+
+/// Переставляет группу так, чтобы транзакция, тратящая выход другой транзакции группы, шла после нее;
+/// остальные транзакции сохраняют свой порядок
+fn spenders_after_creators(group: &mut [(usize, &Transaction)]) {
+    let mut pending: Vec<(usize, &Transaction)> = group.to_vec();
+    let mut placed = 0;
+    while !pending.is_empty() {
+        let waits = |tx: &Transaction| tx.inputs.iter().any(|input| pending.iter().any(|(_, other)| other.id == input.outpoint.txid));
+        let next = pending.iter().position(|(_, tx)| !waits(tx)).unwrap_or(0);
+        group[placed] = pending.remove(next);
+        placed += 1;
+    }
+}
+
+/// Предки группы без нее самой, по возрастанию номера
+fn group_ancestors(parents: &[Vec<usize>], g: usize) -> Vec<usize> {
+    let mut visited = vec![false; parents.len()];
+    visited[g] = true;
+    let mut stack = parents[g].clone();
+    while let Some(parent) = stack.pop() {
+        if !visited[parent] {
+            visited[parent] = true;
+            stack.extend(&parents[parent]);
+        }
+    }
+    visited[g] = false;
+    (0..parents.len()).filter(|&other| visited[other]).collect()
+}
+
+/// Канонический порядок обычных транзакций блока в виде перестановки индексов. Транзакции одного
+/// отправителя образуют группу и идут подряд по номеру, времени создания и идентификатору. Группы выбираются
+/// по убыванию ставки пакета — суммы комиссий на сумму весов группы и еще не поставленных групп, которые
+/// пополняют ее отправителя, — при равенстве по адресу; перед группой ставятся ее предки. Если группы
+/// пополняют друг друга по кругу, круг разрывается в пользу группы, выбранной раньше.
+pub fn canonical_order(transactions: &[Transaction]) -> Vec<usize> {
+    let mut entries: Vec<(usize, &Transaction)> = transactions.iter().enumerate().collect();
+    entries.sort_by(|a, b| by_sender(a.1, b.1));
+    for group in entries.chunk_by_mut(|a, b| a.1.sender == b.1.sender) {
+        spenders_after_creators(group);
+    }
+    let groups: Vec<&[(usize, &Transaction)]> = entries.chunk_by(|a, b| a.1.sender == b.1.sender).collect();
+    
+    // Суммы группы складываются уже в каноническом порядке, чтобы не зависеть от порядка на входе
+    let totals: Vec<(f64, u64)> = groups.iter()
+        .map(|group| group.iter().fold((0.0, 0u64), |(fees, weight), (_, tx)| (fees + tx.fee, weight + rate_weight(tx))))
+        .collect();
+    let parents = group_parents(&groups);
+    let ancestors: Vec<Vec<usize>> = (0..groups.len()).map(|g| group_ancestors(&parents, g)).collect();
+    
+    if ancestors.iter().all(Vec::is_empty) {
+        let mut ranked: Vec<usize> = (0..groups.len()).collect();
+        ranked.sort_by(|&a, &b| (totals[b].0 / totals[b].1 as f64).total_cmp(&(totals[a].0 / totals[a].1 as f64)).then(a.cmp(&b)));
+        return ranked.into_iter().flat_map(|g| groups[g].iter().map(|(i, _)| *i)).collect();
+    }
+    
+    let mut placed = vec![false; groups.len()];
+    let package_rate = |g: usize, placed: &[bool]| {
+        let (fees, weight) = ancestors[g].iter()
+            .filter(|&&ancestor| !placed[ancestor])
+            .fold(totals[g], |(fees, weight), &ancestor| (fees + totals[ancestor].0, weight + totals[ancestor].1));
+        fees / weight as f64
+    };
+    
+    let mut order = Vec::with_capacity(transactions.len());
+    while let Some(best) = (0..groups.len())
+        .filter(|&g| !placed[g])
+        .map(|g| (g, package_rate(g, &placed)))
+        .reduce(|best, next| if next.1.total_cmp(&best.1).is_gt() { next } else { best })
+        .map(|(g, _)| g)
+    {
+        // Предки ставятся обходом в глубину: родитель раньше потомка, уже открытые группы круга пропускаются
+        let mut opened = vec![false; groups.len()];
+        opened[best] = true;
+        let mut stack = vec![(best, 0)];
+        while let Some(&(g, next)) = stack.last() {
+            match parents[g].get(next) {
+                Some(&parent) => {
+                    if let Some(top) = stack.last_mut() {
+                        top.1 += 1;
+                    }
+                    if !placed[parent] && !opened[parent] {
+                        opened[parent] = true;
+                        stack.push((parent, 0));
+                    }
+                },
+                None => {
+                    stack.pop();
+                    placed[g] = true;
+                    order.extend(groups[g].iter().map(|(i, _)| *i));
+                },
+            }
+        }
+    }
+    order
+}
+
+/// Переставляет транзакции в канонический порядок
+pub fn sort_canonical(transactions: Vec<Transaction>) -> Vec<Transaction> {
+    let order = canonical_order(&transactions);
+    let mut slots: Vec<Option<Transaction>> = transactions.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| slots.get_mut(i).and_then(Option::take)).collect()
+}
+
+/// Проверяет, что обычные транзакции блока идут в каноническом порядке, а системные — после них
+pub(crate) fn check_canonical_order(block: &Block) -> Result<(), BlockchainError> {
+    let split = block.transactions.iter().position(is_coinbase).unwrap_or(block.transactions.len());
+    let (regular, coinbase) = block.transactions.split_at(split);
+    if !coinbase.iter().all(is_coinbase) {
+        return Err(BlockchainError::InvalidBlock(format!("Block # {} has transactions after its coinbase", block.index)));
+    }
+    if !canonical_order(regular).into_iter().enumerate().all(|(position, i)| position == i) {
+        return Err(BlockchainError::InvalidBlock(format!("Block # {} transactions are not in canonical order", block.index)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+    use crate::clock::MockClock;
+    use crate::entropy::SeededEntropy;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    use std::sync::Arc;
+    
+    /// Цепочка с alice, bob и carol, у которых одинаковые ключи и время при каждой сборке
+    fn seeded_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.set_clock(Arc::new(MockClock::new(GENESIS_TIMESTAMP)));
+        chain.set_entropy(Arc::new(SeededEntropy::new(5)));
+        chain.mining_threads = Some(1);
+        for address in ["alice", "bob", "carol"] {
+            chain.create_wallet(address.to_string()).unwrap();
+        }
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("bob".to_string()).unwrap();
+        chain
+    }
+    
+    fn transfer(from: &str, to: &str, amount: f64, nonce: u64, tip: f64) -> Transaction {
+        let mut tx = Transaction::new_at(from.to_string(), to.to_string(), amount, TransactionType::Transfer, GENESIS_TIMESTAMP);
+        tx.fee += tip;
+        tx.set_nonce(nonce);
+        tx
+    }
+    
+    fn senders(transactions: &[Transaction]) -> Vec<(&str, Option<u64>)> {
+        transactions.iter().map(|tx| (tx.sender.as_str(), tx.nonce)).collect()
+    }
+    
+    #[test]
+    fn groups_go_by_fee_rate_and_senders_by_nonce() {
+        let transactions = vec![
+            transfer("alice", "dave", 10.0, 2, 0.0),
+            transfer("carol", "dave", 10.0, 0, 0.0),
+            transfer("bob", "dave", 10.0, 0, 1.0),
+            transfer("alice", "dave", 10.0, 1, 0.0),
+        ];
+        let sorted = sort_canonical(transactions.clone());
+        assert_eq!(senders(&sorted), vec![("bob", Some(0)), ("alice", Some(1)), ("alice", Some(2)), ("carol", Some(0))]);
+        let mut reversed = transactions;
+        reversed.reverse();
+        assert_eq!(senders(&sort_canonical(reversed)), senders(&sorted));
+    }
+    
+    #[test]
+    fn funding_parent_goes_before_a_richer_child() {
+        // carol платит из пополнения bob, поэтому идет после него, хотя ее ставка выше
+        let transactions = vec![
+            transfer("carol", "dave", 5.0, 0, 2.0),
+            transfer("alice", "dave", 10.0, 0, 0.5),
+            transfer("bob", "carol", 10.0, 0, 0.0),
+        ];
+        assert_eq!(senders(&sort_canonical(transactions)), vec![("bob", Some(0)), ("carol", Some(0)), ("alice", Some(0))]);
+    }
+    
+    #[test]
+    fn submission_order_does_not_change_the_block() {
+        let mut first = seeded_chain();
+        let mut second = seeded_chain();
+        let mut transactions = vec![
+            transfer("alice", "bob", 5.0, first.get_account_nonce("alice"), 0.0),
+            transfer("alice", "carol", 7.0, first.get_account_nonce("alice") + 1, 0.0),
+            transfer("bob", "carol", 3.0, first.get_account_nonce("bob"), 0.3),
+            transfer("bob", "alice", 4.0, first.get_account_nonce("bob") + 1, 0.0),
+        ];
+        for tx in &mut transactions {
+            first.sign_as_sender(tx);
+        }
+        
+        for tx in &transactions {
+            first.add_transaction(tx.clone()).unwrap();
+        }
+        for i in [2, 0, 3, 1] {
+            second.add_transaction(transactions[i].clone()).unwrap();
+        }
+        first.mine_pending_transactions("carol".to_string()).unwrap();
+        second.mine_pending_transactions("carol".to_string()).unwrap();
+        
+        let block = first.get_latest_block();
+        assert_eq!(serde_json::to_vec(block).unwrap(), serde_json::to_vec(second.get_latest_block()).unwrap());
+        assert_eq!(senders(&block.transactions[..4]), vec![("bob", Some(1)), ("bob", Some(2)), ("alice", Some(1)), ("alice", Some(2))]);
+        assert!(block.transactions[4..].iter().all(is_coinbase));
+    }
+    
+    #[test]
+    fn shuffled_block_fails_validation() {
+        let verifier = seeded_chain();
+        let mut producer = seeded_chain();
+        for (from, to) in [("alice", "carol"), ("bob", "carol")] {
+            let mut tx = transfer(from, to, 1.0, producer.get_account_nonce(from), 0.0);
+            producer.sign_as_sender(&mut tx);
+            producer.add_transaction(tx).unwrap();
+        }
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        let mined = producer.get_latest_block().clone();
+        verifier.verify_block(&mined).unwrap();
+        
+        let mut block = mined;
+        block.transactions.swap(0, 1);
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.mine_block();
+        let result = verifier.verify_block(&block);
+        assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason.ends_with("not in canonical order")), "{:?}", result);
+        
+        let reward = block.transactions.pop().unwrap();
+        block.transactions.insert(0, reward);
+        assert!(matches!(check_canonical_order(&block), Err(BlockchainError::InvalidBlock(reason)) if reason.ends_with("after its coinbase")));
+    }
+    
+    #[test]
+    fn spent_outputs_come_before_their_spenders() {
+        use crate::utxo::{OutPoint, TxOutput};
+        
+        let output = |address: &str, amount| TxOutput { address: address.to_string(), amount };
+        let coin = (OutPoint { txid: "coinbase".to_string(), index: 0 }, output("alice", 100.0));
+        let parent = Transaction::spend_at(vec![coin], vec![output("bob", 30.0), output("alice", 69.0)], 1.0, GENESIS_TIMESTAMP);
+        // Подбираем сдачу так, чтобы потомок с высокой комиссией сортировался по идентификатору раньше родителя
+        let child = (1..).map(|cents| {
+            let (outpoint, change) = parent.created_outputs().nth(1).map(|(outpoint, change)| (outpoint, change.clone())).unwrap();
+            let fee = cents as f64 / 100.0;
+            Transaction::spend_at(vec![(outpoint, change)], vec![output("carol", 60.0), output("alice", 9.0 - fee)], fee, GENESIS_TIMESTAMP)
+        }).find(|child| child.id < parent.id).unwrap();
+        let grandchild = {
+            let (outpoint, paid) = parent.created_outputs().next().map(|(outpoint, paid)| (outpoint, paid.clone())).unwrap();
+            Transaction::spend_at(vec![(outpoint, paid)], vec![output("alice", 29.0)], 1.0, GENESIS_TIMESTAMP)
+        };
+        
+        for transactions in [vec![child.clone(), grandchild.clone(), parent.clone()], vec![grandchild.clone(), parent.clone(), child.clone()]] {
+            let ordered: Vec<String> = sort_canonical(transactions).into_iter().map(|tx| tx.id).collect();
+            let position = |tx: &Transaction| ordered.iter().position(|id| *id == tx.id).unwrap();
+            assert!(position(&parent) < position(&child));
+            assert!(position(&parent) < position(&grandchild));
+        }
+    }
+}
@@ -0,0 +1,308 @@
+#![deny(clippy::indexing_slicing)]
+
+use crate::block::Block;
+use crate::blockchain::{receiver_credits, Blockchain};
+use crate::bridge::BRIDGE_MINTER;
+use crate::mint::MINT_AUTHORITY;
+use crate::errors::BlockchainError;
+use crate::features::Feature;
+use crate::fee_market::retain_deferred;
+use crate::freeze::FREEZE_AUTHORITY;
+use crate::mempool::Pool;
+use crate::merkle::IncrementalMerkle;
+use crate::rewards::balance_debit;
+use crate::sponsorship::fee_payer_debit;
+use crate::transaction::{Transaction, TransactionType};
+use std::collections::{BTreeMap, HashMap};
+
+/// Как изменился бы баланс адреса
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BalanceChange {
+    pub before: f64,
+    pub after: f64,
+}
+
+/// Что изменилось бы в цепочке, если применить операции оверлея
+#[derive(Debug, Clone)]
+pub struct OverlayDiff {
+    pub balances: BTreeMap<String, BalanceChange>,
+    /// Кошельки, которых нет в цепочке
+    pub new_wallets: Vec<String>,
+    /// Смоделированные блоки поверх вершины
+    pub blocks: Vec<Block>,
+    /// Транзакции, добавленные в пул оверлея и еще не попавшие в блок
+    pub pending_transactions: Vec<String>,
+}
+
+/// Пробное состояние поверх цепочки: блоки и кошельки читаются из нее, а изменения копятся
+/// в самом оверлее. Цепочка заимствуется только на чтение, поэтому никакие операции оверлея,
+/// в том числе прерванные паникой, не могут ее изменить; при удалении оверлея изменения пропадают.
+pub struct ChainOverlay<'a> {
+    base: &'a Blockchain,
+    /// Пул цепочки на момент создания оверлея
+    base_pool: Pool,
+    balances: HashMap<String, f64>,
+    pending: Vec<Transaction>,
+    /// Попали ли ожидающие транзакции самой цепочки в смоделированный блок
+    base_pending_included: bool,
+    blocks: Vec<Block>,
+}
+
+impl Blockchain {
+    /// Создает пустой оверлей для пробного применения транзакций и блоков
+    pub fn snapshot(&self) -> ChainOverlay<'_> {
+        ChainOverlay {
+            base: self,
+            base_pool: self.mempool.lock().clone(),
+            balances: HashMap::new(),
+            pending: Vec::new(),
+            base_pending_included: false,
+            blocks: Vec::new(),
+        }
+    }
+}
+
+impl ChainOverlay<'_> {
+    /// Баланс с учетом изменений оверлея
+    pub fn get_balance(&self, address: &str) -> f64 {
+        self.balances.get(address).copied().unwrap_or_else(|| self.base.get_balance(address))
+    }
+    
+    /// Базовая комиссия следующего блока с учетом смоделированных блоков
+    pub fn current_base_fee(&self) -> Option<f64> {
+        self.base.expected_base_fee(self.blocks.last().unwrap_or_else(|| self.base.get_latest_block()))
+    }
+    
+    fn has_wallet(&self, address: &str) -> bool {
+        self.balances.contains_key(address) || self.base.wallets.contains_key(address)
+    }
+    
+    /// Ожидающие транзакции с точки зрения оверлея
+    pub fn pending_transactions(&self) -> impl Iterator<Item = &Transaction> {
+        let base: &[Transaction] = if self.base_pending_included { &[] } else { &self.base_pool.transactions };
+        base.iter().chain(&self.pending)
+    }
+    
+    /// Ставит транзакцию в пул оверлея с теми же проверками и списанием, что и `add_transaction`
+    pub fn apply_transaction(&mut self, transaction: Transaction) -> Result<(), BlockchainError> {
+        self.base.require_account_mode("Overlay simulation")?;
+        if !transaction.is_valid() {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} has no sender, receiver or amount", transaction.id)));
+        }
+        
+        if let Some(capacity) = self.base_pool.capacity {
+            if self.pending_transactions().count() >= capacity {
+                return Err(BlockchainError::MempoolFull { capacity });
+            }
+        }
+        
+        self.base.check_transaction_addresses(&transaction)?;
+        self.base.check_feature(&transaction)?;
+        self.base.check_bridge_transaction(&transaction)?;
+        self.base.check_mint_transaction(&transaction)?;
+        self.base.check_minimum_fee(&transaction)?;
+        self.base.check_reaped_replay(&transaction)?;
+        self.base.check_base_fee(&transaction, self.current_base_fee())?;
+        self.base.check_treasury_transaction(&transaction)?;
+        self.base.check_staking_transaction(&transaction)?;
+        self.base.check_vesting_transaction(&transaction)?;
+        self.base.check_freeze_transaction(&transaction)?;
+        self.base.check_wallet_closure(&transaction, (self.base.chain.len() + self.blocks.len()) as u64)?;
+        self.base.check_expiry(&transaction, (self.base.chain.len() + self.blocks.len()) as u64)?;
+        self.base.check_fee_payer(&transaction)?;
+        self.base.check_asset_transaction(&transaction)?;
+        
+        if transaction.sender != "BLOCKCHAIN_REWARD" && transaction.sender != BRIDGE_MINTER && transaction.sender != MINT_AUTHORITY
+            && transaction.sender != FREEZE_AUTHORITY {
+            if !self.has_wallet(&transaction.sender) {
+                return Err(BlockchainError::WalletNotFound { address: transaction.sender.clone() });
+            }
+            
+            let total_amount = balance_debit(&transaction);
+            let balance = self.get_balance(&transaction.sender);
+            let height = (self.base.chain.len() + self.blocks.len()) as u64;
+            let locked = self.base.wallets.get(&transaction.sender).map_or(0.0, |wallet| wallet.unvested(height));
+            let available = (balance - locked).max(0.0);
+            if available < total_amount {
+                return Err(BlockchainError::InsufficientBalance { required: total_amount, available });
+            }
+            self.balances.insert(transaction.sender.clone(), balance - total_amount);
+            
+            if let Some((payer, fee)) = fee_payer_debit(&transaction) {
+                let balance = self.get_balance(payer);
+                if balance < fee {
+                    return Err(BlockchainError::InsufficientBalance { required: fee, available: balance.max(0.0) });
+                }
+                self.balances.insert(payer.to_string(), balance - fee);
+            }
+        }
+        
+        self.pending.push(transaction);
+        Ok(())
+    }
+    
+    /// Моделирует блок из ожидающих транзакций так же, как `mine_pending_transactions`,
+    /// но без поиска печати: у блока нулевой nonce и его хеш не удовлетворяет сложности
+    pub fn simulate_block(&mut self, miner_address: &str) -> Result<&Block, BlockchainError> {
+        self.base.check_address(miner_address)?;
+        
+        let tip = self.blocks.last().unwrap_or_else(|| self.base.get_latest_block());
+        let height = tip.index + 1;
+        let base_fee = self.base.expected_base_fee(tip);
+        let pool = Pool {
+            transactions: self.pending_transactions().cloned().collect(),
+            parents: self.base_pool.parents.clone(),
+            system_queue: self.base_pool.system_queue.clone(),
+            ..Pool::default()
+        };
+        let (mut transactions, selected) = self.base.select_block_transactions(&pool, height, base_fee);
+        let mut merkle = IncrementalMerkle::from_transactions(self.base.hash_algorithm, &transactions);
+        for tx in self.base.coinbase_transactions(height, miner_address, &transactions, base_fee) {
+            merkle.push(&tx);
+            transactions.push(tx);
+        }
+        
+        let mut block = Block::from_candidate(height, transactions, &merkle, tip.hash.clone(), self.base.current_target(), self.base.next_block_timestamp());
+        block.set_base_fee(base_fee);
+        
+        for tx in receiver_credits(&block.transactions) {
+            let balance = self.get_balance(&tx.receiver) + tx.amount;
+            self.balances.insert(tx.receiver.clone(), balance);
+        }
+        
+        let mut pending = pool.transactions;
+        retain_deferred(&mut pending, &selected);
+        self.pending = pending;
+        self.base_pending_included = true;
+        Ok(self.blocks.push_mut(block))
+    }
+    
+    /// Ищет транзакцию развертывания контракта в смоделированных блоках, пуле оверлея и цепочке
+    pub fn find_smart_contract(&self, contract_address: &str) -> Option<&Transaction> {
+        let is_deployment = |tx: &&Transaction| {
+            tx.receiver == contract_address && matches!(tx.transaction_type, TransactionType::SmartContract(_))
+        };
+        
+        self.blocks.iter()
+            .flat_map(|block| &block.transactions)
+            .chain(&self.pending)
+            .find(is_deployment)
+            .or_else(|| self.base.find_smart_contract(contract_address))
+    }
+    
+    /// Имитирует вызов контракта против состояния оверлея
+    pub fn execute_smart_contract(&self, contract_address: &str, function: &str, args: Vec<String>) -> Result<String, BlockchainError> {
+        self.base.require_feature(Feature::ContractCalls)?;
+        if self.find_smart_contract(contract_address).is_none() {
+            return Err(BlockchainError::ContractError { reason: format!("Smart contract {} not found", contract_address) });
+        }
+        
+        Ok(format!("Called function {} in smart contract {}: {:?}", function, contract_address, args))
+    }
+    
+    /// Изменения относительно цепочки
+    pub fn diff(&self) -> OverlayDiff {
+        let balances = self.balances.iter()
+            .map(|(address, after)| (address.clone(), BalanceChange { before: self.base.get_balance(address), after: *after }))
+            .filter(|(_, change)| change.before != change.after)
+            .collect();
+        
+        let mut new_wallets: Vec<String> = self.balances.keys()
+            .filter(|address| !self.base.wallets.contains_key(*address))
+            .cloned()
+            .collect();
+        new_wallets.sort();
+        
+        OverlayDiff {
+            balances,
+            new_wallets,
+            blocks: self.blocks.clone(),
+            pending_transactions: self.pending.iter().map(|tx| tx.id.clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ChainFixture;
+    
+    /// Подписанные переводы от `alice` с последовательными nonce
+    fn transfers(chain: &Blockchain, receivers: &[(&str, f64)]) -> Vec<Transaction> {
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let nonce = chain.get_account_nonce("alice");
+        receivers.iter().zip(nonce..).map(|((receiver, amount), nonce)| {
+            let mut tx = Transaction::new("alice".to_string(), receiver.to_string(), *amount, TransactionType::Transfer);
+            tx.set_nonce(nonce);
+            tx.sign(&keypair);
+            tx
+        }).collect()
+    }
+    
+    #[test]
+    fn simulated_block_predicts_the_mined_balances() {
+        let mut chain = ChainFixture::funded().build().unwrap().chain;
+        let addresses = ["alice", "bob", "carol", "miner"];
+        let txs = transfers(&chain, &[("bob", 10.0), ("carol", 25.0), ("bob", 5.0)]);
+        let tip = chain.get_latest_block().hash.clone();
+        let before: Vec<f64> = addresses.iter().map(|address| chain.get_balance(address)).collect();
+        
+        let predicted: Vec<f64> = {
+            let mut overlay = chain.snapshot();
+            for tx in &txs {
+                overlay.apply_transaction(tx.clone()).unwrap();
+            }
+            let block = overlay.simulate_block("miner").unwrap();
+            assert_eq!(block.transactions.len(), txs.len() + 1);
+            assert_eq!(block.previous_hash, tip);
+            assert!(overlay.pending_transactions().next().is_none());
+            addresses.iter().map(|address| overlay.get_balance(address)).collect()
+        };
+        
+        // Цепочка не изменилась после работы оверлея
+        assert_eq!(chain.chain.len(), 2);
+        assert_eq!(chain.get_latest_block().hash, tip);
+        assert!(chain.mempool().is_empty());
+        assert_eq!(addresses.iter().map(|address| chain.get_balance(address)).collect::<Vec<_>>(), before);
+        
+        for tx in txs {
+            chain.add_transaction(tx).unwrap();
+        }
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let mined: Vec<f64> = addresses.iter().map(|address| chain.get_balance(address)).collect();
+        assert_eq!(mined, predicted);
+        assert_ne!(mined, before);
+    }
+    
+    #[test]
+    fn overlays_do_not_see_each_other() {
+        let chain = ChainFixture::funded().build().unwrap().chain;
+        let to_bob = transfers(&chain, &[("bob", 10.0)]);
+        let to_carol = transfers(&chain, &[("carol", 20.0)]);
+        let alice = chain.get_balance("alice");
+        let carol_debit = to_carol.iter().map(balance_debit).sum::<f64>();
+        
+        let mut first = chain.snapshot();
+        let mut second = chain.snapshot();
+        for tx in to_bob {
+            first.apply_transaction(tx).unwrap();
+        }
+        for tx in to_carol {
+            second.apply_transaction(tx).unwrap();
+        }
+        first.simulate_block("miner").unwrap();
+        
+        assert_eq!(first.get_balance("bob"), 10.0);
+        assert_eq!(first.get_balance("carol"), 0.0);
+        assert_eq!(second.get_balance("bob"), 0.0);
+        assert_eq!(second.get_balance("alice"), alice - carol_debit);
+        assert_eq!(second.diff().blocks.len(), 0);
+        assert_eq!(first.diff().blocks.len(), 1);
+        assert_eq!(second.diff().pending_transactions.len(), 1);
+        assert!(!first.diff().balances.contains_key("carol"));
+        assert!(!second.diff().balances.contains_key("bob"));
+        
+        assert_eq!(chain.get_balance("alice"), alice);
+        assert_eq!(chain.get_balance("bob"), 0.0);
+    }
+}
@@ -0,0 +1,370 @@
+use crate::blockchain::{receiver_credits, Blockchain};
+use crate::mempool::{reserved_balance, Pool};
+use crate::rewards::balance_debit;
+use crate::trace::warn;
+use crate::transaction::Transaction;
+use crate::utxo::LedgerMode;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Пакет: транзакция пула вместе с неподтвержденными предками, без которых ее нельзя включить в блок
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Package {
+    /// Идентификаторы транзакций пакета, предки раньше потомков
+    pub transactions: Vec<String>,
+    pub fee: f64,
+    pub weight: u64,
+}
+
+impl Package {
+    /// Ставка пакета: сумма комиссий на сумму весов
+    pub fn fee_rate(&self) -> f64 {
+        self.fee / self.weight.max(1) as f64
+    }
+}
+
+/// Вес транзакции в ставке пакета; как и в каноническом порядке, не меньше единицы
+pub(crate) fn rate_weight(tx: &Transaction) -> u64 {
+    tx.weight().max(1)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    Active,
+    Done,
+}
+
+/// Убирает ребра, замыкающие циклы. Родителем записывается только транзакция, которая уже лежала
+/// в пуле, так что циклов быть не должно; если они все же появились, пакеты считаются без этих ребер.
+fn break_cycles(pending: &[Transaction], parents: &mut [Vec<usize>]) {
+    let advance = |stack: &mut Vec<(usize, usize)>| {
+        if let Some(top) = stack.last_mut() {
+            top.1 += 1;
+        }
+    };
+    let mut visits = vec![Visit::New; parents.len()];
+    for root in 0..parents.len() {
+        if visits[root] != Visit::New {
+            continue;
+        }
+        visits[root] = Visit::Active;
+        let mut stack = vec![(root, 0)];
+        while let Some(&(node, next)) = stack.last() {
+            let Some(&parent) = parents[node].get(next) else {
+                visits[node] = Visit::Done;
+                stack.pop();
+                continue;
+            };
+            match visits[parent] {
+                Visit::Active => {
+                    warn!(tx_id = %pending[node].id, parent = %pending[parent].id, "Dependency cycle in the mempool");
+                    parents[node].remove(next);
+                },
+                Visit::New => {
+                    advance(&mut stack);
+                    visits[parent] = Visit::Active;
+                    stack.push((parent, 0));
+                },
+                Visit::Done => advance(&mut stack),
+            }
+        }
+    }
+}
+
+/// Транзакция `index` с предками, которые еще не отмечены в `excluded`, предки раньше потомков
+pub(crate) fn with_ancestors(parents: &[Vec<usize>], index: usize, excluded: &[bool]) -> Vec<usize> {
+    let mut visited = vec![false; parents.len()];
+    let mut package = Vec::new();
+    let mut stack = vec![(index, 0)];
+    visited[index] = true;
+    while let Some((node, next)) = stack.pop() {
+        match parents[node].get(next) {
+            Some(&parent) => {
+                stack.push((node, next + 1));
+                if !visited[parent] && !excluded[parent] {
+                    visited[parent] = true;
+                    stack.push((parent, 0));
+                }
+            },
+            None => package.push(node),
+        }
+    }
+    package
+}
+
+/// Отмечает в `excluded` всех потомков уже отмеченных транзакций
+pub(crate) fn exclude_descendants(parents: &[Vec<usize>], excluded: &mut [bool]) {
+    let mut children = vec![Vec::new(); parents.len()];
+    for (child, list) in parents.iter().enumerate() {
+        for &parent in list {
+            children[parent].push(child);
+        }
+    }
+    let mut stack: Vec<usize> = (0..excluded.len()).filter(|&index| excluded[index]).collect();
+    while let Some(node) = stack.pop() {
+        for &child in &children[node] {
+            if !excluded[child] {
+                excluded[child] = true;
+                stack.push(child);
+            }
+        }
+    }
+}
+
+impl Blockchain {
+    /// Сколько транзакции пула `pending` зачислят на основной баланс адреса, когда попадут в блок
+    pub(crate) fn pending_credit(&self, address: &str, pending: &[Transaction]) -> f64 {
+        receiver_credits(pending)
+            .filter(|tx| tx.receiver == address && !self.burns_dust_credit(tx))
+            .map(|tx| tx.amount)
+            .sum()
+    }
+    
+    /// Транзакции пула, на зачисления которых рассчитывает отправитель: пусто, если ему хватает
+    /// своего баланса за вычетом занятого пулом
+    pub(crate) fn funding_parents(&self, tx: &Transaction, pending: &[Transaction]) -> Vec<String> {
+        if self.spendable_balance(&tx.sender, self.chain.len() as u64) - reserved_balance(pending, &tx.sender) >= balance_debit(tx) {
+            return Vec::new();
+        }
+        receiver_credits(pending)
+            .filter(|pending| pending.receiver == tx.sender && !self.burns_dust_credit(pending))
+            .map(|pending| pending.id.clone())
+            .collect()
+    }
+    
+    /// Родители каждой транзакции пула по индексам. В счетной цепочке это транзакции, на чьи
+    /// зачисления рассчитывал потомок при постановке в пул, и транзакция того же отправителя с
+    /// предыдущим номером, в UTXO-цепочке — создатели тратимых выходов.
+    pub(crate) fn pending_graph(&self, pool: &Pool) -> Vec<Vec<usize>> {
+        let pending = &pool.transactions;
+        let positions: HashMap<&str, usize> = pending.iter().enumerate().map(|(index, tx)| (tx.id.as_str(), index)).collect();
+        let nonces: HashMap<(&str, u64), usize> = pending.iter().enumerate()
+            .filter_map(|(index, tx)| tx.nonce.map(|nonce| ((tx.sender.as_str(), nonce), index)))
+            .collect();
+        let mut parents: Vec<Vec<usize>> = pending.iter()
+            .map(|tx| {
+                let mut list: Vec<usize> = match self.ledger_mode {
+                    LedgerMode::Account => pool.parents.get(&tx.id).into_iter().flatten()
+                        .filter_map(|id| positions.get(id.as_str()).copied())
+                        .chain(tx.nonce.and_then(|nonce| nonce.checked_sub(1))
+                            .and_then(|previous| nonces.get(&(tx.sender.as_str(), previous)).copied()))
+                        .collect(),
+                    LedgerMode::Utxo => tx.inputs.iter()
+                        .filter_map(|input| positions.get(input.outpoint.txid.as_str()).copied())
+                        .collect(),
+                };
+                list.sort_unstable();
+                list.dedup();
+                list
+            })
+            .collect();
+        break_cycles(pending, &mut parents);
+        parents
+    }
+    
+    /// Пакет транзакции пула: она сама и все ее неподтвержденные предки
+    pub fn pending_package(&self, tx_id: &str) -> Option<Package> {
+        let pool = self.mempool.lock();
+        let index = pool.transactions.iter().position(|tx| tx.id == tx_id)?;
+        let parents = self.pending_graph(&pool);
+        let members = with_ancestors(&parents, index, &vec![false; parents.len()]);
+        let transactions: Vec<&Transaction> = members.iter().map(|&member| &pool.transactions[member]).collect();
+        Some(Package {
+            transactions: transactions.iter().map(|tx| tx.id.clone()).collect(),
+            fee: transactions.iter().map(|tx| tx.fee).sum(),
+            weight: transactions.iter().map(|tx| rate_weight(tx)).sum(),
+        })
+    }
+    
+    /// Потомки транзакции пула, от ближних к дальним
+    pub(crate) fn pending_descendants(&self, tx_id: &str) -> Vec<String> {
+        let pool = self.mempool.lock();
+        let Some(index) = pool.transactions.iter().position(|tx| tx.id == tx_id) else {
+            return Vec::new();
+        };
+        let parents = self.pending_graph(&pool);
+        let mut excluded = vec![false; parents.len()];
+        excluded[index] = true;
+        exclude_descendants(&parents, &mut excluded);
+        excluded[index] = false;
+        let mut descendants: Vec<usize> = (0..excluded.len()).filter(|&other| excluded[other]).collect();
+        // У предка всегда меньше предков, чем у его потомка
+        let all = vec![false; parents.len()];
+        descendants.sort_by_key(|&other| with_ancestors(&parents, other, &all).len());
+        descendants.into_iter().map(|other| pool.transactions[other].id.clone()).collect()
+    }
+    
+    /// Забывает родителей транзакций, которые ушли из пула, и подтвержденных родителей
+    pub(crate) fn prune_pending_parents(&mut self) {
+        let pool = self.mempool.get_mut();
+        let pending: HashSet<&str> = pool.transactions.iter().map(|tx| tx.id.as_str()).collect();
+        pool.parents.retain(|child, parents| {
+            parents.retain(|parent| pending.contains(parent.as_str()));
+            pending.contains(child.as_str()) && !parents.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::errors::BlockchainError;
+    use crate::fee_market::BaseFeeConfig;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    const BASE_FEE: f64 = 0.001;
+    
+    /// Перевод, оплачивающий базовую комиссию по своему весу и чаевые
+    fn transfer(chain: &Blockchain, sender: &str, receiver: &str, amount: f64, tip: f64) -> Transaction {
+        let mut tx = Transaction::new(sender.to_string(), receiver.to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(sender));
+        chain.sign_as_sender(&mut tx);
+        tx.fee = BASE_FEE * tx.weight() as f64 + tip;
+        tx.reseal();
+        chain.sign_as_sender(&mut tx);
+        tx
+    }
+    
+    /// alice, carol и david с наградами за блок, bob без средств; в блок помещаются два перевода
+    fn market_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        for name in ["alice", "carol", "david"] {
+            chain.create_wallet(name.to_string()).unwrap();
+            chain.mine_pending_transactions(name.to_string()).unwrap();
+        }
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let transfer_weight = transfer(&chain, "carol", "erin", 1.0, 0.1).weight();
+        chain.fee_market = Some(BaseFeeConfig {
+            initial_base_fee: BASE_FEE,
+            target_block_weight: 2 * transfer_weight,
+            elasticity_multiplier: 1,
+            change_denominator: 8.0,
+            min_base_fee: 0.0,
+        });
+        chain
+    }
+    
+    fn mined_ids(chain: &Blockchain) -> Vec<String> {
+        chain.get_latest_block().transactions.iter().map(|tx| tx.id.clone()).collect()
+    }
+    
+    #[test]
+    fn high_fee_child_pulls_a_stuck_parent_into_the_block() {
+        let mut chain = market_chain();
+        let parent = transfer(&chain, "alice", "bob", 10.0, 0.01);
+        chain.add_transaction(parent.clone()).unwrap();
+        for name in ["carol", "david", "carol"] {
+            let tx = transfer(&chain, name, "erin", 1.0, 0.1);
+            chain.add_transaction(tx).unwrap();
+        }
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(!mined_ids(&chain).contains(&parent.id), "the low-fee parent should lose to richer transfers");
+        assert!(chain.mempool().transactions().iter().any(|tx| tx.id == parent.id));
+        
+        // bob тратит еще не подтвержденное зачисление и платит за двоих
+        let child = transfer(&chain, "bob", "erin", 5.0, 1.0);
+        chain.add_transaction(child.clone()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let mined = mined_ids(&chain);
+        let parent_position = mined.iter().position(|id| *id == parent.id).expect("parent mined with its child");
+        let child_position = mined.iter().position(|id| *id == child.id).expect("child mined");
+        assert!(parent_position < child_position, "{:?}", mined);
+        assert!(chain.is_chain_valid());
+        assert!((chain.get_balance("bob") - (10.0 - 5.0 - child.fee)).abs() < 1e-9);
+        assert!((chain.get_balance("erin") - 5.0 - 2.0).abs() < 1e-9);
+        assert_eq!(chain.mempool().len(), 1);
+    }
+    
+    #[test]
+    fn package_totals_are_the_sum_of_their_parts() {
+        let mut chain = market_chain();
+        let parent = transfer(&chain, "alice", "bob", 10.0, 0.01);
+        chain.add_transaction(parent.clone()).unwrap();
+        let child = transfer(&chain, "bob", "carol", 4.0, 0.5);
+        chain.add_transaction(child.clone()).unwrap();
+        let grandchild = transfer(&chain, "carol", "david", 103.0, 0.2);
+        chain.add_transaction(grandchild.clone()).unwrap();
+        
+        let package = chain.pending_package(&child.id).unwrap();
+        assert_eq!(package.transactions, vec![parent.id.clone(), child.id.clone()]);
+        assert_eq!(package.fee, parent.fee + child.fee);
+        assert_eq!(package.weight, rate_weight(&parent) + rate_weight(&child));
+        assert_eq!(package.fee_rate(), (parent.fee + child.fee) / package.weight as f64);
+        
+        let whole = chain.pending_package(&grandchild.id).unwrap();
+        assert_eq!(whole.transactions, vec![parent.id.clone(), child.id.clone(), grandchild.id.clone()]);
+        assert_eq!(whole.fee, package.fee + grandchild.fee);
+        assert_eq!(whole.weight, package.weight + rate_weight(&grandchild));
+        
+        let alone = chain.pending_package(&parent.id).unwrap();
+        assert_eq!(alone, Package { transactions: vec![parent.id.clone()], fee: parent.fee, weight: rate_weight(&parent) });
+        assert_eq!(chain.pending_package("missing"), None);
+    }
+    
+    #[test]
+    fn cancelling_a_parent_evicts_its_descendants() {
+        let mut chain = market_chain();
+        let parent = transfer(&chain, "alice", "bob", 10.0, 0.01);
+        chain.add_transaction(parent.clone()).unwrap();
+        let child = transfer(&chain, "bob", "erin", 5.0, 1.0);
+        chain.add_transaction(child.clone()).unwrap();
+        let unrelated = transfer(&chain, "carol", "erin", 1.0, 0.1);
+        chain.add_transaction(unrelated.clone()).unwrap();
+        
+        chain.cancel_pending_transaction(&parent.id).unwrap();
+        let pending: Vec<String> = chain.mempool().transactions().iter().map(|tx| tx.id.clone()).collect();
+        assert_eq!(pending, vec![unrelated.id.clone()]);
+        assert!(chain.mempool.get_mut().parents.is_empty());
+        assert_eq!(chain.get_balance("alice"), 100.0);
+        assert_eq!(chain.get_balance("bob"), 0.0);
+    }
+    
+    #[test]
+    fn expiring_parent_takes_its_child_with_it() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut producer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        
+        let mut parent = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        parent.set_nonce(chain.get_account_nonce("alice"));
+        parent.set_valid_until(2);
+        chain.sign_as_sender(&mut parent);
+        chain.add_transaction(parent.clone()).unwrap();
+        let mut child = Transaction::new("bob".to_string(), "carol".to_string(), 5.0, TransactionType::Transfer);
+        child.set_nonce(chain.get_account_nonce("bob"));
+        chain.sign_as_sender(&mut child);
+        chain.add_transaction(child.clone()).unwrap();
+        assert_eq!(chain.pending_descendants(&parent.id), vec![child.id.clone()]);
+        
+        // Блок высоты 2 от другого узла их не включил, и родитель больше не попадет в блок
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.accept_block(producer.get_latest_block().clone()).unwrap();
+        assert!(chain.mempool().is_empty());
+        assert_eq!(chain.get_balance("alice"), 100.0);
+        assert_eq!(chain.get_balance("bob"), 0.0);
+    }
+    
+    #[test]
+    fn pool_credits_are_spent_only_once() {
+        let mut chain = market_chain();
+        chain.add_transaction(transfer(&chain, "alice", "bob", 50.0, 0.0)).unwrap();
+        // Перевод себе тратит зачисление и возвращает его же: свободно остается прежняя сумма без комиссий
+        chain.add_transaction(transfer(&chain, "bob", "bob", 20.0, 0.0)).unwrap();
+        assert!(chain.get_balance("bob") < 0.0);
+        let result = chain.add_transaction(transfer(&chain, "bob", "erin", 49.5, 0.0));
+        assert!(matches!(result, Err(BlockchainError::InsufficientBalance { .. })), "{:?}", result);
+        chain.add_transaction(transfer(&chain, "bob", "erin", 49.0, 0.0)).unwrap();
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.mempool().is_empty());
+        assert_eq!(chain.get_balance("erin"), 49.0);
+        assert!(chain.get_balance("bob") >= 0.0);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::blockchain::{Blockchain, TARGET_BLOCK_TIME};
+use crate::errors::BlockchainError;
+use crate::features::Feature;
+use crate::reserved::ReservedSpace;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+
+/// После стольких халвингов награда считается нулевой
+const MAX_HALVINGS: u64 = 64;
+
+/// Параметры, которые можно менять голосованием; параметры выпуска влияют на проверку прошлых
+/// блоков и сюда не входят. Возможности цепочки включаются значением 1 и выключаются 0.
+pub const GOVERNABLE_PARAMS: [&str; 6] = [
+    "target_block_time", "existential_deposit",
+    "feature.smart_contracts", "feature.contract_calls", "feature.data", "feature.asset_issuance",
+];
+
+fn default_target_block_time() -> f64 {
+    TARGET_BLOCK_TIME
+}
+
+/// Параметры выпуска монет: награда за блок, график халвинга и предельная эмиссия
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainParams {
+    /// Награда за блок до первого халвинга; в файле цепочки хранится как `mining_reward`
+    #[serde(rename = "mining_reward")]
+    pub initial_reward: f64,
+    /// Через сколько блоков награда уменьшается вдвое; без значения (или 0) не уменьшается
+    #[serde(default)]
+    pub halving_interval_blocks: Option<u64>,
+    /// Сколько монет может быть выпущено наградами; после этого блоки несут только комиссии
+    #[serde(default)]
+    pub max_supply: Option<f64>,
+    /// Скидки на минимальную комиссию для отправителей со стейком
+    #[serde(default)]
+    pub fee_discount_tiers: Vec<FeeDiscountTier>,
+    /// Меньше этой суммы кошелек не создается, а опустевший удаляется
+    #[serde(default)]
+    pub existential_deposit: Option<ExistentialDeposit>,
+    /// Куда зачисляются награды за блоки и выплаты стейкинга
+    #[serde(default)]
+    pub reward_mode: RewardMode,
+    /// Желаемое время между блоками в секундах, к которому подстраивается цель майнинга
+    #[serde(default = "default_target_block_time")]
+    pub target_block_time: f64,
+    /// Что происходит с переводами на закрытые кошельки
+    #[serde(default)]
+    pub closed_wallet_policy: ClosedWalletPolicy,
+    /// Выключенные возможности; после генезиса меняются только голосованием
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub disabled_features: BTreeSet<Feature>,
+    /// Параметры выпуска блоков PoS и DPoS; у PoW-цепочек их нет
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stake_params: Option<StakeParams>,
+    /// Место в блоке, которое сначала занимают системные транзакции
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved_space: Option<ReservedSpace>,
+    /// Открытый ключ эмитента: транзакции `Mint` принимаются только с его подписью, а без него
+    /// выпуск выключен
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint_authority: Option<String>,
+}
+
+/// Наименьший интервал между блоками PoS и DPoS в секундах у новых цепочек
+pub const DEFAULT_MIN_BLOCK_INTERVAL: u64 = 1;
+
+/// Расписание блоков PoS и DPoS. Сложность и nonce у таких цепочек не используются: блоки
+/// несут сложность 0, наибольшую цель и nonce 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakeParams {
+    /// Сколько секунд должно пройти от родителя до нового блока
+    pub min_block_interval: u64,
+    /// Длина слота жребия производителя в секундах
+    pub slot_duration: u64,
+}
+
+impl Default for StakeParams {
+    /// Параметры цепочек, сохраненных до их появления: интервал не проверяется
+    fn default() -> Self {
+        StakeParams { min_block_interval: 0, slot_duration: TARGET_BLOCK_TIME as u64 }
+    }
+}
+
+/// Как начисляются награды за блоки (с комиссиями) и выплаты стейкинга
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RewardMode {
+    /// Сразу на баланс получателя
+    #[default]
+    Credit,
+    /// В накопленные награды получателя; на баланс их переводит транзакция `ClaimRewards`
+    Accrue,
+}
+
+/// Экзистенциальный депозит и что делать с переводом, который создал бы кошелек меньше него
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExistentialDeposit {
+    pub amount: f64,
+    #[serde(default)]
+    pub policy: DustPolicy,
+}
+
+/// Судьба перевода на новый адрес меньше депозита
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DustPolicy {
+    /// Транзакция не принимается
+    #[default]
+    Reject,
+    /// Транзакция проходит, но сумма сгорает, и кошелек не создается
+    Burn,
+}
+
+/// Судьба перевода на закрытый кошелек
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ClosedWalletPolicy {
+    /// Транзакция не принимается
+    #[default]
+    Reject,
+    /// Перевод зачисляется, и кошелек снова открыт
+    Reopen,
+}
+
+/// Ступень скидки: отправитель со стейком не меньше `min_stake` платит минимальную комиссию
+/// за вычетом `percent` процентов
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeeDiscountTier {
+    pub min_stake: f64,
+    pub percent: f64,
+}
+
+impl ChainParams {
+    /// Постоянная награда без халвинга и ограничения эмиссии
+    pub fn new(initial_reward: f64) -> Self {
+        ChainParams { initial_reward, halving_interval_blocks: None, max_supply: None, fee_discount_tiers: Vec::new(), existential_deposit: None, reward_mode: RewardMode::Credit, target_block_time: TARGET_BLOCK_TIME, closed_wallet_policy: ClosedWalletPolicy::Reject, disabled_features: BTreeSet::new(), stake_params: None, reserved_space: None, mint_authority: None }
+    }
+    
+    /// Проверяет, что параметр с таким именем можно установить голосованием в это значение
+    pub fn check_governable(name: &str, value: f64) -> Result<(), BlockchainError> {
+        if !GOVERNABLE_PARAMS.contains(&name) {
+            return Err(BlockchainError::InvalidTransaction(format!("Parameter {} cannot be changed by governance", name)));
+        }
+        let valid = match name {
+            "target_block_time" => value.is_finite() && value > 0.0,
+            _ if Feature::from_param(name).is_some() => value == 0.0 || value == 1.0,
+            _ => value.is_finite() && value >= 0.0,
+        };
+        if !valid {
+            return Err(BlockchainError::InvalidTransaction(format!("Value {} is invalid for parameter {}", value, name)));
+        }
+        Ok(())
+    }
+    
+    /// Устанавливает параметр, принятый голосованием; имя и значение уже проверены `check_governable`
+    pub(crate) fn set_governable(&mut self, name: &str, value: f64) {
+        match name {
+            "target_block_time" => self.target_block_time = value,
+            "existential_deposit" => {
+                let policy = self.existential_deposit.map(|deposit| deposit.policy).unwrap_or_default();
+                self.existential_deposit = Some(ExistentialDeposit { amount: value, policy });
+            },
+            _ => {
+                if let Some(feature) = Feature::from_param(name) {
+                    if value == 0.0 {
+                        self.disabled_features.insert(feature);
+                    } else {
+                        self.disabled_features.remove(&feature);
+                    }
+                }
+            },
+        }
+    }
+    
+    /// Доля скидки на минимальную комиссию для стейка: по самой выгодной ступени, до которой он
+    /// дотягивает; без стейка скидки нет
+    pub fn fee_discount(&self, stake: f64) -> f64 {
+        if stake <= 0.0 {
+            return 0.0;
+        }
+        self.fee_discount_tiers.iter()
+            .filter(|tier| stake >= tier.min_stake)
+            .map(|tier| tier.percent / 100.0)
+            .fold(0.0, f64::max)
+    }
+    
+    fn halving_interval(&self) -> Option<u64> {
+        self.halving_interval_blocks.filter(|&interval| interval > 0)
+    }
+    
+    /// Награда по графику халвинга без учета предельной эмиссии
+    fn scheduled_reward(&self, height: u64) -> f64 {
+        let halvings = self.halving_interval().map_or(0, |interval| height / interval);
+        if halvings >= MAX_HALVINGS {
+            return 0.0;
+        }
+        self.initial_reward / (1u64 << halvings) as f64
+    }
+    
+    /// Сколько выпустили бы по графику блоки с высотами 1..height; генезис награды не несет
+    fn scheduled_issuance(&self, height: u64) -> f64 {
+        let Some(interval) = self.halving_interval() else {
+            return self.initial_reward * height.saturating_sub(1) as f64;
+        };
+        
+        let mut issued = 0.0;
+        let mut start = 1;
+        while start < height {
+            let era_end = (start / interval + 1) * interval;
+            let end = era_end.min(height);
+            let reward = self.scheduled_reward(start);
+            if reward == 0.0 {
+                break;
+            }
+            issued += reward * (end - start) as f64;
+            start = end;
+        }
+        issued
+    }
+    
+    /// Сколько еще можно выпустить к высоте height, если сверх наград блоков выпущено `extra_issued`;
+    /// None, если эмиссия не ограничена
+    pub fn remaining_supply(&self, height: u64, extra_issued: f64) -> Option<f64> {
+        self.max_supply.map(|max_supply| (max_supply - self.scheduled_issuance(height) - extra_issued).max(0.0))
+    }
+    
+    /// Награда за блок указанной высоты (без комиссий), если до этой высоты сверх наград блоков выпущено `extra_issued`
+    pub fn reward_after_issuance(&self, height: u64, extra_issued: f64) -> f64 {
+        if height == 0 {
+            return 0.0;
+        }
+        
+        let reward = self.scheduled_reward(height);
+        self.remaining_supply(height, extra_issued).map_or(reward, |remaining| reward.min(remaining))
+    }
+}
+
+impl Blockchain {
+    /// Награда за блок указанной высоты по параметрам цепочки (без комиссий); инфляция стейкинга
+    /// расходует тот же лимит эмиссии
+    pub fn reward_at_height(&self, height: u64) -> f64 {
+        self.params.reward_after_issuance(height, self.inflation_issued_before(height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    fn chain(halving_interval_blocks: Option<u64>, max_supply: Option<f64>) -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.params.halving_interval_blocks = halving_interval_blocks;
+        chain.params.max_supply = max_supply;
+        chain
+    }
+    
+    fn mine(chain: &mut Blockchain, blocks: u64) {
+        for _ in 0..blocks {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+    }
+    
+    fn coinbase_amount(block: &Block) -> f64 {
+        block.transactions.iter().find(|tx| tx.sender == "BLOCKCHAIN_REWARD").unwrap().amount
+    }
+    
+    #[test]
+    fn reward_halves_at_the_interval() {
+        let mut chain = chain(Some(3), None);
+        assert_eq!(chain.reward_at_height(0), 0.0);
+        assert_eq!(chain.reward_at_height(2), 100.0);
+        assert_eq!(chain.reward_at_height(3), 50.0);
+        assert_eq!(chain.reward_at_height(5), 50.0);
+        assert_eq!(chain.reward_at_height(6), 25.0);
+        assert_eq!(chain.reward_at_height(3 * MAX_HALVINGS), 0.0);
+        
+        mine(&mut chain, 4);
+        let rewards: Vec<f64> = chain.chain.iter().skip(1).map(coinbase_amount).collect();
+        assert_eq!(rewards, [100.0, 100.0, 50.0, 50.0]);
+        assert_eq!(chain.circulating_supply(), 300.0);
+    }
+    
+    #[test]
+    fn pre_halving_reward_after_the_boundary_is_rejected() {
+        let mut chain = chain(Some(3), None);
+        mine(&mut chain, 2);
+        
+        let mut honest: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        mine(&mut honest, 1);
+        chain.verify_block(honest.get_latest_block()).unwrap();
+        
+        let mut greedy: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        greedy.params.halving_interval_blocks = None;
+        mine(&mut greedy, 1);
+        assert_eq!(coinbase_amount(greedy.get_latest_block()), 100.0);
+        let result = chain.verify_block(greedy.get_latest_block());
+        assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason == "Wrong reward in block # 3"), "{:?}", result);
+    }
+    
+    #[test]
+    fn issuance_stops_exactly_at_the_cap() {
+        let mut chain = chain(None, Some(250.0));
+        mine(&mut chain, 4);
+        
+        let rewards: Vec<f64> = chain.chain.iter().skip(1).map(coinbase_amount).collect();
+        assert_eq!(rewards, [100.0, 100.0, 50.0, 0.0]);
+        assert_eq!(chain.circulating_supply(), 250.0);
+        assert_eq!(chain.get_balance("miner"), 250.0);
+        assert_eq!(chain.reward_at_height(5), 0.0);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::address::Address;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::fmt::Write;
+
+/// Схема платежных ссылок
+const URI_SCHEME: &str = "hellochain:";
+
+/// Начало обязательных параметров: незнакомый обязательный параметр делает ссылку недействительной
+const REQUIRED_PREFIX: &str = "req-";
+
+/// Запрос платежа, который кошелек показывает как ссылку или QR-код
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    pub address: String,
+    /// Сумма; без нее сумму вводит плательщик
+    pub amount: Option<f64>,
+    /// Назначение платежа, попадает в данные транзакции
+    pub memo: Option<String>,
+    /// Время в секундах, после которого запрос недействителен
+    pub expires: Option<i64>,
+    /// Цепочка, в которой ожидается платеж
+    pub chain_id: Option<u64>,
+}
+
+/// Кодирует все, кроме незарезервированных символов RFC 3986
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> Result<String, BlockchainError> {
+    let invalid = || BlockchainError::InvalidTransaction(format!("Invalid percent-encoding in {}", text));
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = text.get(i + 1..i + 3).ok_or_else(invalid)?;
+                decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                i += 3;
+            },
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+fn invalid_param(name: &str, value: &str) -> BlockchainError {
+    BlockchainError::InvalidTransaction(format!("Invalid payment request parameter {}={}", name, value))
+}
+
+impl PaymentRequest {
+    pub fn new(address: String) -> Self {
+        PaymentRequest { address, amount: None, memo: None, expires: None, chain_id: None }
+    }
+    
+    /// Ссылка вида `hellochain:<адрес>?amount=..&memo=..&exp=..&chain=..`
+    pub fn to_uri(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            // `Display` у f64 печатает кратчайшую запись, которая читается обратно в то же число
+            params.push(format!("amount={}", amount));
+        }
+        if let Some(memo) = &self.memo {
+            params.push(format!("memo={}", percent_encode(memo)));
+        }
+        if let Some(expires) = self.expires {
+            params.push(format!("exp={}", expires));
+        }
+        if let Some(chain_id) = self.chain_id {
+            params.push(format!("chain={}", chain_id));
+        }
+        
+        let mut uri = format!("{}{}", URI_SCHEME, percent_encode(&self.address));
+        if !params.is_empty() {
+            uri.push('?');
+            uri.push_str(&params.join("&"));
+        }
+        uri
+    }
+    
+    /// Разбирает ссылку и проверяет адрес. Незнакомые параметры пропускаются, если они не
+    /// начинаются с `req-`: такие плательщик обязан понимать, поэтому ссылка с ними отклоняется.
+    pub fn parse(uri: &str) -> Result<PaymentRequest, BlockchainError> {
+        let rest = uri.strip_prefix(URI_SCHEME)
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("{} is not a {} URI", uri, URI_SCHEME.trim_end_matches(':'))))?;
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let address = percent_decode(address)?;
+        if address.is_empty() {
+            return Err(BlockchainError::InvalidTransaction("Payment request has no address".to_string()));
+        }
+        if let Address::System(_) = Address::parse(&address)? {
+            return Err(BlockchainError::InvalidTransaction(format!("Cannot request a payment to {}", address)));
+        }
+        
+        let mut request = PaymentRequest::new(address);
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let name = percent_decode(name)?;
+            let value = percent_decode(value)?;
+            match name.as_str() {
+                "amount" => {
+                    let amount: f64 = value.parse().map_err(|_| invalid_param(&name, &value))?;
+                    if !amount.is_finite() || amount <= 0.0 {
+                        return Err(invalid_param(&name, &value));
+                    }
+                    request.amount = Some(amount);
+                },
+                "memo" => request.memo = Some(value),
+                "exp" => request.expires = Some(value.parse().map_err(|_| invalid_param(&name, &value))?),
+                "chain" => request.chain_id = Some(value.parse().map_err(|_| invalid_param(&name, &value))?),
+                _ if name.starts_with(REQUIRED_PREFIX) => {
+                    return Err(BlockchainError::InvalidTransaction(format!("Unsupported required parameter {}", name)));
+                },
+                _ => {},
+            }
+        }
+        Ok(request)
+    }
+}
+
+impl Blockchain {
+    /// Готовит перевод по запросу платежа; подписывает и отправляет его вызывающий.
+    /// Назначение платежа записывается данными транзакции.
+    pub fn build_payment(&self, request: &PaymentRequest, from_wallet: &str) -> Result<Transaction, BlockchainError> {
+        self.check_address(&request.address)?;
+        self.check_address(from_wallet)?;
+        if let Some(chain_id) = request.chain_id.filter(|chain_id| *chain_id != self.chain_id) {
+            return Err(BlockchainError::InvalidTransaction(format!("Payment request is for chain {}, not {}", chain_id, self.chain_id)));
+        }
+        let now = self.now_ts();
+        if let Some(expires) = request.expires.filter(|expires| *expires <= now) {
+            return Err(BlockchainError::InvalidTransaction(format!("Payment request expired at {}", expires)));
+        }
+        let amount = request.amount
+            .ok_or_else(|| BlockchainError::InvalidTransaction("Payment request has no amount".to_string()))?;
+        
+        let transaction_type = match &request.memo {
+            Some(memo) => TransactionType::Data(memo.clone().into_bytes()),
+            None => TransactionType::Transfer,
+        };
+        Ok(self.new_transaction_at(from_wallet.to_string(), request.address.clone(), amount, transaction_type, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::clock::MockClock;
+    use crate::entropy::SeededEntropy;
+    use crate::test_support::ChainFixture;
+    use crate::wallet::Wallet;
+    use std::sync::Arc;
+    
+    fn chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.set_clock(Arc::new(MockClock::new(GENESIS_TIMESTAMP + 100)));
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain
+    }
+    
+    #[test]
+    fn requests_round_trip_with_unicode_memos_and_exact_amounts() {
+        let address = Wallet::generate(&SeededEntropy::new(1), Network::Mainnet).address;
+        for amount in [12.5, 0.1 + 0.2, 1e-8, 1.0 / 3.0, f64::MIN_POSITIVE] {
+            let request = PaymentRequest {
+                address: address.clone(),
+                amount: Some(amount),
+                memo: Some("Счёт №42 — кофе ☕ & булочка?=да".to_string()),
+                expires: Some(GENESIS_TIMESTAMP + 3600),
+                chain_id: Some(7),
+            };
+            let uri = request.to_uri();
+            assert!(uri.starts_with(&format!("hellochain:{}?amount=", address)), "{}", uri);
+            assert!(uri.is_ascii() && !uri["hellochain:".len()..].contains([' ', ':', '#']), "{}", uri);
+            let parsed = PaymentRequest::parse(&uri).unwrap();
+            assert_eq!(parsed, request);
+            assert_eq!(parsed.amount.unwrap().to_bits(), amount.to_bits());
+        }
+        
+        let bare = PaymentRequest::new("alice".to_string());
+        assert_eq!(bare.to_uri(), "hellochain:alice");
+        assert_eq!(PaymentRequest::parse("hellochain:alice").unwrap(), bare);
+    }
+    
+    #[test]
+    fn unknown_parameters_are_ignored_unless_required() {
+        let parsed = PaymentRequest::parse("hellochain:alice?label=Shop&amount=2&x-note=%E2%98%95").unwrap();
+        assert_eq!((parsed.amount, parsed.memo), (Some(2.0), None));
+        assert!(matches!(
+            PaymentRequest::parse("hellochain:alice?amount=2&req-asset=gold"),
+            Err(BlockchainError::InvalidTransaction(reason)) if reason == "Unsupported required parameter req-asset"
+        ));
+        
+        for uri in [
+            "bitcoin:alice?amount=2",
+            "hellochain:?amount=2",
+            "hellochain:BLOCKCHAIN_REWARD",
+            "hellochain:alice?amount=-1",
+            "hellochain:alice?amount=NaN",
+            "hellochain:alice?exp=soon",
+            "hellochain:alice?memo=%E2%98",
+            "hellochain:alice?memo=%4",
+        ] {
+            assert!(PaymentRequest::parse(uri).is_err(), "{}", uri);
+        }
+        // Адрес сети с испорченной контрольной суммой
+        let mut address = Wallet::generate(&SeededEntropy::new(1), Network::Mainnet).address;
+        let last = address.pop().unwrap();
+        address.push(if last == '0' { '1' } else { '0' });
+        assert!(PaymentRequest::parse(&format!("hellochain:{}", address)).is_err());
+    }
+    
+    #[test]
+    fn built_payment_pays_the_request() {
+        let mut chain = chain();
+        let request = PaymentRequest::parse("hellochain:bob?amount=12.5&memo=%D0%BA%D0%BE%D1%84%D0%B5&chain=1").unwrap();
+        let mut tx = chain.build_payment(&request, "alice").unwrap();
+        assert_eq!((tx.sender.as_str(), tx.receiver.as_str(), tx.amount), ("alice", "bob", 12.5));
+        assert_eq!(tx.transaction_type, TransactionType::Data("кофе".as_bytes().to_vec()));
+        assert_eq!(tx.timestamp, GENESIS_TIMESTAMP + 100);
+        
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("bob"), 12.5);
+        
+        let no_amount = PaymentRequest::new("bob".to_string());
+        assert!(chain.build_payment(&no_amount, "alice").is_err());
+        let other_chain = PaymentRequest { chain_id: Some(2), ..request };
+        assert!(matches!(chain.build_payment(&other_chain, "alice"), Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("chain 2")));
+    }
+    
+    #[test]
+    fn wrong_network_and_expired_requests_are_refused() {
+        let chain = chain();
+        let testnet = Wallet::generate(&SeededEntropy::new(1), Network::Testnet).address;
+        let request = PaymentRequest::parse(&format!("hellochain:{}?amount=1", testnet)).unwrap();
+        assert!(matches!(chain.build_payment(&request, "alice"), Err(BlockchainError::WrongNetwork { expected: "mainnet", found: "testnet" })));
+        
+        let expires = GENESIS_TIMESTAMP + 100;
+        let expired = PaymentRequest { amount: Some(1.0), expires: Some(expires), ..PaymentRequest::new("bob".to_string()) };
+        assert!(matches!(chain.build_payment(&expired, "alice"), Err(BlockchainError::InvalidTransaction(reason)) if reason == format!("Payment request expired at {}", expires)));
+        let valid = PaymentRequest { expires: Some(expires + 1), ..expired };
+        assert!(chain.build_payment(&valid, "alice").is_ok());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::assets::Asset;
+use crate::blockchain::Blockchain;
+use crate::governance::Governance;
+use crate::trace::warn;
+use crate::wallet::Wallet;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+
+/// Меньшие расхождения сумм считаются округлением
+const BALANCE_TOLERANCE: f64 = 1e-9;
+
+/// Поле кошелька, значение которого в живом состоянии расходится с восстановленным по цепочке
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StateDifference {
+    pub address: String,
+    /// Имя поля кошелька; `wallet` — кошелек есть только с одной стороны
+    pub field: &'static str,
+    pub live: Value,
+    pub rebuilt: Value,
+}
+
+/// Итог восстановления состояния кошельков по цепочке
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StateRebuildReport {
+    pub differences: Vec<StateDifference>,
+    /// Заменено ли живое состояние восстановленным
+    pub applied: bool,
+}
+
+impl StateRebuildReport {
+    pub fn is_clean(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// Ненулевые балансы активов; нулевые записи остаются после переводов и ничего не значат
+fn held_assets(wallet: &Wallet) -> BTreeMap<&str, u64> {
+    wallet.assets.iter().filter(|(_, &amount)| amount != 0).map(|(id, &amount)| (id.as_str(), amount)).collect()
+}
+
+pub(crate) fn is_empty(wallet: &Wallet) -> bool {
+    wallet.balance.abs() <= BALANCE_TOLERANCE && wallet.staking_balance.abs() <= BALANCE_TOLERANCE
+        && wallet.claimable_rewards.abs() <= BALANCE_TOLERANCE && held_assets(wallet).is_empty()
+}
+
+/// Добавляет к кошелькам пополнения в обход блоков и переводит стейки валидаторов из баланса
+/// в стейк; `sign` -1 отменяет уже учтенные
+fn add_off_chain<'a>(
+    wallets: &mut HashMap<String, Wallet>,
+    credits: &BTreeMap<String, f64>,
+    stakes: impl IntoIterator<Item = (&'a String, &'a f64)>,
+    sign: f64,
+) {
+    for (address, amount) in credits {
+        wallets.entry(address.clone()).or_insert_with(|| Wallet::new(address.clone())).balance += sign * amount;
+    }
+    for (address, stake) in stakes {
+        let wallet = wallets.entry(address.clone()).or_insert_with(|| Wallet::new(address.clone()));
+        wallet.balance -= sign * stake;
+        wallet.staking_balance += sign * stake;
+    }
+}
+
+/// Сравнивает кошельки поле за полем
+fn wallet_differences(address: &str, live: &Wallet, rebuilt: &Wallet) -> Vec<StateDifference> {
+    let mut differences = Vec::new();
+    let mut differ = |field, live: Value, rebuilt: Value| {
+        differences.push(StateDifference { address: address.to_string(), field, live, rebuilt });
+    };
+    
+    for (field, live_amount, rebuilt_amount) in [
+        ("balance", live.balance, rebuilt.balance),
+        ("staking_balance", live.staking_balance, rebuilt.staking_balance),
+        ("claimable_rewards", live.claimable_rewards, rebuilt.claimable_rewards),
+    ] {
+        if (live_amount - rebuilt_amount).abs() > BALANCE_TOLERANCE {
+            differ(field, json!(live_amount), json!(rebuilt_amount));
+        }
+    }
+    if live.vesting != rebuilt.vesting {
+        differ("vesting", json!(live.vesting), json!(rebuilt.vesting));
+    }
+    if live.frozen != rebuilt.frozen {
+        differ("frozen", json!(live.frozen), json!(rebuilt.frozen));
+    }
+    if live.closed_at != rebuilt.closed_at {
+        differ("closed_at", json!(live.closed_at), json!(rebuilt.closed_at));
+    }
+    if live.public_key != rebuilt.public_key {
+        differ("public_key", json!(live.public_key), json!(rebuilt.public_key));
+    }
+    if live.next_nonce != rebuilt.next_nonce {
+        differ("next_nonce", json!(live.next_nonce), json!(rebuilt.next_nonce));
+    }
+    if held_assets(live) != held_assets(rebuilt) {
+        differ("assets", json!(held_assets(live)), json!(held_assets(rebuilt)));
+    }
+    differences
+}
+
+impl Blockchain {
+    /// Заново проигрывает все блоки от генезиса в пустой набор кошельков, сравнивает результат с
+    /// живым состоянием и, если это не пробный прогон, заменяет его восстановленным. Пополнения и
+    /// стейки валидаторов, сделанные в обход блоков, берутся из их учета, а списания транзакций
+    /// пула повторяются поверх цепочки. Проводки и записи журнала изменений балансов блоков, которых
+    /// в цепочке уже нет, при этом отбрасываются.
+    pub fn rebuild_state_from_chain(&mut self, dry_run: bool) -> StateRebuildReport {
+        let (mut wallets, assets) = self.replay_state();
+        // Ключи узла в цепочке не записаны; переносим их до сравнения, чтобы они не считались расхождением
+        for (address, wallet) in &mut wallets {
+            if let Some(previous) = self.wallets.get(address) {
+                wallet.keep_node_keys(previous);
+            }
+        }
+        
+        let addresses: BTreeSet<&String> = self.wallets.keys().chain(wallets.keys()).collect();
+        let mut differences = Vec::new();
+        for address in addresses {
+            match (self.wallets.get(address), wallets.get(address)) {
+                (Some(live), Some(rebuilt)) => differences.extend(wallet_differences(address, live, rebuilt)),
+                (live, rebuilt) => differences.push(StateDifference {
+                    address: address.clone(),
+                    field: "wallet",
+                    live: json!(live.is_some()),
+                    rebuilt: json!(rebuilt.is_some()),
+                }),
+            }
+        }
+        
+        let mut reverted = Vec::new();
+        if !dry_run {
+            self.trim_ledger_to_chain();
+            reverted = self.trim_balance_audit_to_chain();
+        }
+        let applied = !dry_run && !differences.is_empty();
+        if applied {
+            warn!(differences = differences.len(), "Wallet state rebuilt from the chain");
+            let live = std::mem::replace(&mut self.wallets, wallets);
+            self.record_rebuild_corrections(&live, &reverted);
+            self.assets = assets;
+            self.rebuild_wallet_histories();
+        }
+        StateRebuildReport { differences, applied }
+    }
+    
+    /// Проигрывает цепочку теми же функциями, что применяют блоки, на месте живого состояния
+    /// и возвращает восстановленные кошельки и активы; живое состояние остается прежним
+    fn replay_state(&mut self) -> (HashMap<String, Wallet>, HashMap<String, Asset>) {
+        let (wallets, assets) = self.replay_base();
+        let (mut wallets, assets) = self.replay_blocks(wallets, assets, self.first_replayed_height() as usize..self.chain.len());
+        
+        // Удаление пыли зависит от пула узла и не проигрывается: удаленный и не пополненный
+        // снова адрес просто не восстанавливается
+        wallets.retain(|address, wallet| {
+            self.wallets.contains_key(address) || !self.reaped_accounts.contains_key(address) || !is_empty(wallet)
+        });
+        (wallets, assets)
+    }
+    
+    /// Кошельки и активы до первого проигрываемого блока: пополнения в обход блоков и стейки
+    /// валидаторов высоты не имеют и считаются сделанными в генезисе. У узла из снимка основой
+    /// служит состояние снимка, к которому добавляются пополнения и стейки, сделанные после него.
+    pub(crate) fn replay_base(&self) -> (HashMap<String, Wallet>, HashMap<String, Asset>) {
+        // Кошельки, созданные без средств, в цепочке не видны
+        let mut wallets: HashMap<String, Wallet> = self.wallets.keys()
+            .map(|address| (address.clone(), Wallet::new(address.clone())))
+            .collect();
+        let mut assets = HashMap::new();
+        if let Some(base) = &self.snapshot_base {
+            wallets.extend(base.state.wallets.clone());
+            assets.extend(base.state.assets.clone());
+            add_off_chain(&mut wallets, &base.state.off_chain_credits, &base.state.validators, -1.0);
+        }
+        add_off_chain(&mut wallets, &self.off_chain_credits, &self.validators, 1.0);
+        (wallets, assets)
+    }
+    
+    /// Проигрывает блоки с номерами из `blocks` той же `apply_block_effects`, что применяет новые блоки,
+    /// поверх переданных кошельков и активов. Живое состояние, пул, журнал изменений балансов
+    /// и управление остаются прежними, а подписчики событий ничего не получают.
+    pub(crate) fn replay_blocks(
+        &mut self,
+        wallets: HashMap<String, Wallet>,
+        assets: HashMap<String, Asset>,
+        blocks: Range<usize>,
+    ) -> (HashMap<String, Wallet>, HashMap<String, Asset>) {
+        let utxo_set = self.utxo_set_before(blocks.start);
+        let live_utxo_set = std::mem::replace(&mut self.utxo_set, utxo_set);
+        let live_wallets = std::mem::replace(&mut self.wallets, wallets);
+        let live_assets = std::mem::replace(&mut self.assets, assets);
+        let replayed_governance = match &self.snapshot_base {
+            Some(base) => base.state.governance.clone(),
+            None => self.governance.as_ref().map(|governance| Governance::new(governance.config.clone())),
+        };
+        let live_governance = std::mem::replace(&mut self.governance, replayed_governance);
+        let chain = std::mem::take(&mut self.chain);
+        let live_pool = std::mem::take(self.mempool.get_mut());
+        let events = std::mem::take(&mut self.events);
+        let params = self.params.clone();
+        let reaped_accounts = self.reaped_accounts.clone();
+        let off_chain_credits = self.off_chain_credits.clone();
+        let audit_log = std::mem::take(&mut self.balance_audit_log);
+        let ledger = std::mem::take(&mut self.ledger);
+        
+        for block in chain.get(blocks).unwrap_or_default() {
+            self.apply_block_effects(block);
+        }
+        
+        let wallets = std::mem::replace(&mut self.wallets, live_wallets);
+        let assets = std::mem::replace(&mut self.assets, live_assets);
+        self.utxo_set = live_utxo_set;
+        self.governance = live_governance;
+        self.chain = chain;
+        *self.mempool.get_mut() = live_pool;
+        self.events = events;
+        self.params = params;
+        self.reaped_accounts = reaped_accounts;
+        self.off_chain_credits = off_chain_credits;
+        self.balance_audit_log = audit_log;
+        self.ledger = ledger;
+        (wallets, assets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    
+    fn send(chain: &mut Blockchain, from: &str, to: &str, amount: f64) {
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+    }
+    
+    /// Переводы, выпуск, стейк валидатора, грант с вестингом и перевод в пуле
+    fn scenario_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.enable_minting("alice").unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        send(&mut chain, "alice", "bob", 40.0);
+        chain.mint("carol", 15.0).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        chain.add_validator("bob".to_string(), 10.0).unwrap();
+        chain.grant_vesting("alice", "bob", 20.0, 3, 2, 4).unwrap();
+        chain.mine_pending_transactions("bob".to_string()).unwrap();
+        send(&mut chain, "bob", "alice", 5.0);
+        chain
+    }
+    
+    #[test]
+    fn normal_scenario_rebuilds_clean() {
+        let mut chain = scenario_chain();
+        let report = chain.rebuild_state_from_chain(false);
+        assert!(report.is_clean(), "{:?}", report.differences);
+        assert!(!report.applied);
+    }
+    
+    #[test]
+    fn mutated_balance_is_reported_and_repaired() {
+        let mut chain = scenario_chain();
+        // Пересборка сверяет подтвержденный баланс, без перевода bob, ждущего в пуле
+        let balance = chain.wallets["bob"].balance;
+        chain.wallets.get_mut("bob").unwrap().balance += 25.0;
+        chain.wallets.get_mut("carol").unwrap().next_nonce = 7;
+        
+        let report = chain.rebuild_state_from_chain(true);
+        assert!(!report.applied);
+        let fields: Vec<(&str, &str)> = report.differences.iter().map(|d| (d.address.as_str(), d.field)).collect();
+        assert_eq!(fields, vec![("bob", "balance"), ("carol", "next_nonce")]);
+        assert_eq!(report.differences[0].rebuilt, json!(balance));
+        assert_eq!(chain.wallets["bob"].balance, balance + 25.0);
+        
+        let report = chain.rebuild_state_from_chain(false);
+        assert!(report.applied);
+        assert_eq!(report.differences.len(), 2);
+        assert_eq!(chain.wallets["bob"].balance, balance);
+        assert_eq!(chain.get_wallet_info("carol").unwrap().next_nonce, 0);
+        assert!(chain.rebuild_state_from_chain(true).is_clean());
+        // Восстановленное состояние продолжает принимать транзакции
+        send(&mut chain, "bob", "carol", 1.0);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(chain.rebuild_state_from_chain(true).is_clean());
+    }
+    
+    #[test]
+    fn empty_chain_rebuilds_to_genesis_allocations() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.allocate_at_genesis("alice", 50.0).unwrap();
+        assert!(chain.rebuild_state_from_chain(true).is_clean());
+        
+        chain.wallets.get_mut("alice").unwrap().balance = 0.0;
+        chain.wallets.get_mut("bob").unwrap().balance = 30.0;
+        let report = chain.rebuild_state_from_chain(false);
+        assert_eq!(report.differences.len(), 2);
+        assert_eq!(chain.get_balance("alice"), 50.0);
+        assert_eq!(chain.get_balance("bob"), 0.0);
+        assert_eq!(chain.wallets.values().map(|wallet| wallet.balance).sum::<f64>(), 50.0);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::blockchain::Blockchain;
+use crate::fee_market::pays_base_fee;
+use crate::transaction::Transaction;
+use serde::{Serialize, Deserialize};
+
+/// Состояние транзакции с точки зрения узла
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReceiptStatus {
+    Pending,
+    Confirmed,
+    /// Убрана из пула: срок истек раньше, чем она попала в блок
+    Expired,
+}
+
+/// Квитанция о транзакции: где и в каком состоянии она находится
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionReceipt {
+    pub tx_id: String,
+    pub status: ReceiptStatus,
+    pub block_index: Option<u64>,
+    pub block_hash: Option<String>,
+    pub sender: String,
+    pub receiver: String,
+    pub amount: f64,
+    pub fee: f64,
+    /// На сколько комиссия меньше полной минимальной благодаря скидке за стейк
+    #[serde(default)]
+    pub fee_discount: f64,
+    /// Спонсор, заплативший комиссию вместо отправителя
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_payer: Option<String>,
+}
+
+/// Скидка, которой воспользовалась транзакция: недоплата до полной минимальной комиссии
+fn fee_discount(tx: &Transaction) -> f64 {
+    if !pays_base_fee(tx) {
+        return 0.0;
+    }
+    (Transaction::minimum_fee(&tx.transaction_type, tx.amount) - tx.fee).max(0.0)
+}
+
+impl Blockchain {
+    /// Возвращает квитанцию для транзакции из блока, из списка ожидающих или из просроченных
+    pub fn get_transaction_receipt(&self, tx_id: &str) -> Option<TransactionReceipt> {
+        for block in &self.chain {
+            if let Some(tx) = block.transactions.iter().find(|tx| tx.id == tx_id) {
+                return Some(TransactionReceipt {
+                    tx_id: tx.id.clone(),
+                    status: ReceiptStatus::Confirmed,
+                    block_index: Some(block.index),
+                    block_hash: Some(block.hash.clone()),
+                    sender: tx.sender.clone(),
+                    receiver: tx.receiver.clone(),
+                    amount: tx.amount,
+                    fee: tx.fee,
+                    fee_discount: fee_discount(tx),
+                    fee_payer: tx.fee_payer.as_ref().map(|payer| payer.address.clone()),
+                });
+            }
+        }
+        
+        let pending = self.mempool.get(tx_id).map(|tx| (tx, ReceiptStatus::Pending));
+        pending.or_else(|| self.expired_transactions.get(tx_id).map(|tx| (tx.clone(), ReceiptStatus::Expired)))
+            .map(|(tx, status)| TransactionReceipt {
+                tx_id: tx.id.clone(),
+                status,
+                block_index: None,
+                block_hash: None,
+                sender: tx.sender.clone(),
+                receiver: tx.receiver.clone(),
+                amount: tx.amount,
+                fee: tx.fee,
+                fee_discount: fee_discount(&tx),
+                fee_payer: tx.fee_payer.as_ref().map(|payer| payer.address.clone()),
+            })
+    }
+}
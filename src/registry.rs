@@ -0,0 +1,272 @@
+use crate::blockchain::{Blockchain, ConsensusAlgorithm, EMPTY_CHAIN};
+use crate::errors::BlockchainError;
+use crate::shared::SharedBlockchain;
+use crate::spec::ChainSpec;
+use crate::storage;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Имя, под которым серверы с одной цепочкой регистрируют ее в реестре
+pub const DEFAULT_CHAIN_NAME: &str = "main";
+
+/// Каталог внутри каталога данных, где лежат цепочки реестра
+pub const CHAINS_DIR: &str = "chains";
+
+/// Каталог данных цепочки `name` внутри общего каталога данных
+pub fn chain_dir(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir.join(CHAINS_DIR).join(name)
+}
+
+/// Имя цепочки становится именем каталога, поэтому допускаются только буквы, цифры, `-` и `_`
+pub fn validate_chain_name(name: &str) -> Result<(), BlockchainError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(BlockchainError::Storage(format!(
+            "Invalid chain name '{}': use letters, digits, '-' and '_'", name
+        )));
+    }
+    Ok(())
+}
+
+/// Сводка по цепочке реестра
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainSummary {
+    pub name: String,
+    pub genesis_hash: String,
+    pub chain_id: u64,
+    pub height: u64,
+    pub consensus: ConsensusAlgorithm,
+}
+
+#[derive(Default)]
+struct Registry {
+    chains: BTreeMap<String, SharedBlockchain>,
+    default: Option<String>,
+}
+
+/// Несколько именованных цепочек в одном процессе. Одна цепочка не регистрируется дважды:
+/// ни под тем же именем, ни под другим с тем же генезисом и идентификатором цепочки
+#[derive(Clone, Default)]
+pub struct ChainRegistry {
+    inner: Arc<RwLock<Registry>>,
+}
+
+impl ChainRegistry {
+    pub fn new() -> Self {
+        ChainRegistry::default()
+    }
+    
+    /// Реестр из одной цепочки, которая становится цепочкой по умолчанию
+    pub fn single(name: &str, chain: impl Into<SharedBlockchain>) -> Result<Self, BlockchainError> {
+        let registry = ChainRegistry::new();
+        registry.register(name, chain)?;
+        Ok(registry)
+    }
+    
+    /// Добавляет цепочку под именем `name`; первая зарегистрированная становится цепочкой по умолчанию
+    pub fn register(&self, name: &str, chain: impl Into<SharedBlockchain>) -> Result<SharedBlockchain, BlockchainError> {
+        validate_chain_name(name)?;
+        let chain = chain.into();
+        let identity = chain_identity(&chain)?;
+        
+        let mut registry = self.inner.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if registry.chains.contains_key(name) {
+            return Err(BlockchainError::DuplicateChain { name: name.to_string(), existing: name.to_string() });
+        }
+        for (existing, other) in &registry.chains {
+            if chain_identity(other)? == identity {
+                return Err(BlockchainError::DuplicateChain { name: name.to_string(), existing: existing.clone() });
+            }
+        }
+        
+        registry.chains.insert(name.to_string(), chain.clone());
+        registry.default.get_or_insert_with(|| name.to_string());
+        Ok(chain)
+    }
+    
+    /// Создает цепочку по спецификации и регистрирует ее
+    pub fn create(&self, name: &str, spec: &ChainSpec) -> Result<SharedBlockchain, BlockchainError> {
+        self.register(name, spec.build()?)
+    }
+    
+    pub fn get(&self, name: &str) -> Result<SharedBlockchain, BlockchainError> {
+        self.inner.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .chains.get(name)
+            .cloned()
+            .ok_or_else(|| BlockchainError::ChainNotFound { name: name.to_string() })
+    }
+    
+    /// Цепочка по имени, а без имени — цепочка по умолчанию
+    pub fn resolve(&self, name: Option<&str>) -> Result<SharedBlockchain, BlockchainError> {
+        match name {
+            Some(name) => self.get(name),
+            None => {
+                let default = self.default_name()
+                    .ok_or_else(|| BlockchainError::ChainNotFound { name: String::from("default") })?;
+                self.get(&default)
+            },
+        }
+    }
+    
+    pub fn default_name(&self) -> Option<String> {
+        self.inner.read().unwrap_or_else(|poisoned| poisoned.into_inner()).default.clone()
+    }
+    
+    pub fn set_default(&self, name: &str) -> Result<(), BlockchainError> {
+        let mut registry = self.inner.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !registry.chains.contains_key(name) {
+            return Err(BlockchainError::ChainNotFound { name: name.to_string() });
+        }
+        registry.default = Some(name.to_string());
+        Ok(())
+    }
+    
+    /// Убирает цепочку из реестра, не трогая ее файлы
+    pub fn remove(&self, name: &str) -> Result<SharedBlockchain, BlockchainError> {
+        let mut registry = self.inner.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let chain = registry.chains.remove(name)
+            .ok_or_else(|| BlockchainError::ChainNotFound { name: name.to_string() })?;
+        if registry.default.as_deref() == Some(name) {
+            registry.default = registry.chains.keys().next().cloned();
+        }
+        Ok(chain)
+    }
+    
+    /// Сводки по всем цепочкам в порядке имен
+    pub fn list(&self) -> Vec<ChainSummary> {
+        let registry = self.inner.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        registry.chains.iter()
+            .map(|(name, chain)| {
+                let chain = chain.read();
+                ChainSummary {
+                    name: name.clone(),
+                    genesis_hash: chain.chain.first().map(|block| block.hash.clone()).unwrap_or_default(),
+                    chain_id: chain.chain_id,
+                    height: chain.get_latest_block().index,
+                    consensus: chain.consensus_algorithm.clone(),
+                }
+            })
+            .collect()
+    }
+    
+    /// Сохраняет каждую цепочку в ее собственный каталог `chains/<имя>`
+    pub fn save(&self, data_dir: &Path) -> Result<(), BlockchainError> {
+        let chains: Vec<(String, SharedBlockchain)> = self.inner.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .chains.iter()
+            .map(|(name, chain)| (name.clone(), chain.clone()))
+            .collect();
+        for (name, chain) in chains {
+            chain.read().save(&chain_dir(data_dir, &name))?;
+        }
+        Ok(())
+    }
+    
+    /// Загружает все цепочки из `chains/` каталога данных; по умолчанию — первая по имени
+    pub fn load(data_dir: &Path) -> Result<Self, BlockchainError> {
+        let registry = ChainRegistry::new();
+        let root = data_dir.join(CHAINS_DIR);
+        if !root.is_dir() {
+            return Ok(registry);
+        }
+        
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&root).map_err(|e| BlockchainError::Storage(format!("{}: {}", root.display(), e)))? {
+            let entry = entry.map_err(|e| BlockchainError::Storage(format!("{}: {}", root.display(), e)))?;
+            if let Some(name) = entry.file_name().to_str() {
+                if validate_chain_name(name).is_ok() && storage::is_initialized(&entry.path()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        
+        for name in names {
+            registry.register(&name, Blockchain::load(&chain_dir(data_dir, &name))?)?;
+        }
+        Ok(registry)
+    }
+}
+
+/// Чем цепочка отличается от других: генезис зависит только от сложности,
+/// поэтому к его хешу добавляется идентификатор цепочки
+fn chain_identity(chain: &SharedBlockchain) -> Result<(String, u64), BlockchainError> {
+    let chain = chain.read();
+    let genesis = chain.chain.first().ok_or_else(|| BlockchainError::InvalidBlock(EMPTY_CHAIN.to_string()))?;
+    Ok((genesis.hash.clone(), chain.chain_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::SignatureVerification;
+    use serde_json::json;
+    
+    fn spec(value: serde_json::Value) -> ChainSpec {
+        serde_json::from_value(value).unwrap()
+    }
+    
+    fn main_spec() -> ChainSpec {
+        spec(json!({ "chain_id": 1, "accept_legacy_addresses": true, "difficulty": 1, "mining_reward": 50.0, "consensus": "ProofOfWork", "allocations": { "alice": 100.0 } }))
+    }
+    
+    fn side_spec() -> ChainSpec {
+        spec(json!({ "chain_id": 2, "accept_legacy_addresses": true, "difficulty": 2, "mining_reward": 10.0, "consensus": "ProofOfWork", "allocations": { "bob": 200.0 } }))
+    }
+    
+    #[test]
+    fn chains_stay_isolated_through_save_and_load() {
+        let registry = ChainRegistry::new();
+        let main = registry.create("main", &main_spec()).unwrap();
+        let side = registry.create("side", &side_spec()).unwrap();
+        for _ in 0..2 {
+            main.write().mine_pending_transactions("alice".to_string()).unwrap();
+        }
+        side.write().mine_pending_transactions("bob".to_string()).unwrap();
+        
+        let dir = std::env::temp_dir().join(format!("hellochain-registry-{}", std::process::id()));
+        registry.save(&dir).unwrap();
+        assert!(storage::is_initialized(&chain_dir(&dir, "main")));
+        assert!(storage::is_initialized(&chain_dir(&dir, "side")));
+        let loaded = ChainRegistry::load(&dir);
+        let _ = fs::remove_dir_all(&dir);
+        let loaded = loaded.unwrap();
+        
+        assert_eq!(loaded.list(), registry.list());
+        let summaries = loaded.list();
+        assert_eq!(summaries.iter().map(|summary| (summary.name.as_str(), summary.chain_id, summary.height)).collect::<Vec<_>>(), vec![("main", 1, 2), ("side", 2, 1)]);
+        assert_eq!(loaded.default_name().as_deref(), Some("main"));
+        
+        let main = loaded.resolve(None).unwrap();
+        let side = loaded.resolve(Some("side")).unwrap();
+        let (main, side) = (main.read(), side.read());
+        main.validate_chain(SignatureVerification::Serial).unwrap();
+        side.validate_chain(SignatureVerification::Serial).unwrap();
+        assert_eq!(main.get_balance("alice"), 200.0);
+        assert_eq!(main.get_balance("bob"), 0.0);
+        assert_eq!(side.get_balance("bob"), 210.0);
+        assert_eq!(side.get_balance("alice"), 0.0);
+        assert_eq!(side.difficulty, 2);
+    }
+    
+    #[test]
+    fn a_chain_is_registered_only_once() {
+        let registry = ChainRegistry::single("main", main_spec().build().unwrap()).unwrap();
+        assert!(matches!(
+            registry.create("main", &side_spec()),
+            Err(BlockchainError::DuplicateChain { existing, .. }) if existing == "main"
+        ));
+        assert!(matches!(
+            registry.create("copy", &main_spec()),
+            Err(BlockchainError::DuplicateChain { name, existing }) if name == "copy" && existing == "main"
+        ));
+        assert!(matches!(registry.create("../escape", &side_spec()), Err(BlockchainError::Storage(_))));
+        assert!(matches!(registry.get("side"), Err(BlockchainError::ChainNotFound { .. })));
+        
+        registry.create("side", &side_spec()).unwrap();
+        registry.set_default("side").unwrap();
+        registry.remove("side").unwrap();
+        assert_eq!(registry.default_name().as_deref(), Some("main"));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::block::BlockHeader;
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::errors::BlockchainError;
+use crate::hashing::HashKind;
+use crate::light_client::{InclusionProof, LightClientError};
+use crate::merkle::leaf_hash;
+use crate::signing::{self, owned_key};
+use crate::supply::{balance_effect, involves, SUPPLY_TOLERANCE};
+use crate::sync::validate_header;
+use crate::transaction::calculate_hash;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Доверенная точка отсчета для проверки доказательств резервов: заголовок генезиса или контрольной
+/// точки и балансы после него. Проверяющий получает ее независимо от того, кто доказывает.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReserveCheckpoint {
+    pub header: BlockHeader,
+    pub consensus: ConsensusAlgorithm,
+    /// Балансы по цепочке и пополнениям в обход блоков, как в `audit_supply`
+    pub balances: BTreeMap<String, f64>,
+    /// Открытые ключи, закрепленные за именами без контрольной суммы; ключ адреса сети выводится из него самого
+    #[serde(default)]
+    pub public_keys: BTreeMap<String, String>,
+}
+
+/// Доказательство резервов: баланс адреса на высоте выводится из заголовков после контрольной точки
+/// и доказательств включения всех транзакций адреса, а владелец подписывает итоговое утверждение.
+/// Заголовки не фиксируют, какие адреса затрагивает блок, поэтому за полноту списка транзакций
+/// ручается подпись владельца, а не сама цепочка.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReserveProof {
+    pub address: String,
+    pub height: u64,
+    /// Заголовки от блока после контрольной точки до `height` включительно
+    pub headers: Vec<BlockHeader>,
+    /// Транзакции, где адрес отправитель, получатель или спонсор, по порядку в цепочке
+    pub inclusions: Vec<InclusionProof>,
+    pub balance: f64,
+    /// Открытый ключ владельца адреса в hex
+    pub public_key: String,
+    /// Подпись ed25519 владельца адреса над `statement`
+    pub signature: String,
+}
+
+impl ReserveProof {
+    /// Подписываемое утверждение: адрес, высота и хеш блока на ней, баланс и транзакции, из которых он сложился.
+    /// Хешируется алгоритмом цепочки `hash_algorithm`.
+    pub fn statement(&self, hash_algorithm: HashKind) -> String {
+        let tip = self.headers.last().map_or("", |header| header.hash.as_str());
+        let transactions: Vec<&str> = self.inclusions.iter().map(|inclusion| inclusion.transaction.id.as_str()).collect();
+        calculate_hash(hash_algorithm, &format!("reserve{}{}{}{:?}{}", self.address, self.height, tip, self.balance, transactions.join(",")))
+    }
+}
+
+/// Проверяет доказательство резервов без цепочки: заголовки продолжают контрольную точку, каждая транзакция
+/// включена в свой блок и затрагивает адрес, баланс сходится с пересчитанным, а утверждение подписал владелец
+/// ключом, из которого выведен адрес или который закреплен за ним в контрольной точке. Возвращает пересчитанный баланс.
+pub fn verify_reserve_proof(proof: &ReserveProof, checkpoint: &ReserveCheckpoint) -> Result<f64, LightClientError> {
+    let mut parent = &checkpoint.header;
+    for header in &proof.headers {
+        validate_header(parent, header, &checkpoint.consensus).map_err(LightClientError::InvalidHeader)?;
+        parent = header;
+    }
+    if parent.index != proof.height {
+        return Err(LightClientError::InvalidProof(format!("headers end at {}, not at the proof height {}", parent.index, proof.height)));
+    }
+    
+    let mut seen = HashSet::new();
+    let mut balance = checkpoint.balances.get(&proof.address).copied().unwrap_or(0.0);
+    for inclusion in &proof.inclusions {
+        let header = inclusion.block_index.checked_sub(checkpoint.header.index + 1)
+            .and_then(|offset| proof.headers.get(offset as usize))
+            .filter(|header| header.hash == inclusion.block_hash)
+            .ok_or_else(|| LightClientError::NotOnBestChain(inclusion.block_hash.clone()))?;
+        let tx = &inclusion.transaction;
+        if inclusion.proof.leaf_hash != leaf_hash(tx) || !inclusion.proof.verify(header.hash_algorithm, &header.merkle_root) {
+            return Err(LightClientError::InvalidProof(format!("transaction {} is not in block {}", tx.id, inclusion.block_index)));
+        }
+        if !involves(tx, &proof.address) || !seen.insert(tx.id.as_str()) {
+            return Err(LightClientError::InvalidProof(format!("transaction {} does not belong to the proof", tx.id)));
+        }
+        balance += balance_effect(tx, &proof.address);
+    }
+    
+    if (balance - proof.balance).abs() > SUPPLY_TOLERANCE {
+        return Err(LightClientError::InvalidProof(format!("claimed balance {} differs from the computed {}", proof.balance, balance)));
+    }
+    let bound = checkpoint.public_keys.get(&proof.address).map(String::as_str);
+    let signed = owned_key(&proof.address, &proof.public_key, bound)
+        .is_some_and(|public_key| signing::verify(&public_key, proof.statement(checkpoint.header.hash_algorithm).as_bytes(), &proof.signature));
+    if !signed {
+        return Err(LightClientError::InvalidProof(format!("statement is not signed by {}", proof.address)));
+    }
+    Ok(balance)
+}
+
+impl Blockchain {
+    /// Точка отсчета для проверки доказательств резервов на высоте `height`; высота 0 — генезис
+    /// с начальными распределениями
+    pub fn reserve_checkpoint(&self, height: u64) -> Result<ReserveCheckpoint, BlockchainError> {
+        let block = self.chain.get(height as usize)
+            .ok_or_else(|| BlockchainError::InvalidBlock(format!("Height {} is above the tip {}", height, self.get_latest_block().index)))?;
+        Ok(ReserveCheckpoint {
+            header: block.header(),
+            consensus: self.consensus_algorithm.clone(),
+            balances: self.expected_balances_at(height),
+            public_keys: self.wallets.values()
+                .filter_map(|wallet| wallet.public_key.clone().map(|public_key| (wallet.address.clone(), public_key)))
+                .collect(),
+        })
+    }
+    
+    /// Доказательство баланса адреса после блока `height`, проверяемое от генезиса
+    pub fn export_reserve_proof(&self, address: &str, height: u64) -> Result<ReserveProof, BlockchainError> {
+        self.export_reserve_proof_since(address, 0, height)
+    }
+    
+    /// То же от контрольной точки `checkpoint`: у очень активных адресов доказательство от генезиса
+    /// слишком велико, а история до контрольной точки заменяется ее балансами. Утверждение подписывается
+    /// ключом кошелька, поэтому он должен храниться на узле.
+    pub fn export_reserve_proof_since(&self, address: &str, checkpoint: u64, height: u64) -> Result<ReserveProof, BlockchainError> {
+        self.require_account_mode("Reserve proofs")?;
+        let keypair = self.wallets.get(address)
+            .ok_or_else(|| BlockchainError::WalletNotFound { address: address.to_string() })?
+            .keypair()
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Wallet {} holds no key", address)))?;
+        let tip = self.get_latest_block().index;
+        if height > tip || checkpoint > height {
+            return Err(BlockchainError::InvalidBlock(format!("Heights {}..{} are not within the chain up to {}", checkpoint, height, tip)));
+        }
+        
+        let blocks = self.chain.get(checkpoint as usize + 1..=height as usize).unwrap_or_default();
+        if let Some(block) = blocks.iter().find(|block| block.is_pruned()) {
+            return Err(BlockchainError::InvalidBlock(format!("Block # {} has no body on this node", block.index)));
+        }
+        let mut balance = self.expected_balances_at(checkpoint).get(address).copied().unwrap_or(0.0);
+        let mut inclusions = Vec::new();
+        for block in blocks {
+            for tx in block.transactions.iter().filter(|tx| involves(tx, address)) {
+                let proof = block.merkle_proof(&tx.id)
+                    .ok_or_else(|| BlockchainError::TransactionNotFound { tx_id: tx.id.clone() })?;
+                balance += balance_effect(tx, address);
+                inclusions.push(InclusionProof { block_hash: block.hash.clone(), block_index: block.index, transaction: tx.clone(), proof });
+            }
+        }
+        
+        let mut proof = ReserveProof {
+            address: address.to_string(),
+            height,
+            headers: blocks.iter().map(|block| block.header()).collect(),
+            inclusions,
+            balance,
+            public_key: keypair.public_key_hex(),
+            signature: String::new(),
+        };
+        proof.signature = keypair.sign(proof.statement(self.hash_algorithm).as_bytes());
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::entropy::OsEntropy;
+    use crate::signing::Keypair;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    
+    /// Цепочка, где `alice` с ключом и `miner` без ключа получили по награде
+    fn chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut tx = Transaction::new("alice".to_string(), "miner".to_string(), 50.0, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain
+    }
+    
+    #[test]
+    fn owner_signed_proof_verifies() {
+        let chain = chain();
+        let height = chain.get_latest_block().index;
+        let proof = chain.export_reserve_proof("alice", height).unwrap();
+        let checkpoint = chain.reserve_checkpoint(0).unwrap();
+        assert_eq!(verify_reserve_proof(&proof, &checkpoint).unwrap(), chain.get_balance("alice"));
+    }
+    
+    #[test]
+    fn proof_signed_by_a_stranger_is_rejected() {
+        let chain = chain();
+        let height = chain.get_latest_block().index;
+        let checkpoint = chain.reserve_checkpoint(0).unwrap();
+        let mut proof = chain.export_reserve_proof("alice", height).unwrap();
+        let stranger = Keypair::generate(&OsEntropy);
+        proof.public_key = stranger.public_key_hex();
+        proof.signature = stranger.sign(proof.statement(HashKind::Sha256).as_bytes());
+        assert!(matches!(verify_reserve_proof(&proof, &checkpoint), Err(LightClientError::InvalidProof(_))));
+        
+        // Подпись владельца не переносится на другое утверждение
+        let mut proof = chain.export_reserve_proof("alice", height).unwrap();
+        proof.height -= 1;
+        proof.headers.pop();
+        assert!(verify_reserve_proof(&proof, &checkpoint).is_err());
+    }
+    
+    #[test]
+    fn keyless_wallet_cannot_prove_reserves() {
+        let chain = chain();
+        let height = chain.get_latest_block().index;
+        assert!(matches!(chain.export_reserve_proof("miner", height), Err(BlockchainError::InvalidTransaction(_))));
+        assert!(matches!(chain.export_reserve_proof("nobody", height), Err(BlockchainError::WalletNotFound { .. })));
+    }
+}
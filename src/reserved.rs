@@ -0,0 +1,251 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::fee_market::pays_base_fee;
+use crate::mempool::Pool;
+use crate::packages::with_ancestors;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+
+/// Категория системной транзакции, которой может достаться зарезервированное место в блоке
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SystemCategory {
+    /// Предложения и голоса управления
+    Governance,
+    /// Расходы казны по одобрениям подписантов
+    Treasury,
+    /// Выпуск и возврат средств мостом
+    Bridge,
+    /// Заморозка и разморозка счетов
+    AccountFreeze,
+}
+
+impl SystemCategory {
+    /// Категория транзакции; у обычных транзакций ее нет
+    pub fn of(tx: &Transaction) -> Option<SystemCategory> {
+        match tx.transaction_type {
+            TransactionType::Propose { .. } | TransactionType::Vote { .. } | TransactionType::SettleProposal { .. } => Some(SystemCategory::Governance),
+            TransactionType::TreasurySpend { .. } => Some(SystemCategory::Treasury),
+            TransactionType::BridgeMint { .. } | TransactionType::BridgeRelease { .. } => Some(SystemCategory::Bridge),
+            TransactionType::FreezeAccount { .. } | TransactionType::UnfreezeAccount { .. } => Some(SystemCategory::AccountFreeze),
+            _ => None,
+        }
+    }
+}
+
+fn default_max_wait_blocks() -> u64 {
+    1
+}
+
+/// Доля веса блока, которую сначала занимают системные транзакции из отдельной очереди.
+/// Неизрасходованный резерв достается обычным транзакциям. Правило о пропущенных системных
+/// транзакциях проверяется только для блоков, которые собирает сам узел: очередь чужого
+/// производителя не видна, поэтому блоки пиров принимаются без этой проверки.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReservedSpace {
+    /// Доля предельного веса блока, от 0 до 1
+    pub fraction: f64,
+    /// Категории, которые идут в резерв
+    pub categories: Vec<SystemCategory>,
+    /// Столько блоков системная транзакция может ждать, пока резерв расходуется на другие
+    #[serde(default = "default_max_wait_blocks")]
+    pub max_wait_blocks: u64,
+}
+
+/// Системная транзакция в очереди резерва и высота, на которой она туда попала
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct QueuedSystemTransaction {
+    pub(crate) tx_id: String,
+    pub(crate) queued_at: u64,
+}
+
+/// Индексы транзакций пула из очереди резерва, от давних к новым
+pub(crate) fn system_queue_order(pool: &Pool) -> Vec<usize> {
+    let index: HashMap<&str, usize> = pool.transactions.iter().enumerate().map(|(i, tx)| (tx.id.as_str(), i)).collect();
+    pool.system_queue.iter().filter_map(|queued| index.get(queued.tx_id.as_str()).copied()).collect()
+}
+
+impl Blockchain {
+    /// Включает резерв места в блоке; он делится от предельного веса, поэтому нужен рынок комиссий
+    pub fn enable_reserved_space(&mut self, config: ReservedSpace) -> Result<(), BlockchainError> {
+        if self.fee_market.is_none() {
+            return Err(BlockchainError::InvalidBlock("Reserved block space needs a fee market".to_string()));
+        }
+        if !(0.0..=1.0).contains(&config.fraction) {
+            return Err(BlockchainError::InvalidBlock(format!("Reserved fraction {} is not between 0 and 1", config.fraction)));
+        }
+        self.params.reserved_space = Some(config);
+        Ok(())
+    }
+    
+    /// Вес блока, зарезервированный под системные транзакции
+    pub fn reserved_weight(&self) -> Option<u64> {
+        let reserved = self.params.reserved_space.as_ref()?;
+        let config = self.fee_market.as_ref()?;
+        Some((config.max_block_weight() as f64 * reserved.fraction) as u64)
+    }
+    
+    fn is_reserved(&self, tx: &Transaction) -> bool {
+        self.params.reserved_space.as_ref()
+            .is_some_and(|reserved| SystemCategory::of(tx).is_some_and(|category| reserved.categories.contains(&category)))
+    }
+    
+    /// Ставит системную транзакцию, поступающую в пул, в конец очереди резерва
+    pub(crate) fn enqueue_system_transaction(&self, pool: &mut Pool, tx: &Transaction) {
+        if self.is_reserved(tx) {
+            let queued_at = self.chain.len() as u64;
+            pool.system_queue.push(QueuedSystemTransaction { tx_id: tx.id.clone(), queued_at });
+        }
+    }
+    
+    /// Убирает из очереди резерва транзакции, которых больше нет в пуле
+    pub(crate) fn prune_system_queue(&mut self) {
+        let pool = self.mempool.get_mut();
+        let pending: HashSet<&str> = pool.transactions.iter().map(|tx| tx.id.as_str()).collect();
+        pool.system_queue.retain(|queued| pending.contains(queued.tx_id.as_str()));
+    }
+    
+    /// Системные транзакции пула в очереди резерва, от давних к новым
+    pub fn system_queue(&self) -> Vec<Transaction> {
+        let pool = self.mempool.lock();
+        system_queue_order(&pool).into_iter().map(|i| pool.transactions[i].clone()).collect()
+    }
+    
+    /// Проверяет собранный узлом блок: если резерв израсходован не полностью, в блоке должна быть
+    /// каждая допустимая системная транзакция, ждущая дольше `max_wait_blocks` и помещающаяся в остаток
+    pub(crate) fn check_reserved_space(&self, included: &[Transaction], pool: &Pool, selected: &[bool], height: u64, base_fee: Option<f64>) -> Result<(), BlockchainError> {
+        let (Some(reserved), Some(reserved_weight)) = (&self.params.reserved_space, self.reserved_weight()) else {
+            return Ok(());
+        };
+        let pending = &pool.transactions;
+        let used: u64 = included.iter().filter(|tx| self.is_reserved(tx) && pays_base_fee(tx)).map(Transaction::weight).sum();
+        let parents = self.pending_graph(pool);
+        let excluded = self.unselectable(pending, &parents, height, base_fee);
+        // Отобранные в блок предки уже не входят в пакет оставшейся транзакции
+        let skipped: Vec<bool> = excluded.iter().zip(selected).map(|(&excluded, &selected)| excluded || selected).collect();
+        let queued_at: HashMap<&str, u64> = pool.system_queue.iter().map(|queued| (queued.tx_id.as_str(), queued.queued_at)).collect();
+        
+        for i in system_queue_order(pool) {
+            let tx = &pending[i];
+            let waited = height.saturating_sub(queued_at.get(tx.id.as_str()).copied().unwrap_or(height));
+            if skipped[i] || waited < reserved.max_wait_blocks {
+                continue;
+            }
+            let package_weight: u64 = with_ancestors(&parents, i, &skipped).iter()
+                .map(|&j| &pending[j])
+                .filter(|tx| pays_base_fee(tx))
+                .map(Transaction::weight)
+                .sum();
+            if used + package_weight <= reserved_weight {
+                return Err(BlockchainError::InvalidBlock(format!(
+                    "Block # {} leaves {} of reserved weight unused but skips system transaction {} queued {} blocks ago",
+                    height, reserved_weight - used, tx.id, waited
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::fee_market::{block_weight, BaseFeeConfig};
+    use crate::governance::{GovernanceConfig, ProposalAction, GOVERNANCE_ESCROW};
+    use crate::test_support::ChainFixture;
+    
+    const BASE_FEE: f64 = 0.001;
+    
+    /// Подписанная транзакция, оплачивающая базовую комиссию по своему весу и чаевые
+    fn priced(chain: &Blockchain, sender: &str, receiver: &str, amount: f64, transaction_type: TransactionType, tip: f64) -> Transaction {
+        let mut tx = Transaction::new(sender.to_string(), receiver.to_string(), amount, transaction_type);
+        tx.set_nonce(chain.get_account_nonce(sender));
+        chain.sign_as_sender(&mut tx);
+        tx.fee = BASE_FEE * tx.weight() as f64 + tip;
+        tx.reseal();
+        chain.sign_as_sender(&mut tx);
+        tx
+    }
+    
+    fn proposal(chain: &Blockchain) -> Transaction {
+        let action = ProposalAction::Signal("raise the block size".to_string());
+        priced(chain, "alice", GOVERNANCE_ESCROW, 1.0, TransactionType::Propose { action }, 0.0)
+    }
+    
+    /// Кошельки carol и david с наградами, в блок помещаются четыре перевода, а резерв вмещает
+    /// одно предложение alice
+    fn reserved_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        for name in ["alice", "carol", "david"] {
+            chain.create_wallet(name.to_string()).unwrap();
+            chain.mine_pending_transactions(name.to_string()).unwrap();
+        }
+        chain.enable_governance(GovernanceConfig {
+            min_deposit: 1.0,
+            voting_period_blocks: 10,
+            activation_delay_blocks: 1,
+            quorum: 0.5,
+            threshold: 0.5,
+        }).unwrap();
+        let transfer_weight = priced(&chain, "carol", "erin", 1.0, TransactionType::Transfer, 1.0).weight();
+        chain.fee_market = Some(BaseFeeConfig {
+            initial_base_fee: BASE_FEE,
+            target_block_weight: 2 * transfer_weight,
+            elasticity_multiplier: 2,
+            change_denominator: 8.0,
+            min_base_fee: 0.0,
+        });
+        let max_weight = chain.fee_market.as_ref().unwrap().max_block_weight();
+        chain.enable_reserved_space(ReservedSpace {
+            fraction: (proposal(&chain).weight() as f64 + 0.5) / max_weight as f64,
+            categories: vec![SystemCategory::Governance],
+            max_wait_blocks: 1,
+        }).unwrap();
+        chain
+    }
+    
+    /// Шесть переводов с высокими чаевыми: вдвое больше, чем вмещает блок
+    fn saturate(chain: &mut Blockchain) {
+        for name in ["carol", "david", "carol", "david", "carol", "david"] {
+            let tx = priced(chain, name, "erin", 1.0, TransactionType::Transfer, 1.0);
+            chain.add_transaction(tx).unwrap();
+        }
+    }
+    
+    #[test]
+    fn system_transaction_confirms_under_a_saturated_pool() {
+        let mut chain = reserved_chain();
+        saturate(&mut chain);
+        let proposal = proposal(&chain);
+        chain.add_transaction(proposal.clone()).unwrap();
+        assert_eq!(chain.system_queue().iter().map(|tx| tx.id.as_str()).collect::<Vec<_>>(), vec![proposal.id.as_str()]);
+        
+        // Без резерва дешевое предложение проигрывает переводам с чаевыми
+        let mut unreserved: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        unreserved.params.reserved_space = None;
+        unreserved.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(!unreserved.get_latest_block().transactions.iter().any(|tx| tx.id == proposal.id));
+        
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let block = chain.get_latest_block();
+        assert!(block.transactions.iter().any(|tx| tx.id == proposal.id));
+        assert!(block_weight(&block.transactions) <= chain.fee_market.as_ref().unwrap().max_block_weight());
+        assert!(chain.system_queue().is_empty());
+        assert!(chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn unused_reserve_goes_to_ordinary_transactions() {
+        let mut chain = reserved_chain();
+        saturate(&mut chain);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        // Четыре перевода заполняют и обычное место, и пустующий резерв
+        let transfers = chain.get_latest_block().transactions.iter().filter(|tx| tx.transaction_type == TransactionType::Transfer && tx.sender != "BLOCKCHAIN_REWARD").count();
+        assert_eq!(transfers, 4);
+        assert_eq!(chain.mempool().len(), 2);
+        let max_weight = chain.fee_market.as_ref().unwrap().max_block_weight();
+        assert!(max_weight - block_weight(&chain.get_latest_block().transactions) < chain.reserved_weight().unwrap());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::balance_audit::BalanceBucket;
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::mempool::reserved_claim;
+use crate::params::RewardMode;
+use crate::staking::STAKING_MINTER;
+use crate::transaction::{Transaction, TransactionType};
+
+/// Сколько транзакция списывает с основного баланса отправителя; востребование наград платит
+/// с него только комиссию, а комиссию спонсируемой транзакции платит спонсор
+pub(crate) fn balance_debit(tx: &Transaction) -> f64 {
+    let fee = if tx.fee_payer.is_some() { 0.0 } else { tx.fee };
+    match tx.transaction_type {
+        TransactionType::ClaimRewards => fee,
+        _ => tx.amount + fee,
+    }
+}
+
+/// Сколько транзакция списывает с накопленных наград отправителя
+pub(crate) fn claim_debit(tx: &Transaction) -> Option<f64> {
+    matches!(tx.transaction_type, TransactionType::ClaimRewards).then_some(tx.amount)
+}
+
+impl Blockchain {
+    /// Накопленные и еще не востребованные награды адреса за вычетом занятых транзакциями пула
+    pub fn claimable(&self, address: &str) -> f64 {
+        self.wallets.get(address).map_or(0.0, |wallet| wallet.claimable_rewards - reserved_claim(&self.mempool.lock().transactions, address))
+    }
+    
+    /// Ставит в очередь перевод накопленных наград на основной баланс. Возвращает идентификатор транзакции.
+    pub fn claim_rewards(&mut self, address: &str, amount: f64) -> Result<String, BlockchainError> {
+        let mut tx = self.new_transaction(address.to_string(), address.to_string(), amount, TransactionType::ClaimRewards);
+        if let Some(base_fee) = self.current_base_fee() {
+            tx.fee = tx.fee.max(base_fee * tx.weight() as f64);
+        }
+        self.sign_as_sender(&mut tx);
+        
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Копится ли сумма транзакции в наградах получателя, а не на его балансе
+    pub(crate) fn accrues_reward(&self, tx: &Transaction) -> bool {
+        self.params.reward_mode == RewardMode::Accrue && (tx.sender == "BLOCKCHAIN_REWARD" || tx.sender == STAKING_MINTER)
+    }
+    
+    /// Зачисляет награды блока в накопленные, если цепочка их копит
+    pub(crate) fn accrue_block_rewards(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            if self.accrues_reward(tx) {
+                let wallet = self.get_or_create_wallet(&tx.receiver);
+                wallet.claimable_rewards += tx.amount;
+                self.record_credit(tx, BalanceBucket::Claimable);
+            }
+        }
+    }
+    
+    /// Проверяет, что награды востребует их владелец себе
+    pub(crate) fn check_claim_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        if claim_debit(tx).is_some() && tx.sender != tx.receiver {
+            return Err(BlockchainError::InvalidTransaction(format!("Rewards of {} can only be claimed to the same address", tx.sender)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    /// Цепочка в режиме накопления, где у alice 10 на балансе для комиссий и 100 накопленной награды
+    fn accruing_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.params.reward_mode = RewardMode::Accrue;
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.allocate_at_genesis("alice", 10.0).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain
+    }
+    
+    #[test]
+    fn accrued_reward_moves_to_the_balance_only_when_claimed() {
+        let mut chain = accruing_chain();
+        assert_eq!(chain.get_balance("alice"), 10.0);
+        assert_eq!(chain.claimable("alice"), 100.0);
+        assert_eq!(chain.get_wallet_info("alice").unwrap().claimable_rewards, 100.0);
+        
+        chain.claim_rewards("alice", 100.0).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.claimable("alice"), 0.0);
+        assert!((chain.get_balance("alice") - (10.0 + 100.0 - 0.1)).abs() < 1e-9);
+        // Награда майнера тоже копится, а комиссия востребования входит в нее
+        assert_eq!(chain.get_balance("miner"), 0.0);
+        assert!((chain.claimable("miner") - 100.1).abs() < 1e-9);
+        assert!(chain.rebuild_state_from_chain(true).is_clean());
+    }
+    
+    #[test]
+    fn claims_cannot_exceed_accruals() {
+        let mut chain = accruing_chain();
+        let result = chain.claim_rewards("alice", 100.5);
+        assert!(matches!(result, Err(BlockchainError::InsufficientBalance { required, available }) if required == 100.5 && available == 100.0), "{:?}", result);
+        // Две заявки вместе тоже не больше накопленного
+        chain.claim_rewards("alice", 60.0).unwrap();
+        assert!(matches!(chain.claim_rewards("alice", 60.0), Err(BlockchainError::InsufficientBalance { .. })));
+        
+        let mut tx = Transaction::new_at("alice".to_string(), "bob".to_string(), 10.0, TransactionType::ClaimRewards, chain.now_ts());
+        chain.sign_as_sender(&mut tx);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidTransaction(_))));
+    }
+    
+    #[test]
+    fn block_with_an_over_claim_fails_validation() {
+        let chain = accruing_chain();
+        
+        // Узел с завышенными накоплениями включает в блок лишнее востребование
+        let mut producer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        producer.wallets.get_mut("alice").unwrap().claimable_rewards = 500.0;
+        let mut claim = Transaction::new_at("alice".to_string(), "alice".to_string(), 300.0, TransactionType::ClaimRewards, chain.now_ts());
+        chain.sign_as_sender(&mut claim);
+        producer.add_transaction(claim).unwrap();
+        producer.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let result = chain.verify_block(producer.get_latest_block());
+        assert!(matches!(result, Err(BlockchainError::InsufficientBalance { required, .. }) if required == 300.0), "{:?}", result);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::blockchain::Blockchain;
+use crate::bridge::{BRIDGE_ESCROW, BRIDGE_MINTER};
+use crate::freeze::FREEZE_AUTHORITY;
+use crate::governance::GOVERNANCE_ESCROW;
+use crate::mint::MINT_AUTHORITY;
+use crate::staking::STAKING_MINTER;
+use crate::treasury::TREASURY_MINTER;
+use crate::vesting::BalanceDetails;
+use crate::wallet::Wallet;
+use serde::Serialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fmt;
+
+/// Служебные адреса протокола: выпуск, сжигание, эскроу и хранилище данных
+const PROTOCOL_ADDRESSES: [&str; 9] = [
+    "BLOCKCHAIN_REWARD", "BLOCKCHAIN_DATA", BRIDGE_MINTER, BRIDGE_ESCROW, MINT_AUTHORITY, TREASURY_MINTER, STAKING_MINTER, FREEZE_AUTHORITY,
+    GOVERNANCE_ESCROW,
+];
+
+/// Корзина распределения: адреса, чьи средства не меньше `min` и меньше `max`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DistributionBucket {
+    pub min: f64,
+    /// Нет у последней корзины
+    pub max: Option<f64>,
+    pub addresses: usize,
+    pub holdings: f64,
+}
+
+/// Распределение средств по адресам с ненулевыми средствами
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DistributionReport {
+    pub addresses: usize,
+    pub total_holdings: f64,
+    /// Корзины по порядкам: меньше 1, 1–10, 10–100 и так далее до самой крупной непустой
+    pub buckets: Vec<DistributionBucket>,
+    /// Доли всех средств у 1% и 10% крупнейших адресов (хотя бы у одного адреса)
+    pub top_1_percent_share: f64,
+    pub top_10_percent_share: f64,
+}
+
+impl fmt::Display for DistributionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Addresses: {}, total holdings: {}", self.addresses, self.total_holdings)?;
+        for bucket in &self.buckets {
+            match bucket.max {
+                Some(max) => writeln!(f, "{} - {}: {} addresses, {}", bucket.min, max, bucket.addresses, bucket.holdings)?,
+                None => writeln!(f, "{}+: {} addresses, {}", bucket.min, bucket.addresses, bucket.holdings)?,
+            }
+        }
+        write!(f, "Top 1%: {:.2}%, top 10%: {:.2}%", self.top_1_percent_share * 100.0, self.top_10_percent_share * 100.0)
+    }
+}
+
+/// Все средства кошелька: баланс с грантами вестинга и стейк. Стейк возвращается без периода
+/// разблокировки, поэтому отдельной суммы в разблокировке нет.
+fn holdings(wallet: &Wallet) -> f64 {
+    wallet.balance + wallet.staking_balance
+}
+
+/// Номер корзины: 0 — меньше 1, k — от 10^(k-1) до 10^k
+fn bucket_index(holdings: f64) -> usize {
+    let mut index = 0;
+    let mut bound = 1.0;
+    while holdings >= bound {
+        index += 1;
+        bound *= 10.0;
+    }
+    index
+}
+
+/// Место в рейтинге: больше средств выше, при равенстве выше меньший адрес
+struct Ranked<'a> {
+    holdings: f64,
+    address: &'a str,
+}
+
+impl Ord for Ranked<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.holdings.total_cmp(&other.holdings).then_with(|| other.address.cmp(self.address))
+    }
+}
+
+impl PartialOrd for Ranked<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked<'_> {}
+
+impl Blockchain {
+    /// Служебный ли адрес: протокольные адреса, казна и кран
+    pub fn is_system_address(&self, address: &str) -> bool {
+        PROTOCOL_ADDRESSES.contains(&address)
+            || self.treasury.as_ref().is_some_and(|treasury| treasury.config.address == address)
+            || self.faucet.as_ref().is_some_and(|faucet| faucet.config.address == address)
+    }
+    
+    /// Кошельки с ненулевыми средствами, без служебных, если они не запрошены
+    fn holders(&self, include_system: bool) -> impl Iterator<Item = (&str, f64)> {
+        self.wallets.iter()
+            .filter(move |(address, _)| include_system || !self.is_system_address(address))
+            .map(|(address, wallet)| (address.as_str(), holdings(wallet)))
+            .filter(|&(_, holdings)| holdings > 0.0)
+    }
+    
+    /// `n` крупнейших держателей без служебных адресов
+    pub fn top_holders(&self, n: usize) -> Vec<(String, BalanceDetails)> {
+        self.top_holders_with(n, false)
+    }
+    
+    /// `n` крупнейших держателей по всем средствам; при равенстве раньше идет меньший адрес
+    pub fn top_holders_with(&self, n: usize, include_system: bool) -> Vec<(String, BalanceDetails)> {
+        let mut heap = BinaryHeap::with_capacity(n.saturating_add(1).min(self.wallets.len() + 1));
+        for (address, holdings) in self.holders(include_system) {
+            heap.push(Reverse(Ranked { holdings, address }));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        
+        let height = self.chain.len() as u64;
+        heap.into_sorted_vec().into_iter()
+            .map(|Reverse(ranked)| (ranked.address.to_string(), BalanceDetails::of(self.wallets.get(ranked.address), height)))
+            .collect()
+    }
+    
+    /// Распределение средств без служебных адресов
+    pub fn distribution_report(&self) -> DistributionReport {
+        self.distribution_report_with(false)
+    }
+    
+    /// Распределение средств по корзинам-порядкам и доли крупнейших держателей
+    pub fn distribution_report_with(&self, include_system: bool) -> DistributionReport {
+        let mut all: Vec<f64> = self.holders(include_system).map(|(_, holdings)| holdings).collect();
+        if all.is_empty() {
+            return DistributionReport::default();
+        }
+        all.sort_by(|a, b| b.total_cmp(a));
+        
+        let mut buckets: Vec<DistributionBucket> = Vec::new();
+        for &holdings in &all {
+            let index = bucket_index(holdings);
+            while buckets.len() <= index {
+                let min = if buckets.is_empty() { 0.0 } else { 10f64.powi(buckets.len() as i32 - 1) };
+                buckets.push(DistributionBucket { min, max: Some(10f64.powi(buckets.len() as i32)), addresses: 0, holdings: 0.0 });
+            }
+            if let Some(bucket) = buckets.get_mut(index) {
+                bucket.addresses += 1;
+                bucket.holdings += holdings;
+            }
+        }
+        if let Some(last) = buckets.last_mut() {
+            last.max = None;
+        }
+        
+        let total_holdings: f64 = all.iter().sum();
+        let top_share = |percent: usize| {
+            let count = (all.len() * percent).div_ceil(100);
+            all.iter().take(count).sum::<f64>() / total_holdings
+        };
+        DistributionReport {
+            addresses: all.len(),
+            total_holdings,
+            top_1_percent_share: top_share(1),
+            top_10_percent_share: top_share(10),
+            buckets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    /// Держатели с 0.5, 5, 5, 50, 70 (из них 30 в стейке), 500 и 500, пустой кошелек и эскроу моста с 1000
+    fn distributed_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        for (address, amount) in [("a", 0.5), ("b", 5.0), ("c", 5.0), ("d", 50.0), ("g", 70.0), ("f", 500.0), ("e", 500.0), ("zero", 0.0)] {
+            chain.create_wallet(address.to_string()).unwrap();
+            chain.allocate_at_genesis(address, amount).unwrap();
+        }
+        chain.add_validator("g".to_string(), 30.0).unwrap();
+        chain.get_or_create_wallet(BRIDGE_ESCROW).balance = 1000.0;
+        chain
+    }
+    
+    #[test]
+    fn top_holders_are_ranked_by_holdings_then_address() {
+        let chain = distributed_chain();
+        let top = chain.top_holders(3);
+        assert_eq!(top.iter().map(|(address, _)| address.as_str()).collect::<Vec<_>>(), vec!["e", "f", "g"]);
+        let (_, g) = &top[2];
+        assert_eq!((g.total, g.staked), (40.0, 30.0));
+        
+        assert_eq!(chain.top_holders(100).len(), 7);
+        assert!(chain.top_holders(0).is_empty());
+        assert_eq!(chain.top_holders_with(1, true)[0].0, BRIDGE_ESCROW);
+    }
+    
+    #[test]
+    fn distribution_counts_each_order_of_magnitude() {
+        let chain = distributed_chain();
+        let report = chain.distribution_report();
+        assert_eq!(report.addresses, 7);
+        assert_eq!(report.total_holdings, 1130.5);
+        let buckets: Vec<(f64, Option<f64>, usize, f64)> = report.buckets.iter().map(|bucket| (bucket.min, bucket.max, bucket.addresses, bucket.holdings)).collect();
+        assert_eq!(buckets, vec![(0.0, Some(1.0), 1, 0.5), (1.0, Some(10.0), 2, 10.0), (10.0, Some(100.0), 2, 120.0), (100.0, None, 2, 1000.0)]);
+        assert_eq!(report.top_1_percent_share, 500.0 / 1130.5);
+        assert_eq!(report.top_10_percent_share, 500.0 / 1130.5);
+        
+        let with_system = chain.distribution_report_with(true);
+        assert_eq!(with_system.addresses, 8);
+        assert_eq!(with_system.buckets.len(), 5);
+        assert_eq!(with_system.buckets.last().map(|bucket| (bucket.min, bucket.addresses)), Some((1000.0, 1)));
+        
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json.pointer("/buckets/3/max"), Some(&serde_json::Value::Null));
+        assert_eq!(Blockchain::new(1, 100.0, ConsensusAlgorithm::ProofOfWork).distribution_report(), DistributionReport::default());
+    }
+}
@@ -0,0 +1,489 @@
+use crate::errors::{BlockchainError, ErrorBody};
+use crate::registry::{ChainRegistry, DEFAULT_CHAIN_NAME};
+use crate::shared::SharedBlockchain;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+/// Стандартные коды ошибок JSON-RPC 2.0
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// Коды ошибок блокчейна из диапазона, отведённого спецификацией под сервер
+pub const INSUFFICIENT_BALANCE: i64 = -32001;
+pub const INVALID_TRANSACTION: i64 = -32002;
+pub const INVALID_BLOCK: i64 = -32003;
+pub const CONSENSUS_ERROR: i64 = -32004;
+pub const MEMPOOL_FULL: i64 = -32005;
+pub const RATE_LIMITED: i64 = -32006;
+pub const FAUCET_DEPLETED: i64 = -32007;
+pub const INVALID_SIGNATURE: i64 = -32008;
+pub const DUPLICATE_TRANSACTION: i64 = -32009;
+pub const NOT_FOUND: i64 = -32010;
+pub const FEE_TOO_LOW: i64 = -32011;
+pub const ACCOUNT_FROZEN: i64 = -32012;
+pub const CONTRACT_ERROR: i64 = -32013;
+pub const WALLET_CLOSED: i64 = -32014;
+pub const STALE_TEMPLATE: i64 = -32015;
+pub const WRONG_LEDGER_MODE: i64 = -32016;
+pub const WRONG_NETWORK: i64 = -32017;
+pub const HASH_ALGORITHM_MISMATCH: i64 = -32018;
+pub const INVALID_NONCE: i64 = -32019;
+pub const FEATURE_DISABLED: i64 = -32020;
+
+/// Ошибка JSON-RPC с кодом, сообщением и необязательными структурированными данными
+#[derive(Debug, Clone)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into(), data: None }
+    }
+    
+    fn invalid_params(message: impl Into<String>) -> Self {
+        RpcError::new(INVALID_PARAMS, message)
+    }
+    
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+impl From<BlockchainError> for RpcError {
+    /// Код JSON-RPC зависит от вида ошибки, а в `data` лежит `ErrorBody` со стабильным кодом и полями ошибки
+    fn from(error: BlockchainError) -> Self {
+        let code = match &error {
+            BlockchainError::InsufficientBalance { .. } => INSUFFICIENT_BALANCE,
+            BlockchainError::InvalidTransaction(_) | BlockchainError::MalformedTransaction { .. }
+            | BlockchainError::WrongChainId { .. } => INVALID_TRANSACTION,
+            BlockchainError::InvalidBlock(_) => INVALID_BLOCK,
+            BlockchainError::ConsensusError(_) | BlockchainError::ValidatorJailed { .. }
+            | BlockchainError::NotScheduledProducer { .. } => CONSENSUS_ERROR,
+            BlockchainError::MempoolFull { .. } => MEMPOOL_FULL,
+            BlockchainError::Storage(_) => INTERNAL_ERROR,
+            BlockchainError::RateLimited { .. } => RATE_LIMITED,
+            BlockchainError::FaucetDepleted { .. } => FAUCET_DEPLETED,
+            BlockchainError::WalletNotFound { .. } | BlockchainError::ValidatorNotFound { .. }
+            | BlockchainError::TransactionNotFound { .. } | BlockchainError::ChainNotFound { .. } => NOT_FOUND,
+            BlockchainError::InvalidSignature { .. } => INVALID_SIGNATURE,
+            BlockchainError::DuplicateTransaction { .. } => DUPLICATE_TRANSACTION,
+            BlockchainError::FeeTooLow { .. } => FEE_TOO_LOW,
+            BlockchainError::AccountFrozen { .. } => ACCOUNT_FROZEN,
+            BlockchainError::FeatureDisabled { .. } => FEATURE_DISABLED,
+            BlockchainError::ContractError { .. } => CONTRACT_ERROR,
+            BlockchainError::DuplicateChain { .. } | BlockchainError::DuplicateWallet { .. }
+            | BlockchainError::InvalidConfig(_) => INVALID_PARAMS,
+            BlockchainError::WalletClosed { .. } => WALLET_CLOSED,
+            BlockchainError::StaleTemplate { .. } => STALE_TEMPLATE,
+            BlockchainError::WrongLedgerMode { .. } => WRONG_LEDGER_MODE,
+            BlockchainError::WrongNetwork { .. } => WRONG_NETWORK,
+            BlockchainError::HashAlgorithmMismatch { .. } => HASH_ALGORITHM_MISMATCH,
+            BlockchainError::InvalidNonce { .. } => INVALID_NONCE,
+        };
+        
+        let body = ErrorBody::from(&error);
+        RpcError { code, message: body.message.clone(), data: Some(json!(body)) }
+    }
+}
+
+/// Создает маршрутизатор JSON-RPC, принимающий запросы методом POST на корневой путь
+pub fn router(chain: SharedBlockchain) -> Router {
+    let registry = ChainRegistry::single(DEFAULT_CHAIN_NAME, chain)
+        .expect("an empty registry accepts any chain under a valid name");
+    registry_router(registry)
+}
+
+/// Маршрутизатор JSON-RPC над реестром: именованный параметр `chain` выбирает цепочку,
+/// без него запрос идет в цепочку по умолчанию
+pub fn registry_router(registry: ChainRegistry) -> Router {
+    Router::new()
+        .route("/", post(handle_http))
+        .with_state(registry)
+}
+
+/// Запускает JSON-RPC сервер на уже открытом сокете
+pub async fn serve(listener: tokio::net::TcpListener, chain: SharedBlockchain) -> std::io::Result<()> {
+    axum::serve(listener, router(chain)).await
+}
+
+async fn handle_http(State(registry): State<ChainRegistry>, body: String) -> Response {
+    match handle_payload(&registry, &body).await {
+        Some(response) => Json(response).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Обрабатывает тело запроса (одиночный вызов или пакет) и возвращает ответ, если он нужен
+pub async fn handle_payload(registry: &ChainRegistry, body: &str) -> Option<Value> {
+    let payload: Value = match serde_json::from_str(body) {
+        Ok(payload) => payload,
+        Err(e) => return Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)))),
+    };
+    
+    match payload {
+        Value::Array(requests) if requests.is_empty() => {
+            Some(error_response(Value::Null, RpcError::new(INVALID_REQUEST, "Empty batch")))
+        },
+        Value::Array(requests) => {
+            let mut responses = Vec::new();
+            for request in requests {
+                if let Some(response) = handle_request(registry, request).await {
+                    responses.push(response);
+                }
+            }
+            
+            if responses.is_empty() {
+                None
+            } else {
+                Some(Value::Array(responses))
+            }
+        },
+        request => handle_request(registry, request).await,
+    }
+}
+
+async fn handle_request(registry: &ChainRegistry, request: Value) -> Option<Value> {
+    let Value::Object(request) = request else {
+        return Some(error_response(Value::Null, RpcError::new(INVALID_REQUEST, "Request must be an object")));
+    };
+    
+    let id = request.get("id").cloned();
+    let valid_id = matches!(id, None | Some(Value::Null) | Some(Value::Number(_)) | Some(Value::String(_)));
+    let method = request.get("method").and_then(Value::as_str);
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    
+    if request.get("jsonrpc") != Some(&json!("2.0")) || !valid_id || method.is_none()
+        || !matches!(params, Value::Null | Value::Array(_) | Value::Object(_)) {
+        let id = if valid_id { id.unwrap_or(Value::Null) } else { Value::Null };
+        return Some(error_response(id, RpcError::new(INVALID_REQUEST, "Invalid request")));
+    }
+    
+    let result = dispatch(registry, method.unwrap_or_default(), &params).await;
+    
+    // Уведомления (запросы без id) выполняются, но не получают ответа
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "error": error.to_json(), "id": id })
+}
+
+/// Достает параметр по позиции или по имени
+fn param<'a>(params: &'a Value, index: usize, name: &str) -> Option<&'a Value> {
+    match params {
+        Value::Array(values) => values.get(index),
+        Value::Object(values) => values.get(name),
+        _ => None,
+    }
+}
+
+fn string_param(params: &Value, index: usize, name: &str) -> Result<String, RpcError> {
+    param(params, index, name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| RpcError::invalid_params(format!("Expected string parameter '{}'", name)))
+}
+
+fn u64_param(params: &Value, index: usize, name: &str) -> Result<u64, RpcError> {
+    param(params, index, name)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| RpcError::invalid_params(format!("Expected non-negative integer parameter '{}'", name)))
+}
+
+fn i64_param(params: &Value, index: usize, name: &str) -> Result<i64, RpcError> {
+    param(params, index, name)
+        .and_then(Value::as_i64)
+        .ok_or_else(|| RpcError::invalid_params(format!("Expected integer parameter '{}'", name)))
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+/// Имя цепочки из именованного параметра `chain`; позиционные параметры всегда идут в цепочку по умолчанию
+fn chain_param(params: &Value) -> Result<Option<&str>, RpcError> {
+    match params.get("chain") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(name)) => Ok(Some(name)),
+        Some(_) => Err(RpcError::invalid_params("Expected string parameter 'chain'")),
+    }
+}
+
+async fn dispatch(registry: &ChainRegistry, method: &str, params: &Value) -> Result<Value, RpcError> {
+    if method == "registry_listChains" {
+        return to_value(registry.list());
+    }
+    let chain = &registry.resolve(chain_param(params)?)?;
+    
+    match method {
+        "chain_getBlock" => {
+            let chain = chain.read();
+            let block = match param(params, 0, "block") {
+                Some(Value::Number(height)) => {
+                    let height = height.as_u64().ok_or_else(|| RpcError::invalid_params("Height must be a non-negative integer"))?;
+                    chain.get_block_by_index(height)
+                },
+                Some(Value::String(hash)) => chain.get_block_by_hash(hash),
+                _ => return Err(RpcError::invalid_params("Expected block height or hash")),
+            };
+            
+            block.map(to_value)
+                .unwrap_or_else(|| Err(RpcError::new(NOT_FOUND, "Block not found")))
+        },
+        "chain_getHead" => to_value(chain.read().get_latest_block()),
+        "chain_getFeatures" => to_value(chain.read().enabled_features()),
+        "tx_submitRaw" => {
+            let raw = string_param(params, 0, "raw")?;
+            let tx_id = chain.submit_raw_transaction(raw)?;
+            Ok(json!(tx_id))
+        },
+        "tx_getReceipt" => {
+            let tx_id = string_param(params, 0, "id")?;
+            chain.read().get_transaction_receipt(&tx_id)
+                .map(to_value)
+                .unwrap_or_else(|| Err(BlockchainError::TransactionNotFound { tx_id }.into()))
+        },
+        "wallet_getBalance" => {
+            let address = string_param(params, 0, "address")?;
+            Ok(json!(chain.read().get_balance(&address)))
+        },
+        "mempool_pending" => {
+            let chain = chain.read();
+            to_value(chain.mempool().transactions())
+        },
+        "mempool_package" => {
+            let tx_id = string_param(params, 0, "id")?;
+            chain.read().pending_package(&tx_id)
+                .map(to_value)
+                .unwrap_or_else(|| Err(BlockchainError::TransactionNotFound { tx_id }.into()))
+        },
+        "miner_mine" => {
+            let miner = string_param(params, 0, "address")?;
+            let chain = chain.clone();
+            // Майнинг долго занимает процессор, поэтому уводим его с потоков асинхронного рантайма
+            tokio::task::spawn_blocking(move || to_value(chain.mine_block(miner)?))
+            .await
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?
+        },
+        "miner_getBlockTemplate" => {
+            let miner = string_param(params, 0, "address")?;
+            to_value(chain.write().get_block_template(&miner)?)
+        },
+        "miner_submitSolution" => {
+            let template_id = string_param(params, 0, "template_id")?;
+            let nonce = u64_param(params, 1, "nonce")?;
+            let timestamp = i64_param(params, 2, "timestamp")?;
+            let mut chain = chain.write();
+            let miner = chain.block_template_miner(&template_id).map(str::to_string);
+            match chain.submit_block_solution(&template_id, nonce, timestamp) {
+                Ok(()) => to_value(chain.get_latest_block()),
+                // Вместо устаревшего шаблона сразу выдаем свежий тому же майнеру
+                Err(error @ BlockchainError::StaleTemplate { .. }) => {
+                    let mut error = RpcError::from(error);
+                    if let (Some(miner), Some(data)) = (miner, error.data.as_mut()) {
+                        data["template"] = to_value(chain.get_block_template(&miner)?)?;
+                    }
+                    Err(error)
+                },
+                Err(error) => Err(error.into()),
+            }
+        },
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Method {} not found", method))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+    use crate::test_support::ChainFixture;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use tokio::runtime::Runtime;
+    
+    /// Запускает сервер на свободном порту; он работает, пока жив рантайм
+    fn start_server(chain: Blockchain) -> (Runtime, SocketAddr) {
+        let runtime = Runtime::new().unwrap();
+        let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(serve(listener, SharedBlockchain::new(chain)));
+        (runtime, addr)
+    }
+    
+    /// Отправляет тело POST-запросом и возвращает код ответа и разобранное тело, если оно есть
+    fn post(addr: SocketAddr, body: &str) -> (u16, Option<Value>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            addr, body.len(), body
+        ).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, (!body.is_empty()).then(|| serde_json::from_str(body).unwrap()))
+    }
+    
+    /// Вызывает метод и возвращает ответ целиком
+    fn call(addr: SocketAddr, method: &str, params: Value) -> Value {
+        post(addr, &json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 }).to_string()).1.unwrap()
+    }
+    
+    fn chain() -> Blockchain {
+        ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork)
+    }
+    
+    #[test]
+    fn batch_answers_calls_and_skips_notifications() {
+        let (_runtime, addr) = start_server(chain());
+        let (status, body) = post(addr, r#"[
+            {"jsonrpc": "2.0", "method": "chain_getHead", "id": 1},
+            {"jsonrpc": "2.0", "method": "miner_mine", "params": ["miner"]},
+            {"jsonrpc": "2.0", "method": "wallet_getBalance", "params": {"address": "miner"}, "id": "balance"},
+            {"jsonrpc": "2.0", "method": "chain_unknown", "id": 2}
+        ]"#);
+        
+        assert_eq!(status, 200);
+        let body = body.unwrap();
+        let responses = body.as_array().unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], json!(1));
+        assert_eq!(responses[0]["result"]["index"], json!(0));
+        // Уведомление выполнено, хотя ответа на него нет
+        assert_eq!(responses[1], json!({ "jsonrpc": "2.0", "result": 100.0, "id": "balance" }));
+        assert_eq!(responses[2]["id"], json!(2));
+        assert_eq!(responses[2]["error"]["code"], json!(METHOD_NOT_FOUND));
+    }
+    
+    #[test]
+    fn notifications_get_no_content() {
+        let (_runtime, addr) = start_server(chain());
+        let (status, body) = post(addr, r#"{"jsonrpc": "2.0", "method": "chain_getHead"}"#);
+        assert_eq!((status, body), (204, None));
+        let (status, body) = post(addr, r#"[{"jsonrpc": "2.0", "method": "chain_getHead"}, {"jsonrpc": "2.0", "method": "chain_unknown"}]"#);
+        assert_eq!((status, body), (204, None));
+    }
+    
+    #[test]
+    fn malformed_requests_get_error_responses() {
+        let (_runtime, addr) = start_server(chain());
+        for (body, code) in [
+            ("{not json", PARSE_ERROR),
+            ("[]", INVALID_REQUEST),
+            (r#"{"jsonrpc": "1.0", "method": "chain_getHead", "id": 1}"#, INVALID_REQUEST),
+            (r#"{"jsonrpc": "2.0", "method": "chain_getBlock", "params": [true], "id": 1}"#, INVALID_PARAMS),
+            (r#"{"jsonrpc": "2.0", "method": "chain_getBlock", "params": [7], "id": 1}"#, NOT_FOUND),
+        ] {
+            let (status, response) = post(addr, body);
+            assert_eq!(status, 200, "{}", body);
+            assert_eq!(response.unwrap()["error"]["code"], json!(code), "{}", body);
+        }
+        
+        let (_, response) = post(addr, r#"{"jsonrpc": "2.0", "method": "tx_getReceipt", "params": ["missing"], "id": 3}"#);
+        let response = response.unwrap();
+        assert_eq!(response["id"], json!(3));
+        assert_eq!(response["error"]["code"], json!(NOT_FOUND));
+        assert_eq!(response["error"]["data"]["code"], json!(ErrorBody::from(&BlockchainError::TransactionNotFound { tx_id: "missing".to_string() }).code));
+    }
+    
+    #[test]
+    fn submitted_transaction_goes_through_the_pool_into_a_block() {
+        use crate::receipt::{ReceiptStatus, TransactionReceipt};
+        use crate::test_support::{signed_transfer, ChainFixture};
+        
+        let chain = ChainFixture::funded().build().unwrap().chain;
+        let tx = signed_transfer(&chain, "alice", "bob", 10.0);
+        let raw = tx.to_raw_hex(chain.chain_id);
+        let features = chain.enabled_features();
+        let (_runtime, addr) = start_server(chain);
+        
+        assert_eq!(call(addr, "tx_submitRaw", json!([raw]))["result"], json!(tx.id));
+        let pending = call(addr, "mempool_pending", json!([]))["result"].clone();
+        assert_eq!(pending.as_array().unwrap().len(), 1);
+        assert_eq!(pending[0]["id"], json!(tx.id));
+        let package = &call(addr, "mempool_package", json!([tx.id]))["result"];
+        assert_eq!(package["transactions"], json!([tx.id]));
+        let receipt: TransactionReceipt = serde_json::from_value(call(addr, "tx_getReceipt", json!([tx.id]))["result"].clone()).unwrap();
+        assert_eq!(receipt.status, ReceiptStatus::Pending);
+        
+        let block = call(addr, "miner_mine", json!(["miner"]))["result"].clone();
+        assert_eq!(call(addr, "mempool_pending", json!([]))["result"], json!([]));
+        let receipt: TransactionReceipt = serde_json::from_value(call(addr, "tx_getReceipt", json!([tx.id]))["result"].clone()).unwrap();
+        assert_eq!(receipt.status, ReceiptStatus::Confirmed);
+        assert_eq!(receipt.block_hash.as_deref(), block["hash"].as_str());
+        assert_eq!(call(addr, "chain_getBlock", json!([block["hash"]]))["result"]["index"], json!(2));
+        assert_eq!(call(addr, "wallet_getBalance", json!(["bob"]))["result"], json!(10.0));
+        assert_eq!(call(addr, "chain_getFeatures", json!([]))["result"], json!(features));
+    }
+    
+    #[test]
+    fn disabled_feature_has_its_own_server_code() {
+        use crate::builder::BlockchainBuilder;
+        use crate::errors::ErrorCode;
+        use crate::features::Feature;
+        use crate::transaction::{Transaction, TransactionType};
+        
+        let mut chain = BlockchainBuilder::new().difficulty(1).accept_legacy_addresses(true).disable_feature(Feature::Data).build().unwrap();
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut tx = Transaction::new("alice".to_string(), "BLOCKCHAIN_DATA".to_string(), 0.1, TransactionType::Data(vec![1, 2, 3]));
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(chain.get_wallet_info("alice").unwrap().keypair().unwrap());
+        let raw = tx.to_raw_hex(chain.chain_id);
+        
+        let (_runtime, addr) = start_server(chain);
+        let (_, response) = post(addr, &json!({ "jsonrpc": "2.0", "method": "tx_submitRaw", "params": [raw], "id": 1 }).to_string());
+        let error = &response.unwrap()["error"];
+        assert!((-32099..=-32000).contains(&FEATURE_DISABLED));
+        assert_eq!(error["code"], json!(FEATURE_DISABLED));
+        assert_eq!(error["data"]["code"], json!(ErrorCode::FeatureDisabled));
+        assert_eq!(error["data"]["data"]["feature"], json!(Feature::Data.name()));
+    }
+    
+    #[test]
+    fn stale_solution_comes_back_with_a_fresh_template() {
+        use crate::errors::ErrorCode;
+        use crate::template::BlockTemplate;
+        
+        let (_runtime, addr) = start_server(chain());
+        let template: BlockTemplate = serde_json::from_value(call(addr, "miner_getBlockTemplate", json!(["miner"]))["result"].clone()).unwrap();
+        call(addr, "miner_mine", json!(["other"]));
+        
+        let response = call(addr, "miner_submitSolution", json!([template.template_id, 0, template.header.timestamp]));
+        let error = &response["error"];
+        assert_eq!(error["data"]["code"], json!(ErrorCode::StaleTemplate));
+        let fresh: BlockTemplate = serde_json::from_value(error["data"]["template"].clone()).unwrap();
+        assert_eq!(fresh.header.index, 2);
+        assert_ne!(fresh.template_id, template.template_id);
+        
+        let mut header = fresh.header.clone();
+        let nonce = (0..).find(|&nonce| {
+            header.nonce = nonce;
+            header.hash = header.calculate_hash();
+            header.meets_difficulty()
+        }).unwrap();
+        let response = call(addr, "miner_submitSolution", json!([fresh.template_id, nonce, fresh.header.timestamp]));
+        assert_eq!(response["result"]["index"], json!(2));
+        assert_eq!(call(addr, "wallet_getBalance", json!(["miner"]))["result"], json!(100.0));
+    }
+}
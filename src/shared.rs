@@ -0,0 +1,50 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::Transaction;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Потокобезопасный дескриптор блокчейна для серверов и фоновых задач
+#[derive(Clone)]
+pub struct SharedBlockchain {
+    inner: Arc<RwLock<Blockchain>>,
+}
+
+impl SharedBlockchain {
+    /// Оборачивает блокчейн в разделяемый дескриптор
+    pub fn new(blockchain: Blockchain) -> Self {
+        SharedBlockchain {
+            inner: Arc::new(RwLock::new(blockchain)),
+        }
+    }
+    
+    /// Блокирует цепочку на чтение
+    pub fn read(&self) -> RwLockReadGuard<'_, Blockchain> {
+        self.inner.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    /// Блокирует цепочку на запись
+    pub fn write(&self) -> RwLockWriteGuard<'_, Blockchain> {
+        self.inner.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    /// Проверяет транзакцию и ставит ее в пул под блокировкой цепочки на чтение, параллельно
+    /// с другими читателями; после возврата транзакцию видят все чтения цепочки
+    pub fn add_transaction(&self, transaction: Transaction) -> Result<(), BlockchainError> {
+        self.read().submit_transaction(transaction)
+    }
+    
+    /// Разбирает и ставит в пул сырую транзакцию, как `add_transaction`; возвращает ее идентификатор
+    pub fn submit_raw_transaction(&self, raw: impl AsRef<[u8]>) -> Result<String, BlockchainError> {
+        let chain = self.read();
+        let transaction = chain.check_raw_transaction(raw.as_ref())?;
+        let tx_id = transaction.id.clone();
+        chain.submit_transaction(transaction)?;
+        Ok(tx_id)
+    }
+}
+
+impl From<Blockchain> for SharedBlockchain {
+    fn from(blockchain: Blockchain) -> Self {
+        SharedBlockchain::new(blockchain)
+    }
+}
@@ -0,0 +1,383 @@
+use crate::address::{address_bytes, Address};
+use crate::blockchain::Blockchain;
+use crate::entropy::EntropySource;
+use crate::errors::BlockchainError;
+use crate::transaction::{Transaction, TransactionType};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Открытый ключ ed25519, которым проверяются подписи отправителя
+pub use ed25519_dalek::VerifyingKey as PublicKey;
+
+/// Ключевая пара ed25519 кошелька. В хранилище ключей узла записывается как hex секретного
+/// ключа; открытый ключ выводится из него.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Keypair {
+    signing_key: SigningKey,
+}
+
+impl Keypair {
+    /// Новая ключевая пара из случайности цепочки
+    pub fn generate(entropy: &dyn EntropySource) -> Self {
+        let mut secret = [0; 32];
+        entropy.fill_bytes(&mut secret);
+        Keypair::from_secret(secret)
+    }
+    
+    pub fn from_secret(secret: [u8; 32]) -> Self {
+        Keypair { signing_key: SigningKey::from_bytes(&secret) }
+    }
+    
+    pub fn public_key(&self) -> PublicKey {
+        self.signing_key.verifying_key()
+    }
+    
+    /// Открытый ключ в hex, как он записывается в транзакцию
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.public_key().as_bytes())
+    }
+    
+    /// Подпись сообщения в hex
+    pub fn sign(&self, message: &[u8]) -> String {
+        hex::encode(self.signing_key.sign(message).to_bytes())
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair").field("public_key", &self.public_key_hex()).finish_non_exhaustive()
+    }
+}
+
+impl TryFrom<String> for Keypair {
+    type Error = String;
+    
+    fn try_from(secret: String) -> Result<Self, String> {
+        let bytes = hex::decode(&secret).map_err(|e| format!("Invalid secret key: {}", e))?;
+        let secret: [u8; 32] = bytes.try_into().map_err(|_| "Secret key must be 32 bytes".to_string())?;
+        Ok(Keypair::from_secret(secret))
+    }
+}
+
+impl From<Keypair> for String {
+    fn from(keypair: Keypair) -> String {
+        hex::encode(keypair.signing_key.to_bytes())
+    }
+}
+
+/// Разбирает открытый ключ из hex; None, если это не точка кривой
+pub fn parse_public_key(public_key: &str) -> Option<PublicKey> {
+    let bytes: [u8; 32] = hex::decode(public_key).ok()?.try_into().ok()?;
+    PublicKey::from_bytes(&bytes).ok()
+}
+
+/// Ключ `offered`, если он принадлежит адресу: адрес сети выведен из этого ключа, а имя без
+/// контрольной суммы закреплено за ним (`bound` — закрепленный ключ в hex)
+pub(crate) fn owned_key(address: &str, offered: &str, bound: Option<&str>) -> Option<PublicKey> {
+    let public_key = parse_public_key(offered)?;
+    match Address::parse(address) {
+        Ok(Address::Network { bytes, .. }) => (address_bytes(&public_key) == bytes).then_some(public_key),
+        _ => (bound? == offered).then_some(public_key),
+    }
+}
+
+/// Проверяет подпись в hex под сообщением
+pub fn verify(public_key: &PublicKey, message: &[u8], signature: &str) -> bool {
+    let Some(bytes) = hex::decode(signature).ok().and_then(|bytes| <[u8; 64]>::try_from(bytes).ok()) else {
+        return false;
+    };
+    public_key.verify(message, &Signature::from_bytes(&bytes)).is_ok()
+}
+
+impl Blockchain {
+    /// Ключ владельца адреса, если предъявлен именно он: адрес сети должен быть выведен из этого ключа,
+    /// а имя без контрольной суммы — закреплено за ним подтвержденной транзакцией `RegisterKey`. Кошелек
+    /// без закрепленного ключа, например получивший только награду за блок, тратить нельзя.
+    pub(crate) fn owner_key(&self, address: &str, offered: Option<&str>) -> Option<PublicKey> {
+        owned_key(address, offered?, self.registered_key(address))
+    }
+    
+    /// Проверяет подпись отправителя: транзакция подписана ключом, который в ней записан, и этот ключ
+    /// принадлежит владельцу адреса (`owner_key`) или закреплен за ним одной из `registered` — транзакций
+    /// `RegisterKey` пула или блока выше этой. Закрепление ключа подписывается им самим, а системным
+    /// транзакциям подпись не нужна.
+    pub(crate) fn check_sender_signature(&self, tx: &Transaction, registered: &HashMap<&str, &str>) -> Result<(), BlockchainError> {
+        if !tx.needs_sender_signature() {
+            return Ok(());
+        }
+        let offered = tx.public_key.as_deref();
+        let owner_key = if tx.transaction_type == TransactionType::RegisterKey {
+            offered.and_then(parse_public_key)
+        } else {
+            let bound = self.registered_key(&tx.sender).or_else(|| registered.get(tx.sender.as_str()).copied());
+            offered.and_then(|offered| owned_key(&tx.sender, offered, bound))
+        };
+        let valid = owner_key.is_some_and(|public_key| tx.verify_signature(&public_key));
+        if !valid {
+            return Err(BlockchainError::InvalidSignature { tx_id: tx.id.clone() });
+        }
+        Ok(())
+    }
+    
+    /// Подписывает транзакцию ключом кошелька отправителя, если ключ хранится на узле, и перед этим
+    /// назначает ей следующий номер отправителя, если номера еще нет; иначе транзакция остается
+    /// неподписанной, и `add_transaction` ее отклонит
+    pub(crate) fn sign_as_sender(&self, tx: &mut Transaction) {
+        if let Some(keypair) = self.wallets.get(&tx.sender).and_then(|wallet| wallet.keypair()) {
+            if tx.nonce.is_none() && tx.needs_sender_signature() {
+                tx.set_nonce(self.get_account_nonce(&tx.sender));
+            }
+            tx.sign(keypair);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::block::Block;
+    use crate::entropy::OsEntropy;
+    use crate::ordering::sort_canonical;
+    use crate::spec::ChainSpec;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    use crate::wallet::Wallet;
+    
+    /// PoW-цепочка, где у `alice` своя пара ключей, а `miner` получил перевод, не имея ключа
+    fn chain_with_keyless_miner() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut tx = Transaction::new("alice".to_string(), "miner".to_string(), 50.0, TransactionType::Transfer);
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain
+    }
+    
+    fn transfer(chain: &Blockchain, from: &str, to: &str, amount: f64, keypair: &Keypair) -> Transaction {
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(from));
+        tx.sign(keypair);
+        tx
+    }
+    
+    #[test]
+    fn stranger_cannot_spend_a_keyless_wallet() {
+        let mut chain = chain_with_keyless_miner();
+        let balance = chain.get_balance("miner");
+        let thief = Keypair::generate(&OsEntropy);
+        
+        let tx = transfer(&chain, "miner", "alice", 40.0, &thief);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+        // Отклоненная попытка не закрепляет ключ вора за адресом
+        assert_eq!(chain.get_wallet_info("miner").unwrap().public_key, None);
+        let tx = transfer(&chain, "miner", "alice", 40.0, &thief);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+        assert_eq!(chain.get_balance("miner"), balance);
+    }
+    
+    #[test]
+    fn named_wallet_only_accepts_its_own_key() {
+        let mut chain = chain_with_keyless_miner();
+        let stranger = Keypair::generate(&OsEntropy);
+        let tx = transfer(&chain, "alice", "miner", 10.0, &stranger);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+        
+        let owner = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let tx = transfer(&chain, "alice", "miner", 10.0, &owner);
+        assert!(chain.add_transaction(tx).is_ok());
+    }
+    
+    #[test]
+    fn unsigned_and_tampered_transactions_are_rejected() {
+        let mut chain = chain_with_keyless_miner();
+        let owner = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        
+        let mut unsigned = Transaction::new("alice".to_string(), "miner".to_string(), 10.0, TransactionType::Transfer);
+        unsigned.set_nonce(0);
+        assert!(matches!(chain.add_transaction(unsigned), Err(BlockchainError::InvalidSignature { .. })));
+        
+        let mut tampered = transfer(&chain, "alice", "miner", 10.0, &owner);
+        tampered.fee *= 10.0;
+        assert!(matches!(chain.add_transaction(tampered), Err(BlockchainError::InvalidSignature { .. })));
+    }
+    
+    #[test]
+    fn network_address_needs_the_key_it_was_derived_from() {
+        let mut chain = chain_with_keyless_miner();
+        let wallet = Wallet::generate(&OsEntropy, Network::Mainnet);
+        let address = chain.add_wallet(wallet).unwrap().address.clone();
+        chain.mine_pending_transactions(address.clone()).unwrap();
+        
+        let stranger = Keypair::generate(&OsEntropy);
+        let tx = transfer(&chain, &address, "alice", 10.0, &stranger);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+        
+        let owner = chain.get_wallet_info(&address).unwrap().keypair().unwrap().clone();
+        let tx = transfer(&chain, &address, "alice", 10.0, &owner);
+        assert!(chain.add_transaction(tx).is_ok());
+    }
+    
+    #[test]
+    fn registered_key_is_bound_to_the_name() {
+        let mut chain = chain_with_keyless_miner();
+        let owner = Keypair::generate(&OsEntropy);
+        let other = Keypair::generate(&OsEntropy);
+        assert!(chain.import_wallet("carol", Some(&owner.public_key_hex())).is_err());
+        chain.register_key("carol", &owner).unwrap();
+        assert!(chain.register_key("carol", &other).is_err());
+        chain.mine_pending_transactions("carol".to_string()).unwrap();
+        assert_eq!(chain.registered_key("carol"), Some(owner.public_key_hex().as_str()));
+        assert!(chain.import_wallet("carol", Some(&other.public_key_hex())).is_err());
+        
+        let tx = transfer(&chain, "carol", "alice", 10.0, &other);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+        let tx = transfer(&chain, "carol", "alice", 10.0, &owner);
+        assert!(chain.add_transaction(tx).is_ok());
+    }
+    
+    #[test]
+    fn used_name_cannot_register_a_key() {
+        let mut chain = chain_with_keyless_miner();
+        let thief = Keypair::generate(&OsEntropy);
+        assert!(matches!(chain.register_key("miner", &thief), Err(BlockchainError::InvalidTransaction(_))));
+        assert!(chain.import_wallet("miner", Some(&thief.public_key_hex())).is_err());
+        assert!(chain.register_key("BLOCKCHAIN_DATA", &thief).is_err());
+        
+        let tx = transfer(&chain, "miner", "alice", 10.0, &thief);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+    }
+    
+    #[test]
+    fn stranger_cannot_register_a_key_for_an_allocated_name() {
+        let holder = Keypair::generate(&OsEntropy);
+        let spec: ChainSpec = serde_json::from_value(serde_json::json!({
+            "difficulty": 1, "mining_reward": 50.0, "consensus": "ProofOfWork", "accept_legacy_addresses": true,
+            "allocations": { "alice": 100.0 }, "allocation_keys": { "alice": holder.public_key_hex() }
+        })).unwrap();
+        let mut chain = spec.build().unwrap();
+        // Распределение без ключа у кошелька без пары: закрепить за ним ключ не может никто
+        chain.insert_wallet(Wallet::new("carol".to_string())).unwrap();
+        chain.allocate_at_genesis("carol", 100.0).unwrap();
+        
+        let thief = Keypair::generate(&OsEntropy);
+        for name in ["alice", "carol"] {
+            let result = chain.register_key(name, &thief);
+            assert!(matches!(result, Err(BlockchainError::InvalidTransaction(_))), "{}: {:?}", name, result);
+        }
+        chain.register_key("alice", &holder).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.registered_key("alice"), Some(holder.public_key_hex().as_str()));
+        assert!(chain.add_transaction(transfer(&chain, "alice", "bob", 10.0, &holder)).is_ok());
+    }
+    
+    #[test]
+    fn peer_accepts_spends_of_a_name_registered_on_another_node() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let owner = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        chain.add_transaction(transfer(&chain, "alice", "bob", 10.0, &owner)).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        for block in chain.chain.iter().skip(1).cloned() {
+            peer.accept_block(block).unwrap();
+        }
+        assert_eq!(peer.get_latest_block().hash, chain.get_latest_block().hash);
+        assert_eq!(peer.registered_key("alice"), Some(owner.public_key_hex().as_str()));
+        for address in ["alice", "bob", "miner"] {
+            assert_eq!(peer.get_balance(address), chain.get_balance(address), "{}", address);
+        }
+        // Секретный ключ узла не попадает в состояние, которое видят другие
+        assert!(!serde_json::to_string(&chain).unwrap().contains(&String::from(owner)));
+    }
+    
+    #[test]
+    fn peer_accepts_the_key_of_a_genesis_validator() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfStake);
+        chain.create_wallet("validator".to_string()).unwrap();
+        chain.allocate_at_genesis("validator", 1000.0).unwrap();
+        chain.add_validator("validator".to_string(), 1000.0).unwrap();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        
+        // Стейк внесен вне блоков, как и распределение генезиса, и закреплению не мешает
+        chain.mine_pending_transactions("validator".to_string()).unwrap();
+        peer.accept_block(chain.get_latest_block().clone()).unwrap();
+        assert_eq!(peer.registered_key("validator"), chain.registered_key("validator"));
+        assert!(peer.registered_key("validator").is_some());
+    }
+    
+    #[test]
+    fn import_rejects_a_key_of_another_network_address() {
+        let mut chain = chain_with_keyless_miner();
+        let address = Wallet::generate(&OsEntropy, Network::Mainnet).address;
+        let other = Keypair::generate(&OsEntropy);
+        assert!(chain.import_wallet(&address, Some(&other.public_key_hex())).is_err());
+        assert!(chain.import_wallet("dave", Some("not a key")).is_err());
+    }
+    
+    #[test]
+    fn name_with_incoming_transfers_waiting_cannot_register_a_key() {
+        let mut chain = chain_with_keyless_miner();
+        let mut producer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        let owner = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let thief = Keypair::generate(&OsEntropy);
+        let payment = transfer(&chain, "alice", "bob", 10.0, &owner);
+        chain.add_transaction(payment.clone()).unwrap();
+        
+        // Пока перевод ждет в пуле, его нельзя обогнать закреплением чужого ключа
+        let result = chain.register_key("bob", &thief);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("incoming transfers waiting")), "{:?}", result);
+        assert_eq!(chain.registered_key("bob"), None);
+        
+        // Блок, где имя получает перевод вместе с закреплением ключа, тоже отклоняется
+        producer.add_transaction(payment).unwrap();
+        producer.mine_pending_transactions("alice".to_string()).unwrap();
+        let mut block = producer.get_latest_block().clone();
+        let reward = block.transactions.pop().unwrap();
+        block.transactions.push(chain.key_registration("bob", &thief));
+        block.transactions = sort_canonical(block.transactions);
+        block.transactions.push(reward);
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.mine_block();
+        let result = chain.verify_block(&block);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("incoming transfers waiting")), "{:?}", result);
+        
+        // После блока с переводом имя занято
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        assert!(chain.register_key("bob", &thief).is_err());
+    }
+    
+    #[test]
+    fn miner_can_spend_the_reward_that_created_its_wallet() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        // Ключ закреплен тем же блоком, что принес награду
+        let keypair = chain.get_wallet_info("miner").unwrap().keypair().unwrap().clone();
+        assert_eq!(chain.registered_key("miner"), Some(keypair.public_key_hex().as_str()));
+        assert_eq!(chain.get_latest_block().transactions.len(), 2);
+        peer.accept_block(chain.get_latest_block().clone()).unwrap();
+        assert_eq!(peer.registered_key("miner"), chain.registered_key("miner"));
+        
+        chain.add_transaction(transfer(&chain, "miner", "alice", 40.0, &keypair)).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        peer.accept_block(chain.get_latest_block().clone()).unwrap();
+        assert_eq!(peer.get_balance("alice"), 40.0);
+        assert!(chain.register_key("miner", &Keypair::generate(&OsEntropy)).is_err());
+        
+        // Невышедший блок не оставляет ни кошелька, ни закрепления
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfStake);
+        assert!(chain.mine_pending_transactions("stranger".to_string()).is_err());
+        assert!(chain.get_wallet_info("stranger").is_none());
+        assert!(chain.mempool().is_empty());
+    }
+}
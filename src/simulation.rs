@@ -0,0 +1,373 @@
+use crate::blockchain::{Blockchain, ConsensusAlgorithm, TARGET_BLOCK_TIME};
+use crate::block::GENESIS_TIMESTAMP;
+use crate::clock::MockClock;
+use crate::entropy::SeededEntropy;
+use crate::errors::BlockchainError;
+use crate::supply::is_system_sender;
+use crate::transaction::{Transaction, TransactionType};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+/// Сколько слотов симуляция ждет производителя PoS-блока, прежде чем сдаться
+const MAX_PRODUCER_SLOTS: usize = 10_000;
+
+/// Как распределены начальные балансы кошельков
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BalanceDistribution {
+    Equal(f64),
+    Uniform { min: f64, max: f64 },
+    /// Тяжелый хвост: немного богатых и много бедных
+    Pareto { scale: f64, shape: f64 },
+}
+
+impl BalanceDistribution {
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        match *self {
+            BalanceDistribution::Equal(balance) => balance,
+            BalanceDistribution::Uniform { min, max } if min < max => rng.random_range(min..max),
+            BalanceDistribution::Uniform { min, .. } => min,
+            BalanceDistribution::Pareto { scale, shape } => {
+                let u: f64 = rng.random_range(f64::EPSILON..1.0);
+                scale / u.powf(1.0 / shape)
+            },
+        }
+    }
+}
+
+/// Сколько отправитель платит сверх минимальной комиссии
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FeeStrategy {
+    Minimum,
+    /// Минимальная комиссия, умноженная на множитель
+    Multiplier(f64),
+    /// Минимальная комиссия плюс фиксированная надбавка
+    Tip(f64),
+}
+
+impl FeeStrategy {
+    fn apply(self, tx: &mut Transaction) {
+        match self {
+            FeeStrategy::Minimum => {},
+            FeeStrategy::Multiplier(factor) => tx.fee *= factor,
+            FeeStrategy::Tip(tip) => tx.fee += tip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    pub wallets: usize,
+    pub initial_balance: BalanceDistribution,
+    /// Среднее число действий пользователей на блок; фактическое берется от 0 до удвоенного
+    pub transactions_per_block: usize,
+    /// Стратегии комиссий с весами, с которыми их выбирают отправители
+    pub fee_strategies: Vec<(FeeStrategy, u32)>,
+    /// Сколько первых кошельков становятся валидаторами, ставя половину баланса
+    pub validators: usize,
+    pub consensus: ConsensusAlgorithm,
+    /// Доля действий, которые вызывают контракт (первое такое действие его развертывает)
+    pub contract_call_rate: f64,
+    /// Доля действий, которые сохраняют данные
+    pub data_store_rate: f64,
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            wallets: 20,
+            initial_balance: BalanceDistribution::Equal(1000.0),
+            transactions_per_block: 10,
+            fee_strategies: vec![(FeeStrategy::Minimum, 1)],
+            validators: 0,
+            consensus: ConsensusAlgorithm::ProofOfWork,
+            contract_call_rate: 0.05,
+            data_store_rate: 0.05,
+            seed: 0,
+        }
+    }
+}
+
+/// Итог одного блока симуляции
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedBlock {
+    pub height: u64,
+    pub transactions: usize,
+    pub fees: f64,
+    /// Глубина пула перед выпуском блока
+    pub mempool_depth: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub seed: u64,
+    pub height: u64,
+    pub tip_hash: String,
+    pub submitted: u64,
+    pub confirmed: u64,
+    /// Отклоненные действия по кодам ошибок
+    pub rejected: BTreeMap<String, u64>,
+    pub total_fees: f64,
+    pub contract_calls: u64,
+    pub data_stores: u64,
+    /// Коэффициент Джини итоговых балансов: 0 — поровну, близко к 1 — все у одного
+    pub gini: f64,
+    pub final_balances: BTreeMap<String, f64>,
+    pub blocks: Vec<SimulatedBlock>,
+}
+
+impl SimulationReport {
+    pub fn to_json(&self) -> Result<String, BlockchainError> {
+        serde_json::to_string_pretty(self).map_err(|e| BlockchainError::Storage(e.to_string()))
+    }
+    
+    /// Блоки симуляции в CSV: высота, транзакции, комиссии и глубина пула
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("height,transactions,fees,mempool_depth\n");
+        for block in &self.blocks {
+            let _ = writeln!(csv, "{},{},{},{}", block.height, block.transactions, block.fees, block.mempool_depth);
+        }
+        csv
+    }
+}
+
+/// Коэффициент Джини по отсортированным неотрицательным значениям
+fn gini(values: &[f64]) -> f64 {
+    let mut sorted: Vec<f64> = values.iter().map(|value| value.max(0.0)).collect();
+    sorted.sort_by(f64::total_cmp);
+    let total: f64 = sorted.iter().sum();
+    if sorted.is_empty() || total <= 0.0 {
+        return 0.0;
+    }
+    let n = sorted.len() as f64;
+    let weighted: f64 = sorted.iter().enumerate().map(|(i, value)| (i + 1) as f64 * value).sum();
+    2.0 * weighted / (n * total) - (n + 1.0) / n
+}
+
+/// Детерминированная симуляция активности: управляемые часы и генератор из зерна,
+/// поэтому два запуска с одной конфигурацией дают одинаковые цепочки
+pub struct Simulation {
+    config: SimulationConfig,
+    chain: Blockchain,
+    clock: Arc<MockClock>,
+    rng: StdRng,
+    wallets: Vec<String>,
+    validators: Vec<String>,
+    contract: Option<String>,
+    report: SimulationReport,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> Result<Self, BlockchainError> {
+        let mut chain = Blockchain::new(1, 50.0, config.consensus.clone());
+        // Участники симуляции — имена вроде `sim0000`
+        chain.accept_legacy_addresses = true;
+        let clock = Arc::new(MockClock::new(GENESIS_TIMESTAMP));
+        chain.set_clock(clock.clone());
+        chain.set_entropy(Arc::new(SeededEntropy::new(config.seed)));
+        chain.mining_threads = Some(1);
+        
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let wallets: Vec<String> = (0..config.wallets).map(|i| format!("sim{:04}", i)).collect();
+        for address in &wallets {
+            chain.create_wallet(address.clone())?;
+            chain.allocate_at_genesis(address, config.initial_balance.sample(&mut rng))?;
+        }
+        let validators: Vec<String> = wallets.iter().take(config.validators).cloned().collect();
+        for address in &validators {
+            chain.add_validator(address.clone(), chain.get_balance(address) / 2.0)?;
+        }
+        
+        let report = SimulationReport { seed: config.seed, ..SimulationReport::default() };
+        Ok(Simulation { config, chain, clock, rng, wallets, validators, contract: None, report })
+    }
+    
+    pub fn chain(&self) -> &Blockchain {
+        &self.chain
+    }
+    
+    pub fn into_chain(self) -> Blockchain {
+        self.chain
+    }
+    
+    /// Проводит цепочку через `blocks` блоков и возвращает отчет за все время симуляции
+    pub fn run(&mut self, blocks: u64) -> Result<SimulationReport, BlockchainError> {
+        for _ in 0..blocks {
+            let actions = self.rng.random_range(0..=2 * self.config.transactions_per_block);
+            for _ in 0..actions {
+                self.act();
+            }
+            let mempool_depth = self.chain.mempool().len();
+            self.produce_block()?;
+            
+            let block = self.chain.get_latest_block();
+            // Закрепление ключей ставит в пул сама симуляция при создании кошельков, это не действие пользователя
+            let user_transactions: Vec<&Transaction> = block.transactions.iter()
+                .filter(|tx| !is_system_sender(&tx.sender) && tx.transaction_type != TransactionType::RegisterKey)
+                .collect();
+            let fees = user_transactions.iter().fold(0.0, |total, tx| total + tx.fee);
+            self.report.confirmed += user_transactions.len() as u64;
+            self.report.total_fees += fees;
+            self.report.blocks.push(SimulatedBlock { height: block.index, transactions: user_transactions.len(), fees, mempool_depth });
+        }
+        
+        let tip = self.chain.get_latest_block();
+        self.report.height = tip.index;
+        self.report.tip_hash = tip.hash.clone();
+        self.report.final_balances = self.wallets.iter().map(|address| (address.clone(), self.chain.get_balance(address))).collect();
+        let balances: Vec<f64> = self.report.final_balances.values().copied().collect();
+        self.report.gini = gini(&balances);
+        Ok(self.report.clone())
+    }
+    
+    /// Одно действие случайного пользователя: перевод, вызов контракта или сохранение данных
+    fn act(&mut self) {
+        if self.wallets.is_empty() {
+            return;
+        }
+        let sender = self.wallets[self.rng.random_range(0..self.wallets.len())].clone();
+        let roll: f64 = self.rng.random();
+        
+        let result = if roll < self.config.contract_call_rate {
+            self.call_contract(&sender)
+        } else if roll < self.config.contract_call_rate + self.config.data_store_rate {
+            let data: Vec<u8> = (0..self.rng.random_range(16..128)).map(|_| self.rng.random()).collect();
+            self.report.data_stores += 1;
+            self.chain.store_data(sender, data).map(|_| ())
+        } else {
+            self.transfer(sender)
+        };
+        
+        self.report.submitted += 1;
+        if let Err(e) = result {
+            *self.report.rejected.entry(format!("{:?}", e.code())).or_default() += 1;
+        }
+    }
+    
+    fn transfer(&mut self, sender: String) -> Result<(), BlockchainError> {
+        let receiver = self.wallets[self.rng.random_range(0..self.wallets.len())].clone();
+        let share: f64 = self.rng.random_range(0.01..0.2);
+        let amount = (self.chain.get_balance(&sender) * share * 100.0).floor() / 100.0;
+        let mut tx = self.chain.new_transaction(sender, receiver, amount, TransactionType::Transfer);
+        self.fee_strategy().apply(&mut tx);
+        self.chain.sign_as_sender(&mut tx);
+        self.chain.add_transaction(tx)
+    }
+    
+    fn call_contract(&mut self, sender: &str) -> Result<(), BlockchainError> {
+        self.report.contract_calls += 1;
+        match &self.contract {
+            Some(contract) => self.chain.execute_smart_contract(contract, "ping", vec![sender.to_string()]).map(|_| ()),
+            None => {
+                self.contract = Some(self.chain.create_smart_contract(sender.to_string(), "fn ping() {}".to_string(), 1.0)?);
+                Ok(())
+            },
+        }
+    }
+    
+    fn fee_strategy(&mut self) -> FeeStrategy {
+        let total: u32 = self.config.fee_strategies.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return FeeStrategy::Minimum;
+        }
+        let mut pick = self.rng.random_range(0..total);
+        for (strategy, weight) in &self.config.fee_strategies {
+            if pick < *weight {
+                return *strategy;
+            }
+            pick -= weight;
+        }
+        FeeStrategy::Minimum
+    }
+    
+    /// Выпускает блок: в PoW — случайным кошельком, в PoS и DPoS — первым по адресу
+    /// валидатором, вытянувшим жребий, сдвигая часы на слот, пока такой не найдется
+    fn produce_block(&mut self) -> Result<(), BlockchainError> {
+        if self.chain.consensus_algorithm == ConsensusAlgorithm::ProofOfWork {
+            self.clock.advance(TARGET_BLOCK_TIME as i64);
+            let miner = match self.wallets.len() {
+                0 => "miner".to_string(),
+                len => self.wallets[self.rng.random_range(0..len)].clone(),
+            };
+            self.chain.get_or_create_wallet(&miner);
+            return self.chain.mine_pending_transactions(miner);
+        }
+        
+        for _ in 0..MAX_PRODUCER_SLOTS {
+            self.clock.advance(TARGET_BLOCK_TIME as i64);
+            for producer in &self.validators {
+                if self.chain.mine_pending_transactions(producer.clone()).is_ok() {
+                    return Ok(());
+                }
+            }
+        }
+        Err(BlockchainError::ConsensusError(format!("No validator won the draw in {} slots", MAX_PRODUCER_SLOTS)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::SignatureVerification;
+    
+    fn small_config() -> SimulationConfig {
+        SimulationConfig {
+            wallets: 4,
+            initial_balance: BalanceDistribution::Uniform { min: 100.0, max: 500.0 },
+            transactions_per_block: 3,
+            fee_strategies: vec![(FeeStrategy::Minimum, 2), (FeeStrategy::Tip(0.5), 1)],
+            contract_call_rate: 0.2,
+            data_store_rate: 0.2,
+            seed: 11,
+            ..SimulationConfig::default()
+        }
+    }
+    
+    #[test]
+    fn small_run_matches_the_golden_report() {
+        let report = Simulation::new(small_config()).unwrap().run(8).unwrap();
+        assert_eq!(report.seed, 11);
+        assert_eq!(report.height, 8);
+        assert_eq!(report.tip_hash, "05808a60d714f832863b451808b26fb16082d97afdf64c09a7d2f763683cc4cb");
+        assert_eq!((report.submitted, report.confirmed), (24, 19));
+        assert_eq!(report.rejected, BTreeMap::from([("ContractError".to_string(), 2)]));
+        assert_eq!((report.contract_calls, report.data_stores), (6, 7));
+        assert!((report.total_fees - 4.12431).abs() < 1e-9);
+        assert!((report.gini - 0.148029).abs() < 1e-6);
+        assert_eq!(report.to_csv(), "\
+height,transactions,fees,mempool_depth
+1,1,0.5449,5
+2,5,0.11556000000000001,5
+3,0,0,0
+4,2,0.6051899999999999,2
+5,3,0.5203000000000001,3
+6,2,0.5476300000000001,2
+7,5,1.7009100000000001,5
+8,1,0.08982,1
+");
+        
+        let restored: SimulationReport = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(restored, report);
+    }
+    
+    #[test]
+    fn simulated_chains_validate_and_balance() {
+        let stake = SimulationConfig { validators: 2, consensus: ConsensusAlgorithm::ProofOfStake, ..small_config() };
+        for config in [small_config(), stake] {
+            let mut simulation = Simulation::new(config).unwrap();
+            let report = simulation.run(6).unwrap();
+            let chain = simulation.chain();
+            chain.validate_chain(SignatureVerification::Serial).unwrap();
+            assert!(chain.audit_supply().is_ok());
+            assert_eq!(report.height, 6);
+            assert_eq!(report.tip_hash, chain.get_latest_block().hash);
+            for (address, balance) in &report.final_balances {
+                assert_eq!(*balance, chain.get_balance(address));
+            }
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::assets::Asset;
+use crate::block::{Block, BlockHeader};
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::governance::Governance;
+use crate::hashing::{check_block_hash_algorithm, HashKind};
+use crate::params::ChainParams;
+use crate::staking::Staking;
+use crate::sync::validate_header;
+use crate::target::CompactTarget;
+use crate::trace::info;
+use crate::transaction::{calculate_hash, verify_signatures, Transaction, TransactionType};
+use crate::treasury::Treasury;
+use crate::wallet::Wallet;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter;
+use std::sync::atomic::Ordering;
+
+/// Состояние цепочки после блока снимка. Кошельки и активы восстановлены по блокам, остальное
+/// узел хранит вне блоков и берется таким, каким оно было при выгрузке.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChainState {
+    /// Кошельки вместе с ключами, которые транзакции `RegisterKey` закрепили за именами
+    pub wallets: BTreeMap<String, Wallet>,
+    pub assets: BTreeMap<String, Asset>,
+    pub validators: BTreeMap<String, f64>,
+    pub jailed_validators: BTreeSet<String>,
+    pub off_chain_credits: BTreeMap<String, f64>,
+    /// Транзакции развертывания смарт-контрактов по адресам контрактов
+    pub contracts: BTreeMap<String, Transaction>,
+    /// Сколько выпущено стейкингом до блока снимка включительно
+    pub staking_issued: f64,
+    pub difficulty: usize,
+    pub target: Option<CompactTarget>,
+    pub params: ChainParams,
+    pub staking: Option<Staking>,
+    pub treasury: Option<Treasury>,
+    pub governance: Option<Governance>,
+}
+
+impl ChainState {
+    /// Корень состояния: хеш канонического JSON (ключи по порядку) вместе с хешем блока снимка,
+    /// посчитанный алгоритмом цепочки
+    pub fn root(&self, hash_algorithm: HashKind, block_hash: &str) -> Result<String, BlockchainError> {
+        let value = serde_json::to_value(self).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+        Ok(calculate_hash(hash_algorithm, &format!("{}{}", block_hash, value)))
+    }
+}
+
+/// Снимок для быстрой синхронизации: заголовки от генезиса, полный блок на высоте снимка
+/// и состояние после него
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChainSnapshot {
+    pub chain_id: u64,
+    /// Заголовки блоков ниже высоты снимка, начиная с генезиса
+    pub headers: Vec<BlockHeader>,
+    pub block: Block,
+    pub state: ChainState,
+    pub state_root: String,
+}
+
+impl ChainSnapshot {
+    pub fn height(&self) -> u64 {
+        self.block.index
+    }
+    
+    /// Доверенная точка этого снимка. Ее публикует узел, выгрузивший снимок, отдельно от него
+    /// самого: точке, взятой из полученного снимка, верить нельзя.
+    pub fn checkpoint(&self) -> SnapshotCheckpoint {
+        SnapshotCheckpoint { height: self.height(), block_hash: self.block.hash.clone(), state_root: self.state_root.clone() }
+    }
+}
+
+/// Высота, хеш блока и корень состояния, которым узел доверяет, например из конфигурации сети.
+/// Заголовки блоков не фиксируют состояние, поэтому без такой точки снимок не загружается.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotCheckpoint {
+    pub height: u64,
+    pub block_hash: String,
+    pub state_root: String,
+}
+
+/// Состояние, с которого начал узел, загруженный из снимка
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct SnapshotBase {
+    pub height: u64,
+    pub state: ChainState,
+}
+
+impl Blockchain {
+    /// Высота, с которой цепочку можно проигрывать: блоки ниже снимка хранятся без тел
+    pub(crate) fn first_replayed_height(&self) -> u64 {
+        self.snapshot_base.as_ref().map_or(1, |base| base.height + 1)
+    }
+    
+    /// Снимок состояния после блока `height`; высота должна быть окончательной
+    pub fn export_state_snapshot(&mut self, height: u64) -> Result<ChainSnapshot, BlockchainError> {
+        let finalized = self.finalized_height();
+        if height > finalized {
+            return Err(BlockchainError::InvalidBlock(format!("Height {} is not final yet; the finalized height is {}", height, finalized)));
+        }
+        let first = self.first_replayed_height();
+        if height + 1 < first {
+            return Err(BlockchainError::InvalidBlock(format!("State below the snapshot height {} is not available", first - 1)));
+        }
+        
+        let (wallets, assets) = self.replay_base();
+        let (wallets, assets) = self.replay_blocks(wallets, assets, first as usize..height as usize + 1);
+        let mut contracts = self.snapshot_base.as_ref().map(|base| base.state.contracts.clone()).unwrap_or_default();
+        for tx in self.chain[first as usize..=height as usize].iter().flat_map(|block| &block.transactions) {
+            if matches!(tx.transaction_type, TransactionType::SmartContract(_)) {
+                contracts.entry(tx.receiver.clone()).or_insert_with(|| tx.clone());
+            }
+        }
+        
+        let state = ChainState {
+            wallets: wallets.into_iter().collect(),
+            assets: assets.into_iter().collect(),
+            validators: self.validators.iter().map(|(address, stake)| (address.clone(), *stake)).collect(),
+            jailed_validators: self.jailed_validators.iter().cloned().collect(),
+            off_chain_credits: self.off_chain_credits.clone(),
+            contracts,
+            staking_issued: self.inflation_issued_before(height + 1),
+            difficulty: self.difficulty,
+            target: self.target,
+            params: self.params.clone(),
+            staking: self.staking.clone(),
+            treasury: self.treasury.clone(),
+            governance: self.governance.clone(),
+        };
+        let block = self.chain[height as usize].clone();
+        Ok(ChainSnapshot {
+            chain_id: self.chain_id,
+            headers: self.chain[..height as usize].iter().map(Block::header).collect(),
+            state_root: state.root(self.hash_algorithm, &block.hash)?,
+            block,
+            state,
+        })
+    }
+    
+    /// Загружает снимок в узел, у которого есть только генезис: проверяет цепочку заголовков от
+    /// своего генезиса, блок снимка и корень состояния, который должен совпасть с доверенной точкой,
+    /// затем ставит состояние. Дальше узел синхронизирует только блоки выше снимка.
+    pub fn bootstrap_from_snapshot(&mut self, snapshot: ChainSnapshot, checkpoint: &SnapshotCheckpoint) -> Result<(), BlockchainError> {
+        if snapshot.chain_id != self.chain_id {
+            return Err(BlockchainError::WrongChainId { expected: self.chain_id, actual: snapshot.chain_id });
+        }
+        if self.chain.len() != 1 || !self.mempool.is_empty() {
+            return Err(BlockchainError::InvalidBlock("Only a node at genesis can bootstrap from a snapshot".to_string()));
+        }
+        self.require_account_mode("Bootstrapping from a snapshot")?;
+        
+        let height = snapshot.height();
+        if height == 0 || snapshot.headers.len() as u64 != height {
+            return Err(BlockchainError::InvalidBlock(format!("Snapshot must carry every header below height {}", height)));
+        }
+        if snapshot.headers[0] != self.get_latest_block().header() {
+            return Err(BlockchainError::InvalidBlock("Snapshot does not start at this chain's genesis".to_string()));
+        }
+        let tip = snapshot.block.header();
+        for (parent, header) in snapshot.headers.iter().zip(snapshot.headers.iter().skip(1).chain(iter::once(&tip))) {
+            validate_header(parent, header, &self.consensus_algorithm)
+                .map_err(|e| BlockchainError::InvalidBlock(format!("Snapshot header chain: {}", e)))?;
+        }
+        check_block_hash_algorithm(&snapshot.block, self.hash_algorithm)?;
+        if snapshot.block.merkle_root != Block::calculate_merkle_root(self.hash_algorithm, &snapshot.block.transactions) {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong Merkle root in block # {}", height)));
+        }
+        verify_signatures(&snapshot.block.transactions, self.signature_verification, self.accepts_legacy_signatures(snapshot.block.index))?;
+        
+        let state_root = snapshot.state.root(self.hash_algorithm, &snapshot.block.hash)?;
+        if state_root != snapshot.state_root {
+            return Err(BlockchainError::InvalidBlock("Snapshot state does not match its state root".to_string()));
+        }
+        if checkpoint.height != height || checkpoint.block_hash != snapshot.block.hash || checkpoint.state_root != state_root {
+            return Err(BlockchainError::InvalidBlock("Snapshot does not match the trusted checkpoint".to_string()));
+        }
+        
+        let ChainSnapshot { headers, block, state, .. } = snapshot;
+        self.chain.extend(headers.into_iter().skip(1).map(Block::from_header));
+        self.chain.push(block);
+        self.tip_epoch.fetch_add(1, Ordering::Relaxed);
+        self.wallets = state.wallets.clone().into_iter().collect();
+        self.assets = state.assets.clone().into_iter().collect();
+        self.validators = state.validators.clone().into_iter().collect();
+        self.jailed_validators = state.jailed_validators.iter().cloned().collect();
+        self.off_chain_credits = state.off_chain_credits.clone();
+        self.difficulty = state.difficulty;
+        self.target = state.target;
+        self.params = state.params.clone();
+        self.staking = state.staking.clone();
+        self.treasury = state.treasury.clone();
+        self.governance = state.governance.clone();
+        self.snapshot_base = Some(SnapshotBase { height, state });
+        
+        self.rebuild_address_index();
+        self.rebuild_wallet_histories();
+        self.rebuild_fee_history();
+        info!(height, state_root = %state_root, "Bootstrapped from a state snapshot");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::forks::FINALITY_DEPTH;
+    use crate::shared::SharedBlockchain;
+    use crate::sync::{sync_chain, SyncOptions};
+    use crate::test_support::{ChainFixture, FIXTURE_MINER};
+    
+    #[test]
+    fn bootstrapped_node_keeps_key_bindings_for_the_next_block() {
+        let mut source = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        let mut fresh: Blockchain = serde_json::from_str(&serde_json::to_string(&source).unwrap()).unwrap();
+        source.create_wallet("alice".to_string()).unwrap();
+        source.mine_pending_transactions("alice".to_string()).unwrap();
+        for _ in 0..FINALITY_DEPTH {
+            source.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        
+        // Снимок проходит через JSON, как при передаче по сети
+        let snapshot = source.export_state_snapshot(1).unwrap();
+        let snapshot: ChainSnapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let checkpoint = snapshot.checkpoint();
+        fresh.bootstrap_from_snapshot(snapshot, &checkpoint).unwrap();
+        let alice = source.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        assert_eq!(fresh.registered_key("alice"), Some(alice.public_key_hex().as_str()));
+        
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(source.get_account_nonce("alice"));
+        tx.sign(&alice);
+        source.add_transaction(tx).unwrap();
+        source.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        for block in source.chain.iter().skip(2).cloned() {
+            fresh.accept_block(block).unwrap();
+        }
+        assert_eq!(fresh.get_latest_block().hash, source.get_latest_block().hash);
+        for address in ["alice", "bob", "miner"] {
+            assert_eq!(fresh.get_balance(address), source.get_balance(address), "{}", address);
+        }
+    }
+    
+    #[test]
+    fn node_bootstraps_at_height_300_and_syncs_the_next_20_blocks() {
+        let mut source = ChainFixture::new()
+            .with_wallets(&["alice"], 100.0)
+            .with_transactions(|batch| batch.transfer("alice", "bob", 25.0))
+            .with_mined_blocks(300 + FINALITY_DEPTH)
+            .build()
+            .unwrap();
+        let mut fresh = ChainFixture::chain(1, 50.0, ConsensusAlgorithm::ProofOfWork);
+        
+        let snapshot = source.chain.export_state_snapshot(300).unwrap();
+        let checkpoint = snapshot.checkpoint();
+        let snapshot: ChainSnapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        fresh.bootstrap_from_snapshot(snapshot, &checkpoint).unwrap();
+        assert_eq!(fresh.get_latest_block().index, 300);
+        assert_eq!(fresh.get_latest_block().hash, source.chain.chain[300].hash);
+        
+        for _ in 0..20 - FINALITY_DEPTH {
+            source.mine_block().unwrap();
+        }
+        let fresh = SharedBlockchain::new(fresh);
+        let peer = SharedBlockchain::new(source.chain);
+        let report = sync_chain(&fresh, &[&peer], &SyncOptions::default());
+        assert!(report.rejected_peers.is_empty(), "{:?}", report.rejected_peers);
+        assert_eq!((report.start_height, report.final_height), (300, 320));
+        
+        let (fresh, source) = (fresh.read(), peer.read());
+        assert_eq!(fresh.get_latest_block().hash, source.get_latest_block().hash);
+        for address in ["alice", "bob", FIXTURE_MINER] {
+            assert_eq!(fresh.get_balance(address), source.get_balance(address), "{}", address);
+        }
+    }
+    
+    #[test]
+    fn corrupted_snapshot_is_rejected() {
+        let mut source = ChainFixture::new()
+            .with_wallets(&["alice"], 100.0)
+            .with_mined_blocks(1 + FINALITY_DEPTH)
+            .build()
+            .unwrap();
+        let snapshot = source.chain.export_state_snapshot(1).unwrap();
+        let checkpoint = snapshot.checkpoint();
+        let fresh = || ChainFixture::chain(1, 50.0, ConsensusAlgorithm::ProofOfWork);
+        let inflate = |snapshot: &mut ChainSnapshot| {
+            if let Some(wallet) = snapshot.state.wallets.get_mut("alice") {
+                wallet.balance += 1_000.0;
+            }
+        };
+        
+        // Состояние подменено, корень прежний
+        let mut tampered = snapshot.clone();
+        inflate(&mut tampered);
+        // Состояние подменено вместе с корнем: снимок согласован сам с собой, но не с доверенной точкой
+        let mut forged = snapshot.clone();
+        inflate(&mut forged);
+        forged.state_root = forged.state.root(HashKind::Sha256, &forged.block.hash).unwrap();
+        // Блок снимка не тот, что в цепочке заголовков
+        let mut reordered = snapshot.clone();
+        reordered.block.transactions.reverse();
+        
+        for corrupted in [tampered, forged, reordered] {
+            let mut node = fresh();
+            assert!(matches!(node.bootstrap_from_snapshot(corrupted, &checkpoint), Err(BlockchainError::InvalidBlock(_))));
+            assert_eq!(node.chain.len(), 1);
+            assert_eq!(node.get_balance("alice"), 0.0);
+        }
+        let mut untrusted = checkpoint.clone();
+        untrusted.state_root = calculate_hash(HashKind::Sha256, "another state");
+        assert!(fresh().bootstrap_from_snapshot(snapshot.clone(), &untrusted).is_err());
+        
+        let mut node = fresh();
+        node.bootstrap_from_snapshot(snapshot, &checkpoint).unwrap();
+        assert_eq!(node.get_balance("alice"), source.chain.get_balance("alice"));
+        source.mine_block().unwrap();
+        node.accept_block(source.chain.get_latest_block().clone()).unwrap();
+    }
+}
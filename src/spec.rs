@@ -0,0 +1,246 @@
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::errors::BlockchainError;
+use crate::faucet::FaucetConfig;
+use crate::features::Feature;
+use crate::fee_market::BaseFeeConfig;
+use crate::freeze::FreezeConfig;
+use crate::governance::GovernanceConfig;
+use crate::hashing::HashKind;
+use crate::mining::MiningOptions;
+use crate::params::{ExistentialDeposit, FeeDiscountTier, RewardMode, StakeParams};
+use crate::reserved::ReservedSpace;
+use crate::signing::parse_public_key;
+use crate::staking::StakingConfig;
+use crate::target::MAX_DIFFICULTY;
+use crate::treasury::TreasuryConfig;
+
+use crate::address::Network;
+use crate::utxo::LedgerMode;
+use crate::wallet::Wallet;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Описание новой цепочки: параметры консенсуса и начальное распределение средств
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainSpec {
+    /// Идентификатор цепочки для подписываемых транзакций
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Сеть, чьи адреса с префиксом принимает цепочка
+    #[serde(default)]
+    pub network: Network,
+    /// Принимать ли адреса без префикса сети; по умолчанию нет, демо-имена вроде `alice` включаются явно
+    #[serde(default)]
+    pub accept_legacy_addresses: bool,
+    /// Хеш-функция блоков, листьев Меркла и идентификаторов транзакций
+    #[serde(default)]
+    pub hash_algorithm: HashKind,
+    /// Сложность PoW; у PoS и DPoS не используется
+    #[serde(default)]
+    pub difficulty: usize,
+    pub mining_reward: f64,
+    /// Через сколько блоков награда уменьшается вдвое
+    #[serde(default)]
+    pub halving_interval_blocks: Option<u64>,
+    /// Предельная эмиссия наградами блоков
+    #[serde(default)]
+    pub max_supply: Option<f64>,
+    /// Скидки на комиссию по ступеням стейка отправителя
+    #[serde(default)]
+    pub fee_discount_tiers: Vec<FeeDiscountTier>,
+    /// Минимальный баланс существующего кошелька
+    #[serde(default)]
+    pub existential_deposit: Option<ExistentialDeposit>,
+    /// Копить награды для последующего востребования вместо зачисления на баланс
+    #[serde(default)]
+    pub reward_mode: RewardMode,
+    pub consensus: ConsensusAlgorithm,
+    /// Начальные балансы кошельков
+    #[serde(default)]
+    pub allocations: BTreeMap<String, f64>,
+    /// Открытые ключи в hex, за которыми закрепляются имена из `allocations`; без ключа имя
+    /// закрепляется за парой, которую создает собравший цепочку узел
+    #[serde(default)]
+    pub allocation_keys: BTreeMap<String, String>,
+    /// Стейки валидаторов, списываются с начальных балансов
+    #[serde(default)]
+    pub validators: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub max_pending_transactions: Option<usize>,
+    /// Потоки PoW-майнинга; без значения используются все ядра
+    #[serde(default)]
+    pub mining_threads: Option<usize>,
+    /// Через сколько попыток майнинг проверяет, не сменилась ли вершина
+    #[serde(default)]
+    pub mining_check_interval: Option<u64>,
+    /// Обновление времени и extra-nonce во время долгого перебора
+    #[serde(default)]
+    pub mining_options: MiningOptions,
+    /// Интервал между блоками и длина слота для PoS и DPoS
+    #[serde(default)]
+    pub stake_params: Option<StakeParams>,
+    /// Доля ложных срабатываний фильтров адресов в блоках
+    #[serde(default)]
+    pub address_filter_rate: Option<f64>,
+    /// Кран тестовой сети; его кошелек пополняется через `allocations`
+    #[serde(default)]
+    pub faucet: Option<FaucetConfig>,
+    /// Базовая комиссия со сжиганием
+    #[serde(default)]
+    pub fee_market: Option<BaseFeeConfig>,
+    /// Доля веса блока под системные транзакции; нужен рынок комиссий
+    #[serde(default)]
+    pub reserved_space: Option<ReservedSpace>,
+    /// Казна, получающая долю награды за блок
+    #[serde(default)]
+    pub treasury: Option<TreasuryConfig>,
+    /// Инфляционные выплаты валидаторам по эпохам
+    #[serde(default)]
+    pub staking: Option<StakingConfig>,
+    /// Администраторы, которые могут замораживать счета
+    #[serde(default)]
+    pub account_freeze: Option<FreezeConfig>,
+    /// Голосование по предложениям, взвешенное стейком валидаторов
+    #[serde(default)]
+    pub governance: Option<GovernanceConfig>,
+    /// Учет по счетам или по непотраченным выходам; UTXO-цепочка начинается без начальных балансов
+    #[serde(default)]
+    pub ledger_mode: LedgerMode,
+    /// Открытый ключ эмитента в hex; без него транзакции `Mint` не принимаются
+    #[serde(default)]
+    pub mint_authority: Option<String>,
+    /// Выключенные возможности; после генезиса меняются только голосованием
+    #[serde(default)]
+    pub disabled_features: BTreeSet<Feature>,
+}
+
+impl ChainSpec {
+    /// Читает спецификацию из JSON-файла
+    pub fn from_file(path: &Path) -> Result<ChainSpec, BlockchainError> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| BlockchainError::Storage(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| BlockchainError::Storage(format!("Invalid chain spec {}: {}", path.display(), e)))
+    }
+    
+    /// Создает блокчейн по спецификации
+    pub fn build(&self) -> Result<Blockchain, BlockchainError> {
+        if self.difficulty > MAX_DIFFICULTY {
+            return Err(BlockchainError::InvalidConfig(format!("Difficulty {} exceeds the maximum {}", self.difficulty, MAX_DIFFICULTY)));
+        }
+        let mut chain = Blockchain::new(self.difficulty, self.mining_reward, self.consensus.clone());
+        chain.set_hash_algorithm(self.hash_algorithm)?;
+        chain.mempool.set_capacity(self.max_pending_transactions);
+        chain.mining_threads = self.mining_threads;
+        chain.mining_check_interval = self.mining_check_interval;
+        chain.mining_options = self.mining_options;
+        chain.params.halving_interval_blocks = self.halving_interval_blocks;
+        chain.params.max_supply = self.max_supply;
+        if let Some(tier) = self.fee_discount_tiers.iter().find(|tier| !(0.0..=100.0).contains(&tier.percent)) {
+            return Err(BlockchainError::InvalidTransaction(format!("Fee discount {}% is not between 0 and 100", tier.percent)));
+        }
+        chain.params.fee_discount_tiers = self.fee_discount_tiers.clone();
+        if let Some(deposit) = self.existential_deposit {
+            if !deposit.amount.is_finite() || deposit.amount < 0.0 {
+                return Err(BlockchainError::InvalidTransaction(format!("Existential deposit {} is invalid", deposit.amount)));
+            }
+        }
+        chain.params.existential_deposit = self.existential_deposit;
+        chain.params.reward_mode = self.reward_mode;
+        chain.params.disabled_features = self.disabled_features.clone();
+        if let Some(stake_params) = self.stake_params {
+            if self.consensus == ConsensusAlgorithm::ProofOfWork {
+                return Err(BlockchainError::ConsensusError("Stake parameters need proof of stake".to_string()));
+            }
+            if stake_params.slot_duration == 0 || stake_params.slot_duration > i64::MAX as u64 {
+                return Err(BlockchainError::ConsensusError(format!("Slot duration {} is invalid", stake_params.slot_duration)));
+            }
+            chain.params.stake_params = Some(stake_params);
+        }
+        chain.fee_market = self.fee_market.clone();
+        if let Some(reserved_space) = &self.reserved_space {
+            chain.enable_reserved_space(reserved_space.clone())?;
+        }
+        if let Some(treasury) = &self.treasury {
+            chain.enable_treasury(treasury.clone())?;
+        }
+        if let Some(staking) = &self.staking {
+            chain.enable_staking(staking.clone())?;
+        }
+        if let Some(account_freeze) = &self.account_freeze {
+            chain.enable_account_freeze(account_freeze.clone())?;
+        }
+        if let Some(governance) = &self.governance {
+            chain.enable_governance(governance.clone())?;
+        }
+        if let Some(chain_id) = self.chain_id {
+            chain.chain_id = chain_id;
+        }
+        chain.network = self.network;
+        chain.accept_legacy_addresses = self.accept_legacy_addresses;
+        if let Some(rate) = self.address_filter_rate {
+            chain.set_address_filter_rate(rate);
+        }
+        if self.ledger_mode == LedgerMode::Utxo {
+            chain.enable_utxo_mode()?;
+        }
+        if let Some(authority) = &self.mint_authority {
+            if parse_public_key(authority).is_none() {
+                return Err(BlockchainError::InvalidConfig(format!("Mint authority {} is not a public key", authority)));
+            }
+            if self.ledger_mode == LedgerMode::Utxo {
+                return Err(BlockchainError::InvalidConfig("Minting needs the account ledger".to_string()));
+            }
+            chain.params.mint_authority = Some(authority.clone());
+        }
+        
+        if let Some(address) = self.allocation_keys.keys().find(|address| !self.allocations.contains_key(*address)) {
+            return Err(BlockchainError::InvalidConfig(format!("Allocation key for {} without an allocation", address)));
+        }
+        for (address, amount) in &self.allocations {
+            chain.check_address(address)?;
+            match self.allocation_keys.get(address) {
+                Some(public_key) => {
+                    if parse_public_key(public_key).is_none() {
+                        return Err(BlockchainError::InvalidConfig(format!("Allocation key of {} is not a public key", address)));
+                    }
+                    chain.insert_wallet(Wallet::new(address.clone()))?;
+                    chain.allocation_keys.insert(address.clone(), public_key.to_ascii_lowercase());
+                },
+                None => {
+                    chain.create_wallet(address.clone())?;
+                },
+            }
+            chain.allocate_at_genesis(address, *amount)?;
+        }
+        
+        for (address, stake) in &self.validators {
+            chain.check_address(address)?;
+            chain.add_validator(address.clone(), *stake)?;
+        }
+        
+        if let Some(faucet) = &self.faucet {
+            chain.check_address(&faucet.address)?;
+            if chain.get_wallet_info(&faucet.address).is_none() {
+                chain.create_wallet(faucet.address.clone())?;
+            }
+            chain.enable_faucet(faucet.clone());
+        }
+        
+        Ok(chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn unreachable_difficulty_is_invalid_config() {
+        let spec: ChainSpec = serde_json::from_value(json!({ "difficulty": MAX_DIFFICULTY + 1, "mining_reward": 1.0, "consensus": "ProofOfWork" })).unwrap();
+        let result = spec.build();
+        assert!(matches!(&result, Err(BlockchainError::InvalidConfig(reason)) if reason == "Difficulty 64 exceeds the maximum 63"), "{:?}", result.err());
+    }
+}
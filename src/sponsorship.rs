@@ -0,0 +1,139 @@
+use crate::balance_audit::{BalanceBucket, BalanceChangeReason};
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::{Transaction, TransactionType};
+
+/// Кто платит комиссию транзакции: спонсор, если он есть, иначе отправитель
+pub(crate) fn fee_bearer(tx: &Transaction) -> &str {
+    tx.fee_payer.as_ref().map_or(tx.sender.as_str(), |payer| payer.address.as_str())
+}
+
+/// Спонсор и комиссия, которую с него списывает транзакция
+pub(crate) fn fee_payer_debit(tx: &Transaction) -> Option<(&str, f64)> {
+    tx.fee_payer.as_ref().map(|payer| (payer.address.as_str(), tx.fee))
+}
+
+impl Blockchain {
+    /// Проверяет спонсора транзакции: это не сам отправитель, он подписал транзакцию своим ключом
+    /// (`owner_key`), комиссия не выше подписанного им предела, а его кошелек существует, не заморожен
+    /// и не закрыт. Баланс спонсора проверяется вместе с балансом отправителя.
+    pub(crate) fn check_fee_payer(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        let Some(payer) = &tx.fee_payer else {
+            return Ok(());
+        };
+        if payer.address == tx.sender {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} names its sender as the fee payer", tx.id)));
+        }
+        let countersigned = self.owner_key(&payer.address, payer.public_key.as_deref())
+            .is_some_and(|public_key| tx.verify_fee_payer(&public_key));
+        if !countersigned {
+            return Err(BlockchainError::InvalidSignature { tx_id: tx.id.clone() });
+        }
+        if matches!(tx.transaction_type, TransactionType::CloseWallet) {
+            return Err(BlockchainError::InvalidTransaction(format!("Wallet closure {} cannot be sponsored", tx.id)));
+        }
+        if tx.fee > payer.max_fee {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Fee {} of transaction {} exceeds the fee payer's limit {}", tx.fee, tx.id, payer.max_fee
+            )));
+        }
+        
+        if !self.wallets.contains_key(&payer.address) {
+            return Err(BlockchainError::WalletNotFound { address: payer.address.clone() });
+        }
+        if self.is_frozen(&payer.address) {
+            return Err(BlockchainError::AccountFrozen { address: payer.address.clone() });
+        }
+        if self.is_closed(&payer.address) {
+            return Err(BlockchainError::WalletClosed { address: payer.address.clone() });
+        }
+        Ok(())
+    }
+    
+    /// Списывает комиссию со спонсора транзакции
+    pub(crate) fn debit_fee_payer(&mut self, tx: &Transaction) {
+        let Some((payer, fee)) = fee_payer_debit(tx) else {
+            return;
+        };
+        if let Some(wallet) = self.wallets.get_mut(payer) {
+            wallet.balance -= fee;
+        }
+        self.record_balance_change(payer, BalanceBucket::Balance, -fee, BalanceChangeReason::Transaction { tx_id: tx.id.clone() });
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::FeePayer;
+    
+    /// Цепочка, где у `alice` и `victim` есть средства и свои ключи
+    fn funded_chain() -> Blockchain {
+        ChainFixture::funded().with_block_mined_by("victim").build().unwrap().chain
+    }
+    
+    fn transfer(chain: &Blockchain) -> Transaction {
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.fee = 3.0;
+        tx
+    }
+    
+    fn sign_as_alice(chain: &Blockchain, tx: &mut Transaction) {
+        chain.get_wallet_info("alice").unwrap().sign_transaction(tx).unwrap();
+    }
+    
+    #[test]
+    fn sender_cannot_sign_for_the_fee_payer() {
+        let mut chain = funded_chain();
+        let victim_balance = chain.get_balance("victim");
+        let alice_key = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        
+        let mut tx = transfer(&chain);
+        tx.sponsor(&alice_key, "victim", 3.0);
+        sign_as_alice(&chain, &mut tx);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+        assert_eq!(chain.get_balance("victim"), victim_balance);
+    }
+    
+    #[test]
+    fn keyless_sponsorship_is_rejected() {
+        let mut chain = funded_chain();
+        let mut tx = transfer(&chain);
+        tx.fee_payer = Some(FeePayer { address: "victim".to_string(), max_fee: 3.0, signature: "sig_forged".to_string(), public_key: None });
+        sign_as_alice(&chain, &mut tx);
+        assert!(matches!(chain.add_transaction(tx), Err(BlockchainError::InvalidSignature { .. })));
+    }
+    
+    #[test]
+    fn block_with_a_forged_sponsorship_is_rejected() {
+        let mut chain = funded_chain();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        let alice_key = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        
+        let mut tx = transfer(&chain);
+        tx.sponsor(&alice_key, "victim", 3.0);
+        sign_as_alice(&chain, &mut tx);
+        // Майнер-сообщник кладет транзакцию в пул в обход проверок
+        chain.mempool.get_mut().transactions.push(tx);
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let block = chain.get_latest_block().clone();
+        assert!(matches!(peer.accept_block(block), Err(BlockchainError::InvalidSignature { .. })));
+    }
+    
+    #[test]
+    fn countersigned_sponsor_pays_the_fee() {
+        let mut chain = funded_chain();
+        let alice_balance = chain.get_balance("alice");
+        let victim_balance = chain.get_balance("victim");
+        
+        let mut tx = transfer(&chain);
+        chain.get_wallet_info("victim").unwrap().sponsor_transaction(&mut tx, 3.0).unwrap();
+        sign_as_alice(&chain, &mut tx);
+        chain.add_transaction(tx).unwrap();
+        assert_eq!(chain.get_balance("alice"), alice_balance - 10.0);
+        assert_eq!(chain.get_balance("victim"), victim_balance - 3.0);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+/// Отправитель выплат инфляционного вознаграждения за стейкинг
+pub const STAKING_MINTER: &str = "BLOCKCHAIN_STAKING";
+
+/// Параметры инфляционного вознаграждения валидаторов
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StakingConfig {
+    /// Длина эпохи в блоках; выплата входит в каждый блок с высотой, кратной ей
+    pub epoch_length_blocks: u64,
+    /// Годовая ставка от суммы стейка, например 0.05
+    pub annual_rate: f64,
+    /// Сколько блоков считается годом при пересчете ставки на эпоху
+    pub blocks_per_year: u64,
+}
+
+impl StakingConfig {
+    /// Доля стейка, которую получает валидатор за полную эпоху
+    pub fn epoch_rate(&self) -> f64 {
+        self.annual_rate * self.epoch_length_blocks as f64 / self.blocks_per_year as f64
+    }
+    
+    /// Завершает ли блок этой высоты эпоху
+    pub fn is_epoch_boundary(&self, height: u64) -> bool {
+        height > 0 && height.is_multiple_of(self.epoch_length_blocks)
+    }
+}
+
+/// Стейкинг: параметры и высоты, с которых валидаторы непрерывно состоят в наборе
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Staking {
+    pub config: StakingConfig,
+    /// Валидаторы без записи считаются состоящими в наборе с генезиса
+    #[serde(default)]
+    bonded_since: HashMap<String, u64>,
+}
+
+impl Staking {
+    pub fn new(config: StakingConfig) -> Self {
+        Staking { config, bonded_since: HashMap::new() }
+    }
+    
+    /// Доля эпохи, завершаемой блоком height, в течение которой валидатор был в наборе
+    pub fn uptime(&self, validator: &str, height: u64) -> f64 {
+        let length = self.config.epoch_length_blocks;
+        let epoch_start = (height + 1).saturating_sub(length);
+        let since = self.bonded_since.get(validator).copied().unwrap_or(0).max(epoch_start);
+        (height + 1).saturating_sub(since) as f64 / length as f64
+    }
+    
+    pub(crate) fn bond(&mut self, validator: &str, height: u64) {
+        self.bonded_since.entry(validator.to_string()).or_insert(height);
+    }
+    
+    pub(crate) fn unbond(&mut self, validator: &str) {
+        self.bonded_since.remove(validator);
+    }
+}
+
+impl Blockchain {
+    /// Включает инфляционные выплаты валидаторам на границах эпох
+    pub fn enable_staking(&mut self, config: StakingConfig) -> Result<(), BlockchainError> {
+        self.require_account_mode("Staking")?;
+        if config.epoch_length_blocks == 0 || config.blocks_per_year == 0 {
+            return Err(BlockchainError::InvalidTransaction("Epoch length and blocks per year must be positive".to_string()));
+        }
+        if !config.annual_rate.is_finite() || config.annual_rate < 0.0 {
+            return Err(BlockchainError::InvalidTransaction(format!("Staking rate {} is invalid", config.annual_rate)));
+        }
+        
+        self.staking = Some(Staking::new(config));
+        Ok(())
+    }
+    
+    /// Отстраняет валидатора: он не может выпускать блоки и не получает выплат эпохи
+    pub fn jail_validator(&mut self, address: &str) -> Result<(), BlockchainError> {
+        if !self.validators.contains_key(address) {
+            return Err(BlockchainError::ValidatorNotFound { address: address.to_string() });
+        }
+        self.jailed_validators.insert(address.to_string());
+        Ok(())
+    }
+    
+    /// Возвращает отстраненного валидатора к работе
+    pub fn unjail_validator(&mut self, address: &str) -> Result<(), BlockchainError> {
+        if !self.jailed_validators.remove(address) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} is not jailed", address)));
+        }
+        Ok(())
+    }
+    
+    /// Сколько выплачено стейкингом в блоках ниже указанной высоты; выплаты в блоках ниже снимка
+    /// состояния берутся из него
+    pub fn inflation_issued_before(&self, height: u64) -> f64 {
+        let Some(staking) = &self.staking else {
+            return 0.0;
+        };
+        let (issued, start) = match &self.snapshot_base {
+            Some(base) if height > base.height => (base.state.staking_issued, base.height + 1),
+            _ => (0.0, 0),
+        };
+        
+        issued + self.chain.iter()
+            .take(height as usize)
+            .skip(start as usize)
+            .filter(|block| staking.config.is_epoch_boundary(block.index))
+            .flat_map(|block| &block.transactions)
+            .filter(|tx| tx.sender == STAKING_MINTER)
+            .map(|tx| tx.amount)
+            .sum::<f64>()
+    }
+    
+    /// Выплаты валидаторам в блоке указанной высоты, упорядоченные по адресу. Каждый получает
+    /// ставку эпохи от своего стейка, умноженную на долю эпохи в наборе; отстраненные не получают
+    /// ничего. Если лимит эмиссии не вмещает всю сумму, выплаты уменьшаются пропорционально.
+    pub fn epoch_payouts(&self, height: u64) -> Vec<(String, f64)> {
+        let Some(staking) = self.staking.as_ref().filter(|staking| staking.config.is_epoch_boundary(height)) else {
+            return Vec::new();
+        };
+        
+        let mut payouts: Vec<(String, f64)> = self.validators.iter()
+            .filter(|(address, _)| !self.jailed_validators.contains(*address))
+            .map(|(address, stake)| (address.clone(), stake * staking.config.epoch_rate() * staking.uptime(address, height)))
+            .filter(|(_, amount)| *amount > 0.0)
+            .collect();
+        payouts.sort_by(|a, b| a.0.cmp(&b.0));
+        
+        let total: f64 = payouts.iter().map(|(_, amount)| amount).sum();
+        let remaining = self.params.remaining_supply(height + 1, self.inflation_issued_before(height));
+        if let Some(remaining) = remaining.filter(|&remaining| remaining < total) {
+            let scale = remaining / total;
+            for (_, amount) in &mut payouts {
+                *amount *= scale;
+            }
+            payouts.retain(|(_, amount)| *amount > 0.0);
+        }
+        payouts
+    }
+    
+    /// Транзакции выплат стейкинга для блока указанной высоты
+    pub(crate) fn staking_payout_transactions(&self, height: u64) -> Vec<Transaction> {
+        self.epoch_payouts(height).into_iter()
+            .map(|(validator, amount)| {
+                let mut tx = self.new_transaction(STAKING_MINTER.to_string(), validator, amount, TransactionType::Transfer);
+                tx.fee = 0.0;
+                tx
+            })
+            .collect()
+    }
+    
+    /// Проверяет, что блок содержит ровно положенные на его высоте выплаты стейкинга
+    pub(crate) fn check_staking_payouts(&self, block: &Block) -> Result<(), BlockchainError> {
+        let expected = self.epoch_payouts(block.index);
+        let mut actual = block.transactions.iter().filter(|tx| tx.sender == STAKING_MINTER);
+        
+        for (validator, amount) in &expected {
+            match actual.next() {
+                Some(tx) if &tx.receiver == validator && (tx.amount - amount).abs() <= 1e-9 => {},
+                _ => return Err(BlockchainError::InvalidBlock(format!("Wrong staking payouts in block # {}", block.index))),
+            }
+        }
+        if actual.next().is_some() {
+            return Err(BlockchainError::InvalidBlock(format!("Wrong staking payouts in block # {}", block.index)));
+        }
+        Ok(())
+    }
+    
+    /// Выплаты стейкинга выпускает только протокол
+    pub(crate) fn check_staking_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        if tx.sender == STAKING_MINTER {
+            return Err(BlockchainError::InvalidTransaction(format!("{} is reserved for staking payouts", STAKING_MINTER)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    const EPOCH: u64 = 5;
+    
+    /// Цепочка с тремя валидаторами со стейками 10, 30 и 20; последний отстранен. Все вступают
+    /// в набор на высоте 4, поэтому эпоха 6..=10 у них полная.
+    fn chain_with_validators() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.enable_staking(StakingConfig { epoch_length_blocks: EPOCH, annual_rate: 0.1, blocks_per_year: 100 }).unwrap();
+        for validator in ["small", "large", "jailed"] {
+            chain.create_wallet(validator.to_string()).unwrap();
+            chain.mine_pending_transactions(validator.to_string()).unwrap();
+        }
+        chain.add_validator("small".to_string(), 10.0).unwrap();
+        chain.add_validator("large".to_string(), 30.0).unwrap();
+        chain.add_validator("jailed".to_string(), 20.0).unwrap();
+        chain.jail_validator("jailed").unwrap();
+        chain
+    }
+    
+    fn payouts_in(chain: &Blockchain, height: usize) -> Vec<(String, f64)> {
+        chain.chain[height].transactions.iter()
+            .filter(|tx| tx.sender == STAKING_MINTER)
+            .map(|tx| (tx.receiver.clone(), tx.amount))
+            .collect()
+    }
+    
+    #[test]
+    fn payouts_follow_stake_rate_and_uptime() {
+        let mut chain = chain_with_validators();
+        let before = chain.get_balance("jailed");
+        while chain.chain.len() as u64 <= 2 * EPOCH {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        
+        // Ставка эпохи 0.1 * 5 / 100 = 0.005; в первой эпохе валидаторы были в наборе 2 блока из 5
+        assert_eq!(payouts_in(&chain, 5), [("large".to_string(), 30.0 * 0.005 * 0.4), ("small".to_string(), 10.0 * 0.005 * 0.4)]);
+        let full_epoch = payouts_in(&chain, 10);
+        assert_eq!(full_epoch, [("large".to_string(), 0.15), ("small".to_string(), 0.05)]);
+        assert!((full_epoch[0].1 / full_epoch[1].1 - 3.0).abs() < 1e-12);
+        for height in (1..=2 * EPOCH).filter(|height| !height.is_multiple_of(EPOCH)) {
+            assert!(payouts_in(&chain, height as usize).is_empty());
+        }
+        
+        assert_eq!(chain.get_balance("jailed"), before);
+        assert!((chain.inflation_issued_before(11) - 0.2 * 1.4).abs() < 1e-12);
+    }
+    
+    #[test]
+    fn replay_reproduces_the_payouts() {
+        let mut chain = chain_with_validators();
+        for _ in 0..2 * EPOCH {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        assert!(chain.is_chain_valid());
+        let report = chain.rebuild_state_from_chain(true);
+        assert!(report.is_clean(), "{:?}", report.differences);
+    }
+}
@@ -0,0 +1,384 @@
+#![deny(clippy::indexing_slicing)]
+
+use crate::block::Block;
+use crate::blockchain::{validate_stored_block, Blockchain, EMPTY_CHAIN};
+use crate::errors::BlockchainError;
+use crate::hashing::{check_block_hash_algorithm, check_test_vectors, HashKind};
+use crate::signing::Keypair;
+use crate::transaction::SignatureVerification;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Имя файла с состоянием цепочки внутри каталога данных
+pub const CHAIN_FILE: &str = "chain.json";
+
+/// Имя файла с секретными ключами кошельков узла внутри каталога данных. Секретные ключи не входят
+/// в состояние цепочки, снимки и прочее, что сериализуется из `Blockchain`: `save` пишет их сюда,
+/// на Unix с доступом только для владельца, а `load` возвращает кошелькам.
+pub const KEYSTORE_FILE: &str = "keystore.json";
+
+/// Путь к файлу состояния цепочки в каталоге данных
+pub fn chain_file(data_dir: &Path) -> PathBuf {
+    data_dir.join(CHAIN_FILE)
+}
+
+/// Путь к хранилищу ключей в каталоге данных
+pub fn keystore_file(data_dir: &Path) -> PathBuf {
+    data_dir.join(KEYSTORE_FILE)
+}
+
+/// Ключевые пары кошельков по адресам из хранилища ключей; нет файла — нет ключей
+fn read_keystore(data_dir: &Path) -> Result<BTreeMap<String, Keypair>, BlockchainError> {
+    let path = keystore_file(data_dir);
+    match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| storage_error(&path, e)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(storage_error(&path, e)),
+    }
+}
+
+/// Пишет файл через временный и переименование; `private` закрывает его от других пользователей
+fn write_replacing(path: &Path, bytes: &[u8], private: bool) -> Result<(), BlockchainError> {
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, bytes).map_err(|e| storage_error(&tmp_path, e))?;
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600)).map_err(|e| storage_error(&tmp_path, e))?;
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    fs::rename(&tmp_path, path).map_err(|e| storage_error(path, e))
+}
+
+/// Проверяет, есть ли в каталоге сохраненная цепочка
+pub fn is_initialized(data_dir: &Path) -> bool {
+    chain_file(data_dir).is_file()
+}
+
+/// Сколько блоков загружено и проверено к моменту отчета
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadProgress {
+    pub blocks_loaded: u64,
+}
+
+fn storage_error(path: &Path, error: impl std::fmt::Display) -> BlockchainError {
+    BlockchainError::Storage(format!("{}: {}", path.display(), error))
+}
+
+/// Состояние потоковой загрузки: первая ошибка проверки сохраняется здесь, а десериализатору
+/// отдается только ее текст, чтобы вызывающий получил исходный `BlockchainError`
+struct StreamingLoad<F> {
+    verification: SignatureVerification,
+    /// Алгоритм цепочки; у файлов без поля `hash_algorithm` — SHA-256
+    hash_algorithm: HashKind,
+    /// Высота, до которой блоки могут нести прежние хеш-подписи; у файлов без поля — все блоки
+    legacy_signatures_until: Option<u64>,
+    progress_interval: u64,
+    on_progress: F,
+    error: Option<BlockchainError>,
+}
+
+/// Читает объект цепочки: поле `chain` проверяется поблочно, остальные поля собираются как есть
+struct ChainFileSeed<'a, F>(&'a mut StreamingLoad<F>);
+
+/// Читает массив блоков, проверяя каждый блок против предыдущего сразу после декодирования
+struct BlocksSeed<'a, F>(&'a mut StreamingLoad<F>);
+
+impl<'de, F: FnMut(LoadProgress)> DeserializeSeed<'de> for ChainFileSeed<'_, F> {
+    type Value = Blockchain;
+    
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Blockchain, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: FnMut(LoadProgress)> Visitor<'de> for ChainFileSeed<'_, F> {
+    type Value = Blockchain;
+    
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a blockchain object")
+    }
+    
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Blockchain, A::Error> {
+        let mut fields = Map::new();
+        let mut blocks = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "chain" {
+                blocks = Some(map.next_value_seed(BlocksSeed(&mut *self.0))?);
+            } else if key == "hash_algorithm" {
+                // Поле записано перед блоками: их хеши проверяются уже алгоритмом цепочки
+                let algorithm = map.next_value::<HashKind>()?;
+                if let Err(e) = check_test_vectors(algorithm) {
+                    let message = e.to_string();
+                    self.0.error = Some(e);
+                    return Err(de::Error::custom(message));
+                }
+                self.0.hash_algorithm = algorithm;
+                fields.insert(key, serde_json::to_value(algorithm).map_err(de::Error::custom)?);
+            } else if key == "legacy_signatures_until" {
+                let until = map.next_value::<Option<u64>>()?;
+                self.0.legacy_signatures_until = until;
+                fields.insert(key, serde_json::to_value(until).map_err(de::Error::custom)?);
+            } else {
+                fields.insert(key, map.next_value::<Value>()?);
+            }
+        }
+        
+        let blocks = blocks.ok_or_else(|| de::Error::missing_field("chain"))?;
+        // Поля разбираются без уже прочитанных блоков; генезис нужен, чтобы пройти проверку на пустую цепочку
+        let genesis = blocks.first().ok_or_else(|| de::Error::custom(EMPTY_CHAIN))?;
+        fields.insert(String::from("chain"), Value::Array(vec![serde_json::to_value(genesis).map_err(de::Error::custom)?]));
+        let mut chain = Blockchain::deserialize(Value::Object(fields)).map_err(de::Error::custom)?;
+        chain.chain = blocks;
+        Ok(chain)
+    }
+}
+
+impl<'de, F: FnMut(LoadProgress)> DeserializeSeed<'de> for BlocksSeed<'_, F> {
+    type Value = Vec<Block>;
+    
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Block>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(LoadProgress)> Visitor<'de> for BlocksSeed<'_, F> {
+    type Value = Vec<Block>;
+    
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of blocks")
+    }
+    
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Block>, A::Error> {
+        let load = self.0;
+        let mut blocks: Vec<Block> = Vec::new();
+        while let Some(block) = seq.next_element::<Block>()? {
+            let checked = match blocks.last() {
+                Some(previous) => {
+                    let legacy = load.legacy_signatures_until.is_none_or(|until| block.index <= until);
+                    validate_stored_block(previous, &block, load.verification, legacy)
+                },
+                // Неверный хеш генезиса значит, что цепочка посчитана другим алгоритмом
+                None if block.hash != block.calculate_hash() => {
+                    Err(BlockchainError::HashAlgorithmMismatch { algorithm: load.hash_algorithm.name(), height: block.index })
+                },
+                None => check_block_hash_algorithm(&block, load.hash_algorithm),
+            };
+            if let Err(e) = checked {
+                let message = e.to_string();
+                load.error = Some(e);
+                return Err(de::Error::custom(message));
+            }
+            blocks.push(block);
+            
+            let loaded = blocks.len() as u64;
+            if load.progress_interval > 0 && loaded.is_multiple_of(load.progress_interval) {
+                (load.on_progress)(LoadProgress { blocks_loaded: loaded });
+            }
+        }
+        
+        Ok(blocks)
+    }
+}
+
+impl Blockchain {
+    /// Загружает блокчейн из каталога данных
+    pub fn load(data_dir: &Path) -> Result<Blockchain, BlockchainError> {
+        let path = chain_file(data_dir);
+        let bytes = fs::read(&path).map_err(|e| storage_error(&path, e))?;
+        let mut chain: Blockchain = serde_json::from_slice(&bytes).map_err(|e| storage_error(&path, e))?;
+        chain.check_hash_algorithm()?;
+        chain.attach_keystore(data_dir)?;
+        // Файл без поля записан до подписей ed25519, и старые хеш-подписи остаются только в его блоках
+        chain.legacy_signatures_until.get_or_insert(chain.get_latest_block().index);
+        chain.rebuild_address_index();
+        chain.rebuild_wallet_histories();
+        chain.rebuild_fee_history();
+        chain.rebuild_utxo_set();
+        Ok(chain)
+    }
+    
+    /// Загружает блокчейн, проверяя каждый блок сразу после чтения из файла, вместо отдельного
+    /// прохода `validate_chain`; останавливается на первом неверном блоке. Каждые
+    /// `progress_interval` блоков вызывает `on_progress` (0 — без отчетов).
+    pub fn load_validated<F: FnMut(LoadProgress)>(
+        data_dir: &Path,
+        verification: SignatureVerification,
+        progress_interval: u64,
+        on_progress: F,
+    ) -> Result<Blockchain, BlockchainError> {
+        let path = chain_file(data_dir);
+        let file = File::open(&path).map_err(|e| storage_error(&path, e))?;
+        // Файлы без поля `hash_algorithm` записаны до выбора алгоритма и посчитаны SHA-256
+        let mut load = StreamingLoad {
+            verification,
+            hash_algorithm: HashKind::default(),
+            legacy_signatures_until: None,
+            progress_interval,
+            on_progress,
+            error: None,
+        };
+        
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+        let result = ChainFileSeed(&mut load).deserialize(&mut deserializer)
+            .and_then(|chain| deserializer.end().map(|()| chain));
+        let mut chain = match result {
+            Ok(chain) => chain,
+            Err(e) => return Err(load.error.take().unwrap_or_else(|| storage_error(&path, e))),
+        };
+        chain.check_hash_algorithm()?;
+        chain.attach_keystore(data_dir)?;
+        chain.legacy_signatures_until.get_or_insert(chain.get_latest_block().index);
+        
+        chain.rebuild_address_index();
+        chain.rebuild_wallet_histories();
+        chain.rebuild_fee_history();
+        chain.rebuild_utxo_set();
+        Ok(chain)
+    }
+    
+    /// Сохраняет блокчейн в каталог данных; файл заменяется целиком через переименование. Ключевые
+    /// пары кошельков дописываются в хранилище ключей, где остаются и ключи удаленных кошельков.
+    pub fn save(&self, data_dir: &Path) -> Result<(), BlockchainError> {
+        fs::create_dir_all(data_dir).map_err(|e| storage_error(data_dir, e))?;
+        
+        let mut keystore = read_keystore(data_dir)?;
+        for wallet in self.wallets.values() {
+            if let Some(keypair) = wallet.keypair() {
+                keystore.insert(wallet.address.clone(), keypair.clone());
+            }
+        }
+        if !keystore.is_empty() {
+            let path = keystore_file(data_dir);
+            let bytes = serde_json::to_vec_pretty(&keystore).map_err(|e| storage_error(&path, e))?;
+            write_replacing(&path, &bytes, true)?;
+        }
+        
+        let path = chain_file(data_dir);
+        let bytes = serde_json::to_vec(self).map_err(|e| storage_error(&path, e))?;
+        write_replacing(&path, &bytes, false)
+    }
+    
+    /// Отдает кошелькам их ключевые пары из хранилища ключей
+    fn attach_keystore(&mut self, data_dir: &Path) -> Result<(), BlockchainError> {
+        for (address, keypair) in read_keystore(data_dir)? {
+            if let Some(wallet) = self.wallets.get_mut(&address) {
+                wallet.set_keypair(keypair);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GENESIS_TIMESTAMP;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    
+    const FIXTURE_BLOCKS: u64 = 2_000;
+    
+    /// Каталог с цепочкой из `FIXTURE_BLOCKS` блоков, по одной награде в каждом; блоки
+    /// собираются напрямую, без пула и выбора транзакций, чтобы фикстура строилась быстро
+    fn fixture(name: &str, corrupt_at: Option<u64>) -> PathBuf {
+        let mut chain = ChainFixture::chain(1, 1.0, ConsensusAlgorithm::ProofOfWork);
+        for index in 1..FIXTURE_BLOCKS {
+            let previous = chain.get_latest_block().hash.clone();
+            let reward = Transaction::new("BLOCKCHAIN_REWARD".to_string(), "miner".to_string(), 1.0, TransactionType::Transfer);
+            let mut block = Block::new_at(index, vec![reward], previous, 1, GENESIS_TIMESTAMP + 60 * index as i64);
+            block.mine_block();
+            chain.chain.push(block);
+        }
+        if let Some(height) = corrupt_at {
+            // Тело меняется без пересчета корня Меркла: хеш заголовка остается верным
+            let block = chain.chain.get_mut(height as usize).unwrap();
+            block.transactions.get_mut(0).unwrap().amount = 1_000.0;
+        }
+        
+        let dir = std::env::temp_dir().join(format!("hellochain-storage-{}-{}", name, std::process::id()));
+        chain.save(&dir).unwrap();
+        dir
+    }
+    
+    fn load(dir: &Path) -> (Result<Blockchain, BlockchainError>, Vec<u64>) {
+        let mut reports = Vec::new();
+        let result = Blockchain::load_validated(dir, SignatureVerification::Serial, 100, |progress| {
+            reports.push(progress.blocks_loaded);
+        });
+        (result, reports)
+    }
+    
+    #[test]
+    fn valid_fixture_loads_with_progress_reports() {
+        let dir = fixture("valid", None);
+        let (result, reports) = load(&dir);
+        let _ = fs::remove_dir_all(&dir);
+        
+        let chain = result.unwrap();
+        assert_eq!(chain.chain.len() as u64, FIXTURE_BLOCKS);
+        assert!(chain.is_chain_valid());
+        assert_eq!(reports, (1..=FIXTURE_BLOCKS / 100).map(|n| n * 100).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    fn file_without_the_signature_cutoff_keeps_its_own_blocks_legacy() {
+        let dir = fixture("cutoff", None);
+        let path = chain_file(&dir);
+        let mut file: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(file.get("legacy_signatures_until"), Some(&Value::from(0)));
+        file.as_object_mut().unwrap().remove("legacy_signatures_until");
+        fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+        
+        let plain = Blockchain::load(&dir);
+        let (streamed, _) = load(&dir);
+        let _ = fs::remove_dir_all(&dir);
+        
+        // Старые хеш-подписи остаются допустимы только в блоках, уже записанных в файл
+        assert_eq!(plain.unwrap().legacy_signatures_until, Some(FIXTURE_BLOCKS - 1));
+        assert_eq!(streamed.unwrap().legacy_signatures_until, Some(FIXTURE_BLOCKS - 1));
+    }
+    
+    #[test]
+    fn corrupted_block_stops_the_load_at_its_height() {
+        let dir = fixture("corrupt", Some(1_500));
+        let (result, reports) = load(&dir);
+        
+        let error = result.err().unwrap();
+        assert!(matches!(&error, BlockchainError::InvalidBlock(reason) if reason == "Wrong Merkle root in block # 1500"), "{}", error);
+        // Блоки после неверного не читались
+        assert_eq!(reports.last(), Some(&1_500));
+        
+        // Прерванная загрузка ничего не оставляет: исправленный файл загружается заново
+        fs::remove_dir_all(&dir).unwrap();
+        let dir = fixture("corrupt", None);
+        let (result, _) = load(&dir);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(result.unwrap().chain.len() as u64, FIXTURE_BLOCKS);
+    }
+    
+    #[test]
+    fn chain_file_without_blocks_is_rejected_by_both_loaders() {
+        let dir = std::env::temp_dir().join(format!("hellochain-storage-empty-{}", std::process::id()));
+        Blockchain::new(1, 1.0, ConsensusAlgorithm::ProofOfWork).save(&dir).unwrap();
+        let mut file: serde_json::Value = serde_json::from_slice(&fs::read(chain_file(&dir)).unwrap()).unwrap();
+        *file.get_mut("chain").unwrap() = serde_json::json!([]);
+        fs::write(chain_file(&dir), serde_json::to_vec(&file).unwrap()).unwrap();
+        
+        let loaded = Blockchain::load(&dir);
+        let (validated, _) = load(&dir);
+        let _ = fs::remove_dir_all(&dir);
+        for result in [loaded, validated] {
+            assert!(matches!(&result, Err(BlockchainError::Storage(reason)) if reason.contains(EMPTY_CHAIN)), "{:?}", result.err());
+        }
+        assert!(serde_json::from_value::<Blockchain>(file).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::events::ChainEvent;
+use crate::shared::SharedBlockchain;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// Размер очереди исходящих событий на одного клиента
+pub const CLIENT_QUEUE_CAPACITY: usize = 256;
+
+/// Канал подписки WebSocket
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Channel {
+    NewBlocks,
+    PendingTransactions,
+    Address(String),
+}
+
+impl Channel {
+    /// Разбирает имя канала: `newBlocks`, `pendingTransactions` или `address:{addr}`
+    pub fn parse(name: &str) -> Option<Channel> {
+        match name {
+            "newBlocks" => Some(Channel::NewBlocks),
+            "pendingTransactions" => Some(Channel::PendingTransactions),
+            _ => name.strip_prefix("address:")
+                .filter(|address| !address.is_empty())
+                .map(|address| Channel::Address(address.to_string())),
+        }
+    }
+    
+    /// Возвращает имя канала в том виде, в котором его присылает клиент
+    pub fn name(&self) -> String {
+        match self {
+            Channel::NewBlocks => String::from("newBlocks"),
+            Channel::PendingTransactions => String::from("pendingTransactions"),
+            Channel::Address(address) => format!("address:{}", address),
+        }
+    }
+}
+
+/// Команда клиента: подписаться или отписаться от каналов
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientCommand {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+}
+
+/// Раскладывает событие цепочки на сообщения для каналов, на которые подписан клиент
+pub fn messages_for(event: &ChainEvent, channels: &HashSet<Channel>) -> Vec<Value> {
+    let mut messages = Vec::new();
+    
+    match event {
+        ChainEvent::BlockAdded { block } => {
+            if channels.contains(&Channel::NewBlocks) {
+                messages.push(json!({ "channel": Channel::NewBlocks.name(), "block": block }));
+            }
+            
+            for tx in &block.transactions {
+                let mut parties = vec![&tx.sender];
+                if tx.receiver != tx.sender {
+                    parties.push(&tx.receiver);
+                }
+                
+                for party in parties {
+                    let channel = Channel::Address(party.clone());
+                    if channels.contains(&channel) {
+                        messages.push(json!({
+                            "channel": channel.name(),
+                            "block_index": block.index,
+                            "block_hash": block.hash,
+                            "transaction": tx,
+                        }));
+                    }
+                }
+            }
+        },
+        ChainEvent::TransactionQueued { transaction } => {
+            if channels.contains(&Channel::PendingTransactions) {
+                messages.push(json!({ "channel": Channel::PendingTransactions.name(), "transaction": transaction }));
+            }
+        },
+        ChainEvent::SyncProgress { .. } | ChainEvent::ValidatorSlashed { .. } | ChainEvent::AccountReaped { .. }
+        | ChainEvent::ForkDetected { .. } | ChainEvent::ForkResolved { .. } => {},
+    }
+    
+    messages
+}
+
+/// Создает маршрутизатор с WebSocket-эндпоинтом `/ws`
+pub fn router(chain: SharedBlockchain) -> Router {
+    Router::new()
+        .route("/ws", get(upgrade))
+        .with_state(chain)
+}
+
+async fn upgrade(State(chain): State<SharedBlockchain>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, chain))
+}
+
+async fn handle_socket(mut socket: WebSocket, chain: SharedBlockchain) {
+    let channels: Arc<Mutex<HashSet<Channel>>> = Arc::new(Mutex::new(HashSet::new()));
+    let (sender, mut receiver) = mpsc::channel::<Value>(CLIENT_QUEUE_CAPACITY);
+    
+    let hook_channels = channels.clone();
+    let hook_id = chain.write().add_event_hook(Box::new(move |event| {
+        let subscribed = hook_channels.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for message in messages_for(event, &subscribed) {
+            // Переполненная очередь означает медленного клиента: отписываем его, а не ждём
+            if let Err(TrySendError::Full(_) | TrySendError::Closed(_)) = sender.try_send(message) {
+                return false;
+            }
+        }
+        true
+    }));
+    
+    loop {
+        tokio::select! {
+            outgoing = receiver.recv() => {
+                let Some(message) = outgoing else { break };
+                if socket.send(Message::Text(message.to_string().into())).await.is_err() {
+                    break;
+                }
+            },
+            incoming = socket.recv() => {
+                match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        let reply = apply_command(&channels, &text);
+                        if socket.send(Message::Text(reply.to_string().into())).await.is_err() {
+                            break;
+                        }
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {},
+                }
+            },
+        }
+    }
+    
+    chain.write().remove_event_hook(hook_id);
+}
+
+fn apply_command(channels: &Mutex<HashSet<Channel>>, text: &str) -> Value {
+    let command: ClientCommand = match serde_json::from_str(text) {
+        Ok(command) => command,
+        Err(e) => return json!({ "error": format!("Invalid command: {}", e) }),
+    };
+    
+    let (names, subscribe) = match command {
+        ClientCommand::Subscribe(names) => (names, true),
+        ClientCommand::Unsubscribe(names) => (names, false),
+    };
+    
+    let mut parsed = Vec::new();
+    for name in &names {
+        match Channel::parse(name) {
+            Some(channel) => parsed.push(channel),
+            None => return json!({ "error": format!("Unknown channel {}", name) }),
+        }
+    }
+    
+    let mut subscribed = channels.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for channel in parsed {
+        if subscribe {
+            subscribed.insert(channel);
+        } else {
+            subscribed.remove(&channel);
+        }
+    }
+    
+    let mut active: Vec<String> = subscribed.iter().map(Channel::name).collect();
+    active.sort();
+    json!({ "subscribed": active })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    use std::net::{SocketAddr, TcpStream};
+    use tokio::runtime::Runtime;
+    use tungstenite::WebSocket as Client;
+    
+    fn start_server(chain: SharedBlockchain) -> (Runtime, SocketAddr) {
+        let runtime = Runtime::new().unwrap();
+        let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(async move { axum::serve(listener, router(chain)).await });
+        (runtime, addr)
+    }
+    
+    /// Подключается и подписывается на каналы; после ответа сервера события уже доставляются
+    fn subscribe(addr: SocketAddr, channels: &[&str]) -> Client<TcpStream> {
+        let stream = TcpStream::connect(addr).unwrap();
+        let (mut client, _) = tungstenite::client(format!("ws://{}/ws", addr), stream).unwrap();
+        client.send(json!({ "subscribe": channels }).to_string().into()).unwrap();
+        assert!(next(&mut client).get("subscribed").is_some());
+        client
+    }
+    
+    fn next(client: &mut Client<TcpStream>) -> Value {
+        serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap()
+    }
+    
+    /// Читает `count` событий и проверяет, что до ответа на пустую команду лишних не пришло
+    fn events(client: &mut Client<TcpStream>, count: usize) -> Vec<Value> {
+        let events: Vec<Value> = (0..count).map(|_| next(client)).collect();
+        client.send(json!({ "subscribe": [] }).to_string().into()).unwrap();
+        assert!(next(client).get("subscribed").is_some());
+        events
+    }
+    
+    #[test]
+    fn clients_receive_only_their_channels_in_order() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        
+        let shared = SharedBlockchain::new(chain);
+        let (_runtime, addr) = start_server(shared.clone());
+        let mut blocks = subscribe(addr, &["newBlocks"]);
+        let mut bob = subscribe(addr, &["pendingTransactions", "address:bob"]);
+        
+        shared.add_transaction(tx.clone()).unwrap();
+        shared.write().mine_pending_transactions("alice".to_string()).unwrap();
+        let block = shared.read().get_latest_block().clone();
+        
+        assert_eq!(events(&mut blocks, 1), vec![json!({ "channel": "newBlocks", "block": block })]);
+        assert_eq!(events(&mut bob, 2), vec![
+            json!({ "channel": "pendingTransactions", "transaction": tx }),
+            json!({ "channel": "address:bob", "block_index": 2, "block_hash": block.hash, "transaction": tx }),
+        ]);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::blockchain::Blockchain;
+use crate::bridge::BRIDGE_MINTER;
+use crate::fee_market::{burned_fees, miner_fees};
+use crate::mint::MINT_AUTHORITY;
+use crate::freeze::FREEZE_AUTHORITY;
+use crate::sponsorship::fee_bearer;
+use crate::staking::STAKING_MINTER;
+use crate::transaction::Transaction;
+use crate::treasury::TREASURY_MINTER;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Отправитель награды за блок; переведенное на этот адрес сгорает
+const REWARD_SENDER: &str = "BLOCKCHAIN_REWARD";
+
+/// Допустимое расхождение сумм из-за округления
+pub(crate) const SUPPLY_TOLERANCE: f64 = 1e-6;
+
+/// Системные отправители: их транзакции выпускают средства, а не списывают их с кошелька, поэтому
+/// ни пул, ни блоки, ни проигрывание цепочки их не списывают
+pub(crate) fn is_system_sender(sender: &str) -> bool {
+    [REWARD_SENDER, BRIDGE_MINTER, MINT_AUTHORITY, TREASURY_MINTER, STAKING_MINTER, FREEZE_AUTHORITY].contains(&sender)
+}
+
+/// Затрагивает ли транзакция баланс адреса как отправителя, спонсора или получателя
+pub(crate) fn involves(tx: &Transaction, address: &str) -> bool {
+    tx.sender == address || tx.receiver == address || fee_bearer(tx) == address
+}
+
+/// Изменение баланса адреса от транзакции блока, как его считает `expected_balances_at`
+pub(crate) fn balance_effect(tx: &Transaction, address: &str) -> f64 {
+    let mut effect = 0.0;
+    if !is_system_sender(&tx.sender) {
+        if tx.sender == address {
+            effect -= tx.amount;
+        }
+        if fee_bearer(tx) == address {
+            effect -= tx.fee;
+        }
+    }
+    if tx.receiver == address && tx.receiver != REWARD_SENDER {
+        effect += tx.amount;
+    }
+    effect
+}
+
+/// Итог сверки: все кошельки сходятся с цепочкой
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SupplyAudit {
+    /// Сколько монет должно существовать по `circulating_supply`
+    pub expected_supply: f64,
+    /// Сумма основных балансов, стейкинга и накопленных наград всех кошельков
+    pub wallet_total: f64,
+    /// Занято ожидающими транзакциями: спишется с отправителей, когда они попадут в блок
+    pub in_flight: f64,
+    pub addresses: usize,
+}
+
+/// Кошелек, баланс которого не сходится с цепочкой и пополнениями в обход блоков
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SupplyDiscrepancy {
+    pub address: String,
+    pub expected: f64,
+    /// Баланс со стейкингом, накопленными наградами и списанными в пул суммами
+    pub actual: f64,
+}
+
+impl SupplyDiscrepancy {
+    /// На сколько на кошельке больше, чем должно быть
+    pub fn difference(&self) -> f64 {
+        self.actual - self.expected
+    }
+}
+
+impl Blockchain {
+    /// Учитывает изменение баланса в обход блоков, чтобы сверка его не считала расхождением
+    pub(crate) fn record_off_chain_credit(&mut self, address: &str, amount: f64) {
+        *self.off_chain_credits.entry(address.to_string()).or_insert(0.0) += amount;
+    }
+    
+    /// Сколько монет существует: начальные распределения генезиса, выпуск наградами, транзакциями
+    /// `Mint`, стейкингом и мостом за вычетом сожженного
+    pub fn circulating_supply(&self) -> f64 {
+        let credited: f64 = self.off_chain_credits.values().sum();
+        let issued: f64 = self.chain.iter()
+            .map(|block| {
+                let minted: f64 = block.transactions.iter().filter(|tx| is_system_sender(&tx.sender)).map(|tx| tx.amount).sum();
+                let burned: f64 = block.transactions.iter().filter(|tx| tx.receiver == REWARD_SENDER).map(|tx| tx.amount).sum();
+                // Награда майнеру включает его часть комиссий, а они не выпуск, а перевод
+                minted - miner_fees(&block.transactions, block.base_fee) - burned_fees(&block.transactions, block.base_fee) - burned
+            })
+            .sum();
+        credited + issued
+    }
+    
+    /// Балансы, которые должны быть у адресов по цепочке и пополнениям в обход блоков,
+    /// без учета ожидающих транзакций
+    pub(crate) fn expected_balances(&self) -> BTreeMap<String, f64> {
+        self.expected_balances_at(self.get_latest_block().index)
+    }
+    
+    /// То же после блока `height`: пополнения в обход блоков считаются сделанными в генезисе
+    pub(crate) fn expected_balances_at(&self, height: u64) -> BTreeMap<String, f64> {
+        let mut balances = self.off_chain_credits.clone();
+        for tx in self.chain.iter().take_while(|block| block.index <= height).flat_map(|block| &block.transactions) {
+            if !is_system_sender(&tx.sender) {
+                *balances.entry(tx.sender.clone()).or_insert(0.0) -= tx.amount;
+                *balances.entry(fee_bearer(tx).to_string()).or_insert(0.0) -= tx.fee;
+            }
+            if tx.receiver != REWARD_SENDER {
+                *balances.entry(tx.receiver.clone()).or_insert(0.0) += tx.amount;
+            }
+        }
+        balances
+    }
+    
+    /// Сверяет балансы всех кошельков с цепочкой: у каждого адреса баланс и стейкинг должны совпасть
+    /// с тем, что выводится из блоков и пополнений в обход них. Пул балансы не меняет.
+    pub fn audit_supply(&self) -> Result<SupplyAudit, Vec<SupplyDiscrepancy>> {
+        let in_flight: f64 = self.mempool.lock().transactions.iter()
+            .filter(|tx| !is_system_sender(&tx.sender))
+            .map(|tx| tx.amount + tx.fee)
+            .sum();
+        
+        let mut actual: BTreeMap<&str, f64> = BTreeMap::new();
+        for (address, wallet) in &self.wallets {
+            *actual.entry(address.as_str()).or_insert(0.0) += wallet.balance + wallet.staking_balance + wallet.claimable_rewards;
+        }
+        
+        let expected = self.expected_balances();
+        let mut addresses: Vec<&str> = expected.keys().map(String::as_str).chain(actual.keys().copied()).collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+        
+        let discrepancies: Vec<SupplyDiscrepancy> = addresses.iter()
+            .map(|address| SupplyDiscrepancy {
+                address: address.to_string(),
+                expected: expected.get(*address).copied().unwrap_or(0.0),
+                actual: actual.get(address).copied().unwrap_or(0.0),
+            })
+            .filter(|discrepancy| discrepancy.difference().abs() > SUPPLY_TOLERANCE)
+            .collect();
+        if !discrepancies.is_empty() {
+            return Err(discrepancies);
+        }
+        
+        Ok(SupplyAudit {
+            expected_supply: self.circulating_supply(),
+            wallet_total: self.wallets.values().map(|wallet| wallet.balance + wallet.staking_balance + wallet.claimable_rewards).sum(),
+            in_flight,
+            addresses: self.wallets.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    fn send(chain: &mut Blockchain, from: &str, to: &str, amount: f64) {
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(from));
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+    }
+    
+    /// Несколько блоков с переводами, пополнением в обход блоков, стейком и транзакцией в пуле
+    fn busy_chain() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.enable_minting("alice").unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        send(&mut chain, "alice", "bob", 30.0);
+        send(&mut chain, "alice", "carol", 5.0);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        // Пополнение в обход блоков тоже должно сходиться
+        #[allow(deprecated)]
+        chain.add_funds_to_wallet("bob", 50.0).unwrap();
+        chain.add_validator("bob".to_string(), 20.0).unwrap();
+        send(&mut chain, "bob", "alice", 12.0);
+        chain.mine_pending_transactions("bob".to_string()).unwrap();
+        send(&mut chain, "alice", "dave", 1.0);
+        chain
+    }
+    
+    #[test]
+    fn busy_chain_audits_clean() {
+        let chain = busy_chain();
+        let audit = chain.audit_supply().unwrap();
+        assert!(audit.in_flight > 1.0);
+        assert!((audit.wallet_total - audit.expected_supply).abs() < SUPPLY_TOLERANCE, "{:?}", audit);
+        assert!((audit.expected_supply - (3.0 * 100.0 + 50.0)).abs() < SUPPLY_TOLERANCE);
+    }
+    
+    #[test]
+    fn corrupted_balance_is_reported_by_address() {
+        let mut chain = busy_chain();
+        chain.wallets.get_mut("bob").unwrap().balance += 7.5;
+        
+        let discrepancies = chain.audit_supply().unwrap_err();
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].address, "bob");
+        assert!((discrepancies[0].difference() - 7.5).abs() < SUPPLY_TOLERANCE);
+    }
+}
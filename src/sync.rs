@@ -0,0 +1,466 @@
+use crate::block::{Block, BlockHeader};
+use crate::blockchain::{BlockAcceptance, Blockchain, ConsensusAlgorithm};
+use crate::events::ChainEvent;
+use crate::shared::SharedBlockchain;
+use std::collections::HashSet;
+use std::thread;
+use thiserror::Error;
+
+/// Ошибки синхронизации
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SyncError {
+    #[error("Peer {peer} is unavailable: {reason}")]
+    PeerUnavailable { peer: String, reason: String },
+    
+    #[error("Peer {peer} sent invalid headers: {reason}")]
+    InvalidHeaders { peer: String, reason: String },
+    
+    #[error("Peer {peer} sent a body that does not match its header at height {height}")]
+    BodyMismatch { peer: String, height: u64 },
+    
+    #[error("No peer could provide blocks for heights {from}..={to}")]
+    NoPeersForRange { from: u64, to: u64 },
+}
+
+/// Источник заголовков и тел блоков для синхронизации
+pub trait SyncPeer: Sync {
+    /// Имя пира для отчетов и ошибок
+    fn id(&self) -> String;
+    
+    /// Возвращает до `count` заголовков, начиная с высоты `from`
+    fn get_headers(&self, from: u64, count: u64) -> Result<Vec<BlockHeader>, SyncError>;
+    
+    /// Возвращает полные блоки с высоты `from` по `to` включительно
+    fn get_bodies(&self, from: u64, to: u64) -> Result<Vec<Block>, SyncError>;
+}
+
+/// Локальный блокчейн тоже может служить источником синхронизации (например, в одном процессе)
+impl SyncPeer for SharedBlockchain {
+    fn id(&self) -> String {
+        format!("local:{}", self.read().chain[0].hash)
+    }
+    
+    fn get_headers(&self, from: u64, count: u64) -> Result<Vec<BlockHeader>, SyncError> {
+        let chain = self.read();
+        Ok(chain.chain.iter()
+            .skip(from as usize)
+            .take(count as usize)
+            .map(Block::header)
+            .collect())
+    }
+    
+    fn get_bodies(&self, from: u64, to: u64) -> Result<Vec<Block>, SyncError> {
+        let chain = self.read();
+        Ok(chain.chain.iter()
+            .filter(|block| block.index >= from && block.index <= to)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Параметры синхронизации
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub header_batch: u64,
+    pub body_batch: u64,
+    pub max_parallel_batches: usize,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            header_batch: 2000,
+            body_batch: 100,
+            max_parallel_batches: 4,
+        }
+    }
+}
+
+/// Итог синхронизации
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub start_height: u64,
+    pub final_height: u64,
+    pub target_height: u64,
+    pub best_peer: Option<String>,
+    pub rejected_peers: Vec<(String, SyncError)>,
+}
+
+/// Проверяет, что заголовок продолжает родителя и запечатан по правилам консенсуса
+pub fn validate_header(parent: &BlockHeader, header: &BlockHeader, consensus: &ConsensusAlgorithm) -> Result<(), String> {
+    if header.index != parent.index + 1 {
+        return Err(format!("height {} does not follow {}", header.index, parent.index));
+    }
+    if header.previous_hash != parent.hash {
+        return Err(format!("header {} does not link to its parent", header.index));
+    }
+    if header.hash_algorithm != parent.hash_algorithm {
+        return Err(format!("header {} is hashed with {}, its parent with {}", header.index, header.hash_algorithm.name(), parent.hash_algorithm.name()));
+    }
+    if header.hash != header.calculate_hash() {
+        return Err(format!("wrong hash of header {}", header.index));
+    }
+    
+    match consensus {
+        ConsensusAlgorithm::ProofOfWork => {
+            if !header.meets_difficulty() {
+                return Err(format!("header {} does not meet its difficulty", header.index));
+            }
+        },
+        ConsensusAlgorithm::ProofOfStake | ConsensusAlgorithm::DelegatedProofOfStake => {
+            if header.validator.is_none() {
+                return Err(format!("header {} has no validator", header.index));
+            }
+        },
+    }
+    
+    Ok(())
+}
+
+/// Суммарная работа цепочки: для PoW растет со сложностью, для PoS каждый блок весит одинаково
+pub fn header_work(header: &BlockHeader, consensus: &ConsensusAlgorithm) -> u128 {
+    match consensus {
+        ConsensusAlgorithm::ProofOfWork => header.difficulty_as_work(),
+        _ => 1,
+    }
+}
+
+impl Blockchain {
+    /// Возвращает суммарную работу всех блоков цепочки
+    pub fn cumulative_work(&self) -> u128 {
+        self.chain.iter()
+            .skip(1)
+            .map(|block| match self.consensus_algorithm {
+                ConsensusAlgorithm::ProofOfWork => block.difficulty_as_work(),
+                _ => 1,
+            })
+            .fold(0u128, u128::saturating_add)
+    }
+}
+
+/// Цепочка заголовков, полученная от одного пира
+struct HeaderChain<'a> {
+    peer: &'a dyn SyncPeer,
+    headers: Vec<BlockHeader>,
+    work: u128,
+}
+
+/// Синхронизирует цепочку с пирами: сначала заголовки, затем тела блоков параллельными пакетами
+pub fn sync_chain(chain: &SharedBlockchain, peers: &[&dyn SyncPeer], options: &SyncOptions) -> SyncReport {
+    let tip = chain.read().get_latest_block().header();
+    
+    let mut report = SyncReport {
+        start_height: tip.index,
+        final_height: tip.index,
+        target_height: tip.index,
+        ..SyncReport::default()
+    };
+    
+    let mut candidates = Vec::new();
+    for peer in peers {
+        match download_headers(chain, *peer, &tip, options) {
+            Ok(headers) => candidates.push(headers),
+            Err(error) => report.rejected_peers.push((peer.id(), error)),
+        }
+    }
+    
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.work));
+    let Some(best) = candidates.first() else {
+        return report;
+    };
+    if best.headers.is_empty() {
+        return report;
+    }
+    
+    report.best_peer = Some(best.peer.id());
+    report.target_height = best.headers.last().map(|header| header.index).unwrap_or(tip.index);
+    
+    // Тела можно брать у всех пиров, чьи заголовки совпадают с лучшей цепочкой
+    let best_hashes: HashSet<&str> = best.headers.iter().map(|header| header.hash.as_str()).collect();
+    let mut sources: Vec<&dyn SyncPeer> = candidates.iter()
+        .filter(|candidate| candidate.headers.last().is_some_and(|header| best_hashes.contains(header.hash.as_str())))
+        .map(|candidate| candidate.peer)
+        .collect();
+    
+    download_bodies(chain, &best.headers, &mut sources, options, &mut report);
+    report.final_height = chain.read().get_latest_block().index;
+    report
+}
+
+fn download_headers<'a>(
+    chain: &SharedBlockchain,
+    peer: &'a dyn SyncPeer,
+    tip: &BlockHeader,
+    options: &SyncOptions,
+) -> Result<HeaderChain<'a>, SyncError> {
+    let (consensus, algorithm, genesis_hash) = {
+        let chain = chain.read();
+        (chain.consensus_algorithm.clone(), chain.hash_algorithm, chain.chain[0].hash.clone())
+    };
+    // Пир с другой хеш-функцией отличается уже генезисом; сообщаем об этом прямо, а не неверным хешем
+    if let Some(genesis) = peer.get_headers(0, 1)?.first() {
+        if genesis.hash_algorithm != algorithm || genesis.hash != genesis.calculate_hash() || genesis.hash != genesis_hash {
+            return Err(SyncError::InvalidHeaders {
+                peer: peer.id(),
+                reason: format!("genesis does not match ours under {}; the peer may use another hash algorithm", algorithm.name()),
+            });
+        }
+    }
+    let mut headers: Vec<BlockHeader> = Vec::new();
+    let mut work = 0u128;
+    let batch = options.header_batch.max(1);
+    
+    loop {
+        let parent = headers.last().unwrap_or(tip).clone();
+        let batch_headers = peer.get_headers(parent.index + 1, batch)?;
+        let received = batch_headers.len() as u64;
+        
+        let mut parent = parent;
+        for header in batch_headers {
+            validate_header(&parent, &header, &consensus)
+                .map_err(|reason| SyncError::InvalidHeaders { peer: peer.id(), reason })?;
+            work = work.saturating_add(header_work(&header, &consensus));
+            parent = header.clone();
+            headers.push(header);
+        }
+        
+        if received < batch {
+            break;
+        }
+    }
+    
+    Ok(HeaderChain { peer, headers, work })
+}
+
+fn download_bodies(
+    chain: &SharedBlockchain,
+    headers: &[BlockHeader],
+    sources: &mut Vec<&dyn SyncPeer>,
+    options: &SyncOptions,
+    report: &mut SyncReport,
+) {
+    let batch = options.body_batch.max(1) as usize;
+    let target = report.target_height;
+    
+    loop {
+        let applied = chain.read().get_latest_block().index;
+        let pending: Vec<&[BlockHeader]> = headers.iter()
+            .position(|header| header.index == applied + 1)
+            .map(|start| headers[start..].chunks(batch).take(options.max_parallel_batches.max(1)).collect())
+            .unwrap_or_default();
+        
+        if pending.is_empty() || sources.is_empty() {
+            return;
+        }
+        
+        // Пакеты раздаются пирам по кругу и скачиваются параллельно
+        let results: Vec<(usize, Result<Vec<Block>, SyncError>)> = thread::scope(|scope| {
+            let handles: Vec<_> = pending.iter().enumerate().map(|(i, range)| {
+                let source_index = i % sources.len();
+                let peer = sources[source_index];
+                let (from, to) = (range[0].index, range[range.len() - 1].index);
+                scope.spawn(move || (source_index, fetch_verified_bodies(peer, range, from, to)))
+            }).collect();
+            
+            handles.into_iter()
+                .map(|handle| handle.join().unwrap_or((0, Err(SyncError::NoPeersForRange { from: 0, to: 0 }))))
+                .collect()
+        });
+        
+        let mut failed_sources = HashSet::new();
+        let mut progressed = false;
+        for (source_index, result) in results {
+            match result {
+                Ok(blocks) if failed_sources.is_empty() => {
+                    let mut chain = chain.write();
+                    for block in blocks {
+                        match chain.accept_block(block) {
+                            Ok(BlockAcceptance::Appended) | Ok(BlockAcceptance::Duplicate) => progressed = true,
+                            _ => {
+                                failed_sources.insert(source_index);
+                                break;
+                            },
+                        }
+                    }
+                    let height = chain.get_latest_block().index;
+                    chain.events.emit(&ChainEvent::SyncProgress { height, target });
+                },
+                Ok(_) => {},
+                Err(error) => {
+                    report.rejected_peers.push((sources[source_index].id(), error));
+                    failed_sources.insert(source_index);
+                },
+            }
+        }
+        
+        // Пропавших или нечестных пиров убираем и продолжаем с последнего применённого блока
+        let mut index = 0;
+        sources.retain(|_| {
+            let keep = !failed_sources.contains(&index);
+            index += 1;
+            keep
+        });
+        
+        if !progressed && failed_sources.is_empty() {
+            return;
+        }
+    }
+}
+
+fn fetch_verified_bodies(peer: &dyn SyncPeer, headers: &[BlockHeader], from: u64, to: u64) -> Result<Vec<Block>, SyncError> {
+    let blocks = peer.get_bodies(from, to)?;
+    if blocks.len() != headers.len() {
+        return Err(SyncError::PeerUnavailable { peer: peer.id(), reason: format!("expected {} blocks, got {}", headers.len(), blocks.len()) });
+    }
+    
+    for (block, header) in blocks.iter().zip(headers) {
+        if block.header() != *header
+            || block.hash != block.calculate_hash()
+            || block.merkle_root != Block::calculate_merkle_root(block.hash_algorithm, &block.transactions) {
+            return Err(SyncError::BodyMismatch { peer: peer.id(), height: header.index });
+        }
+    }
+    
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{ChainFixture, FIXTURE_MINER};
+    use crate::transaction::{Transaction, TransactionType};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// Источник с `blocks` блоками и копия того же узла, не видевшая ни одного блока
+    fn source_and_fresh_node(blocks: u64) -> (SharedBlockchain, SharedBlockchain) {
+        let mut fixture = ChainFixture::new().build().unwrap();
+        let fresh: Blockchain = serde_json::from_str(&serde_json::to_string(&fixture.chain).unwrap()).unwrap();
+        for _ in 0..blocks {
+            fixture.mine_block().unwrap();
+        }
+        (SharedBlockchain::new(fixture.chain), SharedBlockchain::new(fresh))
+    }
+    
+    /// Пир, который пропадает после `bodies_served` запросов тел
+    struct DroppingPeer {
+        inner: SharedBlockchain,
+        bodies_served: AtomicUsize,
+    }
+    
+    impl SyncPeer for DroppingPeer {
+        fn id(&self) -> String {
+            "dropping".to_string()
+        }
+        
+        fn get_headers(&self, from: u64, count: u64) -> Result<Vec<BlockHeader>, SyncError> {
+            self.inner.get_headers(from, count)
+        }
+        
+        fn get_bodies(&self, from: u64, to: u64) -> Result<Vec<Block>, SyncError> {
+            if self.bodies_served.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_err() {
+                return Err(SyncError::PeerUnavailable { peer: self.id(), reason: "connection reset".to_string() });
+            }
+            self.inner.get_bodies(from, to)
+        }
+    }
+    
+    /// Пир, который подменяет ссылку на родителя в заголовке на высоте `forged_height`
+    struct ForgingPeer {
+        inner: SharedBlockchain,
+        forged_height: u64,
+    }
+    
+    impl SyncPeer for ForgingPeer {
+        fn id(&self) -> String {
+            "forging".to_string()
+        }
+        
+        fn get_headers(&self, from: u64, count: u64) -> Result<Vec<BlockHeader>, SyncError> {
+            let mut headers = self.inner.get_headers(from, count)?;
+            for header in headers.iter_mut().filter(|header| header.index == self.forged_height) {
+                header.previous_hash = "0".repeat(64);
+            }
+            Ok(headers)
+        }
+        
+        fn get_bodies(&self, from: u64, to: u64) -> Result<Vec<Block>, SyncError> {
+            self.inner.get_bodies(from, to)
+        }
+    }
+    
+    #[test]
+    fn lagging_node_syncs_across_a_transfer() {
+        let mut source = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        source.create_wallet("alice".to_string()).unwrap();
+        source.mine_pending_transactions("alice".to_string()).unwrap();
+        // Отстающий узел знает только первый блок
+        let lagging: Blockchain = serde_json::from_str(&serde_json::to_string(&source).unwrap()).unwrap();
+        
+        let alice = source.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(source.get_account_nonce("alice"));
+        tx.sign(&alice);
+        source.add_transaction(tx).unwrap();
+        source.mine_pending_transactions("miner".to_string()).unwrap();
+        source.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let (source, lagging) = (SharedBlockchain::new(source), SharedBlockchain::new(lagging));
+        let options = SyncOptions { header_batch: 2, body_batch: 1, max_parallel_batches: 2 };
+        let report = sync_chain(&lagging, &[&source], &options);
+        
+        assert_eq!((report.start_height, report.final_height, report.target_height), (1, 3, 3));
+        assert!(report.rejected_peers.is_empty());
+        let (source, lagging) = (source.read(), lagging.read());
+        assert_eq!(lagging.get_latest_block().hash, source.get_latest_block().hash);
+        assert_eq!(lagging.get_balance("bob"), 10.0);
+        for address in ["alice", "bob", "miner"] {
+            assert_eq!(lagging.get_balance(address), source.get_balance(address), "{}", address);
+        }
+    }
+    
+    #[test]
+    fn fresh_node_syncs_two_hundred_blocks() {
+        let (source, fresh) = source_and_fresh_node(200);
+        let options = SyncOptions { header_batch: 64, body_batch: 16, max_parallel_batches: 4 };
+        let report = sync_chain(&fresh, &[&source], &options);
+        
+        assert_eq!((report.start_height, report.final_height, report.target_height), (0, 200, 200));
+        assert!(report.rejected_peers.is_empty(), "{:?}", report.rejected_peers);
+        let (source, fresh) = (source.read(), fresh.read());
+        assert_eq!(fresh.get_latest_block().hash, source.get_latest_block().hash);
+        assert_eq!(fresh.get_balance(FIXTURE_MINER), source.get_balance(FIXTURE_MINER));
+        assert!(fresh.is_chain_valid());
+    }
+    
+    #[test]
+    fn peer_dropping_mid_sync_is_replaced_by_the_others() {
+        let (source, fresh) = source_and_fresh_node(40);
+        // Первый пакет пропадающий пир еще отдает, на втором обрывает соединение
+        let dropping = DroppingPeer { inner: source.clone(), bodies_served: AtomicUsize::new(1) };
+        let options = SyncOptions { header_batch: 64, body_batch: 5, max_parallel_batches: 2 };
+        let report = sync_chain(&fresh, &[&dropping, &source], &options);
+        
+        assert_eq!((report.final_height, report.target_height), (40, 40));
+        assert!(
+            matches!(report.rejected_peers.as_slice(), [(peer, SyncError::PeerUnavailable { .. })] if peer == "dropping"),
+            "{:?}", report.rejected_peers
+        );
+        assert_eq!(fresh.read().get_latest_block().hash, source.read().get_latest_block().hash);
+    }
+    
+    #[test]
+    fn forged_headers_are_rejected_and_another_peer_is_used() {
+        let (source, fresh) = source_and_fresh_node(20);
+        let forging = ForgingPeer { inner: source.clone(), forged_height: 7 };
+        let options = SyncOptions { header_batch: 8, body_batch: 4, max_parallel_batches: 2 };
+        let report = sync_chain(&fresh, &[&forging, &source], &options);
+        
+        assert!(
+            matches!(report.rejected_peers.as_slice(), [(peer, SyncError::InvalidHeaders { reason, .. })] if peer == "forging" && reason.contains("does not link")),
+            "{:?}", report.rejected_peers
+        );
+        assert_eq!(report.best_peer, Some(source.id()));
+        assert_eq!(report.final_height, 20);
+        assert_eq!(fresh.read().get_latest_block().hash, source.read().get_latest_block().hash);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::transaction::Hash;
+use serde::{Serialize, Deserialize};
+
+/// Версия блока, у которого сложность — число ведущих нулевых шестнадцатеричных цифр хеша
+pub const LEGACY_BLOCK_VERSION: u32 = 0;
+
+/// Версия блока с числовой целью в компактной записи
+pub const COMPACT_TARGET_VERSION: u32 = 1;
+
+/// Наибольшая сложность, при которой цель не нулевая; из 64 цифр хеша хоть одна должна остаться свободной
+pub const MAX_DIFFICULTY: usize = 63;
+
+/// Во сколько раз цель может измениться за одну подстройку
+pub const MAX_RETARGET_FACTOR: f64 = 4.0;
+
+/// 256-битная цель PoW в компактной записи: старший байт — длина цели в байтах,
+/// младшие три — ее старшие байты. Хеш подходит, если как число не больше цели.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactTarget(pub u32);
+
+impl CompactTarget {
+    /// Цель, равносильная требованию `difficulty` ведущих нулевых шестнадцатеричных цифр
+    pub fn from_difficulty(difficulty: usize) -> Self {
+        let bits = 256usize.saturating_sub(4 * difficulty) as u32;
+        if bits <= 24 {
+            return CompactTarget::encode(3, (1u32 << bits) - 1);
+        }
+        
+        let exponent = bits.div_ceil(8);
+        CompactTarget::encode(exponent, (1u32 << (bits - 8 * (exponent - 3))) - 1)
+    }
+    
+    fn encode(exponent: u32, mantissa: u32) -> Self {
+        CompactTarget((exponent << 24) | (mantissa & 0x00ff_ffff))
+    }
+    
+    fn exponent(self) -> u32 {
+        self.0 >> 24
+    }
+    
+    fn mantissa(self) -> u32 {
+        self.0 & 0x00ff_ffff
+    }
+    
+    /// Цель как 256-битное число в порядке big-endian
+    pub fn to_bytes(self) -> [u8; 32] {
+        let mut target = [0u8; 32];
+        let mantissa = self.mantissa().to_be_bytes();
+        for (i, byte) in mantissa[1..].iter().enumerate() {
+            // Байт мантиссы i стоит на позиции exponent - 3 + (2 - i) от младшего конца
+            let Some(position) = (self.exponent() + 2).checked_sub(i as u32 + 3) else { continue };
+            if let Some(index) = 31usize.checked_sub(position as usize) {
+                target[index] = *byte;
+            }
+        }
+        target
+    }
+    
+    pub fn is_met_by(self, hash: &Hash) -> bool {
+        *hash <= self.to_bytes()
+    }
+    
+    /// Ожидаемое число хешей для нахождения решения, примерно 2^256 / цель
+    pub fn work(self) -> u128 {
+        let mantissa = self.mantissa() as u128;
+        if mantissa == 0 {
+            return u128::MAX;
+        }
+        
+        let shift = 256 - 8 * (self.exponent() as i64 - 3);
+        if shift <= 127 {
+            return ((1u128 << shift.max(0)) / mantissa).max(1);
+        }
+        
+        let base = (1u128 << 127) / mantissa;
+        let extra = (shift - 127) as u32;
+        if base.leading_zeros() < extra { u128::MAX } else { base << extra }
+    }
+    
+    /// Сколько ведущих нулевых шестнадцатеричных цифр гарантирует цель
+    pub fn leading_zero_digits(self) -> usize {
+        let target = self.to_bytes();
+        let zero_bytes = target.iter().take_while(|byte| **byte == 0).count();
+        match target.get(zero_bytes) {
+            Some(byte) if *byte < 0x10 => 2 * zero_bytes + 1,
+            Some(_) => 2 * zero_bytes,
+            None => 64,
+        }
+    }
+    
+    /// Умножает цель на `factor`, ограничивая его `MAX_RETARGET_FACTOR`; больше цель — проще майнинг
+    pub fn scale(self, factor: f64) -> Self {
+        let factor = if factor.is_finite() { factor.clamp(1.0 / MAX_RETARGET_FACTOR, MAX_RETARGET_FACTOR) } else { 1.0 };
+        let mut exponent = self.exponent();
+        let mut mantissa = (self.mantissa() as f64 * factor).round().max(1.0) as u64;
+        
+        while mantissa > 0x00ff_ffff {
+            if exponent >= 32 {
+                return CompactTarget::encode(32, 0x00ff_ffff);
+            }
+            mantissa >>= 8;
+            exponent += 1;
+        }
+        while mantissa < 0x0000_8000 && exponent > 3 {
+            mantissa <<= 8;
+            exponent -= 1;
+        }
+        CompactTarget::encode(exponent, mantissa as u32)
+    }
+}
+
+/// Новая цель по фактическому и желаемому времени между блоками за одно и то же число блоков
+pub fn retarget(target: CompactTarget, actual_seconds: f64, expected_seconds: f64) -> CompactTarget {
+    if expected_seconds <= 0.0 {
+        return target;
+    }
+    target.scale(actual_seconds.max(0.0) / expected_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, GENESIS_TIMESTAMP};
+    use crate::blockchain::{ConsensusAlgorithm, TARGET_BLOCK_TIME};
+    use crate::clock::MockClock;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{SignatureVerification, Transaction, TransactionType};
+    use std::sync::Arc;
+    
+    #[test]
+    fn integer_difficulties_keep_their_meaning() {
+        for difficulty in 1..=MAX_DIFFICULTY {
+            let target = CompactTarget::from_difficulty(difficulty);
+            assert_eq!(target.leading_zero_digits(), difficulty);
+            
+            // Цифра на позиции `digit` от старшей, остальные нулевые
+            let hash_with = |digit: usize, value: u8| {
+                let mut hash = [0; 32];
+                hash[digit / 2] = if digit.is_multiple_of(2) { value << 4 } else { value };
+                hash
+            };
+            assert!(target.is_met_by(&hash_with(difficulty, 0xf)), "difficulty {}", difficulty);
+            assert!(!target.is_met_by(&hash_with(difficulty - 1, 0x1)), "difficulty {}", difficulty);
+        }
+    }
+    
+    #[test]
+    fn targets_between_integer_steps_are_mineable() {
+        let (easier, harder) = (CompactTarget::from_difficulty(3), CompactTarget::from_difficulty(4));
+        for factor in [0.75, 0.5, 0.25] {
+            let target = easier.scale(factor);
+            assert!(easier.work() < target.work() && target.work() < harder.work(), "factor {}", factor);
+            
+            let mut block = Block::new_at(1, Vec::new(), Block::genesis(1).hash, 3, GENESIS_TIMESTAMP + 60);
+            block.set_target(target);
+            block.mine_block();
+            assert!(block.meets_difficulty());
+            assert_eq!(block.hash, block.calculate_hash());
+            let hash: Vec<u8> = hex::decode(&block.hash).unwrap();
+            assert!(hash.as_slice() <= target.to_bytes().as_slice());
+            
+            // Вокруг найденного nonce проверка принимает ровно те, хеш которых как число не больше цели
+            let mut accepted = 0;
+            let mined = block.nonce;
+            for nonce in mined.saturating_sub(2000)..mined + 2000 {
+                block.nonce = nonce;
+                block.hash = block.calculate_hash();
+                let below_target = hex::decode(&block.hash).unwrap().as_slice() <= target.to_bytes().as_slice();
+                assert_eq!(block.meets_difficulty(), below_target, "nonce {}", nonce);
+                accepted += usize::from(below_target);
+            }
+            assert!(accepted > 0);
+        }
+    }
+    
+    #[test]
+    fn retarget_converges_toward_the_block_time() {
+        // Майнер с постоянной скоростью: время блока — ожидаемое число хешей, деленное на скорость
+        const HASH_RATE: f64 = 100.0;
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.mining_threads = Some(1);
+        let clock = Arc::new(MockClock::new(GENESIS_TIMESTAMP));
+        chain.set_clock(clock.clone());
+        
+        for _ in 0..100 {
+            clock.advance((chain.current_target().work() as f64 / HASH_RATE).round() as i64);
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+            chain.adjust_difficulty();
+        }
+        
+        let recent = &chain.chain[chain.chain.len() - 21..];
+        let average = (recent[20].timestamp - recent[0].timestamp) as f64 / 20.0;
+        assert!((TARGET_BLOCK_TIME * 0.75..=TARGET_BLOCK_TIME * 1.25).contains(&average), "average block time {}", average);
+        // Установившаяся цель лежит между целыми сложностями 3 и 4
+        let work = chain.current_target().work();
+        assert!(CompactTarget::from_difficulty(3).work() < work && work < CompactTarget::from_difficulty(4).work(), "{:08x}", chain.current_target().0);
+        chain.validate_chain(SignatureVerification::Serial).unwrap();
+    }
+    
+    #[test]
+    fn legacy_blocks_still_validate() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        for index in 1..=3 {
+            let tip = chain.get_latest_block().hash.clone();
+            let timestamp = GENESIS_TIMESTAMP + 60 * index as i64;
+            let reward = Transaction::new_at("BLOCKCHAIN_REWARD".to_string(), "miner".to_string(), 100.0, TransactionType::Transfer, timestamp);
+            let mut block = Block::new_at(index, vec![reward], tip, 2, timestamp);
+            block.mine_block();
+            assert_eq!(block.version, LEGACY_BLOCK_VERSION);
+            assert!(block.hash.starts_with("00") && block.meets_difficulty());
+            assert_eq!(block.difficulty_as_work(), CompactTarget::from_difficulty(2).work());
+            chain.chain.push(block);
+        }
+        chain.validate_chain(SignatureVerification::Serial).unwrap();
+        
+        let mut tampered = chain.get_latest_block().clone();
+        tampered.nonce += 1;
+        tampered.hash = tampered.calculate_hash();
+        assert_eq!(tampered.meets_difficulty(), tampered.hash.starts_with("00"));
+    }
+}
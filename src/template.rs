@@ -0,0 +1,223 @@
+use crate::block::{Block, BlockHeader};
+use crate::blockchain::{BlockAcceptance, Blockchain, ConsensusAlgorithm};
+use crate::errors::BlockchainError;
+use crate::merkle::IncrementalMerkle;
+use crate::trace::info;
+use crate::transaction::Transaction;
+use serde::{Serialize, Deserialize};
+
+/// Сколько выданных шаблонов узел помнит
+pub const MAX_BLOCK_TEMPLATES: usize = 16;
+
+/// Блок-кандидат для внешнего майнера. Майнер перебирает `nonce` (и при желании `timestamp`)
+/// в заголовке, пока `BlockHeader::calculate_hash` не удовлетворит цели, и присылает решение
+/// в `submit_block_solution`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTemplate {
+    pub template_id: String,
+    /// Заголовок с нулевым nonce: высота, предыдущий хеш, корень Меркла, цель и время
+    pub header: BlockHeader,
+    /// Транзакции блока вместе с системными в конце
+    pub transactions: Vec<Transaction>,
+}
+
+/// Выданный шаблон: блок без печати и сколько в нем транзакций из пула
+pub(crate) struct IssuedTemplate {
+    block: Block,
+    selected: usize,
+}
+
+impl Blockchain {
+    /// Собирает блок-кандидат из пула на вершину: отобранные транзакции, системные транзакции
+    /// и награда майнеру. Возвращает блок без печати и число транзакций из пула.
+    pub(crate) fn candidate_block(&self, miner_address: &str) -> Result<(Block, usize), BlockchainError> {
+        if self.consensus_algorithm != ConsensusAlgorithm::ProofOfWork {
+            return Err(BlockchainError::ConsensusError("Mining candidates need proof of work".to_string()));
+        }
+        self.check_address(miner_address)?;
+        
+        let height = self.chain.len() as u64;
+        let base_fee = self.current_base_fee();
+        let mut transactions = {
+            let pool = self.mempool.lock();
+            let (transactions, selected) = self.select_block_transactions(&pool, height, base_fee);
+            self.check_reserved_space(&transactions, &pool, &selected, height, base_fee)?;
+            transactions
+        };
+        let selected = transactions.len();
+        let mut merkle = IncrementalMerkle::from_transactions(self.hash_algorithm, &transactions);
+        for tx in self.coinbase_transactions(height, miner_address, &transactions, base_fee) {
+            merkle.push(&tx);
+            transactions.push(tx);
+        }
+        
+        let mut block = Block::from_candidate(height, transactions, &merkle, self.get_latest_block().hash.clone(), self.current_target(), self.now_ts());
+        block.set_base_fee(base_fee);
+        Ok((block, selected))
+    }
+    
+    /// Выдает шаблон блока для внешнего майнера; шаблоны для прежней вершины забываются
+    pub fn get_block_template(&mut self, miner_address: &str) -> Result<BlockTemplate, BlockchainError> {
+        let (block, selected) = self.candidate_block(miner_address)?;
+        let template = BlockTemplate {
+            template_id: block.hash.clone(),
+            header: block.header(),
+            transactions: block.transactions.clone(),
+        };
+        
+        self.block_templates.retain(|issued| issued.block.previous_hash == block.previous_hash && issued.block.hash != block.hash);
+        if self.block_templates.len() == MAX_BLOCK_TEMPLATES {
+            self.block_templates.remove(0);
+        }
+        self.block_templates.push(IssuedTemplate { block, selected });
+        Ok(template)
+    }
+    
+    /// Майнер, которому выдан шаблон; по нему можно выдать свежий шаблон взамен устаревшего
+    pub fn block_template_miner(&self, template_id: &str) -> Option<&str> {
+        self.block_templates.iter()
+            .find(|issued| issued.block.hash == template_id)
+            .and_then(|issued| issued.block.transactions.last())
+            .map(|coinbase| coinbase.receiver.as_str())
+    }
+    
+    /// Принимает решение шаблона: восстанавливает блок с присланными nonce и временем, проверяет
+    /// печать и добавляет блок через `accept_block`. Шаблон устарел, если вершина сменилась или
+    /// какой-то из его транзакций больше нет в пуле (она попала в другой блок, отменена или просрочена).
+    pub fn submit_block_solution(&mut self, template_id: &str, nonce: u64, timestamp: i64) -> Result<(), BlockchainError> {
+        let stale = || BlockchainError::StaleTemplate { template_id: template_id.to_string() };
+        let position = self.block_templates.iter().position(|issued| issued.block.hash == template_id).ok_or_else(stale)?;
+        let IssuedTemplate { block, selected } = &self.block_templates[position];
+        
+        let current = block.previous_hash == self.get_latest_block().hash
+            && block.transactions[..*selected].iter().all(|tx| self.mempool.contains(&tx.id));
+        if !current {
+            self.block_templates.remove(position);
+            return Err(stale());
+        }
+        
+        let mut block = block.clone();
+        block.nonce = nonce;
+        block.timestamp = timestamp;
+        block.hash = block.calculate_hash();
+        if !block.meets_difficulty() {
+            return Err(BlockchainError::InvalidBlock(format!("Solution for template {} does not meet the target", template_id)));
+        }
+        
+        let (index, hash) = (block.index, block.hash.clone());
+        match self.accept_block(block)? {
+            BlockAcceptance::Appended => {
+                self.block_templates.clear();
+                info!(index, hash = %hash, template_id, "Block from template accepted");
+                Ok(())
+            },
+            _ => Err(stale()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    /// Цепочка с единичной сложностью: у alice средства, в пуле ее перевод bob
+    fn chain_with_transfer() -> (Blockchain, String) {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        chain.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        chain.add_transaction(tx).unwrap();
+        (chain, tx_id)
+    }
+    
+    /// Перебирает nonce так же, как это делал бы внешний майнер: только по заголовку шаблона
+    fn grind(template: &BlockTemplate) -> u64 {
+        let mut header = template.header.clone();
+        (0..).find(|&nonce| {
+            header.nonce = nonce;
+            header.hash = header.calculate_hash();
+            header.meets_difficulty()
+        }).unwrap()
+    }
+    
+    #[test]
+    fn externally_ground_template_is_accepted() {
+        let (mut chain, tx_id) = chain_with_transfer();
+        let template = chain.get_block_template("miner").unwrap();
+        assert_eq!(template.header.index, 2);
+        assert_eq!(template.header.previous_hash, chain.get_latest_block().hash);
+        assert_eq!(template.transactions[0].id, tx_id);
+        assert_eq!(chain.block_template_miner(&template.template_id), Some("miner"));
+        // Шаблон выдается без печати, и пул при этом не трогается
+        assert_eq!(chain.chain.len(), 2);
+        assert_eq!(chain.mempool().len(), 1);
+        
+        let nonce = grind(&template);
+        let wrong_nonce = (0..).find(|&n| {
+            let mut header = template.header.clone();
+            header.nonce = n;
+            header.hash = header.calculate_hash();
+            !header.meets_difficulty()
+        }).unwrap();
+        assert!(matches!(
+            chain.submit_block_solution(&template.template_id, wrong_nonce, template.header.timestamp),
+            Err(BlockchainError::InvalidBlock(message)) if message.contains("does not meet the target")
+        ));
+        
+        chain.submit_block_solution(&template.template_id, nonce, template.header.timestamp).unwrap();
+        let block = chain.get_latest_block();
+        assert_eq!((block.index, block.nonce), (2, nonce));
+        assert!(block.transactions.iter().any(|tx| tx.id == tx_id));
+        assert!(chain.mempool().is_empty());
+        assert_eq!(chain.get_balance("miner"), 100.0 + 10.0 * 0.001);
+        assert!(chain.is_chain_valid());
+        // После принятия блока все выданные шаблоны забыты
+        assert_eq!(chain.block_template_miner(&template.template_id), None);
+    }
+    
+    #[test]
+    fn template_goes_stale_when_a_competing_block_lands() {
+        let (mut chain, _) = chain_with_transfer();
+        let template = chain.get_block_template("miner").unwrap();
+        let nonce = grind(&template);
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        
+        let stale = |result: Result<(), BlockchainError>| matches!(result, Err(BlockchainError::StaleTemplate { template_id }) if template_id == template.template_id);
+        assert!(stale(chain.submit_block_solution(&template.template_id, nonce, template.header.timestamp)));
+        // Отвергнутый шаблон забыт, повторная отправка тоже устарела
+        assert!(stale(chain.submit_block_solution(&template.template_id, nonce, template.header.timestamp)));
+        assert_eq!(chain.chain.len(), 3);
+        
+        // Свежий шаблон строится на новую вершину и принимается
+        let fresh = chain.get_block_template("miner").unwrap();
+        assert_eq!(fresh.header.previous_hash, chain.get_latest_block().hash);
+        chain.submit_block_solution(&fresh.template_id, grind(&fresh), fresh.header.timestamp).unwrap();
+        assert_eq!(chain.chain.len(), 4);
+    }
+    
+    #[test]
+    fn template_goes_stale_when_its_transaction_leaves_the_pool() {
+        let (mut chain, tx_id) = chain_with_transfer();
+        let template = chain.get_block_template("miner").unwrap();
+        chain.mempool.get_mut().transactions.retain(|tx| tx.id != tx_id);
+        assert!(matches!(
+            chain.submit_block_solution(&template.template_id, grind(&template), template.header.timestamp),
+            Err(BlockchainError::StaleTemplate { .. })
+        ));
+    }
+    
+    #[test]
+    fn unknown_template_is_stale() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        assert!(matches!(chain.submit_block_solution("missing", 0, 0), Err(BlockchainError::StaleTemplate { .. })));
+        let mut pos = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfStake);
+        assert!(matches!(pos.get_block_template("miner"), Err(BlockchainError::ConsensusError(_))));
+    }
+}
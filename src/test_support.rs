@@ -0,0 +1,549 @@
+use crate::balance_audit::BalanceBucket;
+use crate::block::{Block, GENESIS_TIMESTAMP};
+use crate::blockchain::{Blockchain, ConsensusAlgorithm, TARGET_BLOCK_TIME};
+use crate::clock::Clock;
+pub use crate::clock::MockClock;
+use crate::entropy::SeededEntropy;
+use crate::errors::BlockchainError;
+use crate::governance::{ProposalAction, VoteChoice};
+use crate::health::HealthStatus;
+use crate::mempool::reserved_balance;
+use crate::params::ChainParams;
+use crate::transaction::{Transaction, TransactionType};
+use proptest::prelude::*;
+use std::sync::Arc;
+
+/// Кошелек, которому фикстура начисляет награды PoW
+pub const FIXTURE_MINER: &str = "miner";
+
+/// Сколько слотов фикстура перебирает в поисках производителя PoS-блока
+const MAX_PRODUCER_SLOTS: usize = 10_000;
+
+/// Транзакции, которые фикстура ставит в очередь
+#[derive(Debug, Default)]
+pub struct TxBatch {
+    transactions: Vec<(String, String, f64, TransactionType)>,
+}
+
+impl TxBatch {
+    pub fn transfer(self, from: &str, to: &str, amount: f64) -> Self {
+        self.transaction(from, to, amount, TransactionType::Transfer)
+    }
+    
+    pub fn data(self, sender: &str, data: &[u8]) -> Self {
+        self.transaction(sender, "BLOCKCHAIN_DATA", 0.1, TransactionType::Data(data.to_vec()))
+    }
+    
+    pub fn transaction(mut self, from: &str, to: &str, amount: f64, transaction_type: TransactionType) -> Self {
+        self.transactions.push((from.to_string(), to.to_string(), amount, transaction_type));
+        self
+    }
+}
+
+#[derive(Debug)]
+enum FixtureStep {
+    Wallets(Vec<String>, f64),
+    Validators(Vec<(String, f64)>),
+    Transactions(TxBatch),
+    MineBlocks(u64),
+    MineBlockTo(String),
+}
+
+/// Декларативная сборка цепочки для тестов: шаги выполняются в порядке вызовов
+#[derive(Debug)]
+pub struct ChainFixture {
+    difficulty: usize,
+    mining_reward: f64,
+    consensus: ConsensusAlgorithm,
+    params: Option<ChainParams>,
+    clock_start: i64,
+    seed: Option<u64>,
+    minting: bool,
+    steps: Vec<FixtureStep>,
+}
+
+impl Default for ChainFixture {
+    fn default() -> Self {
+        ChainFixture {
+            difficulty: 1,
+            mining_reward: 50.0,
+            consensus: ConsensusAlgorithm::ProofOfWork,
+            params: None,
+            clock_start: GENESIS_TIMESTAMP,
+            seed: None,
+            minting: false,
+            steps: Vec::new(),
+        }
+    }
+}
+
+/// Собранная цепочка и то, что понадобится тесту дальше
+pub struct Fixture {
+    pub chain: Blockchain,
+    pub clock: Arc<MockClock>,
+    pub wallets: Vec<String>,
+    pub validators: Vec<String>,
+}
+
+impl ChainFixture {
+    pub fn new() -> Self {
+        ChainFixture::default()
+    }
+    
+    /// Фикстура большинства модульных тестов: награда 100 и кошелек `alice`, добывший первый блок
+    pub fn funded() -> Self {
+        ChainFixture::new().with_mining_reward(100.0).with_block_mined_by("alice")
+    }
+    
+    pub fn with_mining_reward(mut self, mining_reward: f64) -> Self {
+        self.mining_reward = mining_reward;
+        self
+    }
+    
+    pub fn with_difficulty(mut self, difficulty: usize) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+    
+    pub fn with_consensus(mut self, consensus: ConsensusAlgorithm) -> Self {
+        self.consensus = consensus;
+        self
+    }
+    
+    /// Заменяет параметры выпуска; награда за блок берется из них
+    pub fn with_params(mut self, params: ChainParams) -> Self {
+        self.mining_reward = params.initial_reward;
+        self.params = Some(params);
+        self
+    }
+    
+    /// Создает кошельки и пополняет каждый на `balance`
+    pub fn with_wallets(mut self, addresses: &[&str], balance: f64) -> Self {
+        self.steps.push(FixtureStep::Wallets(addresses.iter().map(|address| address.to_string()).collect(), balance));
+        self
+    }
+    
+    /// Создает кошельки валидаторов, пополняет их на размер ставки и ставит ее
+    pub fn with_validators(mut self, validators: &[(&str, f64)]) -> Self {
+        self.steps.push(FixtureStep::Validators(validators.iter().map(|(address, stake)| (address.to_string(), *stake)).collect()));
+        self
+    }
+    
+    pub fn with_transactions(mut self, build: impl FnOnce(TxBatch) -> TxBatch) -> Self {
+        self.steps.push(FixtureStep::Transactions(build(TxBatch::default())));
+        self
+    }
+    
+    /// Выпускает `blocks` блоков, сдвигая часы на `TARGET_BLOCK_TIME` перед каждым
+    pub fn with_mined_blocks(mut self, blocks: u64) -> Self {
+        self.steps.push(FixtureStep::MineBlocks(blocks));
+        self
+    }
+    
+    /// Создает кошелек, если его еще нет, и выпускает блок с наградой ему
+    pub fn with_block_mined_by(mut self, address: &str) -> Self {
+        self.steps.push(FixtureStep::MineBlockTo(address.to_string()));
+        self
+    }
+    
+    /// Время генезиса по управляемым часам; по умолчанию `GENESIS_TIMESTAMP`
+    pub fn using_mock_clock(mut self, start: i64) -> Self {
+        self.clock_start = start;
+        self
+    }
+    
+    pub fn using_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    
+    /// Назначает эмитентом монеты ключ кошелька `FIXTURE_MINER`
+    pub fn with_minting(mut self) -> Self {
+        self.minting = true;
+        self
+    }
+    
+    /// Голая цепочка `Blockchain::new`, принимающая демо-имена вроде `alice`, без майнера и часов фикстуры
+    pub fn chain(difficulty: usize, mining_reward: f64, consensus: ConsensusAlgorithm) -> Blockchain {
+        let mut chain = Blockchain::new(difficulty, mining_reward, consensus);
+        chain.accept_legacy_addresses = true;
+        chain
+    }
+    
+    pub fn build(self) -> Result<Fixture, BlockchainError> {
+        let mut chain = ChainFixture::chain(self.difficulty, self.mining_reward, self.consensus);
+        if let Some(params) = self.params {
+            chain.params = params;
+        }
+        let clock = Arc::new(MockClock::new(self.clock_start));
+        chain.set_clock(clock.clone());
+        if let Some(seed) = self.seed {
+            chain.set_entropy(Arc::new(SeededEntropy::new(seed)));
+        }
+        chain.create_wallet(FIXTURE_MINER.to_string())?;
+        if self.minting {
+            chain.enable_minting(FIXTURE_MINER)?;
+        }
+        
+        let mut fixture = Fixture { chain, clock, wallets: Vec::new(), validators: Vec::new() };
+        for step in self.steps {
+            match step {
+                FixtureStep::Wallets(addresses, balance) => {
+                    for address in addresses {
+                        fixture.chain.create_wallet(address.clone())?;
+                        fixture.chain.allocate_at_genesis(&address, balance)?;
+                        fixture.wallets.push(address);
+                    }
+                },
+                FixtureStep::Validators(validators) => {
+                    for (address, stake) in validators {
+                        fixture.chain.create_wallet(address.clone())?;
+                        fixture.chain.allocate_at_genesis(&address, stake)?;
+                        fixture.chain.add_validator(address.clone(), stake)?;
+                        fixture.validators.push(address);
+                    }
+                },
+                FixtureStep::Transactions(batch) => {
+                    for (from, to, amount, transaction_type) in batch.transactions {
+                        let mut tx = fixture.chain.new_transaction_at(from, to, amount, transaction_type, fixture.clock.now_ts());
+                        fixture.chain.sign_as_sender(&mut tx);
+                        fixture.chain.add_transaction(tx)?;
+                    }
+                },
+                FixtureStep::MineBlocks(blocks) => {
+                    for _ in 0..blocks {
+                        fixture.mine_block()?;
+                    }
+                },
+                FixtureStep::MineBlockTo(address) => {
+                    if fixture.chain.get_wallet_info(&address).is_none() {
+                        fixture.chain.create_wallet(address.clone())?;
+                        fixture.wallets.push(address.clone());
+                    }
+                    fixture.clock.advance(TARGET_BLOCK_TIME as i64);
+                    fixture.chain.mine_pending_transactions(address)?;
+                },
+            }
+        }
+        Ok(fixture)
+    }
+}
+
+impl Fixture {
+    /// Выпускает блок: в PoW — на `FIXTURE_MINER`, в PoS и DPoS — первым по адресу валидатором,
+    /// вытянувшим жребий, сдвигая часы на слот, пока такой не найдется
+    pub fn mine_block(&mut self) -> Result<(), BlockchainError> {
+        if self.chain.consensus_algorithm == ConsensusAlgorithm::ProofOfWork {
+            self.clock.advance(TARGET_BLOCK_TIME as i64);
+            return self.chain.mine_pending_transactions(FIXTURE_MINER.to_string());
+        }
+        
+        let mut producers = self.validators.clone();
+        producers.sort();
+        for _ in 0..MAX_PRODUCER_SLOTS {
+            self.clock.advance(TARGET_BLOCK_TIME as i64);
+            for producer in &producers {
+                if self.chain.mine_pending_transactions(producer.clone()).is_ok() {
+                    return Ok(());
+                }
+            }
+        }
+        Err(BlockchainError::ConsensusError(format!("No validator won the draw in {} slots", MAX_PRODUCER_SLOTS)))
+    }
+}
+
+/// Перевод со следующим номером отправителя, подписанный его ключом на узле
+pub fn signed_transfer(chain: &Blockchain, from: &str, to: &str, amount: f64) -> Transaction {
+    let mut tx = chain.new_transaction(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+    chain.sign_as_sender(&mut tx);
+    tx
+}
+
+/// Адреса, из которых стратегии выбирают отправителей и получателей
+pub const PROPTEST_ADDRESSES: [&str; 4] = ["alice", "bob", "carol", "dave"];
+
+pub fn arb_address() -> impl Strategy<Value = String> {
+    prop::sample::select(&PROPTEST_ADDRESSES[..]).prop_map(str::to_string)
+}
+
+fn arb_amount() -> impl Strategy<Value = f64> {
+    (1u32..100_000).prop_map(|cents| cents as f64 / 100.0)
+}
+
+/// Все типы транзакций с произвольными полями, включая данные до 256 байт
+pub fn arb_transaction_type() -> impl Strategy<Value = TransactionType> {
+    let text = "[a-z0-9]{1,12}";
+    let approvals = || prop::collection::vec(arb_address(), 0..3);
+    prop_oneof![
+        Just(TransactionType::Transfer),
+        "[ -~]{0,64}".prop_map(TransactionType::SmartContract),
+        prop::collection::vec(any::<u8>(), 0..256).prop_map(TransactionType::Data),
+        (text, any::<u32>(), prop::collection::vec(any::<u8>(), 0..256))
+            .prop_map(|(data_id, index, payload)| TransactionType::DataChunk { data_id, index, payload }),
+        (text, any::<u32>(), any::<u64>(), "[0-9a-f]{64}")
+            .prop_map(|(data_id, chunk_count, total_len, digest)| TransactionType::DataManifest { data_id, chunk_count, total_len, digest }),
+        (text, arb_address()).prop_map(|(source_tx_id, relayer)| TransactionType::BridgeMint { source_tx_id, relayer }),
+        (text, arb_address()).prop_map(|(source_tx_id, relayer)| TransactionType::BridgeRelease { source_tx_id, relayer }),
+        approvals().prop_map(|approvals| TransactionType::TreasurySpend { approvals }),
+        (any::<u64>(), any::<u64>(), any::<u64>())
+            .prop_map(|(start_height, cliff_blocks, duration_blocks)| TransactionType::VestingGrant { start_height, cliff_blocks, duration_blocks }),
+        (text, approvals()).prop_map(|(reason, approvals)| TransactionType::FreezeAccount { reason, approvals }),
+        approvals().prop_map(|approvals| TransactionType::UnfreezeAccount { approvals }),
+        ("[A-Z]{1,5}", any::<u64>(), any::<u8>())
+            .prop_map(|(ticker, total_supply, decimals)| TransactionType::IssueAsset { ticker, total_supply, decimals }),
+        (text, any::<u64>()).prop_map(|(asset_id, amount)| TransactionType::AssetTransfer { asset_id, amount }),
+        Just(TransactionType::ClaimRewards),
+        (text, 0.0..1e6).prop_map(|(name, value)| TransactionType::Propose { action: ProposalAction::SetParam { name, value } }),
+        "[ -~]{0,64}".prop_map(|text| TransactionType::Propose { action: ProposalAction::Signal(text) }),
+        (text, prop_oneof![Just(VoteChoice::Yes), Just(VoteChoice::No), Just(VoteChoice::Abstain)])
+            .prop_map(|(proposal_id, choice)| TransactionType::Vote { proposal_id, choice }),
+        (text, any::<bool>()).prop_map(|(proposal_id, passed)| TransactionType::SettleProposal { proposal_id, passed }),
+        Just(TransactionType::CloseWallet),
+        Just(TransactionType::RegisterKey),
+    ]
+}
+
+/// Неподписанные транзакции любого типа между адресами `PROPTEST_ADDRESSES`
+pub fn arb_transaction() -> impl Strategy<Value = Transaction> {
+    (arb_address(), arb_address(), arb_amount(), arb_transaction_type(), GENESIS_TIMESTAMP..GENESIS_TIMESTAMP + 1_000_000)
+        .prop_map(|(sender, receiver, amount, transaction_type, timestamp)| {
+            Transaction::new_at(sender, receiver, amount, transaction_type, timestamp)
+        })
+}
+
+/// Шаг сценария над цепочкой; `Debug` дает читаемую последовательность при сжатии
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainOp {
+    CreateWallet(String),
+    Fund { address: String, amount: f64 },
+    Transfer { from: String, to: String, amount: f64 },
+    Stake { address: String, amount: f64 },
+    StoreData { sender: String, data: Vec<u8> },
+    Mine { miner: String },
+}
+
+impl ChainOp {
+    /// Применяет шаг; отказы цепочки — ожидаемая часть сценария, а не нарушение инвариантов
+    pub fn apply(&self, fixture: &mut Fixture) -> Result<(), BlockchainError> {
+        let chain = &mut fixture.chain;
+        match self {
+            ChainOp::CreateWallet(address) => chain.create_wallet(address.clone()).map(|_| ()),
+            ChainOp::Fund { address, amount } => chain.mint(address, *amount).map(|_| ()),
+            ChainOp::Transfer { from, to, amount } => {
+                let mut tx = chain.new_transaction_at(from.clone(), to.clone(), *amount, TransactionType::Transfer, fixture.clock.now_ts());
+                chain.sign_as_sender(&mut tx);
+                chain.add_transaction(tx)
+            },
+            ChainOp::Stake { address, amount } => chain.add_validator(address.clone(), *amount),
+            ChainOp::StoreData { sender, data } => chain.store_data(sender.clone(), data.clone()).map(|_| ()),
+            ChainOp::Mine { miner } => {
+                fixture.clock.advance(TARGET_BLOCK_TIME as i64);
+                chain.mine_pending_transactions(miner.clone())
+            },
+        }
+    }
+}
+
+pub fn arb_chain_op() -> impl Strategy<Value = ChainOp> {
+    prop_oneof![
+        1 => arb_address().prop_map(ChainOp::CreateWallet),
+        1 => (arb_address(), arb_amount()).prop_map(|(address, amount)| ChainOp::Fund { address, amount }),
+        3 => (arb_address(), arb_address(), arb_amount()).prop_map(|(from, to, amount)| ChainOp::Transfer { from, to, amount }),
+        1 => (arb_address(), arb_amount()).prop_map(|(address, amount)| ChainOp::Stake { address, amount }),
+        1 => (arb_address(), prop::collection::vec(any::<u8>(), 0..64)).prop_map(|(sender, data)| ChainOp::StoreData { sender, data }),
+        2 => arb_address().prop_map(|miner| ChainOp::Mine { miner }),
+    ]
+}
+
+pub fn arb_chain_ops(max_len: usize) -> impl Strategy<Value = Vec<ChainOp>> {
+    prop::collection::vec(arb_chain_op(), 0..=max_len)
+}
+
+/// Прогоняет сценарий на чистой фикстуре, пропуская отклоненные шаги
+pub fn run_chain_ops(ops: &[ChainOp]) -> Fixture {
+    let mut fixture = ChainFixture::new().with_minting().build().expect("an empty fixture always builds");
+    for op in ops {
+        let _ = op.apply(&mut fixture);
+    }
+    fixture
+}
+
+/// Порча уже собранной цепочки, которую инварианты обязаны заметить
+#[derive(Debug, Clone)]
+pub enum ChainMutation {
+    TamperAmount { block: prop::sample::Index, tx: prop::sample::Index, amount: f64 },
+    DropTransaction { block: prop::sample::Index, tx: prop::sample::Index },
+    DuplicateTransaction { block: prop::sample::Index, tx: prop::sample::Index },
+    InflateBalance { address: prop::sample::Index, delta: f64 },
+}
+
+impl ChainMutation {
+    /// Портит цепочку; `false`, если портить нечего, например в цепочке нет транзакций
+    pub fn apply(&self, chain: &mut Blockchain) -> bool {
+        match self {
+            ChainMutation::TamperAmount { block, tx, amount } => {
+                mutate_transaction(chain, block, |transactions| {
+                    let tx = tx.get_mut(transactions);
+                    tx.amount += amount;
+                })
+            },
+            ChainMutation::DropTransaction { block, tx } => {
+                mutate_transaction(chain, block, |transactions| {
+                    transactions.remove(tx.index(transactions.len()));
+                })
+            },
+            ChainMutation::DuplicateTransaction { block, tx } => {
+                mutate_transaction(chain, block, |transactions| {
+                    let copy = tx.get(transactions).clone();
+                    transactions.push(copy);
+                })
+            },
+            ChainMutation::InflateBalance { address, delta } => {
+                let mut addresses: Vec<String> = chain.wallets.keys().cloned().collect();
+                addresses.sort();
+                if addresses.is_empty() {
+                    return false;
+                }
+                let address = address.get(&addresses);
+                chain.wallets.entry(address.clone()).and_modify(|wallet| wallet.balance += delta);
+                true
+            },
+        }
+    }
+}
+
+/// Меняет транзакции одного из блоков после генезиса и пересчитывает хеши от него до вершины,
+/// как сделал бы злоумышленник, переписывающий историю
+fn mutate_transaction(chain: &mut Blockchain, block: &prop::sample::Index, mutate: impl FnOnce(&mut Vec<Transaction>)) -> bool {
+    let heights: Vec<usize> = chain.chain.iter().enumerate()
+        .skip(1)
+        .filter(|(_, block)| !block.transactions.is_empty())
+        .map(|(height, _)| height)
+        .collect();
+    if heights.is_empty() {
+        return false;
+    }
+    let height = *block.get(&heights);
+    let Some(target) = chain.chain.get_mut(height) else {
+        return false;
+    };
+    mutate(&mut target.transactions);
+    target.merkle_root = Block::calculate_merkle_root(target.hash_algorithm, &target.transactions);
+    
+    let mut previous_hash = None;
+    for block in chain.chain.iter_mut().skip(height) {
+        if let Some(previous_hash) = previous_hash {
+            block.previous_hash = previous_hash;
+        }
+        block.hash = block.calculate_hash();
+        previous_hash = Some(block.hash.clone());
+    }
+    true
+}
+
+pub fn arb_chain_mutation() -> impl Strategy<Value = ChainMutation> {
+    let delta = prop_oneof![1u32..1_000_000, Just(1)].prop_map(|cents| cents as f64 / 100.0);
+    prop_oneof![
+        (any::<prop::sample::Index>(), any::<prop::sample::Index>(), delta.clone())
+            .prop_map(|(block, tx, amount)| ChainMutation::TamperAmount { block, tx, amount }),
+        (any::<prop::sample::Index>(), any::<prop::sample::Index>()).prop_map(|(block, tx)| ChainMutation::DropTransaction { block, tx }),
+        (any::<prop::sample::Index>(), any::<prop::sample::Index>()).prop_map(|(block, tx)| ChainMutation::DuplicateTransaction { block, tx }),
+        (any::<prop::sample::Index>(), delta).prop_map(|(address, delta)| ChainMutation::InflateBalance { address, delta }),
+    ]
+}
+
+/// Инварианты, которые должны держаться после любого сценария: проверки `health_check`
+/// (цепочка, индексы, сверка эмиссии, пул), неотрицательные балансы, в том числе за вычетом занятого
+/// пулом с учетом его зачислений, и сохранение хешей при сериализации. Возвращает описания всех нарушений.
+pub fn check_invariants(chain: &Blockchain) -> Result<(), Vec<String>> {
+    let mut violations: Vec<String> = chain.health_check().findings.into_iter()
+        .filter(|finding| finding.status == HealthStatus::Fail)
+        .flat_map(|finding| finding.details.into_iter().map(move |detail| format!("{}: {}", finding.check, detail)))
+        .collect();
+    
+    let pending = chain.mempool().transactions();
+    for (address, wallet) in &chain.wallets {
+        for bucket in BalanceBucket::ALL {
+            if bucket.value(wallet) < -1e-9 {
+                violations.push(format!("{:?} of {} is negative: {}", bucket, address, bucket.value(wallet)));
+            }
+        }
+        // Пул занимает средства отправителя, а тратить он может и зачисления из пула
+        let available = wallet.balance - reserved_balance(&pending, address) + chain.pending_credit(address, &pending);
+        if available < -1e-9 {
+            violations.push(format!("Balance of {} is overcommitted by the pool: {}", address, available));
+        }
+    }
+    
+    let restored = serde_json::to_string(chain)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str::<Blockchain>(&json).map_err(|e| e.to_string()));
+    match restored {
+        Ok(restored) => {
+            let hashes_match = restored.chain.len() == chain.chain.len()
+                && restored.chain.iter().zip(&chain.chain).all(|(restored, block)| {
+                    restored.hash == block.hash && restored.calculate_hash() == block.calculate_hash()
+                });
+            if !hashes_match {
+                violations.push("Serialization round-trip changes block hashes".to_string());
+            }
+        },
+        Err(e) => violations.push(format!("Serialization round-trip fails: {}", e)),
+    }
+    
+    if violations.is_empty() { Ok(()) } else { Err(violations) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Сценарий с хотя бы одним блоком, чтобы порче было что переписать
+    fn arb_mined_ops() -> impl Strategy<Value = Vec<ChainOp>> {
+        arb_chain_ops(16).prop_map(|mut ops| {
+            ops.push(ChainOp::Mine { miner: "alice".to_string() });
+            ops
+        })
+    }
+    
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(128))]
+        
+        #[test]
+        fn generated_sequences_keep_the_invariants(ops in arb_chain_ops(24)) {
+            let fixture = run_chain_ops(&ops);
+            prop_assert_eq!(check_invariants(&fixture.chain), Ok(()));
+        }
+        
+        #[test]
+        fn mutated_chains_break_the_invariants(ops in arb_mined_ops(), mutation in arb_chain_mutation()) {
+            let mut fixture = run_chain_ops(&ops);
+            prop_assume!(mutation.apply(&mut fixture.chain));
+            prop_assert!(check_invariants(&fixture.chain).is_err(), "{:?} went unnoticed", mutation);
+        }
+        
+        #[test]
+        fn transactions_round_trip_through_json(tx in arb_transaction()) {
+            let json = serde_json::to_string(&tx).unwrap();
+            let restored: Transaction = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(&restored.id, &tx.id);
+            prop_assert_eq!(restored.signing_bytes(), tx.signing_bytes());
+            prop_assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        }
+    }
+    
+    /// Раньше повторное создание кошелька затирало его историю и ключ, и индексы расходились с цепочкой
+    #[test]
+    fn recreating_a_wallet_keeps_the_indexes_consistent() {
+        let ops = [
+            ChainOp::CreateWallet("alice".to_string()),
+            ChainOp::Mine { miner: "alice".to_string() },
+            ChainOp::CreateWallet("alice".to_string()),
+            ChainOp::Mine { miner: "alice".to_string() },
+        ];
+        let mut fixture = run_chain_ops(&ops[..2]);
+        assert!(matches!(ops[2].apply(&mut fixture), Err(BlockchainError::DuplicateWallet { .. })));
+        let fixture = run_chain_ops(&ops);
+        assert_eq!(check_invariants(&fixture.chain), Ok(()));
+    }
+}
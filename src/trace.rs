@@ -0,0 +1,141 @@
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{debug, info, info_span, warn};
+
+/// Событие без `tracing`: поля только проверяются компилятором и не вычисляются
+#[cfg(not(feature = "tracing"))]
+macro_rules! noop_event {
+    () => {};
+    ($name:ident = % $value:expr $(, $($rest:tt)*)?) => {{
+        if false { let _ = &$value; }
+        $( $crate::trace::noop_event!($($rest)*); )?
+    }};
+    ($name:ident = ? $value:expr $(, $($rest:tt)*)?) => {{
+        if false { let _ = &$value; }
+        $( $crate::trace::noop_event!($($rest)*); )?
+    }};
+    ($name:ident = $value:expr $(, $($rest:tt)*)?) => {{
+        if false { let _ = &$value; }
+        $( $crate::trace::noop_event!($($rest)*); )?
+    }};
+    (% $name:ident $(, $($rest:tt)*)?) => {{
+        if false { let _ = &$name; }
+        $( $crate::trace::noop_event!($($rest)*); )?
+    }};
+    (? $name:ident $(, $($rest:tt)*)?) => {{
+        if false { let _ = &$name; }
+        $( $crate::trace::noop_event!($($rest)*); )?
+    }};
+    ($value:expr $(, $($rest:tt)*)?) => {{
+        if false { let _ = &$value; }
+        $( $crate::trace::noop_event!($($rest)*); )?
+    }};
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! noop_span {
+    ($name:literal $(, $($fields:tt)*)?) => {{
+        $( $crate::trace::noop_event!($($fields)*); )?
+        $crate::trace::NoopSpan
+    }};
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use {noop_event, noop_event as debug, noop_event as info, noop_event as warn, noop_span as info_span};
+
+/// Заглушка span без `tracing`
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoopSpan;
+
+#[cfg(not(feature = "tracing"))]
+impl NoopSpan {
+    pub(crate) fn entered(self) -> Self {
+        self
+    }
+}
+
+/// Выводит события в stderr без времени и модуля; уровень задает переменная `HELLOCHAIN_LOG`
+/// (по умолчанию info)
+#[cfg(feature = "tracing")]
+pub fn init_subscriber() {
+    use std::io::IsTerminal;
+    
+    let level = std::env::var("HELLOCHAIN_LOG").ok()
+        .and_then(|level| level.parse::<tracing::Level>().ok())
+        .unwrap_or(tracing::Level::INFO);
+    let _ = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_max_level(level)
+        .with_target(false)
+        .without_time()
+        .try_init();
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn init_subscriber() {}
+
+#[cfg(test)]
+mod tests {
+    /// Файлы библиотеки, которым разрешено писать в stdout: это сами интерфейсы командной строки
+    const PRINTING_FRONTENDS: [&str; 3] = ["cli.rs", "console.rs", "main.rs"];
+    
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn mining_emits_block_events_with_fields() {
+        use crate::blockchain::ConsensusAlgorithm;
+        use crate::test_support::ChainFixture;
+        use std::io::Write;
+        use std::sync::{Arc, Mutex};
+        
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+        impl Write for Capture {
+            fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(bytes);
+                Ok(bytes.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || Capture(writer.clone()))
+            .with_ansi(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .without_time()
+            .finish();
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        tracing::subscriber::with_default(subscriber, || {
+            chain.mine_pending_transactions("alice".to_string()).unwrap();
+        });
+        
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        let appended = output.lines().find(|line| line.contains("Block appended")).expect(&output);
+        assert!(appended.contains("mine_pending_transactions{miner=alice height=1}"), "{}", appended);
+        for field in ["index=1", &format!("hash={}", chain.get_latest_block().hash), "difficulty=1", "txs=2", "miner=\"alice\""] {
+            assert!(appended.contains(field), "{} missing from {}", field, appended);
+        }
+        let mined = output.lines().find(|line| line.contains("Block mined")).expect(&output);
+        assert!(mined.starts_with("DEBUG") && mined.contains("nonce="), "{}", mined);
+    }
+    
+    #[test]
+    fn library_code_does_not_print() {
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in std::fs::read_dir(&src).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap();
+            if !name.ends_with(".rs") || PRINTING_FRONTENDS.contains(&name) {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            for (number, line) in source.lines().enumerate() {
+                let code = line.trim_start();
+                let prints = ["print", "println", "eprint", "eprintln", "dbg"].iter().any(|name| code.contains(&format!("{}!(", name)));
+                assert!(code.starts_with("//") || !prints, "{}:{} writes to the console: {}", name, number + 1, code);
+            }
+        }
+    }
+}
@@ -1,8 +1,21 @@
+use crate::codec::encode_transaction;
+use crate::errors::BlockchainError;
+use crate::governance::{ProposalAction, VoteChoice, GOVERNANCE_ESCROW};
+use crate::hashing::{HashKind, HashState};
+use crate::signing::{self, parse_public_key, Keypair, PublicKey};
+use crate::supply::is_system_sender;
+use crate::utxo::{TxInput, TxOutput};
 use chrono::prelude::*;
-use sha2::{Sha256, Digest};
+use serde::{Serialize, Deserialize};
+use std::fmt::{self, Write as _};
+use std::io;
+use std::thread;
+
+/// Хеш в двоичном виде; все алгоритмы цепочки дают 32 байта
+pub type Hash = [u8; 32];
 
 /// Определяет типы транзакций, поддерживаемые блокчейном
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TransactionType {
     /// Простая передача средств между адресами
     Transfer,
@@ -10,10 +23,57 @@ pub enum TransactionType {
     SmartContract(String),
     /// Хранение произвольных данных
     Data(Vec<u8>),
+    /// Фрагмент потоковой загрузки данных с порядковым номером
+    DataChunk { data_id: String, index: u32, payload: Vec<u8> },
+    /// Манифест потоковой загрузки: число фрагментов, общий размер и хеш содержимого
+    DataManifest { data_id: String, chunk_count: u32, total_len: u64, digest: String },
+    /// Выпуск обернутых средств мостом в ответ на блокировку в другой цепочке
+    BridgeMint { source_tx_id: String, relayer: String },
+    /// Возврат средств из эскроу моста в ответ на сжигание обернутых средств
+    BridgeRelease { source_tx_id: String, relayer: String },
+    /// Расход из казны по заявке с одобрениями подписантов
+    TreasurySpend { approvals: Vec<String> },
+    /// Грант получателю, открывающийся линейно после клиффа
+    VestingGrant { start_height: u64, cliff_blocks: u64, duration_blocks: u64 },
+    /// Заморозка счета-получателя администраторами
+    FreezeAccount { reason: String, approvals: Vec<String> },
+    /// Разморозка счета-получателя администраторами
+    UnfreezeAccount { approvals: Vec<String> },
+    /// Выпуск актива эмитенту-отправителю; суммы актива — в минимальных единицах
+    IssueAsset { ticker: String, total_supply: u64, decimals: u8 },
+    /// Перевод актива; комиссия платится в основной монете
+    AssetTransfer { asset_id: String, amount: u64 },
+    /// Перевод накопленных наград отправителя на его же баланс
+    ClaimRewards,
+    /// Предложение для голосования; сумма — залог, который держит эскроу управления
+    Propose { action: ProposalAction },
+    /// Голос валидатора по предложению; повторный голос заменяет прежний
+    Vote { proposal_id: String, choice: VoteChoice },
+    /// Итог голосования: залог возвращается автору или сжигается
+    SettleProposal { proposal_id: String, passed: bool },
+    /// Перевод всего баланса с закрытием кошелька-отправителя
+    CloseWallet,
+    /// Выпуск новых средств на адрес получателя от `MINT_AUTHORITY`
+    Mint,
+    /// Закрепление за именем-отправителем открытого ключа транзакции; подписывается этим ключом
+    RegisterKey,
+}
+
+/// Меньше стольких транзакций на поток подписи проверяются без распараллеливания
+const MIN_TRANSACTIONS_PER_THREAD: usize = 64;
+
+/// Как проверять подписи транзакций блока
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SignatureVerification {
+    /// По одной в текущем потоке — детерминированно, удобно для отладки
+    Serial,
+    /// Пачками на нескольких потоках
+    #[default]
+    Parallel,
 }
 
 /// Представляет транзакцию в блокчейне
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: String,
     pub transaction_type: TransactionType,
@@ -21,26 +81,61 @@ pub struct Transaction {
     pub receiver: String,
     pub amount: f64,
     pub fee: f64,
-    #[allow(dead_code)] // Помечаем как используемые, чтобы убрать предупреждение
     pub timestamp: i64,
-    #[allow(dead_code)]
+    /// Подпись отправителя в hex; пустая, пока транзакция не подписана
+    pub signature: String,
+    /// Открытый ключ отправителя в hex, которым проверяется подпись; у старых транзакций его нет
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Порядковый номер транзакции отправителя, начиная с нуля; входит в идентификатор. Его нет
+    /// у системных транзакций, трат выходов и транзакций, созданных до появления номеров.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// Спонсор, который платит комиссию вместо отправителя
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_payer: Option<FeePayer>,
+    /// Последняя высота блока, в который транзакция может попасть; входит в подпись
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until_height: Option<u64>,
+    /// Потраченные выходы; только в UTXO-цепочке
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<TxInput>,
+    /// Созданные выходы; только в UTXO-цепочке
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<TxOutput>,
+    /// Extra-nonce награды за блок: майнер меняет его, когда перебор nonce заголовка не дал решения; входит в идентификатор
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_nonce: Option<u64>,
+    /// Алгоритм, которым посчитан идентификатор; совпадает с алгоритмом цепочки
+    #[serde(default, skip_serializing_if = "HashKind::is_sha256")]
+    pub hash_algorithm: HashKind,
+}
+
+/// Спонсор транзакции. Его подпись ключом ed25519 покрывает идентификатор, комиссию и предел `max_fee`,
+/// поэтому с него нельзя списать ни сумму перевода, ни комиссию больше подписанной.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeePayer {
+    pub address: String,
+    pub max_fee: f64,
     pub signature: String,
+    /// Открытый ключ спонсора в hex, которым проверяется его подпись; у старых спонсоров его нет
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
 }
 
 impl Transaction {
     /// Создает новую транзакцию с указанными параметрами
     pub fn new(sender: String, receiver: String, amount: f64, transaction_type: TransactionType) -> Self {
-        let timestamp = Utc::now().timestamp();
-        let tx_data = format!("{}{}{}{:?}", sender, receiver, amount, timestamp);
-        let id = calculate_hash(&tx_data);
-        
-        let signature = format!("sig_{}", calculate_hash(&format!("{}{}", id, timestamp)));
+        Transaction::new_at(sender, receiver, amount, transaction_type, Utc::now().timestamp())
+    }
+    
+    /// То же, что `new`, но с заданным временем создания. Транзакция создается неподписанной, ее
+    /// идентификатор считается SHA-256; для цепочки с другим алгоритмом — `Blockchain::new_transaction`.
+    pub fn new_at(sender: String, receiver: String, amount: f64, transaction_type: TransactionType, timestamp: i64) -> Self {
+        let hash_algorithm = HashKind::default();
+        let id = transaction_id(hash_algorithm, &sender, &receiver, amount, timestamp);
         
-        let fee = match transaction_type {
-            TransactionType::Transfer => 0.001 * amount,
-            TransactionType::SmartContract(_) => 0.01 * amount + 0.5,
-            TransactionType::Data(ref data) => 0.005 * amount + (data.len() as f64 * 0.0001),
-        };
+        let fee = Transaction::minimum_fee(&transaction_type, amount);
         
         Transaction {
             id,
@@ -50,20 +145,359 @@ impl Transaction {
             amount,
             fee,
             timestamp,
-            signature,
+            signature: String::new(),
+            public_key: None,
+            nonce: None,
+            fee_payer: None,
+            valid_until_height: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            extra_nonce: None,
+            hash_algorithm,
+        }
+    }
+    
+    /// Передает оплату комиссии спонсору `address`, который подписывает ключом `keypair` текущую
+    /// комиссию с пределом `max_fee`; если комиссия потом изменится, подпись спонсора перестанет сходиться
+    pub fn sponsor(&mut self, keypair: &Keypair, address: &str, max_fee: f64) {
+        self.fee_payer = Some(FeePayer {
+            address: address.to_string(),
+            max_fee,
+            signature: keypair.sign(&self.sponsor_message(address, max_fee)),
+            public_key: Some(keypair.public_key_hex()),
+        });
+    }
+    
+    /// Что подписывает спонсор: идентификатор транзакции, его адрес, комиссия и ее предел
+    fn sponsor_message(&self, address: &str, max_fee: f64) -> Vec<u8> {
+        format!("sponsor{}{}{:?}{:?}", self.id, address, self.fee, max_fee).into_bytes()
+    }
+    
+    /// Проверяет подпись спонсора открытым ключом
+    pub fn verify_fee_payer(&self, public_key: &PublicKey) -> bool {
+        self.fee_payer.as_ref().is_some_and(|payer| {
+            signing::verify(public_key, &self.sponsor_message(&payer.address, payer.max_fee), &payer.signature)
+        })
+    }
+    
+    /// Задает порядковый номер транзакции отправителя (`Blockchain::get_account_nonce`). Номер
+    /// меняет идентификатор, поэтому его задают до спонсора и подписи; прежняя подпись сбрасывается.
+    pub fn set_nonce(&mut self, nonce: u64) {
+        self.nonce = Some(nonce);
+        self.id = self.expected_id();
+        self.signature.clear();
+        self.public_key = None;
+    }
+    
+    /// Пересчитывает идентификатор алгоритмом цепочки, в которую отправляется транзакция.
+    /// Задается до спонсора и подписи; прежняя подпись сбрасывается.
+    pub fn set_hash_algorithm(&mut self, hash_algorithm: HashKind) {
+        self.hash_algorithm = hash_algorithm;
+        self.id = self.expected_id();
+        self.signature.clear();
+        self.public_key = None;
+    }
+    
+    /// Ограничивает срок транзакции: после блока `height` она не может попасть в цепочку.
+    /// Срок входит в подпись, поэтому прежняя подпись отправителя сбрасывается.
+    pub fn set_valid_until(&mut self, height: u64) {
+        self.valid_until_height = Some(height);
+        if !self.inputs.is_empty() || !self.outputs.is_empty() {
+            self.id = self.expected_id();
+        }
+        self.signature.clear();
+        self.public_key = None;
+    }
+    
+    /// Идентификатор по полям транзакции; у транзакций UTXO-цепочки он покрывает входы с выходами и срок,
+    /// чтобы одинаковые награды разных блоков не создавали одинаковых выходов
+    pub(crate) fn expected_id(&self) -> String {
+        let id = match &self.transaction_type {
+            TransactionType::BridgeMint { source_tx_id, relayer } | TransactionType::BridgeRelease { source_tx_id, relayer } => {
+                let id = transaction_id(self.hash_algorithm, &self.sender, &self.receiver, self.amount, self.timestamp);
+                bridge_transaction_id(self.hash_algorithm, relayer, source_tx_id, &id)
+            },
+            _ if self.inputs.is_empty() && self.outputs.is_empty() => {
+                let id = transaction_id(self.hash_algorithm, &self.sender, &self.receiver, self.amount, self.timestamp);
+                match self.nonce {
+                    Some(nonce) => calculate_hash(self.hash_algorithm, &format!("{}nonce{}", id, nonce)),
+                    None => id,
+                }
+            },
+            _ => match self.valid_until_height {
+                Some(height) => calculate_hash(self.hash_algorithm, &format!("{}until{}", self.spend_digest(), height)),
+                None => self.spend_digest(),
+            },
+        };
+        match self.extra_nonce {
+            Some(extra_nonce) => calculate_hash(self.hash_algorithm, &format!("{}extra{}", id, extra_nonce)),
+            None => id,
+        }
+    }
+    
+    /// Хеш полей транзакции вместе со входами и выходами; его подписывают владельцы входов
+    pub(crate) fn spend_digest(&self) -> String {
+        let id = transaction_id(self.hash_algorithm, &self.sender, &self.receiver, self.amount, self.timestamp);
+        
+        let mut hasher = HashWriter::new(self.hash_algorithm);
+        let _ = write!(hasher, "{}", id);
+        for input in &self.inputs {
+            let _ = write!(hasher, "in{}", input.outpoint);
+        }
+        for output in &self.outputs {
+            let _ = write!(hasher, "out{}{:?}", output.address, output.amount);
+        }
+        hasher.finish_hex()
+    }
+    
+    /// Пересчитывает идентификатор после изменения входов, выходов или extra-nonce
+    pub(crate) fn reseal(&mut self) {
+        self.id = self.expected_id();
+    }
+    
+    /// Комиссия, которую сеть требует за транзакцию данного типа и суммы
+    pub fn minimum_fee(transaction_type: &TransactionType, amount: f64) -> f64 {
+        match transaction_type {
+            TransactionType::Transfer | TransactionType::TreasurySpend { .. } | TransactionType::VestingGrant { .. }
+                | TransactionType::ClaimRewards | TransactionType::Propose { .. } | TransactionType::CloseWallet => 0.001 * amount,
+            TransactionType::SmartContract(_) => 0.01 * amount + 0.5,
+            TransactionType::Data(data) => 0.005 * amount + (data.len() as f64 * 0.0001),
+            TransactionType::DataChunk { payload, .. } => 0.005 * amount + (payload.len() as f64 * 0.0001),
+            TransactionType::DataManifest { .. } => 0.005 * amount,
+            TransactionType::BridgeMint { .. } | TransactionType::BridgeRelease { .. } | TransactionType::Mint => 0.0,
+            TransactionType::FreezeAccount { .. } | TransactionType::UnfreezeAccount { .. } => 0.0,
+            TransactionType::IssueAsset { .. } => 1.0,
+            TransactionType::AssetTransfer { .. } | TransactionType::Vote { .. } => 0.001,
+            TransactionType::SettleProposal { .. } | TransactionType::RegisterKey => 0.0,
         }
     }
     
-    /// Проверяет валидность транзакции (наличие отправителя, получателя и положительной суммы)
+    /// Проверяет валидность транзакции (наличие отправителя, получателя и положительной суммы;
+    /// административные действия и операции с активами основной монеты не переводят)
     pub fn is_valid(&self) -> bool {
-        !self.sender.is_empty() && !self.receiver.is_empty() && self.amount > 0.0
+        let without_amount = matches!(
+            self.transaction_type,
+            TransactionType::FreezeAccount { .. } | TransactionType::UnfreezeAccount { .. }
+                | TransactionType::IssueAsset { .. } | TransactionType::AssetTransfer { .. } | TransactionType::Vote { .. }
+                | TransactionType::RegisterKey
+        );
+        !self.sender.is_empty() && !self.receiver.is_empty() && (self.amount > 0.0 || without_amount)
+    }
+    
+    /// Подписывает ли транзакцию отправитель. У системных отправителей ключей нет, транзакции моста
+    /// подтверждает релеер, расход казны — одобрения подписантов, а переводы UTXO-цепочки — подписи
+    /// владельцев входов.
+    pub fn needs_sender_signature(&self) -> bool {
+        !is_system_sender(&self.sender) && self.sender != GOVERNANCE_ESCROW && self.inputs.is_empty()
+            && !matches!(
+                self.transaction_type,
+                TransactionType::BridgeMint { .. } | TransactionType::BridgeRelease { .. } | TransactionType::TreasurySpend { .. }
+            )
+    }
+    
+    /// Канонические байты, которые подписывает отправитель: все поля транзакции в двоичном формате
+    /// без подписей. Идентификатор покрывает входы, выходы и extra-nonce.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut unsigned = self.clone();
+        unsigned.signature.clear();
+        if let Some(payer) = &mut unsigned.fee_payer {
+            payer.signature.clear();
+        }
+        for input in &mut unsigned.inputs {
+            input.signature.clear();
+        }
+        encode_transaction(&unsigned, 0)
+    }
+    
+    /// Записывает в транзакцию открытый ключ пары и подписывает ее канонические байты.
+    /// Спонсора и срок нужно задать до подписи.
+    pub fn sign(&mut self, keypair: &Keypair) {
+        self.public_key = Some(keypair.public_key_hex());
+        self.signature = keypair.sign(&self.signing_bytes());
+    }
+    
+    /// Проверяет подпись отправителя открытым ключом
+    pub fn verify_signature(&self, public_key: &PublicKey) -> bool {
+        signing::verify(public_key, &self.signing_bytes(), &self.signature)
+    }
+    
+    /// Проверяет, что идентификатор соответствует полям транзакции, подпись отправителя — ключу из
+    /// транзакции, а подпись спонсора, если он есть, — ключу спонсора и его комиссии. Принадлежат ли
+    /// ключи отправителю и спонсору, проверяет цепочка.
+    pub fn verify_integrity(&self) -> bool {
+        self.check_integrity(false)
     }
+    
+    /// Как `verify_integrity`; `legacy` принимает у транзакций без ключей прежние хеш-подписи.
+    /// Подделать их может кто угодно, поэтому так проверяются только блоки, сохраненные до подписей ed25519.
+    fn check_integrity(&self, legacy: bool) -> bool {
+        let signed = match &self.public_key {
+            Some(public_key) => parse_public_key(public_key).is_some_and(|public_key| self.verify_signature(&public_key)),
+            None => legacy && self.signature == legacy_signature(self.hash_algorithm, &self.id, self.timestamp, self.valid_until_height),
+        };
+        self.id == self.expected_id()
+            && (signed || !self.needs_sender_signature())
+            && self.fee_payer.as_ref().is_none_or(|payer| match &payer.public_key {
+                Some(public_key) => parse_public_key(public_key).is_some_and(|public_key| self.verify_fee_payer(&public_key)),
+                None => legacy && payer.signature == legacy_fee_payer_signature(self.hash_algorithm, &self.id, &payer.address, self.fee, payer.max_fee),
+            })
+    }
+}
+
+/// Проверяет подписи всех транзакций; `legacy` разрешает прежние хеш-подписи, как в блоках,
+/// сохраненных до подписей ed25519. Если пакетная проверка не прошла, транзакции перепроверяются
+/// по одной, чтобы ошибка назвала первую виновную — ту же, что и при последовательной проверке.
+pub fn verify_signatures(transactions: &[Transaction], verification: SignatureVerification, legacy: bool) -> Result<(), BlockchainError> {
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk_size = transactions.len().div_ceil(threads).max(MIN_TRANSACTIONS_PER_THREAD);
+    
+    if verification == SignatureVerification::Parallel && transactions.len() > chunk_size {
+        let all_valid = thread::scope(|scope| {
+            let batches: Vec<_> = transactions.chunks(chunk_size)
+                .map(|batch| scope.spawn(move || batch.iter().all(|tx| tx.check_integrity(legacy))))
+                .collect();
+            batches.into_iter().all(|batch| batch.join().unwrap_or(false))
+        });
+        if all_valid {
+            return Ok(());
+        }
+    }
+    
+    match transactions.iter().find(|tx| !tx.check_integrity(legacy)) {
+        Some(tx) => Err(BlockchainError::InvalidSignature { tx_id: tx.id.clone() }),
+        None => Ok(()),
+    }
+}
+
+/// Идентификатор транзакции — хеш отправителя, получателя, суммы и времени
+fn transaction_id(hash_algorithm: HashKind, sender: &str, receiver: &str, amount: f64, timestamp: i64) -> String {
+    let mut hasher = HashWriter::new(hash_algorithm);
+    let _ = write!(hasher, "{}{}{}{:?}", sender, receiver, amount, timestamp);
+    hasher.finish_hex()
+}
+
+/// Идентификатор транзакции моста: выводится из релеера и исходной транзакции, чтобы две выплаты
+/// одной суммы не совпали, и покрывает собственные поля выплаты
+fn bridge_transaction_id(hash_algorithm: HashKind, relayer: &str, source_tx_id: &str, id: &str) -> String {
+    calculate_hash(hash_algorithm, &format!("bridge{}{}{}", relayer, source_tx_id, id))
+}
+
+/// Хеш-подпись, которую транзакции несли до подписей ed25519: идентификатор, время и срок.
+/// Проверяется только у транзакций без открытого ключа из уже сохраненных цепочек.
+fn legacy_signature(hash_algorithm: HashKind, id: &str, timestamp: i64, valid_until_height: Option<u64>) -> String {
+    let mut hasher = HashWriter::new(hash_algorithm);
+    let _ = write!(hasher, "{}{}", id, timestamp);
+    if let Some(height) = valid_until_height {
+        let _ = write!(hasher, "until{}", height);
+    }
+    format!("sig_{}", hasher.finish_hex())
 }
 
-/// Вычисляет SHA-256 хеш для переданных данных
-pub fn calculate_hash(data: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(data.as_bytes());
-    let result = hasher.finalize();
-    format!("{:x}", result)
-}
\ No newline at end of file
+/// Хеш-подпись спонсора до подписей ed25519: идентификатор транзакции, его адрес, комиссия и ее предел.
+/// Проверяется только у спонсоров без открытого ключа из уже сохраненных цепочек.
+fn legacy_fee_payer_signature(hash_algorithm: HashKind, id: &str, address: &str, fee: f64, max_fee: f64) -> String {
+    let mut hasher = HashWriter::new(hash_algorithm);
+    let _ = write!(hasher, "{}{}{:?}{:?}", id, address, fee, max_fee);
+    format!("sig_{}", hasher.finish_hex())
+}
+
+/// Вычисляет хеш переданных данных алгоритмом цепочки
+pub fn calculate_hash(hash_algorithm: HashKind, data: &str) -> String {
+    hex::encode(hash_bytes(hash_algorithm, data.as_bytes()))
+}
+
+/// Вычисляет хеш байтов алгоритмом цепочки
+pub fn hash_bytes(hash_algorithm: HashKind, data: &[u8]) -> Hash {
+    hash_algorithm.digest(data)
+}
+
+/// Передает данные в хеш-функцию цепочки по мере записи, не собирая их в промежуточную строку.
+/// Запись через `write!` дает тот же хеш, что `calculate_hash(kind, &format!(...))`.
+#[derive(Clone)]
+pub struct HashWriter {
+    hasher: HashState,
+}
+
+impl HashWriter {
+    pub fn new(hash_algorithm: HashKind) -> Self {
+        HashWriter { hasher: hash_algorithm.state() }
+    }
+    
+    pub fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+    
+    pub fn finish(self) -> Hash {
+        self.hasher.finish()
+    }
+    
+    /// Хеш в виде шестнадцатеричной строки, как у `calculate_hash`
+    pub fn finish_hex(self) -> String {
+        hex::encode(self.finish())
+    }
+}
+
+impl fmt::Write for HashWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.update(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl io::Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::bridge::BRIDGE_MINTER;
+    use crate::test_support::ChainFixture;
+    
+    /// Перевод без ключа с прежней хеш-подписью, как в цепочках до подписей ed25519
+    fn legacy_transfer() -> Transaction {
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.signature = legacy_signature(tx.hash_algorithm, &tx.id, tx.timestamp, tx.valid_until_height);
+        tx
+    }
+    
+    #[test]
+    fn legacy_hash_signatures_pass_only_in_legacy_blocks() {
+        let tx = legacy_transfer();
+        assert!(!tx.verify_integrity());
+        assert!(verify_signatures(std::slice::from_ref(&tx), SignatureVerification::Serial, true).is_ok());
+        let result = verify_signatures(std::slice::from_ref(&tx), SignatureVerification::Serial, false);
+        assert!(matches!(&result, Err(BlockchainError::InvalidSignature { tx_id }) if *tx_id == tx.id), "{:?}", result);
+        
+        // Блок с такой подписью проходит проверку цепочки, только пока он не выше отметки
+        let mut chain = ChainFixture::new().with_mined_blocks(1).build().unwrap().chain;
+        let block = chain.chain.last_mut().unwrap();
+        block.transactions.insert(0, tx);
+        block.merkle_root = Block::calculate_merkle_root(block.hash_algorithm, &block.transactions);
+        block.hash = block.calculate_hash();
+        let result = chain.validate_chain(SignatureVerification::Serial);
+        assert!(matches!(result, Err(BlockchainError::InvalidBlock(_))), "{:?}", result);
+        chain.legacy_signatures_until = Some(1);
+        chain.validate_chain(SignatureVerification::Serial).unwrap();
+    }
+    
+    #[test]
+    fn bridge_transaction_id_covers_the_payout() {
+        let transaction_type = TransactionType::BridgeMint { source_tx_id: "source".to_string(), relayer: "relayer".to_string() };
+        let mut tx = Transaction::new(BRIDGE_MINTER.to_string(), "bob".to_string(), 10.0, transaction_type);
+        tx.id = tx.expected_id();
+        assert!(verify_signatures(std::slice::from_ref(&tx), SignatureVerification::Serial, false).is_ok());
+        
+        tx.amount = 1_000.0;
+        let result = verify_signatures(&[tx], SignatureVerification::Serial, true);
+        assert!(matches!(result, Err(BlockchainError::InvalidSignature { .. })), "{:?}", result);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Отправитель доли награды, которая выпускается в казну
+pub const TREASURY_MINTER: &str = "BLOCKCHAIN_TREASURY";
+
+/// Настройки казны: адрес, доля награды за блок и подписанты, решающие о расходах
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreasuryConfig {
+    /// Кошелек казны; потратить с него можно только одобренной заявкой
+    pub address: String,
+    /// Доля награды за блок (без комиссий), от 0 до 1
+    pub share: f64,
+    /// Кто может предлагать и одобрять расходы
+    pub signers: BTreeSet<String>,
+    /// Сколько одобрений нужно для расхода
+    pub threshold: usize,
+}
+
+/// Заявка на расход из казны
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreasuryProposal {
+    pub receiver: String,
+    pub amount: f64,
+    pub approvals: BTreeSet<String>,
+}
+
+/// Казна: настройки и заявки, еще не исполненные
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Treasury {
+    pub config: TreasuryConfig,
+    proposals: BTreeMap<u64, TreasuryProposal>,
+    next_proposal_id: u64,
+}
+
+impl Treasury {
+    pub fn new(config: TreasuryConfig) -> Self {
+        Treasury { config, proposals: BTreeMap::new(), next_proposal_id: 1 }
+    }
+    
+    pub fn proposal(&self, id: u64) -> Option<&TreasuryProposal> {
+        self.proposals.get(&id)
+    }
+    
+    fn check_signer(&self, signer: &str) -> Result<(), BlockchainError> {
+        if !self.config.signers.contains(signer) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} is not a treasury signer", signer)));
+        }
+        Ok(())
+    }
+}
+
+impl Blockchain {
+    /// Включает казну и создает ее кошелек
+    pub fn enable_treasury(&mut self, config: TreasuryConfig) -> Result<(), BlockchainError> {
+        self.require_account_mode("The treasury")?;
+        if !(0.0..=1.0).contains(&config.share) {
+            return Err(BlockchainError::InvalidTransaction(format!("Treasury share {} is not between 0 and 1", config.share)));
+        }
+        if config.threshold == 0 || config.threshold > config.signers.len() {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Treasury threshold {} does not fit {} signers", config.threshold, config.signers.len()
+            )));
+        }
+        
+        self.get_or_create_wallet(&config.address);
+        self.treasury = Some(Treasury::new(config));
+        Ok(())
+    }
+    
+    /// Баланс казны; 0, если казна не включена
+    pub fn treasury_balance(&self) -> f64 {
+        self.treasury.as_ref().map_or(0.0, |treasury| self.get_balance(&treasury.config.address))
+    }
+    
+    /// Доля награды за блок указанной высоты, которая уходит в казну
+    pub fn treasury_cut(&self, height: u64) -> f64 {
+        self.treasury.as_ref().map_or(0.0, |treasury| self.reward_at_height(height) * treasury.config.share)
+    }
+    
+    /// Транзакция выпуска доли награды в казну; None, если доля нулевая
+    pub(crate) fn treasury_cut_transaction(&self, height: u64) -> Option<Transaction> {
+        let treasury = self.treasury.as_ref()?;
+        let cut = self.treasury_cut(height);
+        if cut <= 0.0 {
+            return None;
+        }
+        
+        let mut tx = self.new_transaction(TREASURY_MINTER.to_string(), treasury.config.address.clone(), cut, TransactionType::Transfer);
+        tx.fee = 0.0;
+        Some(tx)
+    }
+    
+    /// Создает заявку на расход; предложивший подписант сразу ее одобряет
+    pub fn propose_treasury_spend(&mut self, proposer: &str, receiver: String, amount: f64) -> Result<u64, BlockchainError> {
+        let treasury = self.treasury.as_mut()
+            .ok_or(BlockchainError::FeatureDisabled { feature: "Treasury" })?;
+        treasury.check_signer(proposer)?;
+        if amount <= 0.0 {
+            return Err(BlockchainError::InvalidTransaction(format!("Treasury spend amount {} must be positive", amount)));
+        }
+        
+        let id = treasury.next_proposal_id;
+        treasury.next_proposal_id += 1;
+        treasury.proposals.insert(id, TreasuryProposal { receiver, amount, approvals: BTreeSet::from([proposer.to_string()]) });
+        Ok(id)
+    }
+    
+    /// Добавляет одобрение подписанта к заявке
+    pub fn approve_treasury_spend(&mut self, proposal_id: u64, signer: &str) -> Result<(), BlockchainError> {
+        let treasury = self.treasury.as_mut()
+            .ok_or(BlockchainError::FeatureDisabled { feature: "Treasury" })?;
+        treasury.check_signer(signer)?;
+        let proposal = treasury.proposals.get_mut(&proposal_id)
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Treasury proposal {} not found", proposal_id)))?;
+        proposal.approvals.insert(signer.to_string());
+        Ok(())
+    }
+    
+    /// Ставит в очередь расход по заявке, набравшей нужное число одобрений, и возвращает идентификатор транзакции
+    pub fn execute_treasury_spend(&mut self, proposal_id: u64) -> Result<String, BlockchainError> {
+        let treasury = self.treasury.as_ref()
+            .ok_or(BlockchainError::FeatureDisabled { feature: "Treasury" })?;
+        let proposal = treasury.proposal(proposal_id)
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Treasury proposal {} not found", proposal_id)))?;
+        
+        let mut tx = self.new_transaction(
+            treasury.config.address.clone(),
+            proposal.receiver.clone(),
+            proposal.amount,
+            TransactionType::TreasurySpend { approvals: proposal.approvals.iter().cloned().collect() },
+        );
+        if let Some(base_fee) = self.current_base_fee() {
+            tx.fee = tx.fee.max(base_fee * tx.weight() as f64);
+        }
+        
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        if let Some(treasury) = self.treasury.as_mut() {
+            treasury.proposals.remove(&proposal_id);
+        }
+        Ok(tx_id)
+    }
+    
+    /// Проверяет, что средства казны тратятся только одобренными заявками, а доля награды не выпускается вне блока
+    pub(crate) fn check_treasury_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        if tx.sender == TREASURY_MINTER {
+            return Err(BlockchainError::InvalidTransaction(format!("{} only pays the treasury cut of a block", TREASURY_MINTER)));
+        }
+        
+        let treasury = self.treasury.as_ref().filter(|treasury| treasury.config.address == tx.sender);
+        let (treasury, approvals) = match (&tx.transaction_type, treasury) {
+            (TransactionType::TreasurySpend { approvals }, Some(treasury)) => (treasury, approvals),
+            (TransactionType::TreasurySpend { .. }, None) => {
+                return Err(BlockchainError::InvalidTransaction(format!("Treasury spend {} has a wrong sender", tx.id)));
+            },
+            (_, Some(_)) => {
+                return Err(BlockchainError::InvalidTransaction(format!("{} can only be spent by an approved treasury proposal", tx.sender)));
+            },
+            (_, None) => return Ok(()),
+        };
+        
+        let signers: BTreeSet<&String> = approvals.iter().collect();
+        if signers.len() != approvals.len() {
+            return Err(BlockchainError::InvalidTransaction(format!("Treasury spend {} repeats an approval", tx.id)));
+        }
+        for signer in &signers {
+            treasury.check_signer(signer)?;
+        }
+        if signers.len() < treasury.config.threshold {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Treasury spend {} has {} of {} required approvals", tx.id, signers.len(), treasury.config.threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    /// Цепочка с казной на кошельке с ключом, чтобы прямой расход был подписан
+    fn chain_with_treasury(share: f64) -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("treasury".to_string()).unwrap();
+        chain.enable_treasury(TreasuryConfig {
+            address: "treasury".to_string(),
+            share,
+            signers: BTreeSet::from(["alice".to_string(), "bob".to_string(), "carol".to_string()]),
+            threshold: 2,
+        }).unwrap();
+        chain
+    }
+    
+    fn mine(chain: &mut Blockchain, blocks: u64) {
+        for _ in 0..blocks {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+    }
+    
+    fn copy(chain: &Blockchain) -> Blockchain {
+        serde_json::from_str(&serde_json::to_string(chain).unwrap()).unwrap()
+    }
+    
+    #[test]
+    fn treasury_collects_its_share_of_every_block() {
+        let mut chain = chain_with_treasury(0.1);
+        mine(&mut chain, 5);
+        assert!((chain.treasury_balance() - 5.0 * 100.0 * 0.1).abs() < 1e-9);
+        assert!((chain.get_balance("miner") - 5.0 * 90.0).abs() < 1e-9);
+        assert!(chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn block_without_or_with_a_wrong_cut_is_rejected() {
+        let mut chain = chain_with_treasury(0.1);
+        mine(&mut chain, 1);
+        
+        let mut skipping = copy(&chain);
+        skipping.treasury = None;
+        mine(&mut skipping, 1);
+        let mut overpaying = copy(&chain);
+        overpaying.treasury.as_mut().unwrap().config.share = 0.2;
+        mine(&mut overpaying, 1);
+        
+        for producer in [skipping, overpaying] {
+            let result = chain.verify_block(producer.get_latest_block());
+            assert!(matches!(&result, Err(BlockchainError::InvalidBlock(reason)) if reason == "Wrong treasury cut in block # 2"), "{:?}", result);
+        }
+        
+        let mut honest = copy(&chain);
+        mine(&mut honest, 1);
+        chain.verify_block(honest.get_latest_block()).unwrap();
+    }
+    
+    #[test]
+    fn treasury_funds_move_only_by_an_approved_proposal() {
+        let mut chain = chain_with_treasury(0.5);
+        mine(&mut chain, 2);
+        
+        let keypair = chain.get_wallet_info("treasury").unwrap().keypair().unwrap().clone();
+        let mut direct = Transaction::new("treasury".to_string(), "mallory".to_string(), 10.0, TransactionType::Transfer);
+        direct.set_nonce(chain.get_account_nonce("treasury"));
+        direct.sign(&keypair);
+        let result = chain.add_transaction(direct);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.ends_with("approved treasury proposal")), "{:?}", result);
+        let forged = Transaction::new(
+            "treasury".to_string(), "mallory".to_string(), 10.0,
+            TransactionType::TreasurySpend { approvals: vec!["mallory".to_string(), "alice".to_string()] },
+        );
+        let result = chain.add_transaction(forged);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason == "mallory is not a treasury signer"), "{:?}", result);
+        assert!(chain.propose_treasury_spend("mallory", "mallory".to_string(), 10.0).is_err());
+        
+        let proposal = chain.propose_treasury_spend("alice", "dave".to_string(), 10.0).unwrap();
+        assert!(chain.approve_treasury_spend(proposal, "mallory").is_err());
+        let result = chain.execute_treasury_spend(proposal);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.ends_with("has 1 of 2 required approvals")), "{:?}", result);
+        assert!(chain.mempool().is_empty());
+        
+        chain.approve_treasury_spend(proposal, "bob").unwrap();
+        chain.execute_treasury_spend(proposal).unwrap();
+        mine(&mut chain, 1);
+        assert_eq!(chain.get_balance("dave"), 10.0);
+        assert_eq!(chain.get_balance("mallory"), 0.0);
+    }
+}
@@ -0,0 +1,547 @@
+use crate::balance_audit::{BalanceBucket, BalanceChangeReason};
+use crate::block::Block;
+use crate::blockchain::{Blockchain, ConsensusAlgorithm};
+use crate::errors::BlockchainError;
+use crate::key_registration::registered_keys;
+use crate::mempool::Pool;
+use crate::signing::{self, Keypair};
+use crate::transaction::{Transaction, TransactionType};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Как цепочка учитывает средства; выбирается при создании и потом не меняется
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedgerMode {
+    /// Балансы счетов
+    #[default]
+    Account,
+    /// Непотраченные выходы транзакций
+    Utxo,
+}
+
+impl LedgerMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            LedgerMode::Account => "account",
+            LedgerMode::Utxo => "UTXO",
+        }
+    }
+}
+
+/// Ссылка на выход: идентификатор создавшей его транзакции и номер выхода в ней
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct OutPoint {
+    pub txid: String,
+    pub index: u32,
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.txid, self.index)
+    }
+}
+
+/// Выход транзакции: сумма, которую может потратить владелец адреса
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxOutput {
+    pub address: String,
+    pub amount: f64,
+}
+
+/// Вход транзакции: потраченный выход и подпись его владельца
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxInput {
+    pub outpoint: OutPoint,
+    pub signature: String,
+    /// Открытый ключ владельца выхода в hex, которым проверяется подпись входа
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// Что подписывает владелец выхода: хеш входов и выходов транзакции и ссылка на выход
+fn input_message(digest: &str, outpoint: &OutPoint) -> Vec<u8> {
+    format!("input{}{}", digest, outpoint).into_bytes()
+}
+
+/// Выходы, которые уже тратят транзакции пула
+fn spent_in_pool(pending: &[Transaction]) -> HashSet<&OutPoint> {
+    pending.iter().flat_map(|tx| &tx.inputs).map(|input| &input.outpoint).collect()
+}
+
+impl Transaction {
+    /// Перевод UTXO-цепочки: тратит выходы `inputs` (ссылки вместе с самими выходами) и создает `outputs`.
+    /// Отправитель — владелец первого входа, получатель — первый выход на другой адрес. Комиссия должна
+    /// быть разницей между суммами входов и выходов; она задается явно, чтобы не терять точность на вычитании.
+    /// Входы остаются неподписанными: их подписывает владелец выходов (`sign_inputs`).
+    pub fn spend_at(inputs: Vec<(OutPoint, TxOutput)>, outputs: Vec<TxOutput>, fee: f64, timestamp: i64) -> Transaction {
+        let sender = inputs.first().map(|(_, spent)| spent.address.clone()).unwrap_or_default();
+        let receiver = outputs.iter().find(|output| output.address != sender).or(outputs.first())
+            .map(|output| output.address.clone())
+            .unwrap_or_default();
+        let amount = outputs.iter().filter(|output| output.address == receiver).map(|output| output.amount).sum();
+        
+        let mut tx = Transaction::new_at(sender, receiver, amount, TransactionType::Transfer, timestamp);
+        tx.fee = fee;
+        tx.inputs = inputs.into_iter()
+            .map(|(outpoint, _)| TxInput { outpoint, signature: String::new(), public_key: None })
+            .collect();
+        tx.outputs = outputs;
+        tx.reseal();
+        tx
+    }
+    
+    /// Подписывает все входы ключом их владельца; входы чужих выходов с такой подписью не пройдут проверку
+    pub fn sign_inputs(&mut self, keypair: &Keypair) {
+        let digest = self.spend_digest();
+        for input in &mut self.inputs {
+            input.signature = keypair.sign(&input_message(&digest, &input.outpoint));
+            input.public_key = Some(keypair.public_key_hex());
+        }
+    }
+    
+    /// Выходы, которые создает транзакция, вместе со ссылками на них
+    pub fn created_outputs(&self) -> impl Iterator<Item = (OutPoint, &TxOutput)> {
+        self.outputs.iter().enumerate().map(|(index, output)| (OutPoint { txid: self.id.clone(), index: index as u32 }, output))
+    }
+}
+
+impl Blockchain {
+    pub fn ledger_mode(&self) -> LedgerMode {
+        self.ledger_mode
+    }
+    
+    /// Переводит цепочку в UTXO-режим. Это возможно только у PoW-цепочки сразу после генезиса, пока нет
+    /// ни средств, ни казны, стейкинга и управления: стейки и их учет ведутся по счетам.
+    pub fn enable_utxo_mode(&mut self) -> Result<(), BlockchainError> {
+        if self.consensus_algorithm != ConsensusAlgorithm::ProofOfWork {
+            return Err(BlockchainError::ConsensusError("UTXO mode needs proof of work; validator stakes are account balances".to_string()));
+        }
+        let fresh = self.chain.len() == 1 && self.mempool.is_empty() && self.validators.is_empty()
+            && self.wallets.values().all(|wallet| wallet.balance == 0.0 && wallet.staking_balance == 0.0);
+        if !fresh {
+            return Err(BlockchainError::ConsensusError("UTXO mode can only be chosen at genesis".to_string()));
+        }
+        if self.treasury.is_some() || self.staking.is_some() || self.governance.is_some() || self.faucet.is_some() {
+            return Err(BlockchainError::ConsensusError("Treasury, staking, governance and the faucet need account balances".to_string()));
+        }
+        self.ledger_mode = LedgerMode::Utxo;
+        Ok(())
+    }
+    
+    /// Транзакции со входами и выходами допустимы только в UTXO-цепочке
+    pub(crate) fn check_account_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        if !tx.inputs.is_empty() || !tx.outputs.is_empty() {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} spends outputs, but this chain keeps account balances", tx.id)));
+        }
+        Ok(())
+    }
+    
+    /// Ошибка для операции счетного режима, вызванной в UTXO-цепочке
+    pub(crate) fn require_account_mode(&self, operation: &'static str) -> Result<(), BlockchainError> {
+        match self.ledger_mode {
+            LedgerMode::Account => Ok(()),
+            mode => Err(BlockchainError::WrongLedgerMode { operation, mode: mode.name() }),
+        }
+    }
+    
+    /// Непотраченные выходы адреса по порядку ссылок
+    pub fn unspent_outputs(&self, address: &str) -> Vec<(OutPoint, TxOutput)> {
+        let mut outputs: Vec<(OutPoint, TxOutput)> = self.utxo_set.iter()
+            .filter(|(_, output)| output.address == address)
+            .map(|(outpoint, output)| (outpoint.clone(), output.clone()))
+            .collect();
+        outputs.sort_by(|a, b| a.0.cmp(&b.0));
+        outputs
+    }
+    
+    /// Сумма непотраченных выходов адреса, сложенных по порядку ссылок
+    pub(crate) fn utxo_balance(&self, address: &str) -> f64 {
+        self.unspent_outputs(address).iter().map(|(_, output)| output.amount).sum()
+    }
+
+    
+    /// Собирает перевод `amount` с выходов отправителя, которые еще не тратит пул: выходы берутся
+    /// по порядку, пока не покроют сумму с минимальной комиссией, а остаток возвращается отправителю.
+    /// Входы подписываются ключом отправителя, поэтому он должен храниться на узле.
+    pub fn build_utxo_transfer(&self, sender: &str, receiver: &str, amount: f64) -> Result<Transaction, BlockchainError> {
+        if self.ledger_mode != LedgerMode::Utxo {
+            return Err(BlockchainError::WrongLedgerMode { operation: "Spending outputs", mode: self.ledger_mode.name() });
+        }
+        let keypair = self.wallets.get(sender)
+            .ok_or_else(|| BlockchainError::WalletNotFound { address: sender.to_string() })?
+            .keypair()
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Wallet {} holds no key", sender)))?;
+        let fee = Transaction::minimum_fee(&TransactionType::Transfer, amount);
+        let required = amount + fee;
+        
+        let pool = self.mempool.lock();
+        let spent = spent_in_pool(&pool.transactions);
+        let mut inputs = Vec::new();
+        let mut total = 0.0;
+        for (outpoint, output) in self.unspent_outputs(sender) {
+            if total >= required {
+                break;
+            }
+            if !spent.contains(&outpoint) {
+                total += output.amount;
+                inputs.push((outpoint, output));
+            }
+        }
+        if total < required {
+            return Err(BlockchainError::InsufficientBalance { required, available: total });
+        }
+        
+        let mut outputs = vec![TxOutput { address: receiver.to_string(), amount }];
+        if total - required > 0.0 {
+            outputs.push(TxOutput { address: sender.to_string(), amount: total - required });
+        }
+        let mut tx = Transaction::spend_at(inputs, outputs, fee, self.now_ts());
+        tx.set_hash_algorithm(self.hash_algorithm);
+        tx.sign_inputs(keypair);
+        Ok(tx)
+    }
+    
+    /// Проверяет перевод UTXO-цепочки: входы существуют среди непотраченных или в `created`, не потрачены
+    /// в `spent`, не повторяются и подписаны ключами владельцев (`owner_key`), выходы положительны,
+    /// а комиссия равна разнице сумм входов и выходов
+    pub(crate) fn check_utxo_transaction(&self, tx: &Transaction, spent: &HashSet<&OutPoint>, created: &HashMap<OutPoint, &TxOutput>) -> Result<(), BlockchainError> {
+        if tx.transaction_type != TransactionType::Transfer || tx.fee_payer.is_some() {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} is not a plain transfer; a UTXO chain only moves coins between outputs", tx.id)));
+        }
+        if tx.inputs.is_empty() || tx.outputs.is_empty() {
+            return Err(BlockchainError::WrongLedgerMode { operation: "Account transfer", mode: self.ledger_mode.name() });
+        }
+        
+        let digest = tx.spend_digest();
+        let mut input_total = 0.0;
+        let mut seen = HashSet::new();
+        for input in &tx.inputs {
+            if spent.contains(&input.outpoint) || !seen.insert(&input.outpoint) {
+                return Err(BlockchainError::InvalidTransaction(format!("Output {} is already being spent", input.outpoint)));
+            }
+            let Some(output) = self.utxo_set.get(&input.outpoint).or_else(|| created.get(&input.outpoint).copied()) else {
+                return Err(BlockchainError::InvalidTransaction(format!("Output {} does not exist or is spent", input.outpoint)));
+            };
+            let signed = self.owner_key(&output.address, input.public_key.as_deref())
+                .is_some_and(|public_key| signing::verify(&public_key, &input_message(&digest, &input.outpoint), &input.signature));
+            if !signed {
+                return Err(BlockchainError::InvalidSignature { tx_id: tx.id.clone() });
+            }
+            input_total += output.amount;
+        }
+        
+        if let Some(output) = tx.outputs.iter().find(|output| !output.amount.is_finite() || output.amount <= 0.0 || output.address.is_empty()) {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} has an invalid output of {} to '{}'", tx.id, output.amount, output.address)));
+        }
+        let output_total: f64 = tx.outputs.iter().map(|output| output.amount).sum();
+        if input_total < output_total {
+            return Err(BlockchainError::InsufficientBalance { required: output_total, available: input_total });
+        }
+        if (tx.fee - (input_total - output_total)).abs() > 1e-9 {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Fee {} of transaction {} is not the difference {} between its inputs and outputs", tx.fee, tx.id, input_total - output_total
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Проверяет перевод UTXO-цепочки перед постановкой в пул; выход, который уже тратит другая
+    /// транзакция пула, не принимается
+    pub(crate) fn check_queued_utxo_transaction(&self, transaction: &Transaction, pool: &Pool) -> Result<(), BlockchainError> {
+        if !transaction.is_valid() {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} has no sender, receiver or amount", transaction.id)));
+        }
+        self.check_not_pending(transaction, pool)?;
+        self.check_transaction_addresses(transaction)?;
+        self.check_capacity(pool)?;
+        
+        let pending = &pool.transactions;
+        self.check_minimum_fee(transaction)?;
+        self.check_base_fee(transaction, self.current_base_fee())?;
+        self.check_expiry(transaction, self.chain.len() as u64)?;
+        if transaction.transaction_type == TransactionType::RegisterKey {
+            let registered = registered_keys(pending);
+            self.check_sender_signature(transaction, &registered)?;
+            return self.check_key_registration(transaction, &registered, pending);
+        }
+        let spent = spent_in_pool(pending);
+        // Выходы транзакций пула тоже можно тратить: потомок попадет в блок только после создателя
+        let created = pending.iter().flat_map(Transaction::created_outputs).collect();
+        self.check_utxo_transaction(transaction, &spent, &created)
+    }
+    
+    /// Проверяет транзакции блока UTXO-цепочки: награда создает ровно один выход майнеру и привязана
+    /// к высоте блока сроком, а остальные транзакции тратят выходы, существовавшие до блока или созданные
+    /// выше по блоку, причем каждый не больше одного раза
+    pub(crate) fn check_utxo_block(&self, block: &Block, base_fee: Option<f64>) -> Result<(), BlockchainError> {
+        let mut spent = HashSet::new();
+        let mut created = HashMap::new();
+        let mut registered = HashMap::new();
+        for tx in &block.transactions {
+            if tx.sender == "BLOCKCHAIN_REWARD" {
+                let [output] = tx.outputs.as_slice() else {
+                    return Err(BlockchainError::InvalidBlock(format!("Reward in block # {} must create exactly one output", block.index)));
+                };
+                if !tx.inputs.is_empty() || output.address != tx.receiver || output.amount != tx.amount || tx.valid_until_height != Some(block.index) {
+                    return Err(BlockchainError::InvalidBlock(format!("Reward output in block # {} does not match the reward", block.index)));
+                }
+                continue;
+            }
+            
+            if !tx.is_valid() {
+                return Err(BlockchainError::InvalidTransaction(format!("Transaction {} is invalid", tx.id)));
+            }
+            self.check_minimum_fee(tx)?;
+            self.check_base_fee(tx, base_fee)?;
+            self.check_expiry(tx, block.index)?;
+            if tx.transaction_type == TransactionType::RegisterKey {
+                self.check_sender_signature(tx, &registered)?;
+                self.check_key_registration(tx, &registered, &block.transactions)?;
+                registered.extend(registered_keys([tx]));
+                continue;
+            }
+            self.check_utxo_transaction(tx, &spent, &created)?;
+            spent.extend(tx.inputs.iter().map(|input| &input.outpoint));
+            created.extend(tx.created_outputs());
+        }
+        Ok(())
+    }
+    
+    /// Тратит входы блока и добавляет его выходы, перенося суммы на балансы владельцев; транзакции
+    /// пула, чьи входы потрачены блоком, отбрасываются вместе с потомками
+    pub(crate) fn apply_utxo_block(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            let reason = BalanceChangeReason::Transaction { tx_id: tx.id.clone() };
+            for input in &tx.inputs {
+                if let Some(spent) = self.utxo_set.remove(&input.outpoint) {
+                    if let Some(wallet) = self.wallets.get_mut(&spent.address) {
+                        wallet.balance -= spent.amount;
+                    }
+                    self.record_balance_change(&spent.address, BalanceBucket::Balance, -spent.amount, reason.clone());
+                }
+            }
+            for (outpoint, output) in tx.created_outputs() {
+                self.get_or_create_wallet(&output.address).balance += output.amount;
+                self.record_balance_change(&output.address, BalanceBucket::Balance, output.amount, reason.clone());
+                self.utxo_set.insert(outpoint, output.clone());
+            }
+        }
+        self.record_block_ledger(block, &HashSet::new());
+        
+        // Выходы транзакций пула остаются доступны потомкам, пока в пуле остается их создатель;
+        // создатель стоит в пуле раньше потомка
+        let utxo_set = &self.utxo_set;
+        let mut created = HashSet::new();
+        self.mempool.get_mut().transactions.retain(|tx| {
+            let kept = tx.inputs.iter().all(|input| utxo_set.contains_key(&input.outpoint) || created.contains(&input.outpoint));
+            if kept {
+                created.extend(tx.created_outputs().map(|(outpoint, _)| outpoint));
+            }
+            kept
+        });
+    }
+    
+    /// Пересобирает множество непотраченных выходов по всей цепочке, например после загрузки с диска
+    pub fn rebuild_utxo_set(&mut self) {
+        self.utxo_set = self.utxo_set_before(self.chain.len());
+    }
+    
+    /// Непотраченные выходы перед блоком `height`; у счетной цепочки их нет
+    pub(crate) fn utxo_set_before(&self, height: usize) -> HashMap<OutPoint, TxOutput> {
+        let mut utxo_set = HashMap::new();
+        if self.ledger_mode != LedgerMode::Utxo {
+            return utxo_set;
+        }
+        for tx in self.chain.iter().take(height).flat_map(|block| &block.transactions) {
+            for input in &tx.inputs {
+                utxo_set.remove(&input.outpoint);
+            }
+            for (outpoint, output) in tx.created_outputs() {
+                utxo_set.insert(outpoint, output.clone());
+            }
+        }
+        utxo_set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::BlockchainBuilder;
+    use crate::entropy::OsEntropy;
+    
+    /// UTXO-цепочка, где у `rich` и `thief` свои ключи, а `rich` получил одну награду
+    fn utxo_chain() -> Blockchain {
+        let mut chain = BlockchainBuilder::new().difficulty(1).ledger_mode(LedgerMode::Utxo).accept_legacy_addresses(true).build().unwrap();
+        chain.create_wallet("rich".to_string()).unwrap();
+        chain.create_wallet("thief".to_string()).unwrap();
+        chain.mine_pending_transactions("rich".to_string()).unwrap();
+        chain
+    }
+    
+    /// Перевод всех выходов `rich` вору, подписанный ключом `keypair`
+    fn stolen_transfer(chain: &Blockchain, keypair: Option<&Keypair>) -> Transaction {
+        let inputs = chain.unspent_outputs("rich");
+        let total: f64 = inputs.iter().map(|(_, output)| output.amount).sum();
+        let outputs = vec![TxOutput { address: "thief".to_string(), amount: total - 1.0 }];
+        let mut tx = Transaction::spend_at(inputs, outputs, 1.0, chain.now_ts());
+        if let Some(keypair) = keypair {
+            tx.sign_inputs(keypair);
+        }
+        tx
+    }
+    
+    #[test]
+    fn builder_refuses_utxo_mode_without_proof_of_work() {
+        let built = BlockchainBuilder::new().consensus(ConsensusAlgorithm::ProofOfStake).ledger_mode(LedgerMode::Utxo).accept_legacy_addresses(true).build();
+        assert!(matches!(built, Err(BlockchainError::ConsensusError(_))));
+        assert_eq!(utxo_chain().ledger_mode(), LedgerMode::Utxo);
+    }
+    
+    #[test]
+    fn change_returns_to_the_sender() {
+        let mut chain = utxo_chain();
+        let tx = chain.build_utxo_transfer("rich", "thief", 30.0).unwrap();
+        let fee = tx.fee;
+        chain.add_transaction(tx).unwrap();
+        chain.mine_pending_transactions("thief".to_string()).unwrap();
+        
+        let change = chain.unspent_outputs("rich");
+        assert_eq!(change.len(), 1);
+        assert!((change[0].1.amount - (100.0 - 30.0 - fee)).abs() < 1e-9);
+        assert!((chain.get_balance("rich") - (100.0 - 30.0 - fee)).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn output_cannot_be_spent_twice_in_the_mempool() {
+        let mut chain = utxo_chain();
+        let first = chain.build_utxo_transfer("rich", "thief", 30.0).unwrap();
+        let second = chain.build_utxo_transfer("rich", "thief", 20.0).unwrap();
+        assert_eq!(first.inputs[0].outpoint, second.inputs[0].outpoint);
+        chain.add_transaction(first).unwrap();
+        assert!(matches!(chain.add_transaction(second.clone()), Err(BlockchainError::InvalidTransaction(_))));
+        
+        // Новый перевод уже не берет занятый выход, и взять его больше нечем
+        assert!(matches!(chain.build_utxo_transfer("rich", "thief", 20.0), Err(BlockchainError::InsufficientBalance { .. })));
+    }
+    
+    #[test]
+    fn stranger_cannot_spend_outputs_of_another_address() {
+        let mut chain = utxo_chain();
+        let thief_key = chain.get_wallet_info("thief").unwrap().keypair().unwrap().clone();
+        
+        let unsigned = stolen_transfer(&chain, None);
+        assert!(matches!(chain.add_transaction(unsigned), Err(BlockchainError::InvalidSignature { .. })));
+        let forged = stolen_transfer(&chain, Some(&thief_key));
+        assert!(matches!(chain.add_transaction(forged), Err(BlockchainError::InvalidSignature { .. })));
+        assert_eq!(chain.get_balance("rich"), 100.0);
+        
+        let owner_key = chain.get_wallet_info("rich").unwrap().keypair().unwrap().clone();
+        let signed = stolen_transfer(&chain, Some(&owner_key));
+        assert!(chain.add_transaction(signed).is_ok());
+    }
+    
+    #[test]
+    fn block_spending_foreign_outputs_is_rejected() {
+        let mut chain = utxo_chain();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        peer.rebuild_utxo_set();
+        let stranger = Keypair::generate(&OsEntropy);
+        
+        let stolen = stolen_transfer(&chain, Some(&stranger));
+        chain.mempool.get_mut().transactions.push(stolen);
+        chain.mine_pending_transactions("thief".to_string()).unwrap();
+        let block = chain.get_latest_block().clone();
+        assert!(matches!(peer.accept_block(block), Err(BlockchainError::InvalidSignature { .. })));
+    }
+    
+    #[test]
+    fn node_without_the_key_cannot_build_a_transfer() {
+        let mut chain = utxo_chain();
+        let tx = chain.build_utxo_transfer("rich", "miner", 20.0).unwrap();
+        chain.add_transaction(tx).unwrap();
+        chain.mine_pending_transactions("rich".to_string()).unwrap();
+        assert!(chain.get_balance("miner") > 0.0);
+        assert!(matches!(chain.build_utxo_transfer("miner", "thief", 10.0), Err(BlockchainError::InvalidTransaction(_))));
+    }
+    
+    #[test]
+    fn block_spending_an_output_twice_is_rejected() {
+        let mut chain = utxo_chain();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        peer.rebuild_utxo_set();
+        let first = chain.build_utxo_transfer("rich", "thief", 30.0).unwrap();
+        let second = chain.build_utxo_transfer("rich", "thief", 20.0).unwrap();
+        
+        // Пул узла двойную трату не примет, поэтому блок собирается в обход него
+        chain.mempool.get_mut().transactions.extend([first, second]);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        let block = chain.get_latest_block().clone();
+        let result = peer.accept_block(block);
+        assert!(matches!(&result, Err(BlockchainError::InvalidTransaction(reason)) if reason.contains("is already being spent")), "{:?}", result);
+        assert_eq!(peer.chain.len(), 2);
+        assert_eq!(peer.get_balance("rich"), 100.0);
+    }
+    
+    #[test]
+    fn balances_follow_outputs_across_blocks_and_replay() {
+        let mut chain = utxo_chain();
+        let mut peer: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        peer.rebuild_utxo_set();
+        
+        for (sender, receiver, amount) in [("rich", "thief", 30.0), ("thief", "rich", 10.0), ("rich", "miner", 25.0)] {
+            let tx = chain.build_utxo_transfer(sender, receiver, amount).unwrap();
+            let (outpoint, output) = tx.created_outputs().next().map(|(outpoint, output)| (outpoint, output.clone())).unwrap();
+            chain.add_transaction(tx).unwrap();
+            // Выход транзакции пула можно тратить до ее подтверждения
+            if receiver == "thief" {
+                let fee = Transaction::minimum_fee(&TransactionType::Transfer, 5.0);
+                let outputs = vec![TxOutput { address: "rich".to_string(), amount: 5.0 }, TxOutput { address: "thief".to_string(), amount: output.amount - 5.0 - fee }];
+                let mut child = Transaction::spend_at(vec![(outpoint, output)], outputs, fee, chain.now_ts());
+                child.sign_inputs(chain.get_wallet_info("thief").unwrap().keypair().unwrap());
+                chain.add_transaction(child).unwrap();
+            }
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+        
+        // Все созданные монеты лежат в непотраченных выходах: комиссии входят в награды
+        let addresses = ["rich", "thief", "miner"];
+        let total: f64 = addresses.iter().map(|address| chain.get_balance(address)).sum();
+        assert!((total - 4.0 * 100.0).abs() < 1e-9, "{}", total);
+        for address in addresses {
+            assert!((chain.get_balance(address) - chain.utxo_balance(address)).abs() < 1e-9, "{}", address);
+        }
+        assert!((chain.get_balance("thief") - (30.0 - 5.0 - 10.0 - Transaction::minimum_fee(&TransactionType::Transfer, 5.0) - Transaction::minimum_fee(&TransactionType::Transfer, 10.0))).abs() < 1e-9);
+        
+        // Узел, получающий блоки по одному, и пересборка с диска приходят к тем же выходам
+        for block in &chain.chain[2..] {
+            peer.accept_block(block.clone()).unwrap();
+        }
+        let mut reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        reloaded.rebuild_utxo_set();
+        for address in addresses {
+            assert_eq!(peer.unspent_outputs(address), chain.unspent_outputs(address), "{}", address);
+            assert_eq!(reloaded.unspent_outputs(address), chain.unspent_outputs(address), "{}", address);
+            assert_eq!(peer.get_balance(address), chain.get_balance(address), "{}", address);
+        }
+        let report = chain.rebuild_state_from_chain(true);
+        assert!(report.is_clean(), "{:?}", report.differences);
+        assert!(chain.is_chain_valid());
+    }
+    
+    #[test]
+    fn account_operations_fail_on_a_utxo_chain() {
+        let mut chain = utxo_chain();
+        let wrong_mode = |result: Result<(), BlockchainError>| matches!(result, Err(BlockchainError::WrongLedgerMode { mode: "UTXO", .. }));
+        assert!(wrong_mode(chain.add_validator("rich".to_string(), 10.0)));
+        assert!(wrong_mode(chain.allocate_at_genesis("rich", 10.0)));
+        
+        let mut tx = Transaction::new("rich".to_string(), "thief".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("rich"));
+        chain.sign_as_sender(&mut tx);
+        assert!(wrong_mode(chain.add_transaction(tx)));
+        assert_eq!(chain.get_balance("rich"), 100.0);
+        assert!(chain.mempool().is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::entropy::{EntropySource, OsEntropy};
+use crate::mining::hash_rate;
+use crate::trace::{debug, warn};
+use crate::address::{address_bytes, Network};
+use crate::signing::Keypair;
+use crate::wallet::{Wallet, GENERATED_ADDRESS_BYTES};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Сколько адресов поток перебирает между проверками флагов остановки
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+/// Как часто вызывающий поток сообщает о ходе поиска
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Как часто вызывающий поток проверяет, не закончили ли потоки поиска
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Больше стольких ожидаемых попыток поиск считается практически невыполнимым
+const FEASIBLE_ATTEMPTS: f64 = 1e12;
+
+/// Ошибки поиска адреса с префиксом
+#[derive(Error, Debug, PartialEq)]
+pub enum VanityError {
+    #[error("Prefix is empty")]
+    EmptyPrefix,
+    
+    #[error("Prefix is longer than the {max} random characters of an address")]
+    PrefixTooLong { max: usize },
+    
+    #[error("Character {character:?} never appears in addresses")]
+    ImpossibleCharacter { character: char },
+    
+    #[error("No matching address after {attempts} attempts")]
+    TimedOut { attempts: u64 },
+    
+    #[error("Search was cancelled after {attempts} attempts")]
+    Cancelled { attempts: u64 },
+}
+
+/// Сколько уже перебрано
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VanityProgress {
+    pub attempts: u64,
+    pub elapsed: Duration,
+    /// Адресов в секунду с предыдущего отчета
+    pub attempts_per_second: f64,
+    /// Сколько попыток нужно в среднем
+    pub expected_attempts: f64,
+}
+
+/// Префикс в виде полубайтов случайной части адреса. Адрес пишется строчными hex-цифрами,
+/// поэтому без учета регистра заглавные буквы совпадают со строчными, а с учетом — никогда.
+fn prefix_nibbles(network: Network, prefix: &str, case_sensitive: bool) -> Result<Vec<u8>, VanityError> {
+    let prefix = prefix.strip_prefix(network.prefix()).unwrap_or(prefix);
+    if prefix.is_empty() {
+        return Err(VanityError::EmptyPrefix);
+    }
+    if prefix.len() > 2 * GENERATED_ADDRESS_BYTES {
+        return Err(VanityError::PrefixTooLong { max: 2 * GENERATED_ADDRESS_BYTES });
+    }
+    
+    prefix.chars()
+        .map(|character| {
+            let digit = if case_sensitive { character } else { character.to_ascii_lowercase() };
+            match digit {
+                '0'..='9' | 'a'..='f' => digit.to_digit(16).map(|nibble| nibble as u8),
+                _ => None,
+            }
+            .ok_or(VanityError::ImpossibleCharacter { character })
+        })
+        .collect()
+}
+
+/// Среднее число попыток до адреса с префиксом; ошибка, если такого адреса не бывает
+pub fn vanity_difficulty(network: Network, prefix: &str, case_sensitive: bool) -> Result<f64, VanityError> {
+    prefix_nibbles(network, prefix, case_sensitive).map(|nibbles| 16f64.powi(nibbles.len() as i32))
+}
+
+/// Начинаются ли случайные байты адреса с этих полубайтов
+fn matches(bytes: &[u8], nibbles: &[u8]) -> bool {
+    nibbles.iter().enumerate().all(|(i, &nibble)| {
+        bytes.get(i / 2).is_some_and(|&byte| if i % 2 == 0 { byte >> 4 == nibble } else { byte & 0x0f == nibble })
+    })
+}
+
+/// Ищет адрес сети, у которого после ее префикса идет `prefix`, на `threads` потоках до первого совпадения
+pub fn generate_vanity(network: Network, prefix: &str, case_sensitive: bool, threads: usize, timeout: Duration) -> Result<Wallet, VanityError> {
+    generate_vanity_with(network, prefix, case_sensitive, threads, timeout, &AtomicBool::new(false), |_| {})
+}
+
+/// То же, что `generate_vanity`, с отменой и отчетами о ходе поиска. Адреса выводятся так же, как
+/// у `wallet new`, из ключа случайной пары, поэтому найденный кошелек ничем не отличается от обычного.
+/// Выставленный `cancel` останавливает все потоки в пределах нескольких тысяч попыток.
+pub fn generate_vanity_with<F>(
+    network: Network,
+    prefix: &str,
+    case_sensitive: bool,
+    threads: usize,
+    timeout: Duration,
+    cancel: &AtomicBool,
+    mut on_progress: F,
+) -> Result<Wallet, VanityError>
+where
+    F: FnMut(VanityProgress),
+{
+    let nibbles = prefix_nibbles(network, prefix, case_sensitive)?;
+    let expected_attempts = 16f64.powi(nibbles.len() as i32);
+    if expected_attempts > FEASIBLE_ATTEMPTS {
+        warn!(prefix, expected_attempts, "Vanity prefix is unlikely to be found");
+    }
+    
+    let attempts = AtomicU64::new(0);
+    let found = AtomicBool::new(false);
+    let winner: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+    let started = Instant::now();
+    let deadline = started + timeout;
+    let observer = thread::current();
+    
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                let (nibbles, attempts, found, winner, observer) = (&nibbles, &attempts, &found, &winner, &observer);
+                let mut seed = [0; 32];
+                OsEntropy.fill_bytes(&mut seed);
+                scope.spawn(move || {
+                    let mut rng = StdRng::from_seed(seed);
+                    let mut secret = [0; 32];
+                    let mut tried = 0;
+                    loop {
+                        if tried == STOP_CHECK_INTERVAL {
+                            attempts.fetch_add(tried, Ordering::Relaxed);
+                            tried = 0;
+                            if found.load(Ordering::Relaxed) || cancel.load(Ordering::Relaxed) || Instant::now() >= deadline {
+                                break;
+                            }
+                        }
+                        tried += 1;
+                        
+                        rng.fill_bytes(&mut secret);
+                        if matches(&address_bytes(&Keypair::from_secret(secret).public_key()), nibbles) {
+                            if !found.swap(true, Ordering::Relaxed) {
+                                *winner.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(secret);
+                            }
+                            break;
+                        }
+                    }
+                    attempts.fetch_add(tried, Ordering::Relaxed);
+                    observer.unpark();
+                })
+            })
+            .collect();
+        
+        let mut previous = VanityProgress { expected_attempts, ..VanityProgress::default() };
+        let mut next_report = PROGRESS_INTERVAL;
+        while !workers.iter().all(|worker| worker.is_finished()) {
+            thread::park_timeout(PROGRESS_POLL_INTERVAL);
+            let elapsed = started.elapsed();
+            if elapsed < next_report || found.load(Ordering::Relaxed) {
+                continue;
+            }
+            
+            next_report = elapsed + PROGRESS_INTERVAL;
+            let tried = attempts.load(Ordering::Relaxed);
+            let progress = VanityProgress {
+                attempts: tried,
+                elapsed,
+                attempts_per_second: hash_rate(tried.saturating_sub(previous.attempts), elapsed.saturating_sub(previous.elapsed)),
+                expected_attempts,
+            };
+            debug!(attempts = progress.attempts, rate = progress.attempts_per_second, "Vanity search in progress");
+            on_progress(progress);
+            previous = progress;
+        }
+    });
+    
+    let attempts = attempts.into_inner();
+    match winner.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+        Some(secret) => Ok(Wallet::from_keypair(Keypair::from_secret(secret), network)),
+        None if cancel.load(Ordering::Relaxed) => Err(VanityError::Cancelled { attempts }),
+        None => Err(VanityError::TimedOut { attempts }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::Address;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::{Transaction, TransactionType};
+    use std::sync::Arc;
+    
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    
+    #[test]
+    fn short_prefix_is_found_with_a_key_derived_address() {
+        let network = Network::Mainnet;
+        for (prefix, case_sensitive) in [("7", true), ("AB", false)] {
+            let wallet = generate_vanity(network, prefix, case_sensitive, 2, TIMEOUT).unwrap();
+            let expected_start = format!("{}{}", network.prefix(), prefix.to_ascii_lowercase());
+            assert!(wallet.address.starts_with(&expected_start), "{}", wallet.address);
+            let keypair = wallet.keypair().unwrap();
+            assert_eq!(Address::parse(&wallet.address).unwrap(), Address::from_public_key(network, &keypair.public_key()));
+        }
+        // Префикс можно писать вместе с префиксом сети
+        let prefixed = format!("{}c", network.prefix());
+        assert!(generate_vanity(network, &prefixed, true, 1, TIMEOUT).unwrap().address.starts_with(&prefixed));
+    }
+    
+    #[test]
+    fn vanity_wallet_signs_like_any_other() {
+        let wallet = generate_vanity(Network::Mainnet, "5", true, 1, TIMEOUT).unwrap();
+        let address = wallet.address.clone();
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.add_wallet(wallet).unwrap();
+        chain.mine_pending_transactions(address.clone()).unwrap();
+        
+        let mut tx = Transaction::new(address.clone(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(&address));
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert_eq!(chain.get_balance("bob"), 10.0);
+        assert_eq!(chain.get_balance(&address), 100.0 - 10.01);
+    }
+    
+    #[test]
+    fn impossible_prefixes_fail_before_searching() {
+        let network = Network::Mainnet;
+        assert_eq!(vanity_difficulty(network, "abc", true), Ok(4096.0));
+        assert_eq!(generate_vanity(network, "ag", false, 1, TIMEOUT).err(), Some(VanityError::ImpossibleCharacter { character: 'g' }));
+        assert_eq!(generate_vanity(network, "A", true, 1, TIMEOUT).err(), Some(VanityError::ImpossibleCharacter { character: 'A' }));
+        assert_eq!(generate_vanity(network, "", true, 1, TIMEOUT).err(), Some(VanityError::EmptyPrefix));
+        let too_long = "0".repeat(2 * GENERATED_ADDRESS_BYTES + 1);
+        assert_eq!(generate_vanity(network, &too_long, true, 1, TIMEOUT).err(), Some(VanityError::PrefixTooLong { max: 2 * GENERATED_ADDRESS_BYTES }));
+    }
+    
+    #[test]
+    fn cancellation_and_timeout_stop_every_worker() {
+        let network = Network::Mainnet;
+        let unreachable = "0".repeat(2 * GENERATED_ADDRESS_BYTES);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let canceller = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(PROGRESS_INTERVAL + Duration::from_millis(200));
+                cancel.store(true, Ordering::Relaxed);
+            })
+        };
+        
+        let started = Instant::now();
+        let mut reports = Vec::new();
+        let result = generate_vanity_with(network, &unreachable, true, 4, TIMEOUT, &cancel, |progress| reports.push(progress));
+        canceller.join().unwrap();
+        assert!(matches!(result, Err(VanityError::Cancelled { attempts }) if attempts > 0), "{:?}", result.err());
+        assert!(started.elapsed() < Duration::from_secs(5), "{:?}", started.elapsed());
+        assert!(!reports.is_empty());
+        assert!(reports.iter().all(|progress| progress.expected_attempts == 16f64.powi(2 * GENERATED_ADDRESS_BYTES as i32)));
+        
+        let started = Instant::now();
+        let result = generate_vanity(network, &unreachable, true, 2, Duration::from_millis(200));
+        assert!(matches!(result, Err(VanityError::TimedOut { .. })), "{:?}", result.err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::transaction::{Transaction, TransactionType};
+use crate::wallet::Wallet;
+use serde::{Serialize, Deserialize};
+
+/// Вестинг-грант: часть баланса получателя, которая открывается линейно после клиффа
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VestingGrant {
+    pub total: f64,
+    pub start_height: u64,
+    /// До конца клиффа не открыто ничего; в его последний блок открывается накопленная доля
+    pub cliff_blocks: u64,
+    /// Через сколько блоков от начала открыта вся сумма
+    pub duration_blocks: u64,
+}
+
+impl VestingGrant {
+    /// Грант, который создает транзакция; None для транзакций других типов
+    pub fn from_transaction(tx: &Transaction) -> Option<VestingGrant> {
+        match tx.transaction_type {
+            TransactionType::VestingGrant { start_height, cliff_blocks, duration_blocks } => {
+                Some(VestingGrant { total: tx.amount, start_height, cliff_blocks, duration_blocks })
+            },
+            _ => None,
+        }
+    }
+    
+    /// Сколько открыто к блоку указанной высоты
+    pub fn vested_at(&self, height: u64) -> f64 {
+        let elapsed = height.saturating_sub(self.start_height);
+        if height < self.start_height || elapsed < self.cliff_blocks {
+            return 0.0;
+        }
+        if elapsed >= self.duration_blocks {
+            return self.total;
+        }
+        self.total * elapsed as f64 / self.duration_blocks as f64
+    }
+    
+    /// Сколько еще закрыто к блоку указанной высоты
+    pub fn unvested_at(&self, height: u64) -> f64 {
+        self.total - self.vested_at(height)
+    }
+}
+
+/// Баланс кошелька с разбивкой по вестингу
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BalanceDetails {
+    /// Весь баланс, включая закрытую часть грантов
+    pub total: f64,
+    /// Сколько можно потратить в следующем блоке
+    pub spendable: f64,
+    pub vested: f64,
+    pub unvested: f64,
+    pub staked: f64,
+}
+
+impl BalanceDetails {
+    /// Разбивка баланса кошелька для блока указанной высоты; без кошелька все по нулям
+    pub(crate) fn of(wallet: Option<&Wallet>, height: u64) -> Self {
+        let Some(wallet) = wallet else {
+            return BalanceDetails { total: 0.0, spendable: 0.0, vested: 0.0, unvested: 0.0, staked: 0.0 };
+        };
+        
+        let unvested = wallet.unvested(height);
+        BalanceDetails {
+            total: wallet.balance,
+            spendable: wallet.spendable(height),
+            vested: wallet.vesting.iter().map(|grant| grant.total).sum::<f64>() - unvested,
+            unvested,
+            staked: wallet.staking_balance,
+        }
+    }
+}
+
+impl Blockchain {
+    /// Ставит в очередь грант: `total` с баланса `funder` зачисляется `beneficiary` и открывается
+    /// линейно за `duration_blocks` блоков от `start_height`, но не раньше конца клиффа.
+    /// Возвращает идентификатор транзакции.
+    pub fn grant_vesting(&mut self, funder: &str, beneficiary: &str, total: f64, start_height: u64, cliff_blocks: u64, duration_blocks: u64) -> Result<String, BlockchainError> {
+        let mut tx = self.new_transaction(
+            funder.to_string(),
+            beneficiary.to_string(),
+            total,
+            TransactionType::VestingGrant { start_height, cliff_blocks, duration_blocks },
+        );
+        if let Some(base_fee) = self.current_base_fee() {
+            tx.fee = tx.fee.max(base_fee * tx.weight() as f64);
+        }
+        self.sign_as_sender(&mut tx);
+        
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Баланс адреса с разбивкой на открытую и закрытую части грантов
+    pub fn get_balance_detailed(&self, address: &str) -> BalanceDetails {
+        BalanceDetails::of(self.wallets.get(address), self.chain.len() as u64)
+    }
+    
+    /// Сколько адрес может потратить в блоке указанной высоты
+    pub fn spendable_balance(&self, address: &str, height: u64) -> f64 {
+        self.wallets.get(address).map_or(0.0, |wallet| wallet.spendable(height))
+    }
+    
+    /// Проверяет параметры гранта
+    pub(crate) fn check_vesting_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        let Some(grant) = VestingGrant::from_transaction(tx) else {
+            return Ok(());
+        };
+        if grant.duration_blocks == 0 || grant.cliff_blocks > grant.duration_blocks {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Vesting grant {} has cliff {} and duration {} blocks", tx.id, grant.cliff_blocks, grant.duration_blocks
+            )));
+        }
+        if grant.total <= 0.0 {
+            return Err(BlockchainError::InvalidTransaction(format!("Vesting grant {} has no amount", tx.id)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    
+    fn chain_with_founder() -> Blockchain {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("founder".to_string()).unwrap();
+        chain.create_wallet("bob".to_string()).unwrap();
+        chain.mine_pending_transactions("founder".to_string()).unwrap();
+        chain
+    }
+    
+    fn mine_until(chain: &mut Blockchain, height: u64) {
+        while (chain.chain.len() as u64) < height {
+            chain.mine_pending_transactions("miner".to_string()).unwrap();
+        }
+    }
+    
+    fn send(chain: &mut Blockchain, from: &str, amount: f64) -> Result<(), BlockchainError> {
+        let mut tx = Transaction::new(from.to_string(), "carol".to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(from));
+        chain.sign_as_sender(&mut tx);
+        chain.add_transaction(tx)
+    }
+    
+    #[test]
+    fn grant_unlocks_linearly_after_the_cliff() {
+        let grant = VestingGrant { total: 100.0, start_height: 10, cliff_blocks: 4, duration_blocks: 10 };
+        assert_eq!(grant.vested_at(5), 0.0);
+        assert_eq!(grant.vested_at(13), 0.0);
+        assert_eq!(grant.vested_at(14), 40.0);
+        assert_eq!(grant.vested_at(19), 90.0);
+        assert_eq!(grant.vested_at(20), 100.0);
+        assert_eq!(grant.unvested_at(1_000), 0.0);
+    }
+    
+    #[test]
+    fn spending_is_limited_to_the_vested_part() {
+        let mut chain = chain_with_founder();
+        chain.grant_vesting("founder", "bob", 60.0, 2, 4, 10).unwrap();
+        mine_until(&mut chain, 3);
+        assert_eq!(chain.get_balance("bob"), 60.0);
+        assert!(matches!(send(&mut chain, "bob", 10.0), Err(BlockchainError::InsufficientBalance { .. })));
+        
+        // Клифф кончается на высоте 6: открыто 4 блока из 10
+        mine_until(&mut chain, 6);
+        assert_eq!(chain.spendable_balance("bob", 5), 0.0);
+        assert_eq!(chain.spendable_balance("bob", 6), 24.0);
+        assert!(matches!(send(&mut chain, "bob", 30.0), Err(BlockchainError::InsufficientBalance { .. })));
+        send(&mut chain, "bob", 20.0).unwrap();
+        
+        mine_until(&mut chain, 12);
+        let balance = chain.get_balance("bob");
+        assert!(balance < 40.0);
+        assert!((chain.spendable_balance("bob", 11) - (balance - 6.0)).abs() < 1e-9);
+        assert_eq!(chain.spendable_balance("bob", 12), balance);
+        let details = chain.get_balance_detailed("bob");
+        assert_eq!((details.vested, details.unvested, details.spendable), (60.0, 0.0, balance));
+    }
+    
+    #[test]
+    fn overlapping_grants_combine() {
+        let mut chain = chain_with_founder();
+        chain.grant_vesting("founder", "bob", 60.0, 2, 0, 10).unwrap();
+        chain.grant_vesting("founder", "bob", 30.0, 7, 5, 10).unwrap();
+        mine_until(&mut chain, 3);
+        assert_eq!(chain.get_wallet_info("bob").unwrap().vesting.len(), 2);
+        
+        // На высоте 7 первый грант открыт на половину, второй еще нет
+        assert_eq!(chain.spendable_balance("bob", 7), 30.0);
+        // На высоте 12 первый открыт полностью, у второго кончился клифф
+        assert_eq!(chain.spendable_balance("bob", 12), 75.0);
+        assert_eq!(chain.spendable_balance("bob", 17), 90.0);
+        
+        mine_until(&mut chain, 12);
+        let details = chain.get_balance_detailed("bob");
+        assert_eq!((details.total, details.vested, details.unvested), (90.0, 75.0, 15.0));
+        assert!(matches!(send(&mut chain, "bob", 76.0), Err(BlockchainError::InsufficientBalance { .. })));
+        send(&mut chain, "bob", 70.0).unwrap();
+    }
+}
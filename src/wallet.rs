@@ -1,26 +1,184 @@
+#![deny(clippy::indexing_slicing)]
+
+use crate::address::{Address, Network};
+use crate::blockchain::Blockchain;
+use crate::entropy::EntropySource;
 use crate::errors::BlockchainError;
+use crate::signing::Keypair;
+use crate::transaction::Transaction;
+use crate::vesting::VestingGrant;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+
+/// Сколько байт хеша открытого ключа в адресе сети
+pub const GENERATED_ADDRESS_BYTES: usize = 16;
+
+/// Адрес сети, выведенный из открытого ключа, а не имя
+fn is_network_address(address: &str) -> bool {
+    matches!(Address::parse(address), Ok(Address::Network { .. }))
+}
+
+/// Положение транзакции в цепочке: высота блока и номер транзакции в нем
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxLocation {
+    pub block: u64,
+    pub position: u32,
+}
 
 /// Представляет кошелек в блокчейне с адресом, балансом и историей транзакций
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Wallet {
     pub address: String,
     pub balance: f64,
     pub staking_balance: f64,
-    pub transaction_history: Vec<String>,
+    /// Вестинг-гранты; их еще закрытая часть входит в `balance`, но не может быть потрачена
+    #[serde(default)]
+    pub vesting: Vec<VestingGrant>,
+    /// Причина заморозки; пока она задана, кошелек не может отправлять средства
+    #[serde(default)]
+    pub frozen: Option<String>,
+    /// Балансы выпущенных пользователями активов по их идентификаторам, в минимальных единицах
+    #[serde(default)]
+    pub assets: BTreeMap<String, u64>,
+    /// Накопленные награды, которые еще нужно перевести на баланс транзакцией `ClaimRewards`
+    #[serde(default)]
+    pub claimable_rewards: f64,
+    /// Высота блока, в котором кошелек закрыт; закрытый кошелек не может отправлять средства
+    #[serde(default)]
+    pub closed_at: Option<u64>,
+    /// Следующий ожидаемый порядковый номер транзакции по подтвержденным блокам
+    #[serde(default)]
+    pub next_nonce: u64,
+    /// Открытый ключ, за которым закреплен адрес: у имени — ключ из подтвержденной транзакции
+    /// `RegisterKey`, одинаковый на всех узлах, у адреса сети — ключ, из которого он выведен, если
+    /// узел его знает
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Ключевая пара, если кошелек создан на этом узле. В состояние цепочки и снимки не попадает:
+    /// секретные ключи хранит отдельный файл `keystore.json` каталога данных.
+    #[serde(skip)]
+    keypair: Option<Keypair>,
+    /// Транзакции из блоков цепочки; ведется только при добавлении блоков и не сохраняется на диск
+    #[serde(skip)]
+    history: Vec<TxLocation>,
 }
 
 impl Wallet {
-    /// Создает новый кошелек с указанным адресом и нулевыми балансами
+    /// Создает новый кошелек с указанным адресом и нулевыми балансами, без ключей
     pub fn new(address: String) -> Self {
         Wallet {
             address,
             balance: 0.0,
             staking_balance: 0.0,
-            transaction_history: Vec::new(),
+            vesting: Vec::new(),
+            frozen: None,
+            assets: BTreeMap::new(),
+            claimable_rewards: 0.0,
+            closed_at: None,
+            next_nonce: 0,
+            public_key: None,
+            keypair: None,
+            history: Vec::new(),
         }
     }
     
+    /// Кошелек новой ключевой пары с адресом сети, выведенным из ее открытого ключа
+    pub fn generate(entropy: &dyn EntropySource, network: Network) -> Self {
+        Wallet::from_keypair(Keypair::generate(entropy), network)
+    }
+    
+    /// Кошелек ключевой пары с адресом сети, выведенным из ее открытого ключа
+    pub fn from_keypair(keypair: Keypair, network: Network) -> Self {
+        let address = Address::from_public_key(network, &keypair.public_key()).to_string();
+        Wallet::with_keypair(address, keypair)
+    }
+    
+    /// Кошелек с ключевой парой. Адрес сети сразу закрепляется за ее открытым ключом, а имя —
+    /// только транзакцией `RegisterKey`, которую ставит в пул `Blockchain::create_wallet`.
+    pub fn with_keypair(address: String, keypair: Keypair) -> Self {
+        let public_key = is_network_address(&address).then(|| keypair.public_key_hex());
+        Wallet { public_key, keypair: Some(keypair), ..Wallet::new(address) }
+    }
+    
+    pub fn keypair(&self) -> Option<&Keypair> {
+        self.keypair.as_ref()
+    }
+    
+    /// Отдает кошельку ключевую пару из хранилища ключей узла
+    pub(crate) fn set_keypair(&mut self, keypair: Keypair) {
+        if is_network_address(&self.address) {
+            self.public_key = Some(keypair.public_key_hex());
+        }
+        self.keypair = Some(keypair);
+    }
+    
+    /// Переносит из прежнего состояния кошелька ключи, которых нет в цепочке: ключевую пару и
+    /// импортированный открытый ключ адреса сети. Восстановление по блокам их не возвращает.
+    pub(crate) fn keep_node_keys(&mut self, previous: &Wallet) {
+        if let Some(keypair) = &previous.keypair {
+            self.set_keypair(keypair.clone());
+        } else if is_network_address(&self.address) && self.public_key.is_none() {
+            self.public_key = previous.public_key.clone();
+        }
+    }
+    
+    /// Подписывает транзакцию этого кошелька его ключом
+    pub fn sign_transaction(&self, transaction: &mut Transaction) -> Result<(), BlockchainError> {
+        if transaction.sender != self.address {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} is sent from {}, not {}", transaction.id, transaction.sender, self.address)));
+        }
+        let keypair = self.keypair.as_ref()
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Wallet {} holds no key", self.address)))?;
+        transaction.sign(keypair);
+        Ok(())
+    }
+    
+    /// Берет на этот кошелек комиссию транзакции до `max_fee` и подписывает это его ключом;
+    /// отправитель подписывает транзакцию уже после спонсора
+    pub fn sponsor_transaction(&self, transaction: &mut Transaction, max_fee: f64) -> Result<(), BlockchainError> {
+        let keypair = self.keypair.as_ref()
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Wallet {} holds no key", self.address)))?;
+        transaction.sponsor(keypair, &self.address, max_fee);
+        Ok(())
+    }
+    
+    /// Закрытая к блоку указанной высоты часть грантов
+    pub fn unvested(&self, height: u64) -> f64 {
+        self.vesting.iter().map(|grant| grant.unvested_at(height)).sum()
+    }
+    
+    /// Сколько можно потратить в блоке указанной высоты
+    pub fn spendable(&self, height: u64) -> f64 {
+        (self.balance - self.unvested(height)).max(0.0)
+    }
+    
+    /// Число транзакций кошелька в цепочке
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+    
+    /// Положения транзакций кошелька в порядке цепочки
+    pub fn history_locations(&self) -> &[TxLocation] {
+        &self.history
+    }
+    
+    /// Транзакции кошелька в порядке цепочки; читаются из блоков по мере обхода
+    pub fn history<'a>(&'a self, chain: &'a Blockchain) -> impl Iterator<Item = &'a Transaction> {
+        self.history.iter().filter_map(|location| {
+            chain.chain.get(location.block as usize)?.transactions.get(location.position as usize)
+        })
+    }
+    
+    pub(crate) fn record(&mut self, location: TxLocation) {
+        self.history.push(location);
+    }
+    
+    pub(crate) fn clear_history(&mut self) {
+        self.history.clear();
+    }
+    
     /// Переводит указанную сумму с основного баланса на стейкинг для PoS
-    #[allow(dead_code)] // Помечаем как используемые
     pub fn stake(&mut self, amount: f64) -> Result<(), BlockchainError> {
         if amount > self.balance {
             return Err(BlockchainError::InsufficientBalance {
@@ -35,7 +193,6 @@ impl Wallet {
     }
     
     /// Возвращает указанную сумму со стейкинга на основной баланс
-    #[allow(dead_code)]
     pub fn unstake(&mut self, amount: f64) -> Result<(), BlockchainError> {
         if amount > self.staking_balance {
             return Err(BlockchainError::InsufficientBalance {
@@ -48,4 +205,79 @@ impl Wallet {
         self.balance += amount;
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    
+    const ADDRESSES: [&str; 3] = ["alice", "bob", "carol"];
+    
+    fn send(chain: &mut Blockchain, from: &str, to: &str, amount: f64) -> String {
+        let keypair = chain.get_wallet_info(from).unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new(from.to_string(), to.to_string(), amount, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce(from));
+        tx.sign(&keypair);
+        let id = tx.id.clone();
+        chain.add_transaction(tx).unwrap();
+        id
+    }
+    
+    /// Транзакции адреса, найденные полным обходом цепочки
+    fn scanned_history(chain: &Blockchain, address: &str) -> Vec<String> {
+        chain.chain.iter()
+            .flat_map(|block| &block.transactions)
+            .filter(|tx| tx.sender == address || tx.receiver == address)
+            .map(|tx| tx.id.clone())
+            .collect()
+    }
+    
+    fn assert_histories_match_scan(chain: &Blockchain) {
+        for address in ADDRESSES {
+            let wallet = chain.wallets.get(address).unwrap();
+            let history: Vec<String> = wallet.history(chain).map(|tx| tx.id.clone()).collect();
+            assert_eq!(history, scanned_history(chain, address), "{}", address);
+            assert_eq!(wallet.history_len(), history.len());
+        }
+    }
+    
+    #[test]
+    fn histories_match_a_chain_scan() {
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        for address in ADDRESSES {
+            chain.create_wallet(address.to_string()).unwrap();
+        }
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        send(&mut chain, "alice", "bob", 30.0);
+        send(&mut chain, "alice", "carol", 20.0);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        send(&mut chain, "bob", "carol", 5.0);
+        let cancelled = send(&mut chain, "alice", "bob", 1.0);
+        chain.cancel_pending_transaction(&cancelled).unwrap();
+        chain.mine_pending_transactions("bob".to_string()).unwrap();
+        let pending = send(&mut chain, "carol", "alice", 2.0);
+        
+        assert_histories_match_scan(&chain);
+        // Отмененная и ожидающая транзакции в историю не попадают
+        for wallet in chain.wallets.values() {
+            assert!(wallet.history(&chain).all(|tx| tx.id != cancelled && tx.id != pending));
+        }
+        
+        let dir = std::env::temp_dir().join(format!("hellochain-wallet-history-{}", std::process::id()));
+        chain.save(&dir).unwrap();
+        let restored = Blockchain::load(&dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_histories_match_scan(&restored);
+        for address in ADDRESSES {
+            assert_eq!(restored.wallets.get(address).unwrap().history_locations(), chain.wallets.get(address).unwrap().history_locations());
+        }
+    }
+    
+    #[test]
+    fn history_entry_is_smaller_than_a_transaction_id() {
+        assert!(std::mem::size_of::<TxLocation>() <= 16);
+        assert!(std::mem::size_of::<TxLocation>() < std::mem::size_of::<String>() + 64);
+    }
+}
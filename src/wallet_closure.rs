@@ -0,0 +1,218 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::errors::BlockchainError;
+use crate::fee_market::pays_base_fee;
+use crate::params::ClosedWalletPolicy;
+use crate::transaction::{Transaction, TransactionType};
+
+/// Сколько раз сумма перевода уменьшается на шаг представления f64, пока сумма с комиссией
+/// не сойдется с балансом в точности
+const MAX_SWEEP_STEPS: usize = 64;
+
+/// Делит баланс на сумму перевода и комиссию: сумма с комиссией равна балансу в точности, а комиссия
+/// не меньше `rate` от суммы и не меньше `floor`. Комиссия зависит от суммы, поэтому сумма ищется
+/// как неподвижная точка `amount = balance - max(rate * amount, floor)`.
+fn sweep_split(balance: f64, rate: f64, floor: f64) -> Option<(f64, f64)> {
+    let mut amount = (balance / (1.0 + rate)).min(balance - floor);
+    for _ in 0..MAX_SWEEP_STEPS {
+        if amount <= 0.0 {
+            return None;
+        }
+        let fee = balance - amount;
+        if amount + fee == balance && fee + f64::EPSILON >= rate * amount && fee >= floor {
+            return Some((amount, fee));
+        }
+        amount = amount.next_down();
+    }
+    None
+}
+
+impl Blockchain {
+    /// Закрыт ли кошелек
+    pub fn is_closed(&self, address: &str) -> bool {
+        self.wallets.get(address).is_some_and(|wallet| wallet.closed_at.is_some())
+    }
+    
+    /// Ставит в очередь закрытие кошелька: весь баланс за вычетом комиссии уходит на `destination`,
+    /// и после подтверждения кошелек больше не отправляет средства. Возвращает идентификатор транзакции.
+    pub fn close_wallet(&mut self, address: &str, destination: &str, signer: &str) -> Result<String, BlockchainError> {
+        if signer != address {
+            return Err(BlockchainError::InvalidTransaction(format!("{} cannot close wallet {}", signer, address)));
+        }
+        let balance = self.wallets.get(address)
+            .ok_or_else(|| BlockchainError::WalletNotFound { address: address.to_string() })?
+            .balance;
+        self.check_closable(address, self.chain.len() as u64)?;
+        if self.mempool.lock().transactions.iter().any(|tx| tx.sender == address) {
+            return Err(BlockchainError::InvalidTransaction(format!("Wallet {} has pending outgoing transactions", address)));
+        }
+        
+        // Вес не зависит от суммы, поэтому базовую комиссию можно посчитать по пробной транзакции
+        let now = self.now_ts();
+        let probe = Transaction::new_at(address.to_string(), destination.to_string(), balance, TransactionType::CloseWallet, now);
+        let floor = self.current_base_fee().map_or(0.0, |base_fee| base_fee * probe.weight() as f64);
+        let rate = Transaction::minimum_fee(&TransactionType::CloseWallet, 1.0);
+        let (amount, fee) = sweep_split(balance, rate, floor)
+            .ok_or(BlockchainError::InsufficientBalance { required: floor, available: balance })?;
+        
+        let mut tx = self.new_transaction_at(address.to_string(), destination.to_string(), amount, TransactionType::CloseWallet, now);
+        tx.fee = fee;
+        self.sign_as_sender(&mut tx);
+        let tx_id = tx.id.clone();
+        self.add_transaction(tx)?;
+        Ok(tx_id)
+    }
+    
+    /// Проверяет, что у кошелька не осталось средств, которые нельзя вывести переводом
+    fn check_closable(&self, address: &str, height: u64) -> Result<(), BlockchainError> {
+        let Some(wallet) = self.wallets.get(address) else {
+            return Err(BlockchainError::WalletNotFound { address: address.to_string() });
+        };
+        let reason = if wallet.closed_at.is_some() {
+            return Err(BlockchainError::WalletClosed { address: address.to_string() });
+        } else if wallet.staking_balance > 0.0 || self.validators.contains_key(address) {
+            "has bonded stake"
+        } else if wallet.unvested(height) > 0.0 {
+            "has unvested grants"
+        } else if wallet.claimable_rewards > 0.0 {
+            "has unclaimed rewards"
+        } else if wallet.assets.values().any(|&amount| amount > 0) {
+            "holds assets"
+        } else {
+            return Ok(());
+        };
+        Err(BlockchainError::InvalidTransaction(format!("Wallet {} {} and cannot be closed", address, reason)))
+    }
+    
+    /// Отклоняет отправки закрытых кошельков и, если так настроено, переводы на них; выплаты протокола
+    /// зачисляются всегда. Закрывающая транзакция проверяется, как `close_wallet`.
+    pub(crate) fn check_wallet_closure(&self, tx: &Transaction, height: u64) -> Result<(), BlockchainError> {
+        if self.is_closed(&tx.sender) {
+            return Err(BlockchainError::WalletClosed { address: tx.sender.clone() });
+        }
+        if self.params.closed_wallet_policy == ClosedWalletPolicy::Reject && pays_base_fee(tx) && self.is_closed(&tx.receiver) {
+            return Err(BlockchainError::WalletClosed { address: tx.receiver.clone() });
+        }
+        if tx.transaction_type == TransactionType::CloseWallet {
+            if tx.receiver == tx.sender {
+                return Err(BlockchainError::InvalidTransaction(format!("Wallet {} cannot be swept to itself", tx.sender)));
+            }
+            self.check_closable(&tx.sender, height)?;
+        }
+        Ok(())
+    }
+    
+    /// Закрывает кошельки по закрывающим транзакциям блока и снимает из пула их оставшиеся отправки. При `ClosedWalletPolicy::Reopen` перевод на закрытый кошелек его открывает.
+    pub(crate) fn apply_wallet_closures(&mut self, block: &Block) {
+        let reopen = self.params.closed_wallet_policy == ClosedWalletPolicy::Reopen;
+        let mut closed = Vec::new();
+        for tx in &block.transactions {
+            if tx.transaction_type == TransactionType::CloseWallet {
+                if let Some(wallet) = self.wallets.get_mut(&tx.sender) {
+                    wallet.closed_at = Some(block.index);
+                    closed.push(tx.sender.as_str());
+                }
+            } else if reopen && tx.amount > 0.0 {
+                if let Some(wallet) = self.wallets.get_mut(&tx.receiver) {
+                    wallet.closed_at = None;
+                }
+            }
+        }
+        
+        let stuck: Vec<String> = self.mempool.get_mut().transactions.iter()
+            .filter(|pending| closed.contains(&pending.sender.as_str()))
+            .map(|pending| pending.id.clone())
+            .collect();
+        for tx_id in stuck {
+            let _ = self.cancel_pending_transaction(&tx_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed_transfer, ChainFixture};
+    
+    /// alice с наградой за блок, bob с ключом для отправок
+    fn funded_chain() -> Blockchain {
+        ChainFixture::new().with_mining_reward(100.0).with_wallets(&["bob"], 0.0).with_block_mined_by("alice").build().unwrap().chain
+    }
+    
+    #[test]
+    fn sweep_split_adds_up_to_the_balance_exactly() {
+        let rate = Transaction::minimum_fee(&TransactionType::CloseWallet, 1.0);
+        for balance in [100.0, 0.3, 123.456789, 1e-3, 7.0 / 3.0, 1e9 + 0.1] {
+            let (amount, fee) = sweep_split(balance, rate, 0.0).unwrap();
+            assert_eq!(amount + fee, balance);
+            assert!(fee + f64::EPSILON >= rate * amount, "{}: fee {} for {}", balance, fee, amount);
+        }
+        let (_, fee) = sweep_split(10.0, rate, 0.5).unwrap();
+        assert!(fee >= 0.5);
+        assert_eq!(sweep_split(0.4, rate, 0.5), None);
+    }
+    
+    #[test]
+    fn sweep_leaves_exactly_zero_and_closes_the_wallet() {
+        let mut chain = funded_chain();
+        let balance = chain.get_balance("alice");
+        let tx_id = chain.close_wallet("alice", "bob", "alice").unwrap();
+        assert!(!chain.is_closed("alice"));
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let sweep = chain.get_latest_block().transactions.iter().find(|tx| tx.id == tx_id).unwrap().clone();
+        assert_eq!(sweep.amount + sweep.fee, balance);
+        assert_eq!(chain.get_balance("alice"), 0.0);
+        assert_eq!(chain.get_balance("bob"), sweep.amount);
+        assert!(chain.is_closed("alice"));
+        assert_eq!(chain.wallets["alice"].closed_at, Some(2));
+    }
+    
+    #[test]
+    fn closure_is_refused_while_funds_are_locked_or_outgoing() {
+        let mut chain = funded_chain();
+        assert!(chain.close_wallet("alice", "bob", "bob").is_err());
+        assert!(chain.close_wallet("alice", "alice", "alice").is_err());
+        
+        chain.add_transaction(signed_transfer(&chain, "alice", "bob", 10.0)).unwrap();
+        let pending = chain.close_wallet("alice", "carol", "alice").unwrap_err();
+        assert!(pending.to_string().contains("pending outgoing"), "{}", pending);
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        chain.add_validator("alice".to_string(), 20.0).unwrap();
+        let bonded = chain.close_wallet("alice", "carol", "alice").unwrap_err();
+        assert!(bonded.to_string().contains("has bonded stake"), "{}", bonded);
+    }
+    
+    #[test]
+    fn closed_wallet_cannot_send_and_receives_per_policy() {
+        let mut chain = funded_chain();
+        chain.close_wallet("alice", "bob", "alice").unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        assert!(matches!(chain.add_transaction(signed_transfer(&chain, "alice", "bob", 0.0001)), Err(BlockchainError::WalletClosed { address }) if address == "alice"));
+        assert!(matches!(chain.close_wallet("alice", "bob", "alice"), Err(BlockchainError::WalletClosed { .. })));
+        assert!(matches!(chain.add_transaction(signed_transfer(&chain, "bob", "alice", 5.0)), Err(BlockchainError::WalletClosed { address }) if address == "alice"));
+        
+        chain.params.closed_wallet_policy = ClosedWalletPolicy::Reopen;
+        chain.add_transaction(signed_transfer(&chain, "bob", "alice", 5.0)).unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        assert!(!chain.is_closed("alice"));
+        assert_eq!(chain.get_balance("alice"), 5.0);
+    }
+    
+    #[test]
+    fn closed_flag_survives_save_and_load() {
+        let mut chain = funded_chain();
+        chain.close_wallet("alice", "bob", "alice").unwrap();
+        chain.mine_pending_transactions("miner".to_string()).unwrap();
+        
+        let dir = std::env::temp_dir().join(format!("hellochain-closure-{}", std::process::id()));
+        chain.save(&dir).unwrap();
+        let loaded = Blockchain::load(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut loaded = loaded.unwrap();
+        assert!(loaded.is_closed("alice"));
+        assert!(matches!(loaded.add_transaction(signed_transfer(&loaded, "alice", "bob", 0.0001)), Err(BlockchainError::WalletClosed { .. })));
+    }
+}
@@ -0,0 +1,413 @@
+use crate::block::BlockHeader;
+use crate::blockchain::Blockchain;
+use crate::events::{ChainEvent, HookId};
+use crate::trace::warn;
+use crate::transaction::Transaction;
+use hmac::{Hmac, Mac};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Заголовок с HMAC-SHA256 подписью тела запроса
+pub const SIGNATURE_HEADER: &str = "X-Hellochain-Signature";
+/// Заголовок с названием события
+pub const EVENT_HEADER: &str = "X-Hellochain-Event";
+
+/// Какие события получает адрес вебхука
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventFilter {
+    BlockMined,
+    /// Подтвержденные транзакции, где отправитель или получатель входит в список
+    TransactionConfirmed(HashSet<String>),
+    ValidatorSlashed,
+}
+
+/// Адрес вебхука и события, которые на него отправляются
+#[derive(Debug, Clone)]
+pub struct WebhookTarget {
+    pub url: String,
+    pub filters: Vec<EventFilter>,
+}
+
+impl WebhookTarget {
+    pub fn new(url: impl Into<String>) -> Self {
+        WebhookTarget { url: url.into(), filters: Vec::new() }
+    }
+    
+    /// Добавляет фильтр событий
+    pub fn on(mut self, filter: EventFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+}
+
+/// Настройки рассылки вебхуков
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub targets: Vec<WebhookTarget>,
+    /// Секрет для подписи тела запроса
+    pub secret: String,
+    /// Ограничение на один запрос целиком
+    pub timeout: Duration,
+    /// Сколько раз повторять запрос после неудачи
+    pub max_retries: u32,
+    /// Пауза перед первым повтором; каждая следующая вдвое длиннее
+    pub initial_backoff: Duration,
+    /// Размер очереди доставки; события сверх нее отбрасываются
+    pub queue_capacity: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            targets: Vec::new(),
+            secret: String::new(),
+            timeout: Duration::from_secs(5),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            queue_capacity: 1024,
+        }
+    }
+}
+
+/// Тело запроса вебхука
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookPayload {
+    BlockMined { block: BlockHeader, transactions: usize },
+    TransactionConfirmed { address: String, block_index: u64, block_hash: String, transaction: Box<Transaction> },
+    ValidatorSlashed { validator: String, amount: f64 },
+}
+
+impl WebhookPayload {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookPayload::BlockMined { .. } => "block_mined",
+            WebhookPayload::TransactionConfirmed { .. } => "transaction_confirmed",
+            WebhookPayload::ValidatorSlashed { .. } => "validator_slashed",
+        }
+    }
+}
+
+/// Статистика доставки
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookStats {
+    /// Поставлено в очередь
+    pub queued: u64,
+    /// Доставлено с ответом 2xx
+    pub delivered: u64,
+    /// Не доставлено после всех повторов
+    pub failed: u64,
+    /// Число повторных запросов
+    pub retries: u64,
+    /// Отброшено из-за переполненной очереди
+    pub dropped: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    queued: AtomicU64,
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    retries: AtomicU64,
+    dropped: AtomicU64,
+}
+
+struct Delivery {
+    url: String,
+    event: &'static str,
+    body: Vec<u8>,
+}
+
+/// Рассыльщик вебхуков: события кладутся в ограниченную очередь и отправляются из отдельного потока
+pub struct WebhookDispatcher {
+    targets: Arc<Vec<WebhookTarget>>,
+    sender: SyncSender<Delivery>,
+    counters: Arc<Counters>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl WebhookDispatcher {
+    /// Запускает поток доставки
+    pub fn start(config: WebhookConfig) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(config.queue_capacity);
+        let counters = Arc::new(Counters::default());
+        let targets = Arc::new(config.targets.clone());
+        
+        let worker_counters = counters.clone();
+        let worker = thread::spawn(move || deliver_all(config, receiver, worker_counters));
+        
+        WebhookDispatcher { targets, sender, counters, worker: Some(worker) }
+    }
+    
+    /// Раскладывает событие по адресам и ставит запросы в очередь, не дожидаясь отправки
+    pub fn dispatch(&self, event: &ChainEvent) {
+        enqueue(&self.targets, &self.sender, &self.counters, event);
+    }
+    
+    pub fn stats(&self) -> WebhookStats {
+        self.counters.snapshot()
+    }
+    
+    /// Дожидается отправки всего, что уже в очереди, и останавливает поток.
+    /// Обработчики, зарегистрированные в цепочке, держат очередь открытой, поэтому их нужно снять раньше.
+    pub fn shutdown(mut self) -> WebhookStats {
+        let WebhookDispatcher { sender, worker, counters, .. } = &mut self;
+        let (closed, _) = mpsc::sync_channel(0);
+        drop(std::mem::replace(sender, closed));
+        if let Some(worker) = worker.take() {
+            let _ = worker.join();
+        }
+        counters.snapshot()
+    }
+}
+
+impl Blockchain {
+    /// Подключает рассылку вебхуков к событиям цепочки
+    pub fn add_webhooks(&mut self, webhooks: &WebhookDispatcher) -> HookId {
+        let targets = webhooks.targets.clone();
+        let sender = webhooks.sender.clone();
+        let counters = webhooks.counters.clone();
+        self.add_event_hook(Box::new(move |event| enqueue(&targets, &sender, &counters, event)))
+    }
+}
+
+impl Counters {
+    fn snapshot(&self) -> WebhookStats {
+        WebhookStats {
+            queued: self.queued.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Вычисляет подпись тела запроса в виде `sha256=<hex>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Проверяет подпись, пришедшую в заголовке, за постоянное время
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(digest) = signature.strip_prefix("sha256=").and_then(|digest| hex::decode(digest).ok()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&digest).is_ok()
+}
+
+/// Возвращает тела запросов, которые событие порождает для адреса с указанными фильтрами
+pub fn payloads_for(event: &ChainEvent, filters: &[EventFilter]) -> Vec<WebhookPayload> {
+    let mut payloads = Vec::new();
+    
+    for filter in filters {
+        match (filter, event) {
+            (EventFilter::BlockMined, ChainEvent::BlockAdded { block }) => {
+                payloads.push(WebhookPayload::BlockMined { block: block.header(), transactions: block.transactions.len() });
+            },
+            (EventFilter::TransactionConfirmed(addresses), ChainEvent::BlockAdded { block }) => {
+                for tx in &block.transactions {
+                    let mut parties = vec![&tx.sender];
+                    if tx.receiver != tx.sender {
+                        parties.push(&tx.receiver);
+                    }
+                    
+                    for party in parties.into_iter().filter(|party| addresses.contains(*party)) {
+                        payloads.push(WebhookPayload::TransactionConfirmed {
+                            address: party.clone(),
+                            block_index: block.index,
+                            block_hash: block.hash.clone(),
+                            transaction: Box::new(tx.clone()),
+                        });
+                    }
+                }
+            },
+            (EventFilter::ValidatorSlashed, ChainEvent::ValidatorSlashed { validator, amount }) => {
+                payloads.push(WebhookPayload::ValidatorSlashed { validator: validator.clone(), amount: *amount });
+            },
+            _ => {},
+        }
+    }
+    
+    payloads
+}
+
+/// Ставит запросы в очередь; возвращает false, когда поток доставки остановлен и обработчик пора снять
+fn enqueue(targets: &[WebhookTarget], sender: &SyncSender<Delivery>, counters: &Counters, event: &ChainEvent) -> bool {
+    for target in targets {
+        for payload in payloads_for(event, &target.filters) {
+            let Ok(body) = serde_json::to_vec(&payload) else { continue };
+            let delivery = Delivery { url: target.url.clone(), event: payload.name(), body };
+            
+            match sender.try_send(delivery) {
+                Ok(()) => { counters.queued.fetch_add(1, Ordering::Relaxed); },
+                Err(TrySendError::Full(_)) => { counters.dropped.fetch_add(1, Ordering::Relaxed); },
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+    }
+    true
+}
+
+fn deliver_all(config: WebhookConfig, receiver: Receiver<Delivery>, counters: Arc<Counters>) {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(config.timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    
+    for delivery in receiver {
+        let signature = sign(&config.secret, &delivery.body);
+        let mut backoff = config.initial_backoff;
+        
+        for attempt in 0..=config.max_retries {
+            if attempt > 0 {
+                counters.retries.fetch_add(1, Ordering::Relaxed);
+                thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2);
+            }
+            
+            let response = agent.post(&delivery.url)
+                .header("Content-Type", "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .header(EVENT_HEADER, delivery.event)
+                .send(&delivery.body[..]);
+            
+            match response {
+                Ok(response) if response.status().is_success() => {
+                    counters.delivered.fetch_add(1, Ordering::Relaxed);
+                    break;
+                },
+                Ok(response) => warn!(url = %delivery.url, status = %response.status(), attempt, "Webhook rejected"),
+                Err(e) => warn!(url = %delivery.url, error = %e, attempt, "Webhook failed"),
+            }
+            
+            if attempt == config.max_retries {
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusAlgorithm;
+    use crate::test_support::ChainFixture;
+    use crate::transaction::TransactionType;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    
+    /// Полученный запрос: заголовки в нижнем регистре и тело
+    type Received = (HashMap<String, String>, Vec<u8>);
+    
+    /// Локальный HTTP-сервер: отвечает кодами из списка по очереди, последний повторяется
+    fn mock_server(statuses: Vec<u16>) -> (String, Receiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut headers = HashMap::new();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(':') else { break };
+                    headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+                let mut body = vec![0; headers["content-length"].parse().unwrap()];
+                reader.read_exact(&mut body).unwrap();
+                
+                // Запрос учитывается до ответа, чтобы после `shutdown` он уже был в канале
+                if sender.send((headers, body)).is_err() {
+                    return;
+                }
+                let status = statuses[i.min(statuses.len() - 1)];
+                write!(reader.get_mut(), "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+            }
+        });
+        (url, receiver)
+    }
+    
+    fn config(url: String, filters: Vec<EventFilter>) -> WebhookConfig {
+        WebhookConfig {
+            targets: vec![WebhookTarget { url, filters }],
+            secret: "secret".to_string(),
+            initial_backoff: Duration::from_millis(10),
+            ..WebhookConfig::default()
+        }
+    }
+    
+    #[test]
+    fn mined_block_is_posted_with_a_valid_signature() {
+        let (url, received) = mock_server(vec![200]);
+        let filters = vec![EventFilter::BlockMined, EventFilter::TransactionConfirmed(HashSet::from(["bob".to_string()]))];
+        let webhooks = WebhookDispatcher::start(config(url, filters));
+        
+        let mut chain = ChainFixture::chain(1, 100.0, ConsensusAlgorithm::ProofOfWork);
+        chain.create_wallet("alice".to_string()).unwrap();
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+        let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        chain.add_transaction(tx.clone()).unwrap();
+        
+        let hook = chain.add_webhooks(&webhooks);
+        chain.mine_pending_transactions("alice".to_string()).unwrap();
+        chain.remove_event_hook(hook);
+        let stats = webhooks.shutdown();
+        assert_eq!(stats, WebhookStats { queued: 2, delivered: 2, ..WebhookStats::default() });
+        
+        let requests: Vec<Received> = received.try_iter().collect();
+        assert_eq!(requests.len(), 2);
+        for (headers, body) in &requests {
+            assert!(verify_signature("secret", body, &headers[&SIGNATURE_HEADER.to_ascii_lowercase()]));
+            assert!(!verify_signature("other", body, &headers[&SIGNATURE_HEADER.to_ascii_lowercase()]));
+        }
+        let payloads: Vec<WebhookPayload> = requests.iter().map(|(_, body)| serde_json::from_slice(body).unwrap()).collect();
+        let block = chain.get_latest_block();
+        assert!(matches!(&payloads[0], WebhookPayload::BlockMined { block: header, transactions: 2 } if header.hash == block.hash));
+        assert!(matches!(&payloads[1], WebhookPayload::TransactionConfirmed { address, block_index: 2, transaction, .. } if address == "bob" && transaction.id == tx.id));
+        assert_eq!(requests[1].0[&EVENT_HEADER.to_ascii_lowercase()], "transaction_confirmed");
+    }
+    
+    #[test]
+    fn server_errors_are_retried_with_backoff() {
+        let (url, received) = mock_server(vec![500, 500, 200]);
+        let webhooks = WebhookDispatcher::start(config(url, vec![EventFilter::ValidatorSlashed]));
+        webhooks.dispatch(&ChainEvent::ValidatorSlashed { validator: "carol".to_string(), amount: 5.0 });
+        
+        let stats = webhooks.shutdown();
+        assert_eq!(stats, WebhookStats { queued: 1, delivered: 1, retries: 2, ..WebhookStats::default() });
+        let bodies: Vec<Vec<u8>> = received.try_iter().map(|(_, body)| body).collect();
+        assert_eq!(bodies.len(), 3);
+        assert!(bodies.iter().all(|body| body == &bodies[0]));
+    }
+    
+    #[test]
+    fn delivery_gives_up_after_the_retry_limit() {
+        let (url, received) = mock_server(vec![500]);
+        let webhooks = WebhookDispatcher::start(WebhookConfig { max_retries: 2, ..config(url, vec![EventFilter::ValidatorSlashed]) });
+        webhooks.dispatch(&ChainEvent::ValidatorSlashed { validator: "carol".to_string(), amount: 5.0 });
+        
+        let stats = webhooks.shutdown();
+        assert_eq!(stats, WebhookStats { queued: 1, failed: 1, retries: 2, ..WebhookStats::default() });
+        assert_eq!(received.try_iter().count(), 3);
+    }
+}
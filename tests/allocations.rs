@@ -0,0 +1,160 @@
+use hellochain::hashing::HashKind;
+use hellochain::{Block, Blockchain, ConsensusAlgorithm, Transaction, TransactionType};
+use sha2::{Digest, Sha256};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Следит за объемом занятой памяти, его пиком и числом выделений, чтобы поймать лишние копии
+/// на горячих путях: пул при майнинге, строки при переборе nonce и при подсчете корня Меркла
+struct CountingAllocator;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Число выделений памяти в текущем потоке
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+fn grow(size: usize) {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(live, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        grow(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        grow(new_size);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const PAYLOAD_LEN: usize = 64 * 1024;
+const POOL_LEN: usize = 16;
+
+#[test]
+fn mining_holds_no_copy_of_the_pool() {
+    let mut chain = Blockchain::new(1, 200.0, ConsensusAlgorithm::ProofOfWork);
+    chain.accept_legacy_addresses = true;
+    chain.mining_threads = Some(1);
+    chain.create_wallet("alice".to_string()).unwrap();
+    chain.mine_pending_transactions("alice".to_string()).unwrap();
+    // Предельный вес блока вмещает ровно одну транзакцию с данными
+    chain.fee_market = serde_json::from_value(serde_json::json!({
+        "initial_base_fee": 0.0,
+        "target_block_weight": PAYLOAD_LEN,
+    })).unwrap();
+
+    let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+    for i in 0..POOL_LEN {
+        let payload = vec![i as u8; PAYLOAD_LEN];
+        let mut tx = Transaction::new("alice".to_string(), "BLOCKCHAIN_DATA".to_string(), 0.1, TransactionType::Data(payload));
+        tx.set_nonce(chain.get_account_nonce("alice"));
+        tx.sign(&keypair);
+        chain.add_transaction(tx).unwrap();
+    }
+    let pool: Vec<String> = chain.mempool().transactions().iter().map(|tx| tx.id.clone()).collect();
+
+    let before = LIVE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    chain.mine_pending_transactions("alice".to_string()).unwrap();
+    let growth = PEAK.load(Ordering::Relaxed) - before;
+
+    let mined: Vec<String> = chain.get_latest_block().transactions.iter()
+        .filter(|tx| tx.sender != "BLOCKCHAIN_REWARD")
+        .map(|tx| tx.id.clone())
+        .collect();
+    assert_eq!(mined, pool[..1]);
+    let left: Vec<String> = chain.mempool().transactions().iter().map(|tx| tx.id.clone()).collect();
+    assert_eq!(left, pool[1..]);
+    // Копия отобранной транзакции в блоке и ее временные записи допустимы, копия пула — нет
+    assert!(growth < POOL_LEN * PAYLOAD_LEN / 2, "mining held {} extra bytes for a pool of {} bytes", growth, POOL_LEN * PAYLOAD_LEN);
+}
+
+/// Майнит блоки сложности 4 одним потоком, пока попыток не наберется `min_attempts`
+fn mine_blocks(min_attempts: u64) -> (Vec<Block>, u64) {
+    let mut blocks = Vec::new();
+    let mut attempts = 0;
+    while attempts < min_attempts {
+        let mut block = Block::new(1, Vec::new(), format!("{:064x}", blocks.len()), 4);
+        block.mine_block();
+        attempts += block.nonce + 1;
+        blocks.push(block);
+    }
+    (blocks, attempts)
+}
+
+fn thread_allocations() -> u64 {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn pow_attempts_do_not_allocate() {
+    let before = thread_allocations();
+    let (blocks, attempts) = mine_blocks(100_000);
+    let mining = thread_allocations() - before;
+    assert!(blocks.iter().all(Block::meets_difficulty));
+    // Память выделяется на заголовок и итоговый хеш каждого блока, а не на попытки
+    assert!(mining < 16 * blocks.len() as u64, "{} allocations for {} attempts in {} blocks", mining, attempts, blocks.len());
+
+    // Те же попытки через строку полей, как хешировали раньше: хеши совпадают, но каждая попытка выделяет память
+    let formatted_hash = |block: &Block, nonce: u64| {
+        let text = format!("{}{}{}{}{}{}", block.index, block.timestamp, block.merkle_root, block.previous_hash, nonce, block.difficulty);
+        Sha256::digest(text.as_bytes())
+    };
+    let before = thread_allocations();
+    for block in &blocks {
+        for nonce in 0..=block.nonce {
+            std::hint::black_box(formatted_hash(block, nonce));
+        }
+    }
+    assert!(thread_allocations() - before >= attempts);
+    for block in &blocks {
+        assert_eq!(hex::encode(formatted_hash(block, block.nonce)), block.hash);
+    }
+}
+
+#[test]
+fn merkle_root_of_a_large_block_allocates_per_block() {
+    let transactions: Vec<Transaction> = (0..5000)
+        .map(|i| Transaction::new("alice".to_string(), format!("user{}", i), 1.0, TransactionType::Transfer))
+        .collect();
+
+    let before = thread_allocations();
+    let root = Block::calculate_merkle_root(HashKind::Sha256, &transactions);
+    let allocations = thread_allocations() - before;
+    // Буфер хешей листьев и итоговая строка, а не строка на каждый узел дерева
+    assert!(allocations < 8, "{} allocations for {} transactions", allocations, transactions.len());
+
+    // Подсчет через hex-строки на каждом уровне дает тот же корень ценой выделения на узел
+    let before = thread_allocations();
+    let mut level: Vec<String> = transactions.iter()
+        .map(|tx| hex::encode(Sha256::digest(serde_json::to_vec(tx).unwrap())))
+        .collect();
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hex::encode(Sha256::digest(format!("{}{}", left, right))),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    assert!(thread_allocations() - before >= 2 * transactions.len() as u64);
+    assert_eq!(level, [root]);
+}
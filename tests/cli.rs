@@ -0,0 +1,67 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Пустой каталог данных для одного теста
+fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hellochain-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn hellochain(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hellochain"))
+        .arg("--data-dir")
+        .arg(dir.join("chain"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Выполняет команду с `--json`, требует успеха и возвращает разобранный вывод
+fn json(dir: &Path, args: &[&str]) -> Value {
+    let output = hellochain(dir, &[args, &["--json"]].concat());
+    assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stdout));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn end_to_end_transfer_through_the_binary() {
+    let dir = data_dir("transfer");
+    let spec = dir.join("spec.json");
+    std::fs::write(&spec, r#"{"accept_legacy_addresses": true, "difficulty": 1, "mining_reward": 50.0, "consensus": "ProofOfWork", "allocations": {"alice": 100.0}}"#).unwrap();
+    let spec = spec.to_str().unwrap();
+
+    assert!(hellochain(&dir, &["init", spec]).status.success());
+    assert!(!hellochain(&dir, &["init", spec]).status.success());
+    json(&dir, &["wallet", "new", "bob"]);
+    // Без эмитента в спецификации выпуск выключен
+    assert!(!hellochain(&dir, &["wallet", "fund", "alice", "100"]).status.success());
+    json(&dir, &["mine", "miner"]);
+
+    let sent = json(&dir, &["send", "alice", "bob", "10"]);
+    let fee = sent["fee"].as_f64().unwrap();
+    let block = json(&dir, &["mine", "miner"]);
+    assert_eq!(block["index"], 2);
+    assert!(block["transactions"].as_array().unwrap().iter().any(|tx| tx["id"] == sent["id"]));
+
+    assert_eq!(json(&dir, &["wallet", "balance", "alice"])["balance"].as_f64().unwrap(), 100.0 - 10.0 - fee);
+    assert_eq!(json(&dir, &["wallet", "balance", "bob"])["balance"].as_f64().unwrap(), 10.0);
+    assert!(hellochain(&dir, &["chain", "validate"]).status.success());
+
+    let overspend = hellochain(&dir, &["send", "alice", "bob", "1000"]);
+    assert!(!overspend.status.success());
+    assert!(String::from_utf8_lossy(&overspend.stderr).starts_with("Error:"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn commands_fail_without_a_chain() {
+    let dir = data_dir("missing");
+    let output = hellochain(&dir, &["wallet", "balance", "alice", "--json"]);
+    assert!(!output.status.success());
+    let error: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(error.get("error").is_some());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,73 @@
+use hellochain::codec::{decode_raw, decode_transaction, encode_transaction, MAX_RAW_TRANSACTION_LEN};
+use hellochain::network::MAX_FRAME_SIZE;
+use hellochain::entropy::OsEntropy;
+use hellochain::{Block, BlockchainError, Keypair, Transaction, TransactionType};
+
+fn signed_transfer() -> Transaction {
+    let keypair = Keypair::generate(&OsEntropy);
+    let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+    tx.set_nonce(3);
+    tx.sign(&keypair);
+    tx
+}
+
+#[test]
+fn legacy_block_with_huge_difficulty_has_saturated_work() {
+    let mut value = serde_json::to_value(Block::genesis(1)).unwrap();
+    value["difficulty"] = serde_json::json!(u64::MAX);
+    let block = Block::from_json(value.to_string().as_bytes()).unwrap();
+
+    assert_eq!(block.difficulty_as_work(), u128::MAX);
+    assert_eq!(block.header().difficulty_as_work(), u128::MAX);
+    assert!(!block.meets_difficulty());
+}
+
+#[test]
+fn truncated_transactions_fail_or_end_on_an_optional_field() {
+    let bytes = encode_transaction(&signed_transfer(), 1);
+    assert!(decode_transaction(&bytes).is_ok());
+
+    // Необязательные поля идут в конце, поэтому префикс по границе поля остается целой транзакцией
+    for len in 0..bytes.len() {
+        let prefix = &bytes[..len];
+        if let Ok(raw) = decode_transaction(prefix) {
+            assert_eq!(encode_transaction(&raw.transaction, raw.chain_id), prefix, "prefix of {} bytes", len);
+        }
+        assert_eq!(decode_raw(hex::encode(prefix).as_bytes()).is_ok(), decode_transaction(prefix).is_ok());
+    }
+    assert!(decode_transaction(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn oversized_input_is_refused_before_parsing() {
+    let mut bytes = encode_transaction(&signed_transfer(), 1);
+    bytes.resize(MAX_RAW_TRANSACTION_LEN + 1, 0);
+    assert!(decode_transaction(&bytes).is_err());
+    assert!(decode_raw(&bytes).is_err());
+
+    let hex_input = "0".repeat(2 * MAX_RAW_TRANSACTION_LEN + 4);
+    assert!(decode_raw(hex_input.as_bytes()).is_err());
+}
+
+#[test]
+fn too_many_approvals_are_refused() {
+    let approvals = (0..65).map(|i| format!("signer{}", i)).collect();
+    let tx = Transaction::new("treasury".to_string(), "bob".to_string(), 1.0, TransactionType::TreasurySpend { approvals });
+    let bytes = encode_transaction(&tx, 1);
+    assert!(decode_transaction(&bytes).is_err());
+
+    let approvals = (0..64).map(|i| format!("signer{}", i)).collect();
+    let tx = Transaction::new("treasury".to_string(), "bob".to_string(), 1.0, TransactionType::TreasurySpend { approvals });
+    assert!(decode_transaction(&encode_transaction(&tx, 1)).is_ok());
+}
+
+#[test]
+fn oversized_and_malformed_block_json_is_refused() {
+    let oversized = vec![b' '; MAX_FRAME_SIZE + 1];
+    assert!(matches!(Block::from_json(&oversized), Err(BlockchainError::InvalidBlock(_))));
+
+    let json = serde_json::to_string(&Block::genesis(1)).unwrap();
+    for input in [&json[..json.len() / 2], "", "null", "[]", "{\"index\": -1}"] {
+        assert!(matches!(Block::from_json(input.as_bytes()), Err(BlockchainError::InvalidBlock(_))), "{:?}", input);
+    }
+}
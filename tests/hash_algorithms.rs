@@ -0,0 +1,145 @@
+use hellochain::hashing::HashKind;
+use hellochain::registry::ChainRegistry;
+use hellochain::{Blockchain, BlockchainError, ConsensusAlgorithm, Transaction, TransactionType};
+use std::thread;
+
+/// Один и тот же сценарий под заданным алгоритмом: награда alice и ее перевод bob
+fn scenario(kind: HashKind) -> Blockchain {
+    let mut chain = Blockchain::new(1, 50.0, ConsensusAlgorithm::ProofOfWork);
+    chain.set_hash_algorithm(kind).unwrap();
+    chain.accept_legacy_addresses = true;
+    chain.mining_threads = Some(1);
+    chain.create_wallet("alice".to_string()).unwrap();
+    chain.mine_pending_transactions("alice".to_string()).unwrap();
+    let tx = transfer(&chain, "bob", 10.0);
+    chain.add_transaction(tx).unwrap();
+    chain.mine_pending_transactions("miner".to_string()).unwrap();
+    chain
+}
+
+/// Подписанный перевод от alice, посчитанный алгоритмом цепочки
+fn transfer(chain: &Blockchain, receiver: &str, amount: f64) -> Transaction {
+    let mut tx = chain.new_transaction("alice".to_string(), receiver.to_string(), amount, TransactionType::Transfer);
+    tx.set_nonce(chain.get_account_nonce("alice"));
+    tx.sign(chain.get_wallet_info("alice").unwrap().keypair().unwrap());
+    tx
+}
+
+fn data_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("hellochain-hashing-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn each_algorithm_matches_its_test_vectors() {
+    for kind in [HashKind::Sha256, HashKind::Blake3, HashKind::Keccak256] {
+        hellochain::hashing::check_test_vectors(kind).unwrap();
+    }
+    let digests: Vec<_> = [HashKind::Sha256, HashKind::Blake3, HashKind::Keccak256].iter()
+        .map(|kind| kind.digest(b"hellochain"))
+        .collect();
+    assert_ne!(digests[0], digests[1]);
+    assert_ne!(digests[1], digests[2]);
+    assert_ne!(digests[0], digests[2]);
+}
+
+#[test]
+fn chains_validate_only_under_their_own_algorithm() {
+    let sha = scenario(HashKind::Sha256);
+    assert!(sha.is_chain_valid());
+    let mut blake = scenario(HashKind::Blake3);
+    assert!(blake.is_chain_valid());
+    assert_eq!(blake.hash_algorithm, HashKind::Blake3);
+    assert_eq!(sha.hash_algorithm, HashKind::Sha256);
+
+    // Одинаковая история дает разные хеши блоков и идентификаторы транзакций
+    assert_eq!(sha.chain.len(), blake.chain.len());
+    for (ours, theirs) in sha.chain.iter().zip(&blake.chain) {
+        assert_ne!(ours.hash, theirs.hash);
+        // У генезиса нет транзакций и корень Меркла условный
+        if ours.index > 0 {
+            assert_ne!(ours.merkle_root, theirs.merkle_root);
+        }
+    }
+    let transfer = |chain: &Blockchain| chain.chain[2].transactions.iter().find(|tx| tx.sender == "alice").unwrap().id.clone();
+    assert_ne!(transfer(&sha), transfer(&blake));
+    assert_eq!(sha.get_balance("bob"), blake.get_balance("bob"));
+
+    // Цепочка с чужой меткой алгоритма не сходится со своими блоками
+    blake.hash_algorithm = HashKind::Keccak256;
+    let result = blake.check_hash_algorithm();
+    assert!(matches!(result, Err(BlockchainError::HashAlgorithmMismatch { algorithm: "Keccak-256", height: 0 })), "{:?}", result);
+    assert!(!blake.is_chain_valid());
+    blake.hash_algorithm = HashKind::Blake3;
+    blake.check_hash_algorithm().unwrap();
+    assert!(blake.is_chain_valid());
+
+    // Алгоритм выбирается до первой транзакции
+    let result = blake.set_hash_algorithm(HashKind::Sha256);
+    assert!(matches!(result, Err(BlockchainError::InvalidConfig(_))), "{:?}", result);
+}
+
+#[test]
+fn sha256_and_blake3_chains_run_side_by_side() {
+    // Обе цепочки строятся одновременно в одном процессе
+    let (sha, blake) = thread::scope(|scope| {
+        let sha = scope.spawn(|| scenario(HashKind::Sha256));
+        let blake = scope.spawn(|| scenario(HashKind::Blake3));
+        (sha.join().unwrap(), blake.join().unwrap())
+    });
+    let registry = ChainRegistry::new();
+    let sha = registry.register("sha", sha).unwrap();
+    let blake = registry.register("blake", blake).unwrap();
+
+    // Переводы в обеих цепочках чередуются, каждая считает своим алгоритмом
+    for round in 0..2 {
+        for chain in [&sha, &blake] {
+            let tx = transfer(&chain.read(), "carol", 1.0 + round as f64);
+            chain.add_transaction(tx).unwrap();
+            chain.write().mine_pending_transactions("miner".to_string()).unwrap();
+        }
+    }
+    for (chain, kind) in [(&sha, HashKind::Sha256), (&blake, HashKind::Blake3)] {
+        let chain = chain.read();
+        assert_eq!(chain.hash_algorithm, kind);
+        assert!(chain.chain.iter().all(|block| block.hash_algorithm == kind
+            && block.transactions.iter().all(|tx| tx.hash_algorithm == kind)));
+        chain.check_hash_algorithm().unwrap();
+        assert!(chain.is_chain_valid());
+        assert_eq!(chain.get_balance("carol"), 3.0);
+    }
+    assert_ne!(sha.read().get_latest_block().hash, blake.read().get_latest_block().hash);
+
+    // Перевод, посчитанный алгоритмом другой цепочки, не принимается даже с верной подписью
+    let mut foreign = transfer(&blake.read(), "dave", 1.0);
+    foreign.set_hash_algorithm(HashKind::Sha256);
+    foreign.sign(blake.read().get_wallet_info("alice").unwrap().keypair().unwrap());
+    let result = blake.add_transaction(foreign);
+    assert!(matches!(result, Err(BlockchainError::InvalidTransaction(_))), "{:?}", result);
+    assert!(blake.read().is_chain_valid());
+}
+
+#[test]
+fn saved_chain_keeps_its_algorithm() {
+    let dir = data_dir("round-trip");
+    let chain = scenario(HashKind::Keccak256);
+    chain.save(&dir).unwrap();
+
+    let loaded = Blockchain::load(&dir).unwrap();
+    assert_eq!(loaded.hash_algorithm, HashKind::Keccak256);
+    assert!(loaded.chain.iter().all(|block| block.hash_algorithm == HashKind::Keccak256));
+    assert_eq!(loaded.get_latest_block().hash, chain.get_latest_block().hash);
+    assert!(loaded.is_chain_valid());
+
+    // Файл, в котором алгоритм подменен, не загружается с неверными хешами
+    let path = std::fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| std::fs::read_to_string(path).is_ok_and(|text| text.contains("\"hash_algorithm\":\"Keccak256\"")))
+        .expect("chain file records the algorithm");
+    let text = std::fs::read_to_string(&path).unwrap().replace("\"hash_algorithm\":\"Keccak256\"", "\"hash_algorithm\":\"Blake3\"");
+    std::fs::write(&path, text).unwrap();
+    let result = Blockchain::load(&dir);
+    assert!(matches!(result, Err(BlockchainError::HashAlgorithmMismatch { algorithm: "BLAKE3", .. })), "{:?}", result.map(|_| ()));
+    let _ = std::fs::remove_dir_all(&dir);
+}
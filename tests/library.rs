@@ -0,0 +1,39 @@
+use hellochain::{BlockchainBuilder, BlockchainError, ConsensusAlgorithm, Transaction, TransactionType};
+
+#[test]
+fn builder_reports_nonsense_settings_as_invalid_config() {
+    let builders = [
+        BlockchainBuilder::new().difficulty(0),
+        BlockchainBuilder::new().difficulty(64),
+        BlockchainBuilder::new().mining_reward(-1.0),
+        BlockchainBuilder::new().mining_reward(f64::NAN),
+        BlockchainBuilder::new().target_block_time(0),
+    ];
+    for builder in builders {
+        let result = builder.clone().build();
+        assert!(matches!(result, Err(BlockchainError::InvalidConfig(_))), "{:?}", builder);
+    }
+
+    // Сложность у PoS не используется и не проверяется
+    let chain = BlockchainBuilder::new().difficulty(0).consensus(ConsensusAlgorithm::ProofOfStake).accept_legacy_addresses(true).build().unwrap();
+    assert_eq!(chain.chain.len(), 1);
+}
+
+#[test]
+fn built_chain_mines_a_signed_transfer() {
+    let mut chain = BlockchainBuilder::new().difficulty(1).mining_reward(50.0).target_block_time(30).accept_legacy_addresses(true).build().unwrap();
+    chain.create_wallet("alice".to_string()).unwrap();
+    chain.mine_pending_transactions("alice".to_string()).unwrap();
+
+    let keypair = chain.get_wallet_info("alice").unwrap().keypair().unwrap().clone();
+    let mut tx = Transaction::new("alice".to_string(), "bob".to_string(), 10.0, TransactionType::Transfer);
+    tx.set_nonce(chain.get_account_nonce("alice"));
+    tx.sign(&keypair);
+    let fee = tx.fee;
+    chain.add_transaction(tx).unwrap();
+    chain.mine_pending_transactions("miner".to_string()).unwrap();
+
+    assert_eq!(chain.get_balance("alice"), 50.0 - 10.0 - fee);
+    assert_eq!(chain.get_balance("bob"), 10.0);
+    assert_eq!(chain.get_balance("miner"), 50.0 + fee);
+}